@@ -1,8 +1,10 @@
 /// Asynchronous audio asset loading and caching system
 use super::audio_types::*;
+use super::sample_repair::{repair_samples, SampleHealth};
 use anyhow::{Context as AnyhowContext, Result};
 use dashmap::DashMap;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 use std::sync::Arc;
 use smol::channel;
 
@@ -10,6 +12,10 @@ use smol::channel;
 pub struct AssetManager {
     cache: Arc<DashMap<PathBuf, Arc<AudioAssetData>>>,
     loading: Arc<DashMap<PathBuf, Arc<channel::Sender<()>>>>,
+    /// Rate that loaded buffers are resampled to (f32 bits)
+    target_rate: Arc<AtomicU32>,
+    /// Scan loaded buffers for clipping and rebuild short clipped runs
+    scan_clipping: Arc<AtomicBool>,
 }
 
 impl AssetManager {
@@ -17,9 +23,59 @@ impl AssetManager {
         Self {
             cache: Arc::new(DashMap::new()),
             loading: Arc::new(DashMap::new()),
+            target_rate: Arc::new(AtomicU32::new(SAMPLE_RATE.to_bits())),
+            scan_clipping: Arc::new(AtomicBool::new(false)),
         }
     }
 
+    /// Rate that loaded buffers are resampled to
+    pub fn target_rate(&self) -> f32 {
+        f32::from_bits(self.target_rate.load(Ordering::Relaxed))
+    }
+
+    /// Change the buffer rate and reload every cached asset from its source
+    /// file, so buffers are resampled once from the original audio. Returns
+    /// the paths that failed to reload.
+    pub async fn set_target_rate(&self, rate: f32) -> Vec<(PathBuf, anyhow::Error)> {
+        if rate == self.target_rate() {
+            return Vec::new();
+        }
+        self.target_rate.store(rate.to_bits(), Ordering::Relaxed);
+        self.reload_cached().await
+    }
+
+    /// Whether loaded buffers are scanned for clipping
+    pub fn scan_clipping(&self) -> bool {
+        self.scan_clipping.load(Ordering::Relaxed)
+    }
+
+    /// Turn the clipping scan on or off and reload every cached asset, so
+    /// buffers are repaired, or go back to how they are on disk. Returns the
+    /// paths that failed to reload.
+    pub async fn set_scan_clipping(&self, scan: bool) -> Vec<(PathBuf, anyhow::Error)> {
+        if scan == self.scan_clipping() {
+            return Vec::new();
+        }
+        self.scan_clipping.store(scan, Ordering::Relaxed);
+        self.reload_cached().await
+    }
+
+    /// Load every cached asset again from its source file
+    async fn reload_cached(&self) -> Vec<(PathBuf, anyhow::Error)> {
+        let paths: Vec<PathBuf> = self.cache.iter().map(|entry| entry.key().clone()).collect();
+        let mut failed = Vec::new();
+        for path in paths {
+            // Keep the old buffer cached until its replacement is ready
+            match self.load_asset_internal(&path).await {
+                Ok(data) => {
+                    self.cache.insert(path, Arc::new(data));
+                }
+                Err(e) => failed.push((path, e)),
+            }
+        }
+        failed
+    }
+
     /// Load an audio asset asynchronously
     pub async fn load_asset(&self, path: PathBuf) -> Result<Arc<AudioAssetData>> {
         if let Some(cached) = self.cache.get(&path) {
@@ -68,17 +124,31 @@ impl AssetManager {
             .context("No file extension")?
             .to_lowercase();
 
-        match extension.as_str() {
+        let mut data = match extension.as_str() {
             "wav" => self.load_wav(path).await,
             "ogg" => self.load_ogg(path).await,
             "flac" => self.load_flac(path).await,
             "mp3" => self.load_mp3(path).await,
             _ => Err(anyhow::anyhow!("Unsupported audio format: {}", extension)),
-        }
+        }?;
+
+        let scan_clipping = self.scan_clipping();
+        Ok(smol::unblock(move || {
+            // Nothing else holds the buffer yet, so this repairs it in place
+            let channels = data.asset_ref.channels;
+            let health = repair_samples(Arc::make_mut(&mut data.samples), channels, scan_clipping);
+            for line in health.descriptions(data.asset_ref.sample_rate) {
+                tracing::error!("⚠️ {:?}: {}", data.asset_ref.path, line);
+            }
+            data.asset_ref.health = health;
+            data
+        })
+        .await)
     }
 
     async fn load_wav(&self, path: &Path) -> Result<AudioAssetData> {
         let path = path.to_owned();
+        let target_rate = self.target_rate();
 
         smol::unblock(move || {
             let mut reader = hound::WavReader::open(&path)
@@ -87,6 +157,12 @@ impl AssetManager {
             let spec = reader.spec();
             let sample_rate = spec.sample_rate as f32;
             let channels = spec.channels as usize;
+            let source = SourceFormat {
+                sample_rate: spec.sample_rate,
+                bits_per_sample: Some(spec.bits_per_sample),
+                float: spec.sample_format == hound::SampleFormat::Float,
+                channels,
+            };
             let duration_samples = reader.duration() as usize;
 
             let samples: Vec<f32> = match spec.sample_format {
@@ -102,8 +178,8 @@ impl AssetManager {
                 }
             };
 
-            let samples = if sample_rate != SAMPLE_RATE {
-                Self::resample(&samples, sample_rate, SAMPLE_RATE, channels)
+            let samples = if sample_rate != target_rate {
+                Self::resample(&samples, sample_rate, target_rate, channels)
             } else {
                 samples
             };
@@ -111,9 +187,11 @@ impl AssetManager {
             Ok(AudioAssetData {
                 asset_ref: AudioAssetRef {
                     path,
-                    sample_rate: SAMPLE_RATE,
+                    sample_rate: target_rate,
                     channels,
                     duration_samples: samples.len() / channels,
+                    source,
+                    health: SampleHealth::default(),
                 },
                 samples: Arc::new(samples),
             })
@@ -123,6 +201,7 @@ impl AssetManager {
 
     async fn load_ogg(&self, path: &Path) -> Result<AudioAssetData> {
         let path = path.to_owned();
+        let target_rate = self.target_rate();
 
         smol::unblock(move || {
             let file = std::fs::File::open(&path)?;
@@ -130,6 +209,12 @@ impl AssetManager {
             
             let sample_rate = reader.ident_hdr.audio_sample_rate as f32;
             let channels = reader.ident_hdr.audio_channels as usize;
+            let source = SourceFormat {
+                sample_rate: reader.ident_hdr.audio_sample_rate,
+                bits_per_sample: None,
+                float: false,
+                channels,
+            };
             
             let mut samples = Vec::new();
             while let Some(packet) = reader.read_dec_packet_itl()? {
@@ -138,8 +223,8 @@ impl AssetManager {
                 }
             }
 
-            let samples = if sample_rate != SAMPLE_RATE {
-                Self::resample(&samples, sample_rate, SAMPLE_RATE, channels)
+            let samples = if sample_rate != target_rate {
+                Self::resample(&samples, sample_rate, target_rate, channels)
             } else {
                 samples
             };
@@ -147,9 +232,11 @@ impl AssetManager {
             Ok(AudioAssetData {
                 asset_ref: AudioAssetRef {
                     path,
-                    sample_rate: SAMPLE_RATE,
+                    sample_rate: target_rate,
                     channels,
                     duration_samples: samples.len() / channels,
+                    source,
+                    health: SampleHealth::default(),
                 },
                 samples: Arc::new(samples),
             })
@@ -159,6 +246,7 @@ impl AssetManager {
 
     async fn load_flac(&self, path: &Path) -> Result<AudioAssetData> {
         let path = path.to_owned();
+        let target_rate = self.target_rate();
 
         smol::unblock(move || {
             let mut reader = claxon::FlacReader::open(&path)?;
@@ -167,6 +255,12 @@ impl AssetManager {
             let sample_rate = info.sample_rate as f32;
             let channels = info.channels as usize;
             let bits_per_sample = info.bits_per_sample;
+            let source = SourceFormat {
+                sample_rate: info.sample_rate,
+                bits_per_sample: Some(bits_per_sample as u16),
+                float: false,
+                channels,
+            };
             
             let max_val = (1 << (bits_per_sample - 1)) as f32;
             let mut samples = Vec::new();
@@ -176,8 +270,8 @@ impl AssetManager {
                 samples.push(sample as f32 / max_val);
             }
 
-            let samples = if sample_rate != SAMPLE_RATE {
-                Self::resample(&samples, sample_rate, SAMPLE_RATE, channels)
+            let samples = if sample_rate != target_rate {
+                Self::resample(&samples, sample_rate, target_rate, channels)
             } else {
                 samples
             };
@@ -185,9 +279,11 @@ impl AssetManager {
             Ok(AudioAssetData {
                 asset_ref: AudioAssetRef {
                     path,
-                    sample_rate: SAMPLE_RATE,
+                    sample_rate: target_rate,
                     channels,
                     duration_samples: samples.len() / channels,
+                    source,
+                    health: SampleHealth::default(),
                 },
                 samples: Arc::new(samples),
             })
@@ -197,6 +293,7 @@ impl AssetManager {
 
     async fn load_mp3(&self, path: &Path) -> Result<AudioAssetData> {
         let path = path.to_owned();
+        let target_rate = self.target_rate();
 
         smol::unblock(move || {
             use symphonia::core::audio::{AudioBufferRef, Signal};
@@ -294,8 +391,14 @@ impl AssetManager {
             }
 
             let sample_rate_f32 = sample_rate as f32;
-            let samples = if sample_rate_f32 != SAMPLE_RATE {
-                Self::resample(&samples, sample_rate_f32, SAMPLE_RATE, channels)
+            let source = SourceFormat {
+                sample_rate,
+                bits_per_sample: None,
+                float: false,
+                channels,
+            };
+            let samples = if sample_rate_f32 != target_rate {
+                Self::resample(&samples, sample_rate_f32, target_rate, channels)
             } else {
                 samples
             };
@@ -303,9 +406,11 @@ impl AssetManager {
             Ok(AudioAssetData {
                 asset_ref: AudioAssetRef {
                     path,
-                    sample_rate: SAMPLE_RATE,
+                    sample_rate: target_rate,
                     channels,
                     duration_samples: samples.len() / channels,
+                    source,
+                    health: SampleHealth::default(),
                 },
                 samples: Arc::new(samples),
             })
@@ -382,6 +487,8 @@ impl Clone for AssetManager {
         Self {
             cache: self.cache.clone(),
             loading: self.loading.clone(),
+            target_rate: self.target_rate.clone(),
+            scan_clipping: self.scan_clipping.clone(),
         }
     }
 }
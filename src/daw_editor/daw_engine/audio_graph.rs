@@ -0,0 +1,2071 @@
+/// Audio graph mixing engine with tracks, buses, and sends
+use super::asset_manager::AssetManager;
+use super::audio_types::*;
+use super::denormal::{flush_denormal, DenormalGuard};
+use super::effects::{EffectKind, EffectStates, SavedEffectStates};
+use super::limiter::{LimiterSettings, MasterLimiter};
+use super::metering::{InputMeter, MeterBallistics, MeterBank};
+use super::profiling::{DspProfile, Timings};
+use super::smoothing::{smoothing_samples, GainRamp, DEFAULT_SMOOTHING_MS};
+use super::surround::{pan_into_bed, surround_gains, ChannelLayout};
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Post-fader signal of the tracks routed to one hardware output pair
+#[derive(Clone)]
+pub struct DirectOutput {
+    pub left: Vec<f32>,
+    pub right: Vec<f32>,
+    /// Delay matching the master limiter, so direct outs stay in sync with main
+    delay: [Vec<f32>; 2],
+    delay_position: usize,
+}
+
+impl DirectOutput {
+    fn new() -> Self {
+        Self {
+            left: Vec::new(),
+            right: Vec::new(),
+            delay: [Vec::new(), Vec::new()],
+            delay_position: 0,
+        }
+    }
+
+    fn clear(&mut self, buffer_size: usize) {
+        self.left.clear();
+        self.left.resize(buffer_size, 0.0);
+        self.right.clear();
+        self.right.resize(buffer_size, 0.0);
+    }
+
+    fn apply_delay(&mut self, latency: usize) {
+        if latency == 0 {
+            return;
+        }
+        if self.delay[0].len() != latency {
+            self.delay = [vec![0.0; latency], vec![0.0; latency]];
+            self.delay_position = 0;
+        }
+        for i in 0..self.left.len() {
+            let slot = self.delay_position;
+            self.left[i] = std::mem::replace(&mut self.delay[0][slot], self.left[i]);
+            self.right[i] = std::mem::replace(&mut self.delay[1][slot], self.right[i]);
+            self.delay_position = (slot + 1) % latency;
+        }
+    }
+}
+
+/// Master output of a surround project, one buffer per speaker in WAV
+/// channel order. Empty for stereo projects.
+#[derive(Clone)]
+struct SurroundBed {
+    layout: ChannelLayout,
+    channels: Vec<Vec<f32>>,
+}
+
+impl SurroundBed {
+    fn new(layout: ChannelLayout) -> Self {
+        let count = if layout.is_surround() { layout.channel_count() } else { 0 };
+        Self {
+            layout,
+            channels: vec![Vec::new(); count],
+        }
+    }
+
+    fn clear(&mut self, buffer_size: usize) {
+        for channel in &mut self.channels {
+            channel.clear();
+            channel.resize(buffer_size, 0.0);
+        }
+    }
+
+    /// Pan a track's post-fader signal into the bed
+    fn add(&mut self, track: &Track, left: &[f32], right: &[f32]) {
+        if !self.channels.is_empty() {
+            let gains = surround_gains(self.layout, track.surround_pan);
+            pan_into_bed(left, right, 1.0, &gains, &mut self.channels);
+        }
+    }
+}
+
+/// Running state of the graph put aside by [`AudioGraph::save_state`], so
+/// audio can be rendered ahead and the graph taken back to where it was
+#[derive(Default)]
+pub struct SavedGraphState {
+    effects: SavedEffectStates,
+    master_limiter: Option<MasterLimiter>,
+    direct_outputs: BTreeMap<u16, DirectOutput>,
+    surround: Option<SurroundBed>,
+    track_gains: HashMap<TrackId, TrackGains>,
+    master_gain: Option<GainRamp>,
+}
+
+/// Left and right samples of one block
+type StereoBlock = (Vec<f32>, Vec<f32>);
+
+/// Smoothed gains of one track, so mute, solo and fader changes ramp.
+/// Mute is kept apart from solo because sends can ignore the mute.
+#[derive(Debug, Clone, Copy)]
+struct TrackGains {
+    /// 0 while silenced by another track's solo
+    solo: GainRamp,
+    /// 0 while the track is muted
+    mute: GainRamp,
+    fader: GainRamp,
+}
+
+impl TrackGains {
+    fn new(track: &Track, vca: Option<&Track>, audible: bool) -> Self {
+        Self {
+            solo: GainRamp::new(if audible { 1.0 } else { 0.0 }),
+            mute: GainRamp::new(if is_muted(track, vca) { 0.0 } else { 1.0 }),
+            fader: GainRamp::new(fader_gain(track, vca)),
+        }
+    }
+
+    /// `audible` is whether solo lets the track be heard, `vca` the VCA
+    /// riding it
+    fn set_targets(&mut self, track: &Track, vca: Option<&Track>, audible: bool, samples: usize) {
+        self.solo.set_target(if audible { 1.0 } else { 0.0 }, samples);
+        self.mute.set_target(if is_muted(track, vca) { 0.0 } else { 1.0 }, samples);
+        self.fader.set_target(fader_gain(track, vca), samples);
+    }
+
+    /// Whether nothing of the track reaches the mix or its sends, so it
+    /// can be skipped
+    fn is_silent(&self, track: &Track) -> bool {
+        self.solo.is_silent() || (self.mute.is_silent() && !track.sends_ignoring_mute())
+    }
+}
+
+/// A track is muted by its own mute or its VCA's
+fn is_muted(track: &Track, vca: Option<&Track>) -> bool {
+    track.muted || vca.is_some_and(|vca| vca.muted)
+}
+
+/// A track's fader scaled by its VCA's, so moving the VCA keeps the levels
+/// of its tracks relative to each other
+fn fader_gain(track: &Track, vca: Option<&Track>) -> f32 {
+    track.volume * vca.map_or(1.0, |vca| vca.volume)
+}
+
+/// Whether a track shows its input level: armed to record, or monitoring
+fn meters_input(track: &Track) -> bool {
+    track.active && (track.record_armed || track.monitor != MonitorMode::Off)
+}
+
+/// Audio graph node processor
+pub struct AudioGraph {
+    tracks: HashMap<TrackId, Track>,
+    master_track: Track,
+    asset_manager: AssetManager,
+    track_meters: HashMap<TrackId, MeterData>,
+    master_meter: MeterData,
+    /// Ballistics state behind the meter readings
+    meter_bank: MeterBank,
+    any_solo: bool,
+    /// One block per input device channel
+    inputs: Vec<Vec<f32>>,
+    /// Channels the input device actually has
+    input_channels: u16,
+    input_enabled: bool,
+    master_limiter: MasterLimiter,
+    /// Channels of the output device
+    output_channels: u16,
+    /// Direct outputs by pair index, created when a track routes to them
+    direct_outputs: BTreeMap<u16, DirectOutput>,
+    /// Filter state of every track's insert effects
+    effect_states: EffectStates,
+    /// Play the reference tracks instead of the mix
+    listen_reference: bool,
+    surround: SurroundBed,
+    /// Ramp length for mute, solo, fader and bypass changes
+    smoothing_samples: usize,
+    /// Gains of the tracks heard since the transport started, created at
+    /// their targets so a track starts at its own level
+    track_gains: HashMap<TrackId, TrackGains>,
+    /// Smoothed master fader, None until the first block after a stop
+    master_gain: Option<GainRamp>,
+    /// Time each track takes per buffer, measured in profiling mode
+    track_timings: Timings<TrackId>,
+    /// Frames in the last buffer processed
+    last_block_frames: usize,
+}
+
+impl AudioGraph {
+    pub fn new(asset_manager: AssetManager) -> Self {
+        let master_track = Track::new("Master", TrackType::Master);
+        
+        let mut graph = Self {
+            tracks: HashMap::new(),
+            master_track,
+            asset_manager,
+            track_meters: HashMap::new(),
+            master_meter: MeterData::default(),
+            meter_bank: MeterBank::new(MeterBallistics::default(), SAMPLE_RATE),
+            any_solo: false,
+            inputs: vec![vec![0.0; BUFFER_SIZE]; MAX_INPUT_CHANNELS],
+            input_channels: 2,
+            input_enabled: false,
+            // Off until a project applies its own settings
+            master_limiter: MasterLimiter::new(LimiterSettings {
+                enabled: false,
+                ..LimiterSettings::default()
+            }),
+            output_channels: 2,
+            direct_outputs: BTreeMap::new(),
+            effect_states: EffectStates::new(),
+            listen_reference: false,
+            surround: SurroundBed::new(ChannelLayout::Stereo),
+            smoothing_samples: 0,
+            track_gains: HashMap::new(),
+            master_gain: None,
+            track_timings: Timings::default(),
+            last_block_frames: BUFFER_SIZE,
+        };
+        graph.set_smoothing_samples(smoothing_samples(DEFAULT_SMOOTHING_MS, SAMPLE_RATE));
+        graph
+    }
+
+    pub fn add_track(&mut self, track: Track) -> TrackId {
+        let id = track.id;
+        self.warn_if_output_unavailable(&track);
+        self.tracks.insert(id, track);
+        self.track_meters.insert(id, MeterData::default());
+        self.update_solo_state();
+        self.prune_effect_states();
+        self.attach_impulse_responses();
+        id
+    }
+
+    pub fn remove_track(&mut self, id: TrackId) {
+        self.tracks.remove(&id);
+        self.track_meters.remove(&id);
+        self.meter_bank.remove(id);
+        self.track_gains.remove(&id);
+        self.track_timings.retain(|track_id| *track_id != id);
+        self.update_solo_state();
+        self.prune_effect_states();
+    }
+
+    /// Ramp mute, solo, fader and effect bypass changes over `samples`
+    /// samples, or apply them on the next sample with zero
+    pub fn set_smoothing_samples(&mut self, samples: usize) {
+        self.smoothing_samples = samples;
+        self.effect_states.set_ramp_samples(samples);
+    }
+
+    pub fn smoothing_samples(&self) -> usize {
+        self.smoothing_samples
+    }
+
+    /// How channel and master meters rise and fall
+    pub fn set_meter_ballistics(&mut self, ballistics: MeterBallistics) {
+        self.meter_bank.set_ballistics(ballistics);
+    }
+
+    pub fn meter_ballistics(&self) -> MeterBallistics {
+        self.meter_bank.ballistics()
+    }
+
+    /// Measure how long every track and effect takes per buffer, or stop
+    /// measuring and forget the figures
+    pub fn set_profiling(&mut self, enabled: bool) {
+        self.track_timings.set_enabled(enabled);
+        self.effect_states.timings_mut().set_enabled(enabled);
+    }
+
+    pub fn is_profiling(&self) -> bool {
+        self.track_timings.is_enabled()
+    }
+
+    /// The timings measured so far, empty unless profiling
+    pub fn get_dsp_profile(&self) -> DspProfile {
+        DspProfile {
+            tracks: self.track_timings.snapshot(),
+            effects: self.effect_states.timings().snapshot(),
+            block_frames: self.last_block_frames,
+        }
+    }
+
+    /// Forget the filter state of effects that were removed from their
+    /// tracks, and of tracks that were removed
+    fn prune_effect_states(&mut self) {
+        let tracks = self.tracks.values().chain(std::iter::once(&self.master_track));
+        self.effect_states.retain(tracks.flat_map(|t| t.effects.iter()));
+        self.effect_states.retain_tracks(self.tracks.keys().copied());
+    }
+
+    /// Give each convolution reverb the impulse response in its file, from
+    /// the asset cache. Responses are only rebuilt when the file changes or
+    /// was reloaded at a new project rate, and reverbs whose file isn't
+    /// loaded pass the signal through.
+    fn attach_impulse_responses(&mut self) {
+        let tracks = self.tracks.values().chain(std::iter::once(&self.master_track));
+        let reverbs = tracks
+            .flat_map(|t| t.effects.iter())
+            .filter(|e| matches!(e.kind, EffectKind::ConvolutionReverb { .. }));
+        for effect in reverbs {
+            let source = effect.impulse_response.as_deref().and_then(|path| self.asset_manager.get_cached(path));
+            match source {
+                Some(source) => {
+                    let current = self.effect_states.impulse_source(effect.id);
+                    if !current.is_some_and(|current| Arc::ptr_eq(current, &source)) {
+                        self.effect_states.attach_impulse(effect, source);
+                    }
+                }
+                None => self.effect_states.detach_impulse(effect.id),
+            }
+        }
+    }
+
+    pub fn get_track(&self, id: TrackId) -> Option<&Track> {
+        self.tracks.get(&id)
+    }
+
+    pub fn get_track_mut(&mut self, id: TrackId) -> Option<&mut Track> {
+        self.tracks.get_mut(&id)
+    }
+
+    pub fn get_master_track(&self) -> &Track {
+        &self.master_track
+    }
+
+    pub fn get_master_track_mut(&mut self) -> &mut Track {
+        &mut self.master_track
+    }
+
+    pub fn get_all_tracks(&self) -> Vec<&Track> {
+        self.tracks.values().collect()
+    }
+
+    pub fn get_track_meter(&self, id: TrackId) -> Option<MeterData> {
+        self.track_meters.get(&id).copied()
+    }
+
+    pub fn get_master_meter(&self) -> MeterData {
+        self.master_meter
+    }
+
+    pub fn set_master_limiter(&mut self, settings: LimiterSettings) {
+        self.master_limiter.set_settings(settings);
+    }
+
+    /// Set the channel count of the output device. Tracks routed to outputs
+    /// the device doesn't have fall back to the master bus.
+    pub fn set_output_channels(&mut self, channels: u16) {
+        self.output_channels = channels;
+        self.direct_outputs.retain(|pair, _| Self::pair_fits(*pair, channels));
+        for track in self.tracks.values() {
+            self.warn_if_output_unavailable(track);
+        }
+    }
+
+    pub fn output_channels(&self) -> u16 {
+        self.output_channels
+    }
+
+    /// Signals for the hardware output pairs from the last `process` call
+    pub fn direct_outputs(&self) -> impl Iterator<Item = (u16, &DirectOutput)> {
+        self.direct_outputs.iter().map(|(pair, output)| (*pair, output))
+    }
+
+    fn pair_fits(pair: u16, channels: u16) -> bool {
+        (pair as u32) * 2 + 2 <= channels as u32
+    }
+
+    /// Pair a track is actually played on, None for the master bus
+    fn routed_pair(&self, track: &Track) -> Option<u16> {
+        match track.output {
+            OutputTarget::Pair(pair) if Self::pair_fits(pair, self.output_channels) => Some(pair),
+            _ => None,
+        }
+    }
+
+    fn warn_if_output_unavailable(&self, track: &Track) {
+        if let OutputTarget::Pair(pair) = track.output {
+            if !Self::pair_fits(pair, self.output_channels) {
+                tracing::error!(
+                    "⚠️ Track '{}' is routed to {} but the device has {} outputs, using Main",
+                    track.name,
+                    track.output.label(),
+                    self.output_channels
+                );
+            }
+        }
+    }
+
+    /// Switch the output between the mix and the reference tracks
+    pub fn set_listen_reference(&mut self, listen: bool) {
+        self.listen_reference = listen;
+    }
+
+    pub fn is_listening_to_reference(&self) -> bool {
+        self.listen_reference
+    }
+
+    /// Speaker layout of the master. Surround layouts build a bed next to
+    /// the stereo mix, see [`Self::surround_outputs`].
+    pub fn set_channel_layout(&mut self, layout: ChannelLayout) {
+        if self.surround.layout != layout {
+            self.surround = SurroundBed::new(layout);
+        }
+    }
+
+    pub fn channel_layout(&self) -> ChannelLayout {
+        self.surround.layout
+    }
+
+    /// The surround bed of the last buffer, one slice per speaker, or
+    /// nothing when the layout is stereo. It carries the master fader and
+    /// is limited together with the stereo mix.
+    pub fn surround_outputs(&self) -> &[Vec<f32>] {
+        &self.surround.channels
+    }
+
+    /// Files of the clips that play in the `frames` from `position` but
+    /// aren't loaded yet, for pre-rolling them before the audio thread gets
+    /// there. The audio thread never loads anything, so a clip whose file
+    /// isn't ready when it starts would come in late.
+    pub fn uncached_assets(&self, position: SampleTime, frames: usize) -> Vec<PathBuf> {
+        let mut paths = Vec::new();
+        for track in self.tracks.values().filter(|t| t.active) {
+            let Some((clip_time, skip)) = offset_window(position, track.track_offset_samples, frames) else {
+                continue;
+            };
+            let end = clip_time + (frames - skip) as u64;
+            for clip in track.clips.iter().filter(|c| c.overlaps(clip_time, end)) {
+                if self.asset_manager.get_cached(&clip.asset_path).is_none() && !paths.contains(&clip.asset_path) {
+                    paths.push(clip.asset_path.clone());
+                }
+            }
+        }
+        paths
+    }
+
+    /// Samples the master output lags behind the transport position
+    pub fn output_latency(&self) -> usize {
+        self.master_limiter.latency()
+    }
+
+    /// Gain reduction of the master limiter during the last buffer, in dB
+    pub fn get_master_limiter_reduction(&self) -> f32 {
+        self.master_limiter.gain_reduction_db()
+    }
+
+    pub fn get_master_gain_match_db(&self) -> f32 {
+        self.master_limiter.gain_match_db()
+    }
+
+    /// Enable or disable input monitoring. Disabled when no input device is
+    /// available or the input would capture our own output.
+    pub fn set_input_enabled(&mut self, enabled: bool) {
+        self.input_enabled = enabled;
+        if !enabled {
+            for input in &mut self.inputs {
+                input.fill(0.0);
+            }
+        }
+    }
+
+    /// Set how many channels the input device has, so track inputs beyond
+    /// them read silence and stereo inputs on a mono device read mono
+    pub fn set_input_channels(&mut self, channels: u16) {
+        self.input_channels = channels.min(MAX_INPUT_CHANNELS as u16);
+    }
+
+    pub fn input_channels(&self) -> u16 {
+        self.input_channels
+    }
+
+    /// Input blocks of the first two channels for the next `process` call
+    pub fn input_buffers_mut(&mut self) -> (&mut [f32], &mut [f32]) {
+        let (left, rest) = self.inputs.split_at_mut(1);
+        (&mut left[0], &mut rest[0])
+    }
+
+    /// Input blocks of every device channel for the next `process` call,
+    /// filled by the audio thread
+    pub fn input_channels_mut(&mut self) -> &mut [Vec<f32>] {
+        &mut self.inputs
+    }
+
+    /// Whether any track wants to hear the input during this block
+    pub fn is_monitoring_input(&self, transport: &Transport, buffer_size: usize) -> bool {
+        self.input_enabled
+            && self.tracks.values().any(|t| {
+                t.active
+                    && t.monitor != MonitorMode::Off
+                    && (t.monitor.monitors_input(t.record_armed, transport, transport.position)
+                        || t.monitor.monitors_input(
+                            t.record_armed,
+                            transport,
+                            transport.position + buffer_size as u64,
+                        ))
+            })
+    }
+
+    /// Where playback stops on its own under the transport's
+    /// [`PlaybackEnd`], if it does. Reference tracks stay out of it, as they
+    /// do out of the mix length.
+    pub fn playback_stop_point(&self, transport: &Transport) -> Option<SampleTime> {
+        // Called every callback, so skip the scan when playback carries on anyway
+        if transport.stop_point(0, 0).is_none() {
+            return None;
+        }
+        let mix = self.tracks.values().filter(|t| t.track_type != TrackType::Reference);
+        let last_clip_end = mix.clone().flat_map(|t| t.clips.iter().map(|c| c.end_time())).max().unwrap_or(0);
+        let last_point = mix
+            .flat_map(|t| t.automation.iter().flat_map(|lane| lane.points.last().map(|p| p.time)))
+            .max()
+            .unwrap_or(0);
+        transport.stop_point(last_clip_end, last_clip_end.max(last_point))
+    }
+
+    /// Whether any track's input is metered, playing or not
+    pub fn is_metering_input(&self) -> bool {
+        self.input_enabled && self.tracks.values().any(meters_input)
+    }
+
+    /// Meter the first `frames` samples of the input blocks on every armed
+    /// or monitoring track, before anything on its channel. Called for each
+    /// block processed, and by the audio thread on its own while nothing is.
+    pub fn meter_inputs(&mut self, frames: usize, sample_rate: f32) {
+        let enabled = self.input_enabled;
+        let tracks = &self.tracks;
+        self.meter_bank.retain_inputs(|id| enabled && tracks.get(&id).is_some_and(meters_input));
+        if !enabled {
+            return;
+        }
+        let frames = frames.min(BUFFER_SIZE);
+        let (mut left, mut right) = (vec![0.0; frames], vec![0.0; frames]);
+        for track in self.tracks.values().filter(|t| meters_input(t)) {
+            let input = track.record_input.resolve(self.input_channels);
+            for i in 0..frames {
+                (left[i], right[i]) = input.read(&self.inputs, i);
+            }
+            self.meter_bank.measure_input(track.id, &left, &right, sample_rate);
+        }
+    }
+
+    pub fn get_input_meter(&self, id: TrackId) -> Option<InputMeter> {
+        self.meter_bank.input(id)
+    }
+
+    /// Turn off the input clip light of one track, or of every track
+    pub fn clear_input_clip(&mut self, id: Option<TrackId>) {
+        self.meter_bank.clear_input_clip(id);
+    }
+
+    fn update_solo_state(&mut self) {
+        self.any_solo = self.tracks.values().any(|t| t.solo_active());
+    }
+
+    /// The VCA riding `track`, if it's assigned to one that's active
+    fn vca_of(&self, track: &Track) -> Option<&Track> {
+        track
+            .vca
+            .and_then(|id| self.tracks.get(&id))
+            .filter(|vca| vca.active && vca.track_type == TrackType::Vca)
+    }
+
+    /// Whether solo lets `track` be heard. Soloing a VCA solos its tracks.
+    fn solo_audible(&self, track: &Track, vca: Option<&Track>) -> bool {
+        !track.solo_muted(self.any_solo) || vca.is_some_and(|vca| vca.solo)
+    }
+
+    /// Copy the state the next blocks render from into `saved`: effect
+    /// filters and delays, gain ramps, the limiter and the output delays.
+    /// Plugin instances and the meters carry on regardless.
+    pub fn save_state(&self, saved: &mut SavedGraphState) {
+        self.effect_states.save(&mut saved.effects);
+        saved.master_limiter = Some(self.master_limiter.clone());
+        saved.direct_outputs.clone_from(&self.direct_outputs);
+        saved.surround = Some(self.surround.clone());
+        saved.track_gains.clone_from(&self.track_gains);
+        saved.master_gain = self.master_gain;
+    }
+
+    /// Go back to the state in `saved`
+    pub fn restore_state(&mut self, saved: &mut SavedGraphState) {
+        self.effect_states.restore(&mut saved.effects);
+        if let Some(limiter) = saved.master_limiter.as_mut() {
+            std::mem::swap(&mut self.master_limiter, limiter);
+        }
+        std::mem::swap(&mut self.direct_outputs, &mut saved.direct_outputs);
+        if let Some(surround) = saved.surround.as_mut() {
+            std::mem::swap(&mut self.surround, surround);
+        }
+        std::mem::swap(&mut self.track_gains, &mut saved.track_gains);
+        self.master_gain = saved.master_gain;
+    }
+
+    /// Process audio graph for a buffer
+    pub fn process(
+        &mut self,
+        transport: &Transport,
+        output_left: &mut [f32],
+        output_right: &mut [f32],
+    ) {
+        let buffer_size = output_left.len();
+        let _denormals = DenormalGuard::new();
+        
+        output_left.fill(0.0);
+        output_right.fill(0.0);
+
+        self.meter_inputs(buffer_size, transport.sample_rate);
+
+        let rolling = transport.is_rolling();
+        if !rolling && !self.is_monitoring_input(transport, buffer_size) {
+            self.master_meter = MeterData::default();
+            for meter in self.track_meters.values_mut() {
+                *meter = MeterData::default();
+            }
+            self.meter_bank.reset();
+            self.master_limiter.reset();
+            self.direct_outputs.clear();
+            self.surround.clear(buffer_size);
+            // Nothing is heard while stopped, so gains start at their
+            // targets again rather than ramping from the old ones
+            self.track_gains.clear();
+            self.master_gain = None;
+            return;
+        }
+
+        for output in self.direct_outputs.values_mut() {
+            output.clear(buffer_size);
+        }
+        self.surround.clear(buffer_size);
+
+        // Solo flags can change through get_track_mut, so refresh per buffer
+        self.update_solo_state();
+
+        // Inactive tracks aren't processed, so their meters would hold the
+        // last level they showed
+        for track in self.tracks.values().filter(|t| !t.active) {
+            self.track_meters.remove(&track.id);
+            self.meter_bank.remove(track.id);
+            self.track_gains.remove(&track.id);
+        }
+
+        let mut aux_buffers: HashMap<TrackId, (Vec<f32>, Vec<f32>)> = HashMap::new();
+
+        // Inactive buses get no buffer, so sends into them are dropped
+        for track in self.tracks.values() {
+            if track.active && track.track_type.is_bus() {
+                aux_buffers.insert(
+                    track.id,
+                    (vec![0.0; buffer_size], vec![0.0; buffer_size]),
+                );
+            }
+        }
+
+        // Tracks are summed in a fixed order so renders are bit-identical
+        // between runs, independent of hash map ordering
+        let mut track_ids: Vec<TrackId> = self.tracks.keys().copied().collect();
+        track_ids.sort();
+        // Each with the signal before the mute, kept when a send ignores it
+        let mut track_outputs: Vec<(TrackId, StereoBlock, Option<StereoBlock>)> = Vec::new();
+        let mut reference_outputs: Vec<(TrackId, (Vec<f32>, Vec<f32>))> = Vec::new();
+        // Taken out for the block so tracks can be read while effects run
+        let mut effect_states = std::mem::take(&mut self.effect_states);
+        let mut track_gains = std::mem::take(&mut self.track_gains);
+
+        for track_id in track_ids {
+            let Some(track) = self.tracks.get(&track_id) else {
+                continue;
+            };
+            // The mix keeps running while the reference plays, so switching
+            // back doesn't cut off reverb tails
+            let is_reference = track.track_type == TrackType::Reference;
+            let routed = track.active
+                && !track.track_type.is_bus()
+                && track.track_type != TrackType::Vca
+                && (!is_reference || self.listen_reference);
+            if !routed {
+                continue;
+            }
+
+            let vca = self.vca_of(track);
+            let audible = is_reference || self.solo_audible(track, vca);
+            let gains = track_gains.entry(track_id).or_insert_with(|| TrackGains::new(track, vca, audible));
+            gains.set_targets(track, vca, audible, self.smoothing_samples);
+            // A muted track keeps playing until it has faded out, and for
+            // as long as one of its sends ignores the mute
+            if gains.is_silent(track) {
+                continue;
+            }
+
+            let started = self.track_timings.start();
+            let mut left = vec![0.0; buffer_size];
+            let mut right = vec![0.0; buffer_size];
+            self.process_track(track, transport, rolling, &mut effect_states, &mut left, &mut right);
+            gains.solo.apply(&mut left, &mut right);
+            let unmuted = track.sends_ignoring_mute().then(|| (left.clone(), right.clone()));
+            gains.mute.apply(&mut left, &mut right);
+            self.track_timings.record(track_id, started);
+
+            if is_reference {
+                reference_outputs.push((track_id, (left, right)));
+            } else {
+                track_outputs.push((track_id, (left, right), unmuted));
+            }
+        }
+
+        for (track_id, (left, right), unmuted) in &track_outputs {
+            if let (Some(track), Some(gains)) = (self.tracks.get(track_id), track_gains.get(track_id)) {
+                let unmuted = unmuted.as_ref().map_or((&left[..], &right[..]), |(l, r)| (&l[..], &r[..]));
+                Self::apply_sends(track, transport.position, (&left[..], &right[..]), unmuted, gains.fader, &mut aux_buffers);
+            }
+        }
+
+        // Buses run after all of their source tracks, and after any bus that sends into them
+        for track_id in self.bus_processing_order() {
+            let Some((mut aux_left, mut aux_right)) = aux_buffers.remove(&track_id) else {
+                continue;
+            };
+
+            if let Some(track) = self.tracks.get(&track_id) {
+                let vca = self.vca_of(track);
+                let audible = self.solo_audible(track, vca);
+                let gains = track_gains.entry(track_id).or_insert_with(|| TrackGains::new(track, vca, audible));
+                gains.set_targets(track, vca, audible, self.smoothing_samples);
+                if !gains.is_silent(track) {
+                    let started = self.track_timings.start();
+                    effect_states.process_quick_filters(track, transport.position, transport.sample_rate, &mut aux_left, &mut aux_right);
+                    process_effects(track, transport.position, transport.sample_rate, &mut effect_states, &mut aux_left, &mut aux_right);
+                    apply_automation(track, vca, transport.position, &mut aux_left, &mut aux_right);
+                    gains.solo.apply(&mut aux_left, &mut aux_right);
+                    let unmuted = track.sends_ignoring_mute().then(|| (aux_left.clone(), aux_right.clone()));
+                    gains.mute.apply(&mut aux_left, &mut aux_right);
+                    self.track_timings.record(track_id, started);
+
+                    // Feed buses later in the order; already processed buses were
+                    // removed from the map so feedback loops can't form
+                    let unmuted = unmuted.as_ref().map_or((&aux_left[..], &aux_right[..]), |(l, r)| (&l[..], &r[..]));
+                    Self::apply_sends(track, transport.position, (&aux_left[..], &aux_right[..]), unmuted, gains.fader, &mut aux_buffers);
+
+                    let meter = self.meter_bank.measure(Some(track_id), &aux_left, &aux_right, transport.sample_rate);
+                    gains.fader.apply(&mut aux_left, &mut aux_right);
+                    match self.routed_pair(track) {
+                        Some(pair) => {
+                            let direct = self.direct_outputs.entry(pair).or_insert_with(DirectOutput::new);
+                            if direct.left.len() != buffer_size {
+                                direct.clear(buffer_size);
+                            }
+                            mix_into(track, &aux_left, &aux_right, &mut direct.left, &mut direct.right);
+                        }
+                        None => {
+                            mix_into(track, &aux_left, &aux_right, output_left, output_right);
+                            self.surround.add(track, &aux_left, &aux_right);
+                        }
+                    }
+                    self.track_meters.insert(track_id, meter);
+                }
+            }
+        }
+
+        self.effect_states = effect_states;
+        if self.is_profiling() {
+            self.track_timings.finish_block();
+            self.effect_states.timings_mut().finish_block();
+            self.last_block_frames = buffer_size;
+        }
+
+        for (track_id, (mut left, mut right), _) in track_outputs {
+            if let Some(track) = self.tracks.get(&track_id) {
+                // Meters read before the fader
+                let meter = self.meter_bank.measure(Some(track_id), &left, &right, transport.sample_rate);
+                if let Some(gains) = track_gains.get_mut(&track_id) {
+                    gains.fader.apply(&mut left, &mut right);
+                }
+                match self.routed_pair(track) {
+                    Some(pair) => {
+                        let direct = self.direct_outputs.entry(pair).or_insert_with(DirectOutput::new);
+                        if direct.left.len() != buffer_size {
+                            direct.clear(buffer_size);
+                        }
+                        mix_into(track, &left, &right, &mut direct.left, &mut direct.right);
+                    }
+                    None => {
+                        mix_into(track, &left, &right, output_left, output_right);
+                        self.surround.add(track, &left, &right);
+                    }
+                }
+                self.track_meters.insert(track_id, meter);
+            }
+        }
+
+        let master_volume = self.master_track.volume;
+        let master_gain = self.master_gain.get_or_insert_with(|| GainRamp::new(master_volume));
+        master_gain.set_target(master_volume, self.smoothing_samples);
+        // One curve for the stereo mix and the surround bed
+        let master: Vec<f32> = (0..buffer_size).map(|_| master_gain.next_gain()).collect();
+        for i in 0..buffer_size {
+            output_left[i] *= master[i];
+            output_right[i] *= master[i];
+        }
+        for channel in &mut self.surround.channels {
+            channel.iter_mut().zip(&master).for_each(|(sample, gain)| *sample *= gain);
+        }
+
+        // Limiter runs post-fader so anything rendered through the graph is
+        // limited too, the surround bed linked to the stereo mix
+        self.master_limiter.process_linked(output_left, output_right, &mut self.surround.channels);
+
+        // The reference replaces the mix after the master bus, so the master
+        // fader and limiter don't color it
+        if self.listen_reference {
+            output_left.fill(0.0);
+            output_right.fill(0.0);
+            self.surround.clear(buffer_size);
+            for (track_id, (mut left, mut right)) in reference_outputs {
+                if let Some(track) = self.tracks.get(&track_id) {
+                    let meter = self.meter_bank.measure(Some(track_id), &left, &right, transport.sample_rate);
+                    self.track_meters.insert(track_id, meter);
+                    if let Some(gains) = track_gains.get_mut(&track_id) {
+                        gains.fader.apply(&mut left, &mut right);
+                    }
+                    mix_into(track, &left, &right, output_left, output_right);
+                    self.surround.add(track, &left, &right);
+                }
+            }
+        }
+        self.track_gains = track_gains;
+
+        for i in 0..buffer_size {
+            output_left[i] = flush_denormal(output_left[i]).clamp(-1.0, 1.0);
+            output_right[i] = flush_denormal(output_right[i]).clamp(-1.0, 1.0);
+        }
+        for channel in &mut self.surround.channels {
+            channel.iter_mut().for_each(|sample| *sample = flush_denormal(*sample).clamp(-1.0, 1.0));
+        }
+
+        self.master_meter = self.meter_bank.measure(None, output_left, output_right, transport.sample_rate);
+
+        let latency = self.master_limiter.latency();
+        for output in self.direct_outputs.values_mut() {
+            output.apply_delay(latency);
+        }
+    }
+
+    /// Mix a track's signal into the buffers of the buses it sends to, for a
+    /// block starting at `start`. `signal` is after the mute and `unmuted`
+    /// before it, both before the fader; post-fader sends follow a copy of
+    /// the `fader` ramp. Automated send levels follow their lane sample by
+    /// sample.
+    fn apply_sends(
+        track: &Track,
+        start: SampleTime,
+        signal: (&[f32], &[f32]),
+        unmuted: (&[f32], &[f32]),
+        fader: GainRamp,
+        aux_buffers: &mut HashMap<TrackId, (Vec<f32>, Vec<f32>)>,
+    ) {
+        let mut fader_gains: Option<Vec<f32>> = None;
+        for (slot, send) in track.sends.iter().enumerate() {
+            if !send.enabled {
+                continue;
+            }
+            let Some(target_id) = send.target_track.filter(|target| *target != track.id) else {
+                continue;
+            };
+            let Some((aux_left, aux_right)) = aux_buffers.get_mut(&target_id) else {
+                continue;
+            };
+            let (left, right) = if send.follow_mute { signal } else { unmuted };
+            let frames = left.len().min(aux_left.len());
+            let mut amounts = vec![send.amount; frames];
+            let automated = track
+                .get_automation_lane(AutomationParameter::Send(slot))
+                .is_some_and(|lane| lane.fill(start, &mut amounts));
+            if !automated && send.amount <= 0.0 {
+                continue;
+            }
+            if !send.pre_fader {
+                let gains = fader_gains.get_or_insert_with(|| {
+                    let mut ramp = fader;
+                    (0..left.len()).map(|_| ramp.next_gain()).collect()
+                });
+                amounts.iter_mut().zip(gains.iter()).for_each(|(amount, gain)| *amount *= gain);
+            }
+            for i in 0..frames {
+                aux_left[i] += left[i] * amounts[i];
+                aux_right[i] += right[i] * amounts[i];
+            }
+        }
+    }
+
+    /// Order buses so each one is processed after every bus that sends into it.
+    /// Buses caught in a send loop are appended at the end; the loop itself is
+    /// broken because sends to already processed buses are dropped.
+    fn bus_processing_order(&self) -> Vec<TrackId> {
+        let mut buses: Vec<TrackId> = self.tracks
+            .values()
+            .filter(|t| t.track_type.is_bus())
+            .map(|t| t.id)
+            .collect();
+        buses.sort();
+
+        let bus_targets = |id: &TrackId| -> Vec<TrackId> {
+            self.tracks
+                .get(id)
+                .map(|t| {
+                    t.sends
+                        .iter()
+                        .filter(|s| s.enabled)
+                        .filter_map(|s| s.target_track)
+                        .filter(|target| target != id && buses.contains(target))
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+
+        let mut in_degree: HashMap<TrackId, usize> = buses.iter().map(|id| (*id, 0)).collect();
+        for id in &buses {
+            for target in bus_targets(id) {
+                *in_degree.entry(target).or_default() += 1;
+            }
+        }
+
+        let mut ready: std::collections::VecDeque<TrackId> = buses
+            .iter()
+            .filter(|id| in_degree[*id] == 0)
+            .copied()
+            .collect();
+        let mut order = Vec::with_capacity(buses.len());
+
+        while let Some(id) = ready.pop_front() {
+            order.push(id);
+            for target in bus_targets(&id) {
+                if let Some(degree) = in_degree.get_mut(&target) {
+                    *degree -= 1;
+                    if *degree == 0 {
+                        ready.push_back(target);
+                    }
+                }
+            }
+        }
+
+        for id in buses.iter() {
+            if !order.contains(id) {
+                order.push(*id);
+            }
+        }
+
+        order
+    }
+
+    fn process_track(
+        &self,
+        track: &Track,
+        transport: &Transport,
+        rolling: bool,
+        effect_states: &mut EffectStates,
+        left: &mut [f32],
+        right: &mut [f32],
+    ) {
+        let buffer_size = left.len();
+        let start_time = transport.position;
+
+        // Clips are read at the offset position, frames from before the
+        // timeline start stay silent
+        let window = offset_window(start_time, track.track_offset_samples, buffer_size).filter(|_| rolling);
+        if let Some((clip_time, skip)) = window {
+            let frames = buffer_size - skip;
+            if track.overlap_mode == OverlapMode::Comp {
+                self.render_comped_clips(track, clip_time, frames, &mut left[skip..], &mut right[skip..]);
+            } else {
+                for clip in &track.clips {
+                    // Any overlap, so clips starting and ending inside the
+                    // buffer play at their own frame
+                    if !clip.overlaps(clip_time, clip_time + frames as u64) {
+                        continue;
+                    }
+
+                    if let Some(asset) = self.asset_manager.get_cached(&clip.asset_path) {
+                        self.render_clip(clip, &asset, clip_time, frames, &mut left[skip..], &mut right[skip..]);
+                    }
+                }
+            }
+        }
+
+        // Monitored input replaces playback sample by sample, so Auto switches
+        // exactly at the punch points
+        if self.input_enabled && track.monitor != MonitorMode::Off {
+            let input = track.record_input.resolve(self.input_channels);
+            for i in 0..buffer_size.min(BUFFER_SIZE) {
+                if track.monitor.monitors_input(track.record_armed, transport, start_time + i as u64) {
+                    (left[i], right[i]) = input.read(&self.inputs, i);
+                }
+            }
+        }
+
+        if track.phase_invert.is_active() {
+            track.phase_invert.apply(left, right);
+        }
+
+        effect_states.process_quick_filters(track, start_time, transport.sample_rate, left, right);
+        process_effects(track, start_time, transport.sample_rate, effect_states, left, right);
+        apply_automation(track, self.vca_of(track), start_time, left, right);
+    }
+
+    /// Play a comped track's clips topmost first. Each clip is heard in
+    /// whatever its fades leave uncovered by the clips above it, so a take
+    /// on top hides the ones under it and crossfades into them at its fades.
+    fn render_comped_clips(
+        &self,
+        track: &Track,
+        start_time: SampleTime,
+        buffer_size: usize,
+        left: &mut [f32],
+        right: &mut [f32],
+    ) {
+        let mut uncovered = vec![1.0f32; buffer_size];
+        let mut clip_left = vec![0.0; buffer_size];
+        let mut clip_right = vec![0.0; buffer_size];
+
+        for clip in track.clips.iter().rev() {
+            if !clip.overlaps(start_time, start_time + buffer_size as u64) {
+                continue;
+            }
+            let Some(asset) = self.asset_manager.get_cached(&clip.asset_path) else {
+                continue;
+            };
+            clip_left.fill(0.0);
+            clip_right.fill(0.0);
+            self.render_clip(clip, &asset, start_time, buffer_size, &mut clip_left, &mut clip_right);
+            for i in 0..buffer_size {
+                left[i] += clip_left[i] * uncovered[i];
+                right[i] += clip_right[i] * uncovered[i];
+                uncovered[i] *= 1.0 - clip.envelope_at(start_time + i as u64);
+            }
+            if uncovered.iter().all(|u| *u <= 0.0) {
+                break;
+            }
+        }
+    }
+
+    fn render_clip(
+        &self,
+        clip: &AudioClip,
+        asset: &AudioAssetData,
+        start_time: SampleTime,
+        buffer_size: usize,
+        left: &mut [f32],
+        right: &mut [f32],
+    ) {
+        let clip_start = clip.start_time;
+        let clip_end = clip.end_time();
+        let resampled = clip.time_ratio != 1.0 && clip.time_ratio > 0.0;
+        let stretch = resampled && clip.effective_tempo_mode() == TempoMode::Stretch;
+
+        for i in 0..buffer_size {
+            let sample_time = start_time + i as u64;
+
+            if sample_time < clip_start || sample_time >= clip_end {
+                continue;
+            }
+
+            let relative_time = sample_time - clip_start;
+
+            let fade = clip.fade_at(sample_time);
+            if fade == 0.0 {
+                continue;
+            }
+
+            let frame = if stretch {
+                read_stretched(asset, clip, relative_time)
+            } else if resampled {
+                // Repitch: speed and pitch change together
+                read_interpolated(asset, clip.looped_source_position(clip.offset as f64 + relative_time as f64 / clip.time_ratio))
+            } else {
+                read_frame(asset, clip.looped_source_position((clip.offset + relative_time) as f64) as usize)
+            };
+            let Some((sample_left, sample_right)) = frame else {
+                continue;
+            };
+            let (sample_left, sample_right) =
+                clip.channel_mode.apply(sample_left, sample_right, asset.asset_ref.channels);
+
+            left[i] += sample_left * fade;
+            right[i] += sample_right * fade;
+        }
+    }
+}
+
+/// Samples effect mix automation is evaluated over, counted from the project
+/// start so renders don't depend on the device buffer size. Mix breakpoints
+/// take effect within this many samples of their own.
+const AUTOMATION_SUB_BLOCK: u64 = 32;
+
+/// Run a track's effect chain over a block starting at `start`, at
+/// `sample_rate`. With mix
+/// automation the block is split into sub-blocks, each ramping to where the
+/// lanes are at its end, so mix moves follow the curve rather than the
+/// device buffer.
+fn process_effects(track: &Track, start: SampleTime, sample_rate: f32, effect_states: &mut EffectStates, left: &mut [f32], right: &mut [f32]) {
+    let automated = track.automation.iter().any(|lane| {
+        matches!(lane.parameter, AutomationParameter::EffectMix(_)) && lane.enabled && !lane.points.is_empty()
+    });
+    if !automated {
+        effect_states.process_automated(&track.effects, |_| None, sample_rate, left, right);
+        return;
+    }
+
+    let mut done = 0;
+    while done < left.len() {
+        let time = start + done as u64;
+        let length = ((AUTOMATION_SUB_BLOCK - time % AUTOMATION_SUB_BLOCK) as usize).min(left.len() - done);
+        let end = done + length;
+        effect_states.process_automated(
+            &track.effects,
+            effect_mix_automation(track, time + length as u64),
+            sample_rate,
+            &mut left[done..end],
+            &mut right[done..end],
+        );
+        done = end;
+    }
+}
+
+/// Automated wet/dry mix of each effect in a track's chain at `time`, by
+/// chain index. Given the end of a block, the mix ramps across the block to
+/// where the lane is at its end.
+fn effect_mix_automation(track: &Track, time: SampleTime) -> impl Fn(usize) -> Option<f32> + '_ {
+    move |index| {
+        track
+            .get_automation_lane(AutomationParameter::EffectMix(index))
+            .and_then(|lane| lane.value_at(time))
+    }
+}
+
+/// Apply a track's volume, mute and pan automation to a block starting at
+/// `start`, every breakpoint taking effect on its own sample, along with the
+/// volume and mute automation of the VCA riding it
+fn apply_automation(track: &Track, vca: Option<&Track>, start: SampleTime, left: &mut [f32], right: &mut [f32]) {
+    let frames = left.len();
+    let mut gain = vec![1.0; frames];
+    let mut lane_gain = vec![1.0; frames];
+    for source in std::iter::once(track).chain(vca) {
+        if let Some(lane) = source.get_automation_lane(AutomationParameter::Volume) {
+            if lane.fill(start, &mut lane_gain) {
+                gain.iter_mut().zip(&lane_gain).for_each(|(gain, volume)| *gain *= volume);
+            }
+        }
+        if let Some(lane) = source.get_automation_lane(AutomationParameter::Mute) {
+            if lane.fill_mute_gain(start, MUTE_RAMP_SAMPLES, &mut lane_gain) {
+                gain.iter_mut().zip(&lane_gain).for_each(|(gain, mute)| *gain *= mute);
+            }
+        }
+    }
+    let mut pan = vec![0.0; frames];
+    if let Some(lane) = track.get_automation_lane(AutomationParameter::Pan) {
+        lane.fill(start, &mut pan);
+    }
+
+    for i in 0..frames {
+        let (pan_left, pan_right) = calculate_pan(pan[i]);
+        left[i] = left[i] * gain[i] * pan_left;
+        right[i] = right[i] * gain[i] * pan_right;
+    }
+}
+
+/// Where a track shifted by `offset` samples reads its clips for a buffer
+/// starting at `position`: the clip-time position of the first frame read and
+/// how many frames at the start of the buffer come from before the timeline
+/// start and stay silent. None if the whole buffer does.
+fn offset_window(position: SampleTime, offset: i64, buffer_size: usize) -> Option<(SampleTime, usize)> {
+    let shifted = position as i64 - offset;
+    if shifted >= 0 {
+        return Some((shifted as SampleTime, 0));
+    }
+    let skip = shifted.unsigned_abs();
+    (skip < buffer_size as u64).then_some((0, skip as usize))
+}
+
+/// Grain length of the time stretcher. Grains overlap by half, so there are
+/// always two in flight and their Hann windows sum to one.
+const STRETCH_GRAIN: u64 = 2048;
+
+/// One stereo frame of a source buffer, mono sources on both sides
+fn read_frame(asset: &AudioAssetData, frame: usize) -> Option<(f32, f32)> {
+    if frame >= asset.asset_ref.duration_samples {
+        return None;
+    }
+    let channels = asset.asset_ref.channels;
+    let index = frame * channels;
+    let l = asset.samples.get(index).copied().unwrap_or(0.0);
+    if channels == 1 {
+        Some((l, l))
+    } else {
+        Some((l, asset.samples.get(index + 1).copied().unwrap_or(l)))
+    }
+}
+
+/// A frame at a fractional source position, linearly interpolated
+fn read_interpolated(asset: &AudioAssetData, position: f64) -> Option<(f32, f32)> {
+    if position < 0.0 {
+        return None;
+    }
+    let frame = position.floor();
+    let t = (position - frame) as f32;
+    let (l0, r0) = read_frame(asset, frame as usize)?;
+    let (l1, r1) = read_frame(asset, frame as usize + 1).unwrap_or((l0, r0));
+    Some((l0 + (l1 - l0) * t, r0 + (r1 - r0) * t))
+}
+
+/// Pitch-preserving time stretch by overlap-add. Each grain plays the source
+/// at its own speed, and grains start `1 / time_ratio` as far apart in the
+/// source as they are on the timeline.
+fn read_stretched(asset: &AudioAssetData, clip: &AudioClip, relative_time: SampleTime) -> Option<(f32, f32)> {
+    let hop = STRETCH_GRAIN / 2;
+    let newest = relative_time / hop;
+
+    let mut frame = None;
+    for grain in [Some(newest), newest.checked_sub(1)].into_iter().flatten() {
+        let grain_start = grain * hop;
+        let position = relative_time - grain_start;
+        // The first grain has no predecessor to crossfade from, so it starts at full level
+        let window = if grain == 0 && position < hop {
+            1.0
+        } else {
+            (std::f32::consts::PI * position as f32 / STRETCH_GRAIN as f32).sin().powi(2)
+        };
+        let source = clip.offset as f64 + grain_start as f64 / clip.time_ratio + position as f64;
+        if let Some((l, r)) = read_interpolated(asset, clip.looped_source_position(source)) {
+            let (left, right) = frame.get_or_insert((0.0, 0.0));
+            *left += l * window;
+            *right += r * window;
+        }
+    }
+    frame
+}
+
+/// Add a track's post-fader signal to a stereo destination with its pan
+fn mix_into(track: &Track, left: &[f32], right: &[f32], dest_left: &mut [f32], dest_right: &mut [f32]) {
+    let (pan_left, pan_right) = calculate_pan(track.pan);
+
+    for i in 0..left.len().min(dest_left.len()) {
+        dest_left[i] += left[i] * pan_left;
+        dest_right[i] += right[i] * pan_right;
+    }
+}
+
+pub(crate) fn calculate_pan(pan: f32) -> (f32, f32) {
+    let pan = pan.clamp(-1.0, 1.0);
+    let angle = (pan + 1.0) * 0.25 * std::f32::consts::PI;
+    let left = angle.cos();
+    let right = angle.sin();
+    (left, right)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::surround::SurroundPan;
+    use super::super::test_support::{temp_dir, write_mono};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_calculate_pan() {
+        let (left, right) = calculate_pan(0.0);
+        assert!((left - 0.707).abs() < 0.01);
+        assert!((right - 0.707).abs() < 0.01);
+
+        let (left, right) = calculate_pan(-1.0);
+        assert!(left > 0.99);
+        assert!(right < 0.01);
+
+        let (left, right) = calculate_pan(1.0);
+        assert!(left < 0.01);
+        assert!(right > 0.99);
+    }
+
+    /// Count sign changes, which is twice the frequency over one second
+    fn zero_crossings(samples: &[f32]) -> usize {
+        samples.windows(2).filter(|w| (w[0] < 0.0) != (w[1] < 0.0)).count()
+    }
+
+    #[test]
+    fn test_repitch_and_stretch_playback() {
+        let sample_rate = 48000;
+        let tone: Vec<f32> = (0..sample_rate * 2)
+            .map(|i| (2.0 * std::f32::consts::PI * 500.0 * i as f32 / sample_rate as f32).sin())
+            .collect();
+        let asset = AudioAssetData {
+            asset_ref: AudioAssetRef {
+                path: "tone.wav".into(),
+                sample_rate: sample_rate as f32,
+                channels: 1,
+                duration_samples: tone.len(),
+                source: SourceFormat::default(),
+                health: Default::default(),
+            },
+            samples: Arc::new(tone),
+        };
+        let graph = AudioGraph::new(AssetManager::new());
+
+        // Twice as long on the timeline, i.e. the tempo halved
+        let mut clip = AudioClip::new("tone.wav".into(), 0, sample_rate as u64 * 2);
+        clip.time_ratio = 2.0;
+        let render = |clip: &AudioClip| {
+            let mut left = vec![0.0; sample_rate];
+            let mut right = vec![0.0; sample_rate];
+            graph.render_clip(clip, &asset, sample_rate as u64 / 2, sample_rate, &mut left, &mut right);
+            left
+        };
+
+        clip.tempo_mode = Some(TempoMode::Repitch);
+        let repitched = zero_crossings(&render(&clip));
+        assert!((repitched as i32 - 500).abs() < 10, "repitched crossings {}", repitched);
+
+        clip.tempo_mode = Some(TempoMode::Stretch);
+        let stretched = render(&clip);
+        let crossings = zero_crossings(&stretched);
+        assert!((crossings as i32 - 1000).abs() < 60, "stretched crossings {}", crossings);
+        assert!(stretched.iter().all(|s| s.abs() <= 1.01));
+    }
+
+    #[test]
+    fn test_looped_clip_repeats_its_loop() {
+        let ramp: Vec<f32> = (0..1000).map(|i| i as f32).collect();
+        let asset = AudioAssetData {
+            asset_ref: AudioAssetRef {
+                path: "ramp.wav".into(),
+                sample_rate: 48000.0,
+                channels: 1,
+                duration_samples: ramp.len(),
+                source: SourceFormat::default(),
+                health: Default::default(),
+            },
+            samples: Arc::new(ramp),
+        };
+        let graph = AudioGraph::new(AssetManager::new());
+
+        // Plays 100..400 once, then repeats 300..400 well past the file's end
+        let mut clip = AudioClip::new("ramp.wav".into(), 0, 2000);
+        clip.offset = 100;
+        clip.loop_points = Some(ClipLoop { start: 300, end: 400 });
+        clip.tempo_mode = Some(TempoMode::Fixed);
+        let mut left = vec![0.0; 2000];
+        let mut right = vec![0.0; 2000];
+        graph.render_clip(&clip, &asset, 0, 2000, &mut left, &mut right);
+        assert_eq!(left[0], 100.0);
+        assert_eq!(left[299], 399.0);
+        assert_eq!(left[300], 300.0);
+        assert_eq!(left[1999], 399.0);
+    }
+
+    #[test]
+    fn test_clips_start_on_their_frame_at_any_buffer_size() {
+        let dir = temp_dir();
+        let path = dir.join("dc.wav");
+        write_mono(&path, &[0.5; 2000]);
+
+        let manager = AssetManager::new();
+        let mut track = Track::new("Hits", TrackType::Audio);
+        // One clip starting mid-buffer and one short enough to start and
+        // end inside a single buffer
+        track.clips.push(AudioClip::new(path.clone(), 1000, 2000));
+        track.clips.push(AudioClip::new(path.clone(), 3210, 40));
+        let mut graph = AudioGraph::new(manager.clone());
+        graph.add_track(track);
+        // The limiter's lookahead would delay everything
+        graph.set_master_limiter(LimiterSettings { enabled: false, ..LimiterSettings::default() });
+
+        // Playing from the start needs nothing yet, the first clip's file
+        // is wanted once the playhead is within reach of it
+        assert!(graph.uncached_assets(0, 512).is_empty());
+        assert_eq!(graph.uncached_assets(600, 512), vec![path.clone()]);
+        futures::executor::block_on(manager.load_asset(path.clone())).unwrap();
+        assert!(graph.uncached_assets(600, 512).is_empty());
+
+        for buffer_size in [64, 100, 333, BUFFER_SIZE] {
+            let mut transport = Transport::default();
+            transport.state = TransportState::Playing;
+            let mut output = Vec::new();
+            while output.len() < 4000 {
+                let mut left = vec![0.0; buffer_size];
+                let mut right = vec![0.0; buffer_size];
+                graph.process(&transport, &mut left, &mut right);
+                transport.advance(buffer_size);
+                output.extend(left);
+            }
+            let first = output.iter().position(|s| *s != 0.0);
+            assert_eq!(first, Some(1000), "buffer size {}", buffer_size);
+            assert!(output[2999] != 0.0 && output[3000] == 0.0, "buffer size {}", buffer_size);
+            let short: Vec<usize> = (3000..4000).filter(|&i| output[i] != 0.0).collect();
+            assert_eq!(short.first().zip(short.last()), Some((&3210, &3249)), "buffer size {}", buffer_size);
+        }
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_comped_track_plays_the_top_clip() {
+        let dir = temp_dir();
+        let path = dir.join("dc.wav");
+        write_mono(&path, &[0.5; 2000]);
+        let manager = AssetManager::new();
+        futures::executor::block_on(manager.load_asset(path.clone())).unwrap();
+        let graph = AudioGraph::new(manager);
+
+        let mut track = Track::new("Dialogue", TrackType::Audio);
+        track.clips.push(AudioClip::new(path.clone(), 0, 2000));
+        let mut top = AudioClip::new(path.clone(), 500, 1000);
+        top.gain = 0.5;
+        top.fade_in = 100;
+        track.clips.push(top);
+
+        let render = |track: &Track| {
+            let mut left = vec![0.0; 2000];
+            let mut right = vec![0.0; 2000];
+            let transport = Transport { state: TransportState::Playing, ..Transport::default() };
+            graph.process_track(track, &transport, true, &mut EffectStates::new(), &mut left, &mut right);
+            left
+        };
+        // The pan law applies to both alike, so compare against the bottom clip alone
+        let layered = render(&track);
+        let alone = layered[200];
+        assert!((layered[1000] / alone - 1.5).abs() < 1e-4);
+
+        track.overlap_mode = OverlapMode::Comp;
+        let comped = render(&track);
+        assert!((comped[200] / alone - 1.0).abs() < 1e-4);
+        assert!((comped[1000] / alone - 0.5).abs() < 1e-4);
+        // Through the top clip's fade in the bottom clip hands over to it
+        assert!(comped[550] / alone < 1.0 && comped[550] / alone > 0.5);
+        assert!((comped[1600] / alone - 1.0).abs() < 1e-4);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_offset_window() {
+        // Delayed tracks read behind the playhead, advanced ones ahead of it
+        assert_eq!(offset_window(1000, 0, 512), Some((1000, 0)));
+        assert_eq!(offset_window(1000, 240, 512), Some((760, 0)));
+        assert_eq!(offset_window(1000, -240, 512), Some((1240, 0)));
+
+        // Never read before the start: the first frames stay silent instead
+        assert_eq!(offset_window(0, 100, 512), Some((0, 100)));
+        assert_eq!(offset_window(0, 600, 512), None);
+        assert_eq!(offset_window(512, 600, 512), Some((0, 88)));
+        assert_eq!(offset_window(0, -100, 512), Some((100, 0)));
+    }
+
+    #[test]
+    fn test_audio_graph_creation() {
+        let manager = AssetManager::new();
+        let graph = AudioGraph::new(manager);
+        assert_eq!(graph.get_all_tracks().len(), 0);
+    }
+
+    #[test]
+    fn test_add_remove_track() {
+        let manager = AssetManager::new();
+        let mut graph = AudioGraph::new(manager);
+        
+        let track = Track::new("Test Track", TrackType::Audio);
+        let id = graph.add_track(track);
+        
+        assert!(graph.get_track(id).is_some());
+        
+        graph.remove_track(id);
+        assert!(graph.get_track(id).is_none());
+    }
+
+    #[test]
+    fn test_solo_state() {
+        let manager = AssetManager::new();
+        let mut graph = AudioGraph::new(manager);
+        
+        let mut track1 = Track::new("Track 1", TrackType::Audio);
+        track1.solo = true;
+        let id1 = graph.add_track(track1);
+        
+        assert!(graph.any_solo);
+    }
+
+    #[test]
+    fn test_solo_safe_and_isolate() {
+        let mut dry = Track::new("Dry", TrackType::Audio);
+        let other = Track::new("Other", TrackType::Audio);
+        let reverb = Track::new("Reverb", TrackType::Return);
+        let mut click = Track::new("Click", TrackType::Audio);
+        click.solo_isolate = true;
+        assert!(reverb.solo_safe);
+
+        // Soloing the dry track keeps its reverb return and the click
+        dry.solo = true;
+        assert!(dry.solo_active());
+        assert!(!dry.solo_muted(true));
+        assert!(other.solo_muted(true));
+        assert!(!reverb.solo_muted(true));
+        assert!(!click.solo_muted(true));
+
+        // Soloing an isolated track doesn't silence anything
+        let mut graph = AudioGraph::new(AssetManager::new());
+        click.solo = true;
+        graph.add_track(click);
+        graph.add_track(other);
+        assert!(!graph.any_solo);
+
+        let mut aux = Track::new("Aux", TrackType::Aux);
+        assert!(aux.solo_muted(true));
+        aux.solo_safe = true;
+        assert!(!aux.solo_muted(true));
+    }
+
+    #[test]
+    fn test_auto_monitor_switches_at_punch_in() {
+        let manager = AssetManager::new();
+        let mut graph = AudioGraph::new(manager);
+        graph.set_input_enabled(true);
+
+        let mut track = Track::new("Vocal", TrackType::Audio);
+        track.record_armed = true;
+        track.monitor = MonitorMode::Auto;
+        graph.add_track(track);
+
+        let (input_left, input_right) = graph.input_buffers_mut();
+        input_left.fill(0.5);
+        input_right.fill(0.5);
+
+        let mut transport = Transport::default();
+        transport.state = TransportState::Recording;
+        transport.punch_enabled = true;
+        transport.punch_in = 100;
+        transport.punch_out = 48000;
+
+        let mut left = vec![0.0; BUFFER_SIZE];
+        let mut right = vec![0.0; BUFFER_SIZE];
+        graph.process(&transport, &mut left, &mut right);
+
+        assert_eq!(left[99], 0.0);
+        assert!(left[100] > 0.0);
+        assert!(right[BUFFER_SIZE - 1] > 0.0);
+    }
+
+    #[test]
+    fn test_volume_breakpoint_lands_on_its_sample() {
+        let mut graph = AudioGraph::new(AssetManager::new());
+        graph.set_input_enabled(true);
+
+        // A cut that falls mid-buffer
+        let cut = BUFFER_SIZE as SampleTime * 2 + 188;
+        let mut track = Track::new("Vocal", TrackType::Audio);
+        track.monitor = MonitorMode::In;
+        for (time, value) in [(0, 1.0), (cut, 0.0)] {
+            track.get_automation_lane_mut(AutomationParameter::Volume).add_point(AutomationPoint {
+                id: uuid::Uuid::new_v4(),
+                time,
+                value,
+                curve_type: CurveType::Hold,
+                tension: 0.0,
+            });
+        }
+        graph.add_track(track);
+
+        let mut output = Vec::new();
+        let mut transport = Transport::default();
+        while output.len() < cut as usize + BUFFER_SIZE {
+            let (input_left, input_right) = graph.input_buffers_mut();
+            input_left.fill(0.5);
+            input_right.fill(0.5);
+
+            let mut left = vec![0.0; BUFFER_SIZE];
+            let mut right = vec![0.0; BUFFER_SIZE];
+            graph.process(&transport, &mut left, &mut right);
+            output.extend(left);
+            transport.position += BUFFER_SIZE as u64;
+        }
+
+        assert!(output[cut as usize - 1] > 0.0);
+        assert_eq!(output[cut as usize], 0.0);
+        assert!(output[cut as usize..].iter().all(|sample| *sample == 0.0));
+    }
+
+    #[test]
+    fn test_monitor_reads_the_selected_input_channels() {
+        let mut graph = AudioGraph::new(AssetManager::new());
+        graph.set_input_enabled(true);
+        graph.set_input_channels(4);
+
+        let mut track = Track::new("Guitar", TrackType::Audio);
+        track.monitor = MonitorMode::In;
+        track.record_input = RecordInput::Mono(2);
+        let track_id = track.id;
+        graph.add_track(track);
+
+        for (channel, input) in graph.input_channels_mut().iter_mut().enumerate() {
+            input.fill(channel as f32 * 0.1);
+        }
+
+        let transport = Transport::default();
+        let (mut left, mut right) = (vec![0.0; BUFFER_SIZE], vec![0.0; BUFFER_SIZE]);
+        graph.process(&transport, &mut left, &mut right);
+
+        // Channel 3 alone, heard equally on both sides
+        let (pan_left, pan_right) = calculate_pan(0.0);
+        assert!((left[0] - 0.2 * pan_left).abs() < 1e-5);
+        assert!((right[0] - 0.2 * pan_right).abs() < 1e-5);
+
+        // A pair whose second channel the device lacks is read as mono
+        graph.set_input_channels(3);
+        graph.get_track_mut(track_id).unwrap().record_input = RecordInput::Stereo(2);
+        graph.process(&transport, &mut left, &mut right);
+        assert!((right[0] - 0.2 * pan_right).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_armed_track_meters_input_while_stopped() {
+        let mut graph = AudioGraph::new(AssetManager::new());
+        graph.set_input_enabled(true);
+        let mut track = Track::new("Vocal", TrackType::Audio);
+        track.record_armed = true;
+        track.record_input = RecordInput::Mono(0);
+        let track_id = track.id;
+        graph.add_track(track);
+        assert!(graph.is_metering_input());
+
+        // The audio thread meters input on its own while nothing plays
+        let feed = |graph: &mut AudioGraph, level: f32| {
+            graph.input_channels_mut()[0].fill(level);
+            graph.meter_inputs(BUFFER_SIZE, SAMPLE_RATE);
+            graph.get_input_meter(track_id).unwrap()
+        };
+        let quiet = feed(&mut graph, 0.5);
+        assert_eq!((quiet.meter.peak_left, quiet.meter.peak_right), (0.5, 0.5));
+        assert!(!quiet.clipped);
+
+        // A clip stays lit after the input drops until it's cleared
+        assert!(feed(&mut graph, 1.0).clipped);
+        assert!(feed(&mut graph, 0.1).clipped);
+        graph.clear_input_clip(None);
+        assert!(!feed(&mut graph, 0.1).clipped);
+
+        // Processing a stopped block meters too, and nothing is heard
+        graph.input_channels_mut()[0].fill(0.25);
+        let (mut left, mut right) = (vec![0.0; BUFFER_SIZE], vec![0.0; BUFFER_SIZE]);
+        graph.process(&Transport::default(), &mut left, &mut right);
+        assert_eq!(graph.get_input_meter(track_id).unwrap().meter.peak_left, 0.25);
+        assert!(left.iter().all(|s| *s == 0.0));
+
+        graph.get_track_mut(track_id).unwrap().record_armed = false;
+        graph.meter_inputs(BUFFER_SIZE, SAMPLE_RATE);
+        assert!(graph.get_input_meter(track_id).is_none());
+        assert!(!graph.is_metering_input());
+    }
+
+    #[test]
+    fn test_chained_returns_reach_master() {
+        let manager = AssetManager::new();
+        let mut graph = AudioGraph::new(manager);
+        graph.set_input_enabled(true);
+
+        let mut source = Track::new("Vocal", TrackType::Audio);
+        source.record_armed = true;
+        source.monitor = MonitorMode::In;
+        source.volume = 0.0;
+
+        let mut delay = Track::new("Delay", TrackType::Return);
+        let reverb = Track::new("Reverb", TrackType::Return);
+
+        // Vocal -> Delay -> Reverb, with the fader muting the dry signal
+        source.sends[0] = Send {
+            target_track: Some(delay.id),
+            amount: 1.0,
+            pre_fader: true,
+            enabled: true,
+            follow_mute: true,
+        };
+        delay.sends[0] = Send {
+            target_track: Some(reverb.id),
+            amount: 0.5,
+            pre_fader: true,
+            enabled: true,
+            follow_mute: true,
+        };
+        delay.volume = 0.0;
+
+        let reverb_id = reverb.id;
+        let delay_id = delay.id;
+        graph.add_track(reverb);
+        graph.add_track(delay);
+        graph.add_track(source);
+
+        let order = graph.bus_processing_order();
+        assert_eq!(order, vec![delay_id, reverb_id]);
+
+        let (input_left, input_right) = graph.input_buffers_mut();
+        input_left.fill(0.5);
+        input_right.fill(0.5);
+
+        let transport = Transport::default();
+        let mut left = vec![0.0; BUFFER_SIZE];
+        let mut right = vec![0.0; BUFFER_SIZE];
+        graph.process(&transport, &mut left, &mut right);
+
+        // Only the reverb return contributes to the master bus
+        let (pan_left, _) = calculate_pan(0.0);
+        assert!((left[0] - 0.25 * pan_left).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_sends_tap_before_or_after_fader_and_mute() {
+        let manager = AssetManager::new();
+        let mut graph = AudioGraph::new(manager);
+        graph.set_input_enabled(true);
+
+        let mut source = Track::new("Vocal", TrackType::Audio);
+        source.record_armed = true;
+        source.monitor = MonitorMode::In;
+        source.volume = 0.5;
+        let cue = Track::new("Cue", TrackType::Return);
+        let reverb = Track::new("Reverb", TrackType::Return);
+        // A headphone cue taken before the fader and kept through mutes,
+        // and a default post-fader reverb send
+        source.sends[0] = Send {
+            target_track: Some(cue.id),
+            amount: 1.0,
+            pre_fader: true,
+            enabled: true,
+            follow_mute: false,
+        };
+        source.sends[1] = Send { target_track: Some(reverb.id), amount: 1.0, enabled: true, ..Send::default() };
+        let (cue_id, reverb_id) = (cue.id, reverb.id);
+        let source_id = graph.add_track(source);
+        graph.add_track(cue);
+        graph.add_track(reverb);
+
+        let (input_left, input_right) = graph.input_buffers_mut();
+        input_left.fill(0.5);
+        input_right.fill(0.5);
+
+        let transport = Transport::default();
+        let mut left = vec![0.0; BUFFER_SIZE];
+        let mut right = vec![0.0; BUFFER_SIZE];
+        let bus_peak = |graph: &AudioGraph, id: TrackId| graph.get_track_meter(id).map_or(0.0, |m| m.peak_left);
+
+        graph.process(&transport, &mut left, &mut right);
+        assert!((bus_peak(&graph, cue_id) - 0.5).abs() < 1e-4);
+        assert!((bus_peak(&graph, reverb_id) - 0.25).abs() < 1e-4);
+
+        // Muting the track silences it and the reverb send; the cue carries on
+        graph.get_track_mut(source_id).unwrap().muted = true;
+        graph.process(&transport, &mut left, &mut right);
+        assert!((bus_peak(&graph, cue_id) - 0.5).abs() < 1e-4);
+        assert_eq!(bus_peak(&graph, reverb_id), 0.0);
+        assert!(graph.get_track_meter(source_id).is_none_or(|m| m.peak_left == 0.0));
+    }
+
+    #[test]
+    fn test_vca_rides_its_tracks_without_carrying_audio() {
+        let manager = AssetManager::new();
+        let mut graph = AudioGraph::new(manager);
+        graph.set_input_enabled(true);
+
+        // Two monitored tracks at different levels, each sending post-fader
+        // to its own return so their levels can be read apart
+        let mut vca = Track::new("VCA 1", TrackType::Vca);
+        vca.volume = 0.5;
+        let mut bus_ids = Vec::new();
+        let mut member_ids = Vec::new();
+        for (name, volume) in [("Kick", 1.0), ("Snare", 0.5)] {
+            let bus = Track::new(format!("{} Bus", name), TrackType::Return);
+            let mut track = Track::new(name, TrackType::Audio);
+            track.record_armed = true;
+            track.monitor = MonitorMode::In;
+            track.volume = volume;
+            track.vca = Some(vca.id);
+            track.sends[0] = Send { target_track: Some(bus.id), amount: 1.0, enabled: true, ..Send::default() };
+            bus_ids.push(graph.add_track(bus));
+            member_ids.push(graph.add_track(track));
+        }
+        let vca_id = graph.add_track(vca);
+
+        let transport = Transport::default();
+        let mut left = vec![0.0; BUFFER_SIZE];
+        let mut right = vec![0.0; BUFFER_SIZE];
+        let mut run = |graph: &mut AudioGraph| {
+            // Long enough for fader moves to finish ramping
+            for _ in 0..4 {
+                let (input_left, input_right) = graph.input_buffers_mut();
+                input_left.fill(1.0);
+                input_right.fill(1.0);
+                graph.process(&transport, &mut left, &mut right);
+            }
+            bus_ids.iter().map(|id| graph.get_track_meter(*id).map_or(0.0, |m| m.peak_left)).collect::<Vec<_>>()
+        };
+
+        // Both are halved, keeping the snare 6 dB under the kick
+        let levels = run(&mut graph);
+        assert!((levels[0] - 0.5).abs() < 1e-4, "{:?}", levels);
+        assert!((levels[1] - 0.25).abs() < 1e-4, "{:?}", levels);
+        assert!(graph.get_track_meter(vca_id).is_none());
+
+        // The VCA's automation applies on top of the tracks' own faders
+        graph.get_track_mut(vca_id).unwrap().get_automation_lane_mut(AutomationParameter::Volume).add_point(AutomationPoint {
+            id: uuid::Uuid::new_v4(),
+            time: 0,
+            value: 0.5,
+            curve_type: CurveType::Hold,
+            tension: 0.0,
+        });
+        let levels = run(&mut graph);
+        assert!((levels[0] - 0.25).abs() < 1e-4, "{:?}", levels);
+        assert!((levels[1] - 0.125).abs() < 1e-4, "{:?}", levels);
+
+        // Muting the VCA mutes its tracks, and an unassigned track is left alone
+        graph.get_track_mut(vca_id).unwrap().muted = true;
+        graph.get_track_mut(member_ids[1]).unwrap().vca = None;
+        let levels = run(&mut graph);
+        assert_eq!(levels[0], 0.0);
+        assert!((levels[1] - 0.5).abs() < 1e-4, "{:?}", levels);
+    }
+
+    #[test]
+    fn test_inactive_tracks_are_skipped() {
+        let manager = AssetManager::new();
+        let mut graph = AudioGraph::new(manager);
+        graph.set_input_enabled(true);
+
+        let mut source = Track::new("Vocal", TrackType::Audio);
+        source.record_armed = true;
+        source.monitor = MonitorMode::In;
+        source.volume = 0.0;
+        source.solo = true;
+        let mut reverb = Track::new("Reverb", TrackType::Return);
+        source.sends[0] = Send {
+            target_track: Some(reverb.id),
+            amount: 1.0,
+            pre_fader: true,
+            enabled: true,
+            follow_mute: true,
+        };
+        reverb.active = false;
+        let reverb_id = reverb.id;
+        let source_id = graph.add_track(source);
+        graph.add_track(reverb);
+
+        let (input_left, input_right) = graph.input_buffers_mut();
+        input_left.fill(0.5);
+        input_right.fill(0.5);
+
+        // The send into an inactive return goes nowhere
+        let transport = Transport::default();
+        let mut left = vec![0.0; BUFFER_SIZE];
+        let mut right = vec![0.0; BUFFER_SIZE];
+        graph.process(&transport, &mut left, &mut right);
+        assert_eq!(left[0], 0.0);
+        assert!(graph.get_track_meter(reverb_id).is_none());
+
+        // Reactivating brings it back with its settings untouched
+        graph.get_track_mut(reverb_id).unwrap().active = true;
+        graph.process(&transport, &mut left, &mut right);
+        assert!(left[0] > 0.0);
+
+        // An inactive track's solo no longer mutes the others, and it stops
+        // asking for input monitoring
+        graph.get_track_mut(source_id).unwrap().active = false;
+        graph.update_solo_state();
+        assert!(!graph.any_solo);
+        assert!(!graph.is_monitoring_input(&transport, BUFFER_SIZE));
+    }
+
+    #[test]
+    fn test_surround_bed_follows_the_surround_pan() {
+        let mut graph = AudioGraph::new(AssetManager::new());
+        graph.set_input_enabled(true);
+        let mut track = Track::new("Ambience", TrackType::Audio);
+        track.record_armed = true;
+        track.monitor = MonitorMode::In;
+        // Back left of the field
+        track.surround_pan = SurroundPan { x: -1.0, y: -1.0, lfe: 0.0 };
+        graph.add_track(track);
+
+        let transport = Transport::default();
+        let process = |graph: &mut AudioGraph| {
+            let (input_left, input_right) = graph.input_buffers_mut();
+            input_left.fill(0.25);
+            input_right.fill(0.25);
+            let mut left = vec![0.0; BUFFER_SIZE];
+            let mut right = vec![0.0; BUFFER_SIZE];
+            graph.process(&transport, &mut left, &mut right);
+            (left, right)
+        };
+
+        // Stereo projects build no bed
+        let stereo = process(&mut graph);
+        assert!(graph.surround_outputs().is_empty());
+
+        graph.set_channel_layout(ChannelLayout::Quad);
+        let (left, right) = process(&mut graph);
+        assert_eq!((left[10], right[10]), (stereo.0[10], stereo.1[10]));
+        let bed = graph.surround_outputs();
+        assert_eq!(bed.len(), 4);
+        // A centred input comes out at its own level
+        assert!((bed[2][10] - 0.25).abs() < 0.01, "{:?}", bed.iter().map(|c| c[10]).collect::<Vec<_>>());
+        assert!(bed[0][10].abs() < 1e-3 && bed[1][10].abs() < 1e-3 && bed[3][10].abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_reference_plays_instead_of_the_mix() {
+        let mut graph = AudioGraph::new(AssetManager::new());
+        graph.set_input_enabled(true);
+        graph.get_master_track_mut().volume = 0.5;
+
+        let monitored = |name: &str, track_type| {
+            let mut track = Track::new(name, track_type);
+            track.record_armed = true;
+            track.monitor = MonitorMode::In;
+            track
+        };
+        graph.add_track(monitored("Mix", TrackType::Audio));
+        let mut reference = monitored("Reference", TrackType::Reference);
+        reference.volume = 0.25;
+        // A soloed reference doesn't silence the mix
+        reference.solo = true;
+        graph.add_track(reference);
+
+        let (input_left, input_right) = graph.input_buffers_mut();
+        input_left.fill(0.5);
+        input_right.fill(0.5);
+
+        let transport = Transport::default();
+        let mut left = vec![0.0; BUFFER_SIZE];
+        let mut right = vec![0.0; BUFFER_SIZE];
+        let (pan_left, _) = calculate_pan(0.0);
+
+        graph.process(&transport, &mut left, &mut right);
+        assert!((left[0] - 0.5 * pan_left * 0.5).abs() < 1e-4);
+
+        // Only the reference at its own gain, without the master fader
+        graph.set_listen_reference(true);
+        graph.process(&transport, &mut left, &mut right);
+        assert!((left[0] - 0.5 * pan_left * 0.25).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_direct_output_and_fallback_to_main() {
+        let mut graph = AudioGraph::new(AssetManager::new());
+        graph.set_input_enabled(true);
+        graph.set_output_channels(4);
+
+        let mut stem = Track::new("Stem", TrackType::Audio);
+        stem.record_armed = true;
+        stem.monitor = MonitorMode::In;
+        stem.output = OutputTarget::Pair(1);
+        let stem_id = stem.id;
+        graph.add_track(stem);
+
+        let (input_left, input_right) = graph.input_buffers_mut();
+        input_left.fill(0.5);
+        input_right.fill(0.5);
+
+        let transport = Transport::default();
+        let mut left = vec![0.0; BUFFER_SIZE];
+        let mut right = vec![0.0; BUFFER_SIZE];
+        graph.process(&transport, &mut left, &mut right);
+
+        // Outputs 3/4 carry the track, the master bus stays silent
+        let (pan_left, _) = calculate_pan(0.0);
+        assert!(left.iter().all(|s| *s == 0.0));
+        let (pair, direct) = graph.direct_outputs().next().unwrap();
+        assert_eq!(pair, 1);
+        assert!((direct.left[0] - 0.5 * pan_left).abs() < 1e-4);
+
+        // A stereo device has no outputs 3/4, so the track plays on main
+        graph.set_output_channels(2);
+        assert!(graph.get_track(stem_id).is_some());
+        graph.process(&transport, &mut left, &mut right);
+        assert!((left[0] - 0.5 * pan_left).abs() < 1e-4);
+        assert!(graph.direct_outputs().next().is_none());
+    }
+
+    #[test]
+    fn test_mute_ramps_without_clicking() {
+        let ramp = smoothing_samples(DEFAULT_SMOOTHING_MS, SAMPLE_RATE);
+        let render = |smoothing: usize| {
+            let mut graph = AudioGraph::new(AssetManager::new());
+            graph.set_input_enabled(true);
+            graph.set_smoothing_samples(smoothing);
+            let mut track = Track::new("Tone", TrackType::Audio);
+            track.record_armed = true;
+            track.monitor = MonitorMode::In;
+            let track_id = graph.add_track(track);
+
+            let transport = Transport::default();
+            let mut output = Vec::new();
+            for block in 0..8 {
+                // Muted for blocks 2 to 4, neither toggle on a zero crossing
+                graph.get_track_mut(track_id).unwrap().muted = (2..5).contains(&block);
+                let (input_left, input_right) = graph.input_buffers_mut();
+                for (i, (l, r)) in input_left.iter_mut().zip(input_right.iter_mut()).enumerate() {
+                    let t = (block * BUFFER_SIZE + i) as f32 / SAMPLE_RATE;
+                    *l = 0.5 * (2.0 * std::f32::consts::PI * 440.0 * t).sin();
+                    *r = *l;
+                }
+                let mut left = vec![0.0; BUFFER_SIZE];
+                let mut right = vec![0.0; BUFFER_SIZE];
+                graph.process(&transport, &mut left, &mut right);
+                output.extend(left);
+            }
+            output
+        };
+        let largest_step = |samples: &[f32]| samples.windows(2).map(|w| (w[1] - w[0]).abs()).fold(0.0, f32::max);
+        // Steepest step of the tone itself, at 0.25 after the pan law
+        let tone_step = 0.25 * 2.0 * std::f32::consts::PI * 440.0 / SAMPLE_RATE;
+
+        let smoothed = render(ramp);
+        assert!(largest_step(&smoothed) < tone_step * 1.2, "{} against {}", largest_step(&smoothed), tone_step);
+        // Silent once the ramp is over, then back at full level
+        assert!(smoothed[2 * BUFFER_SIZE + ramp..5 * BUFFER_SIZE].iter().all(|s| *s == 0.0));
+        let peak = smoothed[5 * BUFFER_SIZE + ramp..].iter().fold(0.0_f32, |max, s| max.max(s.abs()));
+        assert!((peak - 0.25).abs() < 0.01, "{}", peak);
+
+        // Without smoothing the mute lands on its sample and steps
+        let instant = render(0);
+        assert!(instant[2 * BUFFER_SIZE..5 * BUFFER_SIZE].iter().all(|s| *s == 0.0));
+        assert!(instant[2 * BUFFER_SIZE - 1].abs() > 0.1);
+        assert!(largest_step(&instant) > 0.1);
+    }
+
+    #[test]
+    fn test_restored_state_forgets_what_was_rendered_ahead() {
+        let graph_with_limiter = || {
+            let mut graph = AudioGraph::new(AssetManager::new());
+            graph.set_input_enabled(true);
+            graph.set_master_limiter(LimiterSettings { enabled: true, ..LimiterSettings::default() });
+            let mut track = Track::new("Input", TrackType::Audio);
+            track.record_armed = true;
+            track.monitor = MonitorMode::In;
+            graph.add_track(track);
+            graph
+        };
+        let render = |graph: &mut AudioGraph, level: f32| {
+            let (input_left, input_right) = graph.input_buffers_mut();
+            input_left.fill(level);
+            input_right.fill(level);
+            let mut left = vec![0.0; BUFFER_SIZE];
+            let mut right = vec![0.0; BUFFER_SIZE];
+            graph.process(&Transport::default(), &mut left, &mut right);
+            left
+        };
+
+        let mut straight = graph_with_limiter();
+        render(&mut straight, 0.25);
+        let expected = render(&mut straight, 0.0);
+        assert!(expected[0] > 0.0);
+
+        // A loud block rendered ahead leaves nothing in the limiter's lookahead
+        let mut ahead = graph_with_limiter();
+        render(&mut ahead, 0.25);
+        let mut saved = SavedGraphState::default();
+        ahead.save_state(&mut saved);
+        render(&mut ahead, 1.0);
+        ahead.restore_state(&mut saved);
+        assert_eq!(render(&mut ahead, 0.0), expected);
+    }
+}
@@ -3,12 +3,20 @@ use super::asset_manager::AssetManager;
 use super::audio_graph::AudioGraph;
 use super::audio_types::*;
 use super::gpu_dsp::{GpuDsp, DspJob};
+use super::limiter::LimiterSettings;
+use super::metering::{InputMeter, MeterBallistics};
+use super::profiling::DspProfile;
+use super::surround::ChannelLayout;
 use super::real_time_audio::{AudioCommand, RealTimeAudio};
+use super::take_recorder::{RecordedTake, TakeTarget, TakeWriter};
 use anyhow::Result;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use std::marker::Send;
 
+/// Frames past the playhead whose clips are loaded before playback starts
+const PREROLL_FRAMES: usize = 2 * BUFFER_SIZE;
+
 /// Main DAW audio service
 pub struct AudioService {
     audio_graph: Arc<parking_lot::RwLock<AudioGraph>>,
@@ -17,6 +25,8 @@ pub struct AudioService {
     gpu_dsp: Arc<RwLock<Option<GpuDsp>>>,
     transport: Arc<parking_lot::RwLock<Transport>>,
     performance_metrics: Arc<RwLock<PerformanceMetrics>>,
+    /// The take being recorded, if one is
+    take: parking_lot::Mutex<Option<TakeWriter>>,
 }
 
 /// Thread-safe position monitor that can be cloned and sent across threads
@@ -64,12 +74,20 @@ impl AudioService {
             gpu_dsp,
             transport,
             performance_metrics,
+            take: parking_lot::Mutex::new(None),
         };
 
         Ok(service)
     }
 
+    /// Any impulse responses the track's convolution reverbs use are loaded
+    /// first, so the graph finds them in the cache
     pub async fn add_track(&self, track: Track) -> TrackId {
+        for path in track.effects.iter().filter_map(|e| e.impulse_response.clone()) {
+            if let Err(e) = self.asset_manager.load_asset(path.clone()).await {
+                tracing::error!("❌ Failed to load impulse response {:?} for '{}': {}", path, track.name, e);
+            }
+        }
         let mut graph = self.audio_graph.write();
         graph.add_track(track)
     }
@@ -113,6 +131,53 @@ impl AudioService {
             .send_command(AudioCommand::SetMasterVolume(volume))
     }
 
+    /// Trim the speaker level without touching the mix, see
+    /// [`EditPreferences::monitor_level_db`](super::preferences::EditPreferences::monitor_level_db)
+    pub async fn set_monitor_gain(&self, gain: f32) -> Result<()> {
+        self.real_time_audio
+            .send_command(AudioCommand::SetMonitorGain(gain))
+    }
+
+    /// Ramp mute, solo, fader and bypass changes over `samples` samples,
+    /// see [`EditPreferences::smoothing_ms`](super::preferences::EditPreferences::smoothing_ms)
+    pub async fn set_smoothing_samples(&self, samples: usize) {
+        let mut graph = self.audio_graph.write();
+        graph.set_smoothing_samples(samples);
+    }
+
+    /// Meter ballistics for every channel and the master, see
+    /// [`EditPreferences::meter_ballistics`](super::preferences::EditPreferences::meter_ballistics)
+    pub async fn set_meter_ballistics(&self, ballistics: MeterBallistics) {
+        let mut graph = self.audio_graph.write();
+        graph.set_meter_ballistics(ballistics);
+    }
+
+    /// Play the reference tracks instead of the mix, or go back to the mix
+    pub async fn set_listen_reference(&self, listen: bool) {
+        let mut graph = self.audio_graph.write();
+        graph.set_listen_reference(listen);
+    }
+
+    pub async fn set_channel_layout(&self, layout: ChannelLayout) {
+        let mut graph = self.audio_graph.write();
+        graph.set_channel_layout(layout);
+    }
+
+    pub async fn set_master_limiter(&self, settings: LimiterSettings) {
+        let mut graph = self.audio_graph.write();
+        graph.set_master_limiter(settings);
+    }
+
+    pub async fn get_master_limiter_reduction(&self) -> f32 {
+        let graph = self.audio_graph.read();
+        graph.get_master_limiter_reduction()
+    }
+
+    pub async fn get_master_gain_match_db(&self) -> f32 {
+        let graph = self.audio_graph.read();
+        graph.get_master_gain_match_db()
+    }
+
     pub async fn set_track_volume(&self, track_id: TrackId, volume: f32) -> Result<()> {
         {
             let mut graph = self.audio_graph.write();
@@ -146,6 +211,19 @@ impl AudioService {
             .send_command(AudioCommand::SetTrackMute { track_id, muted })
     }
 
+    /// Take a track in or out of processing. Its settings are kept as they
+    /// are, so reactivating it plays it exactly as before.
+    pub async fn set_track_active(&self, track_id: TrackId, active: bool) -> Result<()> {
+        {
+            let mut graph = self.audio_graph.write();
+            if let Some(track) = graph.get_track_mut(track_id) {
+                track.active = active;
+            }
+        }
+        self.real_time_audio
+            .send_command(AudioCommand::SetTrackActive { track_id, active })
+    }
+
     pub async fn set_track_solo(&self, track_id: TrackId, solo: bool) -> Result<()> {
         {
             let mut graph = self.audio_graph.write();
@@ -157,7 +235,39 @@ impl AudioService {
             .send_command(AudioCommand::SetTrackSolo { track_id, solo })
     }
 
+    pub async fn set_track_record_arm(&self, track_id: TrackId, armed: bool) -> Result<()> {
+        {
+            let mut graph = self.audio_graph.write();
+            if let Some(track) = graph.get_track_mut(track_id) {
+                track.record_armed = armed;
+            }
+        }
+        self.real_time_audio
+            .send_command(AudioCommand::SetTrackRecordArm { track_id, armed })
+    }
+
+    /// Set how the track's input is monitored. Switching happens in the audio
+    /// graph's input path so Auto follows arm state and punch points.
+    pub async fn set_track_monitor(&self, track_id: TrackId, mode: MonitorMode) -> Result<()> {
+        {
+            let mut graph = self.audio_graph.write();
+            if let Some(track) = graph.get_track_mut(track_id) {
+                track.monitor = mode;
+            }
+        }
+        self.real_time_audio
+            .send_command(AudioCommand::SetTrackMonitor { track_id, mode })
+    }
+
+    /// Choose the input channels a track records and monitors
+    pub async fn set_track_record_input(&self, track_id: TrackId, input: RecordInput) {
+        if let Some(track) = self.audio_graph.write().get_track_mut(track_id) {
+            track.record_input = input;
+        }
+    }
+
     pub async fn play(&self) -> Result<()> {
+        self.preroll(self.real_time_audio.get_position()).await;
         self.real_time_audio.send_command(AudioCommand::Play)
     }
 
@@ -170,9 +280,25 @@ impl AudioService {
     }
 
     pub async fn seek(&self, position: SampleTime) -> Result<()> {
+        self.preroll(position).await;
         self.real_time_audio.send_command(AudioCommand::Seek(position))
     }
 
+    /// Load the files of every clip in the first buffer played from
+    /// `position` and the one after it, so they start on their frame
+    /// instead of once their file has loaded
+    async fn preroll(&self, position: SampleTime) {
+        let paths = self.audio_graph.read().uncached_assets(position, PREROLL_FRAMES);
+        if paths.is_empty() {
+            return;
+        }
+        for (path, result) in paths.iter().zip(self.asset_manager.preload_assets(paths.clone()).await) {
+            if let Err(e) = result {
+                tracing::error!("❌ Failed to pre-roll {:?}: {}", path, e);
+            }
+        }
+    }
+
     pub async fn set_loop(&self, enabled: bool, start: SampleTime, end: SampleTime) -> Result<()> {
         self.real_time_audio.send_command(AudioCommand::SetLoop {
             enabled,
@@ -181,10 +307,26 @@ impl AudioService {
         })
     }
 
+    /// Length of the crossfade where the loop wraps, in milliseconds
+    pub async fn set_loop_crossfade(&self, ms: f32) -> Result<()> {
+        self.real_time_audio.send_command(AudioCommand::SetLoopCrossfade(ms))
+    }
+
+    /// What playback does at the project end, and the project length marker
+    /// it stops at. The clock checks it every callback, against the clips and
+    /// automation as they are then.
+    pub async fn set_playback_end(&self, mode: PlaybackEnd, project_length: Option<SampleTime>) -> Result<()> {
+        self.real_time_audio.send_command(AudioCommand::SetPlaybackEnd { mode, project_length })
+    }
+
     pub async fn set_tempo(&self, tempo: f32) -> Result<()> {
         self.real_time_audio.send_command(AudioCommand::SetTempo(tempo))
     }
 
+    pub async fn set_time_signature(&self, numerator: u32, denominator: u32) -> Result<()> {
+        self.real_time_audio.send_command(AudioCommand::SetTimeSignature { numerator, denominator })
+    }
+
     pub async fn set_metronome(&self, enabled: bool) -> Result<()> {
         self.real_time_audio
             .send_command(AudioCommand::SetMetronome(enabled))
@@ -209,6 +351,43 @@ impl AudioService {
         self.asset_manager.load_asset(path).await
     }
 
+    /// Resample every loaded buffer to a new project rate. Returns the assets
+    /// that failed to reload.
+    pub async fn set_asset_sample_rate(&self, rate: f32) -> Vec<(std::path::PathBuf, anyhow::Error)> {
+        self.asset_manager.set_target_rate(rate).await
+    }
+
+    /// Start recording a take of each target track's input, captured from
+    /// the next block the transport plays. A take already recording is
+    /// finished first and discarded.
+    pub async fn start_take(&self, targets: Vec<TakeTarget>) -> Result<()> {
+        let mut take = self.take.lock();
+        if let Some(previous) = take.take() {
+            previous.finish()?;
+        }
+        *take = Some(TakeWriter::start(self.real_time_audio.take_capture(), targets, self.sample_rate())?);
+        Ok(())
+    }
+
+    /// Stop the take and close its files, None if none was recording
+    pub async fn finish_take(&self) -> Option<Result<RecordedTake>> {
+        let writer = self.take.lock().take()?;
+        Some(writer.finish())
+    }
+
+    /// Run the device at the project rate, so sample positions play back in
+    /// real time. Fails, leaving the device as it was, if it can't run at it.
+    pub async fn set_sample_rate(&self, rate: f32) -> Result<()> {
+        self.real_time_audio.set_sample_rate(rate.round() as u32)
+    }
+
+    /// Turn the clipping scan of loaded files on or off, see
+    /// [`EditPreferences::scan_clipping`](super::preferences::EditPreferences::scan_clipping).
+    /// Returns the assets that failed to reload.
+    pub async fn set_scan_clipping(&self, scan: bool) -> Vec<(std::path::PathBuf, anyhow::Error)> {
+        self.asset_manager.set_scan_clipping(scan).await
+    }
+
     pub async fn add_clip_to_track(
         &self,
         track_id: TrackId,
@@ -277,6 +456,30 @@ impl AudioService {
         graph.get_master_meter()
     }
 
+    /// Input level of an armed or monitoring track, read whether or not the
+    /// transport is running
+    pub async fn get_input_meter(&self, track_id: TrackId) -> Option<InputMeter> {
+        let graph = self.audio_graph.read();
+        graph.get_input_meter(track_id)
+    }
+
+    /// Turn off the input clip light of one track, or of all with `None`
+    pub async fn clear_input_clip(&self, track_id: Option<TrackId>) {
+        let mut graph = self.audio_graph.write();
+        graph.clear_input_clip(track_id);
+    }
+
+    /// Time every track and effect per buffer, see [`DspProfile`](super::profiling::DspProfile)
+    pub async fn set_dsp_profiling(&self, enabled: bool) {
+        let mut graph = self.audio_graph.write();
+        graph.set_profiling(enabled);
+    }
+
+    pub async fn get_dsp_profile(&self) -> DspProfile {
+        let graph = self.audio_graph.read();
+        graph.get_dsp_profile()
+    }
+
     pub async fn get_performance_metrics(&self) -> PerformanceMetrics {
         *self.performance_metrics.read().await
     }
@@ -306,6 +509,14 @@ impl AudioService {
     pub fn channels(&self) -> u16 {
         self.real_time_audio.channels()
     }
+
+    pub fn input_device_name(&self) -> Option<String> {
+        self.real_time_audio.input_device_name()
+    }
+
+    pub fn input_channels(&self) -> u16 {
+        self.real_time_audio.input_channels()
+    }
 }
 
 #[cfg(test)]
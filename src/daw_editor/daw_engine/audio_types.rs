@@ -0,0 +1,2558 @@
+/// Core audio types and structures for the DAW engine
+use super::automation_recording::AutomationMode;
+use super::effects::Effect;
+use super::loop_crossfade::DEFAULT_LOOP_CROSSFADE_MS;
+use super::quick_filters::{self, QuickFilters, MAX_FILTER_HZ, MIN_FILTER_HZ};
+use super::sample_repair::SampleHealth;
+use super::surround::SurroundPan;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Sample rate in Hz
+pub const SAMPLE_RATE: f32 = 48000.0;
+
+/// Buffer size for real-time audio processing
+pub const BUFFER_SIZE: usize = 512;
+
+/// Maximum number of tracks in a project
+pub const MAX_TRACKS: usize = 128;
+
+/// Maximum number of sends per track
+pub const MAX_SENDS: usize = 8;
+
+/// Audio sample buffer type
+pub type AudioBuffer = Vec<f32>;
+
+/// Stereo audio buffer (L, R)
+pub type StereoBuffer = (AudioBuffer, AudioBuffer);
+
+/// Unique identifier for tracks
+pub type TrackId = uuid::Uuid;
+
+/// Unique identifier for clips
+pub type ClipId = uuid::Uuid;
+
+/// Unique identifier for automation points
+pub type AutomationId = uuid::Uuid;
+
+/// Unique identifier for timeline markers
+pub type MarkerId = uuid::Uuid;
+
+/// Unique identifier for arrangement regions
+pub type RegionId = uuid::Uuid;
+
+/// Time position in samples
+pub type SampleTime = u64;
+
+/// Time position in beats
+pub type BeatTime = f64;
+
+/// Decibel value
+pub type Decibels = f32;
+
+/// Audio asset reference
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AudioAssetRef {
+    pub path: PathBuf,
+    pub sample_rate: f32,
+    pub channels: usize,
+    pub duration_samples: usize,
+    /// Format of the file on disk, before conversion to the project rate
+    #[serde(default)]
+    pub source: SourceFormat,
+    /// Bad samples found and repaired as the file loaded
+    #[serde(default)]
+    pub health: SampleHealth,
+}
+
+/// Format an audio file was stored in, captured when it's loaded
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct SourceFormat {
+    pub sample_rate: u32,
+    /// None for lossy formats, which have no fixed bit depth
+    pub bits_per_sample: Option<u16>,
+    pub float: bool,
+    pub channels: usize,
+}
+
+/// Way a source file differs from the project it's used in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FormatMismatch {
+    /// Resampled to the project rate on load, from this rate
+    Resampled { from: u32 },
+    /// Mono material played on both sides of the stereo mix
+    MonoInStereo,
+}
+
+impl FormatMismatch {
+    /// Short text for a clip badge
+    pub fn badge(&self) -> String {
+        match self {
+            FormatMismatch::Resampled { from } => format!("{:.1}k", *from as f32 / 1000.0),
+            FormatMismatch::MonoInStereo => "MONO".to_string(),
+        }
+    }
+
+    pub fn description(&self, project_rate: f32) -> String {
+        match self {
+            FormatMismatch::Resampled { from } => format!(
+                "Recorded at {} Hz and resampled to the project's {} Hz on load",
+                from, project_rate as u32
+            ),
+            FormatMismatch::MonoInStereo => "Mono file in a stereo project, played equally on both sides".to_string(),
+        }
+    }
+}
+
+impl SourceFormat {
+    /// e.g. "44.1 kHz · 24-bit · mono"
+    pub fn describe(&self) -> String {
+        let depth = match (self.bits_per_sample, self.float) {
+            (Some(bits), true) => format!("{}-bit float", bits),
+            (Some(bits), false) => format!("{}-bit", bits),
+            (None, _) => "compressed".to_string(),
+        };
+        let channels = match self.channels {
+            1 => "mono".to_string(),
+            2 => "stereo".to_string(),
+            n => format!("{} channels", n),
+        };
+        format!("{:.1} kHz · {} · {}", self.sample_rate as f32 / 1000.0, depth, channels)
+    }
+
+    /// Differences from a stereo project at `project_rate` worth flagging.
+    /// Empty for assets loaded before the format was recorded.
+    pub fn mismatches(&self, project_rate: f32) -> Vec<FormatMismatch> {
+        let mut mismatches = Vec::new();
+        if self.sample_rate == 0 {
+            return mismatches;
+        }
+        if self.sample_rate != project_rate.round() as u32 {
+            mismatches.push(FormatMismatch::Resampled { from: self.sample_rate });
+        }
+        if self.channels == 1 {
+            mismatches.push(FormatMismatch::MonoInStereo);
+        }
+        mismatches
+    }
+}
+
+/// Cached audio data
+#[derive(Debug, Clone)]
+pub struct AudioAssetData {
+    pub asset_ref: AudioAssetRef,
+    pub samples: Arc<Vec<f32>>,
+}
+
+/// Track type enumeration
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TrackType {
+    Audio,
+    Aux,
+    /// Return bus fed only by sends from other tracks
+    Return,
+    Master,
+    /// Reference material for A/B comparison. It plays instead of the mix
+    /// rather than in it, skipping the master bus, and is never rendered.
+    Reference,
+    /// Control fader for a group of tracks. It carries no audio: its fader,
+    /// mute and volume automation scale those of the tracks assigned to it.
+    Vca,
+}
+
+impl TrackType {
+    /// Buses receive their signal from sends rather than clips
+    pub fn is_bus(&self) -> bool {
+        matches!(self, TrackType::Aux | TrackType::Return)
+    }
+}
+
+/// Where a track or bus leaves the mixer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum OutputTarget {
+    /// The master bus and its limiter, played on outputs 1/2
+    #[default]
+    Main,
+    /// A hardware output pair, bypassing the master bus. Pair 0 is outputs 1/2,
+    /// pair 1 is outputs 3/4 and so on.
+    Pair(u16),
+}
+
+impl OutputTarget {
+    pub fn label(&self) -> String {
+        match self {
+            OutputTarget::Main => "Main".to_string(),
+            OutputTarget::Pair(pair) => format!("Out {}/{}", pair * 2 + 1, pair * 2 + 2),
+        }
+    }
+
+    /// The next target when cycling through a device with `channels` outputs
+    pub fn next(&self, channels: u16) -> Self {
+        let pairs = channels / 2;
+        match self {
+            OutputTarget::Main if pairs > 0 => OutputTarget::Pair(0),
+            OutputTarget::Pair(pair) if pair + 1 < pairs => OutputTarget::Pair(pair + 1),
+            _ => OutputTarget::Main,
+        }
+    }
+}
+
+/// Input monitoring behaviour for a track
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum MonitorMode {
+    /// Never hear the input
+    #[default]
+    Off,
+    /// Always hear the input, replacing playback
+    In,
+    /// Hear the input while armed and stopped, or while recording inside the punch range
+    Auto,
+}
+
+impl MonitorMode {
+    /// Whether the input should be heard at `time`
+    pub fn monitors_input(&self, armed: bool, transport: &Transport, time: SampleTime) -> bool {
+        match self {
+            MonitorMode::Off => false,
+            MonitorMode::In => true,
+            MonitorMode::Auto => {
+                if !armed {
+                    return false;
+                }
+                match transport.state {
+                    TransportState::Stopped | TransportState::Paused => true,
+                    TransportState::Playing => false,
+                    TransportState::Recording => transport.is_punched_in(time),
+                }
+            }
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            MonitorMode::Off => "Off",
+            MonitorMode::In => "In",
+            MonitorMode::Auto => "Auto",
+        }
+    }
+
+    /// Cycle Off -> In -> Auto
+    pub fn next(&self) -> Self {
+        match self {
+            MonitorMode::Off => MonitorMode::In,
+            MonitorMode::In => MonitorMode::Auto,
+            MonitorMode::Auto => MonitorMode::Off,
+        }
+    }
+}
+
+/// What happens where clips on one track overlap
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum OverlapMode {
+    /// Overlapping clips all play, summed. For sound design.
+    #[default]
+    Layer,
+    /// A clip dropped or moved over others cuts a hole in them, crossfaded
+    /// at the edges, so only it plays there. For dialogue and foley.
+    Replace,
+    /// Clips are kept whole, and only the topmost plays where they
+    /// overlap, crossfading through its fades. The last clip dropped or
+    /// moved goes on top, so takes can be comped by dragging them.
+    Comp,
+}
+
+impl OverlapMode {
+    pub fn label(&self) -> &'static str {
+        match self {
+            OverlapMode::Layer => "Layer",
+            OverlapMode::Replace => "Replace",
+            OverlapMode::Comp => "Comp",
+        }
+    }
+
+    /// Cycle Layer -> Replace -> Comp
+    pub fn next(&self) -> Self {
+        match self {
+            OverlapMode::Layer => OverlapMode::Replace,
+            OverlapMode::Replace => OverlapMode::Comp,
+            OverlapMode::Comp => OverlapMode::Layer,
+        }
+    }
+}
+
+/// Most input device channels passed through to the graph
+pub const MAX_INPUT_CHANNELS: usize = 16;
+
+/// Hardware input a track records and monitors, channels counted from 0
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RecordInput {
+    /// One channel, recorded as a mono clip and heard on both sides
+    Mono(u16),
+    /// A channel and the one after it
+    Stereo(u16),
+}
+
+impl Default for RecordInput {
+    fn default() -> Self {
+        RecordInput::Stereo(0)
+    }
+}
+
+impl RecordInput {
+    pub fn label(&self) -> String {
+        match self {
+            RecordInput::Mono(channel) => format!("In {}", channel + 1),
+            RecordInput::Stereo(channel) => format!("In {}/{}", channel + 1, channel + 2),
+        }
+    }
+
+    /// Whether a device with `channels` inputs has every channel this reads
+    pub fn fits(&self, channels: u16) -> bool {
+        match *self {
+            RecordInput::Mono(channel) => channel < channels,
+            RecordInput::Stereo(channel) => channel + 1 < channels,
+        }
+    }
+
+    /// The input actually read on a device with `channels` inputs. A pair
+    /// whose second channel is missing, as on a mono microphone, reads its
+    /// first channel as mono rather than recording a silent right side.
+    pub fn resolve(&self, channels: u16) -> Self {
+        match *self {
+            RecordInput::Stereo(channel) if channel + 1 >= channels && channel < channels => RecordInput::Mono(channel),
+            input => input,
+        }
+    }
+
+    /// Channels of a take recorded from this input
+    pub fn recorded_channels(&self) -> usize {
+        match self {
+            RecordInput::Mono(_) => 1,
+            RecordInput::Stereo(_) => 2,
+        }
+    }
+
+    /// Inputs a device with `channels` channels offers: each channel on its
+    /// own, then the pairs 1/2, 3/4 and so on
+    pub fn available(channels: u16) -> Vec<RecordInput> {
+        let channels = channels.min(MAX_INPUT_CHANNELS as u16);
+        (0..channels)
+            .map(RecordInput::Mono)
+            .chain((0..channels.saturating_sub(1)).step_by(2).map(RecordInput::Stereo))
+            .collect()
+    }
+
+    /// The next input when cycling through a device with `channels` inputs
+    pub fn next(&self, channels: u16) -> Self {
+        let inputs = Self::available(channels);
+        match inputs.iter().position(|input| input == self) {
+            Some(index) => inputs[(index + 1) % inputs.len()],
+            None => inputs.first().copied().unwrap_or_default(),
+        }
+    }
+
+    /// Frame `index` of this input as (left, right), given one buffer per
+    /// device channel. Mono is sent to both sides.
+    pub fn read(&self, buffers: &[Vec<f32>], index: usize) -> (f32, f32) {
+        let sample = |channel: u16| buffers.get(channel as usize).and_then(|b| b.get(index)).copied().unwrap_or(0.0);
+        match *self {
+            RecordInput::Mono(channel) => {
+                let value = sample(channel);
+                (value, value)
+            }
+            RecordInput::Stereo(channel) => (sample(channel), sample(channel + 1)),
+        }
+    }
+}
+
+/// Shape of the automation segment that starts at a point
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CurveType {
+    Linear,
+    /// Keeps the point's value until the next one, then steps
+    Hold,
+    /// Eased in and out of both points
+    #[serde(alias = "Bezier")]
+    SCurve,
+    /// Changes slowly at first and quickly towards the next point
+    Exponential,
+}
+
+impl CurveType {
+    /// Shapes in the order the editor cycles through them
+    pub const ALL: [CurveType; 4] = [CurveType::Linear, CurveType::Exponential, CurveType::SCurve, CurveType::Hold];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            CurveType::Linear => "Linear",
+            CurveType::Hold => "Step",
+            CurveType::SCurve => "S-Curve",
+            CurveType::Exponential => "Exponential",
+        }
+    }
+
+    /// The shape after this one in [`Self::ALL`], wrapping round
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|c| *c == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    /// How far from the first point's value to the next point's value the
+    /// segment is at position `t` (0..1) across it. `tension` bends the
+    /// position first, as a fade's curvature does, see [`fade_shape`].
+    pub fn shape(self, t: f32, tension: f32) -> f32 {
+        let t = fade_shape(t, tension);
+        match self {
+            CurveType::Linear => t,
+            CurveType::Hold => 0.0,
+            CurveType::SCurve => t * t * (3.0 - 2.0 * t),
+            // 36 dB of rise over the segment, scaled to run 0..1
+            CurveType::Exponential => (64f32.powf(t) - 1.0) / 63.0,
+        }
+    }
+}
+
+/// Automation parameter type
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AutomationParameter {
+    Volume,
+    Pan,
+    Send(usize),
+    EffectParam { effect_index: usize, param_index: usize },
+    /// Wet/dry mix of the effect at this index in the track's chain
+    EffectMix(usize),
+    /// Gates the track on and off, 1.0 mutes. Always stepped.
+    Mute,
+    /// Corner of the track's quick high-pass filter, in Hz
+    HighPass,
+    /// Corner of the track's quick low-pass filter, in Hz
+    LowPass,
+}
+
+impl AutomationParameter {
+    /// Parameters that jump between values instead of being interpolated
+    pub fn is_stepped(self) -> bool {
+        matches!(self, AutomationParameter::Mute)
+    }
+
+    /// Nearest round value to `value`, for snapping breakpoints while
+    /// editing: whole dB for volume (silence below the fader floor), 5%
+    /// steps for pan, on or off for mute and whole percent otherwise
+    pub fn snap_value(self, value: f32) -> f32 {
+        match self {
+            AutomationParameter::Volume => {
+                let (_, floor_db) = FADER_LAW[0];
+                if value < db_to_linear(floor_db - 0.5) {
+                    return 0.0;
+                }
+                db_to_linear((20.0 * value.log10()).round().min(FADER_MAX_DB))
+            }
+            AutomationParameter::Pan => ((value * 20.0).round() / 20.0).clamp(-1.0, 1.0),
+            AutomationParameter::Mute => if value >= 0.5 { 1.0 } else { 0.0 },
+            // Twelfths of an octave
+            AutomationParameter::HighPass | AutomationParameter::LowPass => {
+                let steps = 12.0 * (MAX_FILTER_HZ / MIN_FILTER_HZ).log2();
+                quick_filters::filter_frequency((quick_filters::filter_position(value) * steps).round() / steps)
+            }
+            AutomationParameter::Send(_) | AutomationParameter::EffectParam { .. } | AutomationParameter::EffectMix(_) => {
+                ((value * 100.0).round() / 100.0).clamp(0.0, 1.0)
+            }
+        }
+    }
+
+    /// Height of `value` in a lane, 0 at the bottom and 1 at the top. Volume
+    /// follows the fader law so unity sits where it does on the faders.
+    pub fn lane_position(self, value: f32) -> f32 {
+        match self {
+            AutomationParameter::Volume => fader_position(value),
+            AutomationParameter::Pan => (value + 1.0) / 2.0,
+            AutomationParameter::HighPass | AutomationParameter::LowPass => quick_filters::filter_position(value),
+            _ => value.clamp(0.0, 1.0),
+        }
+    }
+
+    /// Value at a height in a lane, the inverse of [`Self::lane_position`]
+    pub fn lane_value(self, position: f32) -> f32 {
+        let position = position.clamp(0.0, 1.0);
+        match self {
+            AutomationParameter::Volume => fader_volume(position),
+            AutomationParameter::Pan => position * 2.0 - 1.0,
+            AutomationParameter::HighPass | AutomationParameter::LowPass => quick_filters::filter_frequency(position),
+            _ => position,
+        }
+    }
+
+    /// Value as shown in the editor, such as "-6.0 dB" or "L25"
+    pub fn format_value(self, value: f32) -> String {
+        match self {
+            AutomationParameter::Volume if value <= 0.0 => "-inf dB".to_string(),
+            AutomationParameter::Volume => format!("{:+.1} dB", 20.0 * value.log10()),
+            AutomationParameter::Pan if value.abs() < 0.005 => "C".to_string(),
+            AutomationParameter::Pan => format!("{}{:.0}", if value < 0.0 { "L" } else { "R" }, value.abs() * 100.0),
+            AutomationParameter::Mute => if value >= 0.5 { "Muted" } else { "On" }.to_string(),
+            AutomationParameter::HighPass | AutomationParameter::LowPass => quick_filters::format_filter_frequency(value),
+            _ => format!("{:.0}%", value * 100.0),
+        }
+    }
+}
+
+/// Length of the gain ramp at each mute automation switch, so gating a track
+/// doesn't click
+pub const MUTE_RAMP_SAMPLES: SampleTime = 64;
+
+/// Automation point
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AutomationPoint {
+    pub id: AutomationId,
+    pub time: SampleTime,
+    pub value: f32,
+    /// Shape of the segment from this point to the next
+    pub curve_type: CurveType,
+    /// Bend of that segment in -1..1, zero leaving the shape as it is
+    #[serde(default)]
+    pub tension: f32,
+}
+
+/// Automation lane
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AutomationLane {
+    pub parameter: AutomationParameter,
+    pub points: Vec<AutomationPoint>,
+    pub enabled: bool,
+}
+
+impl AutomationLane {
+    pub fn new(parameter: AutomationParameter) -> Self {
+        Self {
+            parameter,
+            points: Vec::new(),
+            enabled: true,
+        }
+    }
+
+    /// Get interpolated value at given time
+    pub fn value_at(&self, time: SampleTime) -> Option<f32> {
+        if !self.enabled {
+            return None;
+        }
+        self.curve_value_at(time)
+    }
+
+    /// Value of the curve at `time` whether or not the lane is enabled
+    pub fn curve_value_at(&self, time: SampleTime) -> Option<f32> {
+        if self.points.is_empty() {
+            return None;
+        }
+
+        let idx = self.points.binary_search_by(|p| p.time.cmp(&time));
+
+        match idx {
+            Ok(i) => Some(self.points[i].value),
+            Err(0) => Some(self.points[0].value),
+            Err(i) if i >= self.points.len() => Some(self.points.last().unwrap().value),
+            Err(i) => Some(self.segment_value(&self.points[i - 1], &self.points[i], time)),
+        }
+    }
+
+    /// Value at `time` between the breakpoints `p1` and `p2` around it
+    fn segment_value(&self, p1: &AutomationPoint, p2: &AutomationPoint, time: SampleTime) -> f32 {
+        if self.parameter.is_stepped() {
+            return p1.value;
+        }
+        if p1.curve_type == CurveType::Hold {
+            return p1.value;
+        }
+        let t = (time - p1.time) as f32 / (p2.time - p1.time) as f32;
+        p1.value + (p2.value - p1.value) * p1.curve_type.shape(t, p1.tension)
+    }
+
+    /// Values for the samples from `start` on, one per slot of `out`, as
+    /// `value_at` gives them but walking the breakpoints once, so a block
+    /// follows every breakpoint on its own sample. Returns false and leaves
+    /// `out` alone for a disabled or empty lane.
+    pub fn fill(&self, start: SampleTime, out: &mut [f32]) -> bool {
+        if !self.enabled || self.points.is_empty() {
+            return false;
+        }
+        let points = &self.points;
+        // Index of the first point after the sample being filled
+        let mut next = points.partition_point(|p| p.time <= start);
+        for (time, value) in (start..).zip(out.iter_mut()) {
+            while next < points.len() && points[next].time <= time {
+                next += 1;
+            }
+            *value = match next {
+                0 => points[0].value,
+                n if n == points.len() => points[n - 1].value,
+                n => self.segment_value(&points[n - 1], &points[n], time),
+            };
+        }
+        true
+    }
+
+    pub fn add_point(&mut self, mut point: AutomationPoint) {
+        if self.parameter.is_stepped() {
+            point.curve_type = CurveType::Hold;
+        }
+        let idx = self.points.binary_search_by(|p| p.time.cmp(&point.time));
+        match idx {
+            Ok(i) => self.points[i] = point,
+            Err(i) => self.points.insert(i, point),
+        }
+    }
+
+    /// Gain of a mute lane at `time`: 0 while muted and 1 otherwise. The
+    /// track plays before the first point and throughout when the lane is
+    /// disabled. Each switch ramps over `ramp` samples from its point onwards.
+    pub fn mute_gain_at(&self, time: SampleTime, ramp: SampleTime) -> f32 {
+        let mut gain = [1.0];
+        self.fill_mute_gain(time, ramp, &mut gain);
+        gain[0]
+    }
+
+    /// Mute gains for the samples from `start` on, as from `mute_gain_at`.
+    /// Returns false and leaves `out` alone for a disabled or empty lane.
+    pub fn fill_mute_gain(&self, start: SampleTime, ramp: SampleTime, out: &mut [f32]) -> bool {
+        if !self.enabled || self.points.is_empty() {
+            return false;
+        }
+        let gain = |point: &AutomationPoint| if point.value >= 0.5 { 0.0 } else { 1.0 };
+        let points = &self.points;
+        let mut next = points.partition_point(|p| p.time <= start);
+        for (time, value) in (start..).zip(out.iter_mut()) {
+            while next < points.len() && points[next].time <= time {
+                next += 1;
+            }
+            *value = match next {
+                0 => 1.0,
+                n => {
+                    let target = gain(&points[n - 1]);
+                    let previous = if n >= 2 { gain(&points[n - 2]) } else { 1.0 };
+                    let elapsed = time - points[n - 1].time;
+                    if previous == target || elapsed >= ramp {
+                        target
+                    } else {
+                        previous + (target - previous) * elapsed as f32 / ramp as f32
+                    }
+                }
+            };
+        }
+        true
+    }
+
+    pub fn remove_point(&mut self, id: AutomationId) {
+        self.points.retain(|p| p.id != id);
+    }
+
+    /// Open a gap of `length` samples at `at`. Points from `at` on move later
+    /// and the value at `at` is held across the gap.
+    pub fn insert_time(&mut self, at: SampleTime, length: SampleTime) {
+        let Some(first_moved) = self.points.iter().position(|p| p.time >= at) else {
+            return;
+        };
+        let held = self.curve_value_at(at);
+        let segment_curve = first_moved.checked_sub(1).map(|i| (self.points[i].curve_type, self.points[i].tension));
+
+        for point in &mut self.points[first_moved..] {
+            point.time += length;
+        }
+
+        // Only needed when the gap splits a segment between two points
+        if let (Some(value), Some((curve_type, tension))) = (held, segment_curve) {
+            self.add_point(AutomationPoint {
+                id: uuid::Uuid::new_v4(),
+                time: at,
+                value,
+                curve_type: CurveType::Hold,
+                tension: 0.0,
+            });
+            if self.points.iter().all(|p| p.time != at + length) {
+                self.add_point(AutomationPoint {
+                    id: uuid::Uuid::new_v4(),
+                    time: at + length,
+                    value,
+                    curve_type,
+                    tension,
+                });
+            }
+        }
+    }
+
+    /// Drop every point after `end`, leaving one at `end` with the value the
+    /// curve had there. Returns whether anything changed.
+    pub fn truncate_after(&mut self, end: SampleTime) -> bool {
+        if !self.points.iter().any(|p| p.time > end) {
+            return false;
+        }
+        let value = self.curve_value_at(end);
+        let (curve_type, tension) = self.points.iter().rev()
+            .find(|p| p.time <= end)
+            .map_or((CurveType::Linear, 0.0), |p| (p.curve_type, p.tension));
+        self.points.retain(|p| p.time <= end);
+
+        if let Some(value) = value {
+            if !self.points.iter().any(|p| p.time == end) {
+                self.add_point(AutomationPoint {
+                    id: uuid::Uuid::new_v4(),
+                    time: end,
+                    value,
+                    curve_type,
+                    tension,
+                });
+            }
+        }
+        true
+    }
+}
+
+/// Audio clip on timeline
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AudioClip {
+    pub id: ClipId,
+    pub name: String,
+    pub asset_path: PathBuf,
+    pub start_time: SampleTime,
+    pub duration: SampleTime,
+    pub offset: SampleTime,
+    pub fade_in: SampleTime,
+    pub fade_out: SampleTime,
+    /// Fade-in curvature, see [`fade_shape`]
+    #[serde(default)]
+    pub fade_in_curve: f32,
+    /// Fade-out curvature, see [`fade_shape`]
+    #[serde(default)]
+    pub fade_out_curve: f32,
+    /// Curve family of the fade-in, bent by its curvature
+    #[serde(default)]
+    pub fade_in_shape: FadeShape,
+    /// Curve family of the fade-out, bent by its curvature
+    #[serde(default)]
+    pub fade_out_shape: FadeShape,
+    pub gain: f32,
+    /// Muted clips stay on the timeline but are left out of the mix
+    #[serde(default)]
+    pub muted: bool,
+    /// Loop region inside the source audio, independent of the transport loop
+    #[serde(default)]
+    pub loop_points: Option<ClipLoop>,
+    /// How the clip follows project tempo changes. None uses the default for
+    /// the clip, see [`AudioClip::effective_tempo_mode`]
+    #[serde(default)]
+    pub tempo_mode: Option<TempoMode>,
+    /// Timeline samples per source sample. Above 1.0 the source plays slower
+    /// than recorded, either repitched or stretched depending on the tempo mode
+    #[serde(default = "default_time_ratio")]
+    pub time_ratio: f64,
+    /// Channel conversion applied on playback, leaving the file untouched
+    #[serde(default)]
+    pub channel_mode: ChannelMode,
+    /// Locked clips still play but can't be moved, trimmed or deleted.
+    /// Timeline-wide edits such as inserting silence still move them along
+    /// with everything else.
+    #[serde(default)]
+    pub locked: bool,
+}
+
+fn default_active() -> bool {
+    true
+}
+
+fn default_time_ratio() -> f64 {
+    1.0
+}
+
+/// What a clip does when the project tempo changes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TempoMode {
+    /// Stays at its position in time and plays unchanged
+    Fixed,
+    /// Stays on its beats and changes speed and pitch together, like tape
+    Repitch,
+    /// Stays on its beats and changes speed while keeping its pitch
+    Stretch,
+}
+
+impl TempoMode {
+    pub const ALL: [TempoMode; 3] = [TempoMode::Fixed, TempoMode::Repitch, TempoMode::Stretch];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            TempoMode::Fixed => "Fixed",
+            TempoMode::Repitch => "Repitch",
+            TempoMode::Stretch => "Stretch",
+        }
+    }
+
+    /// Whether clips in this mode keep their musical position
+    pub fn follows_tempo(self) -> bool {
+        self != TempoMode::Fixed
+    }
+}
+
+/// How a clip's source channels are laid out on the track's stereo bus
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ChannelMode {
+    /// Stereo files as they are, mono files on both sides
+    #[default]
+    AsRecorded,
+    /// A mono file duplicated to both channels as a stereo clip
+    Stereo,
+    /// Both channels summed at -3 dB, on both sides
+    MonoSum,
+    /// Only the left channel, on both sides
+    MonoLeft,
+    /// Only the right channel, on both sides
+    MonoRight,
+}
+
+impl ChannelMode {
+    pub const ALL: [ChannelMode; 5] = [
+        ChannelMode::AsRecorded,
+        ChannelMode::Stereo,
+        ChannelMode::MonoSum,
+        ChannelMode::MonoLeft,
+        ChannelMode::MonoRight,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ChannelMode::AsRecorded => "As Recorded",
+            ChannelMode::Stereo => "Stereo",
+            ChannelMode::MonoSum => "Mono (L+R -3dB)",
+            ChannelMode::MonoLeft => "Mono (Left)",
+            ChannelMode::MonoRight => "Mono (Right)",
+        }
+    }
+
+    /// Whether a clip with `source_channels` channels plays as mono
+    pub fn is_mono(self, source_channels: usize) -> bool {
+        match self {
+            ChannelMode::AsRecorded => source_channels < 2,
+            ChannelMode::Stereo => false,
+            ChannelMode::MonoSum | ChannelMode::MonoLeft | ChannelMode::MonoRight => true,
+        }
+    }
+
+    /// Convert one frame read from a source with `source_channels` channels.
+    /// Mono sources are already on both sides, so only the stereo conversions
+    /// to mono change anything.
+    pub fn apply(self, left: f32, right: f32, source_channels: usize) -> (f32, f32) {
+        if source_channels < 2 {
+            return (left, right);
+        }
+        match self {
+            ChannelMode::AsRecorded | ChannelMode::Stereo => (left, right),
+            ChannelMode::MonoSum => {
+                let mono = (left + right) * std::f32::consts::FRAC_1_SQRT_2;
+                (mono, mono)
+            }
+            ChannelMode::MonoLeft => (left, left),
+            ChannelMode::MonoRight => (right, right),
+        }
+    }
+}
+
+/// Loop start/end in source samples (not timeline samples)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClipLoop {
+    pub start: SampleTime,
+    pub end: SampleTime,
+}
+
+impl AudioClip {
+    pub fn new(asset_path: PathBuf, start_time: SampleTime, duration: SampleTime) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4(),
+            name: asset_path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .unwrap_or("Clip")
+                .to_string(),
+            asset_path,
+            start_time,
+            duration,
+            offset: 0,
+            fade_in: 0,
+            fade_out: 0,
+            fade_in_curve: 0.0,
+            fade_out_curve: 0.0,
+            fade_in_shape: FadeShape::default(),
+            fade_out_shape: FadeShape::default(),
+            gain: 1.0,
+            muted: false,
+            loop_points: None,
+            tempo_mode: None,
+            time_ratio: 1.0,
+            channel_mode: ChannelMode::default(),
+            locked: false,
+        }
+    }
+
+    /// The name shown on the clip and used for exports: its own name, or the
+    /// source file's when that's blank
+    pub fn display_name(&self) -> String {
+        if self.name.trim().is_empty() {
+            self.asset_path
+                .file_stem()
+                .map(|s| s.to_string_lossy().into_owned())
+                .unwrap_or_else(|| "clip".to_string())
+        } else {
+            self.name.clone()
+        }
+    }
+
+    /// The tempo mode in effect: the clip's own choice, otherwise Stretch for
+    /// loops and Fixed for one-shots
+    pub fn effective_tempo_mode(&self) -> TempoMode {
+        self.tempo_mode.unwrap_or(if self.loop_points.is_some() {
+            TempoMode::Stretch
+        } else {
+            TempoMode::Fixed
+        })
+    }
+
+    /// Length of the source audio the clip plays, in source frames
+    pub fn source_duration(&self) -> SampleTime {
+        self.timeline_to_source(self.duration)
+    }
+
+    /// Convert a length on the timeline into source frames
+    pub fn timeline_to_source(&self, samples: SampleTime) -> SampleTime {
+        if self.time_ratio > 0.0 {
+            (samples as f64 / self.time_ratio).round() as SampleTime
+        } else {
+            samples
+        }
+    }
+
+    /// Convert a length in source frames into samples on the timeline
+    pub fn source_to_timeline(&self, frames: SampleTime) -> SampleTime {
+        if self.time_ratio > 0.0 {
+            (frames as f64 * self.time_ratio).round() as SampleTime
+        } else {
+            frames
+        }
+    }
+
+    /// Follow a project tempo change from `old_tempo` to `new_tempo`. Clips
+    /// that follow tempo keep their beats, which scales their position,
+    /// length, fades and time ratio; Fixed clips are left alone.
+    pub fn follow_tempo_change(&mut self, old_tempo: f32, new_tempo: f32) {
+        if !self.effective_tempo_mode().follows_tempo() || old_tempo <= 0.0 || new_tempo <= 0.0 {
+            return;
+        }
+        let scale = old_tempo as f64 / new_tempo as f64;
+        let rescale = |samples: SampleTime| (samples as f64 * scale).round() as SampleTime;
+        self.start_time = rescale(self.start_time);
+        self.duration = rescale(self.duration).max(1);
+        self.fade_in = rescale(self.fade_in);
+        self.fade_out = rescale(self.fade_out);
+        self.time_ratio *= scale;
+    }
+
+    /// Audio the clip repeats, or all of it, in source frames. This is what
+    /// a loop's tempo is detected from.
+    pub fn loop_length(&self) -> SampleTime {
+        match self.loop_points {
+            Some(ClipLoop { start, end }) if end > start => end - start,
+            _ => self.source_duration(),
+        }
+    }
+
+    /// Where the clip reads its source for a position in source frames. Past
+    /// the loop end playback wraps back to the loop start, so the loop
+    /// repeats for as long as the clip runs on the timeline.
+    pub fn looped_source_position(&self, position: f64) -> f64 {
+        match self.loop_points {
+            Some(ClipLoop { start, end }) if end > start && position >= end as f64 => {
+                start as f64 + (position - start as f64) % (end - start) as f64
+            }
+            _ => position,
+        }
+    }
+
+    /// Timeline start and end of the loop's first pass, if the clip has a
+    /// loop and reaches it
+    pub fn loop_timeline_range(&self) -> Option<(SampleTime, SampleTime)> {
+        let loop_points = self.loop_points.filter(|l| l.end > l.start && l.start >= self.offset)?;
+        let start = self.start_time + self.source_to_timeline(loop_points.start - self.offset);
+        let end = self.start_time + self.source_to_timeline(loop_points.end - self.offset);
+        (start < self.end_time()).then_some((start, end))
+    }
+
+    /// Stretch the clip so audio recorded at `source_tempo` plays in time
+    /// with `project_tempo`, keeping its pitch. The clip keeps the same
+    /// stretch of source audio and follows later tempo changes.
+    pub fn fit_to_tempo(&mut self, source_tempo: f32, project_tempo: f32) {
+        if source_tempo <= 0.0 || project_tempo <= 0.0 {
+            return;
+        }
+        let ratio = source_tempo as f64 / project_tempo as f64;
+        let scale = if self.time_ratio > 0.0 { ratio / self.time_ratio } else { ratio };
+        let rescale = |samples: SampleTime| (samples as f64 * scale).round() as SampleTime;
+        self.duration = rescale(self.duration).max(1);
+        self.fade_in = rescale(self.fade_in);
+        self.fade_out = rescale(self.fade_out);
+        self.time_ratio = ratio;
+        if !self.effective_tempo_mode().follows_tempo() {
+            self.tempo_mode = Some(TempoMode::Stretch);
+        }
+    }
+
+    /// Check the clip's source range against the length of its audio buffer
+    /// (in frames). The visible region and the loop points must both lie
+    /// within the buffer, and the loop can't start before the clip does. A
+    /// looped clip never reads past its loop end, so it may run longer than
+    /// the audio after its offset.
+    pub fn validate_source_range(&self, source_len: SampleTime) -> anyhow::Result<()> {
+        if self.duration == 0 {
+            return Err(anyhow::anyhow!("Clip length must be greater than zero"));
+        }
+        if self.offset >= source_len {
+            return Err(anyhow::anyhow!(
+                "Source offset {} is beyond the end of the audio ({} samples)",
+                self.offset,
+                source_len
+            ));
+        }
+        if self.loop_points.is_none() && self.offset + self.source_duration() > source_len {
+            return Err(anyhow::anyhow!(
+                "Offset + length ({} samples) exceeds the audio length ({} samples)",
+                self.offset + self.source_duration(),
+                source_len
+            ));
+        }
+        if let Some(loop_points) = self.loop_points {
+            if loop_points.start >= loop_points.end {
+                return Err(anyhow::anyhow!("Loop start must be before loop end"));
+            }
+            if loop_points.start < self.offset {
+                return Err(anyhow::anyhow!(
+                    "Loop start {} is before the clip's source offset ({})",
+                    loop_points.start,
+                    self.offset
+                ));
+            }
+            if loop_points.end > source_len {
+                return Err(anyhow::anyhow!(
+                    "Loop end {} exceeds the audio length ({} samples)",
+                    loop_points.end,
+                    source_len
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn end_time(&self) -> SampleTime {
+        self.start_time + self.duration
+    }
+
+    /// Move every sample position of the clip (position, length, source
+    /// offset, fades, loop) from `from_rate` to `to_rate`, so it keeps its
+    /// place in time
+    pub fn rescale_timing(&mut self, from_rate: f32, to_rate: f32) {
+        let rescale = |samples: SampleTime| rescale_samples(samples, from_rate, to_rate);
+        self.start_time = rescale(self.start_time);
+        self.duration = rescale(self.duration).max(1);
+        self.offset = rescale(self.offset);
+        self.fade_in = rescale(self.fade_in);
+        self.fade_out = rescale(self.fade_out);
+        if let Some(ref mut loop_points) = self.loop_points {
+            loop_points.start = rescale(loop_points.start);
+            loop_points.end = rescale(loop_points.end).max(loop_points.start + 1);
+        }
+    }
+
+    /// Split at a timeline position strictly inside the clip. The left part
+    /// keeps this clip's ID and fade-in, the right part gets a new ID, the
+    /// fade-out and a source offset that continues where the left part ends.
+    /// Both sides of the cut get a `cut_fade` long fade.
+    pub fn split_at(&self, time: SampleTime, cut_fade: SampleTime) -> Option<(AudioClip, AudioClip)> {
+        if time <= self.start_time || time >= self.end_time() {
+            return None;
+        }
+        let left_len = time - self.start_time;
+
+        let mut left = self.clone();
+        left.duration = left_len;
+        left.fade_in = left.fade_in.min(left_len);
+        left.fade_out = cut_fade.min(left_len - left.fade_in);
+
+        let mut right = self.clone();
+        right.id = uuid::Uuid::new_v4();
+        right.start_time = time;
+        right.duration = self.duration - left_len;
+        right.offset = self.offset + self.timeline_to_source(left_len);
+        right.fade_out = right.fade_out.min(right.duration);
+        right.fade_in = cut_fade.min(right.duration - right.fade_out);
+
+        Some((left, right))
+    }
+
+    /// The clip playing source frames `start..end` instead, trimmed or
+    /// extended at either edge. The audio stays where it is on the timeline
+    /// and so do the fade boundaries, shortened if the clip got shorter.
+    /// Start is limited so the clip doesn't move before the timeline start.
+    /// None if the range is empty.
+    pub fn with_source_range(&self, start: SampleTime, end: SampleTime) -> Option<AudioClip> {
+        let to_timeline = |frames: SampleTime| self.source_to_timeline(frames) as i64;
+        // Frame of the source heard at timeline position 0
+        let earliest = self.offset.saturating_sub(self.timeline_to_source(self.start_time));
+        let start = start.max(earliest);
+        if start >= end {
+            return None;
+        }
+
+        let start_time = (self.start_time as i64 + to_timeline(start) - to_timeline(self.offset)).max(0) as SampleTime;
+        let duration = to_timeline(end - start).max(1) as SampleTime;
+        let mut clip = self.clone();
+        clip.start_time = start_time;
+        clip.duration = duration;
+        clip.offset = start;
+        let fade_in_end = self.start_time + self.fade_in;
+        let fade_out_start = self.end_time().saturating_sub(self.fade_out);
+        clip.fade_in = fade_in_end.saturating_sub(start_time).min(duration);
+        clip.fade_out = clip.end_time().saturating_sub(fade_out_start).min(duration - clip.fade_in);
+        Some(clip)
+    }
+
+    /// The clip with one edge dragged to `time` on the timeline. Trimming
+    /// only moves the edge through the source, so audio trimmed off earlier
+    /// comes back when the edge is dragged out again. Edges stop at the
+    /// ends of the source (`source_len` frames) and a frame short of each
+    /// other; a looped clip's start stops at the loop start, while its end
+    /// can run on, repeating the loop.
+    pub fn with_edge_at(&self, is_start: bool, time: SampleTime, source_len: SampleTime) -> Option<AudioClip> {
+        let ratio = if self.time_ratio > 0.0 { self.time_ratio } else { 1.0 };
+        let source_end = self.offset + self.source_duration();
+        // Source frame heard at `time`, before or past the ends of the source
+        let frame = self.offset as i64 + ((time as f64 - self.start_time as f64) / ratio).round() as i64;
+
+        if is_start {
+            let latest = match self.loop_points {
+                Some(loop_points) => loop_points.start.min(source_end.saturating_sub(1)),
+                None => source_end.saturating_sub(1),
+            };
+            self.with_source_range(frame.clamp(0, latest as i64) as SampleTime, source_end)
+        } else {
+            let earliest = self.offset as i64 + 1;
+            let end = match self.loop_points {
+                Some(_) => frame.max(earliest),
+                None => frame.clamp(earliest, (source_len as i64).max(earliest)),
+            };
+            self.with_source_range(self.offset, end as SampleTime)
+        }
+    }
+    
+    /// Convert start time from samples to beats at `sample_rate`
+    pub fn start_beat(&self, tempo: f32, sample_rate: f32) -> BeatTime {
+        Self::samples_to_beats(self.start_time, tempo, sample_rate)
+    }
+
+    /// Set start time from beats at `sample_rate`
+    pub fn set_start_beat(&mut self, beats: BeatTime, tempo: f32, sample_rate: f32) {
+        self.start_time = Self::beats_to_samples(beats, tempo, sample_rate);
+    }
+    
+    /// Convert duration from samples to beats at `sample_rate`
+    pub fn duration_beats(&self, tempo: f32, sample_rate: f32) -> BeatTime {
+        Self::samples_to_beats(self.duration, tempo, sample_rate)
+    }
+    
+    /// Convert samples to beats given tempo
+    fn samples_to_beats(samples: SampleTime, tempo: f32, sample_rate: f32) -> BeatTime {
+        let seconds = samples as f64 / sample_rate as f64;
+        let beats_per_second = tempo as f64 / 60.0;
+        seconds * beats_per_second
+    }
+
+    /// Convert beats to samples given tempo
+    fn beats_to_samples(beats: BeatTime, tempo: f32, sample_rate: f32) -> SampleTime {
+        let beats_per_second = tempo as f64 / 60.0;
+        let seconds = beats / beats_per_second;
+        (seconds * sample_rate as f64) as SampleTime
+    }
+
+    /// Check if clip is active at given time
+    pub fn is_active_at(&self, time: SampleTime) -> bool {
+        !self.muted && time >= self.start_time && time < self.end_time()
+    }
+
+    /// Whether the clip plays anywhere in `from..to`, including clips that
+    /// start and end inside it
+    pub fn overlaps(&self, from: SampleTime, to: SampleTime) -> bool {
+        !self.muted && self.start_time < to && self.end_time() > from
+    }
+
+    /// Get fade coefficient at given time
+    pub fn fade_at(&self, time: SampleTime) -> f32 {
+        self.envelope_at(time) * self.gain
+    }
+
+    /// The fades alone at `time`, 1 between them and 0 outside the clip or
+    /// while it's muted. How much of a comped track the clip covers there.
+    pub fn envelope_at(&self, time: SampleTime) -> f32 {
+        if !self.is_active_at(time) {
+            return 0.0;
+        }
+
+        let relative_time = time - self.start_time;
+        
+        let fade_in_mult = if self.fade_in > 0 && relative_time < self.fade_in {
+            self.fade_in_shape.gain(relative_time as f32 / self.fade_in as f32, self.fade_in_curve)
+        } else {
+            1.0
+        };
+
+        let time_from_end = self.duration.saturating_sub(relative_time);
+        let fade_out_mult = if self.fade_out > 0 && time_from_end < self.fade_out {
+            self.fade_out_shape.gain(time_from_end as f32 / self.fade_out as f32, self.fade_out_curve)
+        } else {
+            1.0
+        };
+
+        fade_in_mult * fade_out_mult
+    }
+}
+
+/// Shape a linear fade position `t` (0..1) into a gain using a curvature
+/// parameter in -1..1. Zero is linear, positive values bend towards an
+/// exponential curve (slow start) and negative values towards a logarithmic
+/// curve (fast start). Used by both the mixer and the timeline drawing so the
+/// displayed fade matches what is heard.
+pub fn fade_shape(t: f32, curve: f32) -> f32 {
+    let t = t.clamp(0.0, 1.0);
+    let curve = curve.clamp(-1.0, 1.0);
+    if curve.abs() < 1e-4 {
+        return t;
+    }
+    t.powf(4.0_f32.powf(curve))
+}
+
+/// Family of curve a clip fade follows. The fade's curvature bends the
+/// position before the curve is applied, so every shape can be skewed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum FadeShape {
+    /// The curvature alone, linear when it is zero
+    #[default]
+    Power,
+    /// Quarter sine, so a fade-out and a fade-in across the same overlap
+    /// keep the sum of their powers constant
+    EqualPower,
+    /// Half cosine, slow at both ends
+    SCurve,
+}
+
+impl FadeShape {
+    pub const ALL: [FadeShape; 3] = [FadeShape::Power, FadeShape::EqualPower, FadeShape::SCurve];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            FadeShape::Power => "Linear",
+            FadeShape::EqualPower => "Equal Power",
+            FadeShape::SCurve => "S-Curve",
+        }
+    }
+
+    /// Gain at fade position `t` (0 silent end, 1 full level)
+    pub fn gain(self, t: f32, curve: f32) -> f32 {
+        let t = fade_shape(t, curve);
+        match self {
+            FadeShape::Power => t,
+            FadeShape::EqualPower => (t * std::f32::consts::FRAC_PI_2).sin(),
+            FadeShape::SCurve => 0.5 - 0.5 * (t * std::f32::consts::PI).cos(),
+        }
+    }
+}
+
+/// Loudest gain a fader reaches at the top of its travel
+pub const FADER_MAX_DB: Decibels = 6.0;
+
+/// Fader law as (travel 0..1, dB) breakpoints, interpolated linearly in dB.
+/// The steps get finer towards unity, which sits at three quarters of the way up.
+const FADER_LAW: [(f32, Decibels); 5] = [(0.05, -60.0), (0.25, -30.0), (0.5, -12.0), (0.75, 0.0), (1.0, FADER_MAX_DB)];
+
+/// Linear gain for a fader `position` (0 bottom, 1 top). Below the lowest
+/// breakpoint the gain drops linearly to silence.
+pub fn fader_volume(position: f32) -> f32 {
+    let position = position.clamp(0.0, 1.0);
+    let (floor_position, floor_db) = FADER_LAW[0];
+    if position < floor_position {
+        return db_to_linear(floor_db) * position / floor_position;
+    }
+    for pair in FADER_LAW.windows(2) {
+        let ((p0, db0), (p1, db1)) = (pair[0], pair[1]);
+        if position <= p1 {
+            return db_to_linear(db0 + (db1 - db0) * (position - p0) / (p1 - p0));
+        }
+    }
+    db_to_linear(FADER_MAX_DB)
+}
+
+/// Fader position (0..1) showing a linear gain, the inverse of [`fader_volume`]
+pub fn fader_position(volume: f32) -> f32 {
+    let (floor_position, floor_db) = FADER_LAW[0];
+    let floor = db_to_linear(floor_db);
+    if volume <= floor {
+        return (volume.max(0.0) / floor * floor_position).min(floor_position);
+    }
+    let db = 20.0 * volume.log10();
+    for pair in FADER_LAW.windows(2) {
+        let ((p0, db0), (p1, db1)) = (pair[0], pair[1]);
+        if db <= db1 {
+            return p0 + (p1 - p0) * (db - db0) / (db1 - db0);
+        }
+    }
+    1.0
+}
+
+/// Parse a typed fader value in dB such as "-6", "+1.5dB" or "-inf"
+pub fn parse_db(text: &str) -> Option<Decibels> {
+    let text = text.trim().trim_end_matches(|c: char| c.eq_ignore_ascii_case(&'b') || c.eq_ignore_ascii_case(&'d')).trim();
+    if text.eq_ignore_ascii_case("-inf") || text.eq_ignore_ascii_case("inf") {
+        return Some(-100.0);
+    }
+    text.parse::<f32>().ok().filter(|db| db.is_finite()).map(|db| db.min(FADER_MAX_DB))
+}
+
+fn db_to_linear(db: Decibels) -> f32 {
+    10.0_f32.powf(db / 20.0)
+}
+
+/// First and last of `frames` frames louder than `threshold_db`, where
+/// `peak_at` gives a frame's loudest channel. None if none of them are.
+pub fn audible_span(frames: usize, threshold_db: f32, peak_at: impl Fn(usize) -> f32) -> Option<(usize, usize)> {
+    let threshold = db_to_linear(threshold_db);
+    let first = (0..frames).find(|&i| peak_at(i) > threshold)?;
+    let last = (first..frames).rev().find(|&i| peak_at(i) > threshold)?;
+    Some((first, last))
+}
+
+/// Send configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Send {
+    pub target_track: Option<TrackId>,
+    pub amount: f32,
+    /// Tap the signal before the track's fader, e.g. for cue mixes
+    pub pre_fader: bool,
+    pub enabled: bool,
+    /// Go silent with the track's mute. Off keeps feeding the bus while the
+    /// track is muted; solos still apply either way.
+    #[serde(default = "default_follow_mute")]
+    pub follow_mute: bool,
+}
+
+fn default_follow_mute() -> bool {
+    true
+}
+
+impl Default for Send {
+    fn default() -> Self {
+        Self {
+            target_track: None,
+            amount: 0.0,
+            pre_fader: false,
+            enabled: false,
+            follow_mute: true,
+        }
+    }
+}
+
+/// Per-channel polarity inversion. A plain `true`/`false` in the project
+/// file applies to both channels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(from = "PhaseInvertRepr")]
+pub struct PhaseInvert {
+    pub left: bool,
+    pub right: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum PhaseInvertRepr {
+    Both(bool),
+    Channels {
+        #[serde(default)]
+        left: bool,
+        #[serde(default)]
+        right: bool,
+    },
+}
+
+impl From<PhaseInvertRepr> for PhaseInvert {
+    fn from(repr: PhaseInvertRepr) -> Self {
+        match repr {
+            PhaseInvertRepr::Both(invert) => Self { left: invert, right: invert },
+            PhaseInvertRepr::Channels { left, right } => Self { left, right },
+        }
+    }
+}
+
+impl PhaseInvert {
+    pub fn is_active(&self) -> bool {
+        self.left || self.right
+    }
+
+    /// Flip the sign of the selected channels in place
+    pub fn apply(&self, left: &mut [f32], right: &mut [f32]) {
+        if self.left {
+            left.iter_mut().for_each(|s| *s = -*s);
+        }
+        if self.right {
+            right.iter_mut().for_each(|s| *s = -*s);
+        }
+    }
+}
+
+/// Track state
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Track {
+    pub id: TrackId,
+    pub name: String,
+    pub track_type: TrackType,
+    pub clips: Vec<AudioClip>,
+    pub volume: f32,
+    pub pan: f32,
+    pub muted: bool,
+    /// Inactive tracks are left out of the graph entirely: they aren't
+    /// processed, feed no sends and can't be soloed, unlike muted tracks
+    /// which are still processed and metered
+    #[serde(default = "default_active")]
+    pub active: bool,
+    pub solo: bool,
+    /// Never silenced by other tracks' solo. On by default for returns, so
+    /// soloed tracks keep their reverbs and delays.
+    #[serde(default)]
+    pub solo_safe: bool,
+    /// Left out of solo entirely: always audible while others are soloed,
+    /// and its own solo doesn't silence anything. For click or guide tracks.
+    #[serde(default)]
+    pub solo_isolate: bool,
+    pub record_armed: bool,
+    /// Input channels the track records and monitors
+    #[serde(default)]
+    pub record_input: RecordInput,
+    #[serde(default)]
+    pub monitor: MonitorMode,
+    /// Polarity inversion, applied right after the track's sources
+    #[serde(default)]
+    pub phase_invert: PhaseInvert,
+    /// High-pass and low-pass after the polarity, before the effects
+    #[serde(default)]
+    pub quick_filters: QuickFilters,
+    /// Output the track's post-fader signal goes to
+    #[serde(default)]
+    pub output: OutputTarget,
+    /// Position in the surround field, used when the project has a surround
+    /// layout. The stereo pan still places the track in the stereo mix.
+    #[serde(default)]
+    pub surround_pan: SurroundPan,
+    /// Manual alignment of all the track's clips in samples, positive plays
+    /// them later and negative earlier. Separate from latency compensation.
+    #[serde(default)]
+    pub track_offset_samples: i64,
+    pub sends: Vec<Send>,
+    /// Insert effects, run in order before the fader
+    #[serde(default)]
+    pub effects: Vec<Effect>,
+    pub automation: Vec<AutomationLane>,
+    /// Whether mapped controllers write automation while the transport rolls
+    #[serde(default)]
+    pub automation_mode: AutomationMode,
+    /// How the track's clips play and are edited where they overlap
+    #[serde(default)]
+    pub overlap_mode: OverlapMode,
+    /// Locks every clip on the track, and the track against deletion
+    #[serde(default)]
+    pub locked: bool,
+    /// VCA whose fader rides this track, on top of its own fader and
+    /// automation
+    #[serde(default)]
+    pub vca: Option<TrackId>,
+    pub color: [f32; 3],
+}
+
+impl Track {
+    pub fn new(name: impl Into<String>, track_type: TrackType) -> Self {
+        let mut sends = Vec::new();
+        for _ in 0..MAX_SENDS {
+            sends.push(Send::default());
+        }
+
+        Self {
+            id: uuid::Uuid::new_v4(),
+            name: name.into(),
+            track_type,
+            clips: Vec::new(),
+            volume: 1.0,
+            pan: 0.0,
+            muted: false,
+            active: true,
+            solo: false,
+            solo_safe: track_type == TrackType::Return,
+            solo_isolate: false,
+            record_armed: false,
+            record_input: RecordInput::default(),
+            monitor: MonitorMode::Off,
+            phase_invert: PhaseInvert::default(),
+            quick_filters: QuickFilters::default(),
+            output: OutputTarget::Main,
+            surround_pan: SurroundPan::default(),
+            track_offset_samples: 0,
+            sends,
+            effects: Vec::new(),
+            automation: Vec::new(),
+            automation_mode: AutomationMode::default(),
+            overlap_mode: OverlapMode::default(),
+            locked: false,
+            vca: None,
+            color: [0.5, 0.5, 0.5],
+        }
+    }
+
+    /// Whether any send feeds a bus while the track is muted
+    pub fn sends_ignoring_mute(&self) -> bool {
+        self.sends.iter().any(|s| s.enabled && !s.follow_mute && s.target_track.is_some())
+    }
+
+    /// Whether `clip` is locked, by itself or with the whole track
+    pub fn clip_locked(&self, clip: &AudioClip) -> bool {
+        self.locked || clip.locked
+    }
+
+    /// Whether this track's solo silences tracks that aren't soloed.
+    /// References are outside the mix, so they never do.
+    pub fn solo_active(&self) -> bool {
+        self.active && self.solo && !self.solo_isolate && self.track_type != TrackType::Reference
+    }
+
+    /// Whether the track is silenced by solo, given whether any track's
+    /// solo is active
+    pub fn solo_muted(&self, any_solo: bool) -> bool {
+        any_solo && !(self.solo || self.solo_safe || self.solo_isolate)
+    }
+
+    pub fn volume_db(&self) -> Decibels {
+        if self.volume <= 0.0 {
+            -100.0
+        } else {
+            20.0 * self.volume.log10()
+        }
+    }
+
+    pub fn set_volume_db(&mut self, db: Decibels) {
+        self.volume = if db <= -100.0 {
+            0.0
+        } else {
+            10.0_f32.powf(db / 20.0)
+        };
+    }
+
+    pub fn get_automation_lane(&self, param: AutomationParameter) -> Option<&AutomationLane> {
+        self.automation.iter().find(|lane| lane.parameter == param)
+    }
+
+    pub fn get_automation_lane_mut(&mut self, param: AutomationParameter) -> &mut AutomationLane {
+        if let Some(idx) = self.automation.iter().position(|lane| lane.parameter == param) {
+            &mut self.automation[idx]
+        } else {
+            self.automation.push(AutomationLane::new(param));
+            self.automation.last_mut().unwrap()
+        }
+    }
+}
+
+/// Transport state
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TransportState {
+    Stopped,
+    Playing,
+    Paused,
+    Recording,
+}
+
+/// A named position on the timeline, such as the start of a cue
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Marker {
+    pub id: MarkerId,
+    pub name: String,
+    pub position: SampleTime,
+}
+
+impl Marker {
+    pub fn new(name: impl Into<String>, position: SampleTime) -> Self {
+        Self { id: uuid::Uuid::new_v4(), name: name.into(), position }
+    }
+}
+
+/// A named span of the timeline describing a section of the arrangement,
+/// such as an intro or a boss fight, independent of the clips under it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Region {
+    pub id: RegionId,
+    pub name: String,
+    pub start: SampleTime,
+    pub end: SampleTime,
+    /// Slot in the track palette the region is drawn with
+    #[serde(default)]
+    pub color: usize,
+}
+
+impl Region {
+    pub fn new(name: impl Into<String>, start: SampleTime, end: SampleTime, color: usize) -> Self {
+        Self { id: uuid::Uuid::new_v4(), name: name.into(), start, end: end.max(start + 1), color }
+    }
+
+    /// Move the region to start at `start`, keeping its length
+    pub fn move_to(&mut self, start: SampleTime) {
+        let length = self.end - self.start;
+        self.start = start;
+        self.end = start + length;
+    }
+}
+
+/// Bar counts tried when guessing a loop's tempo from its length
+pub const LOOP_BAR_COUNTS: [u32; 6] = [1, 2, 4, 8, 16, 32];
+
+/// Tempos a guessed loop tempo may fall in
+const LOOP_TEMPO_RANGE: std::ops::RangeInclusive<f32> = 60.0..=200.0;
+
+/// What playback does when the playhead reaches the end of the project.
+/// An enabled loop takes precedence and wraps instead, and recording always
+/// carries on so a take can run past the last clip.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum PlaybackEnd {
+    /// Stop at the project length marker, or at the end of the last clip
+    /// when no marker is set
+    StopAtProjectEnd,
+    /// Stop after the last clip or automation point, ignoring the marker
+    StopAtLastEvent,
+    /// Keep playing silence, for live and session use
+    #[default]
+    Continue,
+}
+
+impl PlaybackEnd {
+    pub const ALL: [PlaybackEnd; 3] = [PlaybackEnd::StopAtProjectEnd, PlaybackEnd::StopAtLastEvent, PlaybackEnd::Continue];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            PlaybackEnd::StopAtProjectEnd => "Stop at End",
+            PlaybackEnd::StopAtLastEvent => "Stop at Last",
+            PlaybackEnd::Continue => "Continue",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|mode| *mode == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+}
+
+/// Transport configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Transport {
+    pub state: TransportState,
+    pub position: SampleTime,
+    pub loop_enabled: bool,
+    pub loop_start: SampleTime,
+    pub loop_end: SampleTime,
+    /// Length of the crossfade where the loop wraps
+    #[serde(default = "default_loop_crossfade_ms")]
+    pub loop_crossfade_ms: f32,
+    pub tempo: f32,
+    pub time_signature_numerator: u32,
+    pub time_signature_denominator: u32,
+    pub metronome_enabled: bool,
+    #[serde(default)]
+    pub punch_enabled: bool,
+    #[serde(default)]
+    pub punch_in: SampleTime,
+    #[serde(default)]
+    pub punch_out: SampleTime,
+    /// Rate that sample positions are counted in, mirrors the project rate
+    #[serde(default = "default_sample_rate")]
+    pub sample_rate: f32,
+    /// What playback does on reaching the project end
+    #[serde(default)]
+    pub playback_end: PlaybackEnd,
+    /// Project length marker. None ends the project with its last clip.
+    #[serde(default)]
+    pub project_length: Option<SampleTime>,
+}
+
+fn default_sample_rate() -> f32 {
+    SAMPLE_RATE
+}
+
+fn default_loop_crossfade_ms() -> f32 {
+    DEFAULT_LOOP_CROSSFADE_MS
+}
+
+impl Default for Transport {
+    fn default() -> Self {
+        Self {
+            state: TransportState::Stopped,
+            position: 0,
+            loop_enabled: false,
+            loop_start: 0,
+            loop_end: SAMPLE_RATE as u64 * 60,
+            loop_crossfade_ms: DEFAULT_LOOP_CROSSFADE_MS,
+            tempo: 120.0,
+            time_signature_numerator: 4,
+            time_signature_denominator: 4,
+            metronome_enabled: false,
+            punch_enabled: false,
+            punch_in: 0,
+            punch_out: 0,
+            sample_rate: SAMPLE_RATE,
+            playback_end: PlaybackEnd::default(),
+            project_length: None,
+        }
+    }
+}
+
+impl Transport {
+    pub fn play(&mut self) {
+        self.state = TransportState::Playing;
+    }
+
+    pub fn pause(&mut self) {
+        if self.state == TransportState::Playing {
+            self.state = TransportState::Paused;
+        }
+    }
+
+    pub fn stop(&mut self) {
+        self.state = TransportState::Stopped;
+        self.position = 0;
+    }
+
+    pub fn seek(&mut self, position: SampleTime) {
+        self.position = position;
+        if self.loop_enabled {
+            if self.position >= self.loop_end {
+                self.position = self.loop_start;
+            }
+        }
+    }
+
+    /// Move forward, wrapping to the loop start when the loop end is crossed.
+    /// A loop set while the playhead is already past its end takes effect the
+    /// next time playback reaches it rather than jumping immediately.
+    pub fn advance(&mut self, samples: usize) {
+        let before = self.position;
+        self.position += samples as u64;
+        if self.loop_enabled
+            && self.loop_end > self.loop_start
+            && before < self.loop_end
+            && self.position >= self.loop_end
+        {
+            let overshoot = (self.position - self.loop_end) % (self.loop_end - self.loop_start);
+            self.position = self.loop_start + overshoot;
+        }
+    }
+
+    /// Advance like [`Transport::advance`], stopping with the playhead on
+    /// `stop_at` when playback crosses it. Returns whether it stopped.
+    pub fn advance_until(&mut self, samples: usize, stop_at: Option<SampleTime>) -> bool {
+        let before = self.position;
+        self.advance(samples);
+        match stop_at {
+            Some(end) if before < end && self.position >= end => {
+                self.state = TransportState::Stopped;
+                self.position = end;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Where playback stops on its own, given the end of the last clip and of
+    /// the last clip or automation point. None while it carries on: with
+    /// [`PlaybackEnd::Continue`], while recording, or while a loop wraps.
+    pub fn stop_point(&self, last_clip_end: SampleTime, last_event_end: SampleTime) -> Option<SampleTime> {
+        if self.state != TransportState::Playing || (self.loop_enabled && self.loop_end > self.loop_start) {
+            return None;
+        }
+        match self.playback_end {
+            PlaybackEnd::StopAtProjectEnd => Some(self.project_length.unwrap_or(last_clip_end)),
+            PlaybackEnd::StopAtLastEvent => Some(last_event_end),
+            PlaybackEnd::Continue => None,
+        }
+    }
+
+    /// Whether the transport is moving (playing or recording)
+    pub fn is_rolling(&self) -> bool {
+        matches!(self.state, TransportState::Playing | TransportState::Recording)
+    }
+
+    /// Whether recording is active at `time`, honouring the punch range
+    pub fn is_punched_in(&self, time: SampleTime) -> bool {
+        if self.state != TransportState::Recording {
+            return false;
+        }
+        !self.punch_enabled || (time >= self.punch_in && time < self.punch_out)
+    }
+
+    pub fn samples_to_beats(&self, samples: SampleTime) -> BeatTime {
+        let seconds = samples as f64 / self.sample_rate as f64;
+        let beats_per_second = self.tempo as f64 / 60.0;
+        seconds * beats_per_second
+    }
+
+    pub fn beats_to_samples(&self, beats: BeatTime) -> SampleTime {
+        let beats_per_second = self.tempo as f64 / 60.0;
+        let seconds = beats / beats_per_second;
+        (seconds * self.sample_rate as f64) as SampleTime
+    }
+
+    /// Format samples as `bars:beats:ticks`. Positions are 1-based
+    /// (`1:1:000` is the project start), lengths are 0-based.
+    pub fn format_bars_beats(&self, samples: SampleTime, is_position: bool) -> String {
+        let beats_per_bar = self.time_signature_numerator.max(1) as u64;
+        let total_ticks = (self.samples_to_beats(samples) * TICKS_PER_BEAT as f64).round() as u64;
+        let ticks = total_ticks % TICKS_PER_BEAT;
+        let total_beats = total_ticks / TICKS_PER_BEAT;
+        let origin = if is_position { 1 } else { 0 };
+
+        format!(
+            "{}:{}:{:03}",
+            total_beats / beats_per_bar + origin,
+            total_beats % beats_per_bar + origin,
+            ticks
+        )
+    }
+
+    /// Parse `bars:beats[:ticks]` back into samples. Returns None for malformed
+    /// text or values before the project start.
+    pub fn parse_bars_beats(&self, text: &str, is_position: bool) -> Option<SampleTime> {
+        let parts: Vec<&str> = text.trim().split(':').collect();
+        if parts.len() < 2 || parts.len() > 3 {
+            return None;
+        }
+
+        let origin = if is_position { 1 } else { 0 };
+        let bars = parts[0].trim().parse::<i64>().ok()? - origin;
+        let beats = parts[1].trim().parse::<f64>().ok()? - origin as f64;
+        let ticks = match parts.get(2) {
+            Some(t) => t.trim().parse::<i64>().ok()?,
+            None => 0,
+        };
+        if bars < 0 || beats < 0.0 || ticks < 0 {
+            return None;
+        }
+
+        let total_beats = bars as f64 * self.time_signature_numerator.max(1) as f64
+            + beats
+            + ticks as f64 / TICKS_PER_BEAT as f64;
+        Some(self.beats_to_samples(total_beats))
+    }
+
+    /// Tempo at which `frames` of audio last exactly `bars` bars of the
+    /// project meter
+    pub fn tempo_for_length(&self, frames: SampleTime, bars: u32) -> Option<f32> {
+        if frames == 0 || bars == 0 {
+            return None;
+        }
+        let beats = bars as f64 * self.time_signature_numerator.max(1) as f64;
+        let seconds = frames as f64 / self.sample_rate as f64;
+        Some((beats * 60.0 / seconds) as f32)
+    }
+
+    /// Common bar counts that give a loop `frames` long a usable tempo, as
+    /// (bars, BPM), the one closest to 120 BPM first
+    pub fn guess_loop_bars(&self, frames: SampleTime) -> Vec<(u32, f32)> {
+        let mut guesses: Vec<(u32, f32)> = LOOP_BAR_COUNTS
+            .iter()
+            .filter_map(|&bars| Some((bars, self.tempo_for_length(frames, bars)?)))
+            .filter(|(_, tempo)| LOOP_TEMPO_RANGE.contains(tempo))
+            .collect();
+        guesses.sort_by(|a, b| (a.1 / 120.0).log2().abs().total_cmp(&(b.1 / 120.0).log2().abs()));
+        guesses
+    }
+
+    /// Format samples as `m:ss.mmm`
+    pub fn format_timecode(&self, samples: SampleTime) -> String {
+        let millis_total = (samples as f64 * 1000.0 / self.sample_rate as f64).round() as u64;
+        format!(
+            "{}:{:02}.{:03}",
+            millis_total / 60_000,
+            (millis_total / 1000) % 60,
+            millis_total % 1000
+        )
+    }
+
+    /// Musical grid lines between two beat positions. Density adapts to
+    /// `pixels_per_beat`: lines closer than `MIN_GRID_SPACING` pixels are
+    /// dropped, and bars are thinned out (every 2, 4, 8… bars) when zoomed
+    /// far out, so the grid never turns into a solid wall. A triplet `feel`
+    /// splits beats into thirds, sixths and twelfths instead of powers of two.
+    pub fn grid_lines(&self, start_beat: BeatTime, end_beat: BeatTime, pixels_per_beat: f64, feel: GridFeel) -> Vec<GridLine> {
+        let beats_per_bar = self.time_signature_numerator.max(1) as u64;
+        let bar_px = pixels_per_beat * beats_per_bar as f64;
+        if pixels_per_beat <= 0.0 || end_beat < start_beat {
+            return Vec::new();
+        }
+
+        // Smallest power-of-two bar stride whose lines are far enough apart
+        let mut bar_stride = 1u64;
+        while bar_px * (bar_stride as f64) < MIN_GRID_SPACING && bar_stride < 1 << 16 {
+            bar_stride *= 2;
+        }
+        // Labels need more room than lines
+        let mut label_stride = bar_stride;
+        while bar_px * (label_stride as f64) < MIN_GRID_LABEL_SPACING && label_stride < 1 << 16 {
+            label_stride *= 2;
+        }
+
+        let show_beats = pixels_per_beat >= MIN_GRID_SPACING;
+        let label_beats = pixels_per_beat >= MIN_GRID_LABEL_SPACING;
+        // Subdivide beats (halves, quarters, eighths, or thirds, sixths,
+        // twelfths for triplets) while the lines stay visible
+        let base = if feel == GridFeel::Triplet { 3 } else { 2 };
+        let mut subdivisions = 1u64;
+        let mut next = base;
+        while next <= base * 4 && pixels_per_beat / next as f64 >= MIN_GRID_SPACING {
+            subdivisions = next;
+            next *= 2;
+        }
+
+        let step = 1.0 / subdivisions as f64;
+        let first = (start_beat.max(0.0) / step).floor() as u64;
+        let last = (end_beat / step).ceil() as u64;
+
+        (first..=last)
+            .filter_map(|index| {
+                let beat = index as f64 * step;
+                let is_beat = index % subdivisions == 0;
+                let whole_beat = index / subdivisions;
+                let bar = whole_beat / beats_per_bar;
+                let is_bar = is_beat && whole_beat % beats_per_bar == 0;
+
+                let level = if is_bar {
+                    if bar % bar_stride != 0 {
+                        return None;
+                    }
+                    GridLevel::Bar
+                } else if is_beat {
+                    if !show_beats {
+                        return None;
+                    }
+                    GridLevel::Beat
+                } else {
+                    GridLevel::Subdivision
+                };
+
+                let labeled = match level {
+                    GridLevel::Bar => bar % label_stride == 0,
+                    GridLevel::Beat => label_beats,
+                    GridLevel::Subdivision => false,
+                };
+
+                Some(GridLine {
+                    beat,
+                    level,
+                    bar: bar + 1,
+                    beat_in_bar: whole_beat % beats_per_bar + 1,
+                    labeled,
+                })
+            })
+            .collect()
+    }
+
+    /// Snap a beat position to the nearest multiple of `division` beats.
+    /// Dotted divisions don't fit evenly into a bar, so their pattern
+    /// restarts on every bar line and the bar line itself is always a target.
+    pub fn snap_to_division(&self, beat: BeatTime, division: BeatTime, feel: GridFeel) -> BeatTime {
+        if division <= 0.0 {
+            return beat;
+        }
+        if feel != GridFeel::Dotted {
+            return (beat / division).round() * division;
+        }
+
+        let bar = self.time_signature_numerator.max(1) as f64;
+        let bar_start = (beat / bar).floor() * bar;
+        let snapped = (bar_start + ((beat - bar_start) / division).round() * division).min(bar_start + bar);
+        let next_bar = bar_start + bar;
+        if (next_bar - beat).abs() < (snapped - beat).abs() {
+            next_bar
+        } else {
+            snapped
+        }
+    }
+}
+
+/// Tick resolution used by bars:beats:ticks display
+pub const TICKS_PER_BEAT: u64 = 960;
+
+/// How existing material is treated when the project sample rate changes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SampleRateChange {
+    /// Rescale every sample position so material stays on the same beats
+    KeepMusicalTiming,
+    /// Leave sample positions untouched, so material moves in time
+    KeepSamplePositions,
+}
+
+/// Convert a sample count between rates, rounding to the nearest sample
+pub fn rescale_samples(samples: SampleTime, from_rate: f32, to_rate: f32) -> SampleTime {
+    (samples as f64 * to_rate as f64 / from_rate as f64).round() as SampleTime
+}
+
+/// Minimum distance between grid lines in pixels
+pub const MIN_GRID_SPACING: f64 = 8.0;
+
+/// Minimum distance between grid labels in pixels
+pub const MIN_GRID_LABEL_SPACING: f64 = 48.0;
+
+/// Rhythmic feel of a snap or grid division
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GridFeel {
+    Straight,
+    Triplet,
+    Dotted,
+}
+
+/// Emphasis of a grid line
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GridLevel {
+    Bar,
+    Beat,
+    Subdivision,
+}
+
+/// A line of the musical grid. `bar` and `beat_in_bar` are 1-based.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GridLine {
+    pub beat: BeatTime,
+    pub level: GridLevel,
+    pub bar: u64,
+    pub beat_in_bar: u64,
+    pub labeled: bool,
+}
+
+/// Metering data for visualizations
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MeterData {
+    pub peak_left: f32,
+    pub peak_right: f32,
+    pub rms_left: f32,
+    pub rms_right: f32,
+    /// Stereo phase correlation, -1 (out of phase) to +1 (mono). Zero for silence.
+    pub correlation: f32,
+    /// What the meter shows, with the ballistics chosen in the preferences
+    pub level_left: f32,
+    pub level_right: f32,
+}
+
+impl MeterData {
+    pub fn from_buffer(left: &[f32], right: &[f32]) -> Self {
+        let peak_left = left.iter().map(|s| s.abs()).fold(0.0f32, f32::max);
+        let peak_right = right.iter().map(|s| s.abs()).fold(0.0f32, f32::max);
+        
+        let rms_left = (left.iter().map(|s| s * s).sum::<f32>() / left.len() as f32).sqrt();
+        let rms_right = (right.iter().map(|s| s * s).sum::<f32>() / right.len() as f32).sqrt();
+
+        let cross: f32 = left.iter().zip(right.iter()).map(|(l, r)| l * r).sum();
+        let energy = (left.iter().map(|s| s * s).sum::<f32>() * right.iter().map(|s| s * s).sum::<f32>()).sqrt();
+        let correlation = if energy > 1e-12 { (cross / energy).clamp(-1.0, 1.0) } else { 0.0 };
+
+        Self {
+            peak_left,
+            peak_right,
+            rms_left,
+            rms_right,
+            correlation,
+            level_left: peak_left,
+            level_right: peak_right,
+        }
+    }
+
+    pub fn to_db(&self) -> (f32, f32) {
+        let left_db = if self.peak_left > 0.0 {
+            20.0 * self.peak_left.log10()
+        } else {
+            -100.0
+        };
+        
+        let right_db = if self.peak_right > 0.0 {
+            20.0 * self.peak_right.log10()
+        } else {
+            -100.0
+        };
+
+        (left_db, right_db)
+    }
+}
+
+/// Audio processing performance metrics
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PerformanceMetrics {
+    pub callback_duration_us: u64,
+    pub buffer_fill_time_us: u64,
+    pub cpu_usage: f32,
+    pub buffer_underruns: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bars_beats_round_trip() {
+        let transport = Transport::default();
+        let one_bar = transport.beats_to_samples(4.0);
+
+        assert_eq!(transport.format_bars_beats(0, true), "1:1:000");
+        assert_eq!(transport.format_bars_beats(one_bar, true), "2:1:000");
+        assert_eq!(transport.format_bars_beats(one_bar, false), "1:0:000");
+        assert_eq!(transport.parse_bars_beats("2:1:000", true), Some(one_bar));
+        assert_eq!(transport.parse_bars_beats("0:1", true), None);
+    }
+
+    #[test]
+    fn test_grid_lines_adapt_to_zoom() {
+        let transport = Transport::default();
+
+        // Zoomed in: bars, beats and subdivisions, beats labelled
+        let close = transport.grid_lines(0.0, 4.0, 100.0, GridFeel::Straight);
+        assert_eq!(close.iter().filter(|l| l.level == GridLevel::Bar).count(), 2);
+        assert_eq!(close.iter().filter(|l| l.level == GridLevel::Beat).count(), 3);
+        assert!(close.iter().any(|l| l.level == GridLevel::Subdivision));
+        assert!(close.iter().filter(|l| l.level == GridLevel::Beat).all(|l| l.labeled));
+        assert_eq!(close[close.len() - 1].bar, 2);
+
+        // Zoomed out: only every 4th bar survives and lines keep their spacing
+        let far = transport.grid_lines(0.0, 64.0, 0.5, GridFeel::Straight);
+        assert!(far.iter().all(|l| l.level == GridLevel::Bar));
+        assert_eq!(far.iter().map(|l| l.bar).collect::<Vec<_>>(), vec![1, 5, 9, 13, 17]);
+        for pair in far.windows(2) {
+            assert!((pair[1].beat - pair[0].beat) * 0.5 >= MIN_GRID_SPACING);
+        }
+    }
+
+    #[test]
+    fn test_advance_wraps_at_loop_end() {
+        let mut transport = Transport {
+            loop_enabled: true,
+            loop_start: 100,
+            loop_end: 200,
+            position: 150,
+            ..Transport::default()
+        };
+        transport.advance(60);
+        assert_eq!(transport.position, 110);
+
+        // Past the end when the loop was set: keep playing, no jump
+        transport.position = 500;
+        transport.advance(10);
+        assert_eq!(transport.position, 510);
+    }
+
+    #[test]
+    fn test_playback_stops_at_the_chosen_end() {
+        let mut transport = Transport {
+            state: TransportState::Playing,
+            position: 90,
+            playback_end: PlaybackEnd::StopAtProjectEnd,
+            ..Transport::default()
+        };
+        // The marker wins over the last clip, the last event ignores it
+        assert_eq!(transport.stop_point(100, 120), Some(100));
+        transport.project_length = Some(150);
+        assert_eq!(transport.stop_point(100, 120), Some(150));
+        transport.playback_end = PlaybackEnd::StopAtLastEvent;
+        assert_eq!(transport.stop_point(100, 120), Some(120));
+
+        assert!(!transport.advance_until(20, Some(120)));
+        assert!(transport.advance_until(20, Some(120)));
+        assert_eq!((transport.state, transport.position), (TransportState::Stopped, 120));
+
+        // Playing on from past the end keeps going
+        transport.play();
+        transport.position = 130;
+        assert!(!transport.advance_until(10, Some(120)));
+
+        // A loop, recording and Continue never stop
+        let looping = Transport { loop_enabled: true, loop_start: 0, loop_end: 50, ..transport.clone() };
+        assert_eq!(looping.stop_point(100, 120), None);
+        let recording = Transport { state: TransportState::Recording, ..transport.clone() };
+        assert_eq!(recording.stop_point(100, 120), None);
+        let live = Transport { playback_end: PlaybackEnd::Continue, ..transport };
+        assert_eq!(live.stop_point(100, 120), None);
+    }
+
+    #[test]
+    fn test_triplet_and_dotted_divisions() {
+        let transport = Transport::default();
+
+        // At 60 px per beat a triplet grid splits each beat into sixths
+        let lines = transport.grid_lines(0.0, 1.0, 60.0, GridFeel::Triplet);
+        assert_eq!(lines.len(), 7);
+        assert!((lines[2].beat - 1.0 / 3.0).abs() < 1e-9);
+        assert_eq!(lines[6].level, GridLevel::Beat);
+
+        // 1/8T is a third of a beat, independent of tempo
+        let eighth_triplet = 1.0 / 3.0;
+        assert!((transport.snap_to_division(0.3, eighth_triplet, GridFeel::Triplet) - 1.0 / 3.0).abs() < 1e-9);
+        assert!((transport.snap_to_division(2.9, eighth_triplet, GridFeel::Triplet) - 3.0).abs() < 1e-9);
+
+        // Dotted quarters restart on each bar: 0, 1.5, 3, then the next bar at 4
+        assert_eq!(transport.snap_to_division(1.4, 1.5, GridFeel::Dotted), 1.5);
+        assert_eq!(transport.snap_to_division(3.7, 1.5, GridFeel::Dotted), 4.0);
+        assert_eq!(transport.snap_to_division(5.6, 1.5, GridFeel::Dotted), 5.5);
+    }
+
+    #[test]
+    fn test_validate_source_range() {
+        let mut clip = AudioClip::new("loop.wav".into(), 0, 1000);
+        assert!(clip.validate_source_range(1000).is_ok());
+
+        clip.offset = 500;
+        assert!(clip.validate_source_range(1000).is_err());
+
+        clip.duration = 500;
+        clip.loop_points = Some(ClipLoop { start: 600, end: 1200 });
+        assert!(clip.validate_source_range(1000).is_err());
+
+        clip.loop_points = Some(ClipLoop { start: 600, end: 900 });
+        assert!(clip.validate_source_range(1000).is_ok());
+
+        clip.loop_points = Some(ClipLoop { start: 400, end: 900 });
+        assert!(clip.validate_source_range(1000).is_err());
+
+        // Looped, the clip can outlast the audio after its offset
+        clip.loop_points = Some(ClipLoop { start: 600, end: 900 });
+        clip.duration = 5000;
+        assert!(clip.validate_source_range(1000).is_ok());
+    }
+
+    #[test]
+    fn test_loop_wraps_source_position() {
+        let mut clip = AudioClip::new("loop.wav".into(), 1000, 5000);
+        clip.offset = 100;
+        assert_eq!(clip.looped_source_position(2000.0), 2000.0);
+        assert_eq!(clip.loop_timeline_range(), None);
+
+        clip.loop_points = Some(ClipLoop { start: 200, end: 600 });
+        assert_eq!(clip.looped_source_position(599.0), 599.0);
+        assert_eq!(clip.looped_source_position(600.0), 200.0);
+        assert_eq!(clip.looped_source_position(1450.0), 250.0);
+        assert_eq!(clip.loop_timeline_range(), Some((1100, 1500)));
+
+        clip.time_ratio = 2.0;
+        assert_eq!(clip.loop_timeline_range(), Some((1200, 2000)));
+    }
+
+    #[test]
+    fn test_source_range_keeps_audio_in_place() {
+        let mut clip = AudioClip::new("hit.wav".into(), 1000, 4000);
+        clip.offset = 200;
+        clip.fade_in = 1000;
+        clip.fade_out = 500;
+
+        // Trimming both edges
+        let trimmed = clip.with_source_range(600, 4000).unwrap();
+        assert_eq!((trimmed.start_time, trimmed.duration, trimmed.offset), (1400, 3400, 600));
+        assert_eq!((trimmed.fade_in, trimmed.fade_out), (600, 300));
+
+        // Revealing audio before the offset, fades stay where they were
+        let extended = clip.with_source_range(0, 4200).unwrap();
+        assert_eq!((extended.start_time, extended.duration), (800, 4200));
+        assert_eq!((extended.fade_in, extended.fade_out), (1200, 500));
+
+        // A clip near the timeline start can't move before it
+        clip.start_time = 50;
+        assert_eq!(clip.with_source_range(0, 4200).unwrap().offset, 150);
+        assert!(clip.with_source_range(300, 300).is_none());
+    }
+
+    #[test]
+    fn test_trimmed_audio_comes_back() {
+        let mut clip = AudioClip::new("hit.wav".into(), 1000, 4000);
+        clip.offset = 200;
+        let source_len = 5000;
+
+        // Trim the end in, then drag it back out past the original length
+        let short = clip.with_edge_at(false, 3000, source_len).unwrap();
+        assert_eq!((short.start_time, short.duration, short.offset), (1000, 2000, 200));
+        let long = short.with_edge_at(false, 4800, source_len).unwrap();
+        assert_eq!(long.duration, 3800);
+        // Past the end of the file the edge stops
+        assert_eq!(long.with_edge_at(false, 9000, source_len).unwrap().duration, 4800);
+
+        // The start reveals the frames before the offset and stops at frame 0
+        let revealed = clip.with_edge_at(true, 300, source_len).unwrap();
+        assert_eq!((revealed.start_time, revealed.offset, revealed.end_time()), (800, 0, 5000));
+        // Neither edge can cross the other
+        assert_eq!(clip.with_edge_at(true, 9000, source_len).unwrap().duration, 1);
+        assert_eq!(clip.with_edge_at(false, 0, source_len).unwrap().duration, 1);
+
+        // A looped clip's end runs on, its start stops at the loop
+        clip.loop_points = Some(ClipLoop { start: 1000, end: 2000 });
+        assert_eq!(clip.with_edge_at(false, 20000, source_len).unwrap().duration, 19000);
+        assert_eq!(clip.with_edge_at(true, 3000, source_len).unwrap().offset, 1000);
+    }
+
+    #[test]
+    fn test_phase_invert_reads_bool_and_channels() {
+        let both: PhaseInvert = serde_json::from_str("true").unwrap();
+        assert_eq!(both, PhaseInvert { left: true, right: true });
+
+        let right_only: PhaseInvert = serde_json::from_str(r#"{"right": true}"#).unwrap();
+        assert_eq!(right_only, PhaseInvert { left: false, right: true });
+
+        // Inverting one side of a mono signal reads as fully out of phase
+        let mut left = vec![0.5, -0.25, 0.75];
+        let mut right = left.clone();
+        right_only.apply(&mut left, &mut right);
+        assert!((MeterData::from_buffer(&left, &right).correlation + 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_mute_lane_steps_with_short_ramps() {
+        let mut lane = AutomationLane::new(AutomationParameter::Mute);
+        for (time, value) in [(1000, 1.0), (2000, 0.0)] {
+            lane.add_point(AutomationPoint {
+                id: uuid::Uuid::new_v4(),
+                time,
+                value,
+                curve_type: CurveType::Linear,
+                tension: 0.0,
+            });
+        }
+        // Mute lanes never interpolate between points
+        assert!(lane.points.iter().all(|p| p.curve_type == CurveType::Hold));
+        assert_eq!(lane.curve_value_at(1500), Some(1.0));
+
+        let ramp = MUTE_RAMP_SAMPLES;
+        assert_eq!(lane.mute_gain_at(0, ramp), 1.0);
+        assert_eq!(lane.mute_gain_at(1000, ramp), 1.0);
+        assert_eq!(lane.mute_gain_at(1000 + ramp, ramp), 0.0);
+        assert_eq!(lane.mute_gain_at(1500, ramp), 0.0);
+        assert_eq!(lane.mute_gain_at(2000, ramp), 0.0);
+        assert_eq!(lane.mute_gain_at(2000 + ramp / 2, ramp), 0.5);
+        assert_eq!(lane.mute_gain_at(2000 + ramp, ramp), 1.0);
+
+        lane.enabled = false;
+        assert_eq!(lane.mute_gain_at(1500, ramp), 1.0);
+    }
+
+    #[test]
+    fn test_fill_follows_the_curve() {
+        let mut lane = AutomationLane::new(AutomationParameter::Volume);
+        for (time, value, curve_type) in [(100, 0.2, CurveType::Linear), (300, 1.0, CurveType::SCurve), (500, 0.4, CurveType::Hold), (600, 0.8, CurveType::Linear)] {
+            lane.add_point(AutomationPoint {
+                id: uuid::Uuid::new_v4(),
+                time,
+                value,
+                curve_type,
+                tension: 0.0,
+            });
+        }
+        let mut values = vec![0.0; 700];
+        assert!(lane.fill(50, &mut values));
+        for (time, value) in (50..).zip(&values) {
+            assert_eq!(Some(*value), lane.value_at(time), "sample {}", time);
+        }
+
+        let mut mute = AutomationLane::new(AutomationParameter::Mute);
+        for (time, value) in [(10, 1.0), (200, 0.0)] {
+            mute.add_point(AutomationPoint {
+                id: uuid::Uuid::new_v4(),
+                time,
+                value,
+                curve_type: CurveType::Hold,
+                tension: 0.0,
+            });
+        }
+        let mut gains = vec![0.0; 400];
+        assert!(mute.fill_mute_gain(0, MUTE_RAMP_SAMPLES, &mut gains));
+        for (time, gain) in (0..).zip(&gains) {
+            assert_eq!(*gain, mute.mute_gain_at(time, MUTE_RAMP_SAMPLES), "sample {}", time);
+        }
+
+        lane.enabled = false;
+        assert!(!lane.fill(0, &mut values));
+    }
+
+    #[test]
+    fn test_segment_shapes_and_tension() {
+        let segment = |parameter: AutomationParameter, curve_type: CurveType, tension: f32| {
+            let mut lane = AutomationLane::new(parameter);
+            for (time, value) in [(0, 0.0), (1000, 1.0)] {
+                lane.add_point(AutomationPoint { id: uuid::Uuid::new_v4(), time, value, curve_type, tension });
+            }
+            [250, 500, 750].map(|time| lane.value_at(time).unwrap())
+        };
+        let volume = AutomationParameter::Volume;
+        assert_eq!(segment(volume, CurveType::Linear, 0.0), [0.25, 0.5, 0.75]);
+        assert_eq!(segment(volume, CurveType::Hold, 0.0), [0.0; 3]);
+        let s_curve = segment(volume, CurveType::SCurve, 0.0);
+        assert_eq!(s_curve[1], 0.5);
+        assert!(s_curve[0] < 0.25 && s_curve[2] > 0.75);
+        let exponential = segment(volume, CurveType::Exponential, 0.0);
+        assert!((exponential[1] - 7.0 / 63.0).abs() < 1e-6);
+        // Tension bends any shape the way a fade's curvature does
+        assert_eq!(segment(volume, CurveType::Linear, 1.0)[1], 0.0625);
+        assert!(segment(volume, CurveType::Linear, -1.0)[1] > 0.8);
+        // Mute lanes always step, whatever the point asks for
+        assert_eq!(segment(AutomationParameter::Mute, CurveType::SCurve, 0.5), [0.0; 3]);
+
+        // Projects saved before segment shapes keep their eased segments
+        let json = r#"{"id":"00000000-0000-0000-0000-000000000000","time":0,"value":1.0,"curve_type":"Bezier","bezier_handle_in":null,"bezier_handle_out":null}"#;
+        let point: AutomationPoint = serde_json::from_str(json).unwrap();
+        assert_eq!((point.curve_type, point.tension), (CurveType::SCurve, 0.0));
+        assert_eq!(CurveType::Hold.next(), CurveType::Linear);
+    }
+
+    #[test]
+    fn test_source_format_mismatches() {
+        let source = SourceFormat { sample_rate: 44100, bits_per_sample: Some(24), float: false, channels: 1 };
+        assert_eq!(
+            source.mismatches(48000.0),
+            vec![FormatMismatch::Resampled { from: 44100 }, FormatMismatch::MonoInStereo]
+        );
+        assert_eq!(source.describe(), "44.1 kHz · 24-bit · mono");
+        assert_eq!(FormatMismatch::Resampled { from: 44100 }.badge(), "44.1k");
+
+        let native = SourceFormat { sample_rate: 48000, bits_per_sample: Some(32), float: true, channels: 2 };
+        assert!(native.mismatches(48000.0).is_empty());
+        // Assets from before the format was recorded aren't flagged
+        assert!(SourceFormat::default().mismatches(48000.0).is_empty());
+    }
+
+    #[test]
+    fn test_channel_mode_conversions() {
+        let half = 0.5 * std::f32::consts::FRAC_1_SQRT_2;
+        let (l, r) = ChannelMode::MonoSum.apply(0.5, 0.0, 2);
+        assert!((l - half).abs() < 1e-6 && l == r);
+        assert_eq!(ChannelMode::MonoLeft.apply(0.5, 0.25, 2), (0.5, 0.5));
+        assert_eq!(ChannelMode::MonoRight.apply(0.5, 0.25, 2), (0.25, 0.25));
+        assert_eq!(ChannelMode::AsRecorded.apply(0.5, 0.25, 2), (0.5, 0.25));
+
+        // A mono source is already on both sides and isn't summed again
+        assert_eq!(ChannelMode::MonoSum.apply(0.5, 0.5, 1), (0.5, 0.5));
+        assert!(ChannelMode::AsRecorded.is_mono(1));
+        assert!(!ChannelMode::Stereo.is_mono(1));
+        assert!(ChannelMode::MonoLeft.is_mono(2));
+    }
+
+    #[test]
+    fn test_fader_law() {
+        assert!((fader_volume(0.75) - 1.0).abs() < 1e-6);
+        assert!((fader_position(1.0) - 0.75).abs() < 1e-6);
+        assert_eq!(fader_volume(0.0), 0.0);
+        assert!((20.0 * fader_volume(1.0).log10() - FADER_MAX_DB).abs() < 1e-4);
+
+        // Monotonic, and positions survive a round trip through the gain
+        let mut last = -1.0;
+        for step in 0..=100 {
+            let position = step as f32 / 100.0;
+            let volume = fader_volume(position);
+            assert!(volume > last);
+            assert!((fader_position(volume) - position).abs() < 1e-4, "{}", position);
+            last = volume;
+        }
+        // A 1 dB step near unity takes more travel than one near the bottom
+        assert!(fader_position(1.0) - fader_position(db_to_linear(-1.0)) > fader_position(db_to_linear(-40.0)) - fader_position(db_to_linear(-41.0)));
+
+        assert_eq!(parse_db(" -6 dB"), Some(-6.0));
+        assert_eq!(parse_db("+20"), Some(FADER_MAX_DB));
+        assert_eq!(parse_db("-inf"), Some(-100.0));
+        assert_eq!(parse_db("loud"), None);
+    }
+
+    #[test]
+    fn test_record_input_channels() {
+        assert_eq!(RecordInput::available(1), vec![RecordInput::Mono(0)]);
+        assert_eq!(
+            RecordInput::available(4),
+            vec![
+                RecordInput::Mono(0),
+                RecordInput::Mono(1),
+                RecordInput::Mono(2),
+                RecordInput::Mono(3),
+                RecordInput::Stereo(0),
+                RecordInput::Stereo(2),
+            ]
+        );
+        assert_eq!(RecordInput::Mono(3).next(4), RecordInput::Stereo(0));
+        assert_eq!(RecordInput::Stereo(2).next(4), RecordInput::Mono(0));
+        assert_eq!(RecordInput::Stereo(6).next(4), RecordInput::Mono(0));
+        assert_eq!(RecordInput::Stereo(2).label(), "In 3/4");
+
+        // A stereo track on a mono microphone records mono, not a silent right side
+        let on_mic = RecordInput::default().resolve(1);
+        assert_eq!(on_mic, RecordInput::Mono(0));
+        assert_eq!(on_mic.recorded_channels(), 1);
+        assert!(!RecordInput::default().fits(1));
+        assert_eq!(RecordInput::Stereo(2).resolve(4), RecordInput::Stereo(2));
+
+        let buffers = vec![vec![0.1, 0.2], vec![0.3, 0.4], vec![0.5, 0.6]];
+        assert_eq!(RecordInput::Mono(2).read(&buffers, 1), (0.6, 0.6));
+        assert_eq!(RecordInput::Stereo(0).read(&buffers, 0), (0.1, 0.3));
+        assert_eq!(RecordInput::Stereo(2).read(&buffers, 0), (0.5, 0.0));
+    }
+
+    #[test]
+    fn test_automation_value_snap() {
+        let volume = AutomationParameter::Volume;
+        let db = |value: f32| 20.0 * value.log10();
+        assert_eq!(volume.snap_value(1.0), 1.0);
+        assert!((db(volume.snap_value(db_to_linear(-3.4))) + 3.0).abs() < 1e-4);
+        assert!((db(volume.snap_value(db_to_linear(-5.6))) + 6.0).abs() < 1e-4);
+        assert!((db(volume.snap_value(2.5)) - FADER_MAX_DB).abs() < 1e-4);
+        assert_eq!(volume.snap_value(db_to_linear(-70.0)), 0.0);
+        assert_eq!(volume.snap_value(0.0), 0.0);
+
+        let pan = AutomationParameter::Pan;
+        assert_eq!(pan.snap_value(0.02), 0.0);
+        assert_eq!(pan.snap_value(-0.26), -0.25);
+        assert_eq!(pan.snap_value(1.3), 1.0);
+        assert_eq!(AutomationParameter::Mute.snap_value(0.7), 1.0);
+        assert_eq!(AutomationParameter::Send(0).snap_value(0.504), 0.5);
+
+        // Lane heights round trip, with unity where the fader puts it
+        for parameter in [volume, pan, AutomationParameter::Send(0)] {
+            for step in 0..=10 {
+                let position = step as f32 / 10.0;
+                let value = parameter.lane_value(position);
+                assert!((parameter.lane_position(value) - position).abs() < 1e-4, "{:?} {}", parameter, position);
+            }
+        }
+        assert!((volume.lane_position(1.0) - 0.75).abs() < 1e-6);
+        assert_eq!(pan.format_value(-0.25), "L25");
+        assert_eq!(volume.format_value(db_to_linear(-6.0)), "-6.0 dB");
+    }
+
+    #[test]
+    fn test_loop_tempo_detection() {
+        let transport = Transport::default();
+        // Two bars of 4/4 at 128 BPM
+        let frames = (8.0 * 60.0 / 128.0 * SAMPLE_RATE as f64).round() as SampleTime;
+        assert!((transport.tempo_for_length(frames, 2).unwrap() - 128.0).abs() < 0.01);
+        assert_eq!(transport.tempo_for_length(frames, 0), None);
+
+        let guesses = transport.guess_loop_bars(frames);
+        assert_eq!(guesses.iter().map(|(bars, _)| *bars).collect::<Vec<_>>(), vec![2, 1]);
+        assert!(guesses.iter().all(|(_, tempo)| LOOP_TEMPO_RANGE.contains(tempo)));
+
+        // In 3/4 the same length is fewer beats
+        let waltz = Transport { time_signature_numerator: 3, ..Transport::default() };
+        assert!((waltz.tempo_for_length(frames, 2).unwrap() - 96.0).abs() < 0.01);
+
+        // Fitting to the project stretches the clip onto its beats
+        let mut clip = AudioClip::new("loop.wav".into(), 0, frames);
+        clip.fit_to_tempo(128.0, 120.0);
+        assert!((clip.time_ratio - 128.0 / 120.0).abs() < 1e-9);
+        assert_eq!(clip.source_duration(), frames);
+        assert_eq!(clip.effective_tempo_mode(), TempoMode::Stretch);
+        assert!((Transport::default().samples_to_beats(clip.duration) - 8.0).abs() < 1e-3);
+        assert_eq!(clip.loop_length(), frames);
+    }
+}
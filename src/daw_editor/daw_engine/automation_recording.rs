@@ -0,0 +1,488 @@
+/// Automation written from a control surface
+/// A hardware controller learned onto a track parameter rides it while the
+/// transport rolls. A Touch pass writes while the controller moves and hands
+/// back to the existing curve once it's been left alone; a Latch pass keeps
+/// writing the last value until the transport stops. Incoming 7-bit values
+/// are smoothed so the written curve doesn't stair-step, and each finished
+/// pass is thinned down to the points its shape needs.
+use super::audio_types::{AutomationLane, AutomationParameter, AutomationPoint, CurveType, SampleTime, Track, TrackId};
+use super::quick_filters::{MAX_FILTER_HZ, MIN_FILTER_HZ};
+use serde::{Deserialize, Serialize};
+
+/// Seconds a Touch pass waits after the controller's last message before
+/// letting go
+const TOUCH_RELEASE_SECONDS: f64 = 0.5;
+/// Seconds a released Touch pass takes to glide back to the curve
+const TOUCH_RETURN_SECONDS: f64 = 0.1;
+/// Time constant of the smoothing over incoming values
+const SMOOTHING_SECONDS: f64 = 0.03;
+/// Furthest a thinned pass may stray from what was written, in lane height.
+/// Half a 7-bit step, so thinning loses nothing the controller can express.
+const THIN_TOLERANCE: f32 = 0.5 / 127.0;
+
+/// How a track's automation responds to its mapped controllers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum AutomationMode {
+    /// Controllers move the parameter itself and nothing is written
+    #[default]
+    Read,
+    /// Writes while the controller moves, then returns to the curve
+    Touch,
+    /// Writes from the first move until the transport stops
+    Latch,
+}
+
+impl AutomationMode {
+    pub fn label(&self) -> &'static str {
+        match self {
+            AutomationMode::Read => "Read",
+            AutomationMode::Touch => "Touch",
+            AutomationMode::Latch => "Latch",
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            AutomationMode::Read => AutomationMode::Touch,
+            AutomationMode::Touch => AutomationMode::Latch,
+            AutomationMode::Latch => AutomationMode::Read,
+        }
+    }
+
+    pub fn writes(&self) -> bool {
+        *self != AutomationMode::Read
+    }
+}
+
+/// A MIDI controller learned onto a track parameter
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ControlMapping {
+    /// MIDI channel, 0 to 15
+    pub channel: u8,
+    /// Controller number, 0 to 127
+    pub controller: u8,
+    pub track_id: TrackId,
+    pub parameter: AutomationParameter,
+}
+
+/// Add a learned mapping, replacing whatever its controller or its target
+/// was mapped to before
+pub fn learn_mapping(mappings: &mut Vec<ControlMapping>, mapping: ControlMapping) {
+    mappings.retain(|m| {
+        let same_controller = m.channel == mapping.channel && m.controller == mapping.controller;
+        let same_target = m.track_id == mapping.track_id && m.parameter == mapping.parameter;
+        !same_controller && !same_target
+    });
+    mappings.push(mapping);
+}
+
+/// Lane height of a 7-bit controller value
+pub fn controller_position(value: u8) -> f32 {
+    value.min(127) as f32 / 127.0
+}
+
+/// Set a parameter on the track itself, as its own control would. Returns
+/// false for parameters the track has no control for.
+pub fn apply_control(track: &mut Track, parameter: AutomationParameter, value: f32) -> bool {
+    match parameter {
+        AutomationParameter::Volume => track.volume = value,
+        AutomationParameter::Pan => track.pan = value,
+        AutomationParameter::Mute => track.muted = value >= 0.5,
+        AutomationParameter::HighPass => track.quick_filters.high_pass_hz = value.clamp(MIN_FILTER_HZ, MAX_FILTER_HZ),
+        AutomationParameter::LowPass => track.quick_filters.low_pass_hz = value.clamp(MIN_FILTER_HZ, MAX_FILTER_HZ),
+        AutomationParameter::Send(index) => match track.sends.get_mut(index) {
+            Some(send) => send.amount = value,
+            None => return false,
+        },
+        AutomationParameter::EffectMix(index) => match track.effects.get_mut(index) {
+            Some(effect) => effect.mix = value.clamp(0.0, 1.0),
+            None => return false,
+        },
+        AutomationParameter::EffectParam { .. } => return false,
+    }
+    true
+}
+
+/// Value an empty lane stands for, where there is one
+fn resting_value(parameter: AutomationParameter) -> Option<f32> {
+    match parameter {
+        AutomationParameter::Volume => Some(1.0),
+        AutomationParameter::Pan | AutomationParameter::Mute => Some(0.0),
+        // Fully open, where the filter is bypassed
+        AutomationParameter::HighPass => Some(MIN_FILTER_HZ),
+        AutomationParameter::LowPass => Some(MAX_FILTER_HZ),
+        AutomationParameter::Send(_) | AutomationParameter::EffectParam { .. } | AutomationParameter::EffectMix(_) => None,
+    }
+}
+
+fn seconds_to_samples(seconds: f64, sample_rate: f32) -> SampleTime {
+    (seconds * sample_rate as f64).round() as SampleTime
+}
+
+/// One parameter being written
+#[derive(Debug, Clone)]
+struct Pass {
+    track_id: TrackId,
+    parameter: AutomationParameter,
+    mode: AutomationMode,
+    sample_rate: f32,
+    /// The lane before the pass, for the curve a Touch pass returns to
+    original: AutomationLane,
+    start: SampleTime,
+    /// Time of the last point written
+    written_to: SampleTime,
+    last_message: SampleTime,
+    /// Smoothed lane height, gliding towards `target`
+    position: f32,
+    target: f32,
+}
+
+impl Pass {
+    fn start(track: &Track, parameter: AutomationParameter, time: SampleTime, position: f32, sample_rate: f32) -> Self {
+        let original = track.get_automation_lane(parameter).cloned().unwrap_or_else(|| AutomationLane::new(parameter));
+        // Glide in from wherever the curve was, not from the controller
+        let current = original.curve_value_at(time).or(resting_value(parameter));
+        let position = current.map_or(position, |value| parameter.lane_position(value));
+        Self {
+            track_id: track.id,
+            parameter,
+            mode: track.automation_mode,
+            sample_rate,
+            original,
+            start: time,
+            written_to: time,
+            last_message: time,
+            position,
+            target: position,
+        }
+    }
+
+    /// Samples after the last message at which a Touch pass lets go
+    fn release_time(&self) -> Option<SampleTime> {
+        (self.mode == AutomationMode::Touch)
+            .then(|| self.last_message + seconds_to_samples(TOUCH_RELEASE_SECONDS, self.sample_rate))
+    }
+
+    /// Glide on to `time` and write the point there. Existing points the
+    /// pass has moved over are replaced.
+    fn write_to(&mut self, track: &mut Track, time: SampleTime) {
+        if self.parameter.is_stepped() {
+            self.position = if self.target >= 0.5 { 1.0 } else { 0.0 };
+        } else {
+            let elapsed = time.saturating_sub(self.written_to) as f64 / self.sample_rate as f64;
+            let alpha = 1.0 - (-elapsed / SMOOTHING_SECONDS).exp();
+            self.position += (self.target - self.position) * alpha as f32;
+        }
+        let from = self.written_to;
+        let lane = track.get_automation_lane_mut(self.parameter);
+        lane.points.retain(|p| p.time <= from || p.time > time);
+        lane.add_point(line_point(time, self.parameter.lane_value(self.position)));
+        self.written_to = time;
+    }
+
+    /// End the pass at `end`. Touch passes glide back to the curve they
+    /// wrote over, then the pass is thinned.
+    fn finish(mut self, track: &mut Track, end: SampleTime) {
+        let end = end.max(self.written_to);
+        self.write_to(track, end);
+        if self.mode == AutomationMode::Touch {
+            let back = end + seconds_to_samples(TOUCH_RETURN_SECONDS, self.sample_rate);
+            let value = if self.original.points.is_empty() {
+                resting_value(self.parameter)
+            } else {
+                self.original.curve_value_at(back)
+            };
+            if let Some(value) = value {
+                let lane = track.get_automation_lane_mut(self.parameter);
+                lane.points.retain(|p| p.time <= end || p.time > back);
+                lane.add_point(line_point(back, value));
+            }
+        }
+
+        let (parameter, start) = (self.parameter, self.start);
+        let lane = track.get_automation_lane_mut(parameter);
+        let first = lane.points.partition_point(|p| p.time < start);
+        let last = lane.points.partition_point(|p| p.time <= end);
+        let written: Vec<AutomationPoint> = lane.points.drain(first..last).collect();
+        let thinned = thin_points(parameter, written);
+        lane.points.splice(first..first, thinned);
+    }
+}
+
+fn line_point(time: SampleTime, value: f32) -> AutomationPoint {
+    AutomationPoint {
+        id: uuid::Uuid::new_v4(),
+        time,
+        value,
+        curve_type: CurveType::Linear,
+        tension: 0.0,
+    }
+}
+
+/// Drop the points a written pass doesn't need. Curves are simplified with
+/// Ramer-Douglas-Peucker on lane height, so the result never strays from
+/// the written curve by more than [`THIN_TOLERANCE`]; stepped lanes only
+/// lose repeats. The first and last points always stay.
+fn thin_points(parameter: AutomationParameter, points: Vec<AutomationPoint>) -> Vec<AutomationPoint> {
+    if points.len() <= 2 {
+        return points;
+    }
+    if parameter.is_stepped() {
+        let last = points.len() - 1;
+        return points
+            .into_iter()
+            .enumerate()
+            .scan(None, |previous: &mut Option<f32>, (i, point)| {
+                let keep = i == last || *previous != Some(point.value);
+                *previous = Some(point.value);
+                Some(keep.then_some(point))
+            })
+            .flatten()
+            .collect();
+    }
+
+    let heights: Vec<f32> = points.iter().map(|p| parameter.lane_position(p.value)).collect();
+    let mut keep = vec![false; points.len()];
+    keep[0] = true;
+    keep[points.len() - 1] = true;
+    let mut spans = vec![(0, points.len() - 1)];
+    while let Some((from, to)) = spans.pop() {
+        let (t0, t1) = (points[from].time as f64, points[to].time as f64);
+        let farthest = (from + 1..to)
+            .map(|i| {
+                let t = if t1 > t0 { (points[i].time as f64 - t0) / (t1 - t0) } else { 0.0 };
+                let line = heights[from] + (heights[to] - heights[from]) * t as f32;
+                (i, (heights[i] - line).abs())
+            })
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+        if let Some((i, distance)) = farthest {
+            if distance > THIN_TOLERANCE {
+                keep[i] = true;
+                spans.push((from, i));
+                spans.push((i, to));
+            }
+        }
+    }
+    points.into_iter().zip(keep).filter_map(|(point, keep)| keep.then_some(point)).collect()
+}
+
+/// Passes being written from controllers while the transport rolls
+#[derive(Debug, Default)]
+pub struct AutomationRecorder {
+    passes: Vec<Pass>,
+}
+
+impl AutomationRecorder {
+    pub fn is_recording(&self) -> bool {
+        !self.passes.is_empty()
+    }
+
+    /// Take a controller at `position` for `parameter` at `time` on the
+    /// timeline, starting a pass if the parameter isn't being written yet.
+    /// Tracks in Read mode aren't written. Returns whether the lane changed.
+    pub fn control_change(
+        &mut self,
+        track: &mut Track,
+        parameter: AutomationParameter,
+        time: SampleTime,
+        position: f32,
+        sample_rate: f32,
+    ) -> bool {
+        if !track.automation_mode.writes() {
+            return false;
+        }
+        let existing = self.passes.iter().position(|p| p.track_id == track.id && p.parameter == parameter);
+        let index = match existing {
+            Some(index) if time >= self.passes[index].written_to => index,
+            _ => {
+                // The playhead jumped back, at a loop or a seek, so the
+                // old pass ends where it got to and a new one starts here
+                if let Some(index) = existing {
+                    let pass = self.passes.remove(index);
+                    let end = pass.written_to;
+                    pass.finish(track, end);
+                }
+                self.passes.push(Pass::start(track, parameter, time, position, sample_rate));
+                self.passes.len() - 1
+            }
+        };
+        let pass = &mut self.passes[index];
+        pass.write_to(track, time);
+        pass.target = position;
+        pass.last_message = time;
+        true
+    }
+
+    /// Move every pass on to `time`: values keep gliding towards the last
+    /// one received, Latch passes keep writing it and Touch passes left
+    /// alone long enough let go. Passes end if the playhead went back.
+    /// Returns the tracks whose lanes changed.
+    pub fn advance(&mut self, tracks: &mut [Track], time: SampleTime) -> Vec<TrackId> {
+        let mut changed = Vec::new();
+        let mut index = 0;
+        while index < self.passes.len() {
+            let pass = &self.passes[index];
+            let Some(track) = tracks.iter_mut().find(|t| t.id == pass.track_id) else {
+                self.passes.remove(index);
+                continue;
+            };
+            if !changed.contains(&track.id) {
+                changed.push(track.id);
+            }
+            let release = pass.release_time().filter(|&release| release <= time);
+            if time < pass.written_to || release.is_some() {
+                let pass = self.passes.remove(index);
+                let end = release.map_or(pass.written_to, |release| release.max(pass.written_to));
+                pass.finish(track, end);
+                continue;
+            }
+            self.passes[index].write_to(track, time);
+            index += 1;
+        }
+        changed
+    }
+
+    /// End every pass at `time`, when the transport stops. Returns the
+    /// tracks whose lanes changed.
+    pub fn finish(&mut self, tracks: &mut [Track], time: SampleTime) -> Vec<TrackId> {
+        let mut changed = Vec::new();
+        for pass in self.passes.drain(..) {
+            let Some(track) = tracks.iter_mut().find(|t| t.id == pass.track_id) else {
+                continue;
+            };
+            if !changed.contains(&track.id) {
+                changed.push(track.id);
+            }
+            // A Touch pass still held lets go where the controller stopped
+            let end = pass.release_time().map_or(time, |release| release.min(time));
+            pass.finish(track, end);
+        }
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::daw_editor::daw_engine::audio_types::TrackType;
+
+    const RATE: f32 = 48000.0;
+
+    fn track(mode: AutomationMode) -> Track {
+        let mut track = Track::new("Vocals", TrackType::Audio);
+        track.automation_mode = mode;
+        track
+    }
+
+    fn at(seconds: f64) -> SampleTime {
+        seconds_to_samples(seconds, RATE)
+    }
+
+    /// Advance in 50 ms steps, as the playhead updates do
+    fn roll(recorder: &mut AutomationRecorder, tracks: &mut [Track], from: f64, to: f64) {
+        let mut seconds = from;
+        while seconds < to {
+            seconds = (seconds + 0.05).min(to);
+            recorder.advance(tracks, at(seconds));
+        }
+    }
+
+    fn pan_at(track: &Track, seconds: f64) -> f32 {
+        track.get_automation_lane(AutomationParameter::Pan).unwrap().curve_value_at(at(seconds)).unwrap()
+    }
+
+    #[test]
+    fn test_learn_replaces_old_mappings() {
+        let target = uuid::Uuid::new_v4();
+        let mut mappings = Vec::new();
+        let map = |controller, parameter| ControlMapping { channel: 0, controller, track_id: target, parameter };
+        learn_mapping(&mut mappings, map(7, AutomationParameter::Volume));
+        learn_mapping(&mut mappings, map(10, AutomationParameter::Pan));
+        // The volume moves to another knob, and knob 10 now sends to it
+        learn_mapping(&mut mappings, map(10, AutomationParameter::Volume));
+        assert_eq!(mappings, vec![map(10, AutomationParameter::Volume)]);
+        assert_eq!(controller_position(127), 1.0);
+        assert_eq!(controller_position(200), 1.0);
+    }
+
+    #[test]
+    fn test_read_mode_doesnt_write() {
+        let mut track = track(AutomationMode::Read);
+        let mut recorder = AutomationRecorder::default();
+        assert!(!recorder.control_change(&mut track, AutomationParameter::Pan, 0, 1.0, RATE));
+        assert!(!recorder.is_recording() && track.automation.is_empty());
+        assert!(apply_control(&mut track, AutomationParameter::Pan, 0.5));
+        assert_eq!(track.pan, 0.5);
+    }
+
+    #[test]
+    fn test_touch_pass_returns_to_the_curve() {
+        let mut track = track(AutomationMode::Touch);
+        track.get_automation_lane_mut(AutomationParameter::Pan).add_point(line_point(0, -0.5));
+        let mut recorder = AutomationRecorder::default();
+        let mut tracks = vec![track];
+
+        // Sweep the knob hard right a message every 10 ms for a second
+        for step in 0..100 {
+            let time = at(1.0 + step as f64 * 0.01);
+            recorder.control_change(&mut tracks[0], AutomationParameter::Pan, time, 1.0, RATE);
+            recorder.advance(&mut tracks, time);
+        }
+        assert!(pan_at(&tracks[0], 1.0) < -0.45, "the pass glides in from the curve");
+        assert!(pan_at(&tracks[0], 1.5) > 0.99);
+
+        // Let go, the pass ends half a second after the last message
+        recorder.advance(&mut tracks, at(2.2));
+        assert!(recorder.is_recording());
+        recorder.advance(&mut tracks, at(2.6));
+        assert!(!recorder.is_recording());
+        assert!((pan_at(&tracks[0], 3.0) + 0.5).abs() < 1e-5, "back on the old curve");
+
+        // A hundred messages of a held value thin down to a handful
+        let points = tracks[0].get_automation_lane(AutomationParameter::Pan).unwrap().points.len();
+        assert!(points < 20, "{} points", points);
+    }
+
+    #[test]
+    fn test_latch_holds_until_stop() {
+        let mut tracks = vec![track(AutomationMode::Latch)];
+        let mut recorder = AutomationRecorder::default();
+        recorder.control_change(&mut tracks[0], AutomationParameter::Pan, at(0.0), 0.0, RATE);
+        recorder.control_change(&mut tracks[0], AutomationParameter::Pan, at(0.1), 0.0, RATE);
+        roll(&mut recorder, &mut tracks, 0.1, 3.0);
+        assert!(recorder.is_recording(), "a latched pass doesn't let go on its own");
+        assert_eq!(recorder.finish(&mut tracks, at(4.0)), vec![tracks[0].id]);
+        assert!((pan_at(&tracks[0], 4.0) + 1.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_smoothing_removes_stair_steps() {
+        let mut tracks = vec![track(AutomationMode::Latch)];
+        let mut recorder = AutomationRecorder::default();
+        // A slow knob turn arrives as coarse 7-bit steps
+        for step in 0..=10u8 {
+            let time = at(step as f64 * 0.1);
+            recorder.control_change(&mut tracks[0], AutomationParameter::Pan, time, controller_position(64 + step), RATE);
+            recorder.advance(&mut tracks, time);
+        }
+        recorder.finish(&mut tracks, at(1.0));
+        let lane = tracks[0].get_automation_lane(AutomationParameter::Pan).unwrap();
+        let values: Vec<f32> = (0..100).map(|i| lane.curve_value_at(at(i as f64 * 0.01)).unwrap()).collect();
+        assert!(values.windows(2).all(|w| w[1] >= w[0] - 1e-6), "the curve never steps back");
+        let biggest = values.windows(2).map(|w| w[1] - w[0]).fold(0.0, f32::max);
+        assert!(biggest < 2.0 / 127.0, "no jump as big as a controller step: {}", biggest);
+    }
+
+    #[test]
+    fn test_going_back_starts_a_new_pass() {
+        let mut tracks = vec![track(AutomationMode::Latch)];
+        let mut recorder = AutomationRecorder::default();
+        recorder.control_change(&mut tracks[0], AutomationParameter::Pan, at(2.0), 1.0, RATE);
+        roll(&mut recorder, &mut tracks, 2.0, 4.0);
+        // The loop wraps back to the start
+        recorder.control_change(&mut tracks[0], AutomationParameter::Pan, at(1.0), 0.0, RATE);
+        recorder.finish(&mut tracks, at(1.5));
+        assert!(pan_at(&tracks[0], 3.9) > 0.99, "the first pass is kept");
+        assert!(pan_at(&tracks[0], 1.5) < -0.99);
+    }
+}
@@ -0,0 +1,226 @@
+/// Clip clipboard payload
+/// Clips are exchanged through the OS clipboard as JSON using a subset of the
+/// .pdaw schema, so content can be pasted into another project or session.
+use super::audio_types::*;
+use anyhow::{Context as AnyhowContext, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Identifies clipboard text produced by the DAW
+pub const CLIPBOARD_FORMAT: &str = "pulsar-daw/clips";
+
+/// Clipboard payload version. Bump when the clip schema changes incompatibly.
+/// Version 2 dropped the tempo, which nothing read.
+pub const CLIPBOARD_VERSION: u32 = 2;
+
+/// A copied clip and the track it came from, relative to the topmost copied track
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClipboardClip {
+    pub track_offset: usize,
+    pub clip: AudioClip,
+}
+
+/// Versioned set of copied clips. Clip start times are relative to the
+/// earliest copied clip, and every sample position is at `sample_rate`, the
+/// rate of the project they were copied from.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClipboardPayload {
+    pub format: String,
+    pub version: u32,
+    pub sample_rate: f32,
+    pub clips: Vec<ClipboardClip>,
+}
+
+impl ClipboardPayload {
+    /// Build a payload from `(track_index, clip)` pairs of a project running
+    /// at `sample_rate`
+    pub fn from_clips(clips: Vec<(usize, AudioClip)>, sample_rate: f32) -> Self {
+        let min_track = clips.iter().map(|(idx, _)| *idx).min().unwrap_or(0);
+        let min_start = clips.iter().map(|(_, c)| c.start_time).min().unwrap_or(0);
+
+        let clips = clips
+            .into_iter()
+            .map(|(track_idx, mut clip)| {
+                clip.start_time -= min_start;
+                ClipboardClip {
+                    track_offset: track_idx - min_track,
+                    clip,
+                }
+            })
+            .collect();
+
+        Self {
+            format: CLIPBOARD_FORMAT.to_string(),
+            version: CLIPBOARD_VERSION,
+            sample_rate,
+            clips,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.clips.is_empty()
+    }
+
+    /// Rescale the clips' timing for a project running at `sample_rate`
+    pub fn conform_to_rate(&mut self, sample_rate: f32) {
+        if self.sample_rate == sample_rate || self.sample_rate <= 0.0 {
+            return;
+        }
+        for entry in &mut self.clips {
+            entry.clip.rescale_timing(self.sample_rate, sample_rate);
+        }
+        self.sample_rate = sample_rate;
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self).context("Failed to serialize clipboard")
+    }
+
+    /// Parse clipboard text. Payloads from a newer version are read on a
+    /// best-effort basis: unknown fields are ignored and clips that no longer
+    /// match the schema are skipped instead of failing the whole paste.
+    pub fn from_json(text: &str) -> Result<Self> {
+        let value: serde_json::Value =
+            serde_json::from_str(text).context("Clipboard does not contain JSON")?;
+
+        let format = value.get("format").and_then(|v| v.as_str()).unwrap_or_default();
+        if format != CLIPBOARD_FORMAT {
+            return Err(anyhow::anyhow!("Clipboard does not contain DAW clips"));
+        }
+
+        let version = value.get("version").and_then(|v| v.as_u64()).unwrap_or(0) as u32;
+        if version <= CLIPBOARD_VERSION {
+            return serde_json::from_value(value).context("Failed to parse clipboard clips");
+        }
+
+        tracing::warn!(
+            "Clipboard version {} is newer than supported version {}, pasting what can be read",
+            version,
+            CLIPBOARD_VERSION
+        );
+
+        let clips = value
+            .get("clips")
+            .and_then(|v| v.as_array())
+            .map(|clips| {
+                clips
+                    .iter()
+                    .filter_map(|c| serde_json::from_value::<ClipboardClip>(c.clone()).ok())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Ok(Self {
+            format: CLIPBOARD_FORMAT.to_string(),
+            version,
+            sample_rate: value
+                .get("sample_rate")
+                .and_then(|v| v.as_f64())
+                .map(|v| v as f32)
+                .unwrap_or(SAMPLE_RATE),
+            clips,
+        })
+    }
+
+    /// Resolve clip audio files that no longer exist at their original path by
+    /// looking for a file with the same name in `search_dirs`.
+    /// Returns the paths that could not be found.
+    pub fn resolve_asset_paths(&mut self, search_dirs: &[PathBuf]) -> Vec<PathBuf> {
+        let mut missing = Vec::new();
+
+        for entry in &mut self.clips {
+            if entry.clip.asset_path.exists() {
+                continue;
+            }
+
+            let found = entry.clip.asset_path.file_name().and_then(|name| {
+                search_dirs
+                    .iter()
+                    .map(|dir| dir.join(name))
+                    .find(|candidate| candidate.exists())
+            });
+
+            match found {
+                Some(path) => entry.clip.asset_path = path,
+                None => {
+                    if !missing.contains(&entry.clip.asset_path) {
+                        missing.push(entry.clip.asset_path.clone());
+                    }
+                }
+            }
+        }
+
+        missing
+    }
+
+    /// Point every clip using `old_path` at `new_path`
+    pub fn relink(&mut self, old_path: &Path, new_path: PathBuf) {
+        for entry in &mut self.clips {
+            if entry.clip.asset_path == old_path {
+                entry.clip.asset_path = new_path.clone();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let a = AudioClip::new("kick.wav".into(), 96000, 48000);
+        let b = AudioClip::new("snare.wav".into(), 144000, 48000);
+        let payload = ClipboardPayload::from_clips(vec![(2, a), (3, b)], SAMPLE_RATE);
+
+        let json = payload.to_json().unwrap();
+        let parsed = ClipboardPayload::from_json(&json).unwrap();
+
+        assert_eq!(parsed.clips.len(), 2);
+        assert_eq!(parsed.clips[0].track_offset, 0);
+        assert_eq!(parsed.clips[0].clip.start_time, 0);
+        assert_eq!(parsed.clips[1].track_offset, 1);
+        assert_eq!(parsed.clips[1].clip.start_time, 48000);
+    }
+
+    #[test]
+    fn test_newer_version_skips_unreadable_clips() {
+        let clip = AudioClip::new("kick.wav".into(), 0, 48000);
+        let mut value = serde_json::to_value(ClipboardPayload::from_clips(vec![(0, clip)], SAMPLE_RATE)).unwrap();
+        value["version"] = serde_json::json!(CLIPBOARD_VERSION + 1);
+        value["clips"]
+            .as_array_mut()
+            .unwrap()
+            .push(serde_json::json!({ "track_offset": 0, "clip": { "kind": "midi" } }));
+
+        let parsed = ClipboardPayload::from_json(&value.to_string()).unwrap();
+        assert_eq!(parsed.clips.len(), 1);
+    }
+
+    #[test]
+    fn test_paste_rescales_to_the_target_rate() {
+        let mut clip = AudioClip::new("kick.wav".into(), 96000, 48000);
+        clip.offset = 4800;
+        clip.fade_in = 480;
+        clip.fade_out = 960;
+        let first = AudioClip::new("snare.wav".into(), 48000, 48000);
+        let mut payload = ClipboardPayload::from_clips(vec![(0, first), (0, clip)], 48000.0);
+        assert_eq!(ClipboardPayload::from_json(&payload.to_json().unwrap()).unwrap().sample_rate, 48000.0);
+
+        payload.conform_to_rate(96000.0);
+        assert_eq!(payload.sample_rate, 96000.0);
+        let clip = &payload.clips[1].clip;
+        assert_eq!((clip.start_time, clip.duration, clip.offset), (96000, 96000, 9600));
+        assert_eq!((clip.fade_in, clip.fade_out), (960, 1920));
+
+        // Already at the target rate, nothing changes
+        payload.conform_to_rate(96000.0);
+        assert_eq!(payload.clips[1].clip.start_time, 96000);
+    }
+
+    #[test]
+    fn test_rejects_foreign_text() {
+        assert!(ClipboardPayload::from_json("hello").is_err());
+        assert!(ClipboardPayload::from_json("{\"format\": \"other\"}").is_err());
+    }
+}
@@ -0,0 +1,268 @@
+/// Partitioned convolution for the convolution reverb
+/// The impulse response is cut into blocks of [`PARTITION_FRAMES`]. The
+/// first block is convolved directly, sample by sample, so the reverb has no
+/// latency. The rest are each transformed once, when the response loads;
+/// every block of input is then transformed once and multiplied against all
+/// of them in the frequency domain (uniformly partitioned overlap-save). That
+/// result is a block late, which is just where the second partition starts,
+/// so a response seconds long costs one FFT pair per block, a multiply-add
+/// per partition and the direct head.
+use super::spectrogram::fft;
+use std::sync::Arc;
+
+/// Frames per partition, and the length of the directly convolved head
+pub const PARTITION_FRAMES: usize = 256;
+
+/// Longest response used, in frames, ten seconds at 48 kHz. Anything past
+/// this is dropped as it loads.
+pub const MAX_IMPULSE_FRAMES: usize = 480_000;
+
+const FFT_SIZE: usize = 2 * PARTITION_FRAMES;
+
+/// Bins kept per spectrum. The input is real, so the rest mirror these.
+const BINS: usize = PARTITION_FRAMES + 1;
+
+/// An impulse response split into its head and transformed later partitions
+#[derive(Debug)]
+pub struct ImpulseResponse {
+    /// First partition of the left and right channel, as samples
+    head: [Vec<f32>; 2],
+    /// Spectra of the partitions after the head, `BINS` per partition
+    re: [Vec<f32>; 2],
+    im: [Vec<f32>; 2],
+    /// Partitions after the head
+    partitions: usize,
+    frames: usize,
+}
+
+impl ImpulseResponse {
+    /// Transform interleaved `samples` with `channels` channels. A mono
+    /// response is used on both sides; past two channels the rest are ignored.
+    /// The response is scaled to unit energy on its louder side, so every
+    /// file comes out about as loud as the dry signal and the reverb level
+    /// means the same whatever was loaded. Both sides share the gain, which
+    /// keeps the response's own balance.
+    pub fn new(samples: &[f32], channels: usize) -> Self {
+        let channels = channels.max(1);
+        let frames = (samples.len() / channels).min(MAX_IMPULSE_FRAMES);
+        let energy = |channel: usize| (0..frames).map(|frame| samples[frame * channels + channel].powi(2)).sum::<f32>();
+        let loudest = (0..channels.min(2)).map(energy).fold(0.0, f32::max);
+        let gain = if loudest > 0.0 { loudest.sqrt().recip() } else { 1.0 };
+        let partitions = frames.div_ceil(PARTITION_FRAMES).saturating_sub(1);
+        let mut head = [Vec::new(), Vec::new()];
+        let mut spectra = [(Vec::new(), Vec::new()), (Vec::new(), Vec::new())];
+        let (mut re, mut im) = (vec![0.0; FFT_SIZE], vec![0.0; FFT_SIZE]);
+
+        for (side, (side_re, side_im)) in spectra.iter_mut().enumerate() {
+            let channel = side.min(channels - 1);
+            let sample = |frame: usize| samples[frame * channels + channel] * gain;
+            head[side] = (0..frames.min(PARTITION_FRAMES)).map(sample).collect();
+            for partition in 1..=partitions {
+                re.fill(0.0);
+                im.fill(0.0);
+                let start = partition * PARTITION_FRAMES;
+                for (i, frame) in (start..frames.min(start + PARTITION_FRAMES)).enumerate() {
+                    re[i] = sample(frame);
+                }
+                fft(&mut re, &mut im);
+                side_re.extend_from_slice(&re[..BINS]);
+                side_im.extend_from_slice(&im[..BINS]);
+            }
+        }
+
+        let [(left_re, left_im), (right_re, right_im)] = spectra;
+        Self { head, re: [left_re, right_re], im: [left_im, right_im], partitions, frames }
+    }
+
+    /// Length of the response in frames, which is how long the reverb
+    /// rings on after its input stops
+    pub fn frames(&self) -> usize {
+        self.frames
+    }
+}
+
+/// Running convolution of one channel against an impulse response
+#[derive(Clone)]
+pub struct Convolver {
+    impulse: Arc<ImpulseResponse>,
+    /// 0 for left, 1 for right
+    side: usize,
+    /// The previous input block followed by the one being filled
+    input: Vec<f32>,
+    /// Spectra of the last `partitions` input blocks, the newest at `newest`
+    history_re: Vec<f32>,
+    history_im: Vec<f32>,
+    newest: usize,
+    /// Later partitions' share of the block being filled, worked out from
+    /// the blocks before it
+    output: Vec<f32>,
+    position: usize,
+    scratch_re: Vec<f32>,
+    scratch_im: Vec<f32>,
+    sum_re: Vec<f32>,
+    sum_im: Vec<f32>,
+}
+
+impl Convolver {
+    /// Everything is allocated here, so `process` is safe on the audio thread
+    pub fn new(impulse: Arc<ImpulseResponse>, side: usize) -> Self {
+        let history = impulse.partitions * BINS;
+        Self {
+            impulse,
+            side: side.min(1),
+            input: vec![0.0; FFT_SIZE],
+            history_re: vec![0.0; history],
+            history_im: vec![0.0; history],
+            newest: 0,
+            output: vec![0.0; PARTITION_FRAMES],
+            position: 0,
+            scratch_re: vec![0.0; FFT_SIZE],
+            scratch_im: vec![0.0; FFT_SIZE],
+            sum_re: vec![0.0; BINS],
+            sum_im: vec![0.0; BINS],
+        }
+    }
+
+    /// Replace `samples` with the convolved signal scaled by `gain`
+    pub fn process(&mut self, samples: &mut [f32], gain: f32) {
+        for sample in samples.iter_mut() {
+            let now = PARTITION_FRAMES + self.position;
+            self.input[now] = *sample;
+            // The previous block sits in front of this one, so the head
+            // reaches back into it
+            let head = &self.impulse.head[self.side];
+            let direct: f32 = head.iter().zip(self.input[..=now].iter().rev()).map(|(h, x)| h * x).sum();
+            *sample = (direct + self.output[self.position]) * gain;
+            self.position += 1;
+            if self.position == PARTITION_FRAMES {
+                self.run_block();
+                self.position = 0;
+            }
+        }
+    }
+
+    fn run_block(&mut self) {
+        let partitions = self.impulse.partitions;
+        if partitions == 0 {
+            self.input.copy_within(PARTITION_FRAMES.., 0);
+            return;
+        }
+        let (re, im) = (&mut self.scratch_re, &mut self.scratch_im);
+        re.copy_from_slice(&self.input);
+        im.fill(0.0);
+        fft(re, im);
+
+        // The history is a ring walked backwards, so the partition p after
+        // the head meets the input from p blocks ago
+        self.newest = (self.newest + partitions - 1) % partitions;
+        let slot = self.newest * BINS;
+        self.history_re[slot..slot + BINS].copy_from_slice(&re[..BINS]);
+        self.history_im[slot..slot + BINS].copy_from_slice(&im[..BINS]);
+
+        self.sum_re.fill(0.0);
+        self.sum_im.fill(0.0);
+        let (impulse_re, impulse_im) = (&self.impulse.re[self.side], &self.impulse.im[self.side]);
+        for partition in 0..partitions {
+            let slot = (self.newest + partition) % partitions * BINS;
+            let at = partition * BINS;
+            for bin in 0..BINS {
+                let (x_re, x_im) = (self.history_re[slot + bin], self.history_im[slot + bin]);
+                let (h_re, h_im) = (impulse_re[at + bin], impulse_im[at + bin]);
+                self.sum_re[bin] += x_re * h_re - x_im * h_im;
+                self.sum_im[bin] += x_re * h_im + x_im * h_re;
+            }
+        }
+
+        // Inverse transform as the conjugate of the forward one, with the
+        // upper half mirrored back in
+        for bin in 0..FFT_SIZE {
+            let mirrored = if bin < BINS { bin } else { FFT_SIZE - bin };
+            re[bin] = self.sum_re[mirrored];
+            im[bin] = if bin < BINS { -self.sum_im[bin] } else { self.sum_im[mirrored] };
+        }
+        fft(re, im);
+
+        // Overlap-save: only the second half is free of wrap-around
+        let scale = 1.0 / FFT_SIZE as f32;
+        for (out, value) in self.output.iter_mut().zip(&re[PARTITION_FRAMES..]) {
+            *out = value * scale;
+        }
+        self.input.copy_within(PARTITION_FRAMES.., 0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_partitioned_convolution_matches_direct_convolution() {
+        // Longer than two partitions, with a different right channel
+        let frames = 2 * PARTITION_FRAMES + 77;
+        let samples: Vec<f32> = (0..frames)
+            .flat_map(|i| {
+                let decay = (-(i as f32) / 150.0).exp();
+                [decay * (i as f32 * 0.7).sin(), if i == 3 { 1.0 } else { 0.0 }]
+            })
+            .collect();
+        let impulse = Arc::new(ImpulseResponse::new(&samples, 2));
+        assert_eq!(impulse.frames(), frames);
+        // Both sides are scaled by the louder, left one
+        let scale = 1.0 / (0..frames).map(|i| samples[i * 2].powi(2)).sum::<f32>().sqrt();
+
+        let input: Vec<f32> = (0..2000).map(|i| ((i * 7919) % 101) as f32 / 50.0 - 1.0).collect();
+        let (mut left, mut right) = (input.clone(), input.clone());
+        let (mut left_conv, mut right_conv) = (Convolver::new(impulse.clone(), 0), Convolver::new(impulse, 1));
+        // Uneven block sizes, as the audio thread gives
+        let mut start = 0;
+        for size in [100, 37, 256, 1, 600, 1006] {
+            left_conv.process(&mut left[start..start + size], 0.5);
+            right_conv.process(&mut right[start..start + size], 1.0);
+            start += size;
+        }
+
+        // Nothing is late
+        for t in 0..input.len() {
+            let expected: f32 = (0..frames.min(t + 1)).map(|j| samples[j * 2] * scale * input[t - j]).sum();
+            assert!((left[t] - expected * 0.5).abs() < 1e-3, "frame {}: {} vs {}", t, left[t], expected * 0.5);
+        }
+        // The right channel's response is an impulse three frames in
+        assert!(right[..3].iter().all(|s| s.abs() < 1e-4));
+        for t in 0..input.len() - 3 {
+            assert!((right[t + 3] - input[t] * scale).abs() < 1e-4);
+        }
+
+        // A mono response feeds both sides, and one no longer than the head
+        // is convolved directly
+        let mono = ImpulseResponse::new(&[1.0, 0.5], 1);
+        assert_eq!(mono.frames(), 2);
+        assert_eq!(mono.head[0], mono.head[1]);
+        let mut convolver = Convolver::new(Arc::new(mono), 0);
+        let mut block = [1.0, 0.0, 0.0, 2.0];
+        convolver.process(&mut block, 1.0);
+        assert_eq!(block.map(|s| s / block[0]), [1.0, 0.5, 0.0, 2.0]);
+    }
+
+    #[test]
+    fn test_response_is_scaled_to_unit_energy() {
+        let impulse_of = |samples: &[f32], channels: usize| {
+            let mut convolvers = [0, 1].map(|side| Convolver::new(Arc::new(ImpulseResponse::new(samples, channels)), side));
+            convolvers.each_mut().map(|convolver| {
+                let mut block = [1.0, 0.0, 0.0];
+                convolver.process(&mut block, 1.0);
+                block
+            })
+        };
+        // A quiet response and a loud one come out alike
+        assert_eq!(impulse_of(&[0.1, 0.0], 1), [[1.0, 0.0, 0.0]; 2]);
+        let [left, _] = impulse_of(&[3.0, 4.0], 1);
+        assert!((left[0] - 0.6).abs() < 1e-6 && (left[1] - 0.8).abs() < 1e-6);
+
+        // The louder side sets the gain, so the balance stays
+        let [left, right] = impulse_of(&[2.0, 1.0], 2);
+        assert_eq!((left[0], right[0]), (1.0, 0.5));
+
+        // A silent response stays silent
+        assert_eq!(impulse_of(&[0.0; 4], 1), [[0.0; 3]; 2]);
+    }
+}
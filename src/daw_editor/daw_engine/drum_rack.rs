@@ -0,0 +1,290 @@
+/// Drum rack: a grid of pads, each playing one sample
+/// Every pad has its own volume, pan, pitch and MIDI key, and may play only a
+/// region of its sample so one loop can be spread across pads. Pads in the
+/// same choke group cut each other off, like open and closed hi-hats. The
+/// whole rack is stored as one JSON file.
+use super::audio_graph::calculate_pan;
+use super::audio_types::SampleTime;
+use anyhow::{Context as AnyhowContext, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Pads in a new rack, laid out as a 4x4 grid
+pub const DEFAULT_PAD_COUNT: usize = 16;
+
+/// MIDI key of the first pad (C1, General MIDI kick)
+const FIRST_PAD_KEY: u8 = 36;
+
+/// Number of choke groups a pad can join
+pub const CHOKE_GROUPS: u8 = 8;
+
+/// Semitones a pad can be pitched either way
+pub const MAX_PAD_PITCH: f32 = 24.0;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DrumPad {
+    pub name: String,
+    pub sample: Option<PathBuf>,
+    /// Start and end of the part of the sample to play, in samples. None
+    /// plays the whole file.
+    #[serde(default)]
+    pub region: Option<(SampleTime, SampleTime)>,
+    /// MIDI key that triggers the pad
+    pub key: u8,
+    #[serde(default)]
+    pub volume_db: f32,
+    /// -1.0 (left) to 1.0 (right)
+    #[serde(default)]
+    pub pan: f32,
+    /// Transposition in semitones
+    #[serde(default)]
+    pub pitch: f32,
+    /// Pads sharing a group stop each other, 1-based
+    #[serde(default)]
+    pub choke_group: Option<u8>,
+}
+
+impl DrumPad {
+    pub fn new(key: u8) -> Self {
+        Self {
+            name: String::new(),
+            sample: None,
+            region: None,
+            key,
+            volume_db: 0.0,
+            pan: 0.0,
+            pitch: 0.0,
+            choke_group: None,
+        }
+    }
+
+    /// Load a sample onto the pad, naming the pad after the file
+    pub fn assign_sample(&mut self, path: PathBuf, region: Option<(SampleTime, SampleTime)>) {
+        self.name = path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+        self.sample = Some(path);
+        self.region = region;
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::new(self.key);
+    }
+
+    /// Next choke group in the cycle none, 1, 2, ... CHOKE_GROUPS, none
+    pub fn next_choke_group(&self) -> Option<u8> {
+        match self.choke_group {
+            None => Some(1),
+            Some(group) if group < CHOKE_GROUPS => Some(group + 1),
+            Some(_) => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DrumRack {
+    pub name: String,
+    pub pads: Vec<DrumPad>,
+}
+
+impl Default for DrumRack {
+    fn default() -> Self {
+        Self {
+            name: "Drum Rack".to_string(),
+            pads: (0..DEFAULT_PAD_COUNT as u8).map(|i| DrumPad::new(FIRST_PAD_KEY + i)).collect(),
+        }
+    }
+}
+
+impl DrumRack {
+    /// Rack file kept next to the project at `project_path`, such as
+    /// `song.rack.json` for `song.pdaw`
+    pub fn sidecar_path(project_path: &Path) -> PathBuf {
+        project_path.with_extension("rack.json")
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let json = std::fs::read_to_string(path).with_context(|| format!("Failed to read drum rack {:?}", path))?;
+        serde_json::from_str(&json).with_context(|| format!("Failed to parse drum rack {:?}", path))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, json).with_context(|| format!("Failed to write drum rack {:?}", path))
+    }
+
+    /// Pad triggered by a MIDI key
+    pub fn pad_for_key(&self, key: u8) -> Option<usize> {
+        self.pads.iter().position(|pad| pad.key == key)
+    }
+}
+
+/// A pad sound to start playing
+#[derive(Debug, Clone, PartialEq)]
+pub struct PadStart {
+    pub voice_id: u64,
+    pub pad: usize,
+    pub sample: PathBuf,
+    pub region: Option<(SampleTime, SampleTime)>,
+    pub gain_left: f32,
+    pub gain_right: f32,
+    /// Playback rate from the pad's pitch
+    pub pitch_ratio: f32,
+}
+
+#[derive(Debug, Clone)]
+struct PadVoice {
+    id: u64,
+    pad: usize,
+    choke_group: Option<u8>,
+}
+
+/// Plays a [`DrumRack`] from MIDI notes or pad clicks, applying choke groups
+#[derive(Default)]
+pub struct DrumRackPlayer {
+    voices: Vec<PadVoice>,
+    next_voice_id: u64,
+}
+
+impl DrumRackPlayer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Trigger the pad for a MIDI key. See [`Self::trigger`].
+    pub fn note_on(&mut self, rack: &DrumRack, key: u8, velocity: u8) -> (Option<PadStart>, Vec<u64>) {
+        match rack.pad_for_key(key) {
+            Some(pad) => self.trigger(rack, pad, velocity),
+            None => (None, Vec::new()),
+        }
+    }
+
+    /// Trigger a pad. Returns the sound to start, if the pad has a sample,
+    /// and the ids of voices its choke group cuts off, which should stop.
+    pub fn trigger(&mut self, rack: &DrumRack, pad_index: usize, velocity: u8) -> (Option<PadStart>, Vec<u64>) {
+        let Some(pad) = rack.pads.get(pad_index) else {
+            return (None, Vec::new());
+        };
+        let Some(sample) = pad.sample.clone() else {
+            return (None, Vec::new());
+        };
+
+        // A pad in a choke group also cuts its own previous hit
+        let mut choked = Vec::new();
+        if let Some(group) = pad.choke_group {
+            self.voices.retain(|voice| {
+                let cut = voice.choke_group == Some(group);
+                if cut {
+                    choked.push(voice.id);
+                }
+                !cut
+            });
+        }
+
+        let gain = 10.0_f32.powf(pad.volume_db / 20.0) * velocity.min(127) as f32 / 127.0;
+        let (pan_left, pan_right) = calculate_pan(pad.pan);
+        let start = PadStart {
+            voice_id: self.next_voice_id,
+            pad: pad_index,
+            sample,
+            region: pad.region,
+            gain_left: gain * pan_left,
+            gain_right: gain * pan_right,
+            pitch_ratio: 2.0_f32.powf(pad.pitch.clamp(-MAX_PAD_PITCH, MAX_PAD_PITCH) / 12.0),
+        };
+        self.voices.push(PadVoice { id: self.next_voice_id, pad: pad_index, choke_group: pad.choke_group });
+        self.next_voice_id += 1;
+        (Some(start), choked)
+    }
+
+    /// Forget a voice whose sample finished playing
+    pub fn voice_finished(&mut self, voice_id: u64) {
+        self.voices.retain(|voice| voice.id != voice_id);
+    }
+
+    /// Whether any hit of the pad is still sounding
+    pub fn is_pad_playing(&self, pad: usize) -> bool {
+        self.voices.iter().any(|voice| voice.pad == pad)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::test_support::temp_dir;
+
+    fn rack_with_hats() -> DrumRack {
+        let mut rack = DrumRack::default();
+        rack.pads[0].assign_sample(PathBuf::from("kick.wav"), None);
+        rack.pads[2].assign_sample(PathBuf::from("hats.wav"), Some((0, 4800)));
+        rack.pads[2].choke_group = Some(1);
+        rack.pads[6].assign_sample(PathBuf::from("hats.wav"), Some((4800, 24000)));
+        rack.pads[6].choke_group = Some(1);
+        rack
+    }
+
+    #[test]
+    fn test_pads_trigger_from_keys() {
+        let rack = rack_with_hats();
+        let mut player = DrumRackPlayer::new();
+        let (kick, choked) = player.note_on(&rack, 36, 127);
+        let kick = kick.unwrap();
+        assert_eq!(kick.sample, PathBuf::from("kick.wav"));
+        assert_eq!(rack.pads[0].name, "kick");
+        assert!(choked.is_empty());
+        assert!((kick.gain_left - kick.gain_right).abs() < 1e-6);
+
+        // Empty pads and unmapped keys are silent
+        assert_eq!(player.note_on(&rack, 37, 127).0, None);
+        assert_eq!(player.note_on(&rack, 100, 127).0, None);
+        assert!(player.is_pad_playing(0));
+    }
+
+    #[test]
+    fn test_choke_group_cuts_overlapping_pads() {
+        let rack = rack_with_hats();
+        let mut player = DrumRackPlayer::new();
+        let (kick, _) = player.trigger(&rack, 0, 100);
+        let (open, _) = player.trigger(&rack, 6, 100);
+        let (closed, choked) = player.trigger(&rack, 2, 100);
+        assert_eq!(choked, vec![open.unwrap().voice_id]);
+        assert_eq!(closed.unwrap().region, Some((0, 4800)));
+        assert!(!player.is_pad_playing(6));
+        // Pads outside the group keep ringing
+        assert!(player.is_pad_playing(0));
+        player.voice_finished(kick.unwrap().voice_id);
+        assert!(!player.is_pad_playing(0));
+    }
+
+    #[test]
+    fn test_pad_pan_and_pitch() {
+        let mut rack = rack_with_hats();
+        rack.pads[0].pan = -1.0;
+        rack.pads[0].pitch = 12.0;
+        rack.pads[0].volume_db = -6.0;
+        let (start, _) = DrumRackPlayer::new().trigger(&rack, 0, 127);
+        let start = start.unwrap();
+        assert!((start.pitch_ratio - 2.0).abs() < 1e-5);
+        assert!((start.gain_left - 0.501).abs() < 0.01);
+        assert!(start.gain_right < 1e-3);
+    }
+
+    #[test]
+    fn test_rack_json_round_trip() {
+        let dir = temp_dir();
+        let path = dir.join("rack.json");
+        let mut rack = rack_with_hats();
+        rack.pads[1].pitch = -3.0;
+        rack.save(&path).unwrap();
+        assert_eq!(DrumRack::load(&path).unwrap(), rack);
+        std::fs::remove_dir_all(&dir).ok();
+
+        let mut pad = DrumPad::new(40);
+        let groups: Vec<_> = (0..=CHOKE_GROUPS)
+            .map(|_| {
+                pad.choke_group = pad.next_choke_group();
+                pad.choke_group
+            })
+            .collect();
+        assert_eq!(groups.first(), Some(&Some(1)));
+        assert_eq!(groups.last(), Some(&None));
+    }
+}
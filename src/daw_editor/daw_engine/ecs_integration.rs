@@ -66,8 +66,7 @@ impl EcsAudioBridge {
                     time: sample_time,
                     value,
                     curve_type: CurveType::Linear,
-                    bezier_handle_in: None,
-                    bezier_handle_out: None,
+                    tension: 0.0,
                 };
                 audio_service.add_automation_point(track_id, param, point).await?;
             }
@@ -84,8 +83,7 @@ impl EcsAudioBridge {
                     time: current_pos + duration_samples,
                     value: 0.0,
                     curve_type: CurveType::Linear,
-                    bezier_handle_in: None,
-                    bezier_handle_out: None,
+                    tension: 0.0,
                 };
                 audio_service
                     .add_automation_point(track_a, AutomationParameter::Volume, fade_out_point)
@@ -96,8 +94,7 @@ impl EcsAudioBridge {
                     time: current_pos + duration_samples,
                     value: 1.0,
                     curve_type: CurveType::Linear,
-                    bezier_handle_in: None,
-                    bezier_handle_out: None,
+                    tension: 0.0,
                 };
                 audio_service
                     .add_automation_point(track_b, AutomationParameter::Volume, fade_in_point)
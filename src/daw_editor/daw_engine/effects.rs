@@ -0,0 +1,1036 @@
+/// Track insert effects
+/// Each track runs a chain of effects after its clips and monitored input,
+/// before the fader. Effects are plain data saved with the track; the graph
+/// keeps each one's filter state separately, keyed by effect id, so editing a
+/// parameter never resets the signal. Every effect also has a wet/dry mix
+/// for parallel processing without a return bus, with the dry signal delayed
+/// by the effect's latency so the two line up. An effect can also match its
+/// processed signal to the level going in, see [`super::gain_match`], so
+/// bypassing it compares the processing rather than the loudness change.
+/// Single effects or whole chains
+/// are copied between tracks through the OS clipboard as JSON, like clips.
+/// Effects of the `Plugin` kind name a third-party plugin, see
+/// [`super::plugins`]; they keep its settings and pass audio through, as
+/// plugins can't be loaded yet. Effects this build can't read load as
+/// placeholders that pass audio through and save their settings back
+/// unchanged. The
+/// convolution reverb's impulse response is loaded off the audio thread and
+/// attached to its state, see [`EffectStates::attach_impulse`].
+use super::audio_types::{AudioAssetData, SampleTime, Track, TrackId};
+use super::convolution::{Convolver, ImpulseResponse};
+use super::gain_match::GainMatch;
+use super::loudness::Biquad;
+use super::plugins::PluginSlot;
+use super::profiling::Timings;
+use super::quick_filters::QuickFilterState;
+use super::smoothing::GainRamp;
+use anyhow::{Context as AnyhowContext, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+pub type EffectId = uuid::Uuid;
+
+/// Identifies clipboard text holding effects
+pub const EFFECT_CLIPBOARD_FORMAT: &str = "pulsar-daw/effects";
+
+/// Effect clipboard version. Bump when the effect schema changes incompatibly.
+pub const EFFECT_CLIPBOARD_VERSION: u32 = 1;
+
+/// Range and step of one effect parameter, for editing and automation
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ParamInfo {
+    pub name: &'static str,
+    pub min: f32,
+    pub max: f32,
+    /// Amount one nudge in the inspector changes the value by
+    pub step: f32,
+    pub unit: &'static str,
+}
+
+const GAIN_PARAMS: [ParamInfo; 1] = [ParamInfo { name: "Gain", min: -48.0, max: 24.0, step: 0.5, unit: "dB" }];
+
+const REVERB_PARAMS: [ParamInfo; 1] = [ParamInfo { name: "Level", min: -36.0, max: 12.0, step: 0.5, unit: "dB" }];
+
+const EQ_PARAMS: [ParamInfo; 7] = [
+    ParamInfo { name: "Low Freq", min: 20.0, max: 1000.0, step: 10.0, unit: "Hz" },
+    ParamInfo { name: "Low Gain", min: -18.0, max: 18.0, step: 0.5, unit: "dB" },
+    ParamInfo { name: "Mid Freq", min: 100.0, max: 10000.0, step: 50.0, unit: "Hz" },
+    ParamInfo { name: "Mid Gain", min: -18.0, max: 18.0, step: 0.5, unit: "dB" },
+    ParamInfo { name: "Mid Q", min: 0.1, max: 10.0, step: 0.1, unit: "" },
+    ParamInfo { name: "High Freq", min: 1000.0, max: 20000.0, step: 100.0, unit: "Hz" },
+    ParamInfo { name: "High Gain", min: -18.0, max: 18.0, step: 0.5, unit: "dB" },
+];
+
+/// Three-band EQ: low shelf, one peaking band and high shelf
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct EqSettings {
+    pub low_freq: f32,
+    pub low_gain_db: f32,
+    pub mid_freq: f32,
+    pub mid_gain_db: f32,
+    pub mid_q: f32,
+    pub high_freq: f32,
+    pub high_gain_db: f32,
+}
+
+impl Default for EqSettings {
+    fn default() -> Self {
+        Self {
+            low_freq: 120.0,
+            low_gain_db: 0.0,
+            mid_freq: 1000.0,
+            mid_gain_db: 0.0,
+            mid_q: 1.0,
+            high_freq: 8000.0,
+            high_gain_db: 0.0,
+        }
+    }
+}
+
+/// The processing an effect does and its parameters
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum EffectKind {
+    Gain { gain_db: f32 },
+    Eq(EqSettings),
+    /// Convolves the signal with the impulse response in the effect's
+    /// `impulse_response` file, scaled by `level_db`
+    ConvolutionReverb { level_db: f32 },
+    /// Stands for the external plugin in the effect's `plugin` slot, whose
+    /// parameters are saved there rather than in this list. Passes audio
+    /// through, as plugins can't be loaded yet.
+    Plugin,
+    /// Placeholder for an effect this build can't load, see
+    /// [`Effect::unavailable`]. Passes audio through.
+    Missing,
+}
+
+impl EffectKind {
+    /// Every kind of effect at its neutral settings, for the add menu
+    pub fn all() -> [EffectKind; 3] {
+        [
+            EffectKind::Gain { gain_db: 0.0 },
+            EffectKind::Eq(EqSettings::default()),
+            EffectKind::ConvolutionReverb { level_db: 0.0 },
+        ]
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            EffectKind::Gain { .. } => "Gain",
+            EffectKind::Eq(_) => "EQ",
+            EffectKind::ConvolutionReverb { .. } => "Convolution Reverb",
+            EffectKind::Plugin => "Plugin",
+            EffectKind::Missing => "Missing Effect",
+        }
+    }
+
+    /// Samples the processed signal lags the input by. The dry side of the
+    /// mix is held back by as much. None of the built-in effects lag, the
+    /// convolution reverb included; a plugin reports its own.
+    pub fn latency(&self) -> usize {
+        match self {
+            EffectKind::Gain { .. } | EffectKind::Eq(_) | EffectKind::ConvolutionReverb { .. } | EffectKind::Plugin | EffectKind::Missing => 0,
+        }
+    }
+
+    /// Wet share a new effect of this kind starts at. A reverb is usually
+    /// blended in rather than replacing the signal.
+    pub fn default_mix(&self) -> f32 {
+        match self {
+            EffectKind::ConvolutionReverb { .. } => 0.3,
+            _ => 1.0,
+        }
+    }
+
+    pub fn same_type(&self, other: &EffectKind) -> bool {
+        std::mem::discriminant(self) == std::mem::discriminant(other)
+    }
+
+    pub fn params(&self) -> &'static [ParamInfo] {
+        match self {
+            EffectKind::Gain { .. } => &GAIN_PARAMS,
+            EffectKind::Eq(_) => &EQ_PARAMS,
+            EffectKind::ConvolutionReverb { .. } => &REVERB_PARAMS,
+            EffectKind::Plugin | EffectKind::Missing => &[],
+        }
+    }
+
+    pub fn param(&self, index: usize) -> Option<f32> {
+        match self {
+            EffectKind::Gain { gain_db } => (index == 0).then_some(*gain_db),
+            EffectKind::ConvolutionReverb { level_db } => (index == 0).then_some(*level_db),
+            EffectKind::Eq(eq) => [
+                eq.low_freq,
+                eq.low_gain_db,
+                eq.mid_freq,
+                eq.mid_gain_db,
+                eq.mid_q,
+                eq.high_freq,
+                eq.high_gain_db,
+            ]
+            .get(index)
+            .copied(),
+            EffectKind::Plugin | EffectKind::Missing => None,
+        }
+    }
+
+    /// Set a parameter, clamped to its range. Unknown indices are ignored.
+    pub fn set_param(&mut self, index: usize, value: f32) {
+        let Some(info) = self.params().get(index) else {
+            return;
+        };
+        let value = value.clamp(info.min, info.max);
+        match self {
+            EffectKind::Gain { gain_db } | EffectKind::ConvolutionReverb { level_db: gain_db } => *gain_db = value,
+            EffectKind::Eq(eq) => {
+                let field = match index {
+                    0 => &mut eq.low_freq,
+                    1 => &mut eq.low_gain_db,
+                    2 => &mut eq.mid_freq,
+                    3 => &mut eq.mid_gain_db,
+                    4 => &mut eq.mid_q,
+                    5 => &mut eq.high_freq,
+                    _ => &mut eq.high_gain_db,
+                };
+                *field = value;
+            }
+            EffectKind::Plugin | EffectKind::Missing => {}
+        }
+    }
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+fn default_mix() -> f32 {
+    1.0
+}
+
+/// One insert effect on a track
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(from = "StoredEffect", into = "StoredEffect")]
+pub struct Effect {
+    pub id: EffectId,
+    /// Bypassed effects pass the signal through untouched
+    pub enabled: bool,
+    /// Share of the processed signal against the unprocessed one, from 0
+    /// (dry) to 1 (fully wet), on top of any mix the effect has itself
+    pub mix: f32,
+    /// Scale the processed signal back to the level going in
+    pub gain_match: bool,
+    pub kind: EffectKind,
+    /// The external plugin behind a `Plugin` effect
+    pub plugin: Option<PluginSlot>,
+    /// Audio file holding the impulse response of a `ConvolutionReverb`,
+    /// resampled to the project rate as it loads
+    pub impulse_response: Option<PathBuf>,
+    /// Saved settings of a `Missing` effect, written back as they were read
+    pub unavailable: Option<UnavailableEffect>,
+}
+
+/// What was saved for an effect that couldn't be loaded
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnavailableEffect {
+    pub kind: serde_json::Value,
+    pub plugin: Option<serde_json::Value>,
+    /// Why it couldn't be loaded, for the inspector
+    pub reason: String,
+}
+
+/// An effect as saved, with its kind and plugin left as JSON until they're
+/// known to be readable
+#[derive(Serialize, Deserialize)]
+struct StoredEffect {
+    id: EffectId,
+    #[serde(default = "default_enabled")]
+    enabled: bool,
+    #[serde(default = "default_mix")]
+    mix: f32,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    gain_match: bool,
+    kind: serde_json::Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    plugin: Option<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    impulse_response: Option<PathBuf>,
+}
+
+impl From<StoredEffect> for Effect {
+    fn from(stored: StoredEffect) -> Self {
+        let StoredEffect { id, enabled, mix, gain_match, kind, plugin, impulse_response } = stored;
+        let parsed_kind = serde_json::from_value::<EffectKind>(kind.clone());
+        let parsed_plugin = plugin.clone().map(serde_json::from_value::<PluginSlot>).transpose();
+        let reason = match (parsed_kind, parsed_plugin) {
+            (Ok(kind), Ok(plugin)) => return Self { id, enabled, mix, gain_match, kind, plugin, impulse_response, unavailable: None },
+            (Err(e), _) => {
+                let name = kind.get("type").and_then(|t| t.as_str()).unwrap_or("unknown");
+                format!("The '{}' effect isn't available in this version ({})", name, e)
+            }
+            (Ok(_), Err(e)) => format!("The plugin settings can't be read ({})", e),
+        };
+        let unavailable = Some(UnavailableEffect { kind, plugin, reason });
+        Self { id, enabled, mix, gain_match, kind: EffectKind::Missing, plugin: None, impulse_response, unavailable }
+    }
+}
+
+impl From<Effect> for StoredEffect {
+    fn from(effect: Effect) -> Self {
+        let (kind, plugin) = match effect.unavailable {
+            Some(unavailable) => (unavailable.kind, unavailable.plugin),
+            None => (
+                serde_json::to_value(effect.kind).unwrap_or_default(),
+                effect.plugin.and_then(|slot| serde_json::to_value(slot).ok()),
+            ),
+        };
+        Self {
+            id: effect.id,
+            enabled: effect.enabled,
+            mix: effect.mix,
+            gain_match: effect.gain_match,
+            kind,
+            plugin,
+            impulse_response: effect.impulse_response,
+        }
+    }
+}
+
+impl Effect {
+    pub fn new(kind: EffectKind) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4(),
+            enabled: true,
+            mix: kind.default_mix(),
+            gain_match: false,
+            kind,
+            plugin: None,
+            impulse_response: None,
+            unavailable: None,
+        }
+    }
+
+    /// An effect backed by the plugin in `slot`
+    pub fn with_plugin(slot: PluginSlot) -> Self {
+        Self { plugin: Some(slot), ..Self::new(EffectKind::Plugin) }
+    }
+
+    /// Name shown in the chain: the plugin's for plugin effects, and the
+    /// saved type of a missing effect
+    pub fn label(&self) -> &str {
+        let missing_type = self.unavailable.as_ref().and_then(|u| u.kind.get("type")).and_then(|t| t.as_str());
+        match (&self.plugin, missing_type) {
+            (Some(slot), _) if self.kind == EffectKind::Plugin => &slot.name,
+            (_, Some(name)) => name,
+            _ => self.kind.label(),
+        }
+    }
+
+    /// Take over another effect's parameters, mix and gain match, keeping this effect's
+    /// id and bypass state. Fails if the two are different kinds of effect.
+    pub fn paste_parameters(&mut self, source: &Effect) -> Result<()> {
+        let same_plugin = self.plugin.as_ref().map(|p| (&p.plugin_id, p.format))
+            == source.plugin.as_ref().map(|p| (&p.plugin_id, p.format));
+        let missing = self.kind == EffectKind::Missing || source.kind == EffectKind::Missing;
+        if !self.kind.same_type(&source.kind) || !same_plugin || missing {
+            anyhow::bail!(
+                "Can't paste {} parameters onto {}",
+                source.label(),
+                self.label()
+            );
+        }
+        self.kind = source.kind;
+        self.plugin = source.plugin.clone();
+        self.impulse_response = source.impulse_response.clone();
+        self.mix = source.mix;
+        self.gain_match = source.gain_match;
+        Ok(())
+    }
+}
+
+/// Versioned set of copied effects, in chain order
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EffectClipboard {
+    pub format: String,
+    pub version: u32,
+    pub effects: Vec<Effect>,
+}
+
+impl EffectClipboard {
+    pub fn from_effects(effects: Vec<Effect>) -> Self {
+        Self {
+            format: EFFECT_CLIPBOARD_FORMAT.to_string(),
+            version: EFFECT_CLIPBOARD_VERSION,
+            effects,
+        }
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self).context("Failed to serialize effects")
+    }
+
+    pub fn from_json(text: &str) -> Result<Self> {
+        let value: serde_json::Value =
+            serde_json::from_str(text).context("Clipboard does not contain JSON")?;
+
+        let format = value.get("format").and_then(|v| v.as_str()).unwrap_or_default();
+        if format != EFFECT_CLIPBOARD_FORMAT {
+            anyhow::bail!("Clipboard does not contain DAW effects");
+        }
+
+        let version = value.get("version").and_then(|v| v.as_u64()).unwrap_or(0) as u32;
+        if version > EFFECT_CLIPBOARD_VERSION {
+            anyhow::bail!(
+                "Clipboard effects are version {}, newer than supported version {}",
+                version,
+                EFFECT_CLIPBOARD_VERSION
+            );
+        }
+        serde_json::from_value(value).context("Failed to parse clipboard effects")
+    }
+
+    /// Copies of the effects with fresh ids, ready to insert into a chain
+    pub fn instantiate(&self) -> Vec<Effect> {
+        self.effects
+            .iter()
+            .map(|effect| Effect { id: uuid::Uuid::new_v4(), ..effect.clone() })
+            .collect()
+    }
+}
+
+/// RBJ cookbook shelf and peaking filters
+fn db_gain(gain_db: f32) -> f64 {
+    10.0_f64.powf(gain_db as f64 / 40.0)
+}
+
+fn low_shelf(freq: f32, gain_db: f32, sample_rate: f64) -> ([f64; 3], [f64; 2]) {
+    let a = db_gain(gain_db);
+    let w0 = 2.0 * std::f64::consts::PI * freq as f64 / sample_rate;
+    let (sin, cos) = w0.sin_cos();
+    let alpha = sin / 2.0 * std::f64::consts::SQRT_2;
+    let beta = 2.0 * a.sqrt() * alpha;
+    let a0 = (a + 1.0) + (a - 1.0) * cos + beta;
+    (
+        [
+            a * ((a + 1.0) - (a - 1.0) * cos + beta) / a0,
+            2.0 * a * ((a - 1.0) - (a + 1.0) * cos) / a0,
+            a * ((a + 1.0) - (a - 1.0) * cos - beta) / a0,
+        ],
+        [-2.0 * ((a - 1.0) + (a + 1.0) * cos) / a0, ((a + 1.0) + (a - 1.0) * cos - beta) / a0],
+    )
+}
+
+fn high_shelf(freq: f32, gain_db: f32, sample_rate: f64) -> ([f64; 3], [f64; 2]) {
+    let a = db_gain(gain_db);
+    let w0 = 2.0 * std::f64::consts::PI * freq as f64 / sample_rate;
+    let (sin, cos) = w0.sin_cos();
+    let alpha = sin / 2.0 * std::f64::consts::SQRT_2;
+    let beta = 2.0 * a.sqrt() * alpha;
+    let a0 = (a + 1.0) - (a - 1.0) * cos + beta;
+    (
+        [
+            a * ((a + 1.0) + (a - 1.0) * cos + beta) / a0,
+            -2.0 * a * ((a - 1.0) + (a + 1.0) * cos) / a0,
+            a * ((a + 1.0) + (a - 1.0) * cos - beta) / a0,
+        ],
+        [2.0 * ((a - 1.0) - (a + 1.0) * cos) / a0, ((a + 1.0) - (a - 1.0) * cos - beta) / a0],
+    )
+}
+
+fn peaking(freq: f32, gain_db: f32, q: f32, sample_rate: f64) -> ([f64; 3], [f64; 2]) {
+    let a = db_gain(gain_db);
+    let w0 = 2.0 * std::f64::consts::PI * freq as f64 / sample_rate;
+    let (sin, cos) = w0.sin_cos();
+    let alpha = sin / (2.0 * q.max(0.01) as f64);
+    let a0 = 1.0 + alpha / a;
+    (
+        [(1.0 + alpha * a) / a0, -2.0 * cos / a0, (1.0 - alpha * a) / a0],
+        [-2.0 * cos / a0, (1.0 - alpha / a) / a0],
+    )
+}
+
+fn eq_coefficients(eq: &EqSettings, sample_rate: f64) -> [([f64; 3], [f64; 2]); 3] {
+    // Keep the bands below Nyquist at any project rate
+    let nyquist = (sample_rate / 2.0) as f32 * 0.95;
+    [
+        low_shelf(eq.low_freq.min(nyquist), eq.low_gain_db, sample_rate),
+        peaking(eq.mid_freq.min(nyquist), eq.mid_gain_db, eq.mid_q, sample_rate),
+        high_shelf(eq.high_freq.min(nyquist), eq.high_gain_db, sample_rate),
+    ]
+}
+
+/// Running state of one effect
+#[derive(Clone)]
+enum EffectState {
+    Gain,
+    /// Coefficients worked out for `settings` at `sample_rate`
+    Eq { settings: EqSettings, sample_rate: f32, bands: Box<[[Biquad; 3]; 2]> },
+    /// One convolver per channel
+    Convolution(Box<[Convolver; 2]>),
+    Plugin,
+    Missing,
+}
+
+impl EffectState {
+    /// `impulse` is the response attached to a convolution reverb. Without
+    /// one it convolves with a unit impulse, which leaves the signal as it was.
+    fn new(kind: &EffectKind, impulse: Option<&Arc<ImpulseResponse>>, sample_rate: f32) -> Self {
+        match kind {
+            EffectKind::Gain { .. } => EffectState::Gain,
+            EffectKind::Eq(eq) => {
+                let bands = eq_coefficients(eq, sample_rate as f64).map(|(b, a)| Biquad::new(b, a));
+                EffectState::Eq { settings: *eq, sample_rate, bands: Box::new([bands, bands]) }
+            }
+            EffectKind::ConvolutionReverb { .. } => {
+                Self::convolution(impulse.cloned().unwrap_or_else(|| Arc::new(ImpulseResponse::new(&[1.0], 1))))
+            }
+            EffectKind::Plugin => EffectState::Plugin,
+            EffectKind::Missing => EffectState::Missing,
+        }
+    }
+
+    fn convolution(impulse: Arc<ImpulseResponse>) -> Self {
+        EffectState::Convolution(Box::new([Convolver::new(impulse.clone(), 0), Convolver::new(impulse, 1)]))
+    }
+
+    fn process(&mut self, kind: &EffectKind, sample_rate: f32, left: &mut [f32], right: &mut [f32]) {
+        match (self, kind) {
+            (EffectState::Gain, EffectKind::Gain { gain_db }) => {
+                let gain = 10.0_f32.powf(gain_db / 20.0);
+                for sample in left.iter_mut().chain(right.iter_mut()) {
+                    *sample *= gain;
+                }
+            }
+            (EffectState::Eq { settings, sample_rate: rate, bands }, EffectKind::Eq(eq)) => {
+                if settings != eq || *rate != sample_rate {
+                    let coefficients = eq_coefficients(eq, sample_rate as f64);
+                    for channel in bands.iter_mut() {
+                        for (band, (b, a)) in channel.iter_mut().zip(coefficients) {
+                            band.set_coefficients(b, a);
+                        }
+                    }
+                    *settings = *eq;
+                    *rate = sample_rate;
+                }
+                for (channel, samples) in bands.iter_mut().zip([left, right]) {
+                    for sample in samples.iter_mut() {
+                        let mut value = *sample as f64;
+                        for band in channel.iter_mut() {
+                            value = band.process(value);
+                        }
+                        *sample = value as f32;
+                    }
+                }
+            }
+            (EffectState::Convolution(convolvers), EffectKind::ConvolutionReverb { level_db }) => {
+                let gain = 10.0_f32.powf(level_db / 20.0);
+                let [left_conv, right_conv] = &mut **convolvers;
+                left_conv.process(left, gain);
+                right_conv.process(right, gain);
+            }
+            (EffectState::Plugin, EffectKind::Plugin) | (EffectState::Missing, EffectKind::Missing) => {}
+            // The effect was swapped for another kind under the same id
+            (state, kind) => {
+                *state = EffectState::new(kind, None, sample_rate);
+                state.process(kind, sample_rate, left, right);
+            }
+        }
+    }
+}
+
+/// Delay line that holds the dry signal back by an effect's latency
+#[derive(Clone, Default)]
+struct DryDelay {
+    lines: [Vec<f32>; 2],
+    position: usize,
+}
+
+impl DryDelay {
+    fn apply(&mut self, latency: usize, left: &mut [f32], right: &mut [f32]) {
+        if latency == 0 {
+            return;
+        }
+        if self.lines[0].len() != latency {
+            self.lines = [vec![0.0; latency], vec![0.0; latency]];
+            self.position = 0;
+        }
+        for i in 0..left.len() {
+            let slot = self.position;
+            std::mem::swap(&mut left[i], &mut self.lines[0][slot]);
+            std::mem::swap(&mut right[i], &mut self.lines[1][slot]);
+            self.position = (slot + 1) % latency;
+        }
+    }
+}
+
+/// Running state of every effect in the graph, keyed by effect id
+#[derive(Default)]
+pub struct EffectStates {
+    states: HashMap<EffectId, EffectState>,
+    /// Each effect's wet share, ramped so bypassing one, bringing it back or
+    /// moving its mix crossfades rather than clicks
+    mixes: HashMap<EffectId, GainRamp>,
+    /// Dry signal of effects with latency, kept running while they're fully
+    /// wet so turning the mix down doesn't replay stale samples
+    dry_delays: HashMap<EffectId, DryDelay>,
+    /// Level followers of effects with gain match on
+    gain_matches: HashMap<EffectId, GainMatch>,
+    /// Impulse responses of convolution reverbs and the loaded files they
+    /// were transformed from
+    impulses: HashMap<EffectId, (Arc<AudioAssetData>, Arc<ImpulseResponse>)>,
+    /// Length of those crossfades, zero to switch instantly
+    ramp_samples: usize,
+    /// Time each effect takes per buffer, measured in profiling mode
+    timings: Timings<EffectId>,
+    /// Each track's quick high-pass and low-pass, ahead of its chain
+    quick_filters: HashMap<TrackId, QuickFilterState>,
+}
+
+/// Effect state put aside by [`EffectStates::save`]
+#[derive(Default)]
+pub struct SavedEffectStates {
+    states: HashMap<EffectId, EffectState>,
+    mixes: HashMap<EffectId, GainRamp>,
+    dry_delays: HashMap<EffectId, DryDelay>,
+    gain_matches: HashMap<EffectId, GainMatch>,
+    quick_filters: HashMap<TrackId, QuickFilterState>,
+}
+
+impl EffectStates {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_ramp_samples(&mut self, samples: usize) {
+        self.ramp_samples = samples;
+    }
+
+    pub fn timings(&self) -> &Timings<EffectId> {
+        &self.timings
+    }
+
+    pub fn timings_mut(&mut self) -> &mut Timings<EffectId> {
+        &mut self.timings
+    }
+
+    /// Give a convolution reverb the response in `source`, replacing any
+    /// earlier one. The response is transformed and the convolvers built
+    /// here rather than on the audio thread, as a long response needs a lot
+    /// of history.
+    pub fn attach_impulse(&mut self, effect: &Effect, source: Arc<AudioAssetData>) {
+        let impulse = Arc::new(ImpulseResponse::new(&source.samples, source.asset_ref.channels));
+        self.states.insert(effect.id, EffectState::convolution(impulse.clone()));
+        self.impulses.insert(effect.id, (source, impulse));
+    }
+
+    /// Go back to passing the signal through a reverb whose file was removed
+    pub fn detach_impulse(&mut self, effect_id: EffectId) {
+        if self.impulses.remove(&effect_id).is_some() {
+            self.states.remove(&effect_id);
+        }
+    }
+
+    /// Loaded file the response attached to an effect came from
+    pub fn impulse_source(&self, effect_id: EffectId) -> Option<&Arc<AudioAssetData>> {
+        self.impulses.get(&effect_id).map(|(source, _)| source)
+    }
+
+    /// Run a chain over a block at `sample_rate` in order, skipping bypassed
+    /// effects
+    pub fn process(&mut self, chain: &[Effect], sample_rate: f32, left: &mut [f32], right: &mut [f32]) {
+        self.process_automated(chain, |_| None, sample_rate, left, right);
+    }
+
+    /// Run a chain over a block with each effect's mix taken from
+    /// `automated_mix`, by chain index, where it has a value for the block.
+    /// Automated mixes ramp across the block to the next value.
+    pub fn process_automated(
+        &mut self,
+        chain: &[Effect],
+        automated_mix: impl Fn(usize) -> Option<f32>,
+        sample_rate: f32,
+        left: &mut [f32],
+        right: &mut [f32],
+    ) {
+        for (index, effect) in chain.iter().enumerate() {
+            let automated = automated_mix(index);
+            let target = if effect.enabled { automated.unwrap_or(effect.mix).clamp(0.0, 1.0) } else { 0.0 };
+            let ramp = if automated.is_some() && effect.enabled { left.len() } else { self.ramp_samples };
+            let mix = self.mixes.entry(effect.id).or_insert_with(|| GainRamp::new(target));
+            mix.set_target(target, ramp);
+            let latency = effect.kind.latency();
+            if mix.is_silent() {
+                self.dry_delays.remove(&effect.id);
+                continue;
+            }
+            // Only the wet path is matched, so the mix still blends against
+            // the untouched dry signal
+            let mut gain_match = if effect.gain_match {
+                let matcher = self.gain_matches.entry(effect.id).or_insert_with(|| GainMatch::new(sample_rate));
+                matcher.measure_input(left, right);
+                Some(matcher)
+            } else {
+                self.gain_matches.remove(&effect.id);
+                None
+            };
+            let started = self.timings.start();
+            let state = self.states
+                .entry(effect.id)
+                .or_insert_with(|| EffectState::new(&effect.kind, self.impulses.get(&effect.id).map(|(_, impulse)| impulse), sample_rate));
+            if mix.is_settled() && mix.value() == 1.0 && latency == 0 {
+                state.process(&effect.kind, sample_rate, left, right);
+                if let Some(matcher) = gain_match.as_mut() {
+                    matcher.apply(left, right);
+                }
+                self.timings.record(effect.id, started);
+                continue;
+            }
+
+            let (mut dry_left, mut dry_right) = (left.to_vec(), right.to_vec());
+            self.dry_delays.entry(effect.id).or_default().apply(latency, &mut dry_left, &mut dry_right);
+            state.process(&effect.kind, sample_rate, left, right);
+            if let Some(matcher) = gain_match.as_mut() {
+                matcher.apply(left, right);
+            }
+            for i in 0..left.len() {
+                let wet = mix.next_gain();
+                left[i] = dry_left[i] + (left[i] - dry_left[i]) * wet;
+                right[i] = dry_right[i] + (right[i] - dry_right[i]) * wet;
+            }
+            self.timings.record(effect.id, started);
+        }
+    }
+
+    /// Run a track's quick filters over a block starting at `start`
+    pub fn process_quick_filters(&mut self, track: &Track, start: SampleTime, sample_rate: f32, left: &mut [f32], right: &mut [f32]) {
+        self.quick_filters.entry(track.id).or_default().process(track, start, sample_rate, left, right);
+    }
+
+    /// Copy the filter, mix and delay state of every effect into `saved`
+    pub fn save(&self, saved: &mut SavedEffectStates) {
+        saved.states.clone_from(&self.states);
+        saved.mixes.clone_from(&self.mixes);
+        saved.dry_delays.clone_from(&self.dry_delays);
+        saved.gain_matches.clone_from(&self.gain_matches);
+        saved.quick_filters.clone_from(&self.quick_filters);
+    }
+
+    /// Go back to the state in `saved`, leaving the state it replaced there
+    pub fn restore(&mut self, saved: &mut SavedEffectStates) {
+        std::mem::swap(&mut self.states, &mut saved.states);
+        std::mem::swap(&mut self.mixes, &mut saved.mixes);
+        std::mem::swap(&mut self.dry_delays, &mut saved.dry_delays);
+        std::mem::swap(&mut self.gain_matches, &mut saved.gain_matches);
+        std::mem::swap(&mut self.quick_filters, &mut saved.quick_filters);
+    }
+
+    /// Drop the quick filter state of tracks that are gone
+    pub fn retain_tracks(&mut self, live: impl Iterator<Item = TrackId>) {
+        let live: Vec<TrackId> = live.collect();
+        self.quick_filters.retain(|id, _| live.contains(id));
+    }
+
+    /// Drop the state of effects that are no longer in any chain
+    pub fn retain<'a>(&mut self, live: impl Iterator<Item = &'a Effect>) {
+        let live: Vec<EffectId> = live.map(|e| e.id).collect();
+        self.states.retain(|id, _| live.contains(id));
+        self.mixes.retain(|id, _| live.contains(id));
+        self.dry_delays.retain(|id, _| live.contains(id));
+        self.gain_matches.retain(|id, _| live.contains(id));
+        self.impulses.retain(|id, _| live.contains(id));
+        self.timings.retain(|id| live.contains(id));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::audio_types::{AudioAssetRef, SAMPLE_RATE};
+
+    fn sine(freq: f32, len: usize) -> Vec<f32> {
+        (0..len)
+            .map(|i| (2.0 * std::f32::consts::PI * freq * i as f32 / SAMPLE_RATE).sin())
+            .collect()
+    }
+
+    fn peak(samples: &[f32]) -> f32 {
+        samples.iter().fold(0.0_f32, |max, s| max.max(s.abs()))
+    }
+
+    #[test]
+    fn test_eq_boosts_only_its_band() {
+        let mut eq = EqSettings::default();
+        let flat = Effect::new(EffectKind::Eq(eq));
+        eq.mid_gain_db = 12.0;
+        let boosted = Effect::new(EffectKind::Eq(eq));
+
+        let mut states = EffectStates::new();
+        for (freq, expected) in [(1000.0, 3.98), (50.0, 1.0)] {
+            let mut left = sine(freq, 48000);
+            let mut right = left.clone();
+            states.process(std::slice::from_ref(&boosted), SAMPLE_RATE, &mut left, &mut right);
+            assert!((peak(&left[24000..]) - expected).abs() < 0.1, "{} Hz: {}", freq, peak(&left[24000..]));
+
+            // A flat EQ passes the signal through
+            let mut left = sine(freq, 48000);
+            let mut right = left.clone();
+            states.process(std::slice::from_ref(&flat), SAMPLE_RATE, &mut left, &mut right);
+            assert!((peak(&left[24000..]) - 1.0).abs() < 0.01);
+        }
+    }
+
+    #[test]
+    fn test_eq_follows_the_sample_rate() {
+        let mut eq = EqSettings::default();
+        eq.mid_gain_db = 12.0;
+        let boosted = Effect::new(EffectKind::Eq(eq));
+
+        // The same state at 48 and then 96 kHz boosts 1 kHz both times
+        let mut states = EffectStates::new();
+        for rate in [SAMPLE_RATE, 2.0 * SAMPLE_RATE] {
+            let mut left: Vec<f32> = (0..rate as usize)
+                .map(|i| (2.0 * std::f32::consts::PI * 1000.0 * i as f32 / rate).sin())
+                .collect();
+            let mut right = left.clone();
+            states.process(std::slice::from_ref(&boosted), rate, &mut left, &mut right);
+            let settled = &left[left.len() / 2..];
+            assert!((peak(settled) - 3.98).abs() < 0.1, "{} Hz: {}", rate, peak(settled));
+        }
+    }
+
+    #[test]
+    fn test_bypass_and_gain() {
+        let mut gain = Effect::new(EffectKind::Gain { gain_db: -6.0 });
+        let mut states = EffectStates::new();
+        let (mut left, mut right) = (vec![1.0; 4], vec![0.5; 4]);
+        states.process(std::slice::from_ref(&gain), SAMPLE_RATE, &mut left, &mut right);
+        assert!((left[0] - 0.501).abs() < 1e-3);
+        assert!((right[0] - 0.251).abs() < 1e-3);
+
+        gain.enabled = false;
+        let (mut left, mut right) = (vec![1.0; 4], vec![0.5; 4]);
+        states.process(std::slice::from_ref(&gain), SAMPLE_RATE, &mut left, &mut right);
+        assert_eq!(left[0], 1.0);
+
+        states.retain(std::iter::empty());
+        assert!(states.states.is_empty());
+    }
+
+    #[test]
+    fn test_bypass_crossfades_over_the_ramp() {
+        let mut gain = Effect::new(EffectKind::Gain { gain_db: -6.0206 });
+        let mut states = EffectStates::new();
+        states.set_ramp_samples(4);
+        let (mut left, mut right) = (vec![1.0; 4], vec![1.0; 4]);
+        states.process(std::slice::from_ref(&gain), SAMPLE_RATE, &mut left, &mut right);
+        assert!((left[0] - 0.5).abs() < 1e-4);
+
+        // Bypassing moves from the processed signal to the dry one
+        gain.enabled = false;
+        let (mut left, mut right) = (vec![1.0; 6], vec![1.0; 6]);
+        states.process(std::slice::from_ref(&gain), SAMPLE_RATE, &mut left, &mut right);
+        let expected = [0.625, 0.75, 0.875, 1.0, 1.0, 1.0];
+        assert!(left.iter().zip(expected).all(|(l, e)| (l - e).abs() < 1e-4), "{:?}", left);
+    }
+
+    #[test]
+    fn test_mix_runs_the_effect_in_parallel() {
+        let mut gain = Effect::new(EffectKind::Gain { gain_db: -120.0 });
+        gain.mix = 0.25;
+        let mut states = EffectStates::new();
+        let (mut left, mut right) = (vec![1.0; 4], vec![1.0; 4]);
+        states.process(std::slice::from_ref(&gain), SAMPLE_RATE, &mut left, &mut right);
+        assert!((left[0] - 0.75).abs() < 1e-4, "{:?}", left);
+
+        // Automation overrides the stored mix, ramping across the block
+        let (mut left, mut right) = (vec![1.0; 4], vec![1.0; 4]);
+        states.process_automated(std::slice::from_ref(&gain), |_| Some(1.0), SAMPLE_RATE, &mut left, &mut right);
+        let expected = [0.5625, 0.375, 0.1875, 0.0];
+        assert!(left.iter().zip(expected).all(|(l, e)| (l - e).abs() < 1e-4), "{:?}", left);
+
+        // The dry side is held back by the effect's latency
+        let mut delay = DryDelay::default();
+        let (mut left, mut right) = (vec![1.0, 2.0, 3.0], vec![4.0, 5.0, 6.0]);
+        delay.apply(2, &mut left, &mut right);
+        assert_eq!((left, right), (vec![0.0, 0.0, 1.0], vec![0.0, 0.0, 4.0]));
+    }
+
+    #[test]
+    fn test_gain_match_brings_the_wet_signal_back_to_the_input_level() {
+        let mut gain = Effect::new(EffectKind::Gain { gain_db: -6.0206 });
+        gain.gain_match = true;
+        let mut states = EffectStates::new();
+        let input = sine(440.0, 512);
+        let mut settled = Vec::new();
+        for _ in 0..400 {
+            let (mut left, mut right) = (input.clone(), input.clone());
+            states.process(std::slice::from_ref(&gain), SAMPLE_RATE, &mut left, &mut right);
+            settled = left;
+        }
+        assert!((peak(&settled) - 1.0).abs() < 0.01, "peak {}", peak(&settled));
+
+        // Half wet, the matched wet side blends with the dry one at full level
+        gain.mix = 0.5;
+        for _ in 0..400 {
+            let (mut left, mut right) = (input.clone(), input.clone());
+            states.process(std::slice::from_ref(&gain), SAMPLE_RATE, &mut left, &mut right);
+            settled = left;
+        }
+        assert!((peak(&settled) - 1.0).abs() < 0.01, "peak {}", peak(&settled));
+
+        // Turning it off forgets the follower and the level drops again
+        gain.gain_match = false;
+        gain.mix = 1.0;
+        let (mut left, mut right) = (input.clone(), input.clone());
+        states.process(std::slice::from_ref(&gain), SAMPLE_RATE, &mut left, &mut right);
+        assert!(states.gain_matches.is_empty());
+        assert!((peak(&left) - 0.5).abs() < 0.01, "peak {}", peak(&left));
+    }
+
+    #[test]
+    fn test_copy_and_paste_effects() {
+        let mut eq = EqSettings::default();
+        eq.low_gain_db = -4.0;
+        let chain = vec![Effect::new(EffectKind::Eq(eq)), Effect::new(EffectKind::Gain { gain_db: 3.0 })];
+        let json = EffectClipboard::from_effects(chain.clone()).to_json().unwrap();
+
+        let pasted = EffectClipboard::from_json(&json).unwrap().instantiate();
+        assert_eq!(pasted.len(), 2);
+        assert_eq!(pasted[0].kind, chain[0].kind);
+        assert_ne!(pasted[0].id, chain[0].id);
+
+        // Parameters only go onto an effect of the same kind
+        let mut target = Effect::new(EffectKind::Eq(EqSettings::default()));
+        target.enabled = false;
+        let id = target.id;
+        target.paste_parameters(&pasted[0]).unwrap();
+        assert_eq!((target.id, target.enabled, target.kind), (id, false, chain[0].kind));
+        let err = target.paste_parameters(&pasted[1]).unwrap_err();
+        assert_eq!(err.to_string(), "Can't paste Gain parameters onto EQ");
+
+        assert!(EffectClipboard::from_json("{\"format\": \"pulsar-daw/clips\"}").is_err());
+    }
+
+    #[test]
+    fn test_plugin_effects() {
+        let slot = PluginSlot {
+            plugin_id: "com.test.scale".into(),
+            format: Default::default(),
+            name: "Scale".into(),
+            params: vec![super::super::plugins::PluginParamValue { id: 0, value: 0.5 }],
+            state: vec![1, 2, 3],
+        };
+        let mut effect = Effect::with_plugin(slot);
+        assert_eq!(effect.label(), "Scale");
+
+        // The plugin's state travels with the effect JSON as an opaque blob
+        let json = EffectClipboard::from_effects(vec![effect.clone()]).to_json().unwrap();
+        assert!(json.contains("\"state\": \"AQID\""), "{}", json);
+        assert_eq!(EffectClipboard::from_json(&json).unwrap().effects[0], effect);
+
+        // Plugins aren't loaded, so the effect passes audio through
+        let mut states = EffectStates::new();
+        let (mut left, mut right) = (vec![1.0; 4], vec![1.0; 4]);
+        states.process(std::slice::from_ref(&effect), SAMPLE_RATE, &mut left, &mut right);
+        assert_eq!((left, right), (vec![1.0; 4], vec![1.0; 4]));
+        effect.plugin.as_mut().unwrap().set_param(0, 2.0);
+        assert_eq!(effect.plugin.as_ref().unwrap().param(0), Some(2.0));
+
+        // Parameters only paste between instances of the same plugin
+        let mut other = Effect::with_plugin(PluginSlot { plugin_id: "com.test.other".into(), ..effect.plugin.clone().unwrap() });
+        assert!(other.paste_parameters(&effect).is_err());
+    }
+
+    #[test]
+    fn test_missing_effects_pass_through_and_keep_their_settings() {
+        let saved = r#"{
+            "id": "6f1c2b8e-8a59-4c39-9d0e-3c4b8f1b2a10",
+            "enabled": true,
+            "mix": 0.5,
+            "kind": {"type": "reverb", "size": 0.8, "damping": {"low": 200}}
+        }"#;
+        let effect: Effect = serde_json::from_str(saved).unwrap();
+        assert_eq!(effect.kind, EffectKind::Missing);
+        assert_eq!(effect.label(), "reverb");
+        assert!(effect.unavailable.as_ref().unwrap().reason.contains("'reverb'"));
+
+        // Audio goes through untouched, whatever the mix
+        let mut states = EffectStates::new();
+        let (mut left, mut right) = (vec![0.25; 4], vec![-0.5; 4]);
+        states.process(std::slice::from_ref(&effect), SAMPLE_RATE, &mut left, &mut right);
+        assert_eq!((left, right), (vec![0.25; 4], vec![-0.5; 4]));
+
+        // Saving writes back exactly what was read, so a build that has the
+        // effect gets it back in full
+        let resaved: serde_json::Value = serde_json::to_value(&effect).unwrap();
+        let original: serde_json::Value = serde_json::from_str(saved).unwrap();
+        assert_eq!(resaved, original);
+
+        // Known effects are unaffected, and nothing pastes onto a placeholder
+        let gain = Effect::new(EffectKind::Gain { gain_db: -6.0 });
+        let reloaded: Effect = serde_json::from_value(serde_json::to_value(&gain).unwrap()).unwrap();
+        assert_eq!(reloaded, gain);
+        let mut placeholder = effect.clone();
+        assert!(placeholder.paste_parameters(&effect).is_err());
+    }
+
+    #[test]
+    fn test_convolution_reverb_lines_up_with_the_dry_signal() {
+        let mut reverb = Effect::new(EffectKind::ConvolutionReverb { level_db: 0.0 });
+        assert_eq!(reverb.mix, 0.3);
+        reverb.mix = 0.5;
+        let input: Vec<f32> = (0..1024).map(|i| if i % 100 == 0 { 1.0 } else { 0.0 }).collect();
+
+        // With no response loaded the wet side is the dry one, with no
+        // latency to hold the dry side back for
+        assert_eq!(reverb.kind.latency(), 0);
+        let mut states = EffectStates::new();
+        let (mut left, mut right) = (input.clone(), input.clone());
+        states.process(std::slice::from_ref(&reverb), SAMPLE_RATE, &mut left, &mut right);
+        for (i, sample) in left.iter().enumerate() {
+            assert!((sample - input[i]).abs() < 1e-4, "frame {}: {}", i, sample);
+        }
+
+        // A response that echoes eight frames later, at half level in the
+        // file and scaled up to unit energy as it loads
+        let mut samples = vec![0.0; 16];
+        samples[8] = 0.5;
+        let source = Arc::new(AudioAssetData {
+            asset_ref: AudioAssetRef {
+                path: "room.wav".into(),
+                sample_rate: SAMPLE_RATE,
+                channels: 1,
+                duration_samples: 16,
+                source: Default::default(),
+                health: Default::default(),
+            },
+            samples: Arc::new(samples),
+        });
+        reverb.mix = 1.0;
+        reverb.impulse_response = Some("room.wav".into());
+        let mut states = EffectStates::new();
+        states.attach_impulse(&reverb, source.clone());
+        assert!(Arc::ptr_eq(states.impulse_source(reverb.id).unwrap(), &source));
+        let (mut left, mut right) = (input.clone(), input.clone());
+        states.process(std::slice::from_ref(&reverb), SAMPLE_RATE, &mut left, &mut right);
+        assert!((right[8] - 1.0).abs() < 1e-4);
+        assert!(right[0].abs() < 1e-4);
+
+        // The file is saved with the effect, and dropped with it
+        let reloaded: Effect = serde_json::from_value(serde_json::to_value(&reverb).unwrap()).unwrap();
+        assert_eq!(reloaded, reverb);
+        states.retain(std::iter::empty());
+        assert!(states.impulse_source(reverb.id).is_none());
+    }
+
+    #[test]
+    fn test_param_access_is_clamped() {
+        let mut kind = EffectKind::Eq(EqSettings::default());
+        kind.set_param(3, 40.0);
+        assert_eq!(kind.param(3), Some(18.0));
+        assert_eq!(kind.param(7), None);
+        assert_eq!(kind.params().len(), 7);
+    }
+}
@@ -0,0 +1,100 @@
+/// Duplicate to fill
+/// Repeats a group of clips back to back up to a target end, for building
+/// beds out of a single bar. Each copy starts on the grid position nearest
+/// the end of the one before it, so copies of a slightly short or long take
+/// stay on the grid, and the last copy is cut short at the target. A lone
+/// clip can loop its own audio up to the target instead.
+use super::audio_types::*;
+
+/// Where each copy of `start..end` begins when repeating it up to `target`.
+/// `snap` moves a position to the grid; a snapped start that doesn't move
+/// past the copy before it falls back to directly after it.
+pub fn fill_starts(start: SampleTime, end: SampleTime, target: SampleTime, snap: impl Fn(SampleTime) -> SampleTime) -> Vec<SampleTime> {
+    let length = end.saturating_sub(start);
+    let mut starts = Vec::new();
+    if length == 0 {
+        return starts;
+    }
+    let mut previous = start;
+    loop {
+        let unsnapped = previous + length;
+        let next = Some(snap(unsnapped)).filter(|&s| s > previous).unwrap_or(unsnapped);
+        if next >= target {
+            return starts;
+        }
+        starts.push(next);
+        previous = next;
+    }
+}
+
+/// New clips repeating `group` after itself up to `target`, as (index of
+/// the source clip in `group`, copy). Copies keep their fades and get new
+/// IDs; one running past the target is cut there.
+pub fn fill_copies(group: &[AudioClip], target: SampleTime, snap: impl Fn(SampleTime) -> SampleTime) -> Vec<(usize, AudioClip)> {
+    let Some(start) = group.iter().map(|c| c.start_time).min() else {
+        return Vec::new();
+    };
+    let end = group.iter().map(|c| c.end_time()).max().unwrap_or(start);
+
+    let mut copies = Vec::new();
+    for copy_start in fill_starts(start, end, target, snap) {
+        for (index, clip) in group.iter().enumerate() {
+            let mut copy = clip.clone();
+            copy.id = uuid::Uuid::new_v4();
+            copy.start_time = clip.start_time - start + copy_start;
+            if copy.start_time >= target {
+                continue;
+            }
+            if copy.end_time() > target {
+                copy.duration = target - copy.start_time;
+                copy.fade_out = copy.fade_out.min(copy.duration);
+                copy.fade_in = copy.fade_in.min(copy.duration - copy.fade_out);
+            }
+            copies.push((index, copy));
+        }
+    }
+    copies
+}
+
+/// Loop a clip's audio until `target`, using its loop points if it has
+/// them and everything it plays otherwise. Returns whether the clip grew.
+pub fn loop_clip_to(clip: &mut AudioClip, target: SampleTime) -> bool {
+    if target <= clip.end_time() {
+        return false;
+    }
+    if clip.loop_points.is_none() {
+        clip.loop_points = Some(ClipLoop { start: clip.offset, end: clip.offset + clip.source_duration() });
+    }
+    clip.duration = target - clip.start_time;
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_copies_follow_the_grid_and_stop_at_the_target() {
+        // A bar of 48000 samples recorded a little short, on a 48000 grid
+        let grid = |t: SampleTime| (t + 24_000) / 48_000 * 48_000;
+        assert_eq!(fill_starts(0, 47_000, 192_000, grid), [48_000, 96_000, 144_000]);
+        assert_eq!(fill_starts(0, 47_000, 192_000, |t| t), [47_000, 94_000, 141_000, 188_000]);
+        // A snap that would go backwards falls back to straight after
+        assert_eq!(fill_starts(0, 10_000, 30_000, |_| 0), [10_000, 20_000]);
+        assert!(fill_starts(0, 0, 30_000, grid).is_empty());
+
+        let mut kick = AudioClip::new("kick.wav".into(), 0, 48_000);
+        kick.fade_out = 500;
+        let hat = AudioClip::new("hat.wav".into(), 24_000, 24_000);
+        let copies = fill_copies(&[kick.clone(), hat], 120_000, grid);
+        let placed: Vec<(usize, SampleTime, SampleTime)> = copies.iter().map(|(i, c)| (*i, c.start_time, c.duration)).collect();
+        // The second kick is cut at the target and the hat after it is left out
+        assert_eq!(placed, [(0, 48_000, 48_000), (1, 72_000, 24_000), (0, 96_000, 24_000)]);
+        assert!(copies.iter().all(|(_, c)| c.id != kick.id));
+        assert_eq!(copies[0].1.fade_out, 500);
+
+        assert!(loop_clip_to(&mut kick, 192_000));
+        assert_eq!((kick.duration, kick.loop_points), (192_000, Some(ClipLoop { start: 0, end: 48_000 })));
+        assert!(!loop_clip_to(&mut kick, 100_000));
+    }
+}
@@ -0,0 +1,146 @@
+/// Automatic gain matching for A/B comparison of an effect
+/// Tracks the RMS level going into and coming out of a processor and scales
+/// its output by the difference, so switching the effect on and off compares
+/// the processing rather than the loudness change.
+
+/// Time constant of the level measurement, long enough to follow loudness
+/// rather than individual transients
+const MEASURE_MS: f32 = 400.0;
+
+/// Time constant of the compensating gain, so it moves smoothly
+const GAIN_MS: f32 = 150.0;
+
+/// Most compensation applied either way
+const MAX_GAIN_DB: f32 = 12.0;
+
+/// Mean square below which a signal counts as silent (-70 dBFS) and the gain holds
+const SILENCE_ENERGY: f32 = 1e-7;
+
+/// Input/output level follower and compensating gain for one stereo processor
+#[derive(Clone)]
+pub struct GainMatch {
+    input_energy: f32,
+    output_energy: f32,
+    gain: f32,
+    measure_coeff: f32,
+    gain_coeff: f32,
+}
+
+impl GainMatch {
+    pub fn new(sample_rate: f32) -> Self {
+        let coeff = |ms: f32| 1.0 - (-1.0 / (ms / 1000.0 * sample_rate)).exp();
+        Self {
+            input_energy: 0.0,
+            output_energy: 0.0,
+            gain: 1.0,
+            measure_coeff: coeff(MEASURE_MS),
+            gain_coeff: coeff(GAIN_MS),
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.input_energy = 0.0;
+        self.output_energy = 0.0;
+        self.gain = 1.0;
+    }
+
+    /// Compensation currently applied, in dB
+    pub fn gain_db(&self) -> f32 {
+        20.0 * self.gain.log10()
+    }
+
+    /// Measure the signal going into the processor. Call before processing.
+    pub fn measure_input(&mut self, left: &[f32], right: &[f32]) {
+        for (l, r) in left.iter().zip(right) {
+            let energy = (l * l + r * r) * 0.5;
+            self.input_energy += (energy - self.input_energy) * self.measure_coeff;
+        }
+    }
+
+    /// Measure the processed signal and scale it towards the input level
+    pub fn apply(&mut self, left: &mut [f32], right: &mut [f32]) {
+        self.apply_linked(left, right, &mut []);
+    }
+
+    /// As [`Self::apply`], scaling the `linked` channels by the same gain
+    /// without measuring them
+    pub fn apply_linked(&mut self, left: &mut [f32], right: &mut [f32], linked: &mut [Vec<f32>]) {
+        let max_gain = 10.0_f32.powf(MAX_GAIN_DB / 20.0);
+        for (i, (l, r)) in left.iter_mut().zip(right.iter_mut()).enumerate() {
+            let energy = (*l * *l + *r * *r) * 0.5;
+            self.output_energy += (energy - self.output_energy) * self.measure_coeff;
+
+            if self.input_energy > SILENCE_ENERGY && self.output_energy > SILENCE_ENERGY {
+                let target = (self.input_energy / self.output_energy).sqrt().clamp(1.0 / max_gain, max_gain);
+                self.gain += (target - self.gain) * self.gain_coeff;
+            }
+            *l *= self.gain;
+            *r *= self.gain;
+            for channel in linked.iter_mut() {
+                if let Some(sample) = channel.get_mut(i) {
+                    *sample *= self.gain;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sine(frames: usize, amplitude: f32) -> Vec<f32> {
+        (0..frames).map(|i| (i as f32 * 0.07).sin() * amplitude).collect()
+    }
+
+    #[test]
+    fn test_matches_output_to_input_level() {
+        let sample_rate = 48000.0;
+        let mut matcher = GainMatch::new(sample_rate);
+        let input = sine(512, 0.5);
+        let mut settled = Vec::new();
+
+        // An "effect" that drops the level by 6 dB
+        for _ in 0..400 {
+            let (mut left, mut right): (Vec<f32>, Vec<f32>) = (input.clone(), input.clone());
+            matcher.measure_input(&left, &right);
+            for s in left.iter_mut().chain(right.iter_mut()) {
+                *s *= 0.5;
+            }
+            matcher.apply(&mut left, &mut right);
+            settled = left;
+        }
+
+        assert!((matcher.gain_db() - 6.02).abs() < 0.1, "gain {}", matcher.gain_db());
+        let peak = settled.iter().fold(0.0_f32, |m, s| m.max(s.abs()));
+        assert!((peak - 0.5).abs() < 0.01, "peak {}", peak);
+    }
+
+    #[test]
+    fn test_gain_moves_smoothly_and_holds_in_silence() {
+        let mut matcher = GainMatch::new(48000.0);
+        let mut left = sine(512, 0.5);
+        let mut right = left.clone();
+        matcher.measure_input(&left, &right);
+        for s in left.iter_mut().chain(right.iter_mut()) {
+            *s *= 0.25;
+        }
+        matcher.apply(&mut left, &mut right);
+        // One buffer in, the gain has only started to rise
+        assert!(matcher.gain_db() > 0.0 && matcher.gain_db() < 3.0);
+
+        // Once both levels have decayed into silence the gain stops moving
+        let mut silence = || {
+            let (mut left, mut right) = (vec![0.0; 512], vec![0.0; 512]);
+            matcher.measure_input(&left, &right);
+            matcher.apply(&mut left, &mut right);
+            assert!(left.iter().all(|s| *s == 0.0));
+            matcher.gain_db()
+        };
+        for _ in 0..200 {
+            silence();
+        }
+        let held = silence();
+        assert_eq!(silence(), held);
+    }
+}
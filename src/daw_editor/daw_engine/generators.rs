@@ -0,0 +1,217 @@
+/// Generated audio
+/// Silence, test tones, noise and sweeps for calibration and spacing. They
+/// are synthesized once when inserted and written to the project's audio
+/// folder, so from then on they are ordinary clips. Levels are peak dBFS, so
+/// a 0 dBFS sine just reaches full scale.
+use super::audio_types::SampleTime;
+
+/// Quietest level a generator can be set to, in dBFS
+pub const MIN_GENERATOR_DB: f32 = -60.0;
+
+/// Noise starts from the same seed every time, so a generated file can be
+/// made again sample for sample
+const NOISE_SEED: u32 = 0x9E37_79B9;
+
+/// What a generated clip contains
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Generator {
+    Silence,
+    Sine { frequency: f32 },
+    WhiteNoise,
+    /// Noise falling 3 dB per octave, equal energy in every octave
+    PinkNoise,
+    /// Logarithmic sine sweep, spending equal time in every octave
+    Sweep { from: f32, to: f32 },
+}
+
+impl Generator {
+    /// Name given to the clip and its file
+    pub fn name(&self) -> String {
+        match self {
+            Generator::Silence => "Silence".to_string(),
+            Generator::Sine { frequency } => format!("Sine {}", format_frequency(*frequency)),
+            Generator::WhiteNoise => "White Noise".to_string(),
+            Generator::PinkNoise => "Pink Noise".to_string(),
+            Generator::Sweep { from, to } => format!("Sweep {}-{}", format_frequency(*from), format_frequency(*to)),
+        }
+    }
+
+    /// Whether the generator makes sound, and so has a level
+    pub fn has_level(&self) -> bool {
+        *self != Generator::Silence
+    }
+}
+
+/// A generator with its length and level
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GeneratorSettings {
+    pub generator: Generator,
+    pub seconds: f32,
+    /// Peak level in dBFS
+    pub level_db: f32,
+}
+
+impl Default for GeneratorSettings {
+    fn default() -> Self {
+        Self {
+            generator: Generator::Sine { frequency: 1000.0 },
+            seconds: 5.0,
+            level_db: -18.0,
+        }
+    }
+}
+
+impl GeneratorSettings {
+    /// Length in frames at `sample_rate`, at least one
+    pub fn frames(&self, sample_rate: f32) -> SampleTime {
+        ((self.seconds.max(0.0) as f64 * sample_rate as f64).round() as SampleTime).max(1)
+    }
+
+    /// Linear peak gain of the level
+    pub fn peak(&self) -> f32 {
+        if self.level_db <= MIN_GENERATOR_DB {
+            0.0
+        } else {
+            10.0_f32.powf(self.level_db.min(0.0) / 20.0)
+        }
+    }
+
+    /// The generated samples at `sample_rate`, one channel
+    pub fn generate(&self, sample_rate: f32) -> Vec<f32> {
+        let frames = self.frames(sample_rate) as usize;
+        let rate = sample_rate as f64;
+        let peak = self.peak();
+        let nyquist = sample_rate / 2.0;
+        let mut noise = Noise::new(NOISE_SEED);
+
+        let mut samples: Vec<f32> = match self.generator {
+            Generator::Silence => vec![0.0; frames],
+            Generator::Sine { frequency } => {
+                let step = std::f64::consts::TAU * frequency.clamp(0.0, nyquist) as f64 / rate;
+                (0..frames).map(|i| (i as f64 * step).sin() as f32).collect()
+            }
+            Generator::WhiteNoise => (0..frames).map(|_| noise.next()).collect(),
+            Generator::PinkNoise => {
+                let mut pink = PinkFilter::default();
+                (0..frames).map(|_| pink.process(noise.next())).collect()
+            }
+            Generator::Sweep { from, to } => {
+                let from = from.clamp(1.0, nyquist) as f64;
+                let to = to.clamp(1.0, nyquist) as f64;
+                let length = frames as f64 / rate;
+                let ratio = (to / from).ln();
+                (0..frames)
+                    .map(|i| {
+                        let t = i as f64 / rate;
+                        // Phase of a sweep whose frequency rises exponentially
+                        // from `from` to `to` over the length
+                        let phase = if ratio.abs() < 1e-9 {
+                            std::f64::consts::TAU * from * t
+                        } else {
+                            std::f64::consts::TAU * from * length / ratio * ((t / length * ratio).exp() - 1.0)
+                        };
+                        phase.sin() as f32
+                    })
+                    .collect()
+            }
+        };
+
+        // Tones already peak at one; noise is scaled so its loudest sample does
+        let loudest = samples.iter().fold(0.0_f32, |max, s| max.max(s.abs()));
+        let scale = match self.generator {
+            Generator::WhiteNoise | Generator::PinkNoise if loudest > 0.0 => peak / loudest,
+            _ => peak,
+        };
+        samples.iter_mut().for_each(|s| *s *= scale);
+        samples
+    }
+}
+
+/// "440 Hz" or "1 kHz"
+fn format_frequency(frequency: f32) -> String {
+    if frequency >= 1000.0 {
+        format!("{} kHz", frequency / 1000.0)
+    } else {
+        format!("{} Hz", frequency)
+    }
+}
+
+/// Xorshift white noise, uniform in -1..1
+struct Noise(u32);
+
+impl Noise {
+    fn new(seed: u32) -> Self {
+        Self(seed.max(1))
+    }
+
+    fn next(&mut self) -> f32 {
+        let mut x = self.0;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.0 = x;
+        x as f32 / u32::MAX as f32 * 2.0 - 1.0
+    }
+}
+
+/// Paul Kellet's pink noise filter, within 0.05 dB of -3 dB per octave
+/// across the audio band
+#[derive(Default)]
+struct PinkFilter {
+    b: [f32; 7],
+}
+
+impl PinkFilter {
+    fn process(&mut self, white: f32) -> f32 {
+        let b = &mut self.b;
+        b[0] = 0.99886 * b[0] + white * 0.0555179;
+        b[1] = 0.99332 * b[1] + white * 0.0750759;
+        b[2] = 0.96900 * b[2] + white * 0.153852;
+        b[3] = 0.86650 * b[3] + white * 0.3104856;
+        b[4] = 0.55000 * b[4] + white * 0.5329522;
+        b[5] = -0.7616 * b[5] - white * 0.0168980;
+        let pink = b[0] + b[1] + b[2] + b[3] + b[4] + b[5] + b[6] + white * 0.5362;
+        b[6] = white * 0.115926;
+        pink
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tone_level_and_length() {
+        let rate = 48000.0;
+        let tone = GeneratorSettings { generator: Generator::Sine { frequency: 1000.0 }, seconds: 0.5, level_db: -6.0 };
+        let samples = tone.generate(rate);
+        assert_eq!(samples.len(), 24000);
+        let peak = samples.iter().fold(0.0_f32, |max, s| max.max(s.abs()));
+        assert!((20.0 * peak.log10() + 6.0).abs() < 0.01);
+        // A 1 kHz sine at 48 kHz repeats every 48 samples
+        assert!((samples[12] - samples[60]).abs() < 1e-4);
+
+        let silence = GeneratorSettings { generator: Generator::Silence, ..tone };
+        assert!(silence.generate(rate).iter().all(|s| *s == 0.0));
+        assert!(GeneratorSettings { level_db: MIN_GENERATOR_DB, ..tone }.generate(rate).iter().all(|s| *s == 0.0));
+        assert_eq!(tone.generator.name(), "Sine 1 kHz");
+    }
+
+    #[test]
+    fn test_noise_and_sweep() {
+        let rate = 48000.0;
+        for generator in [Generator::WhiteNoise, Generator::PinkNoise] {
+            let noise = GeneratorSettings { generator, seconds: 1.0, level_db: -12.0 }.generate(rate);
+            let peak = noise.iter().fold(0.0_f32, |max, s| max.max(s.abs()));
+            assert!((20.0 * peak.log10() + 12.0).abs() < 0.01, "{:?}", generator);
+            // The same seed gives the same file every time
+            assert_eq!(noise, GeneratorSettings { generator, seconds: 1.0, level_db: -12.0 }.generate(rate));
+        }
+
+        // A sweep crosses zero more and more often as it rises
+        let sweep = GeneratorSettings { generator: Generator::Sweep { from: 20.0, to: 20000.0 }, seconds: 2.0, level_db: 0.0 }.generate(rate);
+        let crossings = |part: &[f32]| part.windows(2).filter(|w| w[0] < 0.0 && w[1] >= 0.0).count();
+        let (start, end) = (crossings(&sweep[..4800]), crossings(&sweep[sweep.len() - 4800..]));
+        assert!(start < 10 && end > 1500, "{} then {}", start, end);
+    }
+}
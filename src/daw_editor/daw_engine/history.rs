@@ -0,0 +1,642 @@
+/// Undo/redo history for project edits
+/// Actions store enough state to be reverted and re-applied against a DawProject
+use super::audio_types::*;
+use super::effects::Effect;
+use super::preferences::Fades;
+use super::project::{DawProject, TrackSnapshot};
+
+/// Maximum number of actions kept on the undo stack
+const MAX_HISTORY: usize = 256;
+
+/// A reversible project edit
+#[derive(Debug, Clone)]
+pub enum EditAction {
+    /// One or more tracks were deleted. Snapshots are stored in deletion order
+    /// so a track and all of its children can be restored together.
+    DeleteTracks { snapshots: Vec<TrackSnapshot> },
+    /// New tracks were added, stored in the order they were inserted
+    AddTracks { label: &'static str, snapshots: Vec<TrackSnapshot> },
+    /// Clips were added, e.g. by pasting
+    AddClips { clips: Vec<(TrackId, AudioClip)> },
+    /// Clips were removed. Each entry keeps the clip's index in its track.
+    RemoveClips { clips: Vec<(TrackId, usize, AudioClip)> },
+    /// Clips were moved in time: (track, clip, old start, new start), along
+    /// with fades changed by auto-crossfading: (track, clip, old, new)
+    MoveClips {
+        moves: Vec<(TrackId, ClipId, SampleTime, SampleTime)>,
+        fades: Vec<(TrackId, ClipId, Fades, Fades)>,
+    },
+    /// Clip properties were edited: (track, clip before, clip after)
+    ModifyClips { changes: Vec<(TrackId, AudioClip, AudioClip)> },
+    /// Clip mute was toggled: (track, clip, muted after the edit)
+    MuteClips { changes: Vec<(TrackId, ClipId, bool)> },
+    /// A clip was renamed
+    RenameClip { track_id: TrackId, clip_id: ClipId, before: String, after: String },
+    /// A track's insert effect chain was edited, stored whole before and after
+    EditEffects { label: &'static str, track_id: TrackId, before: Vec<Effect>, after: Vec<Effect> },
+    /// A timeline-wide edit such as inserting silence, stored as the whole
+    /// arrangement before and after
+    Arrangement { label: &'static str, before: ArrangementSnapshot, after: ArrangementSnapshot },
+    /// A track was printed to a new track next to it and then muted.
+    /// `source_muted` is whether it was muted before.
+    BounceTrack { source: TrackId, source_muted: bool, bounced: TrackSnapshot },
+    /// A VCA fader was moved, riding the tracks assigned to it
+    VcaFader { track_id: TrackId, before: f32, after: f32 },
+    /// Tracks were assigned to a VCA or taken off one: (track, before, after)
+    AssignVca { changes: Vec<(TrackId, Option<TrackId>, Option<TrackId>)> },
+}
+
+impl EditAction {
+    /// Human readable label for menus and tooltips
+    pub fn label(&self) -> &'static str {
+        match self {
+            EditAction::DeleteTracks { snapshots } if snapshots.len() > 1 => "Delete Tracks",
+            EditAction::DeleteTracks { .. } => "Delete Track",
+            EditAction::AddClips { .. } => "Add Clips",
+            EditAction::RemoveClips { .. } => "Delete Clips",
+            EditAction::MoveClips { .. } => "Move Clips",
+            EditAction::ModifyClips { .. } => "Edit Clip Properties",
+            EditAction::MuteClips { changes } if changes.iter().all(|(.., muted)| !muted) => "Unmute Clips",
+            EditAction::MuteClips { .. } => "Mute Clips",
+            EditAction::RenameClip { .. } => "Rename Clip",
+            EditAction::AddTracks { label, .. }
+            | EditAction::EditEffects { label, .. }
+            | EditAction::Arrangement { label, .. } => *label,
+            EditAction::BounceTrack { .. } => "Bounce Track",
+            EditAction::VcaFader { .. } => "Move VCA Fader",
+            EditAction::AssignVca { .. } => "Assign to VCA",
+        }
+    }
+
+    /// Revert this action. Returns the IDs of tracks whose state changed.
+    fn undo(&self, project: &mut DawProject) -> Vec<TrackId> {
+        match self {
+            EditAction::DeleteTracks { snapshots } => {
+                let mut touched = Vec::new();
+                // Restore in reverse so original indices stay valid
+                for snapshot in snapshots.iter().rev() {
+                    touched.extend(project.restore_track(snapshot));
+                }
+                touched
+            }
+            EditAction::AddTracks { snapshots, .. } => {
+                for snapshot in snapshots.iter().rev() {
+                    project.delete_track(snapshot.track.id);
+                }
+                snapshots.iter().map(|s| s.track.id).collect()
+            }
+            EditAction::AddClips { clips } => {
+                for (track_id, clip) in clips {
+                    if let Some(track) = project.get_track_mut(*track_id) {
+                        track.clips.retain(|c| c.id != clip.id);
+                    }
+                }
+                clips.iter().map(|(track_id, _)| *track_id).collect()
+            }
+            EditAction::RemoveClips { clips } => {
+                for (track_id, index, clip) in clips.iter().rev() {
+                    if let Some(track) = project.get_track_mut(*track_id) {
+                        let index = (*index).min(track.clips.len());
+                        track.clips.insert(index, clip.clone());
+                    }
+                }
+                clips.iter().map(|(track_id, _, _)| *track_id).collect()
+            }
+            EditAction::MoveClips { moves, fades } => {
+                let mut touched = Self::apply_fades(project, fades.iter().map(|(t, c, from, _)| (*t, *c, *from)));
+                touched.extend(Self::apply_moves(project, moves.iter().map(|(t, c, from, _)| (*t, *c, *from))));
+                touched
+            }
+            EditAction::ModifyClips { changes } => {
+                Self::replace_clips(project, changes.iter().map(|(t, before, _)| (*t, before)))
+            }
+            EditAction::MuteClips { changes } => {
+                Self::apply_mutes(project, changes.iter().map(|(t, c, muted)| (*t, *c, !muted)))
+            }
+            EditAction::RenameClip { track_id, clip_id, before, .. } => {
+                Self::rename_clip(project, *track_id, *clip_id, before)
+            }
+            EditAction::EditEffects { track_id, before, .. } => Self::replace_effects(project, *track_id, before),
+            EditAction::Arrangement { before, .. } => before.restore(project),
+            EditAction::BounceTrack { source, source_muted, bounced } => {
+                project.delete_track(bounced.track.id);
+                Self::mute_track(project, *source, *source_muted);
+                vec![*source, bounced.track.id]
+            }
+            EditAction::VcaFader { track_id, before, .. } => Self::set_volume(project, *track_id, *before),
+            EditAction::AssignVca { changes } => {
+                Self::assign_vca(project, changes.iter().map(|(track, before, _)| (*track, *before)))
+            }
+        }
+    }
+
+    /// Re-apply this action. Returns the IDs of tracks whose state changed.
+    fn redo(&self, project: &mut DawProject) -> Vec<TrackId> {
+        match self {
+            EditAction::DeleteTracks { snapshots } => {
+                let mut touched = Vec::new();
+                for snapshot in snapshots {
+                    if let Some(removed) = project.delete_track(snapshot.track.id) {
+                        touched.extend(removed.incoming_sends.iter().map(|s| s.source_track));
+                        touched.push(removed.track.id);
+                    }
+                }
+                touched
+            }
+            EditAction::AddTracks { snapshots, .. } => {
+                snapshots.iter().flat_map(|snapshot| project.restore_track(snapshot)).collect()
+            }
+            EditAction::AddClips { clips } => {
+                for (track_id, clip) in clips {
+                    if let Some(track) = project.get_track_mut(*track_id) {
+                        track.clips.push(clip.clone());
+                    }
+                }
+                clips.iter().map(|(track_id, _)| *track_id).collect()
+            }
+            EditAction::RemoveClips { clips } => {
+                for (track_id, _, clip) in clips {
+                    if let Some(track) = project.get_track_mut(*track_id) {
+                        track.clips.retain(|c| c.id != clip.id);
+                    }
+                }
+                clips.iter().map(|(track_id, _, _)| *track_id).collect()
+            }
+            EditAction::MoveClips { moves, fades } => {
+                let mut touched = Self::apply_moves(project, moves.iter().map(|(t, c, _, to)| (*t, *c, *to)));
+                touched.extend(Self::apply_fades(project, fades.iter().map(|(t, c, _, to)| (*t, *c, *to))));
+                touched
+            }
+            EditAction::ModifyClips { changes } => {
+                Self::replace_clips(project, changes.iter().map(|(t, _, after)| (*t, after)))
+            }
+            EditAction::MuteClips { changes } => {
+                Self::apply_mutes(project, changes.iter().copied())
+            }
+            EditAction::RenameClip { track_id, clip_id, after, .. } => {
+                Self::rename_clip(project, *track_id, *clip_id, after)
+            }
+            EditAction::EditEffects { track_id, after, .. } => Self::replace_effects(project, *track_id, after),
+            EditAction::Arrangement { after, .. } => after.restore(project),
+            EditAction::BounceTrack { source, bounced, .. } => {
+                let mut touched = project.restore_track(bounced);
+                Self::mute_track(project, *source, true);
+                touched.push(*source);
+                touched
+            }
+            EditAction::VcaFader { track_id, after, .. } => Self::set_volume(project, *track_id, *after),
+            EditAction::AssignVca { changes } => {
+                Self::assign_vca(project, changes.iter().map(|(track, _, after)| (*track, *after)))
+            }
+        }
+    }
+
+    fn set_volume(project: &mut DawProject, track_id: TrackId, volume: f32) -> Vec<TrackId> {
+        match project.get_track_mut(track_id) {
+            Some(track) => {
+                track.volume = volume;
+                vec![track_id]
+            }
+            None => Vec::new(),
+        }
+    }
+
+    fn assign_vca(
+        project: &mut DawProject,
+        assignments: impl Iterator<Item = (TrackId, Option<TrackId>)>,
+    ) -> Vec<TrackId> {
+        let mut touched = Vec::new();
+        for (track_id, vca) in assignments {
+            if let Some(track) = project.get_track_mut(track_id) {
+                track.vca = vca;
+                touched.push(track_id);
+            }
+        }
+        touched
+    }
+
+    fn mute_track(project: &mut DawProject, track_id: TrackId, muted: bool) {
+        if let Some(track) = project.get_track_mut(track_id) {
+            track.muted = muted;
+        }
+    }
+
+    fn apply_moves(
+        project: &mut DawProject,
+        moves: impl Iterator<Item = (TrackId, ClipId, SampleTime)>,
+    ) -> Vec<TrackId> {
+        let mut touched = Vec::new();
+        for (track_id, clip_id, start_time) in moves {
+            if let Some(clip) = project
+                .get_track_mut(track_id)
+                .and_then(|t| t.clips.iter_mut().find(|c| c.id == clip_id))
+            {
+                clip.start_time = start_time;
+                touched.push(track_id);
+            }
+        }
+        touched
+    }
+
+    fn apply_fades(
+        project: &mut DawProject,
+        fades: impl Iterator<Item = (TrackId, ClipId, Fades)>,
+    ) -> Vec<TrackId> {
+        let mut touched = Vec::new();
+        for (track_id, clip_id, fades) in fades {
+            if let Some(clip) = project
+                .get_track_mut(track_id)
+                .and_then(|t| t.clips.iter_mut().find(|c| c.id == clip_id))
+            {
+                fades.apply_to(clip);
+                touched.push(track_id);
+            }
+        }
+        touched
+    }
+
+    fn apply_mutes(
+        project: &mut DawProject,
+        mutes: impl Iterator<Item = (TrackId, ClipId, bool)>,
+    ) -> Vec<TrackId> {
+        let mut touched = Vec::new();
+        for (track_id, clip_id, muted) in mutes {
+            if let Some(clip) = project
+                .get_track_mut(track_id)
+                .and_then(|t| t.clips.iter_mut().find(|c| c.id == clip_id))
+            {
+                clip.muted = muted;
+                touched.push(track_id);
+            }
+        }
+        touched
+    }
+
+    fn rename_clip(project: &mut DawProject, track_id: TrackId, clip_id: ClipId, name: &str) -> Vec<TrackId> {
+        match project.get_track_mut(track_id).and_then(|t| t.clips.iter_mut().find(|c| c.id == clip_id)) {
+            Some(clip) => {
+                clip.name = name.to_string();
+                vec![track_id]
+            }
+            None => Vec::new(),
+        }
+    }
+
+    fn replace_effects(project: &mut DawProject, track_id: TrackId, effects: &[Effect]) -> Vec<TrackId> {
+        match project.get_track_mut(track_id) {
+            Some(track) => {
+                track.effects = effects.to_vec();
+                vec![track_id]
+            }
+            None => Vec::new(),
+        }
+    }
+
+    fn replace_clips<'a>(
+        project: &mut DawProject,
+        clips: impl Iterator<Item = (TrackId, &'a AudioClip)>,
+    ) -> Vec<TrackId> {
+        let mut touched = Vec::new();
+        for (track_id, replacement) in clips {
+            if let Some(clip) = project
+                .get_track_mut(track_id)
+                .and_then(|t| t.clips.iter_mut().find(|c| c.id == replacement.id))
+            {
+                *clip = replacement.clone();
+                touched.push(track_id);
+            }
+        }
+        touched
+    }
+}
+
+/// Clips and automation of every track plus the tempo, markers, regions and
+/// the transport loop and punch ranges, for edits that touch the whole timeline
+#[derive(Debug, Clone)]
+pub struct ArrangementSnapshot {
+    tracks: Vec<(TrackId, Vec<AudioClip>, Vec<AutomationLane>)>,
+    tempo: f32,
+    time_signature: (u32, u32),
+    loop_range: (bool, SampleTime, SampleTime),
+    punch_range: (SampleTime, SampleTime),
+    markers: Vec<Marker>,
+    regions: Vec<Region>,
+}
+
+impl ArrangementSnapshot {
+    pub fn capture(project: &DawProject) -> Self {
+        let transport = &project.transport;
+        Self {
+            tracks: project
+                .tracks
+                .iter()
+                .chain(std::iter::once(&project.master_track))
+                .map(|t| (t.id, t.clips.clone(), t.automation.clone()))
+                .collect(),
+            tempo: transport.tempo,
+            time_signature: (transport.time_signature_numerator, transport.time_signature_denominator),
+            loop_range: (transport.loop_enabled, transport.loop_start, transport.loop_end),
+            punch_range: (transport.punch_in, transport.punch_out),
+            markers: project.markers.clone(),
+            regions: project.regions.clone(),
+        }
+    }
+
+    /// Put the captured state back. Returns the IDs of the restored tracks,
+    /// leaving out the master bus.
+    fn restore(&self, project: &mut DawProject) -> Vec<TrackId> {
+        let mut touched = Vec::new();
+        for (track_id, clips, automation) in &self.tracks {
+            if *track_id == project.master_track.id {
+                project.master_track.clips = clips.clone();
+                project.master_track.automation = automation.clone();
+            } else if let Some(track) = project.get_track_mut(*track_id) {
+                track.clips = clips.clone();
+                track.automation = automation.clone();
+                touched.push(*track_id);
+            }
+        }
+
+        let transport = &mut project.transport;
+        transport.tempo = self.tempo;
+        (transport.time_signature_numerator, transport.time_signature_denominator) = self.time_signature;
+        (transport.loop_enabled, transport.loop_start, transport.loop_end) = self.loop_range;
+        (transport.punch_in, transport.punch_out) = self.punch_range;
+        project.markers = self.markers.clone();
+        project.regions = self.regions.clone();
+        touched
+    }
+}
+
+/// Undo and redo stacks
+#[derive(Debug, Default)]
+pub struct EditHistory {
+    undo_stack: Vec<EditAction>,
+    redo_stack: Vec<EditAction>,
+}
+
+impl EditHistory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a newly performed action. Clears the redo stack.
+    pub fn push(&mut self, action: EditAction) {
+        self.undo_stack.push(action);
+        if self.undo_stack.len() > MAX_HISTORY {
+            self.undo_stack.remove(0);
+        }
+        self.redo_stack.clear();
+    }
+
+    /// Fold a follow-up edit into the most recent action if it is an
+    /// arrangement edit with the same label, so the two undo as one step.
+    /// Returns false, leaving the history alone, if it isn't.
+    pub fn amend_last(&mut self, label: &'static str, after: ArrangementSnapshot) -> bool {
+        match self.undo_stack.last_mut() {
+            Some(EditAction::Arrangement { label: last_label, after: last_after, .. }) if *last_label == label => {
+                *last_after = after;
+                self.redo_stack.clear();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+
+    /// Undo the most recent action. Returns the IDs of tracks whose state changed.
+    pub fn undo(&mut self, project: &mut DawProject) -> Option<Vec<TrackId>> {
+        let action = self.undo_stack.pop()?;
+        let mut touched = action.undo(project);
+        touched.sort();
+        touched.dedup();
+        self.redo_stack.push(action);
+        Some(touched)
+    }
+
+    /// Redo the most recently undone action. Returns the IDs of tracks whose state changed.
+    pub fn redo(&mut self, project: &mut DawProject) -> Option<Vec<TrackId>> {
+        let action = self.redo_stack.pop()?;
+        let mut touched = action.redo(project);
+        touched.sort();
+        touched.dedup();
+        self.undo_stack.push(action);
+        Some(touched)
+    }
+
+    /// Drop all history, e.g. when loading a different project
+    pub fn clear(&mut self) {
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_undo_delete_track_with_sends() {
+        let mut project = DawProject::new("Test");
+
+        let mut reverb = Track::new("Reverb", TrackType::Aux);
+        let mut drums = Track::new("Drums", TrackType::Audio);
+        let bass = Track::new("Bass", TrackType::Audio);
+
+        // Drums feeds the reverb, and the reverb has a send of its own
+        drums.sends[0] = Send {
+            target_track: Some(reverb.id),
+            amount: 0.5,
+            pre_fader: true,
+            enabled: true,
+            follow_mute: true,
+        };
+        reverb.sends[1].amount = 0.25;
+        reverb.clips.push(AudioClip::new("tail.wav".into(), 0, 48000));
+
+        let drums_id = drums.id;
+        let reverb_id = reverb.id;
+        project.add_track(drums);
+        project.add_track(reverb);
+        project.add_track(bass);
+
+        let mut history = EditHistory::new();
+        let snapshot = project.delete_track(reverb_id).unwrap();
+        history.push(EditAction::DeleteTracks { snapshots: vec![snapshot] });
+
+        // Deleting the aux disconnects every send that pointed at it
+        assert_eq!(project.tracks.len(), 2);
+        let drums = project.get_track(drums_id).unwrap();
+        assert_eq!(drums.sends[0].target_track, None);
+        assert!(!drums.sends[0].enabled);
+
+        history.undo(&mut project).unwrap();
+
+        assert_eq!(project.tracks.len(), 3);
+        assert_eq!(project.tracks[1].id, reverb_id);
+        assert_eq!(project.tracks[1].clips.len(), 1);
+        assert_eq!(project.tracks[1].sends[1].amount, 0.25);
+
+        let drums = project.get_track(drums_id).unwrap();
+        assert_eq!(drums.sends[0].target_track, Some(reverb_id));
+        assert_eq!(drums.sends[0].amount, 0.5);
+        assert!(drums.sends[0].pre_fader);
+        assert!(drums.sends[0].enabled);
+
+        history.redo(&mut project).unwrap();
+        assert_eq!(project.tracks.len(), 2);
+        assert!(project.get_track(reverb_id).is_none());
+        assert!(history.can_undo());
+        assert!(!history.can_redo());
+    }
+
+    #[test]
+    fn test_undo_clip_mute() {
+        let mut project = DawProject::new("Test");
+        let mut track = Track::new("Drums", TrackType::Audio);
+        let clip = AudioClip::new("kick.wav".into(), 0, 48000);
+        let clip_id = clip.id;
+        track.clips.push(clip);
+        let track_id = track.id;
+        project.add_track(track);
+
+        let mut history = EditHistory::new();
+        project.get_track_mut(track_id).unwrap().clips[0].muted = true;
+        let action = EditAction::MuteClips { changes: vec![(track_id, clip_id, true)] };
+        assert_eq!(action.label(), "Mute Clips");
+        history.push(action);
+
+        assert_eq!(history.undo(&mut project).unwrap(), vec![track_id]);
+        assert!(!project.get_track(track_id).unwrap().clips[0].muted);
+
+        history.redo(&mut project).unwrap();
+        assert!(project.get_track(track_id).unwrap().clips[0].muted);
+    }
+
+    #[test]
+    fn test_undo_clip_rename() {
+        let mut project = DawProject::new("Test");
+        let mut track = Track::new("Drums", TrackType::Audio);
+        let clip = AudioClip::new("kick.wav".into(), 0, 48000);
+        let clip_id = clip.id;
+        track.clips.push(clip);
+        let track_id = track.id;
+        project.add_track(track);
+
+        let mut history = EditHistory::new();
+        project.get_track_mut(track_id).unwrap().clips[0].name = "Kick Fill".to_string();
+        let rename = EditAction::RenameClip {
+            track_id,
+            clip_id,
+            before: "kick".to_string(),
+            after: "Kick Fill".to_string(),
+        };
+        assert_eq!(rename.label(), "Rename Clip");
+        history.push(rename);
+        history.undo(&mut project).unwrap();
+        assert_eq!(project.get_track(track_id).unwrap().clips[0].name, "kick");
+        history.redo(&mut project).unwrap();
+        assert_eq!(project.get_track(track_id).unwrap().clips[0].display_name(), "Kick Fill");
+    }
+
+    #[test]
+    fn test_undo_add_tracks() {
+        let mut project = DawProject::new("Test");
+        project.add_track(Track::new("Drums", TrackType::Audio));
+        let mut history = EditHistory::new();
+        let snapshots: Vec<TrackSnapshot> = ["Left", "Right"]
+            .into_iter()
+            .map(|name| {
+                let track = Track::new(name, TrackType::Audio);
+                project.tracks.push(track.clone());
+                TrackSnapshot { track, index: project.tracks.len() - 1, incoming_sends: Vec::new() }
+            })
+            .collect();
+        let mut ids: Vec<TrackId> = snapshots.iter().map(|s| s.track.id).collect();
+        ids.sort();
+        let action = EditAction::AddTracks { label: "Split Channels", snapshots };
+        assert_eq!(action.label(), "Split Channels");
+        history.push(action);
+
+        assert_eq!(history.undo(&mut project).unwrap(), ids);
+        assert_eq!(project.tracks.len(), 1);
+        history.redo(&mut project).unwrap();
+        let names: Vec<&str> = project.tracks.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, ["Drums", "Left", "Right"]);
+    }
+
+    #[test]
+    fn test_undo_effect_chain_edit() {
+        use super::super::effects::EffectKind;
+
+        let mut project = DawProject::new("Test");
+        let track = Track::new("Vocal", TrackType::Audio);
+        let track_id = track.id;
+        project.add_track(track);
+
+        let mut history = EditHistory::new();
+        let before = project.get_track(track_id).unwrap().effects.clone();
+        let after = vec![Effect::new(EffectKind::Gain { gain_db: -3.0 })];
+        project.get_track_mut(track_id).unwrap().effects = after.clone();
+        history.push(EditAction::EditEffects { label: "Add Effect", track_id, before, after: after.clone() });
+
+        assert_eq!(history.undo(&mut project).unwrap(), vec![track_id]);
+        assert!(project.get_track(track_id).unwrap().effects.is_empty());
+        history.redo(&mut project).unwrap();
+        assert_eq!(project.get_track(track_id).unwrap().effects, after);
+    }
+
+    #[test]
+    fn test_undo_insert_silence() {
+        let mut project = DawProject::new("Test");
+        let mut track = Track::new("Drums", TrackType::Audio);
+        track.clips.push(AudioClip::new("loop.wav".into(), 0, 48000));
+        let track_id = track.id;
+        project.add_track(track);
+
+        let mut history = EditHistory::new();
+        let before = ArrangementSnapshot::capture(&project);
+        project.insert_silence(24000, 12000, 0).unwrap();
+        let after = ArrangementSnapshot::capture(&project);
+        history.push(EditAction::Arrangement { label: "Insert Silence", before, after });
+        let split_ids: Vec<ClipId> = project.tracks[0].clips.iter().map(|c| c.id).collect();
+
+        assert!(history.undo(&mut project).unwrap().contains(&track_id));
+        let clips = &project.get_track(track_id).unwrap().clips;
+        assert_eq!(clips.len(), 1);
+        assert_eq!(clips[0].duration, 48000);
+
+        // Redo brings back the same split clips, not freshly generated ones
+        history.redo(&mut project).unwrap();
+        let clips = &project.get_track(track_id).unwrap().clips;
+        assert_eq!(clips.iter().map(|c| c.id).collect::<Vec<_>>(), split_ids);
+        assert_eq!(clips[1].start_time, 36000);
+    }
+
+    #[test]
+    fn test_amended_tempo_changes_undo_together() {
+        let mut project = DawProject::new("Test");
+        let mut history = EditHistory::new();
+        let original = project.transport.tempo;
+
+        let before = ArrangementSnapshot::capture(&project);
+        project.change_tempo(100.0).unwrap();
+        history.push(EditAction::Arrangement { label: "Tap Tempo", before, after: ArrangementSnapshot::capture(&project) });
+        project.change_tempo(104.0).unwrap();
+        assert!(history.amend_last("Tap Tempo", ArrangementSnapshot::capture(&project)));
+        assert!(!history.amend_last("Insert Silence", ArrangementSnapshot::capture(&project)));
+
+        history.undo(&mut project).unwrap();
+        assert_eq!(project.transport.tempo, original);
+        assert!(!history.can_undo());
+        history.redo(&mut project).unwrap();
+        assert_eq!(project.transport.tempo, 104.0);
+    }
+}
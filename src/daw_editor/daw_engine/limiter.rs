@@ -0,0 +1,275 @@
+/// Brickwall limiter for the master bus
+/// Lookahead peak limiter with a 4x oversampled true-peak detector, so
+/// inter-sample peaks stay under the ceiling after resampling downstream.
+/// A surround bed is limited along with the stereo mix, every speaker by the
+/// same gain, so its balance holds and it's as safe as the stereo file.
+use super::audio_types::*;
+use super::gain_match::GainMatch;
+use super::true_peak::{TruePeakDetector, TP_DELAY};
+use serde::{Deserialize, Serialize};
+
+/// Lookahead window in samples (~1.3ms at 48kHz)
+const LOOKAHEAD: usize = 64;
+
+/// Total delay applied to the audio so gain changes land ahead of peaks
+const LATENCY: usize = LOOKAHEAD - 1 + TP_DELAY;
+
+/// User-facing limiter settings, stored with the project
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct LimiterSettings {
+    pub enabled: bool,
+    /// Maximum true-peak output level in dBFS
+    pub ceiling_db: Decibels,
+    pub release_ms: f32,
+    /// Match the output loudness to the input so bypassing the limiter
+    /// compares only its processing. Can push peaks above the ceiling.
+    #[serde(default)]
+    pub gain_match: bool,
+}
+
+impl Default for LimiterSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            ceiling_db: -1.0,
+            release_ms: 50.0,
+            gain_match: false,
+        }
+    }
+}
+
+/// Stereo lookahead limiter state
+#[derive(Clone)]
+pub struct MasterLimiter {
+    settings: LimiterSettings,
+    detector: TruePeakDetector,
+    /// Audio delay lines per channel
+    delay: [Vec<f32>; 2],
+    /// Detector and delay lines of the channels limited with the stereo
+    /// pair, rebuilt when their count changes
+    linked_detector: TruePeakDetector,
+    linked_delay: Vec<Vec<f32>>,
+    /// Release-smoothed gain envelope and its last LOOKAHEAD values
+    envelope: f32,
+    envelope_window: Vec<f32>,
+    /// Min-held gain values, averaged to give smooth attacks
+    held_window: Vec<f32>,
+    position: usize,
+    gain_reduction_db: f32,
+    gain_match: GainMatch,
+}
+
+impl MasterLimiter {
+    pub fn new(settings: LimiterSettings) -> Self {
+        Self {
+            settings,
+            detector: TruePeakDetector::new(2),
+            delay: [vec![0.0; LATENCY], vec![0.0; LATENCY]],
+            linked_detector: TruePeakDetector::new(0),
+            linked_delay: Vec::new(),
+            envelope: 1.0,
+            envelope_window: vec![1.0; LOOKAHEAD],
+            held_window: vec![1.0; LOOKAHEAD],
+            position: 0,
+            gain_reduction_db: 0.0,
+            gain_match: GainMatch::new(SAMPLE_RATE),
+        }
+    }
+
+    pub fn settings(&self) -> LimiterSettings {
+        self.settings
+    }
+
+    pub fn set_settings(&mut self, settings: LimiterSettings) {
+        if settings.enabled && !self.settings.enabled {
+            self.reset();
+        } else if settings.gain_match && !self.settings.gain_match {
+            self.gain_match.reset();
+        }
+        self.settings = settings;
+    }
+
+    /// Loudness compensation applied by gain matching, in dB
+    pub fn gain_match_db(&self) -> f32 {
+        if self.settings.enabled && self.settings.gain_match { self.gain_match.gain_db() } else { 0.0 }
+    }
+
+    /// Largest gain reduction applied during the last processed buffer, in dB
+    pub fn gain_reduction_db(&self) -> f32 {
+        self.gain_reduction_db
+    }
+
+    /// Processing latency in samples while enabled
+    pub fn latency(&self) -> usize {
+        if self.settings.enabled { LATENCY } else { 0 }
+    }
+
+    pub fn reset(&mut self) {
+        self.detector.reset();
+        for line in self.delay.iter_mut().chain(&mut self.linked_delay) {
+            line.fill(0.0);
+        }
+        self.linked_detector.reset();
+        self.envelope = 1.0;
+        self.envelope_window.fill(1.0);
+        self.held_window.fill(1.0);
+        self.position = 0;
+        self.gain_reduction_db = 0.0;
+        self.gain_match.reset();
+    }
+
+    /// Limit a stereo buffer in place
+    pub fn process(&mut self, left: &mut [f32], right: &mut [f32]) {
+        self.process_linked(left, right, &mut []);
+    }
+
+    /// Limit a stereo buffer and the `linked` channels with it, such as a
+    /// surround bed, all by one gain and delayed alike. Gain match follows
+    /// the stereo pair.
+    pub fn process_linked(&mut self, left: &mut [f32], right: &mut [f32], linked: &mut [Vec<f32>]) {
+        if !self.settings.enabled {
+            self.gain_reduction_db = 0.0;
+            return;
+        }
+        if self.linked_delay.len() != linked.len() {
+            self.linked_detector = TruePeakDetector::new(linked.len());
+            self.linked_delay = vec![vec![0.0; LATENCY]; linked.len()];
+        }
+
+        if self.settings.gain_match {
+            self.gain_match.measure_input(left, right);
+        }
+
+        let ceiling = 10.0_f32.powf(self.settings.ceiling_db.min(0.0) / 20.0);
+        let release_samples = (self.settings.release_ms.max(1.0) / 1000.0) * SAMPLE_RATE;
+        let release_coeff = 1.0 - (-1.0 / release_samples).exp();
+        let mut min_gain = 1.0_f32;
+
+        let frames = linked.iter().fold(left.len().min(right.len()), |frames, channel| frames.min(channel.len()));
+        for i in 0..frames {
+            let mut peak = self.detector.push(0, left[i]).max(self.detector.push(1, right[i]));
+            for (channel, samples) in linked.iter().enumerate() {
+                peak = peak.max(self.linked_detector.push(channel, samples[i]));
+            }
+
+            // Gain needed for the sample the detector is looking at, with release smoothing
+            let required = if peak > ceiling { ceiling / peak } else { 1.0 };
+            self.envelope = (self.envelope + (1.0 - self.envelope) * release_coeff).min(required);
+
+            // Hold the minimum over the lookahead, then average it. Every average that
+            // covers a peak only contains held values at or below that peak's gain.
+            let slot = self.position % LOOKAHEAD;
+            self.envelope_window[slot] = self.envelope;
+            let held = self.envelope_window.iter().copied().fold(1.0_f32, f32::min);
+            self.held_window[slot] = held;
+            let gain = self.held_window.iter().sum::<f32>() / LOOKAHEAD as f32;
+            min_gain = min_gain.min(gain);
+
+            let delay_slot = self.position % LATENCY;
+            let delayed_left = std::mem::replace(&mut self.delay[0][delay_slot], left[i]);
+            let delayed_right = std::mem::replace(&mut self.delay[1][delay_slot], right[i]);
+            left[i] = delayed_left * gain;
+            right[i] = delayed_right * gain;
+            for (samples, line) in linked.iter_mut().zip(&mut self.linked_delay) {
+                samples[i] = std::mem::replace(&mut line[delay_slot], samples[i]) * gain;
+            }
+
+            self.position = self.position.wrapping_add(1);
+        }
+
+        self.gain_reduction_db = -20.0 * min_gain.max(1e-6).log10();
+
+        if self.settings.gain_match {
+            self.gain_match.apply_linked(left, right, linked);
+        }
+    }
+}
+
+impl Default for MasterLimiter {
+    fn default() -> Self {
+        Self::new(LimiterSettings::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::PI;
+
+    #[test]
+    fn test_quiet_signal_passes_through_delayed() {
+        let mut limiter = MasterLimiter::default();
+        let mut left: Vec<f32> = (0..BUFFER_SIZE).map(|i| if i == 0 { 0.5 } else { 0.0 }).collect();
+        let mut right = left.clone();
+
+        limiter.process(&mut left, &mut right);
+
+        assert!((left[LATENCY] - 0.5).abs() < 1e-6);
+        assert_eq!(limiter.gain_reduction_db(), 0.0);
+    }
+
+    #[test]
+    fn test_catches_inter_sample_peaks() {
+        // A quarter-rate sine at 45 degrees has sample peaks 3dB below its true peak,
+        // so a sample-peak limiter at -1 dBFS would let it through untouched
+        let mut left: Vec<f32> = (0..BUFFER_SIZE * 4)
+            .map(|i| (PI / 2.0 * i as f32 + PI / 4.0).sin())
+            .collect();
+        let mut right = left.clone();
+        let sample_peak = left.iter().fold(0.0_f32, |m, s| m.max(s.abs()));
+        assert!(sample_peak < 0.75);
+
+        let mut limiter = MasterLimiter::default();
+        for (l, r) in left.chunks_mut(BUFFER_SIZE).zip(right.chunks_mut(BUFFER_SIZE)) {
+            limiter.process(l, r);
+        }
+
+        let ceiling = 10.0_f32.powf(-1.0 / 20.0);
+        let settled = &left[BUFFER_SIZE..];
+        let output_peak = settled.iter().fold(0.0_f32, |m, s| m.max(s.abs()));
+        assert!(limiter.gain_reduction_db() > 0.5);
+        assert!(output_peak <= ceiling * sample_peak + 1e-3);
+    }
+
+    #[test]
+    fn test_linked_channels_are_limited_with_the_stereo_pair() {
+        // A hot surround speaker beside a quiet stereo mix
+        let mut left = vec![0.25; BUFFER_SIZE * 4];
+        let mut right = left.clone();
+        let mut bed = vec![vec![0.25; BUFFER_SIZE * 4], vec![2.0; BUFFER_SIZE * 4]];
+        let mut limiter = MasterLimiter::default();
+        limiter.process_linked(&mut left, &mut right, &mut bed);
+
+        // Everything is delayed alike, then held under the ceiling by one gain
+        assert_eq!(bed[1][LATENCY - 1], 0.0);
+        let ceiling = 10.0_f32.powf(-1.0 / 20.0);
+        let settled = BUFFER_SIZE * 3;
+        assert!(bed[1][settled..].iter().all(|s| *s <= ceiling + 1e-3));
+        assert!(limiter.gain_reduction_db() > 6.0);
+        assert!((left[settled] - bed[0][settled]).abs() < 1e-6);
+        assert!((left[settled] * 8.0 - bed[1][settled]).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_gain_match_restores_level_lost_to_limiting() {
+        // A square wave at full scale only loses level to the ceiling
+        let signal: Vec<f32> = (0..BUFFER_SIZE * 200).map(|i| if (i / 50) % 2 == 0 { 1.0 } else { -1.0 }).collect();
+        let run = |gain_match: bool| {
+            let mut limiter = MasterLimiter::new(LimiterSettings { gain_match, ..LimiterSettings::default() });
+            let (mut left, mut right) = (signal.clone(), signal.clone());
+            for (l, r) in left.chunks_mut(BUFFER_SIZE).zip(right.chunks_mut(BUFFER_SIZE)) {
+                limiter.process(l, r);
+            }
+            let tail = &left[left.len() - BUFFER_SIZE * 20..];
+            let rms = (tail.iter().map(|s| s * s).sum::<f32>() / tail.len() as f32).sqrt();
+            (rms, limiter.gain_match_db())
+        };
+
+        let (plain, no_match) = run(false);
+        let (matched, compensation) = run(true);
+        assert_eq!(no_match, 0.0);
+        assert!(plain < 0.95, "limited level {}", plain);
+        assert!(compensation > 0.5, "compensation {}", compensation);
+        assert!((matched - 1.0).abs() < 0.03, "matched level {}", matched);
+    }
+}
@@ -0,0 +1,104 @@
+/// Loop crossfade
+/// Where a loop wraps, the audio that would have played on past loop_end is
+/// rendered for a few milliseconds and faded out under the loop start fading
+/// in, so the wrap never clicks even when neither side sits on a zero
+/// crossing. The gains are equal power: the two sides are different material,
+/// and a linear fade between them dips in the middle. Playback and the
+/// seamless loop export use the same fade.
+use super::audio_types::{SampleTime, Transport};
+use anyhow::Result;
+
+pub const DEFAULT_LOOP_CROSSFADE_MS: f32 = 5.0;
+
+/// Longest crossfade. The whole fade is rendered in the block that wraps,
+/// so this bounds the extra work the audio thread does there.
+pub const MAX_LOOP_CROSSFADE_MS: f32 = 50.0;
+
+/// Gains of the outgoing and incoming side `t` of the way through a fade,
+/// their squares summing to one
+pub fn equal_power(t: f32) -> (f32, f32) {
+    let angle = t.clamp(0.0, 1.0) * std::f32::consts::FRAC_PI_2;
+    (angle.cos(), angle.sin())
+}
+
+/// Crossfade frames in samples for `transport`'s loop, kept shorter than the
+/// loop itself
+pub fn crossfade_frames(transport: &Transport) -> usize {
+    let frames = (transport.loop_crossfade_ms.clamp(0.0, MAX_LOOP_CROSSFADE_MS) / 1000.0 * transport.sample_rate).round() as SampleTime;
+    frames.min(transport.loop_end.saturating_sub(transport.loop_start).saturating_sub(1)) as usize
+}
+
+/// Parse a typed crossfade length such as "5" or "12.5 ms" for `transport`'s
+/// loop. The error says what's accepted.
+pub fn parse_loop_crossfade(text: &str, transport: &Transport) -> Result<f32> {
+    let number = text.trim().trim_end_matches(|c: char| c.is_ascii_alphabetic()).trim();
+    let ms = number
+        .parse::<f32>()
+        .ok()
+        .filter(|ms| (0.0..=MAX_LOOP_CROSSFADE_MS).contains(ms))
+        .ok_or_else(|| anyhow::anyhow!("Loop crossfade must be a number from 0 to {} ms", MAX_LOOP_CROSSFADE_MS))?;
+    check_loop_crossfade(ms, transport)?;
+    Ok(ms)
+}
+
+/// A crossfade has to be shorter than the loop it smooths
+pub fn check_loop_crossfade(ms: f32, transport: &Transport) -> Result<()> {
+    let loop_ms = transport.loop_end.saturating_sub(transport.loop_start) as f32 / transport.sample_rate.max(1.0) * 1000.0;
+    if ms > 0.0 && ms >= loop_ms {
+        return Err(anyhow::anyhow!("A {} ms crossfade must be shorter than the {:.1} ms loop", ms, loop_ms));
+    }
+    Ok(())
+}
+
+/// Mix `tail`, the audio past the loop end, into `start`, the audio from the
+/// loop start, with `start` beginning `offset` frames into a fade `length`
+/// long. Frames of `start` after the fade are left as they are.
+pub fn crossfade(start: &mut [f32], tail: &[f32], offset: usize, length: usize) {
+    let frames = length.saturating_sub(offset).min(start.len());
+    for (i, (sample, outgoing)) in start[..frames].iter_mut().zip(tail.iter().skip(offset)).enumerate() {
+        let (fade_out, fade_in) = equal_power((offset + i) as f32 / length as f32);
+        *sample = *sample * fade_in + outgoing * fade_out;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_crossfade_is_equal_power_and_continues_the_tail() {
+        for t in [0.0, 0.25, 0.5, 1.0] {
+            let (out, inc) = equal_power(t);
+            assert!((out * out + inc * inc - 1.0).abs() < 1e-6);
+        }
+
+        // The first frame after the wrap is all tail, so it follows on from
+        // the loop end, and the fade can be mixed a block at a time
+        let tail = vec![1.0; 8];
+        let mut whole = vec![-1.0; 12];
+        crossfade(&mut whole, &tail, 0, 8);
+        assert_eq!(whole[0], 1.0);
+        assert!(whole[..8].windows(2).all(|w| w[1] < w[0]));
+        assert_eq!(&whole[8..], &[-1.0; 4]);
+        let mut blocks = vec![-1.0; 12];
+        let (first, second) = blocks.split_at_mut(3);
+        crossfade(first, &tail, 0, 8);
+        crossfade(second, &tail, 3, 8);
+        assert_eq!(blocks, whole);
+
+        // 5 ms at 48 kHz, but never the whole loop
+        let transport = Transport { loop_start: 0, loop_end: 48_000, sample_rate: 48_000.0, ..Transport::default() };
+        assert_eq!(crossfade_frames(&transport), 240);
+        assert_eq!(crossfade_frames(&Transport { loop_end: 100, ..transport }), 99);
+        assert_eq!(crossfade_frames(&Transport { loop_crossfade_ms: 0.0, ..transport }), 0);
+
+        assert_eq!(parse_loop_crossfade(" 12.5 ms", &transport).unwrap(), 12.5);
+        for bad in ["", "fast", "-1", "51"] {
+            assert!(parse_loop_crossfade(bad, &transport).is_err(), "{}", bad);
+        }
+        // 2 ms of loop can't take a 2 ms fade
+        let short = Transport { loop_end: 96, ..transport };
+        assert!(check_loop_crossfade(2.0, &short).is_err());
+        assert!(check_loop_crossfade(1.5, &short).is_ok());
+    }
+}
@@ -0,0 +1,172 @@
+/// Loop set import
+/// Turns a folder of loops into a music bed: one track per file, every loop
+/// starting on bar one, stretched to the project tempo and repeated to the
+/// length of the longest so layers can be muted in and out. A loop's tempo
+/// comes from a BPM in its file name or, failing that, from a length that
+/// lands on whole bars at a whole-number tempo. Files that fit neither are
+/// imported unwarped with the reason.
+use super::audio_types::*;
+use std::path::{Path, PathBuf};
+
+/// Extensions imported from a loop folder
+const LOOP_EXTENSIONS: [&str; 5] = ["wav", "mp3", "ogg", "flac", "aiff"];
+
+/// How far a loop's length may be from whole bars at the tempo in its name,
+/// as a share of its length
+const NAMED_TEMPO_TOLERANCE: f64 = 0.01;
+
+/// How close a tempo guessed from the length has to be to a whole BPM for
+/// the file to count as a clean loop
+const WHOLE_BPM_TOLERANCE: f32 = 0.05;
+
+/// Tempo and length in bars a loop was cut at
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LoopFit {
+    pub tempo: f32,
+    pub bars: u32,
+}
+
+/// One file of a loop set, ready to go on its own track
+#[derive(Debug, Clone)]
+pub struct LoopLayer {
+    pub name: String,
+    pub clip: AudioClip,
+    /// Why the file wasn't stretched, None for loops that were
+    pub unwarped: Option<String>,
+}
+
+/// Audio files directly inside `folder`, by name
+pub fn loop_files(folder: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut files: Vec<PathBuf> = std::fs::read_dir(folder)?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file())
+        .filter(|path| {
+            path.extension()
+                .map(|e| e.to_string_lossy().to_ascii_lowercase())
+                .is_some_and(|e| LOOP_EXTENSIONS.contains(&e.as_str()))
+        })
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+/// A tempo written into a file name, such as "Drums 128bpm" or "bass_92 BPM"
+pub fn tempo_from_name(name: &str) -> Option<f32> {
+    let lower = name.to_ascii_lowercase();
+    lower.match_indices("bpm").find_map(|(index, _)| {
+        let before = lower[..index].trim_end_matches([' ', '_', '-']);
+        let digits = before.len() - before.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.').len();
+        let tempo: f32 = before[before.len() - digits..].parse().ok()?;
+        (40.0..=300.0).contains(&tempo).then_some(tempo)
+    })
+}
+
+/// Work out the tempo a file `frames` long was cut at, from its name or
+/// its length. The error says why it doesn't look like a clean loop.
+pub fn fit_loop(transport: &Transport, frames: SampleTime, name: &str) -> Result<LoopFit, String> {
+    if let Some(named) = tempo_from_name(name) {
+        let beats_per_bar = transport.time_signature_numerator.max(1) as f64;
+        let bars = frames as f64 / transport.sample_rate as f64 * named as f64 / 60.0 / beats_per_bar;
+        let whole = bars.round();
+        if whole < 1.0 || (bars - whole).abs() > bars * NAMED_TEMPO_TOLERANCE {
+            return Err(format!("its length isn't whole bars at the {} BPM in its name", named));
+        }
+        let bars = whole as u32;
+        let tempo = transport.tempo_for_length(frames, bars).unwrap_or(named);
+        return Ok(LoopFit { tempo, bars });
+    }
+
+    transport
+        .guess_loop_bars(frames)
+        .into_iter()
+        .find(|(_, tempo)| (tempo - tempo.round()).abs() <= WHOLE_BPM_TOLERANCE)
+        .map(|(bars, tempo)| LoopFit { tempo, bars })
+        .ok_or_else(|| "its length doesn't come to whole bars at a whole-number tempo".to_string())
+}
+
+/// Lay out a loop set from each file's path and length in frames. Clean
+/// loops are stretched to the project tempo and repeat to the end of the
+/// longest one; the rest play once, unwarped, from bar one.
+pub fn build_loop_set(transport: &Transport, files: &[(PathBuf, SampleTime)]) -> Vec<LoopLayer> {
+    let mut layers: Vec<LoopLayer> = files
+        .iter()
+        .map(|(path, frames)| {
+            let name = path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+            let mut clip = AudioClip::new(path.clone(), 0, (*frames).max(1));
+            let unwarped = match fit_loop(transport, *frames, &name) {
+                Ok(fit) => {
+                    clip.loop_points = Some(ClipLoop { start: 0, end: *frames });
+                    clip.fit_to_tempo(fit.tempo, transport.tempo);
+                    None
+                }
+                Err(reason) => {
+                    clip.tempo_mode = Some(TempoMode::Fixed);
+                    Some(reason)
+                }
+            };
+            LoopLayer { name, clip, unwarped }
+        })
+        .collect();
+
+    let bed = layers
+        .iter()
+        .filter(|layer| layer.unwarped.is_none())
+        .map(|layer| layer.clip.duration)
+        .max();
+    if let Some(bed) = bed {
+        for layer in layers.iter_mut().filter(|layer| layer.unwarped.is_none()) {
+            layer.clip.duration = bed;
+        }
+    }
+    layers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frames_for(bars: u32, tempo: f64) -> SampleTime {
+        (bars as f64 * 4.0 * 60.0 / tempo * SAMPLE_RATE as f64).round() as SampleTime
+    }
+
+    #[test]
+    fn test_tempo_from_name() {
+        assert_eq!(tempo_from_name("Drums 128bpm"), Some(128.0));
+        assert_eq!(tempo_from_name("bass_92_BPM_Am"), Some(92.0));
+        assert_eq!(tempo_from_name("pad-87.5bpm"), Some(87.5));
+        assert_eq!(tempo_from_name("bpm"), None);
+        assert_eq!(tempo_from_name("take 7"), None);
+    }
+
+    #[test]
+    fn test_loop_set_is_stretched_and_aligned() {
+        let transport = Transport::default();
+        let files = vec![
+            (PathBuf::from("Drums.wav"), frames_for(1, 128.0)),
+            (PathBuf::from("Keys 90bpm.wav"), frames_for(4, 90.0)),
+            (PathBuf::from("Riser.wav"), 123_457),
+        ];
+        let layers = build_loop_set(&transport, &files);
+        assert_eq!(layers.iter().map(|l| l.name.as_str()).collect::<Vec<_>>(), vec!["Drums", "Keys 90bpm", "Riser"]);
+
+        // Both loops land on the project tempo and run for the four bars of
+        // the longer one, starting on bar one
+        for layer in &layers[..2] {
+            assert!(layer.unwarped.is_none(), "{:?}", layer.unwarped);
+            assert_eq!(layer.clip.start_time, 0);
+            assert_eq!(layer.clip.effective_tempo_mode(), TempoMode::Stretch);
+            assert!((transport.samples_to_beats(layer.clip.duration) - 16.0).abs() < 1e-3);
+        }
+        assert!((layers[0].clip.time_ratio - 128.0 / 120.0).abs() < 1e-6);
+
+        // The odd length plays once as recorded
+        let riser = &layers[2];
+        assert!(riser.unwarped.is_some());
+        assert_eq!((riser.clip.duration, riser.clip.time_ratio), (123_457, 1.0));
+        assert_eq!(riser.clip.effective_tempo_mode(), TempoMode::Fixed);
+
+        // A tempo in the name has to match the length
+        assert!(fit_loop(&transport, frames_for(2, 100.0), "Loop 128 bpm").is_err());
+    }
+}
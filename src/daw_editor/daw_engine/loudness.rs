@@ -0,0 +1,220 @@
+/// Integrated loudness measurement (ITU-R BS.1770 / EBU R128)
+/// K-weighted mean square over 400ms blocks with 75% overlap, gated at
+/// -70 LUFS absolute and -10 LU relative to the ungated level.
+
+/// Level of silence, anything quieter is ignored by the absolute gate
+const ABSOLUTE_GATE_LUFS: f64 = -70.0;
+
+/// Blocks this far below the running level are ignored by the relative gate
+const RELATIVE_GATE_LU: f64 = 10.0;
+
+/// Four 100ms steps make one gating block
+const STEPS_PER_BLOCK: usize = 4;
+
+/// Loudness a normalized export should reach
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LoudnessTarget {
+    /// Integrated loudness to hit, e.g. -23.0 (EBU R128) or -16.0
+    pub lufs: f32,
+    /// True-peak ceiling applied after the normalization gain, in dBTP
+    pub true_peak_db: f32,
+}
+
+impl LoudnessTarget {
+    pub fn new(lufs: f32) -> Self {
+        Self { lufs, true_peak_db: -1.0 }
+    }
+}
+
+/// Outcome of a loudness-normalized render
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoudnessReport {
+    pub target_lufs: f32,
+    /// Integrated loudness of the mix before normalization, None if silent
+    pub measured_lufs: Option<f32>,
+    /// Integrated loudness of the written file
+    pub achieved_lufs: Option<f32>,
+    /// Gain applied before the true-peak limiter
+    pub gain_db: f32,
+    /// Largest gain reduction the true-peak limiter needed
+    pub max_limiting_db: f32,
+    /// Problems worth showing to the user, e.g. heavy limiting
+    pub warnings: Vec<String>,
+}
+
+/// Second-order IIR section (direct form I)
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Biquad {
+    b: [f64; 3],
+    a: [f64; 2],
+    x: [f64; 2],
+    y: [f64; 2],
+}
+
+impl Biquad {
+    pub(crate) fn new(b: [f64; 3], a: [f64; 2]) -> Self {
+        Self { b, a, x: [0.0; 2], y: [0.0; 2] }
+    }
+
+    /// Swap in new coefficients, keeping the filter's history so a change
+    /// doesn't click
+    pub(crate) fn set_coefficients(&mut self, b: [f64; 3], a: [f64; 2]) {
+        self.b = b;
+        self.a = a;
+    }
+
+    pub(crate) fn process(&mut self, input: f64) -> f64 {
+        let output = self.b[0] * input + self.b[1] * self.x[0] + self.b[2] * self.x[1]
+            - self.a[0] * self.y[0]
+            - self.a[1] * self.y[1];
+        self.x = [input, self.x[0]];
+        self.y = [output, self.y[0]];
+        output
+    }
+}
+
+/// The two K-weighting stages: a high shelf for the head, then a highpass
+fn k_weighting(sample_rate: f64) -> [Biquad; 2] {
+    // Shelf parameters from BS.1770, re-derived for any sample rate
+    let f0 = 1681.974450955533;
+    let gain_db = 3.999843853973347;
+    let q = 0.7071752369554196;
+    let k = (std::f64::consts::PI * f0 / sample_rate).tan();
+    let vh = 10.0_f64.powf(gain_db / 20.0);
+    let vb = vh.powf(0.4996667741545416);
+    let a0 = 1.0 + k / q + k * k;
+    let shelf = Biquad::new(
+        [(vh + vb * k / q + k * k) / a0, 2.0 * (k * k - vh) / a0, (vh - vb * k / q + k * k) / a0],
+        [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+    );
+
+    let f0 = 38.13547087602444;
+    let q = 0.5003270373238773;
+    let k = (std::f64::consts::PI * f0 / sample_rate).tan();
+    let a0 = 1.0 + k / q + k * k;
+    let highpass = Biquad::new(
+        [1.0, -2.0, 1.0],
+        [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+    );
+
+    [shelf, highpass]
+}
+
+/// Streaming integrated loudness meter for stereo audio
+pub struct LoudnessMeter {
+    filters: [[Biquad; 2]; 2],
+    step_len: usize,
+    /// Summed channel energy of the 100ms step being filled
+    step_energy: f64,
+    step_fill: usize,
+    /// Mean energy of each complete step since the start
+    steps: Vec<f64>,
+}
+
+impl LoudnessMeter {
+    pub fn new(sample_rate: f32) -> Self {
+        let filters = k_weighting(sample_rate as f64);
+        Self {
+            filters: [filters, filters],
+            step_len: ((sample_rate / 10.0).round() as usize).max(1),
+            step_energy: 0.0,
+            step_fill: 0,
+            steps: Vec::new(),
+        }
+    }
+
+    /// Feed a stereo buffer
+    pub fn process(&mut self, left: &[f32], right: &[f32]) {
+        for (l, r) in left.iter().zip(right) {
+            let mut energy = 0.0;
+            for (channel, sample) in [*l, *r].into_iter().enumerate() {
+                let [shelf, highpass] = &mut self.filters[channel];
+                let weighted = highpass.process(shelf.process(sample as f64));
+                energy += weighted * weighted;
+            }
+            self.step_energy += energy;
+            self.step_fill += 1;
+
+            if self.step_fill == self.step_len {
+                self.steps.push(self.step_energy / self.step_len as f64);
+                self.step_energy = 0.0;
+                self.step_fill = 0;
+            }
+        }
+    }
+
+    /// Gated integrated loudness of everything fed so far. None when the
+    /// audio is shorter than one block or entirely below the absolute gate.
+    pub fn integrated_lufs(&self) -> Option<f32> {
+        let blocks: Vec<f64> = self
+            .steps
+            .windows(STEPS_PER_BLOCK)
+            .map(|w| w.iter().sum::<f64>() / STEPS_PER_BLOCK as f64)
+            .filter(|energy| energy_to_lufs(*energy) > ABSOLUTE_GATE_LUFS)
+            .collect();
+        if blocks.is_empty() {
+            return None;
+        }
+
+        let ungated = blocks.iter().sum::<f64>() / blocks.len() as f64;
+        let threshold = energy_to_lufs(ungated) - RELATIVE_GATE_LU;
+        let gated: Vec<f64> = blocks.into_iter().filter(|e| energy_to_lufs(*e) > threshold).collect();
+        if gated.is_empty() {
+            return None;
+        }
+
+        Some(energy_to_lufs(gated.iter().sum::<f64>() / gated.len() as f64) as f32)
+    }
+}
+
+fn energy_to_lufs(energy: f64) -> f64 {
+    if energy <= 0.0 {
+        f64::NEG_INFINITY
+    } else {
+        -0.691 + 10.0 * energy.log10()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sine(frames: usize, frequency: f32, amplitude: f32, sample_rate: f32) -> Vec<f32> {
+        (0..frames)
+            .map(|i| (2.0 * std::f32::consts::PI * frequency * i as f32 / sample_rate).sin() * amplitude)
+            .collect()
+    }
+
+    #[test]
+    fn test_stereo_sine_matches_reference_level() {
+        // EBU Tech 3341 case 1: a 1kHz stereo sine at -23 dBFS reads -23 LUFS
+        for sample_rate in [44100.0, 48000.0, 96000.0] {
+            let tone = sine(sample_rate as usize * 5, 1000.0, 10.0_f32.powf(-23.0 / 20.0), sample_rate);
+            let mut meter = LoudnessMeter::new(sample_rate);
+            meter.process(&tone, &tone);
+
+            let lufs = meter.integrated_lufs().unwrap();
+            assert!((lufs + 23.0).abs() < 0.1, "{} Hz measured {}", sample_rate, lufs);
+        }
+    }
+
+    #[test]
+    fn test_gates_ignore_silence_and_quiet_passages() {
+        let sample_rate = 48000.0;
+        let loud = sine(48000 * 3, 1000.0, 10.0_f32.powf(-20.0 / 20.0), sample_rate);
+        let quiet = sine(48000 * 3, 1000.0, 10.0_f32.powf(-50.0 / 20.0), sample_rate);
+        let silence = vec![0.0; 48000 * 3];
+
+        let mut meter = LoudnessMeter::new(sample_rate);
+        for part in [&loud, &silence, &quiet] {
+            meter.process(part, part);
+        }
+        // Only the blocks overlapping the end of the loud part pull it down a little
+        let lufs = meter.integrated_lufs().unwrap();
+        assert!((lufs + 20.0).abs() < 0.3, "measured {}", lufs);
+
+        let mut silent = LoudnessMeter::new(sample_rate);
+        silent.process(&silence, &silence);
+        assert_eq!(silent.integrated_lufs(), None);
+    }
+}
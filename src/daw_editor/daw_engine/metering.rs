@@ -0,0 +1,251 @@
+/// Meter ballistics
+/// How fast channel and master meters rise and fall. Digital peak follows
+/// every sample and falls back slowly, for catching transients; a PPM
+/// integrates over 10 ms so very short peaks read a little low, as broadcast
+/// meters do; a VU averages over 300 ms, closer to how loud the mix sounds.
+/// The reading is worked out on the audio thread sample by sample, so it
+/// doesn't depend on how often the UI polls. Armed tracks also meter their
+/// input before anything on the channel, with a clip light that stays on
+/// until it's cleared, so levels can be set before recording.
+use super::audio_types::{MeterData, TrackId};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum MeterBallistics {
+    /// Instant attack, falling 20 dB in 1.7 s (IEC 60268-18)
+    #[default]
+    DigitalPeak,
+    /// 10 ms integration, falling 20 dB in 1.5 s (IEC 60268-10 Type I)
+    Ppm,
+    /// 300 ms rise and fall on the rectified signal, reading a sine's RMS
+    Vu,
+}
+
+impl MeterBallistics {
+    pub const ALL: [MeterBallistics; 3] = [MeterBallistics::DigitalPeak, MeterBallistics::Ppm, MeterBallistics::Vu];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            MeterBallistics::DigitalPeak => "Digital Peak",
+            MeterBallistics::Ppm => "PPM",
+            MeterBallistics::Vu => "VU",
+        }
+    }
+
+    fn response(self, sample_rate: f32) -> Response {
+        let sample_rate = sample_rate.max(1.0);
+        // Multiplier per sample that falls `db` decibels in `seconds`
+        let fall = |db: f32, seconds: f32| 10f32.powf(-db / 20.0 / (seconds * sample_rate));
+        // One-pole step for a time constant in seconds
+        let pole = |tau: f32| 1.0 - (-1.0 / (tau * sample_rate)).exp();
+        match self {
+            MeterBallistics::DigitalPeak => Response { attack: 1.0, release: fall(20.0, 1.7), scale: 1.0, averaging: false },
+            // A 10 ms burst reads 1 dB under its peak: 1 - e^(-10 ms / tau) = -1 dB
+            MeterBallistics::Ppm => Response { attack: pole(0.010 / 2.216), release: fall(20.0, 1.5), scale: 1.0, averaging: false },
+            // 99% of a step in 300 ms, scaled from the rectified average to RMS
+            MeterBallistics::Vu => Response {
+                attack: pole(0.3 / 100f32.ln()),
+                release: 0.0,
+                scale: std::f32::consts::PI / (2.0 * std::f32::consts::SQRT_2),
+                averaging: true,
+            },
+        }
+    }
+}
+
+/// Input samples at or above this are counted as clipped: within a hair of
+/// the converter's full scale, where a take would be flattened
+pub const INPUT_CLIP_LEVEL: f32 = 0.999;
+
+/// A track's input level and whether it has clipped since last cleared
+#[derive(Debug, Clone, Copy, Default)]
+pub struct InputMeter {
+    pub meter: MeterData,
+    pub clipped: bool,
+}
+
+/// Per-sample coefficients of one ballistics setting
+#[derive(Debug, Clone, Copy)]
+struct Response {
+    /// Step towards a higher input
+    attack: f32,
+    /// Multiplier per sample while falling, for the peak types
+    release: f32,
+    /// Gain on the rectified input
+    scale: f32,
+    /// Rise and fall at the same rate instead of holding peaks
+    averaging: bool,
+}
+
+/// The reading of one stereo meter
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BallisticMeter {
+    levels: [f32; 2],
+}
+
+impl BallisticMeter {
+    fn process(&mut self, response: &Response, left: &[f32], right: &[f32]) -> [f32; 2] {
+        for (level, samples) in self.levels.iter_mut().zip([left, right]) {
+            for sample in samples {
+                let input = sample.abs() * response.scale;
+                *level = if input > *level || response.averaging {
+                    *level + (input - *level) * response.attack
+                } else {
+                    (*level * response.release).max(input)
+                };
+            }
+            if *level < 1e-6 {
+                *level = 0.0;
+            }
+        }
+        self.levels
+    }
+}
+
+/// Meters for every track and the master, all with the same ballistics
+#[derive(Debug, Clone)]
+pub struct MeterBank {
+    ballistics: MeterBallistics,
+    sample_rate: f32,
+    response: Response,
+    tracks: HashMap<TrackId, BallisticMeter>,
+    master: BallisticMeter,
+    inputs: HashMap<TrackId, InputMeterState>,
+}
+
+/// Ballistics and clip light of one track input
+#[derive(Debug, Clone, Copy, Default)]
+struct InputMeterState {
+    meter: BallisticMeter,
+    reading: InputMeter,
+}
+
+impl MeterBank {
+    pub fn new(ballistics: MeterBallistics, sample_rate: f32) -> Self {
+        Self {
+            ballistics,
+            sample_rate,
+            response: ballistics.response(sample_rate),
+            tracks: HashMap::new(),
+            master: BallisticMeter::default(),
+            inputs: HashMap::new(),
+        }
+    }
+
+    pub fn ballistics(&self) -> MeterBallistics {
+        self.ballistics
+    }
+
+    /// Switch every meter to other ballistics, starting them from silence.
+    /// Input clip lights stay as they were.
+    pub fn set_ballistics(&mut self, ballistics: MeterBallistics) {
+        if ballistics != self.ballistics {
+            let mut inputs = std::mem::take(&mut self.inputs);
+            *self = Self::new(ballistics, self.sample_rate);
+            for input in inputs.values_mut() {
+                input.meter = BallisticMeter::default();
+            }
+            self.inputs = inputs;
+        }
+    }
+
+    /// Meter a block of a track, or of the master with `None`
+    pub fn measure(&mut self, track: Option<TrackId>, left: &[f32], right: &[f32], sample_rate: f32) -> MeterData {
+        if sample_rate != self.sample_rate {
+            self.sample_rate = sample_rate;
+            self.response = self.ballistics.response(sample_rate);
+        }
+        let meter = match track {
+            Some(id) => self.tracks.entry(id).or_default(),
+            None => &mut self.master,
+        };
+        let [level_left, level_right] = meter.process(&self.response, left, right);
+        MeterData { level_left, level_right, ..MeterData::from_buffer(left, right) }
+    }
+
+    /// Meter a block of a track's input, lighting its clip light on any
+    /// sample at [`INPUT_CLIP_LEVEL`]
+    pub fn measure_input(&mut self, track: TrackId, left: &[f32], right: &[f32], sample_rate: f32) -> InputMeter {
+        if sample_rate != self.sample_rate {
+            self.sample_rate = sample_rate;
+            self.response = self.ballistics.response(sample_rate);
+        }
+        let input = self.inputs.entry(track).or_default();
+        let [level_left, level_right] = input.meter.process(&self.response, left, right);
+        let meter = MeterData { level_left, level_right, ..MeterData::from_buffer(left, right) };
+        input.reading = InputMeter {
+            meter,
+            clipped: input.reading.clipped || meter.peak_left.max(meter.peak_right) >= INPUT_CLIP_LEVEL,
+        };
+        input.reading
+    }
+
+    pub fn input(&self, track: TrackId) -> Option<InputMeter> {
+        self.inputs.get(&track).map(|i| i.reading)
+    }
+
+    /// Turn off the clip light of one input, or of all of them with `None`
+    pub fn clear_input_clip(&mut self, track: Option<TrackId>) {
+        for (id, input) in self.inputs.iter_mut() {
+            if track.is_none_or(|t| t == *id) {
+                input.reading.clipped = false;
+            }
+        }
+    }
+
+    /// Stop metering the inputs of tracks not in `metered`
+    pub fn retain_inputs(&mut self, metered: impl Fn(TrackId) -> bool) {
+        self.inputs.retain(|id, _| metered(*id));
+    }
+
+    pub fn remove(&mut self, track: TrackId) {
+        self.tracks.remove(&track);
+        self.inputs.remove(&track);
+    }
+
+    /// Drop the channel and master readings. Inputs are metered while
+    /// stopped too, so they keep theirs.
+    pub fn reset(&mut self) {
+        self.tracks.clear();
+        self.master = BallisticMeter::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sine(amplitude: f32, samples: usize) -> Vec<f32> {
+        (0..samples).map(|i| amplitude * (std::f32::consts::TAU * 1000.0 * i as f32 / 48_000.0).sin()).collect()
+    }
+
+    #[test]
+    fn test_ballistics_rise_and_fall_at_their_own_rates() {
+        let reading = |ballistics: MeterBallistics, signal: &[f32]| {
+            let mut bank = MeterBank::new(ballistics, 48_000.0);
+            bank.measure(None, signal, signal, 48_000.0).level_left
+        };
+        let steady = sine(1.0, 48_000);
+        // Held long enough, the peak types read within half a dB of the peak
+        // and the VU reads the RMS
+        assert!((reading(MeterBallistics::DigitalPeak, &steady) - 1.0).abs() < 0.01);
+        assert!(reading(MeterBallistics::Ppm, &steady) > 0.944);
+        assert!((reading(MeterBallistics::Vu, &steady) - 0.707).abs() < 0.03);
+
+        // A 10 ms burst: the PPM reads about 1 dB low and the VU far lower
+        let burst = vec![0.5_f32; 480];
+        assert_eq!(reading(MeterBallistics::DigitalPeak, &burst), 0.5);
+        let ppm_db = 20.0 * (reading(MeterBallistics::Ppm, &burst) / 0.5).log10();
+        assert!((ppm_db + 1.0).abs() < 0.2, "{}", ppm_db);
+        assert!(reading(MeterBallistics::Vu, &burst) < 0.2);
+
+        // The digital peak falls 20 dB over 1.7 s of silence
+        let mut bank = MeterBank::new(MeterBallistics::DigitalPeak, 48_000.0);
+        bank.measure(Some(uuid::Uuid::nil()), &[1.0], &[1.0], 48_000.0);
+        let silence = vec![0.0; 81_600];
+        let fallen = bank.measure(Some(uuid::Uuid::nil()), &silence, &silence, 48_000.0);
+        assert!((fallen.level_left - 0.1).abs() < 0.005, "{}", fallen.level_left);
+        assert_eq!(fallen.peak_left, 0.0);
+    }
+}
@@ -0,0 +1,122 @@
+/// Hardware MIDI input
+/// Every MIDI input port is opened when the engine starts. Messages are
+/// stamped on the MIDI thread with the local time and the engine's position
+/// as they arrive, then queued for the UI, so a message read a little late
+/// still tells the transport chase exactly when it came in.
+use super::audio_types::SampleTime;
+use anyhow::Result;
+use std::time::Instant;
+
+const CLIENT_NAME: &str = "Pulsar DAW";
+
+/// A MIDI message and when it arrived
+#[derive(Debug, Clone)]
+pub struct MidiMessage {
+    pub bytes: Vec<u8>,
+    pub arrived: Instant,
+    /// Engine position when it arrived
+    pub position: SampleTime,
+}
+
+impl MidiMessage {
+    /// Whether the message is MIDI Time Code or clock, which only the
+    /// transport chase reads
+    pub fn is_sync(&self) -> bool {
+        matches!(self.bytes.first(), Some(0xF0..=0xF2 | 0xF8 | 0xFA..=0xFC))
+    }
+
+    /// Channel, controller and value of a control change message
+    pub fn control_change(&self) -> Option<(u8, u8, u8)> {
+        match self.bytes[..] {
+            [status @ 0xB0..=0xBF, controller, value] => Some((status & 0x0F, controller & 0x7F, value & 0x7F)),
+            _ => None,
+        }
+    }
+}
+
+/// The open input ports. Dropping this closes them.
+pub struct MidiInputs {
+    connections: Vec<(String, midir::MidiInputConnection<()>)>,
+}
+
+impl MidiInputs {
+    /// Open every input port, queueing what arrives on the returned channel
+    /// with `position` read for each message. Ports that fail to open are
+    /// logged and left out.
+    pub fn open<F>(position: F) -> (Self, flume::Receiver<MidiMessage>)
+    where
+        F: Fn() -> SampleTime + Clone + Send + 'static,
+    {
+        let (tx, rx) = flume::unbounded();
+        let mut connections = Vec::new();
+        match midir::MidiInput::new(CLIENT_NAME) {
+            Ok(probe) => {
+                for port in probe.ports() {
+                    let name = probe.port_name(&port).unwrap_or_default();
+                    match Self::connect(&port, tx.clone(), position.clone()) {
+                        Ok(connection) => connections.push((name, connection)),
+                        Err(e) => tracing::error!("❌ Failed to open MIDI input '{}': {}", name, e),
+                    }
+                }
+            }
+            Err(e) => tracing::error!("❌ MIDI input unavailable: {}", e),
+        }
+        (Self { connections }, rx)
+    }
+
+    fn connect<F>(port: &midir::MidiInputPort, tx: flume::Sender<MidiMessage>, position: F) -> Result<midir::MidiInputConnection<()>>
+    where
+        F: Fn() -> SampleTime + Send + 'static,
+    {
+        // A port is connected through a client of its own
+        let input = midir::MidiInput::new(CLIENT_NAME)?;
+        input
+            .connect(
+                port,
+                CLIENT_NAME,
+                move |_, bytes, _| {
+                    let message = MidiMessage { bytes: bytes.to_vec(), arrived: Instant::now(), position: position() };
+                    let _ = tx.send(message);
+                },
+                (),
+            )
+            .map_err(|e| anyhow::anyhow!("{}", e))
+    }
+
+    /// Names of the open ports
+    pub fn port_names(&self) -> Vec<String> {
+        self.connections.iter().map(|(name, _)| name.clone()).collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.connections.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sync_messages_are_told_apart_from_channel_messages() {
+        let message = |bytes: &[u8]| MidiMessage { bytes: bytes.to_vec(), arrived: Instant::now(), position: 0 };
+        assert!(message(&[0xF1, 0x23]).is_sync());
+        assert!(message(&[0xF8]).is_sync());
+        assert!(message(&[0xFA]).is_sync());
+        assert!(message(&[0xF2, 0x10, 0x00]).is_sync());
+        assert!(message(&[0xF0, 0x7F, 0x7F, 0x01, 0x01, 0, 0, 0, 0, 0xF7]).is_sync());
+        assert!(!message(&[0xB0, 7, 100]).is_sync());
+        assert!(!message(&[0x90, 60, 100]).is_sync());
+        assert!(!message(&[]).is_sync());
+    }
+
+    #[test]
+    fn test_control_change_is_read_with_its_channel() {
+        let message = |bytes: &[u8]| MidiMessage { bytes: bytes.to_vec(), arrived: Instant::now(), position: 0 };
+        assert_eq!(message(&[0xB0, 7, 100]).control_change(), Some((0, 7, 100)));
+        assert_eq!(message(&[0xBF, 74, 0]).control_change(), Some((15, 74, 0)));
+        assert_eq!(message(&[0x90, 60, 100]).control_change(), None);
+        assert_eq!(message(&[0xB0, 7]).control_change(), None);
+        assert_eq!(message(&[0xF8]).control_change(), None);
+    }
+}
@@ -0,0 +1,55 @@
+/// DAW engine: mixing, routing, automation, fades and the `.pdaw` format
+///
+/// Everything in here works on plain data and has no GPUI dependency, so it
+/// can be driven headless (offline renders, batch tools) and unit tested. The
+/// `daw_ui` module is a view over these types.
+
+pub mod asset_manager;
+pub mod audio_graph;
+pub mod audio_service;
+pub mod audio_types;
+pub mod automation_recording;
+pub mod clipboard;
+pub mod convolution;
+pub mod denormal;
+pub mod drum_rack;
+pub mod ecs_integration;
+pub mod effects;
+pub mod fill;
+pub mod gain_match;
+pub mod generators;
+pub mod gpu_dsp;
+pub mod history;
+pub mod limiter;
+pub mod loop_crossfade;
+pub mod loop_import;
+pub mod loudness;
+pub mod metering;
+pub mod midi_input;
+pub mod peak_cache;
+pub mod plugins;
+pub mod preferences;
+pub mod profiling;
+pub mod project;
+pub mod project_report;
+pub mod quick_filters;
+pub mod real_time_audio;
+pub mod record_quantize;
+pub mod reference_image;
+pub mod render;
+pub mod render_queue;
+pub mod sample_repair;
+pub mod sampler;
+pub mod save_queue;
+pub mod smoothing;
+pub mod spectrogram;
+pub mod surround;
+pub mod take_recorder;
+pub mod tap_tempo;
+#[cfg(test)]
+pub(crate) mod test_support;
+pub mod timing_sidecar;
+pub mod transients;
+pub mod transport_chase;
+pub mod true_peak;
+pub mod view_state;
@@ -0,0 +1,439 @@
+/// Waveform peak pyramids and their on-disk cache
+/// Peaks are min/max pairs and an RMS level at several resolutions so clips
+/// can be drawn at any zoom without touching the samples. Pyramids are written to a cache
+/// directory so reopening a project shows waveforms without rescanning,
+/// and the directory is kept under a size budget by evicting the least
+/// recently used entries.
+use super::audio_types::AudioAssetData;
+use anyhow::{Context as AnyhowContext, Result};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Source frames summarised by one bin of the finest level
+const BASE_BIN_FRAMES: usize = 256;
+
+/// Levels stop halving once they have this few bins
+const MIN_LEVEL_BINS: usize = 16;
+
+/// Bytes hashed from each end of a source file to detect content changes
+const FINGERPRINT_BYTES: u64 = 64 * 1024;
+
+const MAGIC: &[u8; 4] = b"PDPK";
+const FORMAT_VERSION: u32 = 2;
+
+/// Default size budget of the cache directory
+pub const DEFAULT_CACHE_BYTES: u64 = 256 * 1024 * 1024;
+
+/// Min, max and RMS of one bin
+pub type PeakBin = [f32; 3];
+
+/// Min/max peaks and RMS of an audio file at halving resolutions
+#[derive(Debug, Clone, PartialEq)]
+pub struct WaveformPeaks {
+    /// Frames of audio the pyramid was built from, and their rate
+    pub frames: u64,
+    pub sample_rate: f32,
+    /// `levels[0]` has one bin per `BASE_BIN_FRAMES`, each next level half as many
+    levels: Vec<Vec<PeakBin>>,
+}
+
+impl WaveformPeaks {
+    /// Build a pyramid from interleaved samples, merging all channels
+    pub fn from_samples(samples: &[f32], channels: usize, sample_rate: f32) -> Self {
+        let channels = channels.max(1);
+        let frames = samples.len() / channels;
+
+        let base: Vec<PeakBin> = samples
+            .chunks(BASE_BIN_FRAMES * channels)
+            .map(|chunk| {
+                let [min, max, squares] = chunk
+                    .iter()
+                    .fold([0.0_f32; 3], |[min, max, squares], s| [min.min(*s), max.max(*s), squares + s * s]);
+                [min, max, (squares / chunk.len() as f32).sqrt()]
+            })
+            .collect();
+
+        let mut levels = vec![base];
+        while levels.last().is_some_and(|level| level.len() > MIN_LEVEL_BINS) {
+            let next = levels
+                .last()
+                .unwrap()
+                .chunks(2)
+                .map(merge_bins)
+                .collect();
+            levels.push(next);
+        }
+
+        Self { frames: frames as u64, sample_rate, levels }
+    }
+
+    pub fn from_asset(asset: &AudioAssetData) -> Self {
+        Self::from_samples(&asset.samples, asset.asset_ref.channels, asset.asset_ref.sample_rate)
+    }
+
+    /// Length of the source in seconds
+    pub fn duration_secs(&self) -> f64 {
+        if self.sample_rate > 0.0 {
+            self.frames as f64 / self.sample_rate as f64
+        } else {
+            0.0
+        }
+    }
+
+    /// Min/max for `columns` equal slices of the range `start..end` (fractions
+    /// of the file)
+    pub fn columns(&self, start: f64, end: f64, columns: usize) -> Vec<[f32; 2]> {
+        self.column_bins(start, end, columns).into_iter().map(|[min, max, _]| [min, max]).collect()
+    }
+
+    /// Min, max and RMS for `columns` equal slices of the range `start..end`,
+    /// using the coarsest level that still has a bin per column
+    pub fn column_bins(&self, start: f64, end: f64, columns: usize) -> Vec<PeakBin> {
+        if columns == 0 || end <= start || self.levels.is_empty() {
+            return Vec::new();
+        }
+        let span = end - start;
+        let level = self
+            .levels
+            .iter()
+            .rev()
+            .find(|level| level.len() as f64 * span >= columns as f64)
+            .unwrap_or(&self.levels[0]);
+        let bins = level.len() as f64;
+
+        (0..columns)
+            .map(|column| {
+                let from = start + span * column as f64 / columns as f64;
+                let to = start + span * (column + 1) as f64 / columns as f64;
+                let first = (from * bins).floor().max(0.0) as usize;
+                let last = ((to * bins).ceil() as usize).min(level.len()).max(first + 1);
+                merge_bins(level.get(first..last).unwrap_or(&[]))
+            })
+            .collect()
+    }
+
+    fn to_bytes(&self, fingerprint: &Fingerprint) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&fingerprint.to_bytes());
+        bytes.extend_from_slice(&self.frames.to_le_bytes());
+        bytes.extend_from_slice(&self.sample_rate.to_le_bytes());
+        bytes.extend_from_slice(&(self.levels.len() as u32).to_le_bytes());
+        for level in &self.levels {
+            bytes.extend_from_slice(&(level.len() as u32).to_le_bytes());
+            for bin in level {
+                for value in bin {
+                    bytes.extend_from_slice(&value.to_le_bytes());
+                }
+            }
+        }
+        bytes
+    }
+
+    /// Parse a cache file, returning None if it's corrupt or for a different
+    /// version of the source
+    fn from_bytes(bytes: &[u8], expected: &Fingerprint) -> Option<Self> {
+        let mut reader = ByteReader { bytes, position: 0 };
+        if reader.take(4)? != MAGIC || reader.u32()? != FORMAT_VERSION {
+            return None;
+        }
+        if Fingerprint::from_bytes(reader.take(FINGERPRINT_LEN)?)? != *expected {
+            return None;
+        }
+
+        let frames = reader.u64()?;
+        let sample_rate = reader.f32()?;
+        let level_count = reader.u32()? as usize;
+        let mut levels = Vec::with_capacity(level_count.min(64));
+        for _ in 0..level_count {
+            let bins = reader.u32()? as usize;
+            let mut level = Vec::with_capacity(bins.min(bytes.len() / 12));
+            for _ in 0..bins {
+                level.push([reader.f32()?, reader.f32()?, reader.f32()?]);
+            }
+            levels.push(level);
+        }
+        Some(Self { frames, sample_rate, levels })
+    }
+}
+
+/// Combine neighbouring bins of equal length into one
+fn merge_bins(bins: &[PeakBin]) -> PeakBin {
+    if bins.is_empty() {
+        return [0.0; 3];
+    }
+    let [min, max, squares] = bins
+        .iter()
+        .fold([0.0_f32; 3], |[min, max, squares], b| [min.min(b[0]), max.max(b[1]), squares + b[2] * b[2]]);
+    [min, max, (squares / bins.len() as f32).sqrt()]
+}
+
+const FINGERPRINT_LEN: usize = 24;
+
+/// What a cache entry was built from: the file's modification time, length
+/// and a hash of its first and last bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Fingerprint {
+    modified_nanos: u64,
+    len: u64,
+    content_hash: u64,
+}
+
+impl Fingerprint {
+    fn of(path: &Path) -> Result<Self> {
+        let metadata = std::fs::metadata(path).with_context(|| format!("Failed to read {:?}", path))?;
+        let modified_nanos = metadata
+            .modified()
+            .ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or(0);
+        let len = metadata.len();
+
+        let mut file = std::fs::File::open(path).with_context(|| format!("Failed to open {:?}", path))?;
+        let mut head = Vec::new();
+        (&mut file).take(FINGERPRINT_BYTES).read_to_end(&mut head)?;
+        let mut tail = Vec::new();
+        if len > FINGERPRINT_BYTES * 2 {
+            use std::io::{Seek, SeekFrom};
+            file.seek(SeekFrom::End(-(FINGERPRINT_BYTES as i64)))?;
+            file.take(FINGERPRINT_BYTES).read_to_end(&mut tail)?;
+        }
+
+        let mut hasher = DefaultHasher::new();
+        head.hash(&mut hasher);
+        tail.hash(&mut hasher);
+        Ok(Self { modified_nanos, len, content_hash: hasher.finish() })
+    }
+
+    fn to_bytes(self) -> [u8; FINGERPRINT_LEN] {
+        let mut bytes = [0; FINGERPRINT_LEN];
+        bytes[0..8].copy_from_slice(&self.modified_nanos.to_le_bytes());
+        bytes[8..16].copy_from_slice(&self.len.to_le_bytes());
+        bytes[16..24].copy_from_slice(&self.content_hash.to_le_bytes());
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let word = |i: usize| Some(u64::from_le_bytes(bytes.get(i..i + 8)?.try_into().ok()?));
+        Some(Self { modified_nanos: word(0)?, len: word(8)?, content_hash: word(16)? })
+    }
+}
+
+struct ByteReader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        let slice = self.bytes.get(self.position..self.position + len)?;
+        self.position += len;
+        Some(slice)
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        Some(u32::from_le_bytes(self.take(4)?.try_into().ok()?))
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        Some(u64::from_le_bytes(self.take(8)?.try_into().ok()?))
+    }
+
+    fn f32(&mut self) -> Option<f32> {
+        Some(f32::from_le_bytes(self.take(4)?.try_into().ok()?))
+    }
+}
+
+/// Directory of cached peak pyramids, one file per source path
+#[derive(Debug, Clone)]
+pub struct PeakCache {
+    dir: PathBuf,
+    max_bytes: u64,
+}
+
+impl PeakCache {
+    pub fn new(dir: impl Into<PathBuf>, max_bytes: u64) -> Self {
+        Self { dir: dir.into(), max_bytes }
+    }
+
+    /// Cache in the system temp directory with the default size budget
+    pub fn default_location() -> Self {
+        Self::new(std::env::temp_dir().join("pulsar_daw_peaks"), DEFAULT_CACHE_BYTES)
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn entry_path(&self, source: &Path) -> PathBuf {
+        let source = std::fs::canonicalize(source).unwrap_or_else(|_| source.to_path_buf());
+        let mut hasher = DefaultHasher::new();
+        source.hash(&mut hasher);
+        self.dir.join(format!("{:016x}.peaks", hasher.finish()))
+    }
+
+    /// Cached peaks for `source`, or None if missing or built from an older
+    /// version of the file. A hit marks the entry as recently used.
+    pub fn load(&self, source: &Path) -> Option<WaveformPeaks> {
+        let fingerprint = Fingerprint::of(source).ok()?;
+        let entry = self.entry_path(source);
+        let peaks = WaveformPeaks::from_bytes(&std::fs::read(&entry).ok()?, &fingerprint)?;
+
+        if let Ok(file) = std::fs::File::options().write(true).open(&entry) {
+            let _ = file.set_modified(SystemTime::now());
+        }
+        Some(peaks)
+    }
+
+    /// Write peaks for `source`, replacing any stale entry, then evict old
+    /// entries until the cache fits its budget
+    pub fn store(&self, source: &Path, peaks: &WaveformPeaks) -> Result<()> {
+        let fingerprint = Fingerprint::of(source)?;
+        std::fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Failed to create peak cache {:?}", self.dir))?;
+
+        let entry = self.entry_path(source);
+        let temp = entry.with_extension("tmp");
+        std::fs::write(&temp, peaks.to_bytes(&fingerprint))
+            .with_context(|| format!("Failed to write peak cache entry {:?}", temp))?;
+        std::fs::rename(&temp, &entry)
+            .with_context(|| format!("Failed to replace peak cache entry {:?}", entry))?;
+
+        self.evict(Some(&entry))
+    }
+
+    /// Cached peaks for `source`, built from `asset` and stored when missing or stale
+    pub fn load_or_build(&self, source: &Path, asset: &AudioAssetData) -> WaveformPeaks {
+        if let Some(peaks) = self.load(source) {
+            return peaks;
+        }
+        let peaks = WaveformPeaks::from_asset(asset);
+        if let Err(e) = self.store(source, &peaks) {
+            tracing::error!("❌ Failed to cache waveform peaks for {:?}: {:#}", source, e);
+        }
+        peaks
+    }
+
+    /// Delete least recently used entries until the directory is within
+    /// budget. `keep` is never evicted.
+    fn evict(&self, keep: Option<&Path>) -> Result<()> {
+        let mut entries: Vec<(SystemTime, u64, PathBuf)> = std::fs::read_dir(&self.dir)?
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "peaks"))
+            .filter_map(|entry| {
+                let metadata = entry.metadata().ok()?;
+                Some((metadata.modified().unwrap_or(UNIX_EPOCH), metadata.len(), entry.path()))
+            })
+            .collect();
+
+        let mut total: u64 = entries.iter().map(|(_, len, _)| len).sum();
+        entries.sort_by_key(|(modified, ..)| *modified);
+
+        for (_, len, path) in entries {
+            if total <= self.max_bytes {
+                break;
+            }
+            if Some(path.as_path()) == keep {
+                continue;
+            }
+            if std::fs::remove_file(&path).is_ok() {
+                total -= len;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::test_support::temp_dir;
+
+    fn tone(frames: usize, amplitude: f32) -> Vec<f32> {
+        (0..frames).map(|i| (i as f32 * 0.01).sin() * amplitude).collect()
+    }
+
+    #[test]
+    fn test_pyramid_columns() {
+        let mut samples = vec![0.0; 4096];
+        samples[3000] = 0.9;
+        samples[100] = -0.5;
+        let peaks = WaveformPeaks::from_samples(&samples, 1, 48000.0);
+        assert_eq!(peaks.frames, 4096);
+
+        let columns = peaks.columns(0.0, 1.0, 2);
+        assert_eq!(columns, vec![[-0.5, 0.0], [0.0, 0.9]]);
+
+        // Zooming into the second half still finds the peak
+        let zoomed = peaks.columns(0.5, 1.0, 8);
+        assert_eq!(zoomed.len(), 8);
+        assert!(zoomed.iter().any(|[_, max]| *max == 0.9));
+    }
+
+    #[test]
+    fn test_pyramid_rms() {
+        // A full-scale square wave has an RMS of 1, a quiet half of it 0.1
+        let mut samples: Vec<f32> = (0..8192).map(|i| if i % 2 == 0 { 1.0 } else { -1.0 }).collect();
+        for sample in &mut samples[4096..] {
+            *sample *= 0.1;
+        }
+        let peaks = WaveformPeaks::from_samples(&samples, 2, 48000.0);
+        let bins = peaks.column_bins(0.0, 1.0, 2);
+        assert!((bins[0][2] - 1.0).abs() < 1e-5 && (bins[1][2] - 0.1).abs() < 1e-5, "{:?}", bins);
+
+        // Coarser levels combine loudness rather than averaging amplitudes
+        let whole = peaks.column_bins(0.0, 1.0, 1)[0];
+        assert_eq!((whole[0], whole[1]), (-1.0, 1.0));
+        assert!((whole[2] - (1.01_f32 / 2.0).sqrt()).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_cache_round_trip_and_staleness() {
+        let dir = temp_dir();
+        let source = dir.join("tone.raw");
+        std::fs::write(&source, b"first version").unwrap();
+        let cache = PeakCache::new(dir.join("cache"), DEFAULT_CACHE_BYTES);
+
+        let peaks = WaveformPeaks::from_samples(&tone(10_000, 0.5), 1, 48000.0);
+        assert!(cache.load(&source).is_none());
+        cache.store(&source, &peaks).unwrap();
+        assert_eq!(cache.load(&source), Some(peaks));
+
+        // Changing the source invalidates the entry
+        std::fs::write(&source, b"second, longer version").unwrap();
+        assert!(cache.load(&source).is_none());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_cache_evicts_least_recently_used() {
+        let dir = temp_dir();
+        let peaks = WaveformPeaks::from_samples(&tone(50_000, 0.5), 1, 48000.0);
+        let entry_size = peaks.to_bytes(&Fingerprint { modified_nanos: 0, len: 0, content_hash: 0 }).len() as u64;
+        let cache = PeakCache::new(dir.join("cache"), entry_size * 2);
+
+        let sources: Vec<PathBuf> = (0..3).map(|i| dir.join(format!("{}.raw", i))).collect();
+        for (i, source) in sources.iter().enumerate() {
+            std::fs::write(source, format!("source {}", i)).unwrap();
+        }
+
+        cache.store(&sources[0], &peaks).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        cache.store(&sources[1], &peaks).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        // Touch the oldest entry so the second one becomes least recently used
+        assert!(cache.load(&sources[0]).is_some());
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        cache.store(&sources[2], &peaks).unwrap();
+
+        assert!(cache.load(&sources[0]).is_some());
+        assert!(cache.load(&sources[1]).is_none());
+        assert!(cache.load(&sources[2]).is_some());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
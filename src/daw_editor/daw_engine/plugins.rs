@@ -0,0 +1,113 @@
+/// Third-party effect plugins
+/// An insert effect can name an external plugin instead of one of the
+/// built-in kinds. The effect saves the plugin's id, the last parameter
+/// values and the plugin's own state as an opaque blob, and saves them back
+/// unchanged. This build can't load plugins, so such effects pass audio
+/// through; a host has to bring a format loader that enumerates parameters
+/// and runs plugins in a process of their own before they can be heard.
+use serde::{Deserialize, Serialize};
+
+/// Plugin binary formats that can back an effect
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PluginFormat {
+    #[default]
+    Clap,
+}
+
+/// A parameter value saved with an effect, by the plugin's parameter id
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PluginParamValue {
+    pub id: u32,
+    pub value: f32,
+}
+
+/// The plugin behind an effect and what it needs to come back as it was
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PluginSlot {
+    pub plugin_id: String,
+    #[serde(default)]
+    pub format: PluginFormat,
+    /// Name shown in the effect chain
+    pub name: String,
+    /// Parameter values as last set from the editor or automation
+    #[serde(default)]
+    pub params: Vec<PluginParamValue>,
+    /// The plugin's saved state, stored as base64 in project JSON
+    #[serde(default, with = "blob")]
+    pub state: Vec<u8>,
+}
+
+impl PluginSlot {
+    pub fn param(&self, id: u32) -> Option<f32> {
+        self.params.iter().find(|p| p.id == id).map(|p| p.value)
+    }
+
+    pub fn set_param(&mut self, id: u32, value: f32) {
+        match self.params.iter_mut().find(|p| p.id == id) {
+            Some(param) => param.value = value,
+            None => self.params.push(PluginParamValue { id, value }),
+        }
+    }
+}
+
+/// Serde helpers storing bytes as standard base64 text
+mod blob {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    pub fn encode(bytes: &[u8]) -> String {
+        let mut text = String::with_capacity(bytes.len().div_ceil(3) * 4);
+        for chunk in bytes.chunks(3) {
+            let group = chunk.iter().enumerate().fold(0u32, |group, (i, b)| group | (*b as u32) << (16 - 8 * i));
+            for i in 0..4 {
+                if i <= chunk.len() {
+                    text.push(ALPHABET[(group >> (18 - 6 * i) & 63) as usize] as char);
+                } else {
+                    text.push('=');
+                }
+            }
+        }
+        text
+    }
+
+    pub fn decode(text: &str) -> Option<Vec<u8>> {
+        let text = text.trim_end_matches('=');
+        let mut bytes = Vec::with_capacity(text.len() * 3 / 4);
+        let (mut group, mut bits) = (0u32, 0);
+        for c in text.bytes() {
+            let value = ALPHABET.iter().position(|a| *a == c)? as u32;
+            group = group << 6 | value;
+            bits += 6;
+            if bits >= 8 {
+                bits -= 8;
+                bytes.push((group >> bits) as u8);
+            }
+        }
+        Some(bytes)
+    }
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&encode(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        decode(&text).ok_or_else(|| serde::de::Error::custom("plugin state is not valid base64"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blob_round_trip() {
+        for bytes in [&b""[..], b"f", b"fo", b"foo", b"foob", &[0, 255, 128, 7, 64]] {
+            assert_eq!(blob::decode(&blob::encode(bytes)).as_deref(), Some(bytes));
+        }
+        assert_eq!(blob::encode(b"foob"), "Zm9vYg==");
+        assert_eq!(blob::decode("not*base64"), None);
+    }
+}
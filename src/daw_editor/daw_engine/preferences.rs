@@ -0,0 +1,805 @@
+/// User preferences for editing and display
+/// Stored per user in the config directory rather than in projects, so
+/// changing them only affects clips created or edited afterwards.
+use super::audio_types::*;
+use super::metering::MeterBallistics;
+use super::smoothing::{self, DEFAULT_SMOOTHING_MS};
+use super::transport_chase::SyncSource;
+use anyhow::{Context as AnyhowContext, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+const PREFERENCES_FILE: &str = "preferences.json";
+const FADE_PRESETS_FILE: &str = "fade_presets.json";
+
+/// Range of the monitor level trim in dB
+pub const MIN_MONITOR_DB: f32 = -60.0;
+pub const MAX_MONITOR_DB: f32 = 12.0;
+
+/// Quietest level shown by the log waveform scale, in dB
+pub const WAVEFORM_FLOOR_DB: f32 = -48.0;
+
+/// How long following the playhead stays paused after scrolling by hand
+pub const FOLLOW_RESUME_SECONDS: f32 = 4.0;
+
+/// A clip's fade lengths and shapes, the parts of it crossfading changes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Fades {
+    pub fade_in: SampleTime,
+    pub fade_out: SampleTime,
+    pub fade_in_shape: FadeShape,
+    pub fade_out_shape: FadeShape,
+}
+
+impl Fades {
+    pub fn of(clip: &AudioClip) -> Self {
+        Self {
+            fade_in: clip.fade_in,
+            fade_out: clip.fade_out,
+            fade_in_shape: clip.fade_in_shape,
+            fade_out_shape: clip.fade_out_shape,
+        }
+    }
+
+    pub fn apply_to(self, clip: &mut AudioClip) {
+        clip.fade_in = self.fade_in;
+        clip.fade_out = self.fade_out;
+        clip.fade_in_shape = self.fade_in_shape;
+        clip.fade_out_shape = self.fade_out_shape;
+    }
+}
+
+/// Fade and crossfade defaults applied while editing
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EditPreferences {
+    /// Fade-in and fade-out given to new and split clips, in milliseconds
+    pub default_fade_ms: f32,
+    /// Whether moving a clip over another one fades the overlap
+    pub auto_crossfade: bool,
+    /// Longest crossfade made for an overlap, in milliseconds
+    pub crossfade_ms: f32,
+    /// Curve both sides of a crossfade are given
+    pub crossfade_shape: FadeShape,
+    /// Colors given to tracks in the timeline and mixer
+    pub track_palette: TrackPalette,
+    /// Stronger selection, playhead and grid colors
+    pub high_contrast: bool,
+    /// Trim in dB on what goes to the speakers, after the master meter.
+    /// Not part of the mix, so exports never hear it.
+    pub monitor_level_db: f32,
+    /// Ramp over which mute, solo, fader and bypass changes take effect, in
+    /// milliseconds. Zero makes them instant.
+    pub smoothing_ms: f32,
+    /// How channel and master meters rise and fall
+    pub meter_ballistics: MeterBallistics,
+    /// How clip waveforms are drawn, for every clip at once
+    pub waveform: WaveformStyle,
+    /// Scroll the timeline to keep the playhead in view while playing
+    pub follow_playhead: bool,
+    pub follow_mode: FollowMode,
+    /// External master the transport chases, if any
+    pub sync_source: SyncSource,
+    /// Master timecode at the project start in seconds, e.g. 3600 for a
+    /// master starting at 01:00:00:00
+    pub timecode_start_seconds: f64,
+    /// Cut the silence at either end of files dropped onto tracks. The file
+    /// is untouched; the clip just starts and ends inside it.
+    pub trim_import_silence: bool,
+    /// Quieter than this counts as silence when trimming imports, in dBFS
+    pub import_silence_db: f32,
+    /// Kept before the first sound and after the last when trimming
+    /// imports, so attacks and tails survive, in milliseconds
+    pub import_margin_ms: f32,
+    /// Scan files as they load for runs of clipped samples, rebuilding the
+    /// short ones. Off by default as it slows loading down. NaN and
+    /// infinite samples are silenced either way.
+    pub scan_clipping: bool,
+    /// Record arm follows the selected track: selecting an audio track arms
+    /// it and disarms the others. Off leaves arming to the arm buttons.
+    pub arm_follows_selection: bool,
+}
+
+impl Default for EditPreferences {
+    fn default() -> Self {
+        Self {
+            default_fade_ms: 5.0,
+            auto_crossfade: true,
+            crossfade_ms: 10.0,
+            crossfade_shape: FadeShape::EqualPower,
+            track_palette: TrackPalette::default(),
+            high_contrast: false,
+            monitor_level_db: 0.0,
+            smoothing_ms: DEFAULT_SMOOTHING_MS,
+            meter_ballistics: MeterBallistics::DigitalPeak,
+            waveform: WaveformStyle::default(),
+            follow_playhead: true,
+            follow_mode: FollowMode::Page,
+            sync_source: SyncSource::Internal,
+            timecode_start_seconds: 0.0,
+            trim_import_silence: false,
+            import_silence_db: -60.0,
+            import_margin_ms: 5.0,
+            scan_clipping: false,
+            arm_follows_selection: false,
+        }
+    }
+}
+
+impl EditPreferences {
+    /// Preferences file in the user config directory
+    pub fn default_path() -> PathBuf {
+        config_dir().join("pulsar_daw").join(PREFERENCES_FILE)
+    }
+
+    /// Load from `path`, falling back to the defaults if the file is missing
+    /// or unreadable
+    pub fn load(path: &Path) -> Self {
+        match std::fs::read_to_string(path) {
+            Ok(json) => serde_json::from_str(&json).unwrap_or_else(|e| {
+                tracing::error!("⚠️ Ignoring invalid preferences at {:?}: {}", path, e);
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {:?}", parent))?;
+        }
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, json).with_context(|| format!("Failed to write {:?}", path))
+    }
+
+    /// Linear gain of the monitor level trim
+    pub fn monitor_gain(&self) -> f32 {
+        10.0_f32.powf(self.monitor_level_db.clamp(MIN_MONITOR_DB, MAX_MONITOR_DB) / 20.0)
+    }
+
+    pub fn smoothing_samples(&self, sample_rate: f32) -> usize {
+        smoothing::smoothing_samples(self.smoothing_ms, sample_rate)
+    }
+
+    pub fn fade_samples(&self, sample_rate: f32) -> SampleTime {
+        ms_to_samples(self.default_fade_ms, sample_rate)
+    }
+
+    pub fn crossfade_samples(&self, sample_rate: f32) -> SampleTime {
+        ms_to_samples(self.crossfade_ms, sample_rate)
+    }
+
+    /// Give a newly created clip the default fades, at most half its length each
+    pub fn apply_default_fades(&self, clip: &mut AudioClip, sample_rate: f32) {
+        let fade = self.fade_samples(sample_rate).min(clip.duration / 2);
+        clip.fade_in = fade;
+        clip.fade_out = fade;
+    }
+
+    /// Skip the silence at either end of a clip just made from `asset`,
+    /// keeping the margin, when import trimming is on. The clip stays where
+    /// it was placed and starts at the first sound. Returns whether any
+    /// silence was cut.
+    pub fn trim_imported_silence(&self, clip: &mut AudioClip, asset: &AudioAssetData) -> bool {
+        if !self.trim_import_silence {
+            return false;
+        }
+        let channels = asset.asset_ref.channels.max(1);
+        let samples = &asset.samples;
+        let frames = samples.len() / channels;
+        let peak_at = |i: usize| samples[i * channels..(i + 1) * channels].iter().fold(0.0_f32, |max, s| max.max(s.abs()));
+        let Some((first, last)) = audible_span(frames, self.import_silence_db, peak_at) else {
+            return false;
+        };
+
+        let margin = ms_to_samples(self.import_margin_ms, asset.asset_ref.sample_rate) as usize;
+        let (start, end) = (first.saturating_sub(margin), (last + 1 + margin).min(frames));
+        if start == 0 && end == frames {
+            return false;
+        }
+        clip.offset = start as SampleTime;
+        clip.duration = clip.source_to_timeline((end - start) as SampleTime).max(1);
+        true
+    }
+}
+
+/// Which of a clip's fades a preset is applied to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FadeEnds {
+    In,
+    Out,
+    Both,
+}
+
+/// A named fade curve offered on clips
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FadePreset {
+    pub name: String,
+    pub shape: FadeShape,
+    /// Curvature bending the shape, see [`fade_shape`]
+    #[serde(default)]
+    pub curve: f32,
+}
+
+impl FadePreset {
+    pub fn new(name: impl Into<String>, shape: FadeShape, curve: f32) -> Self {
+        Self { name: name.into(), shape, curve: curve.clamp(-1.0, 1.0) }
+    }
+
+    /// Presets every user has, ahead of their own
+    pub fn built_in() -> Vec<FadePreset> {
+        vec![
+            FadePreset::new("Linear", FadeShape::Power, 0.0),
+            FadePreset::new("Equal Power", FadeShape::EqualPower, 0.0),
+            FadePreset::new("S-Curve", FadeShape::SCurve, 0.0),
+            FadePreset::new("Fast In / Slow Out", FadeShape::Power, -0.5),
+        ]
+    }
+
+    /// Whether a fade with `shape` and `curve` follows this preset
+    pub fn matches(&self, shape: FadeShape, curve: f32) -> bool {
+        self.shape == shape && (self.curve - curve).abs() < 1e-3
+    }
+
+    /// Give the `ends` fades of `clip` this curve, keeping their lengths
+    pub fn apply(&self, clip: &mut AudioClip, ends: FadeEnds) {
+        if ends != FadeEnds::Out {
+            clip.fade_in_shape = self.shape;
+            clip.fade_in_curve = self.curve;
+        }
+        if ends != FadeEnds::In {
+            clip.fade_out_shape = self.shape;
+            clip.fade_out_curve = self.curve;
+        }
+    }
+}
+
+/// Fade presets the user saved, kept in their own file next to the
+/// preferences. Users can also add presets by editing it.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct FadePresets {
+    pub user: Vec<FadePreset>,
+}
+
+impl FadePresets {
+    pub fn default_path() -> PathBuf {
+        config_dir().join("pulsar_daw").join(FADE_PRESETS_FILE)
+    }
+
+    /// Load from `path`, with no user presets if the file is missing or
+    /// unreadable
+    pub fn load(path: &Path) -> Self {
+        match std::fs::read_to_string(path) {
+            Ok(json) => serde_json::from_str(&json).unwrap_or_else(|e| {
+                tracing::error!("⚠️ Ignoring invalid fade presets at {:?}: {}", path, e);
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {:?}", parent))?;
+        }
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, json).with_context(|| format!("Failed to write {:?}", path))
+    }
+
+    /// The built-in presets followed by the user's, leaving out user presets
+    /// that repeat a built-in name
+    pub fn all(&self) -> Vec<FadePreset> {
+        let mut presets = FadePreset::built_in();
+        for preset in &self.user {
+            if !presets.iter().any(|p| p.name == preset.name) {
+                presets.push(preset.clone());
+            }
+        }
+        presets
+    }
+
+    /// Add a user preset, replacing one with the same name
+    pub fn add(&mut self, preset: FadePreset) {
+        match self.user.iter_mut().find(|p| p.name == preset.name) {
+            Some(existing) => *existing = preset,
+            None => self.user.push(preset),
+        }
+    }
+}
+
+/// Drawing options for clip waveforms, picked at render time from the
+/// cached peaks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WaveformStyle {
+    /// Fold the negative half onto the positive and draw from the bottom
+    pub rectified: bool,
+    /// Lighter fill over the peaks showing the RMS level
+    pub rms_overlay: bool,
+    /// Scale heights by dB down to `WAVEFORM_FLOOR_DB` so quiet detail shows
+    pub log_scale: bool,
+}
+
+impl WaveformStyle {
+    /// Drawn height of a sample value as a share of full height, keeping its
+    /// sign. Full scale stays 1 on either scale.
+    pub fn scale(self, value: f32) -> f32 {
+        let magnitude = value.abs().min(1.0);
+        let height = if self.log_scale {
+            if magnitude <= 0.0 {
+                0.0
+            } else {
+                (1.0 - 20.0 * magnitude.log10() / WAVEFORM_FLOOR_DB).max(0.0)
+            }
+        } else {
+            magnitude
+        };
+        height.copysign(value)
+    }
+}
+
+/// How the timeline keeps up with the playhead
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum FollowMode {
+    /// Turn a page when the playhead reaches the right edge, leaving the
+    /// view still in between
+    #[default]
+    Page,
+    /// Scroll with the playhead, keeping it in the middle
+    Continuous,
+}
+
+impl FollowMode {
+    pub fn label(self) -> &'static str {
+        match self {
+            FollowMode::Page => "Page",
+            FollowMode::Continuous => "Scroll",
+        }
+    }
+
+    /// Where the view should start to follow a playhead at `playhead`,
+    /// with the view `width` wide and currently starting at `scroll`, all in
+    /// pixels. `None` leaves the view where it is.
+    pub fn scroll_for(self, playhead: f32, scroll: f32, width: f32) -> Option<f32> {
+        if width <= 0.0 {
+            return None;
+        }
+        let target = match self {
+            FollowMode::Continuous => (playhead - width / 2.0).max(0.0),
+            // A playhead that jumped out of view, by seeking or looping
+            // back, starts a page of its own
+            FollowMode::Page if playhead < scroll || playhead >= scroll + 2.0 * width => playhead.max(0.0),
+            FollowMode::Page if playhead >= scroll + width => scroll + width,
+            FollowMode::Page => scroll,
+        };
+        ((target - scroll).abs() >= 0.5).then_some(target)
+    }
+}
+
+/// Okabe-Ito colors without black, ordered so neighbours differ in both
+/// hue and brightness for common kinds of color blindness
+const COLORBLIND_SAFE: [[f32; 3]; 7] = [
+    [0.902, 0.624, 0.0],   // orange
+    [0.0, 0.447, 0.698],   // blue
+    [0.941, 0.894, 0.259], // yellow
+    [0.800, 0.475, 0.655], // reddish purple
+    [0.0, 0.620, 0.451],   // bluish green
+    [0.835, 0.369, 0.0],   // vermillion
+    [0.337, 0.706, 0.914], // sky blue
+];
+
+/// Sequence tracks are colored from, by their position in the project
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum TrackPalette {
+    /// Hues a golden angle apart, so neighbouring tracks are far apart on the
+    /// color wheel
+    #[default]
+    Standard,
+    /// Colors that stay distinct with protanopia, deuteranopia and tritanopia
+    ColorblindSafe,
+}
+
+impl TrackPalette {
+    pub const ALL: [TrackPalette; 2] = [TrackPalette::Standard, TrackPalette::ColorblindSafe];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            TrackPalette::Standard => "Standard",
+            TrackPalette::ColorblindSafe => "Colorblind Safe",
+        }
+    }
+
+    /// RGB color of the track at `index`, components 0-1
+    pub fn color(self, index: usize) -> [f32; 3] {
+        match self {
+            TrackPalette::Standard => hsl_to_rgb((index as f32 * 137.5) % 360.0 / 360.0, 0.5, 0.45),
+            TrackPalette::ColorblindSafe => {
+                // Each trip through the palette is darker than the last so
+                // tracks far apart stay apart
+                let cycle = (index / COLORBLIND_SAFE.len()) % 3;
+                let shade = 1.0 - cycle as f32 * 0.2;
+                COLORBLIND_SAFE[index % COLORBLIND_SAFE.len()].map(|c| c * shade)
+            }
+        }
+    }
+}
+
+fn hsl_to_rgb(hue: f32, saturation: f32, lightness: f32) -> [f32; 3] {
+    let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+    let sector = hue * 6.0;
+    let x = chroma * (1.0 - (sector % 2.0 - 1.0).abs());
+    let (r, g, b) = match sector as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = lightness - chroma / 2.0;
+    [r + m, g + m, b + m]
+}
+
+fn ms_to_samples(ms: f32, sample_rate: f32) -> SampleTime {
+    (ms.max(0.0) as f64 * sample_rate as f64 / 1000.0).round() as SampleTime
+}
+
+fn config_dir() -> PathBuf {
+    let from_env = |name: &str| std::env::var_os(name).filter(|v| !v.is_empty()).map(PathBuf::from);
+    if cfg!(target_os = "windows") {
+        from_env("APPDATA")
+    } else if cfg!(target_os = "macos") {
+        from_env("HOME").map(|home| home.join("Library").join("Application Support"))
+    } else {
+        from_env("XDG_CONFIG_HOME").or_else(|| from_env("HOME").map(|home| home.join(".config")))
+    }
+    .unwrap_or_else(std::env::temp_dir)
+}
+
+/// Fade the overlaps between the clips in `moved` and their neighbours on the
+/// same track. Each crossfade is as long as the overlap, capped at
+/// `max_length`: the later clip fades in from its start and the earlier one
+/// fades out to its end, both with `shape`. Locked clips keep their fades.
+/// Returns every clip whose fades changed as (clip, fades before, fades
+/// after).
+pub fn crossfade_overlaps(
+    clips: &mut [AudioClip],
+    moved: &[ClipId],
+    max_length: SampleTime,
+    shape: FadeShape,
+) -> Vec<(ClipId, Fades, Fades)> {
+    let mut changes: Vec<(ClipId, Fades, Fades)> = Vec::new();
+    if max_length == 0 {
+        return changes;
+    }
+
+    for a in 0..clips.len() {
+        for b in 0..clips.len() {
+            let (earlier, later) = (&clips[a], &clips[b]);
+            if a == b || !(moved.contains(&earlier.id) || moved.contains(&later.id)) {
+                continue;
+            }
+            // Only partial overlaps where `later` starts inside `earlier`
+            let overlapping = later.start_time > earlier.start_time
+                && later.start_time < earlier.end_time()
+                && later.end_time() > earlier.end_time();
+            if !overlapping {
+                continue;
+            }
+
+            let length = (earlier.end_time() - later.start_time).min(max_length);
+            for (index, fade_in) in [(a, None), (b, Some(length))] {
+                let clip = &mut clips[index];
+                if clip.locked {
+                    continue;
+                }
+                let before = Fades::of(clip);
+                match fade_in {
+                    Some(length) => {
+                        clip.fade_in = length.min(clip.duration - clip.fade_out.min(clip.duration));
+                        clip.fade_in_shape = shape;
+                    }
+                    None => {
+                        clip.fade_out = length.min(clip.duration - clip.fade_in.min(clip.duration));
+                        clip.fade_out_shape = shape;
+                    }
+                }
+                let after = Fades::of(clip);
+                if after == before {
+                    continue;
+                }
+                match changes.iter_mut().find(|(id, ..)| *id == clip.id) {
+                    Some(change) => change.2 = after,
+                    None => changes.push((clip.id, before, after)),
+                }
+            }
+        }
+    }
+    changes
+}
+
+/// Cut holes in the clips under the clips in `placed`, for tracks in
+/// [`OverlapMode::Replace`]. What's left of a clip underneath reaches
+/// `overlap` samples into the placed clip at each side, at most half its
+/// length, so [`crossfade_overlaps`] can fade across the cut; clips wholly
+/// under a placed clip are removed and a clip reaching past both of its ends
+/// is split in two. Locked clips are left whole. Returns whether any clip
+/// was cut.
+pub fn replace_overlaps(clips: &mut Vec<AudioClip>, placed: &[ClipId], overlap: SampleTime) -> bool {
+    let mut changed = false;
+    for placed_id in placed {
+        let Some(top) = clips.iter().find(|c| c.id == *placed_id) else {
+            continue;
+        };
+        let (start, end) = (top.start_time, top.end_time());
+        let overlap = overlap.min(top.duration / 2);
+
+        let mut index = 0;
+        while index < clips.len() {
+            let clip = &clips[index];
+            if placed.contains(&clip.id) || clip.locked || clip.start_time >= end || clip.end_time() <= start {
+                index += 1;
+                continue;
+            }
+            // Already no more than a crossfade's worth inside
+            if (clip.start_time < start && clip.end_time() <= start + overlap)
+                || (clip.end_time() > end && clip.start_time >= end - overlap)
+            {
+                index += 1;
+                continue;
+            }
+
+            // Trimming only shortens, so the clip's own audio bounds it
+            let source_end = clip.offset + clip.source_duration();
+            let head = (clip.start_time < start)
+                .then(|| clip.with_edge_at(false, start + overlap, source_end))
+                .flatten();
+            let tail = (clip.end_time() > end)
+                .then(|| clip.with_edge_at(true, end - overlap, source_end))
+                .flatten()
+                .map(|tail| AudioClip { id: uuid::Uuid::new_v4(), ..tail });
+            changed = true;
+
+            match (head, tail) {
+                (Some(head), Some(tail)) => {
+                    clips[index] = head;
+                    clips.insert(index + 1, tail);
+                    index += 2;
+                }
+                (Some(piece), None) | (None, Some(piece)) => {
+                    clips[index] = piece;
+                    index += 1;
+                }
+                (None, None) => {
+                    clips.remove(index);
+                }
+            }
+        }
+    }
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::test_support::temp_dir;
+
+    #[test]
+    fn test_preferences_round_trip_and_defaults() {
+        let path = temp_dir().join(PREFERENCES_FILE);
+        assert_eq!(EditPreferences::load(&path), EditPreferences::default());
+
+        let prefs = EditPreferences {
+            default_fade_ms: 20.0,
+            auto_crossfade: false,
+            crossfade_ms: 50.0,
+            crossfade_shape: FadeShape::SCurve,
+            track_palette: TrackPalette::ColorblindSafe,
+            high_contrast: true,
+            monitor_level_db: -12.0,
+            smoothing_ms: 0.0,
+            meter_ballistics: MeterBallistics::Vu,
+            waveform: WaveformStyle { rectified: true, rms_overlay: true, log_scale: false },
+            follow_playhead: false,
+            follow_mode: FollowMode::Continuous,
+            sync_source: SyncSource::Timecode,
+            timecode_start_seconds: 3600.0,
+            trim_import_silence: true,
+            import_silence_db: -48.0,
+            import_margin_ms: 10.0,
+        };
+        prefs.save(&path).unwrap();
+        assert_eq!(EditPreferences::load(&path), prefs);
+        std::fs::remove_dir_all(path.parent().unwrap()).ok();
+
+        // Older files without every field keep the defaults for the rest
+        let partial: EditPreferences = serde_json::from_str(r#"{"auto_crossfade": false}"#).unwrap();
+        assert_eq!(partial.default_fade_ms, 5.0);
+        assert_eq!(EditPreferences::default().fade_samples(48000.0), 240);
+        assert_eq!(partial.monitor_gain(), 1.0);
+        assert_eq!(partial.smoothing_samples(48000.0), 480);
+        assert!((prefs.monitor_gain() - 0.251).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_follow_modes_page_and_centre() {
+        // A page at a time: still until the playhead reaches the edge
+        assert_eq!(FollowMode::Page.scroll_for(500.0, 0.0, 800.0), None);
+        assert_eq!(FollowMode::Page.scroll_for(801.0, 0.0, 800.0), Some(800.0));
+        // Looping back or seeking far ahead lands with the playhead at the left
+        assert_eq!(FollowMode::Page.scroll_for(100.0, 800.0, 800.0), Some(100.0));
+        assert_eq!(FollowMode::Page.scroll_for(5000.0, 800.0, 800.0), Some(5000.0));
+
+        assert_eq!(FollowMode::Continuous.scroll_for(1000.0, 0.0, 800.0), Some(600.0));
+        assert_eq!(FollowMode::Continuous.scroll_for(200.0, 0.0, 800.0), None);
+        assert_eq!(FollowMode::Continuous.scroll_for(1000.0, 600.2, 800.0), None);
+        assert_eq!(FollowMode::Page.scroll_for(1000.0, 0.0, 0.0), None);
+    }
+
+    #[test]
+    fn test_imports_start_a_margin_before_the_first_sound() {
+        // Stereo, 1000 frames of silence, a hit on the right, then silence
+        let mut samples = vec![0.0_f32; 2 * 4000];
+        for frame in 1000..2000 {
+            samples[frame * 2 + 1] = 0.5;
+        }
+        let asset = AudioAssetData {
+            asset_ref: AudioAssetRef {
+                path: "hit.wav".into(),
+                sample_rate: 48000.0,
+                channels: 2,
+                duration_samples: 4000,
+                source: SourceFormat::default(),
+                health: Default::default(),
+            },
+            samples: std::sync::Arc::new(samples),
+        };
+        let mut clip = AudioClip::new("hit.wav".into(), 96_000, 4000);
+        let prefs = EditPreferences { trim_import_silence: true, import_margin_ms: 5.0, ..EditPreferences::default() };
+
+        assert!(prefs.trim_imported_silence(&mut clip, &asset));
+        // 5 ms is 240 frames kept either side, and the clip stays where it was dropped
+        assert_eq!((clip.start_time, clip.offset, clip.duration), (96_000, 760, 1480));
+
+        let mut untouched = AudioClip::new("hit.wav".into(), 0, 4000);
+        assert!(!EditPreferences::default().trim_imported_silence(&mut untouched, &asset));
+        assert_eq!((untouched.offset, untouched.duration), (0, 4000));
+    }
+
+    #[test]
+    fn test_waveform_scale() {
+        let linear = WaveformStyle::default();
+        assert_eq!((linear.scale(0.5), linear.scale(-0.25), linear.scale(1.5)), (0.5, -0.25, 1.0));
+
+        // On the log scale -24 dB sits halfway and the floor disappears
+        let log = WaveformStyle { log_scale: true, ..linear };
+        assert_eq!(log.scale(1.0), 1.0);
+        assert!((log.scale(-0.0631) + 0.5).abs() < 1e-3);
+        assert_eq!((log.scale(0.001), log.scale(0.0)), (0.0, 0.0));
+        assert!(log.scale(0.01) > 0.15 && linear.scale(0.01) < 0.02);
+    }
+
+    #[test]
+    fn test_crossfade_overlaps() {
+        let mut clips = vec![
+            AudioClip::new("a.wav".into(), 0, 10_000),
+            AudioClip::new("b.wav".into(), 9_000, 10_000),
+            AudioClip::new("c.wav".into(), 30_000, 1_000),
+        ];
+        let moved = vec![clips[1].id];
+
+        let changes = crossfade_overlaps(&mut clips, &moved, 480, FadeShape::EqualPower);
+        assert_eq!(changes.len(), 2);
+        assert_eq!(clips[0].fade_out, 480);
+        assert_eq!(clips[1].fade_in, 480);
+        assert_eq!((clips[0].fade_out_shape, clips[1].fade_in_shape), (FadeShape::EqualPower, FadeShape::EqualPower));
+        assert_eq!(clips[0].fade_in_shape, FadeShape::Power);
+        assert_eq!((clips[2].fade_in, clips[2].fade_out), (0, 0));
+
+        // A short overlap is faded over its whole length
+        clips[1].start_time = 9_900;
+        crossfade_overlaps(&mut clips, &moved, 480, FadeShape::EqualPower);
+        assert_eq!((clips[0].fade_out, clips[1].fade_in), (100, 100));
+
+        // Across the default crossfade the power of the two clips adds up to
+        // full level throughout
+        let start = clips[1].start_time;
+        for time in start..start + 100 {
+            let power = clips[0].fade_at(time).powi(2) + clips[1].fade_at(time).powi(2);
+            assert!((power - 1.0).abs() < 0.01, "{} at {}", power, time);
+        }
+    }
+
+    #[test]
+    fn test_replace_cuts_a_crossfaded_hole() {
+        let mut clips = vec![
+            AudioClip::new("room.wav".into(), 0, 10_000),
+            AudioClip::new("cough.wav".into(), 4_500, 500),
+            AudioClip::new("line.wav".into(), 4_000, 2_000),
+        ];
+        let placed = vec![clips[2].id];
+        assert!(replace_overlaps(&mut clips, &placed, 100));
+
+        // The room tone is split around the new line and the cough under it is gone
+        let spans: Vec<(SampleTime, SampleTime)> = clips.iter().map(|c| (c.start_time, c.end_time())).collect();
+        assert_eq!(spans, [(0, 4_100), (5_900, 10_000), (4_000, 6_000)]);
+        assert_eq!((clips[0].offset, clips[1].offset), (0, 5_900));
+        assert_ne!(clips[0].id, clips[1].id);
+
+        // The pieces reach a crossfade into the line on each side
+        crossfade_overlaps(&mut clips, &placed, 100, FadeShape::EqualPower);
+        assert_eq!((clips[0].fade_out, clips[2].fade_in, clips[2].fade_out, clips[1].fade_in), (100, 100, 100, 100));
+
+        // Nothing left to cut the second time
+        assert!(!replace_overlaps(&mut clips, &placed, 100));
+    }
+
+    #[test]
+    fn test_overlaps_leave_locked_clips_alone() {
+        let mut clips = vec![
+            AudioClip { locked: true, ..AudioClip::new("room.wav".into(), 0, 10_000) },
+            AudioClip::new("line.wav".into(), 4_000, 2_000),
+            AudioClip::new("tail.wav".into(), 5_000, 10_000),
+        ];
+        let placed = vec![clips[1].id];
+        let shape = |c: &AudioClip| (c.id, c.start_time, c.duration, c.offset, c.fade_in, c.fade_out);
+        let locked = shape(&clips[0]);
+
+        // The unlocked clip under the new one is still cut, the locked one isn't
+        assert!(replace_overlaps(&mut clips, &placed, 100));
+        assert_eq!(shape(&clips[0]), locked);
+        assert_eq!((clips[2].start_time, clips[2].end_time()), (5_900, 15_000));
+
+        // Crossfading fades the clip that moved but not the locked one it
+        // lands on
+        let mut clips = vec![
+            AudioClip { locked: true, ..AudioClip::new("a.wav".into(), 0, 10_000) },
+            AudioClip::new("b.wav".into(), 9_000, 10_000),
+        ];
+        let (locked, moved) = (shape(&clips[0]), [clips[1].id]);
+        let changes = crossfade_overlaps(&mut clips, &moved, 480, FadeShape::EqualPower);
+        assert_eq!(shape(&clips[0]), locked);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(clips[1].fade_in, 480);
+    }
+
+    #[test]
+    fn test_fade_presets() {
+        let presets = FadePresets {
+            user: vec![FadePreset::new("Slow Swell", FadeShape::SCurve, 0.4), FadePreset::new("Linear", FadeShape::Power, 0.9)],
+        };
+        let names: Vec<String> = presets.all().into_iter().map(|p| p.name).collect();
+        assert_eq!(names, ["Linear", "Equal Power", "S-Curve", "Fast In / Slow Out", "Slow Swell"]);
+
+        let path = temp_dir().join(FADE_PRESETS_FILE);
+        assert_eq!(FadePresets::load(&path), FadePresets::default());
+        presets.save(&path).unwrap();
+        assert_eq!(FadePresets::load(&path), presets);
+        std::fs::remove_dir_all(path.parent().unwrap()).ok();
+
+        let mut clip = AudioClip::new("a.wav".into(), 0, 1_000);
+        let fast = &FadePreset::built_in()[3];
+        fast.apply(&mut clip, FadeEnds::In);
+        assert!(fast.matches(clip.fade_in_shape, clip.fade_in_curve));
+        assert_eq!((clip.fade_out_shape, clip.fade_out_curve), (FadeShape::Power, 0.0));
+        presets.user[0].apply(&mut clip, FadeEnds::Both);
+        assert_eq!((clip.fade_in_shape, clip.fade_out_shape), (FadeShape::SCurve, FadeShape::SCurve));
+        assert_eq!(clip.fade_out_curve, 0.4);
+    }
+
+    #[test]
+    fn test_neighbouring_track_colors_differ() {
+        let distance = |a: [f32; 3], b: [f32; 3]| a.iter().zip(b).map(|(x, y)| (x - y).powi(2)).sum::<f32>().sqrt();
+        for palette in TrackPalette::ALL {
+            for index in 0..32 {
+                let (a, b) = (palette.color(index), palette.color(index + 1));
+                assert!(distance(a, b) > 0.25, "{:?} tracks {} and {}: {:?} {:?}", palette, index, index + 1, a, b);
+                assert!(a.iter().all(|c| (0.0..=1.0).contains(c)));
+            }
+        }
+        assert_eq!(TrackPalette::ColorblindSafe.color(1), [0.0, 0.447, 0.698]);
+    }
+}
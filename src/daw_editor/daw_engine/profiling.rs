@@ -0,0 +1,174 @@
+/// DSP profiling: how long each track and each insert effect takes per buffer
+/// Off by default. While it's on, the audio graph reads the clock around
+/// every track and every effect it runs, a few tens of nanoseconds each, and
+/// keeps a rolling average and a decaying peak of the time spent per buffer.
+/// The UI reads a [`DspProfile`] snapshot to show what's worth freezing.
+use super::audio_types::TrackId;
+use super::effects::EffectId;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::time::{Duration, Instant};
+
+/// Weight of the newest buffer in the rolling average, so it follows about
+/// the last 20 buffers
+const AVERAGE_WEIGHT: f32 = 0.05;
+
+/// Share of the peak kept each buffer, so a spike stays readable for a
+/// second or so before falling back
+const PEAK_RELEASE: f32 = 0.995;
+
+/// Time one stage took per buffer, in microseconds
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ProcessTiming {
+    pub average_us: f32,
+    pub peak_us: f32,
+    /// Time gathered so far in the buffer being processed. A stage can run
+    /// several times per buffer when it's split up for automation.
+    block_us: f32,
+}
+
+impl ProcessTiming {
+    pub fn add(&mut self, elapsed: Duration) {
+        self.block_us += elapsed.as_secs_f32() * 1_000_000.0;
+    }
+
+    /// Fold the buffer just finished into the average and peak. A stage
+    /// that didn't run in it counts as zero.
+    fn finish_block(&mut self) {
+        self.average_us += (self.block_us - self.average_us) * AVERAGE_WEIGHT;
+        self.peak_us = self.block_us.max(self.peak_us * PEAK_RELEASE);
+        self.block_us = 0.0;
+    }
+}
+
+/// Timings of one kind of stage, by id. Does nothing while disabled, so the
+/// clock is only read when someone is looking.
+#[derive(Debug, Clone)]
+pub struct Timings<K> {
+    enabled: bool,
+    timings: HashMap<K, ProcessTiming>,
+}
+
+impl<K> Default for Timings<K> {
+    fn default() -> Self {
+        Self { enabled: false, timings: HashMap::new() }
+    }
+}
+
+impl<K: Copy + Eq + Hash> Timings<K> {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Turn measuring on or off. Turning it off forgets what was measured.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        if !enabled {
+            self.timings.clear();
+        }
+    }
+
+    /// The time to measure from, or None while disabled
+    pub fn start(&self) -> Option<Instant> {
+        self.enabled.then(Instant::now)
+    }
+
+    /// Add the time since `started` to `key`'s current buffer
+    pub fn record(&mut self, key: K, started: Option<Instant>) {
+        if let Some(started) = started {
+            self.timings.entry(key).or_default().add(started.elapsed());
+        }
+    }
+
+    pub fn finish_block(&mut self) {
+        self.timings.values_mut().for_each(ProcessTiming::finish_block);
+    }
+
+    pub fn get(&self, key: K) -> Option<ProcessTiming> {
+        self.timings.get(&key).copied()
+    }
+
+    pub fn retain(&mut self, mut keep: impl FnMut(&K) -> bool) {
+        self.timings.retain(|key, _| keep(key));
+    }
+
+    pub fn snapshot(&self) -> HashMap<K, ProcessTiming> {
+        self.timings.clone()
+    }
+}
+
+/// What the audio graph measured, for the UI
+#[derive(Debug, Clone, Default)]
+pub struct DspProfile {
+    /// Whole time of each track: playback of its clips or input, its
+    /// effects and its automation
+    pub tracks: HashMap<TrackId, ProcessTiming>,
+    pub effects: HashMap<EffectId, ProcessTiming>,
+    /// Frames in the last buffer, to tell the time there was to spend
+    pub block_frames: usize,
+}
+
+impl DspProfile {
+    /// Time one buffer lasts at `sample_rate`, in microseconds
+    pub fn budget_us(&self, sample_rate: f32) -> f32 {
+        self.block_frames as f32 / sample_rate.max(1.0) * 1_000_000.0
+    }
+
+    /// Average time of a track outside its effects: reading clips, stretching
+    /// and resampling, input monitoring and automation
+    pub fn playback_us(&self, track_id: TrackId, effects: impl IntoIterator<Item = EffectId>) -> f32 {
+        let total = self.tracks.get(&track_id).map_or(0.0, |t| t.average_us);
+        let chain: f32 = effects
+            .into_iter()
+            .filter_map(|id| self.effects.get(&id))
+            .map(|t| t.average_us)
+            .sum();
+        (total - chain).max(0.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timings_average_per_buffer_and_only_when_enabled() {
+        let mut timings: Timings<u32> = Timings::default();
+        timings.record(1, timings.start());
+        assert_eq!(timings.get(1), None);
+
+        timings.set_enabled(true);
+        for _ in 0..200 {
+            // Two runs of 10 us in a buffer count as one of 20 us
+            timings.timings.entry(1).or_default().add(Duration::from_micros(10));
+            timings.timings.entry(1).or_default().add(Duration::from_micros(10));
+            timings.finish_block();
+        }
+        let timing = timings.get(1).unwrap();
+        assert!((timing.average_us - 20.0).abs() < 0.01, "{:?}", timing);
+        assert!((timing.peak_us - 20.0).abs() < 0.01);
+
+        // A spike shows in the peak far more than in the average
+        timings.timings.entry(1).or_default().add(Duration::from_micros(400));
+        timings.finish_block();
+        let timing = timings.get(1).unwrap();
+        assert!((timing.peak_us - 400.0).abs() < 0.01);
+        assert!(timing.average_us < 50.0);
+
+        timings.set_enabled(false);
+        assert_eq!(timings.get(1), None);
+    }
+
+    #[test]
+    fn test_playback_is_the_track_less_its_effects() {
+        let (track, reverb) = (uuid::Uuid::new_v4(), uuid::Uuid::new_v4());
+        let timing = |average_us| ProcessTiming { average_us, ..ProcessTiming::default() };
+        let profile = DspProfile {
+            tracks: HashMap::from([(track, timing(120.0))]),
+            effects: HashMap::from([(reverb, timing(90.0))]),
+            block_frames: 480,
+        };
+        assert_eq!(profile.playback_us(track, [reverb]), 30.0);
+        assert_eq!(profile.budget_us(48000.0), 10_000.0);
+    }
+}
@@ -0,0 +1,1093 @@
+/// DAW project serialization and file format (.pdaw)
+use super::audio_types::*;
+use super::automation_recording::ControlMapping;
+use super::effects::EffectKind;
+use super::limiter::LimiterSettings;
+use super::quick_filters::QuickFilters;
+use super::surround::{ChannelLayout, SurroundPan};
+use anyhow::{Context as AnyhowContext, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::io::Write;
+use std::path::Path;
+
+/// DAW project file format version
+/// 2: return tracks are solo safe by default
+const PROJECT_VERSION: u32 = 2;
+
+/// Supported tempo range in BPM
+pub const MIN_TEMPO: f32 = 20.0;
+pub const MAX_TEMPO: f32 = 999.0;
+
+/// Most beats a bar can have, and the shortest note value a beat can be
+pub const MAX_BEATS_PER_BAR: u32 = 32;
+pub const MAX_BEAT_VALUE: u32 = 32;
+
+/// Folder in the project directory that holds its audio files. Imports,
+/// bounces and flattened tracks all go here.
+pub const AUDIO_FOLDER: &str = "audio";
+
+/// Parse a typed tempo such as "128" or "92.5 bpm". The error says what's
+/// accepted.
+pub fn parse_tempo(text: &str) -> Result<f32> {
+    let number = text.trim().trim_end_matches(|c: char| c.is_ascii_alphabetic()).trim();
+    match number.parse::<f32>() {
+        Ok(tempo) if (MIN_TEMPO..=MAX_TEMPO).contains(&tempo) => Ok(tempo),
+        _ => Err(anyhow::anyhow!("Tempo must be a number from {} to {} BPM", MIN_TEMPO, MAX_TEMPO)),
+    }
+}
+
+/// Parse a typed time signature such as "7/8". The error says what's
+/// accepted.
+pub fn parse_time_signature(text: &str) -> Result<(u32, u32)> {
+    let (numerator, denominator) = text
+        .split_once('/')
+        .and_then(|(n, d)| Some((n.trim().parse::<u32>().ok()?, d.trim().parse::<u32>().ok()?)))
+        .ok_or_else(|| anyhow::anyhow!("Time signature must be written as beats/value, like 3/4 or 7/8"))?;
+    check_time_signature(numerator, denominator)?;
+    Ok((numerator, denominator))
+}
+
+fn check_time_signature(numerator: u32, denominator: u32) -> Result<()> {
+    if !(1..=MAX_BEATS_PER_BAR).contains(&numerator) {
+        return Err(anyhow::anyhow!("A bar must have from 1 to {} beats", MAX_BEATS_PER_BAR));
+    }
+    if !denominator.is_power_of_two() || denominator > MAX_BEAT_VALUE {
+        return Err(anyhow::anyhow!("The beat value must be a power of two up to {}, like 4 or 8", MAX_BEAT_VALUE));
+    }
+    Ok(())
+}
+
+/// Complete DAW project state
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DawProject {
+    pub version: u32,
+    pub name: String,
+    pub created_at: String,
+    pub modified_at: String,
+    pub sample_rate: f32,
+    pub tracks: Vec<Track>,
+    pub transport: Transport,
+    pub master_track: Track,
+    /// Brickwall limiter on the master bus
+    #[serde(default)]
+    pub master_limiter: LimiterSettings,
+    /// Named timeline positions, in the order they were added
+    #[serde(default)]
+    pub markers: Vec<Marker>,
+    /// Named sections of the arrangement, drawn in the lane above the ruler
+    #[serde(default)]
+    pub regions: Vec<Region>,
+    /// Speaker layout of the master bus
+    #[serde(default)]
+    pub channel_layout: ChannelLayout,
+    /// Hardware controllers learned onto track parameters
+    #[serde(default)]
+    pub control_mappings: Vec<ControlMapping>,
+}
+
+impl DawProject {
+    pub fn new(name: impl Into<String>) -> Self {
+        let now = chrono::Utc::now().to_rfc3339();
+        
+        Self {
+            version: PROJECT_VERSION,
+            name: name.into(),
+            created_at: now.clone(),
+            modified_at: now,
+            sample_rate: SAMPLE_RATE,
+            tracks: Vec::new(),
+            transport: Transport::default(),
+            master_track: Track::new("Master", TrackType::Master),
+            master_limiter: LimiterSettings::default(),
+            markers: Vec::new(),
+            regions: Vec::new(),
+            channel_layout: ChannelLayout::default(),
+            control_mappings: Vec::new(),
+        }
+    }
+
+    /// Load project from file
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let contents = std::fs::read_to_string(path.as_ref())
+            .context("Failed to read project file")?;
+        
+        let mut project: DawProject = serde_json::from_str(&contents)
+            .context("Failed to parse project file")?;
+        // Older files only store the rate on the project
+        project.transport.sample_rate = project.sample_rate;
+
+        if project.version > PROJECT_VERSION {
+            return Err(anyhow::anyhow!(
+                "Project version {} is newer than supported version {}",
+                project.version,
+                PROJECT_VERSION
+            ));
+        }
+        if project.version < 2 {
+            // Buses used to ignore solo, keep them audible as before
+            for track in &mut project.tracks {
+                track.solo_safe = track.track_type.is_bus();
+            }
+        }
+        project.version = PROJECT_VERSION;
+
+        Ok(project)
+    }
+
+    /// Save project to file. The project is serialized in memory and checked
+    /// to load back before anything is written, then written to a temporary
+    /// file next to the target and renamed into place, so a failed save
+    /// never truncates the project or leaves one that can't be opened.
+    /// Numbers that aren't finite are saved as safe defaults, see
+    /// [`Self::sanitize_numbers`].
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let mut project_clone = self.clone();
+        project_clone.modified_at = chrono::Utc::now().to_rfc3339();
+        for fix in project_clone.sanitize_numbers() {
+            tracing::error!("⚠️ Saving '{}': {}", self.name, fix);
+        }
+        project_clone.check_unique_ids().context("Refusing to save the project")?;
+
+        let contents = serde_json::to_string_pretty(&project_clone)
+            .context("Failed to serialize project")?;
+        serde_json::from_str::<DawProject>(&contents)
+            .context("Refusing to save the project: it wouldn't load back")?;
+
+        let path = path.as_ref();
+        let mut temp_name = path.file_name().map(|n| n.to_os_string()).unwrap_or_default();
+        temp_name.push(".tmp");
+        let temp_path = path.with_file_name(temp_name);
+
+        let write_result = write_and_replace(&temp_path, path, contents.as_bytes());
+        if write_result.is_err() {
+            let _ = std::fs::remove_file(&temp_path);
+        }
+        write_result
+    }
+
+    /// Replace NaN and infinite numbers, which JSON can't hold and serde_json
+    /// writes as `null`, making the file unloadable. Volumes go back to unity,
+    /// pans to centre, effect parameters and mixes to their defaults, quick
+    /// filters to open, and automation points without a usable value are
+    /// dropped. Returns what was fixed.
+    pub fn sanitize_numbers(&mut self) -> Vec<String> {
+        let mut fixes = Vec::new();
+        fix_number(&mut fixes, &mut self.sample_rate, SAMPLE_RATE, || "Project sample rate".to_string());
+        let transport = Transport::default();
+        fix_number(&mut fixes, &mut self.transport.tempo, transport.tempo, || "Tempo".to_string());
+        fix_number(&mut fixes, &mut self.transport.sample_rate, self.sample_rate, || "Transport sample rate".to_string());
+        fix_number(&mut fixes, &mut self.transport.loop_crossfade_ms, transport.loop_crossfade_ms, || "Loop crossfade".to_string());
+        let limiter = LimiterSettings::default();
+        fix_number(&mut fixes, &mut self.master_limiter.ceiling_db, limiter.ceiling_db, || "Limiter ceiling".to_string());
+        fix_number(&mut fixes, &mut self.master_limiter.release_ms, limiter.release_ms, || "Limiter release".to_string());
+
+        let mut dropped_points = Vec::new();
+        for track in self.tracks.iter_mut().chain(std::iter::once(&mut self.master_track)) {
+            let name = track.name.clone();
+            fix_number(&mut fixes, &mut track.volume, 1.0, || format!("Volume of '{}'", name));
+            fix_number(&mut fixes, &mut track.pan, 0.0, || format!("Pan of '{}'", name));
+            let surround = SurroundPan::default();
+            fix_number(&mut fixes, &mut track.surround_pan.x, surround.x, || format!("Surround pan of '{}'", name));
+            fix_number(&mut fixes, &mut track.surround_pan.y, surround.y, || format!("Surround pan of '{}'", name));
+            fix_number(&mut fixes, &mut track.surround_pan.lfe, surround.lfe, || format!("LFE send of '{}'", name));
+            for channel in &mut track.color {
+                fix_number(&mut fixes, channel, 0.5, || format!("Color of '{}'", name));
+            }
+            for (slot, send) in track.sends.iter_mut().enumerate() {
+                fix_number(&mut fixes, &mut send.amount, 0.0, || format!("Send {} of '{}'", slot + 1, name));
+            }
+            let filters = QuickFilters::default();
+            fix_number(&mut fixes, &mut track.quick_filters.high_pass_hz, filters.high_pass_hz, || format!("High-pass of '{}'", name));
+            fix_number(&mut fixes, &mut track.quick_filters.low_pass_hz, filters.low_pass_hz, || format!("Low-pass of '{}'", name));
+            for effect in &mut track.effects {
+                let default_mix = effect.kind.default_mix();
+                let label = effect.kind.label();
+                fix_number(&mut fixes, &mut effect.mix, default_mix, || format!("{} mix on '{}'", label, name));
+                let defaults = EffectKind::all().into_iter().find(|kind| kind.same_type(&effect.kind));
+                for (index, info) in effect.kind.params().iter().enumerate() {
+                    let value = effect.kind.param(index).unwrap_or(0.0);
+                    if !value.is_finite() {
+                        let fallback = defaults.as_ref().and_then(|kind| kind.param(index)).unwrap_or(info.min);
+                        fixes.push(format!("{} {} on '{}' was {}, saved as {}", effect.kind.label(), info.name, name, value, fallback));
+                        effect.kind.set_param(index, fallback);
+                    }
+                }
+                if let Some(ref mut slot) = effect.plugin {
+                    let plugin_name = slot.name.clone();
+                    slot.params.retain(|param| {
+                        if !param.value.is_finite() {
+                            fixes.push(format!("Parameter {} of {} on '{}' was {}, left to the plugin", param.id, plugin_name, name, param.value));
+                        }
+                        param.value.is_finite()
+                    });
+                }
+            }
+            for clip in &mut track.clips {
+                let clip_name = clip.name.clone();
+                fix_number(&mut fixes, &mut clip.gain, 1.0, || format!("Gain of clip '{}' on '{}'", clip_name, name));
+                fix_number(&mut fixes, &mut clip.fade_in_curve, 0.0, || format!("Fade-in curve of clip '{}' on '{}'", clip_name, name));
+                fix_number(&mut fixes, &mut clip.fade_out_curve, 0.0, || format!("Fade-out curve of clip '{}' on '{}'", clip_name, name));
+                if !(clip.time_ratio.is_finite() && clip.time_ratio > 0.0) {
+                    fixes.push(format!("Time ratio of clip '{}' on '{}' was {}, saved as 1", clip_name, name, clip.time_ratio));
+                    clip.time_ratio = 1.0;
+                }
+            }
+            for lane in &mut track.automation {
+                let before = lane.points.len();
+                lane.points.retain(|point| point.value.is_finite());
+                if lane.points.len() < before {
+                    dropped_points.push((before - lane.points.len(), name.clone()));
+                }
+                for point in &mut lane.points {
+                    fix_number(&mut fixes, &mut point.tension, 0.0, || format!("Curve tension of an automation point on '{}'", name));
+                }
+            }
+        }
+        for (count, name) in dropped_points {
+            fixes.push(format!("Dropped {} automation point(s) on '{}' without a usable value", count, name));
+        }
+        fixes
+    }
+
+    /// Check that no two tracks, clips, markers, regions or effects share an ID.
+    /// Edits and undo find things by ID, so a project with duplicates
+    /// would load but misbehave.
+    pub fn check_unique_ids(&self) -> Result<()> {
+        let mut seen = HashSet::new();
+        let tracks = self.tracks.iter().chain(std::iter::once(&self.master_track));
+        for track in tracks.clone() {
+            if !seen.insert(track.id) {
+                return Err(anyhow::anyhow!("Two tracks share the ID {} (one is '{}')", track.id, track.name));
+            }
+        }
+        for track in tracks.clone() {
+            for clip in &track.clips {
+                if !seen.insert(clip.id) {
+                    return Err(anyhow::anyhow!("Clip '{}' on '{}' has the ID {} of another item", clip.name, track.name, clip.id));
+                }
+            }
+            for effect in &track.effects {
+                if !seen.insert(effect.id) {
+                    return Err(anyhow::anyhow!("{} on '{}' has the ID {} of another item", effect.label(), track.name, effect.id));
+                }
+            }
+        }
+        for marker in &self.markers {
+            if !seen.insert(marker.id) {
+                return Err(anyhow::anyhow!("Marker '{}' has the ID {} of another item", marker.name, marker.id));
+            }
+        }
+        for region in &self.regions {
+            if !seen.insert(region.id) {
+                return Err(anyhow::anyhow!("Region '{}' has the ID {} of another item", region.name, region.id));
+            }
+        }
+        Ok(())
+    }
+
+    /// Add a track to the project
+    pub fn add_track(&mut self, track: Track) {
+        self.tracks.push(track);
+    }
+
+    /// Remove a track from the project
+    pub fn remove_track(&mut self, id: TrackId) {
+        self.tracks.retain(|t| t.id != id);
+    }
+
+    /// Delete a track, disconnecting any sends that target it.
+    /// Returns a snapshot that can restore the track and its routing.
+    pub fn delete_track(&mut self, id: TrackId) -> Option<TrackSnapshot> {
+        let index = self.tracks.iter().position(|t| t.id == id)?;
+        let track = self.tracks.remove(index);
+
+        let mut incoming_sends = Vec::new();
+        for other in self.tracks.iter_mut().chain(std::iter::once(&mut self.master_track)) {
+            for (slot, send) in other.sends.iter_mut().enumerate() {
+                if send.target_track == Some(id) {
+                    incoming_sends.push(SendRef {
+                        source_track: other.id,
+                        slot,
+                        send: send.clone(),
+                    });
+                    *send = Send::default();
+                }
+            }
+        }
+
+        Some(TrackSnapshot {
+            track,
+            index,
+            incoming_sends,
+        })
+    }
+
+    /// Restore a previously deleted track at its original index and reconnect
+    /// sends from other tracks. Returns the IDs of every track that changed.
+    pub fn restore_track(&mut self, snapshot: &TrackSnapshot) -> Vec<TrackId> {
+        let index = snapshot.index.min(self.tracks.len());
+        self.tracks.insert(index, snapshot.track.clone());
+
+        let mut touched = vec![snapshot.track.id];
+        for send_ref in &snapshot.incoming_sends {
+            let source = if self.master_track.id == send_ref.source_track {
+                Some(&mut self.master_track)
+            } else {
+                self.tracks.iter_mut().find(|t| t.id == send_ref.source_track)
+            };
+
+            if let Some(source) = source {
+                while source.sends.len() <= send_ref.slot {
+                    source.sends.push(Send::default());
+                }
+                source.sends[send_ref.slot] = send_ref.send.clone();
+                touched.push(source.id);
+            }
+        }
+
+        touched
+    }
+
+    /// Get a track by ID
+    pub fn get_track(&self, id: TrackId) -> Option<&Track> {
+        self.tracks.iter().find(|t| t.id == id)
+    }
+
+    /// Get a mutable track by ID
+    pub fn get_track_mut(&mut self, id: TrackId) -> Option<&mut Track> {
+        self.tracks.iter_mut().find(|t| t.id == id)
+    }
+
+    /// Get all tracks
+    pub fn get_all_tracks(&self) -> &[Track] {
+        &self.tracks
+    }
+
+    /// Get duration of project in samples
+    pub fn duration(&self) -> SampleTime {
+        let mut max_time = 0u64;
+        
+        for track in &self.tracks {
+            for clip in &track.clips {
+                let end_time = clip.end_time();
+                if end_time > max_time {
+                    max_time = end_time;
+                }
+            }
+        }
+
+        max_time
+    }
+
+    /// End of the last clip in the mix, leaving out reference tracks
+    pub fn mix_duration(&self) -> SampleTime {
+        self.tracks
+            .iter()
+            .filter(|t| t.track_type != TrackType::Reference)
+            .flat_map(|t| t.clips.iter().map(|c| c.end_time()))
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Get duration in seconds
+    pub fn duration_seconds(&self) -> f64 {
+        self.duration() as f64 / self.sample_rate as f64
+    }
+
+    /// Get duration as formatted time string
+    pub fn duration_string(&self) -> String {
+        let seconds = self.duration_seconds();
+        let hours = (seconds / 3600.0) as u32;
+        let minutes = ((seconds % 3600.0) / 60.0) as u32;
+        let secs = (seconds % 60.0) as u32;
+        let millis = ((seconds % 1.0) * 1000.0) as u32;
+        
+        format!("{:02}:{:02}:{:02}.{:03}", hours, minutes, secs, millis)
+    }
+
+    /// Insert `length` samples of silence at `at` across every track. Clips
+    /// spanning `at` are split, everything from `at` on moves later, and
+    /// automation, the loop and punch ranges and regions move with the
+    /// material; regions spanning `at` grow by the silence.
+    /// Split clips get `cut_fade` long fades at the cut.
+    /// Refused if a locked clip would be split or moved, or a locked track's
+    /// automation moved. Returns the IDs of regular tracks whose state changed.
+    pub fn insert_silence(&mut self, at: SampleTime, length: SampleTime, cut_fade: SampleTime) -> Result<Vec<TrackId>> {
+        if length == 0 {
+            return Ok(Vec::new());
+        }
+        for track in self.tracks.iter().chain(std::iter::once(&self.master_track)) {
+            if track.clips.iter().any(|clip| track.clip_locked(clip) && clip.end_time() > at) {
+                return Err(anyhow::anyhow!("The clip is locked; unlock it to edit it"));
+            }
+            if track.locked && track.automation.iter().any(|lane| lane.points.iter().any(|p| p.time >= at)) {
+                return Err(anyhow::anyhow!("The track is locked; unlock it to edit it"));
+            }
+        }
+
+        let master_id = self.master_track.id;
+        let mut touched = Vec::new();
+        for track in self.tracks.iter_mut().chain(std::iter::once(&mut self.master_track)) {
+            let mut changed = false;
+
+            let mut index = 0;
+            while index < track.clips.len() {
+                if let Some((left, right)) = track.clips[index].split_at(at, cut_fade) {
+                    track.clips[index] = left;
+                    track.clips.insert(index + 1, right);
+                    index += 1;
+                }
+                index += 1;
+            }
+            for clip in &mut track.clips {
+                if clip.start_time >= at {
+                    clip.start_time += length;
+                    changed = true;
+                }
+            }
+
+            for lane in &mut track.automation {
+                if lane.points.iter().any(|p| p.time >= at) {
+                    lane.insert_time(at, length);
+                    changed = true;
+                }
+            }
+
+            // The master bus is not a regular track, callers can't resync it by ID
+            if changed && track.id != master_id {
+                touched.push(track.id);
+            }
+        }
+
+        let shift = |start: &mut SampleTime, end: &mut SampleTime| {
+            if *start >= at {
+                *start += length;
+                *end += length;
+            } else if *end > at {
+                *end += length;
+            }
+        };
+        let transport = &mut self.transport;
+        shift(&mut transport.loop_start, &mut transport.loop_end);
+        shift(&mut transport.punch_in, &mut transport.punch_out);
+        for marker in self.markers.iter_mut().filter(|m| m.position >= at) {
+            marker.position += length;
+        }
+        for region in &mut self.regions {
+            shift(&mut region.start, &mut region.end);
+        }
+
+        Ok(touched)
+    }
+
+    /// Lock every track, or unlock every track and clip. Returns whether
+    /// anything changed.
+    pub fn set_all_locked(&mut self, locked: bool) -> bool {
+        let mut changed = false;
+        for track in &mut self.tracks {
+            changed |= track.locked != locked;
+            track.locked = locked;
+            if !locked {
+                for clip in track.clips.iter_mut().filter(|c| c.locked) {
+                    clip.locked = false;
+                    changed = true;
+                }
+            }
+        }
+        changed
+    }
+
+    /// Trim the project to the end of its last clip: automation after the end
+    /// is removed, the loop and punch ranges and regions are clamped to it and
+    /// regions starting after it are dropped. Refused if a locked track's
+    /// automation would be cut. Returns the IDs of regular tracks whose state
+    /// changed.
+    pub fn trim_to_content(&mut self) -> Result<Vec<TrackId>> {
+        let end = self.duration();
+        let cut = |track: &Track| track.automation.iter().any(|lane| lane.points.iter().any(|p| p.time > end));
+        if self.tracks.iter().chain(std::iter::once(&self.master_track)).any(|track| track.locked && cut(track)) {
+            return Err(anyhow::anyhow!("The track is locked; unlock it to edit it"));
+        }
+
+        let master_id = self.master_track.id;
+        let mut touched = Vec::new();
+        for track in self.tracks.iter_mut().chain(std::iter::once(&mut self.master_track)) {
+            let mut changed = false;
+            for lane in &mut track.automation {
+                changed |= lane.truncate_after(end);
+            }
+            if changed && track.id != master_id {
+                touched.push(track.id);
+            }
+        }
+
+        let transport = &mut self.transport;
+        transport.loop_end = transport.loop_end.min(end);
+        transport.loop_start = transport.loop_start.min(transport.loop_end);
+        if transport.loop_start == transport.loop_end {
+            transport.loop_enabled = false;
+        }
+        transport.punch_out = transport.punch_out.min(end);
+        transport.punch_in = transport.punch_in.min(transport.punch_out);
+        self.regions.retain(|region| region.start < end);
+        for region in &mut self.regions {
+            region.end = region.end.min(end);
+        }
+
+        Ok(touched)
+    }
+
+    /// Change the project sample rate. With `KeepMusicalTiming` every sample
+    /// position (clips, fades, source offsets, clip loops, automation, the
+    /// transport loop and punch range, markers, regions) is rescaled so material stays on the
+    /// same beats. Source buffers must be resampled to the new rate either way.
+    pub fn change_sample_rate(&mut self, new_rate: f32, mode: SampleRateChange) -> Result<()> {
+        if !(8_000.0..=384_000.0).contains(&new_rate) {
+            return Err(anyhow::anyhow!("Unsupported sample rate: {} Hz", new_rate));
+        }
+
+        let old_rate = self.sample_rate;
+        if mode == SampleRateChange::KeepMusicalTiming && old_rate != new_rate {
+            let rescale = |samples: SampleTime| rescale_samples(samples, old_rate, new_rate);
+
+            for track in self.tracks.iter_mut().chain(std::iter::once(&mut self.master_track)) {
+                for clip in &mut track.clips {
+                    clip.rescale_timing(old_rate, new_rate);
+                }
+
+                for lane in &mut track.automation {
+                    for point in &mut lane.points {
+                        point.time = rescale(point.time);
+                    }
+                    // Rounding can collapse neighbouring points onto one sample
+                    lane.points.dedup_by_key(|p| p.time);
+                }
+            }
+
+            let transport = &mut self.transport;
+            transport.position = rescale(transport.position);
+            transport.loop_start = rescale(transport.loop_start);
+            transport.loop_end = rescale(transport.loop_end);
+            transport.punch_in = rescale(transport.punch_in);
+            transport.punch_out = rescale(transport.punch_out);
+            for marker in &mut self.markers {
+                marker.position = rescale(marker.position);
+            }
+            for region in &mut self.regions {
+                region.start = rescale(region.start);
+                region.end = rescale(region.end).max(region.start + 1);
+            }
+        }
+
+        self.sample_rate = new_rate;
+        self.transport.sample_rate = new_rate;
+        Ok(())
+    }
+
+    /// Change the project tempo. Clips follow according to their tempo mode;
+    /// automation, markers, regions, the loop and punch ranges and the playhead keep
+    /// their beats.
+    /// Returns the IDs of regular tracks whose state changed.
+    pub fn change_tempo(&mut self, new_tempo: f32) -> Result<Vec<TrackId>> {
+        if !(MIN_TEMPO..=MAX_TEMPO).contains(&new_tempo) {
+            return Err(anyhow::anyhow!("Tempo must be between {} and {} BPM", MIN_TEMPO, MAX_TEMPO));
+        }
+        let old_tempo = self.transport.tempo;
+        if old_tempo == new_tempo {
+            return Ok(Vec::new());
+        }
+        // Beats stay put, so sample positions scale by old / new
+        let rescale = |samples: SampleTime| rescale_samples(samples, new_tempo, old_tempo);
+
+        let master_id = self.master_track.id;
+        let mut touched = Vec::new();
+        for track in self.tracks.iter_mut().chain(std::iter::once(&mut self.master_track)) {
+            for clip in &mut track.clips {
+                clip.follow_tempo_change(old_tempo, new_tempo);
+            }
+            for lane in &mut track.automation {
+                for point in &mut lane.points {
+                    point.time = rescale(point.time);
+                }
+                lane.points.dedup_by_key(|p| p.time);
+            }
+            if track.id != master_id {
+                touched.push(track.id);
+            }
+        }
+
+        let transport = &mut self.transport;
+        transport.position = rescale(transport.position);
+        transport.loop_start = rescale(transport.loop_start);
+        transport.loop_end = rescale(transport.loop_end);
+        transport.punch_in = rescale(transport.punch_in);
+        transport.punch_out = rescale(transport.punch_out);
+        transport.tempo = new_tempo;
+        for marker in &mut self.markers {
+            marker.position = rescale(marker.position);
+        }
+        for region in &mut self.regions {
+            region.start = rescale(region.start);
+            region.end = rescale(region.end).max(region.start + 1);
+        }
+        Ok(touched)
+    }
+
+    /// Change the time signature. Beats keep their length, so nothing on
+    /// the timeline moves; only the bars are counted differently.
+    pub fn change_time_signature(&mut self, numerator: u32, denominator: u32) -> Result<()> {
+        check_time_signature(numerator, denominator)?;
+        self.transport.time_signature_numerator = numerator;
+        self.transport.time_signature_denominator = denominator;
+        Ok(())
+    }
+
+    /// Validate project integrity
+    pub fn validate(&self) -> Result<()> {
+        for track in &self.tracks {
+            for clip in &track.clips {
+                if clip.start_time >= clip.end_time() {
+                    return Err(anyhow::anyhow!(
+                        "Invalid clip timing in track '{}': start >= end",
+                        track.name
+                    ));
+                }
+            }
+
+            for automation in &track.automation {
+                if automation.points.is_empty() {
+                    continue;
+                }
+
+                for window in automation.points.windows(2) {
+                    if window[0].time >= window[1].time {
+                        return Err(anyhow::anyhow!(
+                            "Invalid automation timing in track '{}'",
+                            track.name
+                        ));
+                    }
+                }
+            }
+        }
+
+        for region in &self.regions {
+            if region.start >= region.end {
+                return Err(anyhow::anyhow!("Invalid timing of region '{}': start >= end", region.name));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Export project to a different format (stub for future formats)
+    pub fn export(&self, path: impl AsRef<Path>, format: ExportFormat) -> Result<()> {
+        match format {
+            ExportFormat::Json => self.save(path),
+            ExportFormat::Ron => {
+                let contents = ron::to_string(&self)
+                    .context("Failed to serialize project to RON")?;
+                std::fs::write(path.as_ref(), contents)
+                    .context("Failed to write RON file")?;
+                Ok(())
+            }
+        }
+    }
+
+    /// Import project from a different format (stub for future formats)
+    pub fn import(path: impl AsRef<Path>, format: ExportFormat) -> Result<Self> {
+        match format {
+            ExportFormat::Json => Self::load(path),
+            ExportFormat::Ron => {
+                let contents = std::fs::read_to_string(path.as_ref())
+                    .context("Failed to read RON file")?;
+                let project: DawProject = ron::from_str(&contents)
+                    .context("Failed to parse RON file")?;
+                Ok(project)
+            }
+        }
+    }
+}
+
+/// Set a number that isn't finite to `fallback`, noting it in `fixes`
+fn fix_number(fixes: &mut Vec<String>, value: &mut f32, fallback: f32, what: impl FnOnce() -> String) {
+    if !value.is_finite() {
+        fixes.push(format!("{} was {}, saved as {}", what(), value, fallback));
+        *value = fallback;
+    }
+}
+
+/// Write `contents` to `temp_path`, flush it to disk, then rename it over `path`
+fn write_and_replace(temp_path: &Path, path: &Path, contents: &[u8]) -> Result<()> {
+    let mut file = std::fs::File::create(temp_path)
+        .context("Failed to create temporary project file")?;
+    file.write_all(contents)
+        .context("Failed to write project file")?;
+    file.sync_all()
+        .context("Failed to flush project file")?;
+    std::fs::rename(temp_path, path)
+        .context("Failed to replace project file")?;
+    Ok(())
+}
+
+/// A send on another track that pointed at a deleted track
+#[derive(Debug, Clone)]
+pub struct SendRef {
+    pub source_track: TrackId,
+    pub slot: usize,
+    pub send: Send,
+}
+
+/// Everything needed to put a deleted track back exactly where it was
+#[derive(Debug, Clone)]
+pub struct TrackSnapshot {
+    pub track: Track,
+    pub index: usize,
+    pub incoming_sends: Vec<SendRef>,
+}
+
+/// Export format options
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Json,
+    Ron,
+}
+
+/// Create a default demo project
+pub fn create_demo_project() -> DawProject {
+    let mut project = DawProject::new("Demo Project");
+    
+    let mut track1 = Track::new("Drums", TrackType::Audio);
+    track1.color = [0.9, 0.3, 0.3];
+    project.add_track(track1);
+
+    let mut track2 = Track::new("Bass", TrackType::Audio);
+    track2.color = [0.3, 0.9, 0.3];
+    project.add_track(track2);
+
+    let mut track3 = Track::new("Synth", TrackType::Audio);
+    track3.color = [0.3, 0.3, 0.9];
+    project.add_track(track3);
+
+    let mut aux_track = Track::new("Reverb", TrackType::Aux);
+    aux_track.color = [0.7, 0.7, 0.3];
+    project.add_track(aux_track);
+
+    project
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_project_creation() {
+        let project = DawProject::new("Test Project");
+        assert_eq!(project.name, "Test Project");
+        assert_eq!(project.version, PROJECT_VERSION);
+        assert_eq!(project.tracks.len(), 0);
+    }
+
+    #[test]
+    fn test_add_remove_track() {
+        let mut project = DawProject::new("Test");
+        let track = Track::new("Track 1", TrackType::Audio);
+        let id = track.id;
+        
+        project.add_track(track);
+        assert_eq!(project.tracks.len(), 1);
+        
+        project.remove_track(id);
+        assert_eq!(project.tracks.len(), 0);
+    }
+
+    #[test]
+    fn test_lock_and_unlock_all() {
+        let mut project = DawProject::new("Test");
+        let mut track = Track::new("Drums", TrackType::Audio);
+        track.clips.push(AudioClip::new("kick.wav".into(), 0, 48000));
+        track.clips[0].locked = true;
+        project.add_track(track);
+        project.add_track(Track::new("Bass", TrackType::Audio));
+
+        assert!(project.tracks[0].clip_locked(&project.tracks[0].clips[0]));
+        assert!(project.set_all_locked(true));
+        assert!(project.tracks.iter().all(|t| t.locked));
+        assert!(!project.set_all_locked(true));
+
+        // Unlocking all clears the clip's own lock too
+        assert!(project.set_all_locked(false));
+        assert!(!project.tracks[0].locked && !project.tracks[0].clips[0].locked);
+        assert!(!project.set_all_locked(false));
+    }
+
+    #[test]
+    fn test_save_load() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let path = temp_dir.path().join("test.pdaw");
+        
+        let mut project = DawProject::new("Test Save");
+        project.add_track(Track::new("Track 1", TrackType::Audio));
+        
+        project.save(&path)?;
+        
+        let loaded = DawProject::load(&path)?;
+        assert_eq!(loaded.name, "Test Save");
+        assert_eq!(loaded.tracks.len(), 1);
+        
+        Ok(())
+    }
+
+    #[test]
+    fn test_save_sanitizes_numbers_json_cant_hold() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let path = temp_dir.path().join("nan.pdaw");
+
+        let mut project = DawProject::new("NaN");
+        let mut track = Track::new("Vocal", TrackType::Audio);
+        track.volume = f32::NAN;
+        track.pan = f32::INFINITY;
+        track.effects.push(super::super::effects::Effect::new(EffectKind::Gain { gain_db: f32::NAN }));
+        let mut lane = AutomationLane::new(AutomationParameter::Volume);
+        for (time, value) in [(0, 0.5), (100, f32::NAN)] {
+            lane.add_point(AutomationPoint {
+                id: uuid::Uuid::new_v4(),
+                time,
+                value,
+                curve_type: CurveType::Linear,
+                tension: 0.0,
+            });
+        }
+        track.automation.push(lane);
+        project.add_track(track);
+
+        let mut sanitized = project.clone();
+        assert_eq!(sanitized.sanitize_numbers().len(), 4);
+        project.save(&path)?;
+        let loaded = DawProject::load(&path)?;
+        let track = &loaded.tracks[0];
+        assert_eq!((track.volume, track.pan), (1.0, 0.0));
+        assert_eq!(track.effects[0].kind.param(0), Some(0.0));
+        assert_eq!(track.automation[0].points.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_sanitize_resets_effect_mixes_and_quick_filters() {
+        let mut project = DawProject::new("NaN");
+        let mut track = Track::new("Vocal", TrackType::Audio);
+        track.quick_filters.high_pass_hz = f32::NAN;
+        track.quick_filters.low_pass_hz = f32::INFINITY;
+        let mut reverb = super::super::effects::Effect::new(EffectKind::ConvolutionReverb { level_db: 0.0 });
+        reverb.mix = f32::NAN;
+        track.effects.push(reverb);
+        project.add_track(track);
+
+        assert_eq!(project.sanitize_numbers().len(), 3);
+        let track = &project.tracks[0];
+        assert_eq!(track.quick_filters, QuickFilters::default());
+        assert_eq!(track.effects[0].mix, track.effects[0].kind.default_mix());
+        assert!(project.sanitize_numbers().is_empty());
+    }
+
+    #[test]
+    fn test_save_refuses_duplicate_ids() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let path = temp_dir.path().join("dupes.pdaw");
+        let mut project = DawProject::new("Dupes");
+        project.add_track(Track::new("Track 1", TrackType::Audio));
+        project.save(&path)?;
+
+        let clip = AudioClip::new("a.wav".into(), 0, 100);
+        project.tracks[0].clips.push(clip.clone());
+        project.tracks[0].clips.push(clip);
+        let error = project.save(&path).unwrap_err();
+        assert!(format!("{:#}", error).contains("has the ID"), "{:#}", error);
+        // The good file on disk is left alone
+        assert!(DawProject::load(&path)?.tracks[0].clips.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_change_sample_rate_keeps_musical_timing() {
+        let mut project = DawProject::new("Test");
+        let mut track = Track::new("Track 1", TrackType::Audio);
+        let mut clip = AudioClip::new("loop.wav".into(), 48_000, 96_000);
+        clip.fade_in = 480;
+        track.clips.push(clip);
+        project.add_track(track);
+        let beat_before = project.transport.samples_to_beats(48_000);
+
+        project.change_sample_rate(44_100.0, SampleRateChange::KeepMusicalTiming).unwrap();
+
+        let clip = &project.tracks[0].clips[0];
+        assert_eq!((clip.start_time, clip.duration, clip.fade_in), (44_100, 88_200, 441));
+        assert_eq!(project.transport.samples_to_beats(clip.start_time), beat_before);
+
+        project.change_sample_rate(96_000.0, SampleRateChange::KeepSamplePositions).unwrap();
+        assert_eq!(project.tracks[0].clips[0].start_time, 44_100);
+        assert_eq!(project.transport.sample_rate, 96_000.0);
+
+        assert!(project.change_sample_rate(0.0, SampleRateChange::KeepMusicalTiming).is_err());
+    }
+
+    #[test]
+    fn test_insert_silence_splits_and_shifts() {
+        let mut project = DawProject::new("Test");
+        let mut track = Track::new("Track 1", TrackType::Audio);
+        let mut spanning = AudioClip::new("pad.wav".into(), 0, 10_000);
+        spanning.fade_in = 100;
+        spanning.fade_out = 200;
+        track.clips.push(spanning);
+        track.clips.push(AudioClip::new("hit.wav".into(), 20_000, 1_000));
+
+        let lane = track.get_automation_lane_mut(AutomationParameter::Volume);
+        for (time, value) in [(0, 0.0), (10_000, 1.0)] {
+            lane.add_point(AutomationPoint {
+                id: uuid::Uuid::new_v4(),
+                time,
+                value,
+                curve_type: CurveType::Linear,
+                tension: 0.0,
+            });
+        }
+        let track_id = track.id;
+        project.add_track(track);
+        project.transport.loop_start = 2_000;
+        project.transport.loop_end = 8_000;
+        project.transport.punch_in = 6_000;
+        project.transport.punch_out = 7_000;
+        project.regions.push(Region::new("Intro", 0, 6_000, 0));
+        project.regions.push(Region::new("Boss", 22_000, 40_000, 1));
+
+        assert_eq!(project.insert_silence(5_000, 3_000, 50).unwrap(), vec![track_id]);
+
+        let clips = &project.tracks[0].clips;
+        assert_eq!(clips.len(), 3);
+        assert_eq!((clips[0].start_time, clips[0].duration, clips[0].fade_in, clips[0].fade_out), (0, 5_000, 100, 50));
+        assert_eq!((clips[1].start_time, clips[1].duration, clips[1].offset), (8_000, 5_000, 5_000));
+        assert_eq!((clips[1].fade_in, clips[1].fade_out), (50, 200));
+        assert_ne!(clips[0].id, clips[1].id);
+        assert_eq!(clips[2].start_time, 23_000);
+
+        // The ramp is held flat across the gap and then continues
+        let lane = project.tracks[0].get_automation_lane(AutomationParameter::Volume).unwrap();
+        assert_eq!(lane.value_at(5_000), Some(0.5));
+        assert_eq!(lane.value_at(6_500), Some(0.5));
+        assert_eq!(lane.value_at(13_000), Some(1.0));
+
+        let transport = &project.transport;
+        assert_eq!((transport.loop_start, transport.loop_end), (2_000, 11_000));
+        assert_eq!((transport.punch_in, transport.punch_out), (9_000, 10_000));
+        let regions: Vec<_> = project.regions.iter().map(|r| (r.start, r.end)).collect();
+        assert_eq!(regions, vec![(0, 9_000), (25_000, 43_000)]);
+
+        // Trimming drops automation past the last clip and clamps the loop
+        project.transport.loop_end = 40_000;
+        project.tracks[0].get_automation_lane_mut(AutomationParameter::Volume).add_point(AutomationPoint {
+            id: uuid::Uuid::new_v4(),
+            time: 30_000,
+            value: 0.0,
+            curve_type: CurveType::Linear,
+            tension: 0.0,
+        });
+        assert_eq!(project.trim_to_content().unwrap(), vec![track_id]);
+        let lane = project.tracks[0].get_automation_lane(AutomationParameter::Volume).unwrap();
+        assert_eq!(lane.points.last().unwrap().time, 24_000);
+        assert_eq!(project.transport.loop_end, 24_000);
+        // Regions starting after the last clip are dropped
+        let regions: Vec<_> = project.regions.iter().map(|r| (r.start, r.end)).collect();
+        assert_eq!(regions, vec![(0, 9_000)]);
+    }
+
+    #[test]
+    fn test_change_tempo_follows_tempo_modes() {
+        let mut project = DawProject::new("Test");
+        let mut track = Track::new("Track 1", TrackType::Audio);
+        let one_shot = AudioClip::new("hit.wav".into(), 48_000, 12_000);
+        let mut looped = AudioClip::new("loop.wav".into(), 48_000, 96_000);
+        looped.loop_points = Some(ClipLoop { start: 0, end: 96_000 });
+        let mut tape = AudioClip::new("tape.wav".into(), 48_000, 96_000);
+        tape.tempo_mode = Some(TempoMode::Repitch);
+        track.clips.extend([one_shot, looped, tape]);
+        project.add_track(track);
+
+        assert_eq!(project.tracks[0].clips[0].effective_tempo_mode(), TempoMode::Fixed);
+        assert_eq!(project.tracks[0].clips[1].effective_tempo_mode(), TempoMode::Stretch);
+
+        // 120 -> 240 BPM halves the length of every beat
+        project.change_tempo(240.0).unwrap();
+        let clips = &project.tracks[0].clips;
+        assert_eq!((clips[0].start_time, clips[0].duration, clips[0].time_ratio), (48_000, 12_000, 1.0));
+        assert_eq!((clips[1].start_time, clips[1].duration, clips[1].time_ratio), (24_000, 48_000, 0.5));
+        assert_eq!((clips[2].start_time, clips[2].duration), (24_000, 48_000));
+        // The same source audio is still played in full
+        assert_eq!(clips[1].source_duration(), 96_000);
+        assert_eq!(project.transport.tempo, 240.0);
+
+        assert!(project.change_tempo(0.0).is_err());
+    }
+
+    #[test]
+    fn test_typed_tempo_and_time_signature_are_checked() {
+        assert_eq!(parse_tempo(" 92.5 bpm").unwrap(), 92.5);
+        assert_eq!(parse_tempo("140").unwrap(), 140.0);
+        for bad in ["", "fast", "5", "1200", "NaN"] {
+            assert!(parse_tempo(bad).is_err(), "{}", bad);
+        }
+
+        assert_eq!(parse_time_signature("7 / 8").unwrap(), (7, 8));
+        for bad in ["4", "0/4", "3/5", "4/64", "33/4", "a/b"] {
+            assert!(parse_time_signature(bad).is_err(), "{}", bad);
+        }
+
+        let mut project = DawProject::new("Test");
+        project.change_time_signature(6, 8).unwrap();
+        assert!(project.change_time_signature(6, 6).is_err());
+        assert_eq!((project.transport.time_signature_numerator, project.transport.time_signature_denominator), (6, 8));
+    }
+
+    #[test]
+    fn test_demo_project() {
+        let project = create_demo_project();
+        assert!(project.tracks.len() > 0);
+        assert!(project.validate().is_ok());
+    }
+
+    #[test]
+    fn test_export_ron() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let path = temp_dir.path().join("test.ron");
+        
+        let project = DawProject::new("Test RON");
+        project.export(&path, ExportFormat::Ron)?;
+        
+        let loaded = DawProject::import(&path, ExportFormat::Ron)?;
+        assert_eq!(loaded.name, "Test RON");
+        
+        Ok(())
+    }
+
+    #[test]
+    fn test_old_projects_keep_buses_solo_safe() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let path = temp_dir.path().join("old.pdaw");
+
+        let mut project = DawProject::new("Old");
+        project.add_track(Track::new("Vocal", TrackType::Audio));
+        project.add_track(Track::new("Bus", TrackType::Aux));
+        let mut json = serde_json::to_value(&project)?;
+        json["version"] = 1.into();
+        for track in json["tracks"].as_array_mut().unwrap() {
+            track.as_object_mut().unwrap().remove("solo_safe");
+        }
+        std::fs::write(&path, serde_json::to_string(&json)?)?;
+
+        let loaded = DawProject::load(&path)?;
+        assert_eq!(loaded.version, PROJECT_VERSION);
+        assert!(!loaded.tracks[0].solo_safe);
+        assert!(loaded.tracks[1].solo_safe);
+        Ok(())
+    }
+}
@@ -0,0 +1,298 @@
+/// Project statistics for handoff and cleanup
+/// Summarizes what a project holds and which files it needs, from the model
+/// and the files on disk, along with anything that would trip up a save or a
+/// render and the audio files in the project folder that no clip uses. The
+/// report can be written as text for people or JSON for pipelines.
+use super::audio_types::*;
+use super::project::DawProject;
+use anyhow::{Context as AnyhowContext, Result};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::path::{Path, PathBuf};
+
+/// Extensions counted as audio when looking for unused files
+const AUDIO_EXTENSIONS: [&str; 5] = ["wav", "mp3", "ogg", "flac", "aiff"];
+
+/// An audio file the project's clips use
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FileUsage {
+    /// As stored in the project, relative to its folder or absolute
+    pub path: PathBuf,
+    pub clips: usize,
+    /// Names of the clips playing the file, sorted, each once
+    pub clip_names: Vec<String>,
+    /// Size on disk, None when the file is missing
+    pub size_bytes: Option<u64>,
+}
+
+/// Summary of a project
+#[derive(Debug, Clone, Serialize)]
+pub struct ProjectReport {
+    pub name: String,
+    /// Every track except the master, buses included
+    pub tracks: usize,
+    /// Aux and return buses among `tracks`
+    pub buses: usize,
+    pub clips: usize,
+    pub duration_seconds: f64,
+    pub automation_points: usize,
+    pub markers: usize,
+    pub regions: usize,
+    /// Files the clips use, by path
+    pub files: Vec<FileUsage>,
+    /// Combined size of the files that exist
+    pub total_file_bytes: u64,
+    /// Problems found in the project: missing files, broken references
+    /// and anything saving would have to fix or refuse
+    pub warnings: Vec<String>,
+    /// Audio files under the project folder that no clip uses
+    pub unused_files: Vec<PathBuf>,
+}
+
+impl ProjectReport {
+    /// Build the report for a project in `base_dir`, which relative clip
+    /// paths are resolved against and which is searched for unused files
+    pub fn build(project: &DawProject, base_dir: &Path) -> Self {
+        let resolve = |path: &Path| if path.is_relative() { base_dir.join(path) } else { path.to_path_buf() };
+
+        let mut uses: BTreeMap<&PathBuf, (usize, BTreeSet<String>)> = BTreeMap::new();
+        for clip in project.tracks.iter().flat_map(|t| &t.clips) {
+            let (count, names) = uses.entry(&clip.asset_path).or_default();
+            *count += 1;
+            names.insert(clip.display_name());
+        }
+        let files: Vec<FileUsage> = uses
+            .into_iter()
+            .map(|(path, (clips, names))| FileUsage {
+                path: path.clone(),
+                clips,
+                clip_names: names.into_iter().collect(),
+                size_bytes: std::fs::metadata(resolve(path)).ok().filter(|m| m.is_file()).map(|m| m.len()),
+            })
+            .collect();
+
+        let mut warnings = Vec::new();
+        for file in files.iter().filter(|f| f.size_bytes.is_none()) {
+            warnings.push(format!("Missing audio file {:?}, used by {}", file.path, plural(file.clips, "clip")));
+        }
+        if let Err(e) = project.validate() {
+            warnings.push(format!("{:#}", e));
+        }
+        if let Err(e) = project.check_unique_ids() {
+            warnings.push(format!("{:#}", e));
+        }
+        // Saving replaces numbers JSON can't hold, so say which it would
+        warnings.extend(project.clone().sanitize_numbers());
+
+        let track_ids: HashSet<TrackId> = project.tracks.iter().map(|t| t.id).collect();
+        for track in &project.tracks {
+            let dangling = track.sends.iter().filter(|s| s.target_track.is_some_and(|id| !track_ids.contains(&id))).count();
+            if dangling > 0 {
+                warnings.push(format!("'{}' has {} to a track that no longer exists", track.name, plural(dangling, "send")));
+            }
+        }
+        let orphaned = project.control_mappings.iter().filter(|m| !track_ids.contains(&m.track_id)).count();
+        if orphaned > 0 {
+            warnings.push(format!("{} mapped to tracks that no longer exist", plural(orphaned, "controller")));
+        }
+
+        let used: HashSet<PathBuf> = files.iter().map(|f| comparable(&resolve(&f.path))).collect();
+        let unused_files = find_unused_audio_files(base_dir, &used);
+
+        Self {
+            name: project.name.clone(),
+            tracks: project.tracks.len(),
+            buses: project.tracks.iter().filter(|t| t.track_type.is_bus()).count(),
+            clips: project.tracks.iter().map(|t| t.clips.len()).sum(),
+            duration_seconds: project.mix_duration() as f64 / project.sample_rate as f64,
+            automation_points: project
+                .tracks
+                .iter()
+                .chain(std::iter::once(&project.master_track))
+                .flat_map(|t| &t.automation)
+                .map(|lane| lane.points.len())
+                .sum(),
+            markers: project.markers.len(),
+            regions: project.regions.len(),
+            total_file_bytes: files.iter().filter_map(|f| f.size_bytes).sum(),
+            files,
+            warnings,
+            unused_files,
+        }
+    }
+
+    /// The report as plain text, one fact per line
+    pub fn to_text(&self) -> String {
+        let minutes = (self.duration_seconds / 60.0).floor();
+        let mut lines = vec![
+            format!("Project: {}", self.name),
+            format!("Tracks: {} ({})", self.tracks, plural(self.buses, "bus")),
+            format!("Clips: {}", self.clips),
+            format!("Duration: {}:{:04.1}", minutes, self.duration_seconds - minutes * 60.0),
+            format!("Automation points: {}", self.automation_points),
+            format!("Markers: {}", self.markers),
+            format!("Regions: {}", self.regions),
+            format!("Files: {}, {}", self.files.len(), format_bytes(self.total_file_bytes)),
+        ];
+        for file in &self.files {
+            let size = file.size_bytes.map_or_else(|| "missing".to_string(), format_bytes);
+            lines.push(format!(
+                "  {} ({}, {}): {}",
+                file.path.display(),
+                plural(file.clips, "clip"),
+                size,
+                file.clip_names.join(", ")
+            ));
+        }
+        lines.push(format!("Unused audio files: {}", self.unused_files.len()));
+        lines.extend(self.unused_files.iter().map(|path| format!("  {}", path.display())));
+        lines.push(format!("Warnings: {}", self.warnings.len()));
+        lines.extend(self.warnings.iter().map(|warning| format!("  - {}", warning)));
+        lines.join("\n") + "\n"
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self).context("Failed to serialize project report")
+    }
+
+    /// Write the report to `path`, as JSON for a `.json` file and as text
+    /// otherwise
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let json = path.extension().is_some_and(|e| e.eq_ignore_ascii_case("json"));
+        let contents = if json { self.to_json()? } else { self.to_text() };
+        std::fs::write(path, contents).with_context(|| format!("Failed to write project report {:?}", path))
+    }
+}
+
+/// Audio files anywhere under `folder` that aren't in `used`, sorted.
+/// Hidden folders are skipped. `used` holds paths as [`comparable`] makes
+/// them.
+pub fn find_unused_audio_files(folder: &Path, used: &HashSet<PathBuf>) -> Vec<PathBuf> {
+    let mut unused = Vec::new();
+    let mut folders = vec![folder.to_path_buf()];
+    while let Some(folder) = folders.pop() {
+        let Ok(entries) = std::fs::read_dir(&folder) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let hidden = path.file_name().is_some_and(|n| n.to_string_lossy().starts_with('.'));
+            let Ok(kind) = entry.file_type() else {
+                continue;
+            };
+            if kind.is_dir() {
+                if !hidden {
+                    folders.push(path);
+                }
+            } else if is_audio_file(&path) && !used.contains(&comparable(&path)) {
+                unused.push(path);
+            }
+        }
+    }
+    unused.sort();
+    unused
+}
+
+fn is_audio_file(path: &Path) -> bool {
+    path.extension()
+        .map(|e| e.to_string_lossy().to_ascii_lowercase())
+        .is_some_and(|e| AUDIO_EXTENSIONS.contains(&e.as_str()))
+}
+
+/// A path in a form that compares equal for the same file however it was
+/// written, where the file exists
+pub fn comparable(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+fn plural(count: usize, noun: &str) -> String {
+    match (count, noun) {
+        (1, _) => format!("1 {}", noun),
+        (_, "bus") => format!("{} buses", count),
+        _ => format!("{} {}s", count, noun),
+    }
+}
+
+/// A size in bytes as shown to people, such as "12.3 MB"
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit + 1 < UNITS.len() {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::test_support::temp_dir;
+
+    #[test]
+    fn test_format_bytes() {
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(1536), "1.5 KB");
+        assert_eq!(format_bytes(5 * 1024 * 1024), "5.0 MB");
+    }
+
+    #[test]
+    fn test_project_report() {
+        let dir = temp_dir();
+        std::fs::create_dir_all(dir.join("Audio").join(".cache")).unwrap();
+        std::fs::write(dir.join("Audio").join("kick.wav"), vec![0u8; 2048]).unwrap();
+        std::fs::write(dir.join("Audio").join("old take.WAV"), b"unused").unwrap();
+        std::fs::write(dir.join("Audio").join(".cache").join("hidden.wav"), b"skipped").unwrap();
+        std::fs::write(dir.join("notes.txt"), b"not audio").unwrap();
+
+        let mut project = DawProject::new("Handoff");
+        let mut drums = Track::new("Drums", TrackType::Audio);
+        for start in [0, 48000] {
+            drums.clips.push(AudioClip::new("Audio/kick.wav".into(), start, 48000));
+        }
+        drums.clips[1].name = "Kick Fill".to_string();
+        drums.clips.push(AudioClip::new("Audio/gone.wav".into(), 96000, 48000));
+        let mut lane = AutomationLane::new(AutomationParameter::Volume);
+        for time in [0, 1000, 2000] {
+            lane.add_point(AutomationPoint {
+                id: uuid::Uuid::new_v4(),
+                time,
+                value: 1.0,
+                curve_type: CurveType::Linear,
+                tension: 0.0,
+            });
+        }
+        drums.automation.push(lane);
+        drums.sends[0].target_track = Some(uuid::Uuid::new_v4());
+        project.add_track(drums);
+        project.add_track(Track::new("Reverb", TrackType::Return));
+
+        let report = ProjectReport::build(&project, &dir);
+        assert_eq!((report.tracks, report.buses, report.clips), (2, 1, 3));
+        assert_eq!(report.automation_points, 3);
+        assert!((report.duration_seconds - 144000.0 / project.sample_rate as f64).abs() < 1e-9);
+        assert_eq!(report.files.len(), 2);
+        let kick = report.files.iter().find(|f| f.path.ends_with("kick.wav")).unwrap();
+        assert_eq!((kick.clips, kick.size_bytes), (2, Some(2048)));
+        assert_eq!(kick.clip_names, ["Kick Fill", "kick"]);
+        assert_eq!(report.total_file_bytes, 2048);
+        assert!(report.warnings.iter().any(|w| w.contains("gone.wav")), "{:?}", report.warnings);
+        assert!(report.warnings.iter().any(|w| w.contains("'Drums' has 1 send")), "{:?}", report.warnings);
+        assert_eq!(report.unused_files, vec![dir.join("Audio").join("old take.WAV")]);
+
+        // Text for people, JSON for pipelines
+        let text = report.to_text();
+        assert!(text.contains("Tracks: 2 (1 bus)") && text.contains("Unused audio files: 1"), "{}", text);
+        assert!(text.contains("(2 clips, 2.0 KB): Kick Fill, kick"), "{}", text);
+        let path = dir.join("report.json");
+        report.write(&path).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(json["clips"], 3);
+        assert_eq!(json["unused_files"].as_array().unwrap().len(), 1);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,199 @@
+/// Quick high-pass and low-pass filters on every track
+/// A 12 dB/octave Butterworth high-pass and low-pass at the track input,
+/// ahead of the insert effects, for clearing rumble and hiss across many
+/// tracks without reaching for an EQ. Each one is bypassed at its fully open
+/// frequency, so tracks that don't use them cost nothing.
+use super::audio_types::{AutomationLane, AutomationParameter, SampleTime, Track};
+use super::loudness::Biquad;
+use serde::{Deserialize, Serialize};
+
+/// Lowest corner, where the high-pass is fully open and bypassed
+pub const MIN_FILTER_HZ: f32 = 20.0;
+
+/// Highest corner, where the low-pass is fully open and bypassed
+pub const MAX_FILTER_HZ: f32 = 20_000.0;
+
+/// Samples filter automation is evaluated over, counted from the project
+/// start so renders don't depend on the device buffer size
+const FILTER_SUB_BLOCK: u64 = 32;
+
+/// Corner frequencies of a track's quick filters, in Hz
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct QuickFilters {
+    pub high_pass_hz: f32,
+    pub low_pass_hz: f32,
+}
+
+impl Default for QuickFilters {
+    fn default() -> Self {
+        Self { high_pass_hz: MIN_FILTER_HZ, low_pass_hz: MAX_FILTER_HZ }
+    }
+}
+
+impl QuickFilters {
+    pub fn high_pass_active(&self) -> bool {
+        self.high_pass_hz > MIN_FILTER_HZ
+    }
+
+    pub fn low_pass_active(&self) -> bool {
+        self.low_pass_hz < MAX_FILTER_HZ
+    }
+}
+
+/// Position of a corner frequency on a control, 0 at 20 Hz and 1 at 20 kHz,
+/// even per octave
+pub fn filter_position(hz: f32) -> f32 {
+    ((hz.clamp(MIN_FILTER_HZ, MAX_FILTER_HZ) / MIN_FILTER_HZ).ln() / (MAX_FILTER_HZ / MIN_FILTER_HZ).ln()).clamp(0.0, 1.0)
+}
+
+/// Corner frequency at a control position, the inverse of [`filter_position`]
+pub fn filter_frequency(position: f32) -> f32 {
+    MIN_FILTER_HZ * (MAX_FILTER_HZ / MIN_FILTER_HZ).powf(position.clamp(0.0, 1.0))
+}
+
+/// "80 Hz" or "12.0 kHz"
+pub fn format_filter_frequency(hz: f32) -> String {
+    if hz >= 1000.0 {
+        format!("{:.1} kHz", hz / 1000.0)
+    } else {
+        format!("{:.0} Hz", hz)
+    }
+}
+
+/// One filter, both channels
+#[derive(Debug, Clone, Copy)]
+struct Stage {
+    frequency: f32,
+    channels: [Biquad; 2],
+}
+
+impl Stage {
+    fn new(high_pass: bool, frequency: f32, sample_rate: f32) -> Self {
+        let (b, a) = butterworth(high_pass, frequency, sample_rate);
+        Self { frequency, channels: [Biquad::new(b, a); 2] }
+    }
+
+    fn run(&mut self, high_pass: bool, frequency: f32, sample_rate: f32, left: &mut [f32], right: &mut [f32]) {
+        if frequency != self.frequency {
+            // History is kept, so moving the corner doesn't click
+            let (b, a) = butterworth(high_pass, frequency, sample_rate);
+            self.channels.iter_mut().for_each(|c| c.set_coefficients(b, a));
+            self.frequency = frequency;
+        }
+        for (filter, samples) in self.channels.iter_mut().zip([left, right]) {
+            for sample in samples.iter_mut() {
+                *sample = filter.process(*sample as f64) as f32;
+            }
+        }
+    }
+}
+
+/// Second-order Butterworth coefficients, kept below Nyquist
+fn butterworth(high_pass: bool, frequency: f32, sample_rate: f32) -> ([f64; 3], [f64; 2]) {
+    let frequency = frequency.min(sample_rate * 0.45) as f64;
+    let w0 = std::f64::consts::TAU * frequency / sample_rate as f64;
+    let (sin, cos) = w0.sin_cos();
+    let alpha = sin / std::f64::consts::SQRT_2;
+    let a0 = 1.0 + alpha;
+    let b = if high_pass {
+        [(1.0 + cos) / 2.0, -(1.0 + cos), (1.0 + cos) / 2.0]
+    } else {
+        [(1.0 - cos) / 2.0, 1.0 - cos, (1.0 - cos) / 2.0]
+    };
+    (b.map(|b| b / a0), [-2.0 * cos / a0, (1.0 - alpha) / a0])
+}
+
+/// Running state of one track's quick filters
+#[derive(Debug, Clone, Default)]
+pub struct QuickFilterState {
+    high_pass: Option<Stage>,
+    low_pass: Option<Stage>,
+}
+
+impl QuickFilterState {
+    /// Filter a block of `track` starting at `start`. Automated corners are
+    /// followed every few samples; a filter at its open end is skipped and
+    /// starts from silence when it's brought back.
+    pub fn process(&mut self, track: &Track, start: SampleTime, sample_rate: f32, left: &mut [f32], right: &mut [f32]) {
+        let lane = |parameter| track.get_automation_lane(parameter).filter(|l| l.enabled && !l.points.is_empty());
+        let (high_lane, low_lane) = (lane(AutomationParameter::HighPass), lane(AutomationParameter::LowPass));
+        let filters = track.quick_filters;
+        if high_lane.is_none() && low_lane.is_none() {
+            self.run(filters, sample_rate, left, right);
+            return;
+        }
+
+        let mut done = 0;
+        while done < left.len() {
+            let time = start + done as u64;
+            let length = ((FILTER_SUB_BLOCK - time % FILTER_SUB_BLOCK) as usize).min(left.len() - done);
+            let at = |lane: Option<&AutomationLane>, fixed: f32| {
+                lane.and_then(|l| l.value_at(time)).unwrap_or(fixed).clamp(MIN_FILTER_HZ, MAX_FILTER_HZ)
+            };
+            let automated = QuickFilters {
+                high_pass_hz: at(high_lane, filters.high_pass_hz),
+                low_pass_hz: at(low_lane, filters.low_pass_hz),
+            };
+            let end = done + length;
+            self.run(automated, sample_rate, &mut left[done..end], &mut right[done..end]);
+            done = end;
+        }
+    }
+
+    fn run(&mut self, filters: QuickFilters, sample_rate: f32, left: &mut [f32], right: &mut [f32]) {
+        for (high_pass, active, frequency, stage) in [
+            (true, filters.high_pass_active(), filters.high_pass_hz, &mut self.high_pass),
+            (false, filters.low_pass_active(), filters.low_pass_hz, &mut self.low_pass),
+        ] {
+            if !active {
+                *stage = None;
+                continue;
+            }
+            stage
+                .get_or_insert_with(|| Stage::new(high_pass, frequency, sample_rate))
+                .run(high_pass, frequency, sample_rate, left, right);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::audio_types::TrackType;
+
+    /// Level of a sine at `hz` after the track's filters, once settled
+    fn gain_at(track: &Track, hz: f32) -> f32 {
+        let rate = 48_000.0;
+        let tone: Vec<f32> = (0..9600).map(|i| (std::f32::consts::TAU * hz * i as f32 / rate).sin()).collect();
+        let (mut left, mut right) = (tone.clone(), tone);
+        QuickFilterState::default().process(track, 0, rate, &mut left, &mut right);
+        left[4800..].iter().fold(0.0_f32, |max, s| max.max(s.abs()))
+    }
+
+    #[test]
+    fn test_filters_cut_outside_their_corners_and_open_ones_do_nothing() {
+        let mut track = Track::new("Dialogue", TrackType::Audio);
+        let tone: Vec<f32> = (0..256).map(|i| (i as f32 * 0.3).sin()).collect();
+        let (mut left, mut right) = (tone.clone(), tone.clone());
+        QuickFilterState::default().process(&track, 0, 48_000.0, &mut left, &mut right);
+        assert_eq!(left, tone);
+
+        track.quick_filters = QuickFilters { high_pass_hz: 100.0, low_pass_hz: 5_000.0 };
+        // A 12 dB/octave slope is about -24 dB two octaves out and -3 dB at the corner
+        assert!(gain_at(&track, 25.0) < 0.08);
+        assert!((gain_at(&track, 100.0) - 0.707).abs() < 0.05);
+        assert!((gain_at(&track, 700.0) - 1.0).abs() < 0.03);
+        assert!(gain_at(&track, 20_000.0) < 0.08);
+    }
+
+    #[test]
+    fn test_filter_positions_are_even_per_octave() {
+        assert_eq!(filter_position(MIN_FILTER_HZ), 0.0);
+        assert_eq!(filter_position(MAX_FILTER_HZ), 1.0);
+        assert!((filter_frequency(filter_position(440.0)) - 440.0).abs() < 0.01);
+        assert!((filter_position(40.0) - filter_position(20.0) - (filter_position(8000.0) - filter_position(4000.0))).abs() < 1e-6);
+        assert_eq!(format_filter_frequency(80.0), "80 Hz");
+        assert_eq!(format_filter_frequency(12_000.0), "12.0 kHz");
+    }
+}
@@ -0,0 +1,768 @@
+/// Real-time audio thread using CPAL for cross-platform audio I/O
+use super::audio_graph::{AudioGraph, SavedGraphState};
+use super::audio_types::*;
+use super::loop_crossfade::{crossfade, crossfade_frames};
+use super::take_recorder::TakeCapture;
+use anyhow::{Context as AnyhowContext, Result};
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::{Device, Stream, StreamConfig};
+use crossbeam::channel::{Receiver, Sender};
+use crossbeam::queue::ArrayQueue;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
+/// Capacity of the input ring buffer in frames
+const INPUT_QUEUE_FRAMES: usize = BUFFER_SIZE * 8;
+
+/// One frame of every input channel, unused channels left at zero
+pub type InputFrame = [f32; MAX_INPUT_CHANNELS];
+
+/// The opened input device
+struct InputDevice {
+    _stream: Stream,
+    name: String,
+    channels: u16,
+}
+
+/// Commands sent to the audio thread
+#[derive(Debug, Clone)]
+pub enum AudioCommand {
+    SetMasterVolume(f32),
+    /// Gain on the main outputs only, after metering. Never rendered.
+    SetMonitorGain(f32),
+    SetTrackVolume { track_id: TrackId, volume: f32 },
+    SetTrackPan { track_id: TrackId, pan: f32 },
+    SetTrackMute { track_id: TrackId, muted: bool },
+    SetTrackActive { track_id: TrackId, active: bool },
+    SetTrackSolo { track_id: TrackId, solo: bool },
+    SetTrackRecordArm { track_id: TrackId, armed: bool },
+    SetTrackMonitor { track_id: TrackId, mode: MonitorMode },
+    Play,
+    Pause,
+    Stop,
+    Seek(SampleTime),
+    SetLoop { enabled: bool, start: SampleTime, end: SampleTime },
+    SetLoopCrossfade(f32),
+    SetPlaybackEnd { mode: PlaybackEnd, project_length: Option<SampleTime> },
+    SetTempo(f32),
+    SetTimeSignature { numerator: u32, denominator: u32 },
+    SetMetronome(bool),
+}
+
+/// Messages sent from the audio thread
+#[derive(Debug, Clone)]
+pub enum AudioMessage {
+    TransportUpdate(Transport),
+    MeterUpdate { track_id: Option<TrackId>, meter: MeterData },
+    PerformanceMetrics(PerformanceMetrics),
+    Underrun,
+    Error(String),
+}
+
+/// Lock-free communication structure for audio thread
+pub struct AudioThreadComm {
+    command_rx: Receiver<AudioCommand>,
+    message_tx: Sender<AudioMessage>,
+    transport: Arc<parking_lot::RwLock<Transport>>,
+    running: Arc<AtomicBool>,
+    position: Arc<AtomicU64>,
+    /// Input frames of the take being recorded, if one is
+    take: Arc<TakeCapture>,
+}
+
+impl AudioThreadComm {
+    pub fn new(
+        command_rx: Receiver<AudioCommand>,
+        message_tx: Sender<AudioMessage>,
+        transport: Arc<parking_lot::RwLock<Transport>>,
+    ) -> Self {
+        Self {
+            command_rx,
+            message_tx,
+            transport,
+            running: Arc::new(AtomicBool::new(true)),
+            position: Arc::new(AtomicU64::new(0)),
+            take: Arc::new(TakeCapture::default()),
+        }
+    }
+
+    pub fn stop(&self) {
+        self.running.store(false, Ordering::Release);
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running.load(Ordering::Acquire)
+    }
+
+    pub fn get_position(&self) -> SampleTime {
+        self.position.load(Ordering::Acquire)
+    }
+
+    pub fn set_position(&self, pos: SampleTime) {
+        self.position.store(pos, Ordering::Release);
+    }
+}
+
+/// Real-time audio engine
+pub struct RealTimeAudio {
+    /// Reopened, with the input, when the project rate changes
+    output: parking_lot::Mutex<Option<Stream>>,
+    input: parking_lot::Mutex<Option<InputDevice>>,
+    host: cpal::Host,
+    device: Device,
+    config: parking_lot::RwLock<StreamConfig>,
+    audio_graph: Arc<parking_lot::RwLock<AudioGraph>>,
+    input_queue: Arc<ArrayQueue<InputFrame>>,
+    command_tx: Sender<AudioCommand>,
+    message_rx: Receiver<AudioMessage>,
+    transport: Arc<parking_lot::RwLock<Transport>>,
+    comm: Arc<AudioThreadComm>,
+}
+
+impl RealTimeAudio {
+    pub fn new(audio_graph: Arc<parking_lot::RwLock<AudioGraph>>) -> Result<Self> {
+        let host = cpal::default_host();
+        let device = host
+            .default_output_device()
+            .context("No output device available")?;
+
+        let config = device.default_output_config()?;
+        let sample_rate = config.sample_rate().0;
+        let channels = config.channels();
+
+        tracing::debug!(
+            "Audio device: {} - {} Hz, {} channels",
+            device.name().unwrap_or_else(|_| "Unknown".to_string()),
+            sample_rate,
+            channels
+        );
+
+        let config: StreamConfig = config.into();
+
+        let input_queue = Arc::new(ArrayQueue::new(INPUT_QUEUE_FRAMES));
+        let input = Self::build_input_stream(&host, &device, input_queue.clone(), None);
+        {
+            let mut graph = audio_graph.write();
+            graph.set_input_enabled(input.is_some());
+            graph.set_input_channels(input.as_ref().map_or(0, |input| input.channels));
+            graph.set_output_channels(channels);
+        }
+
+        let (command_tx, command_rx) = crossbeam::channel::unbounded();
+        let (message_tx, message_rx) = crossbeam::channel::unbounded();
+
+        // Sample positions count at the device's rate until a project asks
+        // for its own
+        let transport = Arc::new(parking_lot::RwLock::new(Transport { sample_rate: sample_rate as f32, ..Transport::default() }));
+        let comm = Arc::new(AudioThreadComm::new(
+            command_rx,
+            message_tx,
+            transport.clone(),
+        ));
+
+        let stream = Self::open_output(&device, &config, comm.clone(), transport.clone(), audio_graph.clone(), input_queue.clone())?;
+
+        Ok(Self {
+            output: parking_lot::Mutex::new(Some(stream)),
+            input: parking_lot::Mutex::new(input),
+            host,
+            device,
+            config: parking_lot::RwLock::new(config),
+            audio_graph,
+            input_queue,
+            command_tx,
+            message_rx,
+            transport,
+            comm,
+        })
+    }
+
+    /// Open and start the output stream. The callback renders the graph a
+    /// block at a time and advances the transport with every frame played.
+    fn open_output(
+        device: &Device,
+        config: &StreamConfig,
+        stream_comm: Arc<AudioThreadComm>,
+        stream_transport: Arc<parking_lot::RwLock<Transport>>,
+        stream_audio_graph: Arc<parking_lot::RwLock<AudioGraph>>,
+        input_queue: Arc<ArrayQueue<InputFrame>>,
+    ) -> Result<Stream> {
+        let mut callback_block = OutputBlock::new(BUFFER_SIZE);
+        let mut loop_tail = LoopTail::default();
+        let device_channels = config.channels as usize;
+        let sample_rate = config.sample_rate.0;
+        let mut buffer_position = 0;
+        let mut monitor_gain = 1.0f32;
+        let mut callback_count = 0u64;
+        let underrun_count = 0u64;
+
+        let stream = device.build_output_stream(
+            config,
+            move |data: &mut [f32], _: &cpal::OutputCallbackInfo| {
+                let start_time = Instant::now();
+
+                while let Ok(cmd) = stream_comm.command_rx.try_recv() {
+                    match cmd {
+                        AudioCommand::Play => {
+                            stream_transport.write().play();
+                        }
+                        AudioCommand::Pause => {
+                            stream_transport.write().pause();
+                        }
+                        AudioCommand::Stop => {
+                            stream_transport.write().stop();
+                            stream_comm.set_position(0);
+                            loop_tail.cancel();
+                        }
+                        AudioCommand::Seek(pos) => {
+                            stream_transport.write().seek(pos);
+                            stream_comm.set_position(pos);
+                            loop_tail.cancel();
+                        }
+                        AudioCommand::SetLoop { enabled, start, end } => {
+                            let mut t = stream_transport.write();
+                            t.loop_enabled = enabled;
+                            t.loop_start = start;
+                            t.loop_end = end;
+                        }
+                        AudioCommand::SetLoopCrossfade(ms) => {
+                            stream_transport.write().loop_crossfade_ms = ms;
+                        }
+                        AudioCommand::SetPlaybackEnd { mode, project_length } => {
+                            let mut t = stream_transport.write();
+                            t.playback_end = mode;
+                            t.project_length = project_length;
+                        }
+                        AudioCommand::SetTempo(tempo) => {
+                            stream_transport.write().tempo = tempo;
+                        }
+                        AudioCommand::SetTimeSignature { numerator, denominator } => {
+                            let mut t = stream_transport.write();
+                            t.time_signature_numerator = numerator;
+                            t.time_signature_denominator = denominator;
+                        }
+                        AudioCommand::SetMonitorGain(gain) => {
+                            monitor_gain = gain;
+                        }
+                        _ => {}
+                    }
+                }
+
+                let transport = stream_transport.read().clone();
+                let rolling = transport.is_rolling();
+                let (monitoring, stop_at) = stream_audio_graph
+                    .try_read()
+                    .map(|g| (g.is_monitoring_input(&transport, BUFFER_SIZE), g.playback_stop_point(&transport)))
+                    .unwrap_or((false, None));
+
+                if !rolling && !monitoring {
+                    // Nothing is heard, but armed tracks still meter what
+                    // arrived, and the queue is emptied so monitoring starts
+                    // without latency build-up
+                    Self::meter_idle_input(&stream_audio_graph, &input_queue, transport.sample_rate);
+                    data.fill(0.0);
+                    return;
+                }
+
+                // Render new buffer if we've consumed the current one
+                if buffer_position == 0 {
+                    Self::render_block(&stream_audio_graph, &transport, &input_queue, &stream_comm.take, &mut callback_block, &mut loop_tail);
+                }
+
+                let frames_needed = data.len() / device_channels.max(1);
+                let mut out_idx = 0;
+
+                for _ in 0..frames_needed {
+                    if buffer_position >= BUFFER_SIZE {
+                        buffer_position = 0;
+
+                        // Render next buffer
+                        let transport = stream_transport.read().clone();
+                        Self::render_block(&stream_audio_graph, &transport, &input_queue, &stream_comm.take, &mut callback_block, &mut loop_tail);
+                    }
+
+                    // Main goes to outputs 1/2 through the monitor trim, or
+                    // the surround bed to the first outputs when the device
+                    // has enough of them; otherwise the stereo mix plays as a
+                    // fold-down. Direct outs are added on their own pairs.
+                    let frame = &mut data[out_idx..out_idx + device_channels];
+                    frame.fill(0.0);
+                    if !callback_block.surround.is_empty() && callback_block.surround.len() <= device_channels {
+                        for (out, channel) in frame.iter_mut().zip(&callback_block.surround) {
+                            *out = (channel[buffer_position] * monitor_gain).clamp(-1.0, 1.0);
+                        }
+                    } else {
+                        let main_left = (callback_block.left[buffer_position] * monitor_gain).clamp(-1.0, 1.0);
+                        let main_right = (callback_block.right[buffer_position] * monitor_gain).clamp(-1.0, 1.0);
+                        if let [left, right, ..] = frame {
+                            *left = main_left;
+                            *right = main_right;
+                        } else if let [mono] = frame {
+                            *mono = (main_left + main_right) * 0.5;
+                        }
+                    }
+                    for (first_channel, left, right) in &callback_block.direct {
+                        if first_channel + 1 < device_channels {
+                            frame[*first_channel] = (frame[*first_channel] + left[buffer_position]).clamp(-1.0, 1.0);
+                            frame[first_channel + 1] = (frame[first_channel + 1] + right[buffer_position]).clamp(-1.0, 1.0);
+                        }
+                    }
+                    out_idx += device_channels;
+                    buffer_position += 1;
+
+                    if rolling {
+                        // Advancing through the transport applies loop wrapping
+                        // on the sample the block was split at, so loop
+                        // changes take effect at the next wrap
+                        let mut transport = stream_transport.write();
+                        let stopped = transport.advance_until(1, stop_at);
+                        stream_comm.set_position(transport.position);
+                        if stopped {
+                            // Nothing past the end is heard, and playing
+                            // again renders afresh from the playhead
+                            data[out_idx..].fill(0.0);
+                            buffer_position = 0;
+                            loop_tail.cancel();
+                            break;
+                        }
+                    }
+                }
+
+                let duration = start_time.elapsed();
+                callback_count += 1;
+
+                if callback_count % 100 == 0 {
+                    let metrics = PerformanceMetrics {
+                        callback_duration_us: duration.as_micros() as u64,
+                        buffer_fill_time_us: 0,
+                        cpu_usage: (duration.as_micros() as f32
+                            / (frames_needed as f32 / sample_rate as f32 * 1_000_000.0))
+                            * 100.0,
+                        buffer_underruns: underrun_count,
+                    };
+
+                    let _ = stream_comm.message_tx.try_send(AudioMessage::PerformanceMetrics(metrics));
+                }
+            },
+            move |err| {
+                tracing::error!("Audio stream error: {}", err);
+            },
+            None,
+        )?;
+
+        stream.play()?;
+        Ok(stream)
+    }
+
+    /// Reopen the output, and the input with it, at `rate` so the
+    /// project's sample positions play in real time. Does nothing when the
+    /// streams already run at that rate, and fails with the streams as they
+    /// were if the device can't run at it.
+    pub fn set_sample_rate(&self, rate: u32) -> Result<()> {
+        let mut config = self.config.write();
+        if config.sample_rate.0 == rate {
+            return Ok(());
+        }
+        let supported = self.device.supported_output_configs()?.any(|range| {
+            range.channels() == config.channels && range.min_sample_rate().0 <= rate && rate <= range.max_sample_rate().0
+        });
+        if !supported {
+            anyhow::bail!("{} can't play at {} Hz", self.device_name(), rate);
+        }
+
+        let reopened = StreamConfig { sample_rate: cpal::SampleRate(rate), ..config.clone() };
+        let open = |config: &StreamConfig| {
+            Self::open_output(
+                &self.device,
+                config,
+                self.comm.clone(),
+                self.transport.clone(),
+                self.audio_graph.clone(),
+                self.input_queue.clone(),
+            )
+        };
+        let mut output = self.output.lock();
+        // Some devices only take one stream, so the old one closes first
+        output.take();
+        match open(&reopened) {
+            Ok(stream) => *output = Some(stream),
+            Err(e) => {
+                *output = open(&config).ok();
+                return Err(e.context(format!("Failed to reopen {} at {} Hz", self.device_name(), rate)));
+            }
+        }
+        *config = reopened;
+        self.transport.write().sample_rate = rate as f32;
+
+        let mut input = self.input.lock();
+        input.take();
+        *input = Self::build_input_stream(&self.host, &self.device, self.input_queue.clone(), Some(rate));
+        let mut graph = self.audio_graph.write();
+        graph.set_input_enabled(input.is_some());
+        graph.set_input_channels(input.as_ref().map_or(0, |input| input.channels));
+        Ok(())
+    }
+
+    /// Pull input into the graph and render one block. A block the loop
+    /// wraps in is rendered up to the loop end, then the crossfade tail past
+    /// the end, then on from the loop start with the tail faded out under it.
+    /// The graph goes back to its state at the loop end once the tail is
+    /// rendered, so the tail's reverb, dry delays and limiter lookahead
+    /// don't play on into the loop start. That copy is made once per wrap.
+    fn render_block(
+        audio_graph: &parking_lot::RwLock<AudioGraph>,
+        transport: &Transport,
+        input_queue: &ArrayQueue<InputFrame>,
+        take: &TakeCapture,
+        block: &mut OutputBlock,
+        tail: &mut LoopTail,
+    ) {
+        let Some(mut graph) = audio_graph.try_write() else {
+            // If we can't get the lock, output silence to avoid blocking
+            block.clear();
+            tail.cancel();
+            return;
+        };
+        let frames = block.left.len();
+        let wrap = (transport.is_rolling() && transport.loop_enabled && transport.loop_end > transport.loop_start)
+            .then(|| transport.loop_end.checked_sub(transport.position))
+            .flatten()
+            .map(|until_end| until_end as usize)
+            .filter(|&split| split > 0 && split <= frames);
+        let split = wrap.unwrap_or(frames);
+
+        Self::render_segment(&mut graph, transport, input_queue, take, block, 0, split);
+        block.mix_tail(tail, 0, split);
+        if wrap.is_none() {
+            return;
+        }
+
+        let fade = crossfade_frames(transport);
+        tail.begin(fade);
+        graph.save_state(&mut tail.saved);
+        let mut at = transport.clone();
+        for done in (0..fade).step_by(BUFFER_SIZE) {
+            let length = (fade - done).min(BUFFER_SIZE);
+            // The live input belongs to the loop start, not the tail
+            for input in graph.input_channels_mut() {
+                input[..length].fill(0.0);
+            }
+            at.position = transport.loop_end + done as SampleTime;
+            tail.block.process(&mut graph, &at, done, length);
+        }
+        graph.restore_state(&mut tail.saved);
+
+        at.position = transport.loop_start;
+        Self::render_segment(&mut graph, &at, input_queue, take, block, split, frames - split);
+        block.mix_tail(tail, split, frames - split);
+    }
+
+    /// Render `frames` frames from `transport`'s position into `block` from
+    /// `offset`, with as many frames of input, which go to the take too while
+    /// one records
+    fn render_segment(
+        graph: &mut AudioGraph,
+        transport: &Transport,
+        input_queue: &ArrayQueue<InputFrame>,
+        take: &TakeCapture,
+        block: &mut OutputBlock,
+        offset: usize,
+        frames: usize,
+    ) {
+        if frames == 0 {
+            return;
+        }
+        let recording = take.is_capturing() && transport.is_rolling();
+        if recording {
+            take.mark_position(transport.position);
+        }
+        let inputs = graph.input_channels_mut();
+        for i in 0..frames {
+            let frame = input_queue.pop().unwrap_or([0.0; MAX_INPUT_CHANNELS]);
+            if recording {
+                take.push(frame);
+            }
+            for (input, sample) in inputs.iter_mut().zip(frame) {
+                input[i] = sample;
+            }
+        }
+        block.process(graph, transport, offset, frames);
+    }
+
+    /// Meter every input frame queued while the graph isn't processing, a
+    /// block at a time, and drop them
+    fn meter_idle_input(audio_graph: &parking_lot::RwLock<AudioGraph>, input_queue: &ArrayQueue<InputFrame>, sample_rate: f32) {
+        let Some(mut graph) = audio_graph.try_write().filter(|g| g.is_metering_input()) else {
+            while input_queue.pop().is_some() {}
+            return;
+        };
+        while !input_queue.is_empty() {
+            let inputs = graph.input_channels_mut();
+            let mut frames = 0;
+            while frames < BUFFER_SIZE {
+                let Some(frame) = input_queue.pop() else {
+                    break;
+                };
+                for (input, sample) in inputs.iter_mut().zip(frame) {
+                    input[frames] = sample;
+                }
+                frames += 1;
+            }
+            graph.meter_inputs(frames, sample_rate);
+        }
+    }
+
+    /// Open the default input device for monitoring, at `rate` or its own
+    /// default. Returns `None` if there is no input, if it can't run at
+    /// `rate`, or if the input is a loopback of the output device, which
+    /// would feed the monitored signal straight back into itself.
+    fn build_input_stream(
+        host: &cpal::Host,
+        output_device: &Device,
+        queue: Arc<ArrayQueue<InputFrame>>,
+        rate: Option<u32>,
+    ) -> Option<InputDevice> {
+        let device = host.default_input_device()?;
+        let input_name = device.name().unwrap_or_default();
+        let output_name = output_device.name().unwrap_or_default();
+
+        if is_loopback_input(&input_name, &output_name) {
+            tracing::error!("❌ Input '{}' captures the output device, monitoring disabled", input_name);
+            return None;
+        }
+
+        let mut config: StreamConfig = device.default_input_config().ok()?.into();
+        if let Some(rate) = rate {
+            config.sample_rate = cpal::SampleRate(rate);
+        }
+        let channels = config.channels as usize;
+
+        let stream = device
+            .build_input_stream(
+                &config,
+                move |data: &[f32], _: &cpal::InputCallbackInfo| {
+                    for frame in data.chunks(channels.max(1)) {
+                        let mut input = [0.0; MAX_INPUT_CHANNELS];
+                        for (channel, sample) in input.iter_mut().zip(frame) {
+                            *channel = *sample;
+                        }
+                        queue.force_push(input);
+                    }
+                },
+                move |err| {
+                    tracing::error!("Audio input stream error: {}", err);
+                },
+                None,
+            )
+            .inspect_err(|e| tracing::error!("❌ Failed to open input '{}' at {} Hz: {}", input_name, config.sample_rate.0, e))
+            .ok()?;
+
+        stream.play().ok()?;
+        if channels > MAX_INPUT_CHANNELS {
+            tracing::error!("⚠️ Input '{}' has {} channels, only the first {} can be recorded", input_name, channels, MAX_INPUT_CHANNELS);
+        }
+        Some(InputDevice {
+            _stream: stream,
+            name: input_name,
+            channels: channels as u16,
+        })
+    }
+
+    pub fn send_command(&self, command: AudioCommand) -> Result<()> {
+        self.command_tx
+            .send(command)
+            .context("Failed to send command to audio thread")
+    }
+
+    pub fn try_recv_message(&self) -> Option<AudioMessage> {
+        self.message_rx.try_recv().ok()
+    }
+
+    pub fn get_transport(&self) -> Transport {
+        self.transport.read().clone()
+    }
+
+    pub fn get_position(&self) -> SampleTime {
+        self.comm.get_position()
+    }
+
+    /// Where the audio thread puts the input of a take being recorded
+    pub fn take_capture(&self) -> Arc<TakeCapture> {
+        self.comm.take.clone()
+    }
+
+    pub fn device_name(&self) -> String {
+        self.device
+            .name()
+            .unwrap_or_else(|_| "Unknown".to_string())
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.config.read().sample_rate.0
+    }
+
+    pub fn channels(&self) -> u16 {
+        self.config.read().channels
+    }
+
+    /// Name of the input device, None if no input is open
+    pub fn input_device_name(&self) -> Option<String> {
+        self.input.lock().as_ref().map(|input| input.name.clone())
+    }
+
+    /// Channels of the input device, 0 if no input is open
+    pub fn input_channels(&self) -> u16 {
+        self.input.lock().as_ref().map_or(0, |input| input.channels.min(MAX_INPUT_CHANNELS as u16))
+    }
+}
+
+impl Drop for RealTimeAudio {
+    fn drop(&mut self) {
+        self.comm.stop();
+    }
+}
+
+/// One block of every output: the main mix, the hardware output pairs as
+/// (first channel, left, right), and the surround bed if the project has one
+#[derive(Debug, Default)]
+struct OutputBlock {
+    left: Vec<f32>,
+    right: Vec<f32>,
+    direct: Vec<(usize, Vec<f32>, Vec<f32>)>,
+    surround: Vec<Vec<f32>>,
+}
+
+impl OutputBlock {
+    fn new(frames: usize) -> Self {
+        Self { left: vec![0.0; frames], right: vec![0.0; frames], ..Self::default() }
+    }
+
+    fn clear(&mut self) {
+        self.left.fill(0.0);
+        self.right.fill(0.0);
+        self.direct.clear();
+        self.surround.clear();
+    }
+
+    fn resize(&mut self, frames: usize) {
+        for channel in [&mut self.left, &mut self.right].into_iter().chain(self.surround.iter_mut()) {
+            channel.resize(frames, 0.0);
+        }
+        for (_, left, right) in &mut self.direct {
+            left.resize(frames, 0.0);
+            right.resize(frames, 0.0);
+        }
+    }
+
+    /// Render `frames` frames into the block from `offset`
+    fn process(&mut self, graph: &mut AudioGraph, transport: &Transport, offset: usize, frames: usize) {
+        let range = offset..offset + frames;
+        graph.process(transport, &mut self.left[range.clone()], &mut self.right[range.clone()]);
+
+        // Reuse the buffers so routing changes don't allocate every block
+        let size = self.left.len();
+        let mut used = 0;
+        for (pair, output) in graph.direct_outputs() {
+            if used == self.direct.len() {
+                self.direct.push((0, vec![0.0; size], vec![0.0; size]));
+            }
+            let (first_channel, left, right) = &mut self.direct[used];
+            *first_channel = pair as usize * 2;
+            left[range.clone()].copy_from_slice(&output.left[..frames]);
+            right[range.clone()].copy_from_slice(&output.right[..frames]);
+            used += 1;
+        }
+        self.direct.truncate(used);
+
+        let bed = graph.surround_outputs();
+        self.surround.resize_with(bed.len(), || vec![0.0; size]);
+        for (channel, source) in self.surround.iter_mut().zip(bed) {
+            channel[range.clone()].copy_from_slice(&source[..frames]);
+        }
+    }
+
+    /// Fade `tail` out under `frames` frames of the block from `offset`
+    fn mix_tail(&mut self, tail: &mut LoopTail, offset: usize, frames: usize) {
+        if !tail.is_fading() || frames == 0 {
+            return;
+        }
+        let (length, played) = (tail.block.left.len(), tail.played);
+        let range = offset..offset + frames;
+        crossfade(&mut self.left[range.clone()], &tail.block.left, played, length);
+        crossfade(&mut self.right[range.clone()], &tail.block.right, played, length);
+        for (first_channel, left, right) in &mut self.direct {
+            if let Some((_, tail_left, tail_right)) = tail.block.direct.iter().find(|(c, ..)| *c == *first_channel) {
+                crossfade(&mut left[range.clone()], tail_left, played, length);
+                crossfade(&mut right[range.clone()], tail_right, played, length);
+            }
+        }
+        for (channel, tail_channel) in self.surround.iter_mut().zip(&tail.block.surround) {
+            crossfade(&mut channel[range.clone()], tail_channel, played, length);
+        }
+        tail.played += frames;
+    }
+}
+
+/// The audio past the loop end, rendered when the loop wraps and faded out
+/// over the frames after it, which may run into the next blocks
+#[derive(Default)]
+struct LoopTail {
+    block: OutputBlock,
+    played: usize,
+    /// The graph's state at the loop end, put back after the tail
+    saved: SavedGraphState,
+}
+
+impl LoopTail {
+    fn begin(&mut self, frames: usize) {
+        self.block.resize(frames);
+        self.played = 0;
+    }
+
+    fn is_fading(&self) -> bool {
+        self.played < self.block.left.len()
+    }
+
+    fn cancel(&mut self) {
+        self.played = self.block.left.len();
+    }
+}
+
+/// Detect input devices that record the output they're paired with, such as
+/// PulseAudio "Monitor of ..." sources or Windows "Stereo Mix"
+fn is_loopback_input(input_name: &str, output_name: &str) -> bool {
+    let input = input_name.to_lowercase();
+    let output = output_name.to_lowercase();
+
+    input.contains("loopback")
+        || input.contains("stereo mix")
+        || input.contains("what u hear")
+        || (input.starts_with("monitor of") && input.contains(output.as_str()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_audio_command_clone() {
+        let cmd = AudioCommand::Play;
+        let _cloned = cmd.clone();
+    }
+
+    #[test]
+    fn test_transport_default() {
+        let transport = Transport::default();
+        assert_eq!(transport.state, TransportState::Stopped);
+        assert_eq!(transport.position, 0);
+        assert_eq!(transport.tempo, 120.0);
+    }
+
+    #[test]
+    fn test_loopback_input_detection() {
+        assert!(is_loopback_input("Monitor of Built-in Audio", "Built-in Audio"));
+        assert!(is_loopback_input("Stereo Mix (Realtek Audio)", "Speakers"));
+        assert!(!is_loopback_input("USB Microphone", "Built-in Audio"));
+    }
+}
@@ -0,0 +1,127 @@
+/// Record quantize
+/// With record quantize on, a take's start snaps to the nearest grid line and
+/// its length to a whole number of grid steps when recording stops, so loops
+/// come out the right length however early or late record was hit. With a
+/// count-in, capture starts a bar before the record position, so a start that
+/// snaps back past the moment record was pressed still has audio under it.
+use super::audio_types::*;
+use std::path::PathBuf;
+
+/// Timeline span kept of a take recorded over `start..end`, starting on a
+/// multiple of `division` beats and lasting at least one. While looping, a
+/// take that starts inside the loop keeps no more than one pass of it.
+pub fn quantize_take(transport: &Transport, start: SampleTime, end: SampleTime, division: BeatTime) -> (SampleTime, SampleTime) {
+    let end = end.max(start);
+    if division <= 0.0 {
+        return (start, end);
+    }
+    let first = (transport.samples_to_beats(start) / division).round() * division;
+    let steps = ((transport.samples_to_beats(end) - transport.samples_to_beats(start)) / division).round().max(1.0);
+    let quantized_start = transport.beats_to_samples(first);
+    let mut quantized_end = transport.beats_to_samples(first + steps * division);
+    if transport.loop_enabled
+        && transport.loop_end > transport.loop_start
+        && (transport.loop_start..transport.loop_end).contains(&quantized_start)
+    {
+        quantized_end = quantized_end.min(transport.loop_end);
+    }
+    (quantized_start, quantized_end)
+}
+
+/// Where capture begins for a recording started at `position`: a bar earlier
+/// with a count-in, but never before the project start
+pub fn capture_start(transport: &Transport, position: SampleTime, count_in: bool) -> SampleTime {
+    if !count_in {
+        return position;
+    }
+    let bar = transport.beats_to_samples(transport.time_signature_numerator.max(1) as BeatTime);
+    position.saturating_sub(bar)
+}
+
+/// Clip for a take written to `path`, whose audio starts at `captured_from`
+/// on the timeline, cut to `span`. A span reaching back before the capture
+/// starts where the audio does rather than shifting it off the grid.
+pub fn take_clip(path: PathBuf, captured_from: SampleTime, span: (SampleTime, SampleTime)) -> AudioClip {
+    let (start, end) = span;
+    let start = start.max(captured_from);
+    let mut clip = AudioClip::new(path, start, end.saturating_sub(start).max(1));
+    clip.offset = start - captured_from;
+    clip
+}
+
+/// Clip for a finished take written to `path`, recorded from `start` with
+/// audio covering `captured_from..end` on the timeline. With a `division`
+/// the take is quantized to it, otherwise it keeps the span recorded.
+pub fn finished_take_clip(
+    transport: &Transport,
+    path: PathBuf,
+    start: SampleTime,
+    captured_from: SampleTime,
+    end: SampleTime,
+    division: Option<BeatTime>,
+) -> AudioClip {
+    let start = start.max(captured_from);
+    let span = match division {
+        Some(division) => quantize_take(transport, start, end, division),
+        None => (start, end.max(start)),
+    };
+    take_clip(path, captured_from, span)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_one_bar_take() {
+        let transport = Transport::default();
+        let bar = transport.beats_to_samples(4.0);
+        let beat = transport.beats_to_samples(1.0);
+
+        // Record hit a little late and stopped a little early, or the other
+        // way round, still makes exactly one bar from the bar line
+        assert_eq!(quantize_take(&transport, bar + 900, 2 * bar - 1_500, 4.0), (bar, 2 * bar));
+        assert_eq!(quantize_take(&transport, bar - 700, 2 * bar + 400, 4.0), (bar, 2 * bar));
+        // A stab shorter than the grid keeps one step, finer grids keep beats
+        assert_eq!(quantize_take(&transport, 10, 200, 4.0), (0, bar));
+        assert_eq!(quantize_take(&transport, beat + 50, 3 * beat - 80, 1.0), (beat, 3 * beat));
+
+        // A take running past a one-bar loop keeps one pass
+        let looping = Transport { loop_enabled: true, loop_start: bar, loop_end: 2 * bar, ..Transport::default() };
+        assert_eq!(quantize_take(&looping, bar + 300, 3 * bar, 4.0), (bar, 2 * bar));
+    }
+
+    #[test]
+    fn test_count_in_capture() {
+        let transport = Transport::default();
+        let bar = transport.beats_to_samples(4.0);
+        assert_eq!(capture_start(&transport, 2 * bar, true), bar);
+        assert_eq!(capture_start(&transport, 2 * bar, false), 2 * bar);
+        assert_eq!(capture_start(&transport, 100, true), 0);
+
+        // Count-in audio before the bar line is trimmed off the take
+        let span = quantize_take(&transport, 2 * bar + 600, 3 * bar, 4.0);
+        let clip = take_clip(PathBuf::from("take.wav"), bar, span);
+        assert_eq!((clip.start_time, clip.offset, clip.duration), (2 * bar, bar, bar));
+
+        // Without audio before the bar line the clip starts where the take does
+        let clip = take_clip(PathBuf::from("take.wav"), 2 * bar + 600, span);
+        assert_eq!((clip.start_time, clip.offset, clip.duration), (2 * bar + 600, 0, bar - 600));
+    }
+
+    #[test]
+    fn test_finished_take_clip_is_quantized() {
+        let transport = Transport::default();
+        let bar = transport.beats_to_samples(4.0);
+
+        // Record hit late with a count-in, stopped a little past the next bar
+        let start = 2 * bar + 900;
+        let captured_from = capture_start(&transport, start, true);
+        let clip = finished_take_clip(&transport, PathBuf::from("take.wav"), start, captured_from, 3 * bar + 1_200, Some(4.0));
+        assert_eq!((clip.start_time, clip.offset, clip.duration), (2 * bar, bar - 900, bar));
+
+        // With record quantize off the take keeps what was recorded from record
+        let clip = finished_take_clip(&transport, PathBuf::from("take.wav"), start, captured_from, 3 * bar + 1_200, None);
+        assert_eq!((clip.start_time, clip.offset, clip.duration), (start, bar, bar + 300));
+    }
+}
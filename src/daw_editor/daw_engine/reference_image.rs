@@ -0,0 +1,244 @@
+/// Reference images
+/// Waveform and spectrogram pictures of a track or the mix over a time range,
+/// for delivery notes and QA. They're drawn offline from the same peak
+/// pyramid and STFT the editor draws with, into an RGB buffer that's written
+/// as a PNG, so nothing here needs a window.
+use super::peak_cache::WaveformPeaks;
+use super::spectrogram::{heat_color, FrequencyScale, Spectrogram, SpectrogramSettings};
+use anyhow::{Context as AnyhowContext, Result};
+use std::path::Path;
+
+/// Smallest and largest image side in pixels
+pub const MIN_IMAGE_SIZE: u32 = 16;
+pub const MAX_IMAGE_SIZE: u32 = 8192;
+
+/// Sizes offered in the export dialog
+pub const IMAGE_SIZES: [(u32, u32); 4] = [(1024, 256), (1920, 540), (2560, 1080), (3840, 2160)];
+
+const BACKGROUND: [u8; 3] = [18, 18, 22];
+const CENTRE_LINE: [u8; 3] = [60, 60, 70];
+const PEAK_COLOR: [u8; 3] = [70, 170, 150];
+const RMS_COLOR: [u8; 3] = [130, 225, 200];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ImageView {
+    Waveform,
+    Spectrogram,
+    /// Waveform across the top third, spectrogram below it
+    #[default]
+    Both,
+}
+
+impl ImageView {
+    pub const ALL: [ImageView; 3] = [ImageView::Waveform, ImageView::Spectrogram, ImageView::Both];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ImageView::Waveform => "Waveform",
+            ImageView::Spectrogram => "Spectrogram",
+            ImageView::Both => "Both",
+        }
+    }
+}
+
+/// What a reference image shows and at what size
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReferenceImageSettings {
+    pub width: u32,
+    pub height: u32,
+    pub view: ImageView,
+    pub scale: FrequencyScale,
+    /// Levels the spectrogram's colors span: black at `min_db` and below,
+    /// pale yellow at `max_db` and above
+    pub min_db: f32,
+    pub max_db: f32,
+}
+
+impl Default for ReferenceImageSettings {
+    fn default() -> Self {
+        Self { width: 1920, height: 540, view: ImageView::Both, scale: FrequencyScale::Log, min_db: -96.0, max_db: 0.0 }
+    }
+}
+
+impl ReferenceImageSettings {
+    pub fn check(&self) -> Result<()> {
+        for (side, size) in [("width", self.width), ("height", self.height)] {
+            if !(MIN_IMAGE_SIZE..=MAX_IMAGE_SIZE).contains(&size) {
+                return Err(anyhow::anyhow!(
+                    "Image {} must be from {} to {} pixels, not {}",
+                    side, MIN_IMAGE_SIZE, MAX_IMAGE_SIZE, size
+                ));
+            }
+        }
+        if self.min_db >= self.max_db {
+            return Err(anyhow::anyhow!(
+                "The dB range must run from low to high, not {} to {} dB",
+                self.min_db, self.max_db
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// 8-bit RGB pixels, row after row from the top
+#[derive(Debug, Clone, PartialEq)]
+pub struct RgbImage {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+impl RgbImage {
+    pub fn new(width: u32, height: u32, color: [u8; 3]) -> Self {
+        Self { width, height, pixels: color.repeat(width as usize * height as usize) }
+    }
+
+    pub fn pixel(&self, x: u32, y: u32) -> [u8; 3] {
+        let index = (y as usize * self.width as usize + x as usize) * 3;
+        [self.pixels[index], self.pixels[index + 1], self.pixels[index + 2]]
+    }
+
+    pub fn set_pixel(&mut self, x: u32, y: u32, color: [u8; 3]) {
+        if x < self.width && y < self.height {
+            let index = (y as usize * self.width as usize + x as usize) * 3;
+            self.pixels[index..index + 3].copy_from_slice(&color);
+        }
+    }
+
+    /// Rows `from..to` of column `x`
+    fn fill_column(&mut self, x: u32, from: u32, to: u32, color: [u8; 3]) {
+        for y in from..to.min(self.height) {
+            self.set_pixel(x, y, color);
+        }
+    }
+
+    /// Encode as an 8-bit RGB PNG
+    pub fn to_png(&self) -> Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        let mut encoder = png::Encoder::new(&mut bytes, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().context("Failed to start the PNG")?;
+        writer.write_image_data(&self.pixels).context("Failed to encode the PNG")?;
+        writer.finish().context("Failed to finish the PNG")?;
+        Ok(bytes)
+    }
+
+    pub fn save_png(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent).with_context(|| format!("Failed to create {:?}", parent))?;
+        }
+        std::fs::write(path, self.to_png()?).with_context(|| format!("Failed to write image {:?}", path))
+    }
+}
+
+/// Draw interleaved `samples` at `sample_rate` as `settings` asks, the whole
+/// buffer spread across the width
+pub fn draw_reference_image(samples: &[f32], channels: usize, sample_rate: f32, settings: &ReferenceImageSettings) -> RgbImage {
+    let mut image = RgbImage::new(settings.width, settings.height, BACKGROUND);
+    let (waveform_rows, spectrogram_rows) = match settings.view {
+        ImageView::Waveform => (settings.height, 0),
+        ImageView::Spectrogram => (0, settings.height),
+        ImageView::Both => (settings.height / 3, settings.height - settings.height / 3),
+    };
+    if waveform_rows > 0 {
+        draw_waveform(&mut image, samples, channels, sample_rate, waveform_rows);
+    }
+    if spectrogram_rows > 0 {
+        draw_spectrogram(&mut image, samples, channels, sample_rate, settings, waveform_rows);
+    }
+    image
+}
+
+/// Peaks and RMS of every column across the top `rows` rows
+fn draw_waveform(image: &mut RgbImage, samples: &[f32], channels: usize, sample_rate: f32, rows: u32) {
+    let half = rows as f32 / 2.0;
+    let to_row = |level: f32| ((1.0 - level.clamp(-1.0, 1.0)) * half) as u32;
+    for x in 0..image.width {
+        image.set_pixel(x, (half as u32).min(rows - 1), CENTRE_LINE);
+    }
+
+    let peaks = WaveformPeaks::from_samples(samples, channels, sample_rate);
+    for (x, [min, max, rms]) in peaks.column_bins(0.0, 1.0, image.width as usize).into_iter().enumerate() {
+        let x = x as u32;
+        image.fill_column(x, to_row(max), (to_row(min) + 1).min(rows), PEAK_COLOR);
+        image.fill_column(x, to_row(rms.min(max)), (to_row(-rms.min(-min)) + 1).min(rows), RMS_COLOR);
+    }
+}
+
+/// The spectrogram in the rows from `top` down, highest frequency at the top
+fn draw_spectrogram(
+    image: &mut RgbImage,
+    samples: &[f32],
+    channels: usize,
+    sample_rate: f32,
+    settings: &ReferenceImageSettings,
+    top: u32,
+) {
+    let channels = channels.max(1);
+    let mono: Vec<f32> = samples.chunks(channels).map(|frame| frame.iter().sum::<f32>() / channels as f32).collect();
+    let rows = (image.height - top) as usize;
+    let analysis = SpectrogramSettings {
+        max_columns: image.width as usize,
+        rows,
+        scale: settings.scale,
+        min_db: settings.min_db,
+        ..SpectrogramSettings::default()
+    };
+    let spectrogram = Spectrogram::from_samples(&mono, sample_rate, &analysis);
+    let grid = spectrogram.grid(0, mono.len() as u64, image.width as usize, rows);
+
+    for (y, row) in grid.iter().enumerate() {
+        for (x, db) in row.iter().enumerate() {
+            let color = heat_color(db - settings.max_db, settings.min_db - settings.max_db);
+            image.set_pixel(x as u32, top + y as u32, color.map(|c| (c * 255.0).round() as u8));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reference_image_draws_waveform_and_spectrogram() {
+        // A second of a 1 kHz sine at half scale, in stereo
+        let sample_rate = 48_000.0;
+        let samples: Vec<f32> = (0..48_000)
+            .flat_map(|i| {
+                let s = 0.5 * (std::f32::consts::TAU * 1000.0 * i as f32 / sample_rate).sin();
+                [s, s]
+            })
+            .collect();
+        let settings = ReferenceImageSettings { width: 64, height: 96, ..ReferenceImageSettings::default() };
+        let image = draw_reference_image(&samples, 2, sample_rate, &settings);
+        assert_eq!(image.pixels.len(), 64 * 96 * 3);
+
+        // The waveform in the top 32 rows reaches a quarter of the way from
+        // the centre to either edge, and not much further
+        assert_eq!(image.pixel(10, 9), PEAK_COLOR);
+        assert_eq!(image.pixel(10, 4), BACKGROUND);
+        // The tone is bright in the spectrogram below, the top bins dark
+        let tone_row = 32 + (0..64).max_by_key(|y| image.pixel(20, 32 + y).iter().map(|c| *c as u32).sum::<u32>()).unwrap();
+        assert!(image.pixel(20, tone_row)[1] > 100);
+        assert!(tone_row > 32 + 20, "{}", tone_row);
+        assert_eq!(image.pixel(20, 33), [0, 0, 0]);
+
+        assert!(ReferenceImageSettings { width: 8, ..settings }.check().is_err());
+        assert!(ReferenceImageSettings { min_db: 0.0, max_db: -6.0, ..settings }.check().is_err());
+        assert!(settings.check().is_ok());
+    }
+
+    #[test]
+    fn test_png_decodes_back_to_the_pixels() {
+        let mut image = RgbImage::new(20, 3, BACKGROUND);
+        image.set_pixel(19, 2, PEAK_COLOR);
+        let bytes = image.to_png().unwrap();
+
+        let mut reader = png::Decoder::new(std::io::Cursor::new(bytes)).read_info().unwrap();
+        let mut pixels = vec![0; reader.output_buffer_size().unwrap()];
+        let info = reader.next_frame(&mut pixels).unwrap();
+        assert_eq!((info.width, info.height, info.color_type), (20, 3, png::ColorType::Rgb));
+        assert_eq!(&pixels[..info.buffer_size()], &image.pixels[..]);
+    }
+}
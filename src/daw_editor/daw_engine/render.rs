@@ -0,0 +1,2393 @@
+/// Offline project rendering
+/// Bounces a `.pdaw` project to a WAV file without the editor, for batch and
+/// CI pipelines. The project is checked up front and every problem is
+/// reported together, so a render either matches playback or fails loudly.
+use super::asset_manager::AssetManager;
+use super::audio_graph::{calculate_pan, AudioGraph};
+use super::audio_types::*;
+use super::effects::EffectKind;
+use super::generators::GeneratorSettings;
+use super::limiter::{LimiterSettings, MasterLimiter};
+use super::loop_crossfade::{crossfade, crossfade_frames};
+use super::loudness::{LoudnessMeter, LoudnessReport, LoudnessTarget};
+use super::project::{DawProject, AUDIO_FOLDER};
+use super::quick_filters::QuickFilters;
+use super::reference_image::{draw_reference_image, ReferenceImageSettings, RgbImage};
+use super::true_peak::{OverCount, OverMeter};
+use anyhow::{Context as AnyhowContext, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+/// Sample format of the rendered file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum RenderBitDepth {
+    Int16,
+    Int24,
+    #[default]
+    Float32,
+}
+
+/// Options for an offline render
+#[derive(Debug, Clone, Default)]
+pub struct RenderOptions {
+    /// First sample to render, defaults to the project start
+    pub start: Option<SampleTime>,
+    /// Sample to stop at, defaults to the end of the last clip
+    pub end: Option<SampleTime>,
+    /// Extra time rendered after `end` so reverb and release tails aren't
+    /// cut. Convolution reverbs lengthen it to their longest response.
+    pub tail_samples: SampleTime,
+    pub bit_depth: RenderBitDepth,
+    /// Normalize the bounce to an integrated loudness with true-peak limiting
+    pub loudness: Option<LoudnessTarget>,
+    /// Cut leading and trailing silence, for tight one-shot assets
+    pub trim: Option<SilenceTrim>,
+    /// Render the transport loop region as a gapless game loop: the
+    /// `tail_samples` rendered past the loop end are mixed back over its
+    /// start, so wrapping from the end to the start is inaudible, with the
+    /// transport's loop crossfade at the seam. Overrides `start` and `end`.
+    pub seamless_loop: bool,
+    /// Run the mix through a true-peak limiter at this ceiling in dBTP, so
+    /// the file has no overs. With `loudness` set the lower of the two
+    /// ceilings is used.
+    pub true_peak_ceiling: Option<f32>,
+    /// Fail the render when the written file still has samples or
+    /// inter-sample peaks over 0 dBFS. The file is left in place.
+    pub fail_on_overs: bool,
+}
+
+/// Silence trimming for a bounce
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SilenceTrim {
+    /// Frames quieter than this on both channels count as silence, in dBFS
+    pub threshold_db: f32,
+    /// Fade-out ending at the detected end, in milliseconds
+    pub fade_out_ms: f32,
+}
+
+impl Default for SilenceTrim {
+    fn default() -> Self {
+        Self { threshold_db: -60.0, fade_out_ms: 10.0 }
+    }
+}
+
+/// What silence trimming removed from a render
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrimReport {
+    pub threshold_db: f32,
+    pub leading_frames: u64,
+    pub trailing_frames: u64,
+    /// Length of the fade-out applied at the new end
+    pub fade_out_frames: u64,
+    /// Why the render was left untrimmed, if it was
+    pub skipped: Option<String>,
+}
+
+/// Overs in a render: in the mix the graph produced, and in what was
+/// written once normalization, limiting and trimming were done. A pipeline
+/// can fail a build on `output.total() > 0`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct OverReport {
+    pub mix: OverCount,
+    pub output: OverCount,
+    /// Ceiling the output was limited to, if any
+    pub ceiling_db: Option<f32>,
+    /// Largest gain reduction the ceiling limiter needed, in dB
+    pub max_limiting_db: f32,
+}
+
+/// True-peak limiting above this much gain reduction is reported as excessive
+const EXCESSIVE_LIMITING_DB: f32 = 3.0;
+
+/// Normalized renders further than this from the target are reported
+const LOUDNESS_TOLERANCE_LU: f32 = 0.5;
+
+/// What a finished render produced
+#[derive(Debug, Clone)]
+pub struct RenderSummary {
+    pub output_path: PathBuf,
+    /// Timeline position of the first frame in the file, after any silence
+    /// trimmed from the front
+    pub start: SampleTime,
+    pub sample_rate: u32,
+    pub frames: u64,
+    /// Highest absolute sample value across both channels
+    pub peak: f32,
+    /// Before/after loudness when `RenderOptions::loudness` was set
+    pub loudness: Option<LoudnessReport>,
+    /// What was cut when `RenderOptions::trim` was set
+    pub trim: Option<TrimReport>,
+    pub overs: OverReport,
+}
+
+/// Load a project, build its audio graph and bounce the master output to a
+/// WAV file at `output`.
+pub fn render_project(
+    project_path: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    options: &RenderOptions,
+) -> Result<RenderSummary> {
+    let project_path = project_path.as_ref();
+    let project = DawProject::load(project_path)
+        .with_context(|| format!("Failed to load project {:?}", project_path))?;
+    let base_dir = project_path.parent().unwrap_or_else(|| Path::new("."));
+    render_loaded_project(&project, base_dir, output.as_ref(), options)
+}
+
+/// Render an already loaded project. Relative asset paths are resolved
+/// against `base_dir`.
+pub fn render_loaded_project(
+    project: &DawProject,
+    base_dir: &Path,
+    output: &Path,
+    options: &RenderOptions,
+) -> Result<RenderSummary> {
+    project.validate().context("Project failed validation")?;
+
+    let mut problems = unsupported_features(project);
+    // Reference tracks are for listening only and never end up in a file
+    let tracks: Vec<Track> = resolve_asset_paths(&project.tracks, base_dir)
+        .into_iter()
+        .filter(|t| t.track_type != TrackType::Reference)
+        .collect();
+
+    let asset_manager = AssetManager::new();
+    problems.extend(load_track_assets(&asset_manager, &tracks, project.sample_rate));
+
+    let layout = project.channel_layout;
+    if layout.is_surround()
+        && (options.loudness.is_some() || options.trim.is_some() || options.seamless_loop || options.true_peak_ceiling.is_some())
+    {
+        problems.push(format!(
+            "Loudness normalization, silence trimming, seamless loops and true-peak ceilings are stereo only, {} renders can't use them",
+            layout.label()
+        ));
+    }
+
+    if !problems.is_empty() {
+        return Err(anyhow::anyhow!(
+            "Cannot render project '{}':\n  - {}",
+            project.name,
+            problems.join("\n  - ")
+        ));
+    }
+
+    let mut tail_samples = options.tail_samples;
+    let (start, end) = if options.seamless_loop {
+        let transport = &project.transport;
+        if transport.loop_end <= transport.loop_start {
+            return Err(anyhow::anyhow!("A seamless loop export needs a loop region"));
+        }
+        if options.trim.is_some() {
+            return Err(anyhow::anyhow!("Silence trimming can't be combined with a seamless loop export"));
+        }
+        // At least the crossfade is rendered past the end to fade out
+        tail_samples = tail_samples.max(crossfade_frames(transport) as SampleTime);
+        (transport.loop_start, transport.loop_end)
+    } else {
+        // Reverbs ring on past the end of the mix
+        tail_samples = tail_samples.max(reverb_tail(&asset_manager, &tracks));
+        (options.start.unwrap_or(0), options.end.unwrap_or_else(|| project.mix_duration()))
+    };
+    let end = end + tail_samples;
+    if end <= start {
+        return Err(anyhow::anyhow!("Nothing to render: end {} is not after start {}", end, start));
+    }
+
+    let sample_rate = project.sample_rate.round() as u32;
+    let mut total_frames = end - start;
+    let pass = RenderPass {
+        project,
+        tracks: &tracks,
+        asset_manager: &asset_manager,
+        start,
+        reference: false,
+    };
+    if layout.is_surround() {
+        return render_surround(&pass, total_frames, output, sample_rate, options);
+    }
+
+    // A seamless loop is rendered with its tail up front and wrapped, and
+    // everything after works on the wrapped loop
+    let source = if options.seamless_loop {
+        let (mut left, mut right) = (Vec::new(), Vec::new());
+        pass.run(total_frames, |l, r| {
+            left.extend_from_slice(l);
+            right.extend_from_slice(r);
+            Ok(())
+        })?;
+        let loop_frames = (end - tail_samples - start) as usize;
+        let fade = crossfade_frames(&project.transport);
+        wrap_loop_tail(&mut left, loop_frames, fade);
+        wrap_loop_tail(&mut right, loop_frames, fade);
+        tracing::info!("Wrapped {} frames of tail over the loop start, crossfading {}", tail_samples, fade);
+        total_frames = loop_frames as u64;
+        MixSource::Loop { left, right }
+    } else {
+        MixSource::Graph(pass)
+    };
+
+    // Normalizing needs the loudness of the whole mix before anything is
+    // written, so the mix is rendered once to measure it. Renders are
+    // deterministic, so the second pass produces the same audio.
+    let ceiling_db = match (options.loudness, options.true_peak_ceiling) {
+        (Some(target), Some(ceiling)) => Some(ceiling.min(target.true_peak_db)),
+        (Some(target), None) => Some(target.true_peak_db),
+        (None, ceiling) => ceiling,
+    };
+    let mut normalizer = match options.loudness {
+        Some(target) => {
+            let target = LoudnessTarget { true_peak_db: ceiling_db.unwrap_or(target.true_peak_db), ..target };
+            let mut meter = LoudnessMeter::new(project.sample_rate);
+            source.run(total_frames, |left, right| {
+                meter.process(left, right);
+                Ok(())
+            })?;
+            Some(Normalizer::new(target, meter.integrated_lufs(), project.sample_rate))
+        }
+        None => None,
+    };
+
+    let mut writer = hound::WavWriter::create(output, wav_spec(sample_rate, options.bit_depth))
+        .with_context(|| format!("Failed to create output file {:?}", output))?;
+
+    // Where the silence ends can only be known once everything is rendered,
+    // so a trimmed render is kept in memory until the end
+    let mut held: Option<(Vec<f32>, Vec<f32>)> = options.trim.map(|_| (Vec::new(), Vec::new()));
+    let mut peak = 0.0_f32;
+    let mut frames = 0_u64;
+    let mut output_overs = OverMeter::new(2);
+    let mut write_frames = |left: &[f32], right: &[f32]| -> Result<()> {
+        output_overs.process(0, left);
+        output_overs.process(1, right);
+        for (l, r) in left.iter().zip(right) {
+            peak = peak.max(l.abs()).max(r.abs());
+            write_sample(&mut writer, *l, options.bit_depth)?;
+            write_sample(&mut writer, *r, options.bit_depth)?;
+        }
+        frames += left.len() as u64;
+        Ok(())
+    };
+    let mut write = |left: &[f32], right: &[f32]| -> Result<()> {
+        match held.as_mut() {
+            Some((held_left, held_right)) => {
+                held_left.extend_from_slice(left);
+                held_right.extend_from_slice(right);
+                Ok(())
+            }
+            None => write_frames(left, right),
+        }
+    };
+
+    // The mix is measured as it comes out of the graph, before anything
+    // after it changes the level. Frames run past the end only flush the
+    // limiter and aren't counted.
+    let mut mix_overs = OverMeter::new(2);
+    let mut unmeasured = total_frames;
+    let mut measure = |left: &[f32], right: &[f32]| {
+        let count = (left.len() as u64).min(unmeasured) as usize;
+        mix_overs.process(0, &left[..count]);
+        mix_overs.process(1, &right[..count]);
+        unmeasured -= count as u64;
+    };
+
+    let mut ceiling = match (&normalizer, options.true_peak_ceiling) {
+        (None, Some(ceiling_db)) => Some(CeilingLimiter::new(ceiling_db)),
+        _ => None,
+    };
+    let (loudness, max_limiting_db) = match (normalizer.as_mut(), ceiling.as_mut()) {
+        (Some(normalizer), _) => {
+            // Render extra frames to flush the limiter's lookahead
+            let latency = normalizer.latency();
+            source.run(total_frames + latency as u64, |left, right| {
+                measure(left, right);
+                let (left, right) = normalizer.process(left, right);
+                write(left, right)
+            })?;
+            let report = normalizer.report();
+            let limiting = report.max_limiting_db;
+            (Some(report), limiting)
+        }
+        (None, Some(ceiling)) => {
+            let latency = ceiling.latency();
+            source.run(total_frames + latency as u64, |left, right| {
+                measure(left, right);
+                let (left, right) = ceiling.process(left, right, 1.0);
+                write(left, right)
+            })?;
+            (None, ceiling.max_limiting_db)
+        }
+        (None, None) => {
+            source.run(total_frames, |left, right| {
+                measure(left, right);
+                write(left, right)
+            })?;
+            (None, 0.0)
+        }
+    };
+
+    let trim = match (options.trim, held) {
+        (Some(trim), Some((mut left, mut right))) => {
+            let report = trim_silence(&mut left, &mut right, trim, project.sample_rate);
+            write_frames(&left, &right)?;
+            Some(report)
+        }
+        _ => None,
+    };
+
+    writer.finalize().context("Failed to finalize output file")?;
+
+    let overs = OverReport {
+        mix: mix_overs.finish(),
+        output: output_overs.finish(),
+        ceiling_db,
+        max_limiting_db,
+    };
+    log_overs(&overs);
+    check_overs(&overs, options)?;
+    if let Some(ref report) = loudness {
+        for warning in &report.warnings {
+            tracing::error!("⚠️ {}", warning);
+        }
+    }
+    if let Some(ref report) = trim {
+        match &report.skipped {
+            Some(reason) => tracing::error!("⚠️ Silence not trimmed: {}", reason),
+            None => tracing::info!(
+                "Trimmed {} leading and {} trailing frames of silence below {:.1} dBFS",
+                report.leading_frames,
+                report.trailing_frames,
+                report.threshold_db
+            ),
+        }
+    }
+
+    Ok(RenderSummary {
+        output_path: output.to_path_buf(),
+        start: start + trim.as_ref().map_or(0, |t| t.leading_frames),
+        sample_rate,
+        frames,
+        peak,
+        loudness,
+        trim,
+        overs,
+    })
+}
+
+fn check_overs(overs: &OverReport, options: &RenderOptions) -> Result<()> {
+    let output = overs.output;
+    if options.fail_on_overs && output.total() > 0 {
+        anyhow::bail!(
+            "Rendered file has {} samples and {} inter-sample peaks over 0 dBFS, up to {:+.1} dBTP",
+            output.sample_overs,
+            output.intersample_overs,
+            output.true_peak_db
+        );
+    }
+    Ok(())
+}
+
+fn log_overs(overs: &OverReport) {
+    let (mix, output) = (overs.mix, overs.output);
+    if mix.total() > 0 {
+        tracing::error!(
+            "⚠️ The mix went over 0 dBFS: {} samples and {} inter-sample peaks, up to {:+.1} dBTP",
+            mix.sample_overs,
+            mix.intersample_overs,
+            mix.true_peak_db
+        );
+    }
+    if output.total() > 0 {
+        tracing::error!(
+            "⚠️ The file has {} samples and {} inter-sample peaks over 0 dBFS, up to {:+.1} dBTP",
+            output.sample_overs,
+            output.intersample_overs,
+            output.true_peak_db
+        );
+    } else if let Some(ceiling) = overs.ceiling_db.filter(|_| overs.max_limiting_db > 0.0) {
+        tracing::info!("Limited to a {:.1} dBTP ceiling with up to {:.1} dB of gain reduction", ceiling, overs.max_limiting_db);
+    }
+}
+
+/// Write the surround bed of a pass to a multichannel WAV, channels in the
+/// layout's order
+fn render_surround(
+    pass: &RenderPass,
+    frames: u64,
+    output: &Path,
+    sample_rate: u32,
+    options: &RenderOptions,
+) -> Result<RenderSummary> {
+    let bit_depth = options.bit_depth;
+    let layout = pass.project.channel_layout;
+    let spec = hound::WavSpec { channels: layout.channel_count() as u16, ..wav_spec(sample_rate, bit_depth) };
+    let mut writer = hound::WavWriter::create(output, spec)
+        .with_context(|| format!("Failed to create output file {:?}", output))?;
+
+    let mut peak = 0.0_f32;
+    let mut written = 0_u64;
+    let mut overs = OverMeter::new(layout.channel_count());
+    pass.run_graph(frames, |graph, _left, _right, range| {
+        let bed = graph.surround_outputs();
+        for (channel, samples) in bed.iter().enumerate() {
+            overs.process(channel, &samples[range.clone()]);
+        }
+        for i in range.clone() {
+            for channel in bed {
+                peak = peak.max(channel[i].abs());
+                write_sample(&mut writer, channel[i], bit_depth)?;
+            }
+        }
+        written += range.len() as u64;
+        Ok(())
+    })?;
+    writer.finalize().context("Failed to finalize output file")?;
+    set_channel_mask(output, layout.channel_mask())?;
+
+    tracing::info!("Rendered {} frames of {} surround", written, layout.label());
+    // Nothing follows the graph in a surround render, so the file is the mix
+    let overs = overs.finish();
+    let overs = OverReport { mix: overs, output: overs, ..OverReport::default() };
+    log_overs(&overs);
+    check_overs(&overs, options)?;
+    Ok(RenderSummary {
+        output_path: output.to_path_buf(),
+        start: pass.start,
+        sample_rate,
+        frames: written,
+        peak,
+        loudness: None,
+        trim: None,
+        overs,
+    })
+}
+
+/// Set the speaker mask of a WAVE_FORMAT_EXTENSIBLE file. hound always
+/// assigns the first speakers in order, which is wrong for quad.
+fn set_channel_mask(path: &Path, mask: u32) -> Result<()> {
+    use std::io::{Read, Seek, SeekFrom, Write};
+
+    let mut file = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(path)
+        .with_context(|| format!("Failed to reopen {:?} for its speaker layout", path))?;
+    // Chunks start after the RIFF header
+    file.seek(SeekFrom::Start(12))?;
+    loop {
+        let mut header = [0u8; 8];
+        file.read_exact(&mut header).context("WAV file has no format chunk")?;
+        let size = u32::from_le_bytes([header[4], header[5], header[6], header[7]]) as i64;
+        if &header[..4] == b"fmt " {
+            let mut format = [0u8; 2];
+            file.read_exact(&mut format)?;
+            // The mask is 20 bytes into an extensible format chunk
+            if u16::from_le_bytes(format) == 0xFFFE && size >= 24 {
+                file.seek(SeekFrom::Current(18))?;
+                file.write_all(&mask.to_le_bytes())?;
+            }
+            return Ok(());
+        }
+        file.seek(SeekFrom::Current(size + size % 2))?;
+    }
+}
+
+/// Options for exporting clips to files of their own
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ClipExportOptions {
+    /// Run each clip through its track's volume, pan, polarity, quick filters,
+    /// effects and automation instead of exporting only the clip with its own
+    /// fades, gain and pitch
+    pub include_track: bool,
+    pub bit_depth: RenderBitDepth,
+}
+
+/// Outcome of a batch clip export
+#[derive(Debug, Clone, Default)]
+pub struct ClipExportReport {
+    pub exported: Vec<PathBuf>,
+    /// Clip name and error for every clip that couldn't be exported
+    pub failed: Vec<(String, String)>,
+    /// Whether the export was stopped before every clip was written
+    pub cancelled: bool,
+}
+
+/// Export each clip to its own WAV file in `folder`, named after the clip.
+/// Names that are already taken get a numbered suffix, so nothing is
+/// overwritten. Looped clips carry their loop in a `smpl` chunk, so samplers
+/// pick it up. `progress` is called with (clips done, total) after every
+/// clip and stops the batch by returning false.
+pub fn export_clips(
+    project: &DawProject,
+    base_dir: &Path,
+    clips: &[(TrackId, ClipId)],
+    folder: &Path,
+    options: &ClipExportOptions,
+    mut progress: impl FnMut(usize, usize) -> bool,
+) -> Result<ClipExportReport> {
+    std::fs::create_dir_all(folder).with_context(|| format!("Failed to create export folder {:?}", folder))?;
+
+    let mut report = ClipExportReport::default();
+    let mut taken = HashSet::new();
+    for (index, (track_id, clip_id)) in clips.iter().enumerate() {
+        match clip_project(project, *track_id, *clip_id, options.include_track) {
+            Some((scratch, start, end)) => {
+                let output = unique_export_path(folder, &scratch.name, &mut taken);
+                let render_options = RenderOptions {
+                    start: Some(start),
+                    end: Some(end),
+                    bit_depth: options.bit_depth,
+                    ..RenderOptions::default()
+                };
+                let loop_range = scratch.tracks[0].clips[0]
+                    .loop_timeline_range()
+                    .map(|(loop_start, loop_end)| (loop_start - start, loop_end.min(end) - start));
+                let rendered = render_loaded_project(&scratch, base_dir, &output, &render_options).and_then(|_| {
+                    match loop_range {
+                        Some((loop_start, loop_end)) => append_smpl_chunk(&output, project.sample_rate, loop_start, loop_end),
+                        None => Ok(()),
+                    }
+                });
+                match rendered {
+                    Ok(()) => report.exported.push(output),
+                    Err(e) => {
+                        let _ = std::fs::remove_file(&output);
+                        report.failed.push((scratch.name, format!("{:#}", e)));
+                    }
+                }
+            }
+            None => report.failed.push((clip_id.to_string(), "Clip no longer exists".to_string())),
+        }
+
+        if !progress(index + 1, clips.len()) && index + 1 < clips.len() {
+            report.cancelled = true;
+            break;
+        }
+    }
+    Ok(report)
+}
+
+/// Add a `smpl` chunk with one forward loop over frames `start..end` to a
+/// finished WAV file and fix up the RIFF size
+fn append_smpl_chunk(path: &Path, sample_rate: f32, start: SampleTime, end: SampleTime) -> Result<()> {
+    use std::io::{Seek, SeekFrom, Write};
+
+    let mut chunk = Vec::with_capacity(68);
+    chunk.extend_from_slice(b"smpl");
+    chunk.extend_from_slice(&60u32.to_le_bytes());
+    let sample_period = (1e9 / sample_rate.max(1.0) as f64).round() as u32;
+    // Manufacturer, product, sample period, MIDI unity note and pitch
+    // fraction, SMPTE format and offset, loop count and sampler data size
+    for value in [0, 0, sample_period, 60, 0, 0, 0, 1, 0u32] {
+        chunk.extend_from_slice(&value.to_le_bytes());
+    }
+    // Cue ID, type (forward), first and last frame, fraction, play count (forever)
+    let last = end.saturating_sub(1).max(start);
+    for value in [0, 0, start as u32, last as u32, 0, 0] {
+        chunk.extend_from_slice(&value.to_le_bytes());
+    }
+
+    let mut file = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(path)
+        .with_context(|| format!("Failed to reopen {:?} for its loop points", path))?;
+    let riff_size = file.seek(SeekFrom::End(0))? - 8 + chunk.len() as u64;
+    file.write_all(&chunk)?;
+    file.seek(SeekFrom::Start(4))?;
+    file.write_all(&u32::try_from(riff_size).context("File too large for a loop chunk")?.to_le_bytes())?;
+    Ok(())
+}
+
+/// Audio from one marker to the next, or across an arrangement region,
+/// exported as a cue named after it
+#[derive(Debug, Clone, PartialEq)]
+pub struct MarkerRegion {
+    /// The marker the cue starts at, or the region it covers
+    pub marker: MarkerId,
+    pub name: String,
+    pub start: SampleTime,
+    pub end: SampleTime,
+}
+
+/// Regions from each marker to the next, the last running to the end of the
+/// project's last clip. Markers are taken in timeline order; markers sharing a position
+/// with another, or at or past the end of the project, give no region and
+/// are reported in the returned warnings.
+pub fn marker_regions(project: &DawProject) -> (Vec<MarkerRegion>, Vec<String>) {
+    let mut warnings = Vec::new();
+    let mut markers: Vec<&Marker> = project.markers.iter().collect();
+    if markers.windows(2).any(|pair| pair[0].position > pair[1].position) {
+        warnings.push("Markers were out of order and have been sorted by position".to_string());
+    }
+    markers.sort_by_key(|m| m.position);
+
+    let project_end = project.mix_duration();
+    let mut regions = Vec::new();
+    for (index, marker) in markers.iter().enumerate() {
+        let end = markers[index + 1..]
+            .iter()
+            .map(|m| m.position)
+            .find(|&position| position > marker.position)
+            .unwrap_or(project_end);
+        if markers[index + 1..].first().is_some_and(|next| next.position == marker.position) {
+            warnings.push(format!("Marker '{}' shares its position with the next marker and was skipped", marker.name));
+        } else if end <= marker.position {
+            warnings.push(format!("Marker '{}' is at or past the end of the project and was skipped", marker.name));
+        } else {
+            regions.push(MarkerRegion { marker: marker.id, name: marker.name.clone(), start: marker.position, end });
+        }
+    }
+    (regions, warnings)
+}
+
+/// The project's arrangement regions as cues, in timeline order, so they
+/// export like marker regions
+pub fn region_cues(project: &DawProject) -> Vec<MarkerRegion> {
+    let mut regions: Vec<&Region> = project.regions.iter().collect();
+    regions.sort_by_key(|r| r.start);
+    regions
+        .into_iter()
+        .map(|region| MarkerRegion { marker: region.id, name: region.name.clone(), start: region.start, end: region.end })
+        .collect()
+}
+
+/// Region between two markers, or from `from` to the next marker when `to`
+/// is None
+pub fn marker_region(project: &DawProject, from: MarkerId, to: Option<MarkerId>) -> Result<MarkerRegion> {
+    let find = |id: MarkerId| {
+        project.markers.iter().find(|m| m.id == id).ok_or_else(|| anyhow::anyhow!("Marker no longer exists"))
+    };
+    let start = find(from)?;
+    let Some(to) = to else {
+        return marker_regions(project)
+            .0
+            .into_iter()
+            .find(|r| r.marker == from)
+            .ok_or_else(|| anyhow::anyhow!("Marker '{}' has no audio after it", start.name));
+    };
+    let end = find(to)?;
+    // Markers picked the wrong way round still describe the same span
+    let (start, end) = if end.position < start.position { (end, start) } else { (start, end) };
+    if end.position == start.position {
+        return Err(anyhow::anyhow!("Markers '{}' and '{}' are at the same position", start.name, end.name));
+    }
+    Ok(MarkerRegion { marker: start.id, name: start.name.clone(), start: start.position, end: end.position })
+}
+
+/// Render each region to its own WAV file in `folder`, named after its
+/// marker. Names already taken get a numbered suffix. `options` supplies the
+/// bit depth, tail and loudness settings; its range is replaced by each
+/// region's. `progress` works as for [`export_clips`].
+pub fn export_marker_regions(
+    project: &DawProject,
+    base_dir: &Path,
+    regions: &[MarkerRegion],
+    folder: &Path,
+    options: &RenderOptions,
+    mut progress: impl FnMut(usize, usize) -> bool,
+) -> Result<ClipExportReport> {
+    std::fs::create_dir_all(folder).with_context(|| format!("Failed to create export folder {:?}", folder))?;
+
+    let mut report = ClipExportReport::default();
+    let mut taken = HashSet::new();
+    for (index, region) in regions.iter().enumerate() {
+        let output = unique_export_path(folder, &region.name, &mut taken);
+        let render_options = RenderOptions {
+            start: Some(region.start),
+            end: Some(region.end),
+            seamless_loop: false,
+            ..options.clone()
+        };
+        match render_loaded_project(project, base_dir, &output, &render_options) {
+            Ok(_) => report.exported.push(output),
+            Err(e) => {
+                let _ = std::fs::remove_file(&output);
+                report.failed.push((region.name.clone(), format!("{:#}", e)));
+            }
+        }
+
+        if !progress(index + 1, regions.len()) && index + 1 < regions.len() {
+            report.cancelled = true;
+            break;
+        }
+    }
+    Ok(report)
+}
+
+/// Options for flattening a project
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FlattenOptions {
+    /// Extra time rendered after the last clip so reverb and delay tails
+    /// aren't cut
+    pub tail_samples: SampleTime,
+    pub bit_depth: RenderBitDepth,
+}
+
+/// Where a flattened copy of a project named `name` is saved in `folder`
+pub fn flattened_project_path(folder: &Path, name: &str) -> PathBuf {
+    folder.join(format!("{} (Flattened).pdaw", name))
+}
+
+/// Flatten a project for handoff or archiving. Every audio track that plays
+/// in the mix is rendered from the start to one file with its effects,
+/// automation, fader and everything its sends feed baked in, and a new
+/// project at `output` plays those files on plain tracks, keeping each
+/// track's mute and solo. The master fader and limiter stay live in the new
+/// project, so the flattened tracks sum to the same mix without needing any
+/// of the original effects. The files go in [`AUDIO_FOLDER`] next
+/// to `output`. `progress` works as for [`export_clips`], once per track; a
+/// cancelled flatten removes what it wrote and saves no project.
+pub fn flatten_project(
+    project: &DawProject,
+    base_dir: &Path,
+    output: &Path,
+    options: &FlattenOptions,
+    mut progress: impl FnMut(usize, usize) -> bool,
+) -> Result<ClipExportReport> {
+    if project.channel_layout.is_surround() {
+        return Err(anyhow::anyhow!(
+            "Flattening is stereo only, {} projects can't be flattened",
+            project.channel_layout.label()
+        ));
+    }
+    project.validate().context("Project failed validation")?;
+    let problems = unsupported_features(project);
+    if !problems.is_empty() {
+        return Err(anyhow::anyhow!(
+            "Cannot flatten project '{}':\n  - {}",
+            project.name,
+            problems.join("\n  - ")
+        ));
+    }
+    let end = project.mix_duration();
+    if end == 0 {
+        return Err(anyhow::anyhow!("Nothing to flatten: the project has no clips"));
+    }
+
+    let folder = output.parent().unwrap_or_else(|| Path::new(".")).join(AUDIO_FOLDER);
+    std::fs::create_dir_all(&folder).with_context(|| format!("Failed to create audio folder {:?}", folder))?;
+
+    let sources: Vec<&Track> = project
+        .tracks
+        .iter()
+        .filter(|t| t.track_type == TrackType::Audio && t.active && !t.clips.is_empty())
+        .collect();
+    let render_options = RenderOptions {
+        start: Some(0),
+        end: Some(end),
+        tail_samples: options.tail_samples,
+        bit_depth: options.bit_depth,
+        ..RenderOptions::default()
+    };
+    // A track's pan lane and its pan each take a centred track down by the
+    // pan law on the way to the master, so the flattened clips are raised
+    // by as much to play back at the level they were rendered at
+    let (centre, _) = calculate_pan(0.0);
+    let unity_gain = 1.0 / (centre * centre);
+
+    let mut report = ClipExportReport::default();
+    let mut taken = HashSet::new();
+    let mut flat_tracks = Vec::new();
+    for (index, source) in sources.iter().enumerate() {
+        // The whole project with only this track's clips, so the buses and
+        // returns it feeds are rendered with it. Solo and the master's
+        // processing are left to the flattened project.
+        // The whole project with only this track's clips, so the buses and
+        // returns it feeds are rendered with it. Solo and the master's
+        // processing are left to the flattened project.
+        let mut scratch = project.clone();
+        for track in &mut scratch.tracks {
+            if track.id == source.id {
+                track.muted = false;
+            } else if !track.track_type.is_bus() {
+                track.clips.clear();
+            }
+            track.solo = false;
+        }
+        scratch.master_track = Track::new("Master", TrackType::Master);
+        scratch.master_limiter.enabled = false;
+
+        let path = unique_export_path(&folder, &source.name, &mut taken);
+        match render_loaded_project(&scratch, base_dir, &path, &render_options) {
+            Ok(summary) => {
+                let file_name = path.file_name().map(PathBuf::from).unwrap_or_default();
+                let mut clip = AudioClip::new(Path::new(AUDIO_FOLDER).join(file_name), 0, summary.frames);
+                clip.name = source.name.clone();
+                clip.gain = unity_gain;
+                let mut track = Track::new(source.name.clone(), TrackType::Audio);
+                track.color = source.color;
+                track.muted = source.muted;
+                track.solo = source.solo;
+                track.clips.push(clip);
+                flat_tracks.push(track);
+                report.exported.push(path);
+            }
+            Err(e) => {
+                let _ = std::fs::remove_file(&path);
+                report.failed.push((source.name.clone(), format!("{:#}", e)));
+            }
+        }
+
+        if !progress(index + 1, sources.len()) && index + 1 < sources.len() {
+            report.cancelled = true;
+            break;
+        }
+    }
+
+    if report.cancelled {
+        for path in report.exported.drain(..) {
+            let _ = std::fs::remove_file(path);
+        }
+        return Ok(report);
+    }
+
+    let flat = DawProject {
+        sample_rate: project.sample_rate,
+        transport: project.transport.clone(),
+        tracks: flat_tracks,
+        master_track: Track { volume: project.master_track.volume, ..Track::new("Master", TrackType::Master) },
+        master_limiter: project.master_limiter,
+        markers: project.markers.clone(),
+        regions: project.regions.clone(),
+        ..DawProject::new(format!("{} (Flattened)", project.name))
+    };
+    flat.save(output).with_context(|| format!("Failed to save flattened project {:?}", output))?;
+    tracing::error!("🧊 Flattened '{}' to {:?}", project.name, output);
+    Ok(report)
+}
+
+/// A track printed to a file by [`bounce_track`]
+#[derive(Debug, Clone)]
+pub struct BouncedTrack {
+    pub path: PathBuf,
+    /// Timeline position of the file's first frame
+    pub start: SampleTime,
+    pub frames: SampleTime,
+    /// Clip gain that plays the file back at the level it was printed at on
+    /// a centred track
+    pub gain: f32,
+}
+
+/// Print one audio track to `folder` as it sounds through its own channel:
+/// clips, effects, automation, filters, fader and pan, but none of what its
+/// sends feed or the master's processing. The file runs from the track's
+/// first clip to its last plus `tail_samples`, or the tail of its
+/// convolution reverbs if that's longer, so it can be dropped back on the
+/// timeline where it came from.
+pub fn bounce_track(
+    project: &DawProject,
+    base_dir: &Path,
+    track_id: TrackId,
+    folder: &Path,
+    tail_samples: SampleTime,
+) -> Result<BouncedTrack> {
+    if project.channel_layout.is_surround() {
+        return Err(anyhow::anyhow!(
+            "Bouncing tracks is stereo only, {} projects can't be bounced",
+            project.channel_layout.label()
+        ));
+    }
+    let track = project.get_track(track_id).context("Track not found")?;
+    if track.track_type != TrackType::Audio {
+        return Err(anyhow::anyhow!("Only audio tracks can be bounced, '{}' is a {:?} track", track.name, track.track_type));
+    }
+    if !track.active {
+        return Err(anyhow::anyhow!("'{}' is inactive, activate it to bounce it", track.name));
+    }
+    let (Some(start), Some(end)) = (
+        track.clips.iter().map(|c| c.start_time).min(),
+        track.clips.iter().map(|c| c.end_time()).max(),
+    ) else {
+        return Err(anyhow::anyhow!("Nothing to bounce: '{}' has no clips", track.name));
+    };
+
+    let mut solo_track = Track {
+        muted: false,
+        solo: false,
+        record_armed: false,
+        monitor: MonitorMode::Off,
+        output: OutputTarget::default(),
+        ..track.clone()
+    };
+    for send in &mut solo_track.sends {
+        send.enabled = false;
+    }
+    let scratch = DawProject {
+        sample_rate: project.sample_rate,
+        transport: project.transport.clone(),
+        tracks: vec![solo_track],
+        master_limiter: LimiterSettings { enabled: false, ..LimiterSettings::default() },
+        ..DawProject::new(track.name.clone())
+    };
+
+    std::fs::create_dir_all(folder).with_context(|| format!("Failed to create {:?}", folder))?;
+    let path = unique_export_path(folder, &format!("{} (Bounced)", track.name), &mut HashSet::new());
+    let options = RenderOptions { start: Some(start), end: Some(end), tail_samples, ..RenderOptions::default() };
+    let summary = match render_loaded_project(&scratch, base_dir, &path, &options) {
+        Ok(summary) => summary,
+        Err(e) => {
+            let _ = std::fs::remove_file(&path);
+            return Err(e);
+        }
+    };
+
+    // The print has the track's pan in it and the new track pans it again,
+    // as for flattened tracks
+    let (centre, _) = calculate_pan(0.0);
+    tracing::error!("🖨️ Bounced '{}' to {:?}", track.name, path);
+    Ok(BouncedTrack { path, start: summary.start, frames: summary.frames, gain: 1.0 / (centre * centre) })
+}
+
+/// Draw the waveform and spectrogram of frames `start..end` of one track, as
+/// it sounds through its own channel like a bounce, or of the master with
+/// `None`, ready to save as a reference image
+pub fn render_reference_image(
+    project: &DawProject,
+    base_dir: &Path,
+    source: Option<TrackId>,
+    start: SampleTime,
+    end: SampleTime,
+    settings: &ReferenceImageSettings,
+) -> Result<RgbImage> {
+    settings.check()?;
+    if end <= start {
+        return Err(anyhow::anyhow!("Nothing to draw: end {} is not after start {}", end, start));
+    }
+
+    let scratch;
+    let project = match source {
+        None => project,
+        Some(track_id) => {
+            let track = project.get_track(track_id).context("Track not found")?;
+            if !matches!(track.track_type, TrackType::Audio | TrackType::Reference) {
+                return Err(anyhow::anyhow!("Only audio tracks can be drawn, '{}' is a {:?} track", track.name, track.track_type));
+            }
+            let mut solo_track = Track {
+                track_type: TrackType::Audio,
+                muted: false,
+                solo: false,
+                active: true,
+                record_armed: false,
+                monitor: MonitorMode::Off,
+                output: OutputTarget::default(),
+                ..track.clone()
+            };
+            for send in &mut solo_track.sends {
+                send.enabled = false;
+            }
+            scratch = DawProject {
+                sample_rate: project.sample_rate,
+                transport: project.transport.clone(),
+                tracks: vec![solo_track],
+                master_limiter: LimiterSettings { enabled: false, ..LimiterSettings::default() },
+                ..DawProject::new(track.name.clone())
+            };
+            &scratch
+        }
+    };
+
+    let tracks: Vec<Track> = resolve_asset_paths(&project.tracks, base_dir)
+        .into_iter()
+        .filter(|t| t.track_type != TrackType::Reference)
+        .collect();
+    let asset_manager = AssetManager::new();
+    let problems = load_track_assets(&asset_manager, &tracks, project.sample_rate);
+    if !problems.is_empty() {
+        return Err(anyhow::anyhow!("Cannot draw '{}':\n  - {}", project.name, problems.join("\n  - ")));
+    }
+
+    let mut samples = Vec::with_capacity((end - start) as usize * 2);
+    let pass = RenderPass { project, tracks: &tracks, asset_manager: &asset_manager, start, reference: false };
+    pass.run(end - start, |left, right| {
+        samples.extend(left.iter().zip(right).flat_map(|(l, r)| [*l, *r]));
+        Ok(())
+    })?;
+    Ok(draw_reference_image(&samples, 2, project.sample_rate, settings))
+}
+
+/// Draw a reference image as [`render_reference_image`] does and write it
+/// to `path` as a PNG
+pub fn export_reference_image(
+    project: &DawProject,
+    base_dir: &Path,
+    source: Option<TrackId>,
+    start: SampleTime,
+    end: SampleTime,
+    settings: &ReferenceImageSettings,
+    path: &Path,
+) -> Result<()> {
+    render_reference_image(project, base_dir, source, start, end, settings)?.save_png(path)?;
+    tracing::error!("🖼️ Wrote reference image of '{}' to {:?}", project.name, path);
+    Ok(())
+}
+
+/// Write a loaded asset to `folder` as a 32-bit float stereo WAV at the rate
+/// it was loaded at, so it plays without conversion in this project. Mono
+/// files are copied to both sides and channels past the second are dropped.
+pub fn conform_asset(asset: &AudioAssetData, folder: &Path) -> Result<PathBuf> {
+    let asset_ref = &asset.asset_ref;
+    let channels = asset_ref.channels.max(1);
+    let stem = asset_ref
+        .path
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "audio".to_string());
+    let rate = asset_ref.sample_rate.round() as u32;
+    let output = unique_export_path(folder, &format!("{} {}k", stem, rate as f32 / 1000.0), &mut HashSet::new());
+
+    let mut writer = hound::WavWriter::create(&output, wav_spec(rate, RenderBitDepth::Float32))
+        .with_context(|| format!("Failed to create {:?}", output))?;
+    for frame in asset.samples.chunks_exact(channels) {
+        let (left, right) = (frame[0], frame[1.min(channels - 1)]);
+        writer.write_sample(left)?;
+        writer.write_sample(right)?;
+    }
+    writer.finalize().with_context(|| format!("Failed to finish {:?}", output))?;
+
+    tracing::error!("🔁 Conformed {:?} to {:?}", asset_ref.path, output);
+    Ok(output)
+}
+
+/// Names of the tracks a file's channels are split onto: L and R for a
+/// stereo file, numbered for more channels
+pub fn split_channel_names(stem: &str, channels: usize) -> Vec<String> {
+    match channels {
+        2 => vec![format!("{} L", stem), format!("{} R", stem)],
+        _ => (1..=channels).map(|channel| format!("{} {}", stem, channel)).collect(),
+    }
+}
+
+/// Write each channel of a loaded asset to `folder` as a 32-bit float mono
+/// WAV at the rate it was loaded at, for stems delivered interleaved that
+/// are really separate mono recordings. Returns (name, file) per channel,
+/// named by [`split_channel_names`].
+pub fn split_asset_channels(asset: &AudioAssetData, folder: &Path) -> Result<Vec<(String, PathBuf)>> {
+    let asset_ref = &asset.asset_ref;
+    let channels = asset_ref.channels;
+    if channels < 2 {
+        return Err(anyhow::anyhow!("{:?} is mono, there are no channels to split", asset_ref.path));
+    }
+    let stem = asset_ref
+        .path
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "audio".to_string());
+    let spec = hound::WavSpec { channels: 1, ..wav_spec(asset_ref.sample_rate.round() as u32, RenderBitDepth::Float32) };
+
+    let mut taken = HashSet::new();
+    let mut split = Vec::new();
+    for (channel, name) in split_channel_names(&stem, channels).into_iter().enumerate() {
+        let output = unique_export_path(folder, &name, &mut taken);
+        let mut writer = hound::WavWriter::create(&output, spec).with_context(|| format!("Failed to create {:?}", output))?;
+        for frame in asset.samples.chunks_exact(channels) {
+            writer.write_sample(frame[channel])?;
+        }
+        writer.finalize().with_context(|| format!("Failed to finish {:?}", output))?;
+        split.push((name, output));
+    }
+
+    tracing::error!("✂️ Split {} channels of {:?} into {:?}", channels, asset_ref.path, folder);
+    Ok(split)
+}
+
+/// Synthesize a generator into `folder` as a 32-bit float stereo WAV at
+/// `sample_rate`, named after it
+pub fn render_generator(settings: &GeneratorSettings, sample_rate: f32, folder: &Path) -> Result<PathBuf> {
+    std::fs::create_dir_all(folder).with_context(|| format!("Failed to create {:?}", folder))?;
+    let output = unique_export_path(folder, &settings.generator.name(), &mut HashSet::new());
+
+    let mut writer = hound::WavWriter::create(&output, wav_spec(sample_rate.round() as u32, RenderBitDepth::Float32))
+        .with_context(|| format!("Failed to create {:?}", output))?;
+    for sample in settings.generate(sample_rate) {
+        writer.write_sample(sample)?;
+        writer.write_sample(sample)?;
+    }
+    writer.finalize().with_context(|| format!("Failed to finish {:?}", output))?;
+
+    tracing::error!("🎛️ Generated {} ({} s at {} dBFS) to {:?}", settings.generator.name(), settings.seconds, settings.level_db, output);
+    Ok(output)
+}
+
+/// A project holding only the given clip on a copy of its track, with the
+/// track's routing and sends switched off. Returns it with the range to
+/// render.
+fn clip_project(
+    project: &DawProject,
+    track_id: TrackId,
+    clip_id: ClipId,
+    include_track: bool,
+) -> Option<(DawProject, SampleTime, SampleTime)> {
+    let track = project.get_track(track_id)?;
+    let mut clip = track.clips.iter().find(|c| c.id == clip_id)?.clone();
+    clip.muted = false;
+    let (start, end) = (clip.start_time, clip.end_time());
+
+    let mut solo_track = Track {
+        // A reference clip exported on its own is wanted in the file
+        track_type: if track.track_type == TrackType::Reference { TrackType::Audio } else { track.track_type },
+        clips: vec![clip.clone()],
+        muted: false,
+        active: true,
+        solo: false,
+        record_armed: false,
+        monitor: MonitorMode::Off,
+        // The render range is the clip's own position on the timeline
+        track_offset_samples: 0,
+        ..track.clone()
+    };
+    for send in &mut solo_track.sends {
+        send.enabled = false;
+    }
+    if !include_track {
+        solo_track.volume = 1.0;
+        solo_track.pan = 0.0;
+        solo_track.phase_invert = PhaseInvert::default();
+        solo_track.quick_filters = QuickFilters::default();
+        solo_track.automation.clear();
+        solo_track.effects.clear();
+    }
+
+    let name = clip.display_name();
+    let scratch = DawProject {
+        sample_rate: project.sample_rate,
+        transport: project.transport.clone(),
+        tracks: vec![solo_track],
+        master_limiter: LimiterSettings { enabled: false, ..LimiterSettings::default() },
+        ..DawProject::new(name)
+    };
+    Some((scratch, start, end))
+}
+
+/// `folder/<name>.wav`, or `<name> (2).wav` and so on when that file exists
+/// or was already handed out in this batch
+fn unique_export_path(folder: &Path, name: &str, taken: &mut HashSet<PathBuf>) -> PathBuf {
+    let name: String = name
+        .trim()
+        .chars()
+        .map(|c| if c.is_control() || "<>:\"/\\|?*".contains(c) { '_' } else { c })
+        .collect();
+    let name = if name.is_empty() { "clip".to_string() } else { name };
+
+    let mut path = folder.join(format!("{}.wav", name));
+    let mut suffix = 2;
+    while taken.contains(&path) || path.exists() {
+        path = folder.join(format!("{} ({}).wav", name, suffix));
+        suffix += 1;
+    }
+    taken.insert(path.clone());
+    path
+}
+
+/// Load every file the tracks' clips use at the project rate. Returns the
+/// problems found, so all of them can be reported together.
+fn load_track_assets(asset_manager: &AssetManager, tracks: &[Track], sample_rate: f32) -> Vec<String> {
+    let mut problems = Vec::new();
+    futures::executor::block_on(asset_manager.set_target_rate(sample_rate));
+    let asset_paths: HashSet<&PathBuf> = tracks
+        .iter()
+        .flat_map(|t| t.clips.iter().map(|c| &c.asset_path))
+        .chain(impulse_paths(tracks))
+        .collect();
+    for path in asset_paths {
+        if !path.exists() {
+            problems.push(format!("Missing audio file: {:?}", path));
+        } else if let Err(e) = futures::executor::block_on(asset_manager.load_asset(path.clone())) {
+            problems.push(format!("Failed to load audio file {:?}: {:#}", path, e));
+        }
+    }
+    problems
+}
+
+/// Impulse responses used by the enabled convolution reverbs on `tracks`
+fn impulse_paths(tracks: &[Track]) -> impl Iterator<Item = &PathBuf> {
+    tracks
+        .iter()
+        .flat_map(|t| t.effects.iter())
+        .filter(|e| e.enabled && matches!(e.kind, EffectKind::ConvolutionReverb { .. }))
+        .filter_map(|e| e.impulse_response.as_ref())
+}
+
+/// Samples a render runs on past its end so the convolution reverbs on
+/// `tracks` die away rather than being cut off: the longest loaded response
+fn reverb_tail(asset_manager: &AssetManager, tracks: &[Track]) -> SampleTime {
+    impulse_paths(tracks)
+        .filter_map(|path| asset_manager.get_cached(path))
+        .map(|asset| (asset.samples.len() / asset.asset_ref.channels.max(1)) as SampleTime)
+        .max()
+        .unwrap_or(0)
+}
+
+/// Loudest a reference is turned up or down to match the mix, in dB
+pub const MAX_REFERENCE_MATCH_DB: f32 = 24.0;
+
+/// Fader gain that plays a reference track at the integrated loudness of the
+/// mix, so an A/B comparison isn't won by whichever is louder. The mix is
+/// measured through the master bus, the reference without it, the same way
+/// each is heard.
+pub fn reference_match_gain(project: &DawProject, base_dir: &Path, reference: TrackId) -> Result<f32> {
+    let track = project
+        .get_track(reference)
+        .filter(|t| t.track_type == TrackType::Reference)
+        .ok_or_else(|| anyhow::anyhow!("Track {} is not a reference track", reference))?;
+    let reference_end = track.clips.iter().map(|c| c.end_time()).max().unwrap_or(0);
+    let mut reference_track = resolve_asset_paths(std::slice::from_ref(track), base_dir).remove(0);
+    reference_track.volume = 1.0;
+    reference_track.muted = false;
+
+    let tracks = resolve_asset_paths(&project.tracks, base_dir);
+    let asset_manager = AssetManager::new();
+    let problems = load_track_assets(&asset_manager, &tracks, project.sample_rate);
+    if !problems.is_empty() {
+        return Err(anyhow::anyhow!("Cannot measure loudness:\n  - {}", problems.join("\n  - ")));
+    }
+
+    let measure = |tracks: &[Track], frames: SampleTime, reference: bool| -> Result<Option<f32>> {
+        let pass = RenderPass { project, tracks, asset_manager: &asset_manager, start: 0, reference };
+        let mut meter = LoudnessMeter::new(project.sample_rate);
+        pass.run(frames, |left, right| {
+            meter.process(left, right);
+            Ok(())
+        })?;
+        Ok(meter.integrated_lufs())
+    };
+    let mix = measure(&tracks, project.mix_duration(), false)?.context("The mix is silent")?;
+    let heard = measure(std::slice::from_ref(&reference_track), reference_end, true)?
+        .with_context(|| format!("Reference '{}' is silent", track.name))?;
+
+    let gain_db = (mix - heard).clamp(-MAX_REFERENCE_MATCH_DB, MAX_REFERENCE_MATCH_DB);
+    Ok(10.0_f32.powf(gain_db / 20.0))
+}
+
+/// Everything needed to run the mix from `start`
+struct RenderPass<'a> {
+    project: &'a DawProject,
+    tracks: &'a [Track],
+    asset_manager: &'a AssetManager,
+    start: SampleTime,
+    /// Play the reference tracks in `tracks` instead of the mix
+    reference: bool,
+}
+
+impl RenderPass<'_> {
+    /// Build a fresh graph and hand `frames` frames of master output to `sink`
+    /// a buffer at a time
+    fn run(&self, frames: u64, mut sink: impl FnMut(&[f32], &[f32]) -> Result<()>) -> Result<()> {
+        self.run_graph(frames, |_graph, left, right, range| sink(&left[range.clone()], &right[range]))
+    }
+
+    /// As [`Self::run`], handing `sink` the graph with its whole buffers and
+    /// the range of them that belongs in the output, so the surround bed can
+    /// be read too
+    fn run_graph(
+        &self,
+        frames: u64,
+        mut sink: impl FnMut(&AudioGraph, &[f32], &[f32], std::ops::Range<usize>) -> Result<()>,
+    ) -> Result<()> {
+        let mut graph = AudioGraph::new(self.asset_manager.clone());
+        // Hardware output routing is for monitoring stems, a bounce always
+        // contains the full mix
+        for track in self.tracks {
+            let mut track = track.clone();
+            track.output = OutputTarget::Main;
+            graph.add_track(track);
+        }
+        graph.get_master_track_mut().volume = self.project.master_track.volume;
+        graph.set_master_limiter(self.project.master_limiter);
+        graph.set_listen_reference(self.reference);
+        graph.set_channel_layout(self.project.channel_layout);
+
+        let mut transport = self.project.transport.clone();
+        transport.state = TransportState::Playing;
+        transport.loop_enabled = false;
+        transport.position = self.start;
+
+        // Render past the end by the graph latency and drop the same amount from
+        // the start, so the file lines up with the timeline
+        let mut skip = graph.output_latency();
+        let mut remaining = frames;
+        let mut left = vec![0.0; BUFFER_SIZE];
+        let mut right = vec![0.0; BUFFER_SIZE];
+
+        while remaining > 0 {
+            graph.process(&transport, &mut left, &mut right);
+            transport.advance(BUFFER_SIZE);
+
+            let from = skip.min(BUFFER_SIZE);
+            skip -= from;
+            let to = (from as u64 + remaining).min(BUFFER_SIZE as u64) as usize;
+            if to > from {
+                sink(&graph, &left, &right, from..to)?;
+                remaining -= (to - from) as u64;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Where the mix written to the file comes from
+enum MixSource<'a> {
+    /// Rendered through the audio graph as it's written
+    Graph(RenderPass<'a>),
+    /// A seamless loop rendered ahead, read round and round so anything
+    /// after it (such as a limiter's lookahead) continues into its start
+    Loop { left: Vec<f32>, right: Vec<f32> },
+}
+
+impl MixSource<'_> {
+    fn run(&self, frames: u64, mut sink: impl FnMut(&[f32], &[f32]) -> Result<()>) -> Result<()> {
+        let (left, right) = match self {
+            MixSource::Graph(pass) => return pass.run(frames, sink),
+            MixSource::Loop { left, right } => (left, right),
+        };
+        let mut position = 0;
+        let mut remaining = frames;
+        while remaining > 0 && !left.is_empty() {
+            let count = (left.len() - position).min(BUFFER_SIZE).min(remaining as usize);
+            sink(&left[position..position + count], &right[position..position + count])?;
+            position = (position + count) % left.len();
+            remaining -= count as u64;
+        }
+        Ok(())
+    }
+}
+
+/// Fold everything past `loop_frames` back over the start of `samples`,
+/// wrapping as many times as the tail is long, and cut it to the loop length.
+/// A tail no longer than `fade` frames crossfades with the loop start, as
+/// playback does at the wrap. A longer one is reverb and release carrying
+/// on, and folds over at full level onto a loop start at full level too: it
+/// follows on from the loop end, so the wrap doesn't click without a fade.
+fn wrap_loop_tail(samples: &mut Vec<f32>, loop_frames: usize, fade: usize) {
+    if loop_frames == 0 {
+        samples.clear();
+        return;
+    }
+    let tail = samples.len().saturating_sub(loop_frames);
+    if tail <= fade {
+        let (start, past_end) = samples.split_at_mut(loop_frames);
+        crossfade(start, past_end, 0, tail);
+        samples.truncate(loop_frames);
+        return;
+    }
+    for i in loop_frames..samples.len() {
+        samples[i % loop_frames] += samples[i];
+    }
+    samples.truncate(loop_frames);
+}
+
+/// True-peak limiting of the final mix, with the limiter's lookahead delay
+/// taken back out
+struct CeilingLimiter {
+    limiter: MasterLimiter,
+    /// Limiter output still to drop to undo its lookahead delay
+    skip: usize,
+    max_limiting_db: f32,
+    left: Vec<f32>,
+    right: Vec<f32>,
+}
+
+impl CeilingLimiter {
+    fn new(ceiling_db: f32) -> Self {
+        let limiter = MasterLimiter::new(LimiterSettings {
+            enabled: true,
+            ceiling_db,
+            ..LimiterSettings::default()
+        });
+        Self {
+            skip: limiter.latency(),
+            limiter,
+            max_limiting_db: 0.0,
+            left: Vec::with_capacity(BUFFER_SIZE),
+            right: Vec::with_capacity(BUFFER_SIZE),
+        }
+    }
+
+    fn latency(&self) -> usize {
+        self.limiter.latency()
+    }
+
+    /// Apply `gain` to one buffer and limit it, returning the part that is
+    /// ready to write
+    fn process(&mut self, left: &[f32], right: &[f32], gain: f32) -> (&[f32], &[f32]) {
+        self.left.clear();
+        self.right.clear();
+        self.left.extend(left.iter().map(|s| s * gain));
+        self.right.extend(right.iter().map(|s| s * gain));
+        self.limiter.process(&mut self.left, &mut self.right);
+        self.max_limiting_db = self.max_limiting_db.max(self.limiter.gain_reduction_db());
+
+        let from = self.skip.min(self.left.len());
+        self.skip -= from;
+        (&self.left[from..], &self.right[from..])
+    }
+}
+
+/// Applies the normalization gain and true-peak limiting during the final
+/// pass, and measures what actually gets written
+struct Normalizer {
+    target: LoudnessTarget,
+    measured: Option<f32>,
+    gain: f32,
+    limiter: CeilingLimiter,
+    after: LoudnessMeter,
+}
+
+impl Normalizer {
+    fn new(target: LoudnessTarget, measured: Option<f32>, sample_rate: f32) -> Self {
+        // Silence can't be normalized, it's written unchanged
+        let gain_db = measured.map(|m| target.lufs - m).unwrap_or(0.0);
+        Self {
+            target,
+            measured,
+            gain: 10.0_f32.powf(gain_db / 20.0),
+            limiter: CeilingLimiter::new(target.true_peak_db),
+            after: LoudnessMeter::new(sample_rate),
+        }
+    }
+
+    fn latency(&self) -> usize {
+        self.limiter.latency()
+    }
+
+    /// Gain and limit one buffer, returning the part that is ready to write
+    fn process(&mut self, left: &[f32], right: &[f32]) -> (&[f32], &[f32]) {
+        let (left, right) = self.limiter.process(left, right, self.gain);
+        self.after.process(left, right);
+        (left, right)
+    }
+
+    fn report(&self) -> LoudnessReport {
+        let achieved = self.after.integrated_lufs();
+        let mut warnings = Vec::new();
+        if self.measured.is_none() {
+            warnings.push("The render is silent, so it wasn't normalized".to_string());
+        }
+        let max_limiting_db = self.limiter.max_limiting_db;
+        if max_limiting_db > EXCESSIVE_LIMITING_DB {
+            warnings.push(format!(
+                "Reaching {:.1} LUFS needed up to {:.1} dB of true-peak limiting; the material may be too dynamic for this target",
+                self.target.lufs, max_limiting_db
+            ));
+        }
+        if let (Some(_), Some(achieved)) = (self.measured, achieved) {
+            if (achieved - self.target.lufs).abs() > LOUDNESS_TOLERANCE_LU {
+                warnings.push(format!(
+                    "Only reached {:.1} LUFS of the {:.1} LUFS target after limiting",
+                    achieved, self.target.lufs
+                ));
+            }
+        }
+
+        LoudnessReport {
+            target_lufs: self.target.lufs,
+            measured_lufs: self.measured,
+            achieved_lufs: achieved,
+            gain_db: 20.0 * self.gain.log10(),
+            max_limiting_db,
+            warnings,
+        }
+    }
+}
+
+/// Cut the frames before the first and after the last one louder than the
+/// threshold and fade out into the new end. Material that is silent
+/// throughout, or never drops below the threshold at either end, is left as
+/// it is.
+fn trim_silence(left: &mut Vec<f32>, right: &mut Vec<f32>, trim: SilenceTrim, sample_rate: f32) -> TrimReport {
+    let span = audible_span(left.len(), trim.threshold_db, |i| left[i].abs().max(right[i].abs()));
+
+    let mut report = TrimReport {
+        threshold_db: trim.threshold_db,
+        leading_frames: 0,
+        trailing_frames: 0,
+        fade_out_frames: 0,
+        skipped: None,
+    };
+    let Some((first, last)) = span else {
+        report.skipped = Some(format!("the render never rises above {:.1} dBFS", trim.threshold_db));
+        return report;
+    };
+    if first == 0 && last + 1 == left.len() {
+        report.skipped = Some(format!("the render never drops below {:.1} dBFS", trim.threshold_db));
+        return report;
+    }
+
+    report.leading_frames = first as u64;
+    report.trailing_frames = (left.len() - last - 1) as u64;
+    for channel in [&mut *left, &mut *right] {
+        channel.truncate(last + 1);
+        channel.drain(..first);
+    }
+
+    let fade = ((trim.fade_out_ms.max(0.0) * sample_rate / 1000.0).round() as usize).min(left.len());
+    let fade_start = left.len() - fade;
+    for channel in [&mut *left, &mut *right] {
+        for (i, sample) in channel[fade_start..].iter_mut().enumerate() {
+            *sample *= 1.0 - (i + 1) as f32 / fade as f32;
+        }
+    }
+    report.fade_out_frames = fade as u64;
+    report
+}
+
+/// Features the offline renderer would silently get wrong
+fn unsupported_features(project: &DawProject) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    for track in &project.tracks {
+        if track.monitor != MonitorMode::Off && track.record_armed {
+            problems.push(format!(
+                "Track '{}' monitors live input, which can't be rendered offline",
+                track.name
+            ));
+        }
+    }
+
+    if let Some(name) = bus_send_loop(project) {
+        problems.push(format!("Bus '{}' is part of a send loop", name));
+    }
+
+    problems
+}
+
+/// Name of a bus that can reach itself through enabled sends, if any
+fn bus_send_loop(project: &DawProject) -> Option<String> {
+    let targets: HashMap<TrackId, Vec<TrackId>> = project
+        .tracks
+        .iter()
+        .filter(|t| t.track_type.is_bus())
+        .map(|t| {
+            let sends = t.sends.iter().filter(|s| s.enabled).filter_map(|s| s.target_track).collect();
+            (t.id, sends)
+        })
+        .collect();
+
+    for start in targets.keys() {
+        let mut stack: Vec<TrackId> = targets[start].clone();
+        let mut seen = HashSet::new();
+        while let Some(id) = stack.pop() {
+            if id == *start {
+                return project.get_track(*start).map(|t| t.name.clone());
+            }
+            if seen.insert(id) {
+                stack.extend(targets.get(&id).into_iter().flatten().copied());
+            }
+        }
+    }
+    None
+}
+
+/// Copy the tracks with relative asset paths made absolute
+fn resolve_asset_paths(tracks: &[Track], base_dir: &Path) -> Vec<Track> {
+    tracks
+        .iter()
+        .cloned()
+        .map(|mut track| {
+            for clip in &mut track.clips {
+                if clip.asset_path.is_relative() {
+                    clip.asset_path = base_dir.join(&clip.asset_path);
+                }
+            }
+            for path in track.effects.iter_mut().filter_map(|e| e.impulse_response.as_mut()) {
+                if path.is_relative() {
+                    *path = base_dir.join(&*path);
+                }
+            }
+            track
+        })
+        .collect()
+}
+
+fn wav_spec(sample_rate: u32, bit_depth: RenderBitDepth) -> hound::WavSpec {
+    let (bits_per_sample, sample_format) = match bit_depth {
+        RenderBitDepth::Int16 => (16, hound::SampleFormat::Int),
+        RenderBitDepth::Int24 => (24, hound::SampleFormat::Int),
+        RenderBitDepth::Float32 => (32, hound::SampleFormat::Float),
+    };
+    hound::WavSpec {
+        channels: 2,
+        sample_rate,
+        bits_per_sample,
+        sample_format,
+    }
+}
+
+fn write_sample<W: std::io::Write + std::io::Seek>(
+    writer: &mut hound::WavWriter<W>,
+    sample: f32,
+    bit_depth: RenderBitDepth,
+) -> Result<()> {
+    let sample = sample.clamp(-1.0, 1.0);
+    match bit_depth {
+        RenderBitDepth::Int16 => writer.write_sample((sample * i16::MAX as f32) as i16),
+        RenderBitDepth::Int24 => writer.write_sample((sample * 8_388_607.0) as i32),
+        RenderBitDepth::Float32 => writer.write_sample(sample),
+    }
+    .context("Failed to write output samples")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::reference_image::ImageView;
+    use super::super::test_support::{temp_dir, write_mono, write_tone};
+
+    #[test]
+    fn test_render_project_to_wav() {
+        let dir = temp_dir();
+        write_tone(&dir.join("tone.wav"), 4800);
+
+        let mut project = DawProject::new("Render");
+        let mut track = Track::new("Tone", TrackType::Audio);
+        track.clips.push(AudioClip::new("tone.wav".into(), 0, 4800));
+        project.add_track(track);
+        let project_path = dir.join("render.pdaw");
+        project.save(&project_path).unwrap();
+
+        let output = dir.join("out.wav");
+        let summary = render_project(&project_path, &output, &RenderOptions::default()).unwrap();
+
+        let reader = hound::WavReader::open(&output).unwrap();
+        assert_eq!(reader.spec().channels, 2);
+        assert_eq!(reader.duration() as u64, summary.frames);
+        assert_eq!(summary.frames, 4800);
+        assert!(summary.peak > 0.0);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_convolution_reverb_tail_is_rendered() {
+        let dir = temp_dir();
+        write_tone(&dir.join("tone.wav"), 4800);
+        write_tone(&dir.join("room.wav"), 2400);
+
+        let mut project = DawProject::new("Reverb");
+        let mut track = Track::new("Tone", TrackType::Audio);
+        track.clips.push(AudioClip::new("tone.wav".into(), 0, 4800));
+        let mut reverb = super::super::effects::Effect::new(EffectKind::ConvolutionReverb { level_db: 0.0 });
+        reverb.mix = 1.0;
+        reverb.impulse_response = Some("room.wav".into());
+        track.effects.push(reverb);
+        project.add_track(track);
+
+        // The file runs on for the response, and the reverb is still
+        // sounding past the end of the clip
+        let output = dir.join("out.wav");
+        let summary = render_loaded_project(&project, &dir, &output, &RenderOptions::default()).unwrap();
+        assert_eq!(summary.frames, 4800 + 2400);
+        let samples: Vec<f32> = hound::WavReader::open(&output).unwrap().into_samples::<f32>().map(|s| s.unwrap()).collect();
+        assert!(samples[2 * 5000..2 * 7000].iter().any(|s| s.abs() > 1e-3));
+
+        // A missing response is reported rather than rendered dry
+        project.tracks[0].effects[0].impulse_response = Some("hall.wav".into());
+        let error = render_loaded_project(&project, &dir, &output, &RenderOptions::default()).unwrap_err();
+        assert!(error.to_string().contains("hall.wav"), "{}", error);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_bus_follows_its_vca_and_own_automation() {
+        let dir = temp_dir();
+        write_tone(&dir.join("tone.wav"), 4800);
+
+        let step = |parameter: AutomationParameter, points: [(SampleTime, f32); 2]| {
+            let mut lane = AutomationLane::new(parameter);
+            for (time, value) in points {
+                lane.add_point(AutomationPoint { id: uuid::Uuid::new_v4(), time, value, curve_type: CurveType::Hold, tension: 0.0 });
+            }
+            lane
+        };
+        let mut project = DawProject::new("Bus VCA");
+        project.master_limiter.enabled = false;
+        let vca = Track::new("VCA 1", TrackType::Vca);
+        let mut bus = Track::new("Bus", TrackType::Return);
+        bus.vca = Some(vca.id);
+        let mut track = Track::new("Tone", TrackType::Audio);
+        track.clips.push(AudioClip::new("tone.wav".into(), 0, 4800));
+        track.sends[0] = Send { target_track: Some(bus.id), amount: 1.0, enabled: true, ..Send::default() };
+        let (vca_id, bus_id) = (vca.id, bus.id);
+        project.add_track(track);
+        project.add_track(bus);
+        project.add_track(vca);
+
+        let render = |project: &DawProject, name: &str| -> Vec<f32> {
+            let output = dir.join(name);
+            render_loaded_project(project, &dir, &output, &RenderOptions::default()).unwrap();
+            hound::WavReader::open(&output).unwrap().into_samples::<f32>().map(|s| s.unwrap()).step_by(2).collect()
+        };
+        let open = render(&project, "open.wav");
+        let mut dry = project.clone();
+        dry.get_track_mut(bus_id).unwrap().volume = 0.0;
+        let dry = render(&dry, "dry.wav");
+        let matches = |rendered: &[f32], expected: &[f32], frames: std::ops::Range<usize>| {
+            frames.clone().all(|i| (rendered[i] - expected[i]).abs() < 1e-5)
+        };
+        assert!(!matches(&open, &dry, 2600..4800));
+
+        // The VCA's volume automation closes the bus two thirds through
+        let mut automated = project.clone();
+        automated.get_track_mut(vca_id).unwrap().automation.push(step(AutomationParameter::Volume, [(0, 1.0), (2400, 0.0)]));
+        let automated = render(&automated, "vca.wav");
+        assert!(matches(&automated, &open, 0..2400));
+        assert!(matches(&automated, &dry, 2400..4800));
+
+        // So does the bus's own mute lane, once its ramp is through
+        let mut muted = project.clone();
+        muted.get_track_mut(bus_id).unwrap().automation.push(step(AutomationParameter::Mute, [(0, 0.0), (2400, 1.0)]));
+        let muted = render(&muted, "mute.wav");
+        assert!(matches(&muted, &open, 0..2400));
+        assert!(matches(&muted, &dry, 2600..4800));
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    /// Two panned, automated tones feeding a return, loud enough to engage
+    /// the master limiter
+    fn reference_project() -> DawProject {
+        let mut project = DawProject::new("Reference");
+        let mut reverb = Track::new("Reverb", TrackType::Return);
+        reverb.volume = 0.5;
+
+        for (name, offset, pan) in [("Left", 0, -0.6), ("Right", 1200, 0.6)] {
+            let mut track = Track::new(name, TrackType::Audio);
+            let mut clip = AudioClip::new("tone.wav".into(), offset, 4800);
+            clip.fade_out = 2400;
+            track.clips.push(clip);
+            track.volume = 3.0;
+            track.pan = pan;
+            track.sends[0] = Send {
+                target_track: Some(reverb.id),
+                amount: 0.4,
+                pre_fader: false,
+                enabled: true,
+                follow_mute: true,
+            };
+            let mut lane = AutomationLane::new(AutomationParameter::Volume);
+            for (time, value) in [(0, 1.0), (3000, 0.25)] {
+                lane.add_point(AutomationPoint {
+                    id: uuid::Uuid::new_v4(),
+                    time,
+                    value,
+                    curve_type: CurveType::Linear,
+                    tension: 0.0,
+                });
+            }
+            track.automation.push(lane);
+            project.add_track(track);
+        }
+        project.add_track(reverb);
+        project
+    }
+
+    fn render_hash(project: &DawProject, dir: &Path, name: &str) -> u64 {
+        use std::hash::{Hash, Hasher};
+
+        let output = dir.join(name);
+        let options = RenderOptions {
+            tail_samples: 2048,
+            ..RenderOptions::default()
+        };
+        render_loaded_project(project, dir, &output, &options).unwrap();
+
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        std::fs::read(&output).unwrap().hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    fn test_render_is_deterministic() {
+        let dir = temp_dir();
+        write_tone(&dir.join("tone.wav"), 4800);
+        let project = reference_project();
+        let mut reordered = project.clone();
+        reordered.tracks.reverse();
+
+        let first = render_hash(&project, &dir, "first.wav");
+        let second = render_hash(&project, &dir, "second.wav");
+        let from_reordered = render_hash(&reordered, &dir, "reordered.wav");
+
+        assert_eq!(first, second);
+        assert_eq!(first, from_reordered);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_reference_tracks_stay_out_of_renders() {
+        let dir = temp_dir();
+        write_tone(&dir.join("tone.wav"), 96000);
+        let mut project = DawProject::new("Reference");
+        let mut track = Track::new("Tone", TrackType::Audio);
+        track.volume = 0.5;
+        track.clips.push(AudioClip::new("tone.wav".into(), 0, 48000));
+        project.add_track(track);
+        let mut reference = Track::new("Reference", TrackType::Reference);
+        reference.clips.push(AudioClip::new("tone.wav".into(), 0, 96000));
+        let reference_id = reference.id;
+        project.add_track(reference);
+
+        // The longer reference doesn't make the render longer or louder
+        let summary = render_loaded_project(&project, &dir, &dir.join("mix.wav"), &RenderOptions::default()).unwrap();
+        assert_eq!(summary.frames, 48000);
+        let mut without = project.clone();
+        without.tracks.retain(|t| t.id != reference_id);
+        let plain = render_loaded_project(&without, &dir, &dir.join("plain.wav"), &RenderOptions::default()).unwrap();
+        assert_eq!(summary.peak, plain.peak);
+
+        // The same tone at half the level needs half the gain to match
+        let gain = reference_match_gain(&project, &dir, reference_id).unwrap();
+        assert!((gain - 0.5).abs() < 0.02, "gain {}", gain);
+        let mix_id = project.tracks[0].id;
+        assert!(reference_match_gain(&project, &dir, mix_id).is_err());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_loudness_normalized_render() {
+        let dir = temp_dir();
+        write_tone(&dir.join("tone.wav"), 96000);
+        let mut project = DawProject::new("Loudness");
+        let mut track = Track::new("Tone", TrackType::Audio);
+        track.clips.push(AudioClip::new("tone.wav".into(), 0, 96000));
+        project.add_track(track);
+
+        let render = |lufs: f32, name: &str| {
+            let options = RenderOptions {
+                loudness: Some(LoudnessTarget::new(lufs)),
+                ..RenderOptions::default()
+            };
+            render_loaded_project(&project, &dir, &dir.join(name), &options).unwrap()
+        };
+
+        let quiet = render(-23.0, "quiet.wav");
+        let report = quiet.loudness.unwrap();
+        assert!(report.measured_lufs.is_some());
+        assert!((report.achieved_lufs.unwrap() + 23.0).abs() < LOUDNESS_TOLERANCE_LU);
+        assert!(report.warnings.is_empty(), "{:?}", report.warnings);
+        assert_eq!(quiet.frames, 96000);
+        assert_eq!(hound::WavReader::open(dir.join("quiet.wav")).unwrap().duration(), 96000);
+
+        // Pushing a tone to 0 LUFS can only be done by limiting it hard
+        let loud = render(0.0, "loud.wav");
+        let report = loud.loudness.unwrap();
+        assert!(report.max_limiting_db > EXCESSIVE_LIMITING_DB);
+        assert!(!report.warnings.is_empty());
+        assert!(loud.peak <= 10.0_f32.powf(-1.0 / 20.0) + 1e-3);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_true_peak_ceiling_removes_overs_from_the_file() {
+        let dir = temp_dir();
+        write_tone(&dir.join("tone.wav"), 9600);
+        let mut project = DawProject::new("Hot");
+        project.master_limiter.enabled = false;
+        let mut track = Track::new("Tone", TrackType::Audio);
+        track.clips.push(AudioClip::new("tone.wav".into(), 0, 9600));
+        // The tone peaks at 0.25, so this drives it about 6 dB over full scale
+        track.volume = 8.0;
+        project.add_track(track);
+
+        let hot = render_loaded_project(&project, &dir, &dir.join("hot.wav"), &RenderOptions::default()).unwrap();
+        assert!(hot.overs.mix.sample_overs > 0);
+        assert_eq!(hot.overs.output, hot.overs.mix);
+        assert_eq!(hot.overs.ceiling_db, None);
+
+        let options = RenderOptions { true_peak_ceiling: Some(-1.0), ..RenderOptions::default() };
+        let limited = render_loaded_project(&project, &dir, &dir.join("limited.wav"), &options).unwrap();
+        assert_eq!(limited.overs.mix, hot.overs.mix);
+        assert_eq!(limited.overs.output.total(), 0, "{:?}", limited.overs);
+        assert!(limited.overs.output.true_peak_db <= -0.9);
+        assert!(limited.overs.max_limiting_db > 5.0);
+        // The limiter's lookahead is taken back out
+        assert_eq!(limited.frames, 9600);
+
+        let strict = RenderOptions { fail_on_overs: true, ..RenderOptions::default() };
+        assert!(render_loaded_project(&project, &dir, &dir.join("strict.wav"), &strict).is_err());
+        let strict = RenderOptions { fail_on_overs: true, ..options };
+        assert!(render_loaded_project(&project, &dir, &dir.join("strict.wav"), &strict).is_ok());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_trim_silence_render() {
+        let dir = temp_dir();
+        write_tone(&dir.join("tone.wav"), 4800);
+        let mut project = DawProject::new("One-shot");
+        let mut track = Track::new("Hit", TrackType::Audio);
+        track.clips.push(AudioClip::new("tone.wav".into(), 2400, 4800));
+        project.add_track(track);
+
+        let options = RenderOptions {
+            tail_samples: 1200,
+            trim: Some(SilenceTrim::default()),
+            ..RenderOptions::default()
+        };
+        let output = dir.join("trimmed.wav");
+        let summary = render_loaded_project(&project, &dir, &output, &options).unwrap();
+        let report = summary.trim.unwrap();
+        assert_eq!(report.skipped, None);
+        // The tone starts with a zero sample, so the trim lands one frame in
+        assert!((2400..=2401).contains(&report.leading_frames), "{:?}", report);
+        assert!(report.trailing_frames >= 1200, "{:?}", report);
+        assert_eq!(report.fade_out_frames, 480);
+        assert_eq!(summary.frames, 8400 - report.leading_frames - report.trailing_frames);
+
+        let samples: Vec<f32> = hound::WavReader::open(&output).unwrap().samples().map(|s| s.unwrap()).collect();
+        assert_eq!(samples.len() as u64, summary.frames * 2);
+        assert!(samples[0].abs() > 0.0);
+        assert_eq!(*samples.last().unwrap(), 0.0);
+
+        // A tone that fills the whole render has nothing to trim
+        let options = RenderOptions {
+            start: Some(2401),
+            trim: Some(SilenceTrim::default()),
+            ..RenderOptions::default()
+        };
+        let summary = render_loaded_project(&project, &dir, &dir.join("full.wav"), &options).unwrap();
+        assert!(summary.trim.unwrap().skipped.is_some());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_seamless_loop_wraps_tail_over_start() {
+        let dir = temp_dir();
+        // A decaying tone standing in for a reverb tail that rings past the loop end
+        let decay: Vec<f32> = (0..4800).map(|i| (i as f32 * 0.01).sin() * 0.5 * (-(i as f32) / 4000.0).exp()).collect();
+        write_mono(&dir.join("decay.wav"), &decay);
+
+        let mut project = DawProject::new("Loop");
+        let mut track = Track::new("Pad", TrackType::Audio);
+        track.clips.push(AudioClip::new("decay.wav".into(), 8400, 4800));
+        project.add_track(track);
+        project.transport.loop_start = 4800;
+        project.transport.loop_end = 9600;
+        let length = 4800;
+
+        let left_channel = |options: &RenderOptions, name: &str| -> Vec<f32> {
+            let output = dir.join(name);
+            render_loaded_project(&project, &dir, &output, options).unwrap();
+            let samples: Vec<f32> = hound::WavReader::open(&output).unwrap().samples().map(|s| s.unwrap()).collect();
+            samples.chunks(2).map(|frame| frame[0]).collect()
+        };
+        let looped = left_channel(&RenderOptions { seamless_loop: true, tail_samples: 4800, ..RenderOptions::default() }, "loop.wav");
+        let continuous = left_channel(&RenderOptions { start: Some(4800), end: Some(9600), tail_samples: 4800, ..RenderOptions::default() }, "long.wav");
+        let naive = left_channel(&RenderOptions { start: Some(4800), end: Some(9600), ..RenderOptions::default() }, "cut.wav");
+
+        assert_eq!(looped.len(), length);
+        for i in 0..length {
+            assert!((looped[i] - (continuous[i] + continuous[i + length])).abs() < 1e-5, "frame {}", i);
+        }
+
+        // Played twice in a row, the step across the seam is no bigger than
+        // the tone's own, where cutting the tail leaves a jump
+        let natural_step = (continuous[length] - continuous[length - 1]).abs();
+        let seam_step = (looped[0] - looped[length - 1]).abs();
+        let cut_step = (naive[0] - naive[length - 1]).abs();
+        assert!(seam_step <= natural_step + 1e-5, "seam {} natural {}", seam_step, natural_step);
+        assert!(cut_step > natural_step * 10.0, "cut {} natural {}", cut_step, natural_step);
+
+        // Without a tail the loop crossfade alone smooths the seam
+        let faded = left_channel(&RenderOptions { seamless_loop: true, ..RenderOptions::default() }, "fade.wav");
+        assert_eq!(faded.len(), length);
+        assert!((faded[0] - faded[length - 1]).abs() <= natural_step + 1e-5);
+
+        // Trimming would break the loop, and there has to be a loop to export
+        let trimmed = RenderOptions { seamless_loop: true, trim: Some(SilenceTrim::default()), ..RenderOptions::default() };
+        assert!(render_loaded_project(&project, &dir, &dir.join("bad.wav"), &trimmed).is_err());
+        project.transport.loop_end = project.transport.loop_start;
+        let options = RenderOptions { seamless_loop: true, ..RenderOptions::default() };
+        assert!(render_loaded_project(&project, &dir, &dir.join("none.wav"), &options).is_err());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_wrap_loop_tail() {
+        let mut samples = vec![1.0, 2.0, 3.0, 10.0, 20.0, 30.0, 100.0];
+        wrap_loop_tail(&mut samples, 3, 0);
+        assert_eq!(samples, vec![111.0, 22.0, 33.0]);
+
+        // A tail as long as the crossfade fades out under the loop start
+        let mut short = vec![1.0, 1.0, 1.0, 1.0, 0.5, 0.5];
+        wrap_loop_tail(&mut short, 4, 2);
+        assert_eq!(short[0], 0.5);
+        assert!((short[1] - 1.5 * std::f32::consts::FRAC_1_SQRT_2).abs() < 1e-6);
+        assert_eq!(&short[2..], &[1.0, 1.0]);
+    }
+
+    #[test]
+    fn test_wrap_loop_tail_longer_than_fade_keeps_the_downbeat() {
+        // The downbeat plays at full level under a ringing tail
+        let mut long = vec![1.0, 1.0, 1.0, 1.0, 0.5, 0.5, 0.5, 0.25, 0.25];
+        wrap_loop_tail(&mut long, 4, 2);
+        assert_eq!(long, vec![1.75, 1.5, 1.5, 1.25]);
+    }
+
+    #[test]
+    fn test_export_clips_to_individual_files() {
+        let dir = temp_dir();
+        write_tone(&dir.join("tone.wav"), 4800);
+        let mut project = DawProject::new("Library");
+        let mut track = Track::new("Hits", TrackType::Audio);
+        track.volume = 0.0;
+        track.quick_filters.low_pass_hz = super::super::quick_filters::MIN_FILTER_HZ;
+        for (start, fade_in) in [(0, 0), (9600, 1200)] {
+            let mut clip = AudioClip::new("tone.wav".into(), start, 2400);
+            clip.name = "Hit".to_string();
+            clip.fade_in = fade_in;
+            track.clips.push(clip);
+        }
+        let track_id = track.id;
+        let clips: Vec<(TrackId, ClipId)> = track.clips.iter().map(|c| (track_id, c.id)).collect();
+        project.add_track(track);
+
+        let folder = dir.join("export");
+        std::fs::create_dir_all(&folder).unwrap();
+        std::fs::write(folder.join("Hit.wav"), b"existing").unwrap();
+
+        let mut calls = Vec::new();
+        let report = export_clips(&project, &dir, &clips, &folder, &ClipExportOptions::default(), |done, total| {
+            calls.push((done, total));
+            true
+        })
+        .unwrap();
+        assert!(report.failed.is_empty(), "{:?}", report.failed);
+        assert_eq!(calls, vec![(1, 2), (2, 2)]);
+        assert_eq!(report.exported, vec![folder.join("Hit (2).wav"), folder.join("Hit (3).wav")]);
+        assert_eq!(std::fs::read(folder.join("Hit.wav")).unwrap(), b"existing");
+
+        // Dry exports ignore the silent fader and the closed low-pass but
+        // keep the clip's own fade
+        let read = |path: &Path| -> Vec<f32> { hound::WavReader::open(path).unwrap().samples().map(|s| s.unwrap()).collect() };
+        let first = read(&report.exported[0]);
+        let faded = read(&report.exported[1]);
+        assert_eq!(first.len(), 4800);
+        assert!(first.iter().any(|s| s.abs() > 0.1));
+        assert!(faded[20].abs() < first[20].abs());
+
+        // With the track included the fader silences it, and cancelling stops the batch
+        let options = ClipExportOptions { include_track: true, ..ClipExportOptions::default() };
+        let report = export_clips(&project, &dir, &clips, &folder, &options, |_, _| false).unwrap();
+        assert!(report.cancelled);
+        assert_eq!(report.exported.len(), 1);
+        assert!(read(&report.exported[0]).iter().all(|s| *s == 0.0));
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_surround_render_writes_every_channel() {
+        use super::super::surround::{ChannelLayout, SurroundPan};
+
+        let dir = temp_dir();
+        write_tone(&dir.join("tone.wav"), 4800);
+        let mut project = DawProject::new("Bed");
+        project.channel_layout = ChannelLayout::Quad;
+        let mut track = Track::new("Ambience", TrackType::Audio);
+        track.surround_pan = SurroundPan { x: 1.0, y: -1.0, lfe: 0.0 };
+        track.clips.push(AudioClip::new("tone.wav".into(), 0, 4800));
+        project.add_track(track);
+
+        let output = dir.join("bed.wav");
+        let summary = render_loaded_project(&project, &dir, &output, &RenderOptions::default()).unwrap();
+        assert_eq!(summary.frames, 4800);
+        let mut reader = hound::WavReader::open(&output).unwrap();
+        assert_eq!(reader.spec().channels, 4);
+        let samples: Vec<f32> = reader.samples().map(|s| s.unwrap()).collect();
+        let level = |channel: usize| samples.iter().skip(channel).step_by(4).fold(0.0_f32, |peak, s| peak.max(s.abs()));
+        // Back right is the right surround, the last channel of quad
+        assert!(level(3) > 0.1);
+        assert!(level(0) < 1e-3 && level(1) < 1e-3 && level(2) < 1e-3);
+
+        // The speaker mask says which speakers those are
+        let bytes = std::fs::read(&output).unwrap();
+        let fmt = bytes.windows(4).position(|w| w == b"fmt ").unwrap() + 8;
+        assert_eq!(u16::from_le_bytes([bytes[fmt], bytes[fmt + 1]]), 0xFFFE);
+        let mask = u32::from_le_bytes(bytes[fmt + 20..fmt + 24].try_into().unwrap());
+        assert_eq!(mask, ChannelLayout::Quad.channel_mask());
+
+        // Stereo-only processing is refused rather than skipped
+        let options = RenderOptions { trim: Some(SilenceTrim::default()), ..RenderOptions::default() };
+        assert!(render_loaded_project(&project, &dir, &output, &options).is_err());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_looped_clip_exports_with_smpl_chunk() {
+        let dir = temp_dir();
+        write_tone(&dir.join("tone.wav"), 4800);
+        let mut project = DawProject::new("Loops");
+        let mut track = Track::new("Pad", TrackType::Audio);
+        let mut clip = AudioClip::new("tone.wav".into(), 1000, 9600);
+        clip.name = "Pad".to_string();
+        clip.offset = 800;
+        clip.loop_points = Some(ClipLoop { start: 1200, end: 3600 });
+        clip.tempo_mode = Some(TempoMode::Fixed);
+        let clips = vec![(track.id, clip.id)];
+        track.clips.push(clip);
+        project.add_track(track);
+
+        let folder = dir.join("export");
+        let report = export_clips(&project, &dir, &clips, &folder, &ClipExportOptions::default(), |_, _| true).unwrap();
+        assert!(report.failed.is_empty(), "{:?}", report.failed);
+
+        // The clip plays past the end of its file by repeating the loop
+        let mut reader = hound::WavReader::open(&report.exported[0]).unwrap();
+        assert_eq!(reader.duration(), 9600);
+        assert!(reader.samples::<f32>().skip(2 * 9000).any(|s| s.unwrap().abs() > 0.1));
+
+        let bytes = std::fs::read(&report.exported[0]).unwrap();
+        assert_eq!(u32::from_le_bytes(bytes[4..8].try_into().unwrap()) as usize, bytes.len() - 8);
+        let at = bytes.windows(4).position(|w| w == b"smpl").unwrap();
+        let field = |index: usize| u32::from_le_bytes(bytes[at + 8 + index * 4..at + 12 + index * 4].try_into().unwrap());
+        assert_eq!(field(7), 1);
+        // The loop is counted from the start of the exported file
+        assert_eq!((field(11), field(12)), (400, 2799));
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_conform_asset_writes_stereo_at_project_rate() {
+        let dir = temp_dir();
+        let asset = AudioAssetData {
+            asset_ref: AudioAssetRef {
+                path: dir.join("take.flac"),
+                sample_rate: 48000.0,
+                channels: 1,
+                duration_samples: 3,
+                source: SourceFormat { sample_rate: 44100, bits_per_sample: Some(16), float: false, channels: 1 },
+                health: Default::default(),
+            },
+            samples: std::sync::Arc::new(vec![0.1, 0.2, 0.3]),
+        };
+
+        let output = conform_asset(&asset, &dir).unwrap();
+        assert_eq!(output, dir.join("take 48k.wav"));
+        let mut reader = hound::WavReader::open(&output).unwrap();
+        assert_eq!((reader.spec().channels, reader.spec().sample_rate), (2, 48000));
+        let samples: Vec<f32> = reader.samples().map(|s| s.unwrap()).collect();
+        assert_eq!(samples, vec![0.1, 0.1, 0.2, 0.2, 0.3, 0.3]);
+
+        // Conforming again doesn't overwrite the first copy
+        assert_eq!(conform_asset(&asset, &dir).unwrap(), dir.join("take 48k (2).wav"));
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_split_asset_channels_writes_one_mono_file_each() {
+        let dir = temp_dir();
+        let mut asset = AudioAssetData {
+            asset_ref: AudioAssetRef {
+                path: dir.join("stem.wav"),
+                sample_rate: 48000.0,
+                channels: 2,
+                duration_samples: 2,
+                source: SourceFormat { sample_rate: 48000, bits_per_sample: Some(24), float: false, channels: 2 },
+                health: Default::default(),
+            },
+            // Dialogue on the left, music on the right
+            samples: std::sync::Arc::new(vec![0.1, 0.5, 0.2, 0.6]),
+        };
+
+        let split = split_asset_channels(&asset, &dir).unwrap();
+        let names: Vec<&str> = split.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["stem L", "stem R"]);
+        for ((_, path), expected) in split.iter().zip([[0.1_f32, 0.2], [0.5, 0.6]]) {
+            let mut reader = hound::WavReader::open(path).unwrap();
+            assert_eq!((reader.spec().channels, reader.spec().sample_rate), (1, 48000));
+            let samples: Vec<f32> = reader.samples().map(|s| s.unwrap()).collect();
+            assert_eq!(samples, expected);
+        }
+
+        // Past two channels they're numbered, and a mono file can't be split
+        assert_eq!(split_channel_names("stem", 3), ["stem 1", "stem 2", "stem 3"]);
+        asset.asset_ref.channels = 1;
+        assert!(split_asset_channels(&asset, &dir).is_err());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_render_reports_every_missing_file() {
+        let dir = temp_dir();
+        let mut project = DawProject::new("Missing");
+        let mut track = Track::new("Drums", TrackType::Audio);
+        track.clips.push(AudioClip::new("kick.wav".into(), 0, 100));
+        track.clips.push(AudioClip::new("snare.wav".into(), 100, 100));
+        project.add_track(track);
+
+        let error = render_loaded_project(&project, &dir, &dir.join("out.wav"), &RenderOptions::default())
+            .unwrap_err()
+            .to_string();
+
+        assert!(error.contains("kick.wav"));
+        assert!(error.contains("snare.wav"));
+        assert!(!dir.join("out.wav").exists());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_marker_regions_are_sorted_and_checked() {
+        let mut project = DawProject::new("Cues");
+        let mut track = Track::new("Tone", TrackType::Audio);
+        track.clips.push(AudioClip::new("tone.wav".into(), 0, 48000));
+        project.add_track(track);
+        project.markers = vec![
+            Marker::new("Combat", 24000),
+            Marker::new("Intro", 0),
+            Marker::new("Duplicate", 12000),
+            Marker::new("Explore", 12000),
+            Marker::new("After End", 60000),
+        ];
+
+        let (regions, warnings) = marker_regions(&project);
+        let spans: Vec<_> = regions.iter().map(|r| (r.name.as_str(), r.start, r.end)).collect();
+        assert_eq!(spans, vec![("Intro", 0, 12000), ("Explore", 12000, 24000), ("Combat", 24000, 60000)]);
+        assert_eq!(warnings.len(), 3, "{:?}", warnings);
+        assert!(warnings[0].contains("sorted"));
+
+        // Two markers picked in either order give the span between them
+        let (intro, combat) = (project.markers[1].id, project.markers[0].id);
+        let region = marker_region(&project, combat, Some(intro)).unwrap();
+        assert_eq!((region.name.as_str(), region.start, region.end), ("Intro", 0, 24000));
+        assert_eq!(marker_region(&project, intro, None).unwrap().end, 12000);
+        assert!(marker_region(&project, project.markers[4].id, None).is_err());
+
+        // Arrangement regions export as cues in timeline order
+        project.regions = vec![Region::new("Boss", 30000, 48000, 1), Region::new("Intro", 0, 20000, 0)];
+        let cues: Vec<_> = region_cues(&project).into_iter().map(|r| (r.name, r.start, r.end)).collect();
+        assert_eq!(cues, vec![("Intro".to_string(), 0, 20000), ("Boss".to_string(), 30000, 48000)]);
+    }
+
+    #[test]
+    fn test_export_marker_regions() {
+        let dir = temp_dir();
+        write_tone(&dir.join("tone.wav"), 4800);
+        let mut project = DawProject::new("Cues");
+        let mut track = Track::new("Tone", TrackType::Audio);
+        track.clips.push(AudioClip::new("tone.wav".into(), 0, 4800));
+        project.add_track(track);
+        project.markers = vec![Marker::new("Stinger", 0), Marker::new("Stinger", 1200)];
+
+        let (regions, _) = marker_regions(&project);
+        let folder = dir.join("cues");
+        let report = export_marker_regions(&project, &dir, &regions, &folder, &RenderOptions::default(), |_, _| true).unwrap();
+        assert!(report.failed.is_empty(), "{:?}", report.failed);
+        assert_eq!(report.exported, vec![folder.join("Stinger.wav"), folder.join("Stinger (2).wav")]);
+        let frames = |path: &Path| hound::WavReader::open(path).unwrap().duration();
+        assert_eq!(frames(&report.exported[0]), 1200);
+        assert_eq!(frames(&report.exported[1]), 3600);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_flattened_project_plays_the_same_mix() {
+        let dir = temp_dir();
+        write_tone(&dir.join("tone.wav"), 4800);
+        let mut project = reference_project();
+        let mut muted = Track::new("Muted", TrackType::Audio);
+        muted.muted = true;
+        muted.clips.push(AudioClip::new("tone.wav".into(), 0, 4800));
+        project.add_track(muted);
+
+        let output = flattened_project_path(&dir.join("handoff"), &project.name);
+        let report = flatten_project(&project, &dir, &output, &FlattenOptions::default(), |_, _| true).unwrap();
+        assert!(report.failed.is_empty(), "{:?}", report.failed);
+        assert_eq!(report.exported.len(), 3, "one file per audio track, none for the return");
+
+        // Plain tracks with nothing but their rendered clip, and muted
+        // tracks stay muted but can be brought back
+        let flat = DawProject::load(&output).unwrap();
+        assert_eq!(flat.tracks.len(), 3);
+        for track in &flat.tracks {
+            assert!(track.effects.is_empty() && track.automation.is_empty());
+            assert!(track.sends.iter().all(|s| s.target_track.is_none()));
+            assert_eq!((track.clips.len(), track.volume, track.pan), (1, 1.0, 0.0));
+        }
+        let muted = flat.tracks.iter().find(|t| t.name == "Muted").unwrap();
+        assert!(muted.muted);
+        assert!(hound::WavReader::open(dir.join("handoff").join(&muted.clips[0].asset_path))
+            .unwrap()
+            .samples::<f32>()
+            .any(|s| s.unwrap().abs() > 0.01));
+
+        // Played from its own folder, the flattened project is the same mix
+        let read = |path: &Path| -> Vec<f32> { hound::WavReader::open(path).unwrap().samples().map(|s| s.unwrap()).collect() };
+        let original = dir.join("original.wav");
+        render_loaded_project(&project, &dir, &original, &RenderOptions::default()).unwrap();
+        let flattened = dir.join("flattened.wav");
+        render_project(&output, &flattened, &RenderOptions::default()).unwrap();
+        let (original, flattened) = (read(&original), read(&flattened));
+        assert_eq!(original.len(), flattened.len());
+        let worst = original.iter().zip(&flattened).map(|(a, b)| (a - b).abs()).fold(0.0, f32::max);
+        assert!(worst < 1e-3, "flattened mix differs by {}", worst);
+
+        // Cancelling leaves nothing behind
+        let again = flattened_project_path(&dir.join("cancelled"), &project.name);
+        let report = flatten_project(&project, &dir, &again, &FlattenOptions::default(), |_, _| false).unwrap();
+        assert!(report.cancelled && report.exported.is_empty());
+        assert!(!again.exists());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_bounced_track_replays_the_track() {
+        let dir = temp_dir();
+        write_tone(&dir.join("tone.wav"), 4800);
+        let project = reference_project();
+        let source = project.tracks.iter().find(|t| t.name == "Right").unwrap();
+        let bounced = bounce_track(&project, &dir, source.id, &dir.join("audio"), 0).unwrap();
+        assert_eq!(bounced.path, dir.join("audio").join("Right (Bounced).wav"));
+        assert_eq!((bounced.start, bounced.frames), (1200, 4800));
+
+        // On a plain track the print sounds like the track did through its
+        // own channel, without the return it also feeds
+        let mut dry = project.clone();
+        dry.tracks.retain(|t| t.id == source.id);
+        dry.tracks[0].sends[0].enabled = false;
+        let mut replay = DawProject::new("Replay");
+        let mut clip = AudioClip::new(bounced.path.clone(), bounced.start, bounced.frames);
+        clip.gain = bounced.gain;
+        let mut track = Track::new("Right (Bounced)", TrackType::Audio);
+        track.clips.push(clip);
+        replay.add_track(track);
+
+        let read = |path: &Path| -> Vec<f32> { hound::WavReader::open(path).unwrap().samples().map(|s| s.unwrap()).collect() };
+        let range = RenderOptions { start: Some(0), end: Some(6000), ..RenderOptions::default() };
+        render_loaded_project(&dry, &dir, &dir.join("dry.wav"), &range).unwrap();
+        render_loaded_project(&replay, &dir, &dir.join("replay.wav"), &range).unwrap();
+        let (dry, replay) = (read(&dir.join("dry.wav")), read(&dir.join("replay.wav")));
+        assert_eq!(dry.len(), replay.len());
+        let worst = dry.iter().zip(&replay).map(|(a, b)| (a - b).abs()).fold(0.0, f32::max);
+        assert!(worst < 1e-3, "bounce differs by {}", worst);
+
+        let reverb = project.tracks.iter().find(|t| t.name == "Reverb").unwrap();
+        assert!(bounce_track(&project, &dir, reverb.id, &dir, 0).is_err());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_reference_image_of_a_track_and_the_master() {
+        let dir = temp_dir();
+        write_tone(&dir.join("tone.wav"), 4800);
+        let project = reference_project();
+        let right = project.tracks.iter().find(|t| t.name == "Right").unwrap().id;
+        let settings = ReferenceImageSettings { width: 60, height: 32, view: ImageView::Waveform, ..ReferenceImageSettings::default() };
+
+        // A column per 100 frames: the track is silent until its clip at
+        // 1200, while the master already has the other track playing
+        let track = render_reference_image(&project, &dir, Some(right), 0, 6000, &settings).unwrap();
+        let master = render_reference_image(&project, &dir, None, 0, 6000, &settings).unwrap();
+        let background = track.pixel(0, 0);
+        let lit = |image: &RgbImage, x: u32| (0..32).filter(|y| *y != 16).any(|y| image.pixel(x, y) != background);
+        assert!(!lit(&track, 5) && lit(&track, 20));
+        assert!(lit(&master, 5));
+
+        let path = dir.join("images").join("right.png");
+        export_reference_image(&project, &dir, Some(right), 0, 6000, &settings, &path).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), track.to_png().unwrap());
+
+        let reverb = project.tracks.iter().find(|t| t.name == "Reverb").unwrap().id;
+        assert!(render_reference_image(&project, &dir, Some(reverb), 0, 6000, &settings).is_err());
+        assert!(render_reference_image(&project, &dir, None, 6000, 6000, &settings).is_err());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
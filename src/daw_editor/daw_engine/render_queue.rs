@@ -0,0 +1,317 @@
+/// Unattended render queue
+/// Runs a list of render jobs one after another, each naming a project, the
+/// part of it to render and where the result goes, for nightly builds of
+/// game audio. Jobs succeed or fail on their own, so one broken project
+/// never stops the rest. Queues are saved as JSON that pipelines can write
+/// by hand.
+use super::audio_types::SampleTime;
+use super::loudness::LoudnessTarget;
+use super::project::DawProject;
+use super::render::{export_marker_regions, marker_regions, render_loaded_project, RenderBitDepth, RenderOptions};
+use anyhow::{Context as AnyhowContext, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Folder beside a project that its queued renders go to
+pub const RENDERS_FOLDER: &str = "Renders";
+
+/// `file` in the Renders folder beside `project`
+pub fn queued_render_path(project: &Path, file: &str) -> PathBuf {
+    project.parent().unwrap_or_else(|| Path::new(".")).join(RENDERS_FOLDER).join(file)
+}
+
+/// The part of a project a job renders
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(tag = "kind")]
+pub enum RenderRange {
+    /// From the start to the end of the last clip
+    #[default]
+    Project,
+    /// Between two timeline positions, in samples
+    Samples { start: SampleTime, end: SampleTime },
+    /// From the named marker to the next one
+    Marker { name: String },
+    /// Every marker region, one file each in the output folder
+    AllMarkers,
+}
+
+impl RenderRange {
+    pub fn label(&self) -> String {
+        match self {
+            RenderRange::Project => "Whole project".to_string(),
+            RenderRange::Samples { start, end } => format!("Samples {} to {}", start, end),
+            RenderRange::Marker { name } => format!("Marker '{}'", name),
+            RenderRange::AllMarkers => "All marker regions".to_string(),
+        }
+    }
+}
+
+/// One render in a queue
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RenderJob {
+    /// Shown in the queue and its reports
+    pub name: String,
+    pub project: PathBuf,
+    /// WAV file to write, or the folder for [`RenderRange::AllMarkers`]
+    pub output: PathBuf,
+    #[serde(default)]
+    pub range: RenderRange,
+    #[serde(default)]
+    pub bit_depth: RenderBitDepth,
+    /// Rendered past the end so tails aren't cut, in seconds
+    #[serde(default)]
+    pub tail_seconds: f32,
+    /// Integrated loudness to normalize to, in LUFS
+    #[serde(default)]
+    pub loudness_lufs: Option<f32>,
+    /// True-peak ceiling to limit the output to, in dBTP
+    #[serde(default)]
+    pub true_peak_ceiling_db: Option<f32>,
+    /// Fail the job when a file it writes has overs
+    #[serde(default)]
+    pub fail_on_overs: bool,
+}
+
+impl RenderJob {
+    /// A whole-project render named after the project file
+    pub fn new(project: impl Into<PathBuf>, output: impl Into<PathBuf>) -> Self {
+        let project = project.into();
+        let name = project.file_stem().map_or_else(|| "Render".to_string(), |s| s.to_string_lossy().into_owned());
+        Self {
+            name,
+            project,
+            output: output.into(),
+            range: RenderRange::Project,
+            bit_depth: RenderBitDepth::default(),
+            tail_seconds: 0.0,
+            loudness_lufs: None,
+            true_peak_ceiling_db: None,
+            fail_on_overs: false,
+        }
+    }
+
+    /// Load the project and render it, returning the files written
+    pub fn run(&self) -> Result<Vec<PathBuf>> {
+        let project = DawProject::load(&self.project)
+            .with_context(|| format!("Failed to load project {:?}", self.project))?;
+        let base_dir = self.project.parent().unwrap_or_else(|| Path::new("."));
+        let options = RenderOptions {
+            tail_samples: (self.tail_seconds.max(0.0) as f64 * project.sample_rate as f64).round() as SampleTime,
+            bit_depth: self.bit_depth,
+            loudness: self.loudness_lufs.map(LoudnessTarget::new),
+            true_peak_ceiling: self.true_peak_ceiling_db,
+            fail_on_overs: self.fail_on_overs,
+            ..RenderOptions::default()
+        };
+
+        let (start, end) = match &self.range {
+            RenderRange::Project => (None, None),
+            RenderRange::Samples { start, end } => {
+                if end <= start {
+                    anyhow::bail!("Render range ends at {} before it starts at {}", end, start);
+                }
+                (Some(*start), Some(*end))
+            }
+            RenderRange::Marker { name } => {
+                let marker = project
+                    .markers
+                    .iter()
+                    .find(|m| m.name == *name)
+                    .ok_or_else(|| anyhow::anyhow!("Project has no marker named '{}'", name))?;
+                let region = marker_regions(&project)
+                    .0
+                    .into_iter()
+                    .find(|r| r.marker == marker.id)
+                    .ok_or_else(|| anyhow::anyhow!("Marker '{}' has no audio after it", name))?;
+                (Some(region.start), Some(region.end))
+            }
+            RenderRange::AllMarkers => {
+                let (regions, warnings) = marker_regions(&project);
+                for warning in &warnings {
+                    tracing::error!("⚠️ {}: {}", self.name, warning);
+                }
+                if regions.is_empty() {
+                    anyhow::bail!("Project has no marker regions to render");
+                }
+                let report = export_marker_regions(&project, base_dir, &regions, &self.output, &options, |_, _| true)?;
+                if !report.failed.is_empty() {
+                    let failed: Vec<String> = report.failed.iter().map(|(name, e)| format!("'{}': {}", name, e)).collect();
+                    anyhow::bail!("{} of {} regions failed: {}", failed.len(), regions.len(), failed.join("; "));
+                }
+                return Ok(report.exported);
+            }
+        };
+
+        if let Some(parent) = self.output.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent).with_context(|| format!("Failed to create {:?}", parent))?;
+        }
+        render_loaded_project(&project, base_dir, &self.output, &RenderOptions { start, end, ..options })?;
+        Ok(vec![self.output.clone()])
+    }
+}
+
+/// Where a job is in a run of the queue
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum JobState {
+    #[default]
+    Queued,
+    Running,
+    /// Finished, with the files it wrote
+    Done(Vec<PathBuf>),
+    Failed(String),
+    /// Not reached because the queue was stopped
+    Cancelled,
+}
+
+impl JobState {
+    pub fn label(&self) -> &'static str {
+        match self {
+            JobState::Queued => "Queued",
+            JobState::Running => "Rendering",
+            JobState::Done(_) => "Done",
+            JobState::Failed(_) => "Failed",
+            JobState::Cancelled => "Cancelled",
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        matches!(self, JobState::Done(_) | JobState::Failed(_) | JobState::Cancelled)
+    }
+}
+
+/// Render jobs run in order
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RenderQueue {
+    pub jobs: Vec<RenderJob>,
+}
+
+impl RenderQueue {
+    /// Load a queue file. Relative project and output paths are taken from
+    /// the queue file's folder, so queues can travel with their projects.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let json = std::fs::read_to_string(path).with_context(|| format!("Failed to read render queue {:?}", path))?;
+        let mut queue: RenderQueue =
+            serde_json::from_str(&json).with_context(|| format!("Failed to parse render queue {:?}", path))?;
+        let base_dir = path.parent().unwrap_or_else(|| Path::new("."));
+        for job in &mut queue.jobs {
+            for path in [&mut job.project, &mut job.output] {
+                if path.is_relative() {
+                    *path = base_dir.join(&*path);
+                }
+            }
+        }
+        Ok(queue)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let json = serde_json::to_string_pretty(self).context("Failed to serialize render queue")?;
+        std::fs::write(path, json).with_context(|| format!("Failed to write render queue {:?}", path))
+    }
+
+    /// Run every job in order. `update` hears each job's state as it starts
+    /// and when it finishes, and stops the queue by returning false; jobs
+    /// not started by then end up Cancelled. A failed job is logged and the
+    /// queue moves on.
+    pub fn run(&self, mut update: impl FnMut(usize, &JobState) -> bool) -> Vec<JobState> {
+        let mut states = vec![JobState::Queued; self.jobs.len()];
+        for (index, job) in self.jobs.iter().enumerate() {
+            if !update(index, &JobState::Running) {
+                break;
+            }
+            let state = match job.run() {
+                Ok(outputs) => {
+                    tracing::info!("🎬 Render job '{}' wrote {} file(s)", job.name, outputs.len());
+                    JobState::Done(outputs)
+                }
+                Err(e) => {
+                    tracing::error!("❌ Render job '{}' failed: {:#}", job.name, e);
+                    JobState::Failed(format!("{:#}", e))
+                }
+            };
+            let keep_going = update(index, &state);
+            states[index] = state;
+            if !keep_going {
+                break;
+            }
+        }
+        for state in states.iter_mut().filter(|s| **s == JobState::Queued) {
+            *state = JobState::Cancelled;
+        }
+        states
+    }
+}
+
+/// One line per job and a total, for logs and CI output
+pub fn queue_summary(jobs: &[RenderJob], states: &[JobState]) -> String {
+    let mut lines: Vec<String> = jobs
+        .iter()
+        .zip(states)
+        .map(|(job, state)| match state {
+            JobState::Done(outputs) => format!("{}: {}, {} file(s)", job.name, state.label(), outputs.len()),
+            JobState::Failed(error) => format!("{}: {}, {}", job.name, state.label(), error),
+            _ => format!("{}: {}", job.name, state.label()),
+        })
+        .collect();
+    let failed = states.iter().filter(|s| matches!(s, JobState::Failed(_))).count();
+    let done = states.iter().filter(|s| matches!(s, JobState::Done(_))).count();
+    lines.push(format!("{} of {} jobs done, {} failed", done, jobs.len(), failed));
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::audio_types::*;
+    use super::super::test_support::{temp_dir, write_tone};
+
+    #[test]
+    fn test_queue_runs_past_failures() {
+        let dir = temp_dir();
+        write_tone(&dir.join("tone.wav"), 9600);
+
+        let mut project = DawProject::new("Footsteps");
+        let mut track = Track::new("Tone", TrackType::Audio);
+        track.clips.push(AudioClip::new("tone.wav".into(), 0, 9600));
+        project.add_track(track);
+        project.markers.push(Marker::new("Left", 0));
+        project.markers.push(Marker::new("Right", 4800));
+        project.save(dir.join("footsteps.pdaw")).unwrap();
+
+        let whole = RenderJob::new("footsteps.pdaw", "out/footsteps.wav");
+        let missing = RenderJob::new("gone.pdaw", "out/gone.wav");
+        let marker = RenderJob {
+            name: "Right step".to_string(),
+            range: RenderRange::Marker { name: "Right".to_string() },
+            ..RenderJob::new("footsteps.pdaw", "out/right.wav")
+        };
+        let regions = RenderJob { range: RenderRange::AllMarkers, ..RenderJob::new("footsteps.pdaw", "out/regions") };
+        let queue = RenderQueue { jobs: vec![whole, missing, marker, regions] };
+        queue.save(dir.join("nightly.json")).unwrap();
+
+        // Paths in the file are relative to it
+        let queue = RenderQueue::load(dir.join("nightly.json")).unwrap();
+        assert_eq!(queue.jobs[0].project, dir.join("footsteps.pdaw"));
+        let mut updates = Vec::new();
+        let states = queue.run(|index, state| {
+            updates.push((index, state.label()));
+            true
+        });
+
+        assert!(matches!(&states[1], JobState::Failed(e) if e.contains("gone.pdaw")), "{:?}", states[1]);
+        assert_eq!(states[0], JobState::Done(vec![dir.join("out/footsteps.wav")]));
+        let reader = hound::WavReader::open(dir.join("out/right.wav")).unwrap();
+        assert_eq!(reader.duration(), 4800);
+        assert!(matches!(&states[3], JobState::Done(files) if files.len() == 2));
+        assert_eq!(updates.len(), 8);
+        assert_eq!(updates[3], (1, "Failed"));
+        assert!(queue_summary(&queue.jobs, &states).ends_with("3 of 4 jobs done, 1 failed"));
+
+        // Stopping after the first job leaves the rest cancelled
+        let states = queue.run(|index, state| !(index == 0 && state.is_finished()));
+        assert!(matches!(states[0], JobState::Done(_)));
+        assert!(states[1..].iter().all(|s| *s == JobState::Cancelled));
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,215 @@
+/// Bad sample detection and repair
+/// Files that went through broken processing can carry NaN or infinite
+/// samples, which poison every bus they reach and end up in exports. Those
+/// are always replaced with silence as a file loads. Scanning for clipping
+/// is optional, as it costs a second pass: runs of samples stuck at full
+/// scale are reported, and short ones are rebuilt with a curve through the
+/// samples either side, which recovers the peak the converter flattened.
+use serde::{Deserialize, Serialize};
+
+/// Samples at or above this are at full scale
+pub const CLIP_LEVEL: f32 = 0.999;
+
+/// Fewest samples in a row at full scale that count as clipped. One or two
+/// can be a legitimately loud peak.
+pub const MIN_CLIPPED_RUN: usize = 3;
+
+/// Longest clipped run that is rebuilt. Beyond this there's too little
+/// left of the waveform to guess its shape.
+pub const MAX_DECLIP_RUN: usize = 32;
+
+/// Clipped runs listed in a report; longer lists only count the rest
+pub const MAX_LISTED_RUNS: usize = 100;
+
+/// Samples in a row stuck at full scale on one channel
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClippedRun {
+    pub channel: usize,
+    /// First frame of the run
+    pub start: usize,
+    pub length: usize,
+}
+
+/// What a clipping scan found
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct ClipScan {
+    /// Clipped runs found on all channels
+    pub runs: usize,
+    /// How many of them were rebuilt
+    pub repaired: usize,
+    /// The first runs in file order, at most [`MAX_LISTED_RUNS`]
+    pub listed: Vec<ClippedRun>,
+}
+
+/// What loading a file found wrong with its samples
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct SampleHealth {
+    /// NaN and infinite samples, replaced with silence
+    pub non_finite: usize,
+    /// None unless the file was scanned for clipping
+    pub clipping: Option<ClipScan>,
+}
+
+impl SampleHealth {
+    pub fn is_clean(&self) -> bool {
+        self.non_finite == 0 && self.clipping.as_ref().is_none_or(|scan| scan.runs == 0)
+    }
+
+    /// Short text for a clip badge, empty for a clean file
+    pub fn badge(&self) -> String {
+        let mut parts = Vec::new();
+        if self.non_finite > 0 {
+            parts.push("NaN");
+        }
+        if self.clipping.as_ref().is_some_and(|scan| scan.runs > 0) {
+            parts.push("CLIP");
+        }
+        parts.join(" ")
+    }
+
+    /// One line per problem, with times for a buffer at `sample_rate`
+    pub fn descriptions(&self, sample_rate: f32) -> Vec<String> {
+        let mut lines = Vec::new();
+        if self.non_finite > 0 {
+            lines.push(format!("{} NaN or infinite samples replaced with silence", self.non_finite));
+        }
+        if let Some(scan) = self.clipping.as_ref().filter(|scan| scan.runs > 0) {
+            let first = scan.listed.first().map_or(String::new(), |run| {
+                format!(", the first at {:.2} s", run.start as f32 / sample_rate.max(1.0))
+            });
+            lines.push(format!(
+                "{} clipped runs{}; {} short enough to rebuild",
+                scan.runs, first, scan.repaired
+            ));
+        }
+        lines
+    }
+}
+
+/// Replace NaN and infinite samples with silence, returning how many there were
+pub fn replace_non_finite(samples: &mut [f32]) -> usize {
+    let mut replaced = 0;
+    for sample in samples.iter_mut().filter(|s| !s.is_finite()) {
+        *sample = 0.0;
+        replaced += 1;
+    }
+    replaced
+}
+
+/// Runs of at least [`MIN_CLIPPED_RUN`] samples at full scale with the
+/// same sign, per channel of interleaved `samples`, in file order
+pub fn find_clipped_runs(samples: &[f32], channels: usize) -> Vec<ClippedRun> {
+    let channels = channels.max(1);
+    let frames = samples.len() / channels;
+    let mut runs = Vec::new();
+    for channel in 0..channels {
+        let sample = |frame: usize| samples[frame * channels + channel];
+        let mut frame = 0;
+        while frame < frames {
+            let first = sample(frame);
+            if first.abs() < CLIP_LEVEL {
+                frame += 1;
+                continue;
+            }
+            let start = frame;
+            while frame < frames && sample(frame).abs() >= CLIP_LEVEL && sample(frame).signum() == first.signum() {
+                frame += 1;
+            }
+            if frame - start >= MIN_CLIPPED_RUN {
+                runs.push(ClippedRun { channel, start, length: frame - start });
+            }
+        }
+    }
+    runs.sort_by_key(|run| (run.start, run.channel));
+    runs
+}
+
+/// Rebuild a clipped run with a cubic through the two samples before it and
+/// the two after, keeping the slope on both sides. Samples the curve would
+/// put below full scale are left as they were. Returns false for runs too
+/// long or too near either end of the file to rebuild.
+pub fn declip_run(samples: &mut [f32], channels: usize, run: ClippedRun) -> bool {
+    let channels = channels.max(1);
+    let frames = samples.len() / channels;
+    let end = run.start + run.length;
+    if run.length > MAX_DECLIP_RUN || run.start < 2 || end + 2 > frames {
+        return false;
+    }
+    let at = |frame: usize| frame * channels + run.channel;
+    let (before, after) = (samples[at(run.start - 1)], samples[at(end)]);
+    let slope_in = before - samples[at(run.start - 2)];
+    let slope_out = samples[at(end + 1)] - after;
+    // Frames from the sample before the run to the one after it
+    let span = (run.length + 1) as f32;
+
+    for offset in 1..=run.length {
+        let t = offset as f32 / span;
+        let (t2, t3) = (t * t, t * t * t);
+        let rebuilt = (2.0 * t3 - 3.0 * t2 + 1.0) * before
+            + (t3 - 2.0 * t2 + t) * span * slope_in
+            + (-2.0 * t3 + 3.0 * t2) * after
+            + (t3 - t2) * span * slope_out;
+        let clipped = &mut samples[at(run.start - 1 + offset)];
+        if rebuilt.signum() == clipped.signum() && rebuilt.abs() > clipped.abs() {
+            *clipped = rebuilt;
+        }
+    }
+    true
+}
+
+/// Check interleaved `samples` as they load: bad samples are always replaced
+/// with silence, and with `scan_clipping` clipped runs are found and the
+/// short ones rebuilt
+pub fn repair_samples(samples: &mut [f32], channels: usize, scan_clipping: bool) -> SampleHealth {
+    let non_finite = replace_non_finite(samples);
+    let clipping = scan_clipping.then(|| {
+        let runs = find_clipped_runs(samples, channels);
+        let repaired = runs.iter().filter(|run| declip_run(samples, channels, **run)).count();
+        ClipScan { runs: runs.len(), repaired, listed: runs.into_iter().take(MAX_LISTED_RUNS).collect() }
+    });
+    SampleHealth { non_finite, clipping }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bad_samples_are_silenced_and_short_clips_rebuilt() {
+        // A sine peaking at 1.5, clipped to full scale, on the left of a
+        // stereo file with a clean right channel
+        let clean: Vec<f32> = (0..64).map(|i| 1.5 * (std::f32::consts::TAU * i as f32 / 64.0).sin()).collect();
+        let mut samples: Vec<f32> = clean.iter().flat_map(|s| [s.clamp(-1.0, 1.0), s * 0.5]).collect();
+        samples[2] = f32::NAN;
+        samples[4] = f32::INFINITY;
+
+        let health = repair_samples(&mut samples, 2, true);
+        assert_eq!(health.non_finite, 2);
+        assert_eq!((samples[2], samples[4]), (0.0, 0.0));
+        let scan = health.clipping.as_ref().unwrap();
+        // One run on each half of the cycle, both rebuilt
+        assert_eq!(scan.runs, 2);
+        assert_eq!(scan.repaired, 2);
+        assert_eq!(scan.listed[0].channel, 0);
+        // The rebuilt peak comes back well above full scale, close to the original
+        let peak = samples.iter().step_by(2).fold(0.0_f32, |max, s| max.max(*s));
+        assert!(peak > 1.3 && peak < 1.7, "{}", peak);
+        assert_eq!(health.badge(), "NaN CLIP");
+
+        // Without the scan only the bad samples are touched
+        let mut flat = vec![0.5, f32::NAN, 1.0, 1.0, 1.0, 1.0, 0.5, 0.2];
+        let health = repair_samples(&mut flat, 1, false);
+        assert_eq!((health.non_finite, health.clipping), (1, None));
+        assert_eq!(flat, [0.5, 0.0, 1.0, 1.0, 1.0, 1.0, 0.5, 0.2]);
+
+        // Two full-scale samples are a peak, not clipping, and a run too
+        // long to rebuild is only reported
+        assert!(find_clipped_runs(&[0.2, 1.0, 1.0, 0.2], 1).is_empty());
+        let mut square = vec![0.0, 0.5];
+        square.extend(std::iter::repeat_n(1.0, MAX_DECLIP_RUN + 1));
+        square.extend([0.5, 0.0]);
+        let health = repair_samples(&mut square, 1, true);
+        assert_eq!(health.clipping.map(|scan| (scan.runs, scan.repaired)), Some((1, 0)));
+        assert!(SampleHealth::default().is_clean());
+    }
+}
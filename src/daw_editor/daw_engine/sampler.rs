@@ -0,0 +1,330 @@
+/// Sample-based instrument: key zones, velocity layers and voice allocation
+/// An instrument maps ranges of MIDI keys to zones, each holding velocity
+/// layers of one or more samples. Soft and hard hits play different layers,
+/// crossfading where neighbouring layers meet, and layers with several
+/// samples alternate between them without playing the same one twice in a
+/// row. Instruments are stored as JSON next to their samples.
+use anyhow::{Context as AnyhowContext, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Default number of voices an instrument can play at once
+const DEFAULT_MAX_VOICES: usize = 32;
+
+/// Samples played for one velocity range of a zone
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VelocityLayer {
+    /// Lowest and highest velocity (1-127) the layer plays at, inclusive
+    pub velocities: (u8, u8),
+    /// Alternatives picked in turn, never the same one twice in a row
+    pub samples: Vec<PathBuf>,
+    #[serde(default)]
+    pub gain_db: f32,
+}
+
+/// Samples mapped to a range of keys
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KeyZone {
+    /// Lowest and highest MIDI key the zone plays on, inclusive
+    pub keys: (u8, u8),
+    /// Key the samples were recorded at, played back unpitched
+    pub root_key: u8,
+    pub layers: Vec<VelocityLayer>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SamplerInstrument {
+    pub name: String,
+    pub zones: Vec<KeyZone>,
+    /// Velocity range over which neighbouring layers of a zone crossfade,
+    /// centred on the boundary between them. Zero switches layers hard.
+    #[serde(default)]
+    pub layer_crossfade: u8,
+    #[serde(default = "default_max_voices")]
+    pub max_voices: usize,
+}
+
+fn default_max_voices() -> usize {
+    DEFAULT_MAX_VOICES
+}
+
+impl SamplerInstrument {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            zones: Vec::new(),
+            layer_crossfade: 0,
+            max_voices: DEFAULT_MAX_VOICES,
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let json = std::fs::read_to_string(path).with_context(|| format!("Failed to read instrument {:?}", path))?;
+        serde_json::from_str(&json).with_context(|| format!("Failed to parse instrument {:?}", path))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, json).with_context(|| format!("Failed to write instrument {:?}", path))
+    }
+
+    /// Layers of `zone` sounding at `velocity` as (layer index, gain). Inside
+    /// a crossfade both layers play with equal-power gains; elsewhere a
+    /// single layer plays at full gain.
+    pub fn layer_gains(&self, zone: &KeyZone, velocity: u8) -> Vec<(usize, f32)> {
+        let half = self.layer_crossfade as f32 / 2.0;
+        let v = velocity as f32;
+        let weights: Vec<(usize, f32)> = zone
+            .layers
+            .iter()
+            .enumerate()
+            .filter_map(|(index, layer)| {
+                let (low, high) = (layer.velocities.0 as f32, layer.velocities.1 as f32);
+                // Only fade towards a neighbouring layer, never at the ends
+                // of the velocity range
+                let has_below = zone.layers.iter().any(|other| other.velocities.1.saturating_add(1) == layer.velocities.0);
+                let has_above = zone.layers.iter().any(|other| other.velocities.0 == layer.velocities.1.saturating_add(1));
+                let fade = |distance: f32| {
+                    if half > 0.0 {
+                        (distance / (2.0 * half) + 0.5).clamp(0.0, 1.0)
+                    } else if distance >= 0.0 {
+                        1.0
+                    } else {
+                        0.0
+                    }
+                };
+                let rise = if has_below { fade(v - (low - 0.5)) } else if v >= low { 1.0 } else { 0.0 };
+                let fall = if has_above { fade((high + 0.5) - v) } else if v <= high { 1.0 } else { 0.0 };
+                let weight = rise.min(fall);
+                (weight > 0.0).then_some((index, weight))
+            })
+            .collect();
+
+        let total: f32 = weights.iter().map(|(_, w)| w).sum();
+        weights
+            .into_iter()
+            .map(|(index, weight)| {
+                let gain = 10.0_f32.powf(zone.layers[index].gain_db / 20.0);
+                (index, (weight / total).sqrt() * gain)
+            })
+            .collect()
+    }
+}
+
+/// A sample to start playing for a note
+#[derive(Debug, Clone, PartialEq)]
+pub struct VoiceStart {
+    pub voice_id: u64,
+    pub sample: PathBuf,
+    pub gain: f32,
+    /// Playback rate, 1.0 at the zone's root key
+    pub pitch_ratio: f32,
+}
+
+#[derive(Debug, Clone)]
+struct Voice {
+    id: u64,
+    key: u8,
+    released: bool,
+}
+
+/// Plays a [`SamplerInstrument`]: picks the zones, layers and round-robin
+/// samples for each note and keeps the voice count within the instrument's
+/// limit by stealing the oldest voices
+pub struct Sampler {
+    pub instrument: SamplerInstrument,
+    voices: Vec<Voice>,
+    next_voice_id: u64,
+    /// Last sample played by each (zone, layer), for no-repeat round-robin
+    last_sample: std::collections::HashMap<(usize, usize), usize>,
+    rng: u64,
+}
+
+impl Sampler {
+    pub fn new(instrument: SamplerInstrument) -> Self {
+        Self {
+            instrument,
+            voices: Vec::new(),
+            next_voice_id: 0,
+            last_sample: std::collections::HashMap::new(),
+            rng: 0x9E37_79B9_7F4A_7C15,
+        }
+    }
+
+    /// Start a note. Returns the samples to play and the ids of voices
+    /// stolen to make room, which should stop at once.
+    pub fn note_on(&mut self, key: u8, velocity: u8) -> (Vec<VoiceStart>, Vec<u64>) {
+        if velocity == 0 {
+            self.note_off(key);
+            return (Vec::new(), Vec::new());
+        }
+
+        let mut starts = Vec::new();
+        for (zone_index, zone) in self.instrument.zones.iter().enumerate() {
+            if key < zone.keys.0 || key > zone.keys.1 {
+                continue;
+            }
+            let pitch_ratio = 2.0_f32.powf((key as f32 - zone.root_key as f32) / 12.0);
+            for (layer_index, gain) in self.instrument.layer_gains(zone, velocity) {
+                let layer = &zone.layers[layer_index];
+                if layer.samples.is_empty() {
+                    continue;
+                }
+                let pick = pick_round_robin(&mut self.rng, layer.samples.len(), self.last_sample.get(&(zone_index, layer_index)).copied());
+                self.last_sample.insert((zone_index, layer_index), pick);
+                starts.push(VoiceStart {
+                    voice_id: self.next_voice_id,
+                    sample: layer.samples[pick].clone(),
+                    gain: gain * velocity as f32 / 127.0,
+                    pitch_ratio,
+                });
+                self.voices.push(Voice { id: self.next_voice_id, key, released: false });
+                self.next_voice_id += 1;
+            }
+        }
+
+        // Steal released voices first, then the oldest held ones
+        let mut stolen = Vec::new();
+        while self.voices.len() > self.instrument.max_voices.max(1) {
+            let index = self.voices.iter().position(|v| v.released).unwrap_or(0);
+            stolen.push(self.voices.remove(index).id);
+        }
+        (starts, stolen)
+    }
+
+    /// Release the voices playing `key`
+    pub fn note_off(&mut self, key: u8) {
+        for voice in self.voices.iter_mut().filter(|v| v.key == key) {
+            voice.released = true;
+        }
+    }
+
+    /// Forget a voice whose sample finished playing
+    pub fn voice_finished(&mut self, voice_id: u64) {
+        self.voices.retain(|v| v.id != voice_id);
+    }
+
+    pub fn active_voices(&self) -> usize {
+        self.voices.len()
+    }
+}
+
+/// Random index below `count` other than `last`, when there's a choice
+fn pick_round_robin(rng: &mut u64, count: usize, last: Option<usize>) -> usize {
+    // xorshift64, so alternation is repeatable from a fresh sampler
+    *rng ^= *rng << 13;
+    *rng ^= *rng >> 7;
+    *rng ^= *rng << 17;
+    match last {
+        Some(last) if count > 1 && last < count => {
+            let pick = (*rng % (count as u64 - 1)) as usize;
+            if pick >= last { pick + 1 } else { pick }
+        }
+        _ => (*rng % count as u64) as usize,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::test_support::temp_dir;
+
+    fn layer(velocities: (u8, u8), samples: &[&str]) -> VelocityLayer {
+        VelocityLayer {
+            velocities,
+            samples: samples.iter().map(PathBuf::from).collect(),
+            gain_db: 0.0,
+        }
+    }
+
+    fn footsteps() -> SamplerInstrument {
+        let mut instrument = SamplerInstrument::new("Footsteps");
+        instrument.zones.push(KeyZone {
+            keys: (36, 48),
+            root_key: 36,
+            layers: vec![layer((1, 63), &["soft.wav"]), layer((64, 127), &["hard1.wav", "hard2.wav", "hard3.wav"])],
+        });
+        instrument
+    }
+
+    #[test]
+    fn test_velocity_picks_layer() {
+        let mut sampler = Sampler::new(footsteps());
+        let (soft, _) = sampler.note_on(36, 30);
+        assert_eq!(soft.len(), 1);
+        assert_eq!(soft[0].sample, PathBuf::from("soft.wav"));
+        assert_eq!(soft[0].pitch_ratio, 1.0);
+
+        let (hard, _) = sampler.note_on(48, 120);
+        assert_eq!(hard.len(), 1);
+        assert!(hard[0].sample.to_string_lossy().starts_with("hard"));
+        assert!((hard[0].pitch_ratio - 2.0).abs() < 1e-5);
+
+        // Outside every zone nothing plays
+        assert!(sampler.note_on(60, 100).0.is_empty());
+    }
+
+    #[test]
+    fn test_layers_crossfade_with_equal_power() {
+        let mut instrument = footsteps();
+        instrument.layer_crossfade = 20;
+        let zone = instrument.zones[0].clone();
+
+        assert_eq!(instrument.layer_gains(&zone, 20), vec![(0, 1.0)]);
+        assert_eq!(instrument.layer_gains(&zone, 110), vec![(1, 1.0)]);
+        // Both layers at the boundary, with constant power
+        let blend = instrument.layer_gains(&zone, 64);
+        assert_eq!(blend.len(), 2);
+        let power: f32 = blend.iter().map(|(_, g)| g * g).sum();
+        assert!((power - 1.0).abs() < 1e-5);
+        // The ends of the velocity range never fade out
+        assert_eq!(instrument.layer_gains(&zone, 1), vec![(0, 1.0)]);
+        assert_eq!(instrument.layer_gains(&zone, 127), vec![(1, 1.0)]);
+    }
+
+    #[test]
+    fn test_round_robin_never_repeats() {
+        let mut sampler = Sampler::new(footsteps());
+        let mut last = PathBuf::new();
+        let mut seen = std::collections::HashSet::new();
+        for _ in 0..50 {
+            let (starts, _) = sampler.note_on(40, 100);
+            let sample = starts[0].sample.clone();
+            assert_ne!(sample, last);
+            seen.insert(sample.clone());
+            last = sample;
+            sampler.voice_finished(starts[0].voice_id);
+        }
+        assert_eq!(seen.len(), 3);
+    }
+
+    #[test]
+    fn test_voice_stealing_prefers_released_voices() {
+        let mut instrument = footsteps();
+        instrument.max_voices = 2;
+        let mut sampler = Sampler::new(instrument);
+        let (first, _) = sampler.note_on(36, 100);
+        let (second, _) = sampler.note_on(37, 100);
+        sampler.note_off(37);
+
+        let (_, stolen) = sampler.note_on(38, 100);
+        assert_eq!(stolen, vec![second[0].voice_id]);
+        let (_, stolen) = sampler.note_on(39, 100);
+        assert_eq!(stolen, vec![first[0].voice_id]);
+        assert_eq!(sampler.active_voices(), 2);
+    }
+
+    #[test]
+    fn test_instrument_json_round_trip() {
+        let dir = temp_dir();
+        let path = dir.join("footsteps.json");
+        let mut instrument = footsteps();
+        instrument.layer_crossfade = 12;
+        instrument.save(&path).unwrap();
+        assert_eq!(SamplerInstrument::load(&path).unwrap(), instrument);
+        std::fs::remove_dir_all(&dir).ok();
+
+        let minimal: SamplerInstrument = serde_json::from_str(r#"{"name": "Empty", "zones": []}"#).unwrap();
+        assert_eq!(minimal.max_voices, DEFAULT_MAX_VOICES);
+    }
+}
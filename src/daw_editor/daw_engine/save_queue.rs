@@ -0,0 +1,173 @@
+/// Background project saving
+/// Projects are serialized and written on a worker thread so large saves don't
+/// stall the UI or audio. Requests made while a write is in flight are
+/// coalesced: only the newest snapshot is written next, and every caller
+/// waiting on a superseded snapshot receives the result of that newer write.
+use super::project::DawProject;
+use futures::channel::oneshot;
+use parking_lot::Mutex;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// Outcome of a save: the project revision that reached disk, or an error message
+pub type SaveResult = Result<u64, String>;
+
+struct PendingSave {
+    path: PathBuf,
+    project: DawProject,
+    revision: u64,
+    waiters: Vec<oneshot::Sender<SaveResult>>,
+}
+
+#[derive(Default)]
+struct Queue {
+    pending: Option<PendingSave>,
+    worker_running: bool,
+}
+
+/// Coalescing background writer for project files
+#[derive(Clone, Default)]
+pub struct SaveQueue {
+    queue: Arc<Mutex<Queue>>,
+}
+
+impl SaveQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queue a snapshot for writing. The receiver resolves once a snapshot at
+    /// least this new has been written, or the write failed.
+    pub fn request(&self, path: PathBuf, project: DawProject, revision: u64) -> oneshot::Receiver<SaveResult> {
+        let (tx, rx) = oneshot::channel();
+        let mut queue = self.queue.lock();
+
+        let mut waiters = queue.pending.take().map(|p| p.waiters).unwrap_or_default();
+        waiters.push(tx);
+        queue.pending = Some(PendingSave {
+            path,
+            project,
+            revision,
+            waiters,
+        });
+
+        if !queue.worker_running {
+            let worker_queue = self.queue.clone();
+            let spawned = std::thread::Builder::new()
+                .name("daw-project-save".into())
+                .spawn(move || Self::run(worker_queue));
+
+            match spawned {
+                Ok(_) => queue.worker_running = true,
+                Err(e) => {
+                    tracing::error!("❌ Failed to start save thread: {}", e);
+                    if let Some(pending) = queue.pending.take() {
+                        for waiter in pending.waiters {
+                            let _ = waiter.send(Err(format!("Failed to start save thread: {}", e)));
+                        }
+                    }
+                }
+            }
+        }
+
+        rx
+    }
+
+    fn run(queue: Arc<Mutex<Queue>>) {
+        loop {
+            let next = {
+                let mut queue = queue.lock();
+                match queue.pending.take() {
+                    Some(next) => next,
+                    None => {
+                        queue.worker_running = false;
+                        return;
+                    }
+                }
+            };
+
+            let result = next
+                .project
+                .save(&next.path)
+                .map(|_| next.revision)
+                .map_err(|e| format!("{:#}", e));
+
+            for waiter in next.waiters {
+                let _ = waiter.send(result.clone());
+            }
+        }
+    }
+}
+
+/// Tracks whether the project has changes that aren't on disk yet. Shared
+/// with the editor host, which asks for the dirty state without UI access.
+#[derive(Debug, Default)]
+pub struct DirtyTracker {
+    revision: AtomicU64,
+    saved_revision: AtomicU64,
+}
+
+impl DirtyTracker {
+    /// Record an edit. Returns the new revision.
+    pub fn mark_dirty(&self) -> u64 {
+        self.revision.fetch_add(1, Ordering::SeqCst) + 1
+    }
+
+    pub fn revision(&self) -> u64 {
+        self.revision.load(Ordering::SeqCst)
+    }
+
+    /// Record that `revision` reached disk. Edits made after the snapshot was
+    /// taken keep the project dirty.
+    pub fn mark_saved(&self, revision: u64) {
+        self.saved_revision.fetch_max(revision, Ordering::SeqCst);
+    }
+
+    /// Treat the current state as saved, e.g. after loading a project
+    pub fn mark_clean(&self) {
+        self.saved_revision.store(self.revision(), Ordering::SeqCst);
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        self.saved_revision.load(Ordering::SeqCst) < self.revision()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::test_support::temp_dir;
+
+    #[test]
+    fn test_coalesced_saves_write_latest_snapshot() {
+        let dir = temp_dir();
+        let path = dir.join("song.pdaw");
+        let queue = SaveQueue::new();
+
+        let receivers: Vec<_> = (1..=5)
+            .map(|revision| {
+                let project = DawProject::new(format!("Revision {}", revision));
+                queue.request(path.clone(), project, revision)
+            })
+            .collect();
+
+        for rx in receivers {
+            let saved = futures::executor::block_on(rx).unwrap().unwrap();
+            assert!(saved >= 1);
+        }
+
+        let loaded = DawProject::load(&path).unwrap();
+        assert_eq!(loaded.name, "Revision 5");
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_failed_save_reports_the_error() {
+        let dir = temp_dir();
+        let queue = SaveQueue::new();
+        let rx = queue.request(dir.join("missing").join("song.pdaw"), DawProject::new("Test"), 1);
+        assert!(futures::executor::block_on(rx).unwrap().is_err());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
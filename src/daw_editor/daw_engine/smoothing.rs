@@ -0,0 +1,129 @@
+/// Anti-click gain ramps for mixer changes
+/// Mute, solo, fader and effect bypass changes reach the audio thread as
+/// jumps. Applied as is, a jump in the middle of a waveform is a step in the
+/// output and clicks, so the mix path moves each gain to its new value over
+/// a short linear ramp instead. A ramp of zero samples keeps changes instant.
+
+/// Ramp time used until the preferences say otherwise, in milliseconds
+pub const DEFAULT_SMOOTHING_MS: f32 = 10.0;
+
+/// Longest ramp offered, in milliseconds
+pub const MAX_SMOOTHING_MS: f32 = 100.0;
+
+/// A gain that follows its target in a straight line over a set number of
+/// samples, landing on it exactly
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GainRamp {
+    current: f32,
+    target: f32,
+    step: f32,
+    remaining: usize,
+}
+
+impl GainRamp {
+    /// A ramp resting at `value`
+    pub fn new(value: f32) -> Self {
+        Self { current: value, target: value, step: 0.0, remaining: 0 }
+    }
+
+    /// Head for `target` over `samples` samples, from wherever the ramp is
+    /// now. Zero samples jumps straight there.
+    pub fn set_target(&mut self, target: f32, samples: usize) {
+        if target == self.target {
+            return;
+        }
+        self.target = target;
+        if samples == 0 {
+            self.current = target;
+            self.remaining = 0;
+        } else {
+            self.step = (target - self.current) / samples as f32;
+            self.remaining = samples;
+        }
+    }
+
+    /// The gain for the next sample
+    pub fn next_gain(&mut self) -> f32 {
+        if self.remaining > 0 {
+            self.remaining -= 1;
+            self.current = if self.remaining == 0 { self.target } else { self.current + self.step };
+        }
+        self.current
+    }
+
+    /// The gain the ramp is at, without advancing it
+    pub fn value(&self) -> f32 {
+        self.current
+    }
+
+    pub fn is_settled(&self) -> bool {
+        self.remaining == 0
+    }
+
+    /// Whether the ramp rests at silence, so the signal it scales can be
+    /// skipped entirely
+    pub fn is_silent(&self) -> bool {
+        self.is_settled() && self.current == 0.0
+    }
+
+    /// Scale a stereo block sample by sample
+    pub fn apply(&mut self, left: &mut [f32], right: &mut [f32]) {
+        if self.is_settled() {
+            if self.current != 1.0 {
+                let gain = self.current;
+                left.iter_mut().chain(right.iter_mut()).for_each(|s| *s *= gain);
+            }
+            return;
+        }
+        for (l, r) in left.iter_mut().zip(right.iter_mut()) {
+            let gain = self.next_gain();
+            *l *= gain;
+            *r *= gain;
+        }
+    }
+}
+
+/// Ramp length for a smoothing time at the given rate
+pub fn smoothing_samples(ms: f32, sample_rate: f32) -> usize {
+    (ms.clamp(0.0, MAX_SMOOTHING_MS) as f64 * sample_rate as f64 / 1000.0).round() as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ramp_reaches_its_target_exactly() {
+        let mut ramp = GainRamp::new(1.0);
+        ramp.set_target(0.0, 4);
+        let gains: Vec<f32> = (0..6).map(|_| ramp.next_gain()).collect();
+        assert_eq!(gains, vec![0.75, 0.5, 0.25, 0.0, 0.0, 0.0]);
+        assert!(ramp.is_silent());
+
+        // Turning round mid-ramp starts from where it got to
+        ramp.set_target(1.0, 4);
+        ramp.next_gain();
+        ramp.set_target(0.0, 1);
+        assert_eq!(ramp.next_gain(), 0.0);
+
+        // No smoothing is sample-accurate
+        ramp.set_target(0.8, 0);
+        assert_eq!(ramp.next_gain(), 0.8);
+        assert!(ramp.is_settled());
+        assert_eq!(smoothing_samples(DEFAULT_SMOOTHING_MS, 48000.0), 480);
+        assert_eq!(smoothing_samples(-5.0, 48000.0), 0);
+    }
+
+    #[test]
+    fn test_apply_ramps_across_blocks() {
+        let mut ramp = GainRamp::new(0.0);
+        ramp.set_target(1.0, 6);
+        let (mut left, mut right) = (vec![1.0; 4], vec![2.0; 4]);
+        ramp.apply(&mut left, &mut right);
+        assert!((left[3] - 4.0 / 6.0).abs() < 1e-6 && (right[0] - 2.0 / 6.0).abs() < 1e-6);
+        let (mut left, mut right) = (vec![1.0; 4], vec![1.0; 4]);
+        ramp.apply(&mut left, &mut right);
+        assert!((left[0] - 5.0 / 6.0).abs() < 1e-6);
+        assert_eq!(&left[1..], &[1.0, 1.0, 1.0]);
+    }
+}
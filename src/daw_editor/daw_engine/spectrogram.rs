@@ -0,0 +1,322 @@
+/// Spectrogram of a clip's audio for the clip editor
+/// A short-time Fourier transform over a clip's source file (or part of it),
+/// mixed to mono, with magnitudes in dB on a logarithmic or linear frequency
+/// axis. It is meant to be built off the UI thread and drawn as a grid of cells.
+use super::audio_types::{AudioAssetData, SampleTime};
+
+/// How rows are spread over the frequency axis
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FrequencyScale {
+    /// Every octave the same height, from `min_freq` up
+    #[default]
+    Log,
+    /// Every row the same width in Hz, from 0 Hz up, so the top octave takes
+    /// half the height
+    Linear,
+}
+
+impl FrequencyScale {
+    pub fn label(self) -> &'static str {
+        match self {
+            FrequencyScale::Log => "Log",
+            FrequencyScale::Linear => "Linear",
+        }
+    }
+
+    /// Frequency `position` (0 to 1) of the way up an axis
+    fn frequency(self, position: f32, min_freq: f32, max_freq: f32) -> f32 {
+        match self {
+            FrequencyScale::Log => min_freq * (max_freq / min_freq).powf(position),
+            FrequencyScale::Linear => min_freq + (max_freq - min_freq) * position,
+        }
+    }
+}
+
+/// STFT parameters and the ranges the result is mapped onto
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpectrogramSettings {
+    /// Window length in frames, rounded up to a power of two
+    pub fft_size: usize,
+    /// Upper bound on analysis frames; the hop grows for long clips
+    pub max_columns: usize,
+    /// Rows of the frequency axis
+    pub rows: usize,
+    /// Bottom of a log axis
+    pub min_freq: f32,
+    pub scale: FrequencyScale,
+    /// Floor of the color map, quieter bins draw as black
+    pub min_db: f32,
+}
+
+impl Default for SpectrogramSettings {
+    fn default() -> Self {
+        Self { fft_size: 2048, max_columns: 1024, rows: 128, min_freq: 20.0, scale: FrequencyScale::Log, min_db: -96.0 }
+    }
+}
+
+/// Magnitudes in dB, one column per analysis frame, rows from the lowest
+/// frequency up
+#[derive(Debug, Clone)]
+pub struct Spectrogram {
+    pub columns: usize,
+    pub rows: usize,
+    /// First source frame analysed, how many were and frames between columns
+    pub start: SampleTime,
+    pub frames: SampleTime,
+    pub hop: usize,
+    pub sample_rate: f32,
+    pub min_freq: f32,
+    pub max_freq: f32,
+    pub scale: FrequencyScale,
+    pub min_db: f32,
+    /// `columns * rows` values, column after column
+    data: Vec<f32>,
+}
+
+impl Spectrogram {
+    /// Analyse `len` frames of the asset from frame `start`
+    pub fn from_asset(asset: &AudioAssetData, start: SampleTime, len: SampleTime, settings: &SpectrogramSettings) -> Self {
+        let channels = asset.asset_ref.channels.max(1);
+        let total = asset.asset_ref.duration_samples as SampleTime;
+        let start = start.min(total);
+        let end = start.saturating_add(len).min(total);
+        let mono: Vec<f32> = (start..end)
+            .map(|frame| {
+                let index = frame as usize * channels;
+                let frame = asset.samples.get(index..index + channels).unwrap_or(&[]);
+                frame.iter().sum::<f32>() / channels as f32
+            })
+            .collect();
+        let mut spectrogram = Self::from_samples(&mono, asset.asset_ref.sample_rate, settings);
+        spectrogram.start = start;
+        spectrogram
+    }
+
+    pub fn from_samples(samples: &[f32], sample_rate: f32, settings: &SpectrogramSettings) -> Self {
+        let fft_size = settings.fft_size.max(16).next_power_of_two();
+        let max_columns = settings.max_columns.max(1);
+        let hop = samples.len().div_ceil(max_columns).max(fft_size / 4).max(1);
+        let columns = samples.len().div_ceil(hop).max(1);
+        let rows = settings.rows.max(1);
+        let max_freq = sample_rate / 2.0;
+        let scale = settings.scale;
+        let min_freq = match scale {
+            FrequencyScale::Log => settings.min_freq.clamp(1.0, max_freq / 2.0),
+            FrequencyScale::Linear => 0.0,
+        };
+
+        // Bin span of each row on the frequency axis
+        let bin_hz = sample_rate / fft_size as f32;
+        let row_bins: Vec<(usize, usize)> = (0..rows)
+            .map(|row| {
+                let low = scale.frequency(row as f32 / rows as f32, min_freq, max_freq);
+                let high = scale.frequency((row + 1) as f32 / rows as f32, min_freq, max_freq);
+                let first = ((low / bin_hz).floor() as usize).min(fft_size / 2);
+                let last = ((high / bin_hz).ceil() as usize).clamp(first + 1, fft_size / 2 + 1);
+                (first, last)
+            })
+            .collect();
+
+        let window: Vec<f32> = (0..fft_size)
+            .map(|i| 0.5 - 0.5 * (2.0 * std::f32::consts::PI * i as f32 / fft_size as f32).cos())
+            .collect();
+        // A full-scale sine peaks at a quarter of the window length with a Hann window
+        let reference = fft_size as f32 / 4.0;
+
+        let mut data = Vec::with_capacity(columns * rows);
+        let mut re = vec![0.0; fft_size];
+        let mut im = vec![0.0; fft_size];
+        for column in 0..columns {
+            // Windows are centred on their column
+            let centre = column * hop + hop / 2;
+            for i in 0..fft_size {
+                let index = (centre + i).checked_sub(fft_size / 2);
+                re[i] = index.and_then(|index| samples.get(index)).copied().unwrap_or(0.0) * window[i];
+                im[i] = 0.0;
+            }
+            fft(&mut re, &mut im);
+
+            for &(first, last) in &row_bins {
+                let magnitude = (first..last)
+                    .map(|bin| (re[bin] * re[bin] + im[bin] * im[bin]).sqrt())
+                    .fold(0.0_f32, f32::max);
+                let db = 20.0 * (magnitude / reference).max(1e-10).log10();
+                data.push(db.max(settings.min_db));
+            }
+        }
+
+        Self {
+            columns,
+            rows,
+            start: 0,
+            frames: samples.len() as SampleTime,
+            hop,
+            sample_rate,
+            min_freq,
+            max_freq,
+            scale,
+            min_db: settings.min_db,
+            data,
+        }
+    }
+
+    pub fn db_at(&self, column: usize, row: usize) -> f32 {
+        self.data.get(column * self.rows + row).copied().unwrap_or(self.min_db)
+    }
+
+    /// Centre frequency of a row in Hz
+    pub fn row_frequency(&self, row: usize) -> f32 {
+        self.scale.frequency((row as f32 + 0.5) / self.rows as f32, self.min_freq, self.max_freq)
+    }
+
+    /// Analysis column covering a source frame
+    pub fn column_at(&self, frame: SampleTime) -> usize {
+        ((frame.saturating_sub(self.start) as usize) / self.hop).min(self.columns - 1)
+    }
+
+    /// Loudest value of each cell when source frames `start..end` are drawn
+    /// as a `columns` by `rows` grid, rows from the top (highest frequency)
+    /// down. Zoomed in past one analysis column per cell, columns repeat.
+    pub fn grid(&self, start: SampleTime, end: SampleTime, columns: usize, rows: usize) -> Vec<Vec<f32>> {
+        let columns = columns.max(1);
+        let rows = rows.clamp(1, self.rows);
+        let span = end.saturating_sub(start).max(1) as f64;
+        let column_range: Vec<(usize, usize)> = (0..columns)
+            .map(|cell| {
+                let from = start + (span * cell as f64 / columns as f64) as SampleTime;
+                let to = start + (span * (cell + 1) as f64 / columns as f64) as SampleTime;
+                let first = self.column_at(from);
+                (first, self.column_at(to.saturating_sub(1)).max(first) + 1)
+            })
+            .collect();
+        (0..rows)
+            .map(|cell_row| {
+                let top = self.rows - cell_row * self.rows / rows;
+                let bottom = self.rows - (cell_row + 1) * self.rows / rows;
+                column_range
+                    .iter()
+                    .map(|&(first, last)| {
+                        (first..last)
+                            .flat_map(|column| (bottom..top).map(move |row| (column, row)))
+                            .map(|(column, row)| self.db_at(column, row))
+                            .fold(self.min_db, f32::max)
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+/// Heat map color for a level in dB: black through purple, red and orange
+/// to pale yellow at 0 dB. Returns RGB in 0.0..=1.0.
+pub fn heat_color(db: f32, min_db: f32) -> [f32; 3] {
+    const STOPS: [[f32; 3]; 5] = [
+        [0.0, 0.0, 0.0],
+        [0.3, 0.05, 0.45],
+        [0.8, 0.15, 0.3],
+        [1.0, 0.6, 0.1],
+        [1.0, 1.0, 0.75],
+    ];
+    let t = ((db - min_db) / -min_db.min(-1.0)).clamp(0.0, 1.0) * (STOPS.len() - 1) as f32;
+    let index = (t.floor() as usize).min(STOPS.len() - 2);
+    let fraction = t - index as f32;
+    let (a, b) = (STOPS[index], STOPS[index + 1]);
+    [0, 1, 2].map(|i| a[i] + (b[i] - a[i]) * fraction)
+}
+
+/// In-place radix-2 FFT. The length must be a power of two.
+pub(crate) fn fft(re: &mut [f32], im: &mut [f32]) {
+    let n = re.len();
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+
+    let mut len = 2;
+    while len <= n {
+        let angle = -2.0 * std::f32::consts::PI / len as f32;
+        let (step_im, step_re) = angle.sin_cos();
+        for start in (0..n).step_by(len) {
+            let (mut w_re, mut w_im) = (1.0_f32, 0.0_f32);
+            for k in 0..len / 2 {
+                let (a, b) = (start + k, start + k + len / 2);
+                let t_re = re[b] * w_re - im[b] * w_im;
+                let t_im = re[b] * w_im + im[b] * w_re;
+                re[b] = re[a] - t_re;
+                im[b] = im[a] - t_im;
+                re[a] += t_re;
+                im[a] += t_im;
+                (w_re, w_im) = (w_re * step_re - w_im * step_im, w_re * step_im + w_im * step_re);
+            }
+        }
+        len <<= 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fft_of_a_cosine() {
+        let n = 64;
+        let mut re: Vec<f32> = (0..n).map(|i| (2.0 * std::f32::consts::PI * 4.0 * i as f32 / n as f32).cos()).collect();
+        let mut im = vec![0.0; n];
+        fft(&mut re, &mut im);
+        // All the energy lands in bins 4 and n - 4
+        assert!((re[4] - n as f32 / 2.0).abs() < 1e-3);
+        assert!((re[n - 4] - n as f32 / 2.0).abs() < 1e-3);
+        assert!(re[5].abs() < 1e-3 && im[4].abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_sine_shows_at_its_frequency() {
+        let sample_rate = 48000.0;
+        let samples: Vec<f32> = (0..48000)
+            .map(|i| 0.5 * (2.0 * std::f32::consts::PI * 1000.0 * i as f32 / sample_rate).sin())
+            .collect();
+        let spectrogram = Spectrogram::from_samples(&samples, sample_rate, &SpectrogramSettings::default());
+        assert!(spectrogram.columns <= 1024);
+
+        let column = spectrogram.columns / 2;
+        let loudest = (0..spectrogram.rows)
+            .max_by(|&a, &b| spectrogram.db_at(column, a).total_cmp(&spectrogram.db_at(column, b)))
+            .unwrap();
+        let freq = spectrogram.row_frequency(loudest);
+        assert!((900.0..1100.0).contains(&freq), "peak at {} Hz", freq);
+        // -6 dBFS sine reads about -6 dB
+        assert!((spectrogram.db_at(column, loudest) + 6.0).abs() < 1.5);
+        assert!(spectrogram.db_at(column, 5) < -60.0);
+
+        let grid = spectrogram.grid(0, 48000, 40, 16);
+        assert_eq!((grid.len(), grid[0].len()), (16, 40));
+        // Zoomed in to a few frames, every cell shows the same column
+        let zoomed = spectrogram.grid(24000, 24010, 4, 16);
+        assert!(zoomed.iter().all(|row| row.iter().all(|&db| db == row[0])));
+        assert_eq!(spectrogram.column_at(1_000_000), spectrogram.columns - 1);
+
+        // On a linear axis from 0 Hz, 1 kHz is a twenty-fourth of the way up
+        let settings = SpectrogramSettings { scale: FrequencyScale::Linear, ..SpectrogramSettings::default() };
+        let linear = Spectrogram::from_samples(&samples, sample_rate, &settings);
+        let loudest = (0..linear.rows)
+            .max_by(|&a, &b| linear.db_at(column, a).total_cmp(&linear.db_at(column, b)))
+            .unwrap();
+        assert_eq!(loudest, 5);
+        assert!((linear.row_frequency(loudest) - 1031.25).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_heat_color_range() {
+        assert_eq!(heat_color(-200.0, -96.0), [0.0, 0.0, 0.0]);
+        assert_eq!(heat_color(0.0, -96.0), [1.0, 1.0, 0.75]);
+    }
+}
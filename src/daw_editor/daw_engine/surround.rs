@@ -0,0 +1,226 @@
+/// Surround channel layouts and panning
+/// The mix is always built in stereo. Projects with a surround layout also
+/// pan every track and bus routed to the main output into a bed with one
+/// channel per speaker, which multichannel renders and output devices with
+/// enough channels get. Stereo projects never build the bed.
+use serde::{Deserialize, Serialize};
+use std::f32::consts::{FRAC_1_SQRT_2, FRAC_PI_2};
+
+/// Channels of the largest layout, so gains fit in a fixed array
+pub const MAX_SURROUND_CHANNELS: usize = 6;
+
+/// Speaker layout of the master bus
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ChannelLayout {
+    #[default]
+    Stereo,
+    Quad,
+    Surround51,
+}
+
+/// One output channel of a layout
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Speaker {
+    pub name: &'static str,
+    /// Degrees clockwise from front centre, None for the LFE
+    pub azimuth: Option<f32>,
+}
+
+const fn speaker(name: &'static str, azimuth: f32) -> Speaker {
+    Speaker { name, azimuth: Some(azimuth) }
+}
+
+// Speakers in WAV channel order
+const STEREO: [Speaker; 2] = [speaker("L", -30.0), speaker("R", 30.0)];
+const QUAD: [Speaker; 4] = [speaker("L", -45.0), speaker("R", 45.0), speaker("Ls", -135.0), speaker("Rs", 135.0)];
+const SURROUND_51: [Speaker; 6] = [
+    speaker("L", -30.0),
+    speaker("R", 30.0),
+    speaker("C", 0.0),
+    Speaker { name: "LFE", azimuth: None },
+    speaker("Ls", -110.0),
+    speaker("Rs", 110.0),
+];
+
+impl ChannelLayout {
+    pub const ALL: [ChannelLayout; 3] = [ChannelLayout::Stereo, ChannelLayout::Quad, ChannelLayout::Surround51];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ChannelLayout::Stereo => "Stereo",
+            ChannelLayout::Quad => "Quad",
+            ChannelLayout::Surround51 => "5.1",
+        }
+    }
+
+    pub fn speakers(&self) -> &'static [Speaker] {
+        match self {
+            ChannelLayout::Stereo => &STEREO,
+            ChannelLayout::Quad => &QUAD,
+            ChannelLayout::Surround51 => &SURROUND_51,
+        }
+    }
+
+    pub fn channel_count(&self) -> usize {
+        self.speakers().len()
+    }
+
+    pub fn is_surround(&self) -> bool {
+        *self != ChannelLayout::Stereo
+    }
+
+    /// `dwChannelMask` of a WAVE_FORMAT_EXTENSIBLE file in this layout
+    pub fn channel_mask(&self) -> u32 {
+        // FL, FR, FC, LFE, BL, BR
+        match self {
+            ChannelLayout::Stereo => 0x3,
+            ChannelLayout::Quad => 0x1 | 0x2 | 0x10 | 0x20,
+            ChannelLayout::Surround51 => 0x3F,
+        }
+    }
+}
+
+/// Where a track sits in the surround field
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SurroundPan {
+    /// -1.0 is left, 1.0 right
+    pub x: f32,
+    /// -1.0 is the back, 1.0 the front
+    pub y: f32,
+    /// Level sent to the LFE channel, 0.0 to 1.0
+    #[serde(default)]
+    pub lfe: f32,
+}
+
+impl Default for SurroundPan {
+    /// Front centre, where a stereo mix puts a centred track
+    fn default() -> Self {
+        Self { x: 0.0, y: 1.0, lfe: 0.0 }
+    }
+}
+
+impl SurroundPan {
+    pub fn clamped(self) -> Self {
+        Self { x: self.x.clamp(-1.0, 1.0), y: self.y.clamp(-1.0, 1.0), lfe: self.lfe.clamp(0.0, 1.0) }
+    }
+}
+
+/// Gain of every speaker in `layout` for a source at `pan`, in channel
+/// order. At the edge of the field the source is panned between the two
+/// speakers either side of it with constant power; towards the middle it
+/// spreads evenly over all of them. The LFE only gets the pan's LFE send.
+pub fn surround_gains(layout: ChannelLayout, pan: SurroundPan) -> [f32; MAX_SURROUND_CHANNELS] {
+    let pan = pan.clamped();
+    let speakers = layout.speakers();
+    let mut gains = [0.0; MAX_SURROUND_CHANNELS];
+
+    // Full-range speakers by azimuth, 0..360 clockwise from the front. This
+    // runs on the audio thread, so nothing is allocated.
+    let mut ring = [(0, 0.0); MAX_SURROUND_CHANNELS];
+    let mut count = 0;
+    for (channel, azimuth) in speakers.iter().enumerate().filter_map(|(channel, s)| Some((channel, s.azimuth?))) {
+        ring[count] = (channel, azimuth.rem_euclid(360.0));
+        count += 1;
+    }
+    let ring = &mut ring[..count];
+    ring.sort_unstable_by(|a, b| a.1.total_cmp(&b.1));
+    if ring.is_empty() {
+        return gains;
+    }
+
+    let radius = pan.x.hypot(pan.y).min(1.0);
+    let azimuth = pan.x.atan2(pan.y).to_degrees().rem_euclid(360.0);
+    // The pair either side of the source, wrapping from the last speaker
+    // round to the first
+    let next = ring.iter().position(|&(_, a)| a > azimuth).unwrap_or(0);
+    let (from, to) = (ring[(next + ring.len() - 1) % ring.len()], ring[next]);
+    let span = (to.1 - from.1).rem_euclid(360.0);
+    let t = if span > 0.0 { (azimuth - from.1).rem_euclid(360.0) / span } else { 0.0 };
+
+    let spread = 1.0 / (ring.len() as f32).sqrt();
+    for &(channel, _) in ring.iter() {
+        gains[channel] = (1.0 - radius) * spread;
+    }
+    gains[from.0] += radius * (t * FRAC_PI_2).cos();
+    gains[to.0] += radius * (t * FRAC_PI_2).sin();
+
+    // Keep the total power of a fully directional source
+    let power: f32 = ring.iter().map(|&(channel, _)| gains[channel] * gains[channel]).sum();
+    if power > 0.0 {
+        let scale = 1.0 / power.sqrt();
+        for &(channel, _) in ring.iter() {
+            gains[channel] *= scale;
+        }
+    }
+
+    if let Some(lfe) = speakers.iter().position(|s| s.azimuth.is_none()) {
+        gains[lfe] = pan.lfe;
+    }
+    gains
+}
+
+/// Add a stereo signal to a surround bed at `gains`. Both sides are folded
+/// to mono with equal power first.
+pub fn pan_into_bed(left: &[f32], right: &[f32], volume: f32, gains: &[f32; MAX_SURROUND_CHANNELS], bed: &mut [Vec<f32>]) {
+    for (channel, &gain) in bed.iter_mut().zip(gains) {
+        let gain = gain * volume * FRAC_1_SQRT_2;
+        if gain == 0.0 {
+            continue;
+        }
+        for ((out, l), r) in channel.iter_mut().zip(left).zip(right) {
+            *out += (l + r) * gain;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn power(gains: &[f32]) -> f32 {
+        gains.iter().map(|g| g * g).sum()
+    }
+
+    #[test]
+    fn test_layouts() {
+        assert_eq!(ChannelLayout::default(), ChannelLayout::Stereo);
+        assert_eq!(ChannelLayout::Quad.channel_count(), 4);
+        assert_eq!(ChannelLayout::Surround51.channel_count(), 6);
+        assert!(ChannelLayout::ALL.iter().all(|l| l.channel_count() <= MAX_SURROUND_CHANNELS));
+        assert_eq!(ChannelLayout::Surround51.channel_mask().count_ones(), 6);
+    }
+
+    #[test]
+    fn test_pan_positions() {
+        // Front centre of 5.1 is the centre speaker on its own
+        let gains = surround_gains(ChannelLayout::Surround51, SurroundPan::default());
+        assert!((gains[2] - 1.0).abs() < 1e-5, "{:?}", gains);
+        assert_eq!(gains[3], 0.0);
+
+        // Hard back left of quad is the left surround
+        let gains = surround_gains(ChannelLayout::Quad, SurroundPan { x: -FRAC_1_SQRT_2, y: -FRAC_1_SQRT_2, lfe: 0.0 });
+        assert!(gains[2] > 0.99, "{:?}", gains);
+
+        // Halfway between two speakers splits with constant power
+        let gains = surround_gains(ChannelLayout::Quad, SurroundPan { x: 0.0, y: 1.0, lfe: 0.0 });
+        assert!((gains[0] - gains[1]).abs() < 1e-5 && (power(&gains) - 1.0).abs() < 1e-4);
+
+        // In the middle every full-range speaker plays, the LFE only gets its send
+        let gains = surround_gains(ChannelLayout::Surround51, SurroundPan { x: 0.0, y: 0.0, lfe: 0.5 });
+        assert!(gains.iter().enumerate().all(|(i, &g)| i == 3 || (g - gains[0]).abs() < 1e-5));
+        assert_eq!(gains[3], 0.5);
+        assert!((power(&[gains[0], gains[1], gains[2], gains[4], gains[5]]) - 1.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_pan_into_bed() {
+        let mut bed = vec![vec![0.0; 4]; 4];
+        let gains = surround_gains(ChannelLayout::Quad, SurroundPan { x: FRAC_1_SQRT_2, y: FRAC_1_SQRT_2, lfe: 0.0 });
+        // A centred mono source, as the stereo mix carries it, comes out at
+        // its own level
+        let side = [0.5 * FRAC_1_SQRT_2; 4];
+        pan_into_bed(&side, &side, 1.0, &gains, &mut bed);
+        assert!((bed[1][0] - 0.5).abs() < 1e-3, "{:?}", bed);
+        assert!(bed[0][0].abs() < 1e-3 && bed[2][0].abs() < 1e-3);
+    }
+}
@@ -0,0 +1,250 @@
+/// Input takes
+/// While a take records, the audio thread copies every input frame it plays
+/// against into a lock-free queue and notes the timeline position of the
+/// first one. A writer thread drains the queue into a WAV per armed track,
+/// each from that track's record input, so a long take never sits in memory.
+/// The files are then cut to the take span and placed on the timeline.
+use super::audio_types::{RecordInput, SampleTime, TrackId};
+use super::real_time_audio::InputFrame;
+use anyhow::{Context as _, Result};
+use crossbeam::queue::ArrayQueue;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+/// Frames queued before the writer must have caught up, a second at 48 kHz
+const TAKE_QUEUE_FRAMES: usize = 48_000;
+/// How often the writer drains the queue
+const DRAIN_INTERVAL: Duration = Duration::from_millis(10);
+
+/// The audio thread's side of a take
+pub struct TakeCapture {
+    queue: ArrayQueue<InputFrame>,
+    capturing: AtomicBool,
+    /// Timeline position of the first captured frame, `u64::MAX` before it
+    started_at: AtomicU64,
+    dropped: AtomicU64,
+}
+
+impl Default for TakeCapture {
+    fn default() -> Self {
+        Self {
+            queue: ArrayQueue::new(TAKE_QUEUE_FRAMES),
+            capturing: AtomicBool::new(false),
+            started_at: AtomicU64::new(u64::MAX),
+            dropped: AtomicU64::new(0),
+        }
+    }
+}
+
+impl TakeCapture {
+    pub fn is_capturing(&self) -> bool {
+        self.capturing.load(Ordering::Acquire)
+    }
+
+    /// Note where the frames about to be pushed play on the timeline. Only
+    /// the first call of a take counts, later frames follow on from it.
+    pub fn mark_position(&self, position: SampleTime) {
+        let _ = self.started_at.compare_exchange(u64::MAX, position, Ordering::AcqRel, Ordering::Acquire);
+    }
+
+    /// Queue one frame, counting it as dropped if the writer fell behind
+    pub fn push(&self, frame: InputFrame) {
+        if self.queue.push(frame).is_err() {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    fn start(&self) {
+        while self.queue.pop().is_some() {}
+        self.started_at.store(u64::MAX, Ordering::Release);
+        self.dropped.store(0, Ordering::Relaxed);
+        self.capturing.store(true, Ordering::Release);
+    }
+}
+
+/// Where an armed track's take is written
+#[derive(Debug, Clone)]
+pub struct TakeTarget {
+    pub track_id: TrackId,
+    pub input: RecordInput,
+    pub path: PathBuf,
+}
+
+/// A finished take
+#[derive(Debug, Clone)]
+pub struct RecordedTake {
+    /// Timeline position of the first frame, None if nothing was captured
+    pub captured_from: Option<SampleTime>,
+    pub frames: SampleTime,
+    /// The file written for each armed track
+    pub files: Vec<(TrackId, PathBuf)>,
+}
+
+impl RecordedTake {
+    /// Timeline position just past the last frame
+    pub fn end(&self) -> Option<SampleTime> {
+        self.captured_from.map(|from| from + self.frames)
+    }
+}
+
+type TakeFile = (TakeTarget, hound::WavWriter<std::io::BufWriter<std::fs::File>>);
+
+/// Writes a take while it records
+pub struct TakeWriter {
+    capture: Arc<TakeCapture>,
+    thread: JoinHandle<Result<(Vec<TakeTarget>, SampleTime)>>,
+}
+
+impl TakeWriter {
+    /// Create the take files and start capturing. `targets` is every armed
+    /// track with where its file goes.
+    pub fn start(capture: Arc<TakeCapture>, targets: Vec<TakeTarget>, sample_rate: u32) -> Result<Self> {
+        let mut files: Vec<TakeFile> = Vec::with_capacity(targets.len());
+        for target in targets {
+            files.push(Self::create(target, sample_rate)?);
+        }
+        capture.start();
+
+        let drained = capture.clone();
+        let thread = std::thread::spawn(move || {
+            let mut frames = 0;
+            loop {
+                let done = !drained.is_capturing();
+                while let Some(frame) = drained.queue.pop() {
+                    write_frame(&mut files, &frame)?;
+                    frames += 1;
+                }
+                if done {
+                    break;
+                }
+                std::thread::sleep(DRAIN_INTERVAL);
+            }
+            let mut targets = Vec::with_capacity(files.len());
+            for (target, writer) in files {
+                writer.finalize().with_context(|| format!("Failed to finish {:?}", target.path))?;
+                targets.push(target);
+            }
+            Ok((targets, frames))
+        });
+        Ok(Self { capture, thread })
+    }
+
+    fn create(target: TakeTarget, sample_rate: u32) -> Result<TakeFile> {
+        if let Some(parent) = target.path.parent() {
+            std::fs::create_dir_all(parent).with_context(|| format!("Failed to create {:?}", parent))?;
+        }
+        let spec = hound::WavSpec {
+            channels: target.input.recorded_channels() as u16,
+            sample_rate,
+            bits_per_sample: 32,
+            sample_format: hound::SampleFormat::Float,
+        };
+        let writer = hound::WavWriter::create(&target.path, spec).with_context(|| format!("Failed to create {:?}", target.path))?;
+        Ok((target, writer))
+    }
+
+    /// Stop capturing and close the files
+    pub fn finish(self) -> Result<RecordedTake> {
+        self.capture.capturing.store(false, Ordering::Release);
+        let (targets, frames) = self.thread.join().map_err(|_| anyhow::anyhow!("Take writer panicked"))??;
+        let dropped = self.capture.dropped.load(Ordering::Relaxed);
+        if dropped > 0 {
+            tracing::error!("⚠️ Take writer fell behind, {} frames were dropped", dropped);
+        }
+        let started_at = self.capture.started_at.load(Ordering::Acquire);
+        Ok(RecordedTake {
+            captured_from: (started_at != u64::MAX && frames > 0).then_some(started_at),
+            frames,
+            files: targets.into_iter().map(|target| (target.track_id, target.path)).collect(),
+        })
+    }
+}
+
+fn write_frame(files: &mut [TakeFile], frame: &InputFrame) -> Result<()> {
+    for (target, writer) in files.iter_mut() {
+        let sample = |channel: u16| frame.get(channel as usize).copied().unwrap_or(0.0);
+        match target.input {
+            RecordInput::Mono(channel) => writer.write_sample(sample(channel))?,
+            RecordInput::Stereo(channel) => {
+                writer.write_sample(sample(channel))?;
+                writer.write_sample(sample(channel + 1))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// File for a take on the track named `track_name`, in the project's
+/// recordings folder, named by when it was recorded
+pub fn take_path(project_dir: &Path, track_name: &str, recorded_at: chrono::DateTime<chrono::Local>) -> PathBuf {
+    let name: String = track_name
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == ' ' || c == '-' || c == '_' { c } else { '_' })
+        .collect();
+    project_dir
+        .join("Recordings")
+        .join(format!("{} {}.wav", name.trim(), recorded_at.format("%Y-%m-%d %H-%M-%S")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::daw_editor::daw_engine::audio_types::MAX_INPUT_CHANNELS;
+    use crate::daw_editor::daw_engine::test_support::temp_dir;
+
+    #[test]
+    fn test_take_writes_each_track_from_its_input() {
+        let dir = temp_dir();
+        let capture = Arc::new(TakeCapture::default());
+        let (vocal, guitars) = (TrackId::new_v4(), TrackId::new_v4());
+        let targets = vec![
+            TakeTarget { track_id: vocal, input: RecordInput::Mono(2), path: dir.join("vocal.wav") },
+            TakeTarget { track_id: guitars, input: RecordInput::Stereo(0), path: dir.join("guitars.wav") },
+        ];
+        let writer = TakeWriter::start(capture.clone(), targets, 48_000).unwrap();
+        assert!(capture.is_capturing());
+
+        // The audio thread marks each block's position, only the first counts
+        for block in 0..4u64 {
+            capture.mark_position(9_600 + block * 100);
+            for i in 0..100 {
+                let mut frame = [0.0; MAX_INPUT_CHANNELS];
+                frame[0] = 0.25;
+                frame[1] = -0.25;
+                frame[2] = (block * 100 + i) as f32 / 1000.0;
+                capture.push(frame);
+            }
+        }
+        let take = writer.finish().unwrap();
+        assert!(!capture.is_capturing());
+        assert_eq!(take.captured_from, Some(9_600));
+        assert_eq!(take.end(), Some(10_000));
+
+        let read = |path: &Path| {
+            let mut reader = hound::WavReader::open(path).unwrap();
+            let channels = reader.spec().channels;
+            (channels, reader.samples::<f32>().map(|s| s.unwrap()).collect::<Vec<_>>())
+        };
+        let (channels, samples) = read(&take.files.iter().find(|(id, _)| *id == vocal).unwrap().1);
+        assert_eq!((channels, samples.len()), (1, 400));
+        assert!((samples[123] - 0.123).abs() < 1e-6);
+        let (channels, samples) = read(&take.files.iter().find(|(id, _)| *id == guitars).unwrap().1);
+        assert_eq!((channels, samples.len()), (2, 800));
+        assert_eq!(&samples[..2], &[0.25, -0.25]);
+
+        std::fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn test_take_without_frames_has_no_position() {
+        let dir = temp_dir();
+        let capture = Arc::new(TakeCapture::default());
+        let target = TakeTarget { track_id: TrackId::new_v4(), input: RecordInput::Mono(0), path: dir.join("empty.wav") };
+        let take = TakeWriter::start(capture, vec![target], 48_000).unwrap().finish().unwrap();
+        assert_eq!((take.captured_from, take.end()), (None, None));
+        std::fs::remove_dir_all(dir).ok();
+    }
+}
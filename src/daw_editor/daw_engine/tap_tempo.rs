@@ -0,0 +1,114 @@
+/// Tempo from tapping along to a beat
+/// The tempo is the average interval over the last few taps, leaving out
+/// intervals far from the median so one early or doubled tap doesn't throw
+/// the estimate. A pause ends the run and the next tap starts a new one.
+use std::time::Instant;
+
+/// Taps averaged over
+const MAX_TAPS: usize = 8;
+
+/// Pause in seconds after which the next tap starts over (30 BPM)
+const MAX_TAP_GAP: f64 = 2.0;
+
+/// How far an interval may be from the median and still count, as a fraction
+const OUTLIER_TOLERANCE: f64 = 0.25;
+
+pub struct TapTempo {
+    epoch: Instant,
+    /// Times of the taps in this run, in seconds, oldest first
+    taps: Vec<f64>,
+}
+
+impl Default for TapTempo {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TapTempo {
+    pub fn new() -> Self {
+        Self { epoch: Instant::now(), taps: Vec::new() }
+    }
+
+    /// Register a tap now. See [`Self::tap`].
+    pub fn tap_now(&mut self) -> Option<f32> {
+        let at = self.epoch.elapsed().as_secs_f64();
+        self.tap(at)
+    }
+
+    /// Register a tap at `at` seconds and return the tempo so far in BPM,
+    /// or None on the first tap of a run
+    pub fn tap(&mut self, at: f64) -> Option<f32> {
+        if self.taps.last().is_some_and(|&last| at - last > MAX_TAP_GAP || at <= last) {
+            self.taps.clear();
+        }
+        self.taps.push(at);
+        if self.taps.len() > MAX_TAPS {
+            self.taps.remove(0);
+        }
+        self.tempo()
+    }
+
+    /// Taps in the current run, up to the number averaged over
+    pub fn tap_count(&self) -> usize {
+        self.taps.len()
+    }
+
+    pub fn reset(&mut self) {
+        self.taps.clear();
+    }
+
+    /// Tempo from the taps in this run, in BPM
+    pub fn tempo(&self) -> Option<f32> {
+        let mut intervals: Vec<f64> = self.taps.windows(2).map(|pair| pair[1] - pair[0]).collect();
+        if intervals.is_empty() {
+            return None;
+        }
+        let mut sorted = intervals.clone();
+        sorted.sort_by(|a, b| a.total_cmp(b));
+        let median = sorted[sorted.len() / 2];
+        intervals.retain(|interval| (interval - median).abs() <= median * OUTLIER_TOLERANCE);
+
+        let average = intervals.iter().sum::<f64>() / intervals.len() as f64;
+        Some((60.0 / average) as f32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tempo_from_taps() {
+        let mut taps = TapTempo::new();
+        assert_eq!(taps.tap(10.0), None);
+        // 0.5 s apart is 120 BPM
+        assert!((taps.tap(10.5).unwrap() - 120.0).abs() < 1e-3);
+        for i in 2..6 {
+            taps.tap(10.0 + i as f64 * 0.5);
+        }
+        assert!((taps.tempo().unwrap() - 120.0).abs() < 1e-3);
+
+        // Only the last taps count, so the estimate follows a change of pace
+        for i in 0..MAX_TAPS {
+            taps.tap(13.0 + i as f64 * 0.4);
+        }
+        assert!((taps.tempo().unwrap() - 150.0).abs() < 1e-3);
+        assert_eq!(taps.tap_count(), MAX_TAPS);
+    }
+
+    #[test]
+    fn test_outliers_and_pauses() {
+        let mut taps = TapTempo::new();
+        // A late tap in an otherwise steady run is left out
+        for at in [0.0, 0.5, 1.0, 1.8, 2.3, 2.8] {
+            taps.tap(at);
+        }
+        assert!((taps.tempo().unwrap() - 120.0).abs() < 1e-3);
+
+        // After a long pause the run starts over
+        assert_eq!(taps.tap(10.0), None);
+        assert_eq!(taps.tap_count(), 1);
+        assert!((taps.tap(10.75).unwrap() - 80.0).abs() < 1e-3);
+    }
+}
@@ -0,0 +1,31 @@
+/// Helpers shared by the tests across the engine and the UI state
+use super::audio_types::SAMPLE_RATE;
+use std::path::{Path, PathBuf};
+
+/// A new empty directory under the system temp dir, removed by the test
+pub fn temp_dir() -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("pulsar-daw-test-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Write `samples` to a mono float WAV at the engine rate
+pub fn write_mono(path: &Path, samples: &[f32]) {
+    let spec = hound::WavSpec {
+        channels: 1,
+        sample_rate: SAMPLE_RATE as u32,
+        bits_per_sample: 32,
+        sample_format: hound::SampleFormat::Float,
+    };
+    let mut writer = hound::WavWriter::create(path, spec).unwrap();
+    for sample in samples {
+        writer.write_sample(*sample).unwrap();
+    }
+    writer.finalize().unwrap();
+}
+
+/// A quiet sine of `frames` frames, as a mono WAV
+pub fn write_tone(path: &Path, frames: usize) {
+    let samples: Vec<f32> = (0..frames).map(|i| (i as f32 * 0.05).sin() * 0.25).collect();
+    write_mono(path, &samples);
+}
@@ -0,0 +1,194 @@
+/// Timing sidecar for rendered mixes
+/// A JSON file written beside a bounced WAV listing where the project's
+/// markers, loop, tempo and regions fall in it, in samples and in seconds
+/// from the first frame of the file, so a game can schedule events against
+/// the audio at runtime. The schema carries a version; fields are only ever
+/// added within one, and anything renamed or removed bumps it.
+use super::audio_types::SampleTime;
+use super::project::DawProject;
+use super::render::{render_loaded_project, RenderOptions, RenderSummary};
+use anyhow::{Context as AnyhowContext, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Version of the sidecar schema written
+pub const TIMING_SIDECAR_VERSION: u32 = 1;
+
+/// A position in the audio file, both ways round
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SidecarTime {
+    pub samples: SampleTime,
+    pub seconds: f64,
+}
+
+impl SidecarTime {
+    fn new(samples: SampleTime, sample_rate: u32) -> Self {
+        Self { samples, seconds: samples as f64 / sample_rate.max(1) as f64 }
+    }
+}
+
+/// A tempo and meter in effect from `time` on
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SidecarTempo {
+    pub time: SidecarTime,
+    pub bpm: f32,
+    /// Beats per bar and the note value of a beat, as in 3/4
+    pub time_signature: [u32; 2],
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SidecarMarker {
+    pub name: String,
+    pub time: SidecarTime,
+}
+
+/// A span of the file, end exclusive
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SidecarSpan {
+    /// Empty for the loop
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub name: String,
+    pub start: SidecarTime,
+    pub end: SidecarTime,
+}
+
+/// The timing data written beside a rendered file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TimingSidecar {
+    pub version: u32,
+    /// Name of the audio file the times refer to, in the same folder
+    pub audio_file: String,
+    pub sample_rate: u32,
+    pub channels: usize,
+    pub length: SidecarTime,
+    /// Where the file's first frame sits on the project timeline
+    pub timeline_start: SidecarTime,
+    /// Tempo changes in order. The project has one tempo for now, so this
+    /// holds a single entry at the start of the file.
+    pub tempo_map: Vec<SidecarTempo>,
+    /// Markers inside the file, in order
+    pub markers: Vec<SidecarMarker>,
+    /// The transport loop when it's on, cut to the file
+    #[serde(rename = "loop")]
+    pub loop_span: Option<SidecarSpan>,
+    /// Arrangement regions overlapping the file, in order and cut to it
+    pub regions: Vec<SidecarSpan>,
+}
+
+impl TimingSidecar {
+    /// Timing of `project` as it lands in the file `summary` describes.
+    /// Anything outside the file is left out.
+    pub fn new(project: &DawProject, summary: &RenderSummary) -> Self {
+        let rate = summary.sample_rate;
+        let (start, end) = (summary.start, summary.start + summary.frames);
+        let time = |position: SampleTime| SidecarTime::new(position - start, rate);
+        let span = |name: &str, from: SampleTime, to: SampleTime| {
+            let (from, to) = (from.max(start), to.min(end));
+            (from < to).then(|| SidecarSpan { name: name.to_string(), start: time(from), end: time(to) })
+        };
+
+        let transport = &project.transport;
+        let mut markers: Vec<SidecarMarker> = project
+            .markers
+            .iter()
+            .filter(|m| (start..end).contains(&m.position))
+            .map(|m| SidecarMarker { name: m.name.clone(), time: time(m.position) })
+            .collect();
+        markers.sort_by_key(|m| m.time.samples);
+        let mut regions: Vec<SidecarSpan> = project.regions.iter().filter_map(|r| span(&r.name, r.start, r.end)).collect();
+        regions.sort_by_key(|r| r.start.samples);
+
+        Self {
+            version: TIMING_SIDECAR_VERSION,
+            audio_file: summary.output_path.file_name().map_or_else(String::new, |n| n.to_string_lossy().into_owned()),
+            sample_rate: rate,
+            channels: project.channel_layout.channel_count(),
+            length: SidecarTime::new(summary.frames, rate),
+            timeline_start: SidecarTime::new(start, rate),
+            tempo_map: vec![SidecarTempo {
+                time: SidecarTime::new(0, rate),
+                bpm: transport.tempo,
+                time_signature: [transport.time_signature_numerator, transport.time_signature_denominator],
+            }],
+            markers,
+            loop_span: transport.loop_enabled.then(|| span("", transport.loop_start, transport.loop_end)).flatten(),
+            regions,
+        }
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self).context("Failed to serialize timing sidecar")
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, self.to_json()?).with_context(|| format!("Failed to write timing sidecar {:?}", path))
+    }
+}
+
+/// The sidecar written beside `audio`: same name, `.json`
+pub fn sidecar_path(audio: &Path) -> PathBuf {
+    audio.with_extension("json")
+}
+
+/// Render the master to `output` and write its timing sidecar beside it.
+/// Returns the render summary and where the sidecar went.
+pub fn render_with_timing(
+    project: &DawProject,
+    base_dir: &Path,
+    output: &Path,
+    options: &RenderOptions,
+) -> Result<(RenderSummary, PathBuf)> {
+    let summary = render_loaded_project(project, base_dir, output, options)?;
+    let sidecar = sidecar_path(output);
+    TimingSidecar::new(project, &summary).write(&sidecar)?;
+    Ok((summary, sidecar))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::audio_types::{Marker, Region};
+    use super::super::render::OverReport;
+
+    #[test]
+    fn test_times_are_relative_to_the_file() {
+        let mut project = DawProject::new("Boss");
+        project.sample_rate = 48_000.0;
+        project.markers.push(Marker::new("Phase 2", 96_000));
+        project.markers.push(Marker::new("Hit", 72_000));
+        project.markers.push(Marker::new("Before", 1_000));
+        project.regions.push(Region::new("Intro", 0, 60_000, 0));
+        project.regions.push(Region::new("Outro", 400_000, 500_000, 0));
+        project.transport.loop_enabled = true;
+        project.transport.loop_start = 48_000;
+        project.transport.loop_end = 144_000;
+
+        let summary = RenderSummary {
+            output_path: PathBuf::from("/tmp/Boss.wav"),
+            start: 24_000,
+            sample_rate: 48_000,
+            frames: 240_000,
+            peak: 1.0,
+            loudness: None,
+            trim: None,
+            overs: OverReport::default(),
+        };
+        let sidecar = TimingSidecar::new(&project, &summary);
+
+        assert_eq!(sidecar.audio_file, "Boss.wav");
+        let markers: Vec<(&str, SampleTime, f64)> =
+            sidecar.markers.iter().map(|m| (m.name.as_str(), m.time.samples, m.time.seconds)).collect();
+        assert_eq!(markers, [("Hit", 48_000, 1.0), ("Phase 2", 72_000, 1.5)]);
+        // The intro is cut to where the file starts and the outro is past its end
+        assert_eq!(sidecar.regions.len(), 1);
+        assert_eq!((sidecar.regions[0].start.samples, sidecar.regions[0].end.samples), (0, 36_000));
+        let looped = sidecar.loop_span.as_ref().unwrap();
+        assert_eq!((looped.start.seconds, looped.end.seconds), (0.5, 2.5));
+        assert_eq!(sidecar.timeline_start.seconds, 0.5);
+
+        let json: serde_json::Value = serde_json::from_str(&sidecar.to_json().unwrap()).unwrap();
+        assert_eq!(json["version"], TIMING_SIDECAR_VERSION);
+        assert_eq!(json["loop"]["end"]["samples"], 120_000);
+        assert_eq!(json["tempo_map"][0]["bpm"], 120.0);
+    }
+}
@@ -0,0 +1,87 @@
+/// Transient detection for aligning clips
+/// Onsets are found where the energy of short windows jumps well above the
+/// window before, then pinned to the first frame that reaches half of the
+/// hit's peak, so layered hits recorded on different mics line up on their
+/// attacks rather than wherever their windows happened to fall.
+use super::audio_types::*;
+
+/// Frames per energy window, about 1.3 ms at 48 kHz
+const WINDOW_FRAMES: usize = 64;
+
+/// Energy a window needs over the one before it to count as an onset (12 dB)
+const RISE_RATIO: f32 = 16.0;
+
+/// Mean square below which a window counts as silence (-50 dBFS)
+const SILENCE_ENERGY: f32 = 1e-5;
+
+/// First onset in source frames `from..to` of interleaved `samples`, as a
+/// source frame. Audio just before `from` counts as the lead-in, so a clip
+/// trimmed in the middle of a sound doesn't report its own start.
+pub fn first_onset(samples: &[f32], channels: usize, from: SampleTime, to: SampleTime) -> Option<SampleTime> {
+    let channels = channels.max(1);
+    let frames = samples.len() / channels;
+    let to = (to as usize).min(frames);
+    let from = from as usize;
+    if from >= to {
+        return None;
+    }
+
+    let frame_power = |frame: usize| -> f32 {
+        samples[frame * channels..(frame + 1) * channels].iter().map(|s| s * s).sum::<f32>() / channels as f32
+    };
+    let window_energy = |start: usize, end: usize| -> f32 {
+        (start..end).map(frame_power).sum::<f32>() / (end - start).max(1) as f32
+    };
+
+    let mut previous = window_energy(from.saturating_sub(WINDOW_FRAMES), from);
+    let mut start = from;
+    while start < to {
+        let end = (start + WINDOW_FRAMES).min(to);
+        let energy = window_energy(start, end);
+        if energy > SILENCE_ENERGY && energy > previous.max(SILENCE_ENERGY) * RISE_RATIO {
+            // The attack may begin in this window and peak in the next one
+            let search_end = (end + WINDOW_FRAMES).min(to);
+            let peak = (start..search_end).map(frame_power).fold(0.0_f32, f32::max);
+            return (start..search_end).find(|&frame| frame_power(frame) >= peak * 0.25).map(|f| f as SampleTime);
+        }
+        previous = energy;
+        start = end;
+    }
+    None
+}
+
+/// Timeline position of the first onset heard in `clip`, if it has one
+pub fn clip_onset(clip: &AudioClip, asset: &AudioAssetData) -> Option<SampleTime> {
+    let channels = asset.asset_ref.channels;
+    let onset = first_onset(&asset.samples, channels, clip.offset, clip.offset + clip.source_duration())?;
+    Some(clip.start_time + clip.source_to_timeline(onset - clip.offset))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Stereo noise floor with a decaying hit from `at`
+    fn hit(frames: usize, at: usize) -> Vec<f32> {
+        (0..frames)
+            .flat_map(|i| {
+                let floor = if i % 2 == 0 { 0.0005 } else { -0.0005 };
+                let tail = if i >= at { 0.8 * (-((i - at) as f32) / 2000.0).exp() * (i as f32 * 0.3).sin().signum() } else { 0.0 };
+                [floor + tail, floor + tail * 0.9]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_first_onset() {
+        let samples = hit(20_000, 5_003);
+        let onset = first_onset(&samples, 2, 0, 20_000).unwrap();
+        assert!((5_003..5_010).contains(&(onset as usize)), "{}", onset);
+
+        // Nothing after the start of a trimmed tail, and nothing in silence
+        assert_eq!(first_onset(&samples, 2, 6_000, 20_000), None);
+        assert_eq!(first_onset(&vec![0.0; 4_000], 2, 0, 2_000), None);
+        // A clip trimmed tight to the hit still finds it
+        assert!(first_onset(&samples, 2, 5_003, 20_000).is_some());
+    }
+}
@@ -0,0 +1,556 @@
+/// Transport chase
+/// With a sync source set, the transport follows an external master rather
+/// than its own clock. MIDI Time Code gives the master's position as a
+/// timecode; MIDI clock gives its tempo and song position in beats. Timecode
+/// readings go through a drift lock that smooths out message jitter, and the
+/// playhead is only relocated once it is further from the master than a
+/// tolerance, so jitter never turns into audible jumps while real drift is
+/// still corrected. Play and stop follow the master too: clock Start,
+/// Continue and Stop messages, or timecode starting to run and dropping out.
+use super::audio_types::{SampleTime, Transport};
+use super::preferences::EditPreferences;
+use serde::{Deserialize, Serialize};
+use std::time::Instant;
+
+/// Furthest the playhead may be from the master before it is relocated
+const CHASE_TOLERANCE_SECONDS: f64 = 0.02;
+/// Time without quarter frames after which running timecode counts as stopped
+const TIMECODE_DROPOUT_SECONDS: f64 = 0.25;
+/// Timecodes in a row needed before running timecode starts playback
+const TIMECODES_TO_PLAY: u32 = 2;
+/// Share of each timecode error the drift lock takes up at once
+const LOCK_POSITION_GAIN: f64 = 0.2;
+/// Share of each timecode error the drift lock folds into its rate
+const LOCK_RATE_GAIN: f64 = 0.05;
+/// Timecode error past which the lock starts over instead of slewing
+const RELOCK_SECONDS: f64 = 0.25;
+/// MIDI clock ticks per quarter note
+const CLOCK_TICKS_PER_BEAT: u32 = 24;
+/// Weight of each new tick interval in the clock tempo estimate
+const CLOCK_SMOOTHING: f64 = 0.1;
+/// Smallest tempo change followed from MIDI clock, in BPM
+const TEMPO_HYSTERESIS: f32 = 0.25;
+
+/// Where the transport takes its position and play state from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum SyncSource {
+    /// The transport runs on its own clock
+    #[default]
+    Internal,
+    /// Chase incoming MIDI Time Code
+    Timecode,
+    /// Chase incoming MIDI clock and song position
+    Clock,
+}
+
+impl SyncSource {
+    pub fn label(self) -> &'static str {
+        match self {
+            SyncSource::Internal => "Internal",
+            SyncSource::Timecode => "MTC",
+            SyncSource::Clock => "MIDI Clock",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            SyncSource::Internal => SyncSource::Timecode,
+            SyncSource::Timecode => SyncSource::Clock,
+            SyncSource::Clock => SyncSource::Internal,
+        }
+    }
+}
+
+/// What the transport should do to follow the master
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChaseEvent {
+    Locate(SampleTime),
+    Play,
+    Stop,
+    Tempo(f32),
+}
+
+/// Frame rate a timecode is counted in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameRate {
+    Fps24,
+    Fps25,
+    /// 29.97 drop-frame
+    Fps2997Drop,
+    Fps30,
+}
+
+impl FrameRate {
+    /// Rate from the two rate bits of an MTC hours byte
+    fn from_bits(bits: u8) -> Self {
+        match bits & 3 {
+            0 => FrameRate::Fps24,
+            1 => FrameRate::Fps25,
+            2 => FrameRate::Fps2997Drop,
+            _ => FrameRate::Fps30,
+        }
+    }
+
+    pub fn frames_per_second(self) -> f64 {
+        match self {
+            FrameRate::Fps24 => 24.0,
+            FrameRate::Fps25 => 25.0,
+            FrameRate::Fps2997Drop => 30_000.0 / 1001.0,
+            FrameRate::Fps30 => 30.0,
+        }
+    }
+
+    /// Frame labels per second, 30 for drop-frame
+    fn nominal(self) -> u64 {
+        match self {
+            FrameRate::Fps24 => 24,
+            FrameRate::Fps25 => 25,
+            FrameRate::Fps2997Drop | FrameRate::Fps30 => 30,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Timecode {
+    pub hours: u8,
+    pub minutes: u8,
+    pub seconds: u8,
+    pub frames: u8,
+    pub rate: FrameRate,
+}
+
+impl Timecode {
+    /// Timecode from an MTC hours byte, which carries the frame rate in its
+    /// upper bits, and the other three fields
+    fn from_mtc(hours: u8, minutes: u8, seconds: u8, frames: u8) -> Self {
+        Self {
+            hours: hours & 0x1f,
+            minutes: minutes & 0x3f,
+            seconds: seconds & 0x3f,
+            frames: frames & 0x1f,
+            rate: FrameRate::from_bits(hours >> 5),
+        }
+    }
+
+    /// Real time since 00:00:00:00 in seconds. Drop-frame timecode skips two
+    /// frame labels each minute except every tenth.
+    pub fn to_seconds(self) -> f64 {
+        let total_minutes = self.hours as u64 * 60 + self.minutes as u64;
+        let mut frames = (total_minutes * 60 + self.seconds as u64) * self.rate.nominal() + self.frames as u64;
+        if self.rate == FrameRate::Fps2997Drop {
+            frames -= 2 * (total_minutes - total_minutes / 10);
+        }
+        frames as f64 / self.rate.frames_per_second()
+    }
+
+    fn frame_seconds(self) -> f64 {
+        1.0 / self.rate.frames_per_second()
+    }
+}
+
+/// Assembles MTC quarter-frame messages into timecodes
+#[derive(Debug, Default)]
+struct QuarterFrames {
+    pieces: [u8; 8],
+    /// Bit per piece received since the last piece 0
+    received: u8,
+}
+
+impl QuarterFrames {
+    /// Take the data byte of a quarter frame. The eighth piece completes a
+    /// timecode, which is where the master was when the first was sent.
+    fn push(&mut self, data: u8) -> Option<Timecode> {
+        let piece = ((data >> 4) & 7) as usize;
+        if piece == 0 {
+            self.received = 0;
+        }
+        self.pieces[piece] = data & 0x0f;
+        self.received |= 1 << piece;
+        if piece != 7 || self.received != 0xff {
+            return None;
+        }
+        let p = self.pieces;
+        Some(Timecode::from_mtc(p[6] | p[7] << 4, p[4] | p[5] << 4, p[2] | p[3] << 4, p[0] | p[1] << 4))
+    }
+
+    fn reset(&mut self) {
+        self.received = 0;
+    }
+}
+
+/// Smooths (local time, master position) readings into a steadily running
+/// master position that also follows a master clock running fast or slow
+#[derive(Debug)]
+struct DriftLock {
+    /// Local time and locked position of the last reading
+    anchor: Option<(f64, f64)>,
+    /// Master seconds per local second
+    rate: f64,
+}
+
+impl Default for DriftLock {
+    fn default() -> Self {
+        Self { anchor: None, rate: 1.0 }
+    }
+}
+
+impl DriftLock {
+    /// Take a reading of `position` at local time `at` and return the locked
+    /// master position there
+    fn update(&mut self, at: f64, position: f64) -> f64 {
+        let Some((anchor_at, anchor_position)) = self.anchor else {
+            self.anchor = Some((at, position));
+            return position;
+        };
+        let elapsed = (at - anchor_at).max(0.0);
+        let predicted = anchor_position + elapsed * self.rate;
+        let error = position - predicted;
+        if error.abs() > RELOCK_SECONDS {
+            *self = Self { anchor: Some((at, position)), rate: 1.0 };
+            return position;
+        }
+
+        let locked = predicted + error * LOCK_POSITION_GAIN;
+        if elapsed > 0.0 {
+            self.rate = (self.rate + error * LOCK_RATE_GAIN / elapsed).clamp(0.9, 1.1);
+        }
+        self.anchor = Some((at, locked));
+        locked
+    }
+
+    fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+/// Follows an external master, turning its MIDI messages into transport
+/// moves. Messages are stamped with local seconds and the engine's
+/// position when they arrived.
+#[derive(Debug)]
+pub struct TransportChase {
+    epoch: Instant,
+    source: SyncSource,
+    /// Master timecode at the project start, in seconds
+    pub timecode_offset: f64,
+    playing: bool,
+    quarter_frames: QuarterFrames,
+    lock: DriftLock,
+    /// Local time of the last quarter frame
+    last_quarter_frame: Option<f64>,
+    /// Timecodes completed since quarter frames last dropped out
+    running_timecodes: u32,
+    /// Song position set by Start, Continue, Stop or a song position
+    /// message, in beats
+    song_beats: f64,
+    /// Clock ticks since `song_beats`
+    ticks: u32,
+    last_tick: Option<f64>,
+    /// Smoothed seconds between clock ticks
+    tick_interval: Option<f64>,
+}
+
+impl Default for TransportChase {
+    fn default() -> Self {
+        Self::new(SyncSource::Internal)
+    }
+}
+
+impl TransportChase {
+    pub fn new(source: SyncSource) -> Self {
+        Self {
+            epoch: Instant::now(),
+            source,
+            timecode_offset: 0.0,
+            playing: false,
+            quarter_frames: QuarterFrames::default(),
+            lock: DriftLock::default(),
+            last_quarter_frame: None,
+            running_timecodes: 0,
+            song_beats: 0.0,
+            ticks: 0,
+            last_tick: None,
+            tick_interval: None,
+        }
+    }
+
+    /// Chase set up from the user's sync source and timecode start
+    pub fn for_preferences(preferences: &EditPreferences) -> Self {
+        Self { timecode_offset: preferences.timecode_start_seconds, ..Self::new(preferences.sync_source) }
+    }
+
+    pub fn source(&self) -> SyncSource {
+        self.source
+    }
+
+    /// Whether the transport follows a master rather than its own clock
+    pub fn is_chasing(&self) -> bool {
+        self.source != SyncSource::Internal
+    }
+
+    /// Switch source, forgetting everything heard from the last one
+    pub fn set_source(&mut self, source: SyncSource) {
+        *self = Self { epoch: self.epoch, timecode_offset: self.timecode_offset, ..Self::new(source) };
+    }
+
+    /// Take a message that arrived at `arrived`. See [`Self::receive`].
+    pub fn receive_at(&mut self, message: &[u8], arrived: Instant, position: SampleTime, transport: &Transport) -> Vec<ChaseEvent> {
+        let at = arrived.saturating_duration_since(self.epoch).as_secs_f64();
+        self.receive(message, at, position, transport)
+    }
+
+    /// Take a MIDI message that arrived at local time `at` seconds while the
+    /// engine was at `position`, returning what the transport should do
+    pub fn receive(&mut self, message: &[u8], at: f64, position: SampleTime, transport: &Transport) -> Vec<ChaseEvent> {
+        match (self.source, message) {
+            (SyncSource::Timecode, [0xF1, data]) => self.quarter_frame(*data, at, position, transport),
+            // Full-frame message, sent while the master shuttles or locates
+            (SyncSource::Timecode, [0xF0, 0x7F, _, 0x01, 0x01, hours, minutes, seconds, frames, 0xF7]) => {
+                self.quarter_frames.reset();
+                self.lock.reset();
+                let timecode = Timecode::from_mtc(*hours, *minutes, *seconds, *frames);
+                vec![ChaseEvent::Locate(self.timecode_position(timecode.to_seconds(), transport))]
+            }
+            (SyncSource::Clock, [0xFA]) => {
+                self.song_beats = 0.0;
+                self.start_clock(transport)
+            }
+            (SyncSource::Clock, [0xFB]) => self.start_clock(transport),
+            (SyncSource::Clock, [0xFC]) if self.playing => {
+                self.song_beats = self.clock_beats();
+                self.ticks = 0;
+                self.playing = false;
+                vec![ChaseEvent::Stop]
+            }
+            // Song position, counted in sixteenth notes
+            (SyncSource::Clock, [0xF2, lsb, msb]) => {
+                self.song_beats = ((*lsb as u32 & 0x7f) | (*msb as u32 & 0x7f) << 7) as f64 / 4.0;
+                self.ticks = 0;
+                if self.playing {
+                    Vec::new()
+                } else {
+                    vec![ChaseEvent::Locate(self.beats_position(self.song_beats, transport.tempo, transport))]
+                }
+            }
+            (SyncSource::Clock, [0xF8]) if self.playing => self.clock_tick(at, position, transport),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Check for timecode that stopped arriving. See [`Self::poll`].
+    pub fn poll_now(&mut self) -> Vec<ChaseEvent> {
+        let at = self.epoch.elapsed().as_secs_f64();
+        self.poll(at)
+    }
+
+    /// Stop once running timecode has dropped out, as the master stopping
+    /// sends nothing more
+    pub fn poll(&mut self, at: f64) -> Vec<ChaseEvent> {
+        if self.source != SyncSource::Timecode {
+            return Vec::new();
+        }
+        let dropped = self.last_quarter_frame.is_some_and(|last| at - last > TIMECODE_DROPOUT_SECONDS);
+        if !dropped {
+            return Vec::new();
+        }
+        self.last_quarter_frame = None;
+        self.running_timecodes = 0;
+        self.quarter_frames.reset();
+        self.lock.reset();
+        if std::mem::take(&mut self.playing) {
+            vec![ChaseEvent::Stop]
+        } else {
+            Vec::new()
+        }
+    }
+
+    fn quarter_frame(&mut self, data: u8, at: f64, position: SampleTime, transport: &Transport) -> Vec<ChaseEvent> {
+        self.last_quarter_frame = Some(at);
+        let Some(timecode) = self.quarter_frames.push(data) else {
+            return Vec::new();
+        };
+        // The eighth piece goes out a frame and three quarters after the
+        // time the timecode carries
+        let master = self.lock.update(at, timecode.to_seconds() + 1.75 * timecode.frame_seconds());
+        let target = self.timecode_position(master, transport);
+        self.running_timecodes += 1;
+
+        if self.playing {
+            self.locate_if_drifted(target, position, transport)
+        } else if self.running_timecodes >= TIMECODES_TO_PLAY {
+            self.playing = true;
+            vec![ChaseEvent::Locate(target), ChaseEvent::Play]
+        } else {
+            Vec::new()
+        }
+    }
+
+    fn start_clock(&mut self, transport: &Transport) -> Vec<ChaseEvent> {
+        self.ticks = 0;
+        self.last_tick = None;
+        self.playing = true;
+        vec![ChaseEvent::Locate(self.beats_position(self.song_beats, transport.tempo, transport)), ChaseEvent::Play]
+    }
+
+    fn clock_tick(&mut self, at: f64, position: SampleTime, transport: &Transport) -> Vec<ChaseEvent> {
+        if let Some(last) = self.last_tick {
+            let interval = at - last;
+            self.tick_interval = Some(match self.tick_interval {
+                Some(smoothed) => smoothed + (interval - smoothed) * CLOCK_SMOOTHING,
+                None => interval,
+            });
+        }
+        self.last_tick = Some(at);
+        self.ticks += 1;
+        // Tempo and drift are checked once a beat
+        if !self.ticks.is_multiple_of(CLOCK_TICKS_PER_BEAT) {
+            return Vec::new();
+        }
+
+        let mut events = Vec::new();
+        let mut tempo = transport.tempo;
+        if let Some(interval) = self.tick_interval.filter(|i| *i > 0.0 && self.ticks >= CLOCK_TICKS_PER_BEAT) {
+            let master = (60.0 / (interval * CLOCK_TICKS_PER_BEAT as f64)) as f32;
+            if (master - transport.tempo).abs() > TEMPO_HYSTERESIS {
+                tempo = (master * 10.0).round() / 10.0;
+                events.push(ChaseEvent::Tempo(tempo));
+            }
+        }
+        let target = self.beats_position(self.clock_beats(), tempo, transport);
+        events.extend(self.locate_if_drifted(target, position, transport));
+        events
+    }
+
+    fn clock_beats(&self) -> f64 {
+        self.song_beats + self.ticks as f64 / CLOCK_TICKS_PER_BEAT as f64
+    }
+
+    fn beats_position(&self, beats: f64, tempo: f32, transport: &Transport) -> SampleTime {
+        (beats * 60.0 / tempo.max(1.0) as f64 * transport.sample_rate as f64).round() as SampleTime
+    }
+
+    fn timecode_position(&self, seconds: f64, transport: &Transport) -> SampleTime {
+        ((seconds - self.timecode_offset).max(0.0) * transport.sample_rate as f64).round() as SampleTime
+    }
+
+    fn locate_if_drifted(&self, target: SampleTime, position: SampleTime, transport: &Transport) -> Vec<ChaseEvent> {
+        let tolerance = CHASE_TOLERANCE_SECONDS * transport.sample_rate as f64;
+        if target.abs_diff(position) as f64 > tolerance {
+            vec![ChaseEvent::Locate(target)]
+        } else {
+            Vec::new()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The eight quarter-frame messages carrying `timecode`
+    fn quarter_frames(hours: u8, minutes: u8, seconds: u8, frames: u8) -> Vec<[u8; 2]> {
+        [frames, seconds, minutes, hours]
+            .iter()
+            .flat_map(|value| [value & 0x0f, value >> 4])
+            .enumerate()
+            .map(|(piece, nibble)| [0xF1, (piece as u8) << 4 | nibble])
+            .collect()
+    }
+
+    #[test]
+    fn test_timecode_seconds() {
+        let mut pieces = QuarterFrames::default();
+        let decoded: Vec<Option<Timecode>> = quarter_frames(1 | 1 << 5, 2, 3, 4).iter().map(|m| pieces.push(m[1])).collect();
+        assert!(decoded[..7].iter().all(Option::is_none));
+        let timecode = decoded[7].unwrap();
+        assert_eq!((timecode.hours, timecode.minutes, timecode.seconds, timecode.frames), (1, 2, 3, 4));
+        assert_eq!(timecode.rate, FrameRate::Fps25);
+        assert!((timecode.to_seconds() - 3723.16).abs() < 1e-9);
+
+        // Ten minutes of drop-frame is 17982 frames
+        let drop = Timecode { hours: 0, minutes: 10, seconds: 0, frames: 0, rate: FrameRate::Fps2997Drop };
+        assert!((drop.to_seconds() - 17982.0 * 1001.0 / 30_000.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_timecode_chase_locks_and_corrects_drift() {
+        let transport = Transport::default();
+        let rate = transport.sample_rate as f64;
+        let mut chase = TransportChase::new(SyncSource::Timecode);
+        chase.timecode_offset = 3600.0;
+
+        // A full-frame message locates while the master is parked
+        let full_frame = [0xF0, 0x7F, 0x7F, 0x01, 0x01, 1 | 1 << 5, 0, 2, 0, 0xF7];
+        assert_eq!(chase.receive(&full_frame, 0.0, 0, &transport), vec![ChaseEvent::Locate(2 * rate as SampleTime)]);
+
+        // Running 25 fps timecode from 01:00:10:00, each quarter frame a little
+        // late or early. The local engine runs 0.5% slow.
+        let mut events = Vec::new();
+        let mut position: Option<(f64, SampleTime)> = None;
+        for frame in 0..250u32 {
+            let master = 10.0 + frame as f64 / 25.0;
+            if frame % 2 != 0 {
+                continue;
+            }
+            let whole = master as u8;
+            let messages = quarter_frames(1 | 1 << 5, 0, whole, ((master - whole as f64) * 25.0).round() as u8);
+            for (piece, message) in messages.iter().enumerate() {
+                let at = master - 10.0 + piece as f64 / 100.0 + if piece % 2 == 0 { 0.002 } else { -0.002 };
+                let engine = position.map_or(0, |(from, start)| start + ((at - from) * rate * 0.995) as SampleTime);
+                for event in chase.receive(message, at, engine, &transport) {
+                    if let ChaseEvent::Locate(target) = event {
+                        position = Some((at, target));
+                    }
+                    events.push((at, event));
+                }
+            }
+        }
+
+        // Playback starts on the second timecode, at the master's position
+        let (at, start) = events[0];
+        assert_eq!(events[1].1, ChaseEvent::Play);
+        let ChaseEvent::Locate(start) = start else { panic!("expected a locate, got {:?}", start) };
+        assert!(((start as f64 / rate) - (10.0 + at)).abs() < 0.01);
+
+        // The slow engine is pulled back a few times over ten seconds, each
+        // time once it's off by more than the tolerance rather than on every
+        // jittery timecode
+        let relocations = events.len() - 2;
+        assert!((2..=3).contains(&relocations), "{} relocations", relocations);
+
+        // Timecode stopping stops the transport
+        assert!(chase.poll(10.1).is_empty());
+        assert_eq!(chase.poll(11.0), vec![ChaseEvent::Stop]);
+    }
+
+    #[test]
+    fn test_clock_chase_follows_tempo_and_song_position() {
+        let transport = Transport::default();
+        let mut chase = TransportChase::new(SyncSource::Clock);
+
+        // Song position of bar 3 while stopped, in sixteenths
+        assert_eq!(chase.receive(&[0xF2, 32, 0], 0.0, 0, &transport), vec![ChaseEvent::Locate(transport.beats_to_samples(8.0))]);
+        // Ticks before Continue are ignored
+        assert!(chase.receive(&[0xF8], 0.0, 0, &transport).is_empty());
+        assert_eq!(
+            chase.receive(&[0xFB], 0.0, 0, &transport),
+            vec![ChaseEvent::Locate(transport.beats_to_samples(8.0)), ChaseEvent::Play]
+        );
+
+        // A master at 100 BPM
+        let tick = 60.0 / 100.0 / 24.0;
+        let mut events = Vec::new();
+        for n in 1..=48 {
+            events.extend(chase.receive(&[0xF8], n as f64 * tick, transport.beats_to_samples(8.0), &transport));
+        }
+        assert!(events.contains(&ChaseEvent::Tempo(100.0)));
+
+        assert_eq!(chase.receive(&[0xFC], 1.0, 0, &transport), vec![ChaseEvent::Stop]);
+        assert!(chase.receive(&[0xFC], 1.1, 0, &transport).is_empty());
+        // Start goes back to the top
+        assert_eq!(chase.receive(&[0xFA], 2.0, 0, &transport), vec![ChaseEvent::Locate(0), ChaseEvent::Play]);
+
+        // Messages for the other source are ignored
+        chase.set_source(SyncSource::Internal);
+        assert!(chase.receive(&[0xFA], 3.0, 0, &transport).is_empty());
+    }
+}
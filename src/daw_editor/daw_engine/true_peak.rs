@@ -0,0 +1,158 @@
+/// True-peak detection and over counting
+/// A 4x oversampled interpolator estimates the peaks between samples, where
+/// a signal that never reaches full scale sample by sample can still clip a
+/// DAC or a lossy encoder. The limiter uses it to hold its ceiling, and
+/// renders use it to count the samples and inter-sample peaks over 0 dBFS.
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
+
+/// Taps of the interpolation filter per phase
+const TP_TAPS: usize = 8;
+
+/// Oversampling factor of the detector
+const TP_OVERSAMPLE: usize = 4;
+
+/// Samples the detector lags behind its input (half the filter length)
+pub const TP_DELAY: usize = TP_TAPS / 2;
+
+/// Oversampled peak estimator for a fixed number of channels
+#[derive(Debug, Clone)]
+pub struct TruePeakDetector {
+    /// Interpolation coefficients for the intermediate oversampled phases
+    coefficients: [[f32; TP_TAPS]; TP_OVERSAMPLE - 1],
+    /// Last TP_TAPS input samples per channel, oldest first
+    history: Vec<[f32; TP_TAPS]>,
+}
+
+impl TruePeakDetector {
+    pub fn new(channels: usize) -> Self {
+        let mut coefficients = [[0.0; TP_TAPS]; TP_OVERSAMPLE - 1];
+        for (phase, taps) in coefficients.iter_mut().enumerate() {
+            let t = (phase + 1) as f32 / TP_OVERSAMPLE as f32;
+            for (tap, c) in taps.iter_mut().enumerate() {
+                // Hann-windowed sinc centred between taps TP_DELAY-1 and TP_DELAY
+                let x = tap as f32 - (TP_DELAY - 1) as f32 - t;
+                let sinc = if x.abs() < 1e-6 { 1.0 } else { (PI * x).sin() / (PI * x) };
+                let window = 0.5 + 0.5 * (PI * x / (TP_DELAY as f32 + 1.0)).cos();
+                *c = sinc * window;
+            }
+        }
+        Self { coefficients, history: vec![[0.0; TP_TAPS]; channels] }
+    }
+
+    pub fn reset(&mut self) {
+        self.history.iter_mut().for_each(|h| *h = [0.0; TP_TAPS]);
+    }
+
+    /// Add a sample to a channel and return the true-peak estimate for the
+    /// sample TP_DELAY samples ago and the span that follows it
+    pub fn push(&mut self, channel: usize, sample: f32) -> f32 {
+        self.push_span(channel, sample).max(self.history[channel][TP_DELAY - 1].abs())
+    }
+
+    /// Add a sample and return the highest interpolated value in the span
+    /// after the sample TP_DELAY samples ago, leaving out both its ends
+    fn push_span(&mut self, channel: usize, sample: f32) -> f32 {
+        let history = &mut self.history[channel];
+        history.copy_within(1.., 0);
+        history[TP_TAPS - 1] = sample;
+        self.coefficients
+            .iter()
+            .map(|taps| taps.iter().zip(history.iter()).map(|(c, x)| c * x).sum::<f32>().abs())
+            .fold(0.0, f32::max)
+    }
+}
+
+/// How much of a signal went over full scale
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct OverCount {
+    /// Samples above 0 dBFS, counted per channel
+    pub sample_overs: u64,
+    /// Spans between two samples at or under 0 dBFS whose reconstructed
+    /// peak goes over it: the overs a sample-peak meter misses
+    pub intersample_overs: u64,
+    /// Highest true peak, in dBTP
+    pub true_peak_db: f32,
+}
+
+impl OverCount {
+    pub fn total(&self) -> u64 {
+        self.sample_overs + self.intersample_overs
+    }
+}
+
+/// Counts overs in a stream of buffers
+#[derive(Debug, Clone)]
+pub struct OverMeter {
+    detector: TruePeakDetector,
+    count: OverCount,
+    true_peak: f32,
+}
+
+impl OverMeter {
+    pub fn new(channels: usize) -> Self {
+        Self { detector: TruePeakDetector::new(channels), count: OverCount::default(), true_peak: 0.0 }
+    }
+
+    /// Measure one buffer of one channel. Every channel has to be given the
+    /// same number of samples.
+    pub fn process(&mut self, channel: usize, samples: &[f32]) {
+        for &sample in samples {
+            self.measure(channel, sample);
+        }
+    }
+
+    fn measure(&mut self, channel: usize, sample: f32) {
+        if sample.abs() > 1.0 {
+            self.count.sample_overs += 1;
+        }
+        let span = self.detector.push_span(channel, sample);
+        let history = &self.detector.history[channel];
+        let (before, after) = (history[TP_DELAY - 1].abs(), history[TP_DELAY].abs());
+        if span > 1.0 && before <= 1.0 && after <= 1.0 {
+            self.count.intersample_overs += 1;
+        }
+        self.true_peak = self.true_peak.max(span).max(before);
+    }
+
+    /// What was counted, with the last few samples run out into silence
+    pub fn finish(mut self) -> OverCount {
+        for channel in 0..self.detector.history.len() {
+            for _ in 0..TP_DELAY {
+                // The padding itself is silent and can't add sample overs
+                self.measure(channel, 0.0);
+            }
+        }
+        self.count.true_peak_db = 20.0 * self.true_peak.max(1e-6).log10();
+        self.count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counts_sample_and_intersample_overs_apart() {
+        // A quarter-rate sine at 45 degrees peaks 3 dB above its samples, so
+        // at 1.3 it goes over inside every other span without a sample over
+        let sine: Vec<f32> = (0..400).map(|i| 1.3 * (PI / 2.0 * i as f32 + PI / 4.0).sin()).collect();
+        let mut meter = OverMeter::new(1);
+        meter.process(0, &sine);
+        let count = meter.finish();
+        assert_eq!(count.sample_overs, 0);
+        assert_eq!(count.intersample_overs, 200, "{:?}", count);
+        assert!(count.true_peak_db > 1.5 && count.true_peak_db < 3.5, "{:?}", count);
+
+        let mut meter = OverMeter::new(2);
+        meter.process(0, &[0.5, 1.2, 0.5, 0.0]);
+        meter.process(1, &[0.0, 0.0, 0.0, 0.0]);
+        let count = meter.finish();
+        assert_eq!(count.sample_overs, 1);
+        assert!(count.true_peak_db > 1.5);
+
+        let mut quiet = OverMeter::new(1);
+        quiet.process(0, &[0.1, -0.2, 0.3]);
+        assert_eq!(quiet.finish().total(), 0);
+    }
+}
@@ -0,0 +1,164 @@
+/// Editor layout saved alongside a project
+/// Panel visibility, zoom, scroll position, track heights and selection are
+/// written to a sidecar next to the `.pdaw` rather than into it, so arranging
+/// the view never marks the project as edited. The last saved layout is also
+/// kept in the user config directory for projects that have no sidecar yet.
+use super::audio_types::{ClipId, TrackId};
+use anyhow::{Context as AnyhowContext, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+const SIDECAR_EXTENSION: &str = "view.json";
+const GLOBAL_LAYOUT_FILE: &str = "layout.json";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ViewState {
+    /// Whether the browser and inspector docks are open
+    pub left_dock_open: bool,
+    pub right_dock_open: bool,
+    pub show_browser: bool,
+    pub show_inspector: bool,
+    pub show_mixer: bool,
+    pub mixer_width: f32,
+    pub mixer_height: f32,
+    /// Whether the mixer shows narrow channel strips
+    pub mixer_narrow: bool,
+    /// Name of the selected inspector tab
+    pub inspector_tab: String,
+    /// Timeline zoom in pixels per beat
+    pub zoom: f64,
+    /// Horizontal timeline scroll in pixels
+    pub scroll_x: f32,
+    /// Index of the track row at the top of the timeline
+    pub top_track: usize,
+    pub track_height: f32,
+    pub track_heights: HashMap<TrackId, f32>,
+    pub selected_tracks: Vec<TrackId>,
+    pub selected_clips: Vec<ClipId>,
+    /// Playhead position in beats
+    pub playhead: f64,
+}
+
+impl Default for ViewState {
+    fn default() -> Self {
+        Self {
+            left_dock_open: true,
+            right_dock_open: true,
+            show_browser: true,
+            show_inspector: true,
+            show_mixer: true,
+            mixer_width: 80.0,
+            mixer_height: 420.0,
+            mixer_narrow: false,
+            inspector_tab: "Track".to_string(),
+            zoom: 50.0,
+            scroll_x: 0.0,
+            top_track: 0,
+            track_height: 120.0,
+            track_heights: HashMap::new(),
+            selected_tracks: Vec::new(),
+            selected_clips: Vec::new(),
+            playhead: 0.0,
+        }
+    }
+}
+
+impl ViewState {
+    /// Sidecar holding the layout of the project at `project_path`, such as
+    /// `song.view.json` for `song.pdaw`
+    pub fn sidecar_path(project_path: &Path) -> PathBuf {
+        project_path.with_extension(SIDECAR_EXTENSION)
+    }
+
+    /// Layout used for projects without a sidecar
+    pub fn global_path() -> PathBuf {
+        super::preferences::EditPreferences::default_path().with_file_name(GLOBAL_LAYOUT_FILE)
+    }
+
+    /// Layout for the project at `project_path`: its sidecar, else the last
+    /// saved layout, else None to keep the current one
+    pub fn load_for_project(project_path: &Path) -> Option<Self> {
+        Self::load(&Self::sidecar_path(project_path)).or_else(|| Self::load(&Self::global_path()))
+    }
+
+    /// Read a layout file. Missing or unreadable files give None; invalid
+    /// ones are logged and ignored.
+    pub fn load(path: &Path) -> Option<Self> {
+        let json = std::fs::read_to_string(path).ok()?;
+        serde_json::from_str(&json)
+            .map_err(|e| tracing::error!("⚠️ Ignoring invalid layout at {:?}: {}", path, e))
+            .ok()
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {:?}", parent))?;
+        }
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, json).with_context(|| format!("Failed to write {:?}", path))
+    }
+
+    /// Write the project's sidecar and make this the layout for new projects
+    pub fn save_for_project(&self, project_path: &Path) -> Result<()> {
+        self.save(&Self::sidecar_path(project_path))?;
+        if let Err(e) = self.save(&Self::global_path()) {
+            tracing::error!("⚠️ Failed to save default layout: {}", e);
+        }
+        Ok(())
+    }
+
+    /// Drop heights and selections of tracks and clips the project no
+    /// longer has, such as when a layout from another project is applied
+    pub fn retain_existing(&mut self, tracks: &[TrackId], clips: &[ClipId]) {
+        self.track_heights.retain(|id, _| tracks.contains(id));
+        self.selected_tracks.retain(|id| tracks.contains(id));
+        self.selected_clips.retain(|id| clips.contains(id));
+        self.top_track = self.top_track.min(tracks.len().saturating_sub(1));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::test_support::temp_dir;
+
+    #[test]
+    fn test_view_state_sidecar_round_trip() {
+        let dir = temp_dir();
+        let project_path = dir.join("song.pdaw");
+        assert_eq!(ViewState::sidecar_path(&project_path), dir.join("song.view.json"));
+        assert_eq!(ViewState::load(&ViewState::sidecar_path(&project_path)), None);
+
+        let (track, clip) = (uuid::Uuid::new_v4(), uuid::Uuid::new_v4());
+        let view = ViewState {
+            right_dock_open: false,
+            show_mixer: false,
+            mixer_narrow: true,
+            inspector_tab: "Automation".to_string(),
+            zoom: 120.0,
+            scroll_x: 640.0,
+            top_track: 3,
+            track_heights: HashMap::from([(track, 200.0)]),
+            selected_tracks: vec![track],
+            selected_clips: vec![clip],
+            playhead: 16.5,
+            ..ViewState::default()
+        };
+        view.save(&ViewState::sidecar_path(&project_path)).unwrap();
+        assert_eq!(ViewState::load(&ViewState::sidecar_path(&project_path)), Some(view.clone()));
+        std::fs::remove_dir_all(&dir).ok();
+
+        // Fields missing from older files keep their defaults
+        let partial: ViewState = serde_json::from_str(r#"{"zoom": 80.0}"#).unwrap();
+        assert_eq!(partial.zoom, 80.0);
+        assert!(partial.show_mixer && partial.left_dock_open);
+
+        let mut other = view;
+        other.retain_existing(&[uuid::Uuid::new_v4()], &[]);
+        assert!(other.track_heights.is_empty() && other.selected_tracks.is_empty() && other.selected_clips.is_empty());
+        assert_eq!(other.top_track, 0);
+    }
+}
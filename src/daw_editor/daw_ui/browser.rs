@@ -4,6 +4,7 @@
 
 use super::state::*;
 use super::panel::DawPanel;
+use super::super::daw_engine::drum_rack::{DrumPad, MAX_PAD_PITCH};
 use gpui::*;
 use gpui::prelude::FluentBuilder;
 use ui::{
@@ -378,13 +379,198 @@ fn render_audio_file_item(
         )
 }
 
-fn render_instruments_tab(_state: &mut DawUiState, cx: &mut Context<DawPanel>) -> impl IntoElement {
-    render_empty_state(
-        "No Instruments", 
-        "Virtual instruments and samplers will appear here",
-        IconName::Album,
-        cx
-    )
+fn render_instruments_tab(state: &mut DawUiState, cx: &mut Context<DawPanel>) -> impl IntoElement {
+    let columns = 4;
+    let rows = state.drum_rack.pads.len().div_ceil(columns);
+
+    v_flex()
+        .w_full()
+        .gap_2()
+        .p_2()
+        .child(
+            div()
+                .text_xs()
+                .font_semibold()
+                .text_color(cx.theme().muted_foreground)
+                .child(state.drum_rack.name.to_uppercase())
+        )
+        // Pad grid, lowest key at the bottom left like a hardware pad controller
+        .child(
+            v_flex()
+                .w_full()
+                .gap_1()
+                .children((0..rows).rev().map(|row| {
+                    h_flex()
+                        .w_full()
+                        .gap_1()
+                        .children((0..columns).map(|column| row * columns + column)
+                            .filter(|&index| index < state.drum_rack.pads.len())
+                            .map(|index| render_drum_pad(state, index, cx)))
+                }))
+        )
+        .child(render_pad_controls(state, cx))
+        .child(Divider::horizontal().my_1())
+        .child(
+            div()
+                .text_xs()
+                .text_color(cx.theme().muted_foreground.opacity(0.7))
+                .child("Drag a file onto a pad to load it")
+        )
+        .children(
+            state.filtered_files.iter().filter_map(|&idx| {
+                state.audio_files.get(idx).map(|file| {
+                    render_audio_file_item(file, idx, state, cx)
+                })
+            })
+        )
+}
+
+fn render_drum_pad(state: &DawUiState, index: usize, cx: &mut Context<DawPanel>) -> impl IntoElement {
+    let pad = &state.drum_rack.pads[index];
+    let is_selected = state.selected_pad == index;
+    let is_drop_target = matches!(state.drag_state, DragState::DraggingFile { .. });
+    let has_sample = pad.sample.is_some();
+    let label = if has_sample { pad.name.clone() } else { format!("{}", pad.key) };
+
+    v_flex()
+        .id(("drum-pad", index))
+        .flex_1()
+        .h(px(44.0))
+        .p_1()
+        .rounded_md()
+        .cursor_pointer()
+        .border_1()
+        .border_color(if is_selected { cx.theme().accent } else { cx.theme().border })
+        .bg(if has_sample { cx.theme().accent.opacity(0.2) } else { cx.theme().muted.opacity(0.1) })
+        .when(is_drop_target, |d| d.hover(|d| d.bg(cx.theme().accent.opacity(0.4))))
+        .on_mouse_down(gpui::MouseButton::Left, cx.listener(move |this, _event: &MouseDownEvent, _window, cx| {
+            this.state.selected_pad = index;
+            cx.notify();
+        }))
+        // Dropping a browser file loads it onto the pad
+        .on_mouse_up(gpui::MouseButton::Left, cx.listener(move |this, _event: &MouseUpEvent, _window, cx| {
+            if let DragState::DraggingFile { file_path, file_name } = this.state.drag_state.clone() {
+                tracing::error!("🥁 Loading '{}' onto pad {}", file_name, index + 1);
+                this.state.edit_pad(index, |pad| pad.assign_sample(file_path, None));
+                this.state.selected_pad = index;
+                this.state.drag_state = DragState::None;
+                cx.notify();
+            }
+        }))
+        .child(
+            div()
+                .text_xs()
+                .font_medium()
+                .text_color(if has_sample { cx.theme().foreground } else { cx.theme().muted_foreground })
+                .overflow_hidden()
+                .whitespace_nowrap()
+                .child(label)
+        )
+        .when_some(pad.choke_group, |d, group| {
+            d.child(
+                div()
+                    .text_xs()
+                    .text_color(cx.theme().yellow)
+                    .child(format!("Choke {}", group))
+            )
+        })
+}
+
+/// Volume, pan, pitch and choke group of the selected pad
+fn render_pad_controls(state: &DawUiState, cx: &mut Context<DawPanel>) -> impl IntoElement {
+    let index = state.selected_pad;
+    let Some(pad) = state.drum_rack.pads.get(index) else {
+        return div().into_any_element();
+    };
+    let pan = if pad.pan.abs() < 0.01 {
+        "C".to_string()
+    } else if pad.pan < 0.0 {
+        format!("L{:.0}", -pad.pan * 100.0)
+    } else {
+        format!("R{:.0}", pad.pan * 100.0)
+    };
+    let choke = pad.choke_group.map_or("Off".to_string(), |group| group.to_string());
+
+    let row = |label: &'static str, value: String, id: &'static str, down: fn(&mut DrumPad), up: fn(&mut DrumPad), cx: &mut Context<DawPanel>| {
+        h_flex()
+            .w_full()
+            .gap_1()
+            .items_center()
+            .child(div().w(px(44.0)).text_xs().text_color(cx.theme().muted_foreground).child(label))
+            .child(
+                Button::new((id, 0))
+                    .label("-")
+                    .ghost()
+                    .compact()
+                    .small()
+                    .on_click(cx.listener(move |this, _, _window, cx| {
+                        this.state.edit_pad(index, down);
+                        cx.notify();
+                    }))
+            )
+            .child(div().flex_1().text_xs().text_center().text_color(cx.theme().foreground).child(value))
+            .child(
+                Button::new((id, 1))
+                    .label("+")
+                    .ghost()
+                    .compact()
+                    .small()
+                    .on_click(cx.listener(move |this, _, _window, cx| {
+                        this.state.edit_pad(index, up);
+                        cx.notify();
+                    }))
+            )
+    };
+
+    v_flex()
+        .w_full()
+        .gap_1()
+        .child(
+            div()
+                .text_xs()
+                .font_semibold()
+                .text_color(cx.theme().foreground)
+                .child(format!("Pad {} · key {}", index + 1, pad.key))
+        )
+        .child(row("Volume", format!("{:+.0} dB", pad.volume_db), "pad-volume",
+            |pad| pad.volume_db = (pad.volume_db - 1.0).max(-60.0),
+            |pad| pad.volume_db = (pad.volume_db + 1.0).min(12.0), cx))
+        .child(row("Pan", pan, "pad-pan",
+            |pad| pad.pan = (pad.pan - 0.1).max(-1.0),
+            |pad| pad.pan = (pad.pan + 0.1).min(1.0), cx))
+        .child(row("Pitch", format!("{:+.0} st", pad.pitch), "pad-pitch",
+            |pad| pad.pitch = (pad.pitch - 1.0).max(-MAX_PAD_PITCH),
+            |pad| pad.pitch = (pad.pitch + 1.0).min(MAX_PAD_PITCH), cx))
+        .child(
+            h_flex()
+                .w_full()
+                .gap_1()
+                .child(
+                    Button::new("pad-choke")
+                        .label(format!("Choke: {}", choke))
+                        .ghost()
+                        .compact()
+                        .small()
+                        .tooltip("Pads in the same choke group cut each other off")
+                        .on_click(cx.listener(move |this, _, _window, cx| {
+                            this.state.edit_pad(index, |pad| pad.choke_group = pad.next_choke_group());
+                            cx.notify();
+                        }))
+                )
+                .child(
+                    Button::new("pad-clear")
+                        .label("Clear")
+                        .ghost()
+                        .compact()
+                        .small()
+                        .disabled(pad.sample.is_none())
+                        .on_click(cx.listener(move |this, _, _window, cx| {
+                            this.state.edit_pad(index, DrumPad::clear);
+                            cx.notify();
+                        }))
+                )
+        )
+        .into_any_element()
 }
 
 fn render_effects_tab(_state: &mut DawUiState, cx: &mut Context<DawPanel>) -> impl IntoElement {
@@ -1,13 +1,280 @@
 /// Clip Editor Component
-/// Detailed clip editing view
+/// Spectrogram of the selected clip's source file with the clip's bounds and
+/// fades drawn over it. A cursor placed on the spectrogram sets trim, fade
+/// and loop points precisely, down to a millisecond. Loop markers can also
+/// be dragged, and the loop auditioned.
 
 use super::state::*;
 use super::panel::DawPanel;
+use super::super::daw_engine::spectrogram::heat_color;
 use gpui::*;
 use gpui::prelude::FluentBuilder;
-use ui::{v_flex, StyledExt, ActiveTheme};
+use ui::{
+    button::*, h_flex, v_flex, Sizable, StyledExt, ActiveTheme,
+};
+
+/// Cells the spectrogram is drawn with
+const GRID_COLUMNS: usize = 160;
+const GRID_ROWS: usize = 48;
+
+/// Smallest fraction of the file the editor zooms in to
+const MIN_ZOOM: f64 = 1.0 / 4096.0;
 
 pub fn render_clip_editor(state: &DawUiState, cx: &mut Context<DawPanel>) -> impl IntoElement {
+    let Some((_, clip)) = state.edited_clip() else {
+        return render_message("Select a clip to edit it here", cx).into_any_element();
+    };
+    let Some(spectrogram) = state.spectrograms.get(&clip.asset_path).cloned() else {
+        return render_message(&format!("Analysing {}…", clip.name), cx).into_any_element();
+    };
+
+    let source_len = spectrogram.start + spectrogram.frames;
+    let (view_start, view_end) = state.clip_editor_view(clip, source_len);
+    let span = (view_end - view_start).max(1) as f64;
+    let fraction = |frame: SampleTime| ((frame as f64 - view_start as f64) / span).clamp(0.0, 1.0) as f32;
+    let sample_rate = spectrogram.sample_rate as f64;
+    let ms = (sample_rate / 1000.0).max(1.0) as i64;
+
+    let clip_start = clip.offset;
+    let clip_end = clip.offset + clip.source_duration();
+    let fade_in_end = clip_start + clip.timeline_to_source(clip.fade_in);
+    let fade_out_start = clip_end.saturating_sub(clip.timeline_to_source(clip.fade_out));
+    let cursor = state.clip_editor_cursor;
+    let dragging = state.clip_editor_drag;
+    // A dragged marker follows the cursor until it's dropped
+    let loop_marker = |edit: ClipCursorEdit, frame: SampleTime| match (dragging, cursor) {
+        (Some(dragged), Some(cursor)) if dragged == edit => cursor,
+        _ => frame,
+    };
+    let loop_points = clip.loop_points.map(|l| {
+        (loop_marker(ClipCursorEdit::LoopStart, l.start), loop_marker(ClipCursorEdit::LoopEnd, l.end))
+    });
+    let can_audition = clip.loop_timeline_range().is_some();
+
+    let nudge = move |id: &'static str, label: &'static str, frames: i64, cx: &mut Context<DawPanel>| {
+        Button::new(id)
+            .label(label)
+            .compact()
+            .small()
+            .ghost()
+            .disabled(cursor.is_none())
+            .on_click(cx.listener(move |this, _, _window, cx| {
+                if let Some(cursor) = this.state.clip_editor_cursor {
+                    this.state.clip_editor_cursor = Some((cursor as i64 + frames).clamp(0, source_len as i64) as SampleTime);
+                    cx.notify();
+                }
+            }))
+    };
+
+    let header = h_flex()
+        .w_full()
+        .px_3()
+        .py_2()
+        .gap_2()
+        .items_center()
+        .border_b_1()
+        .border_color(cx.theme().border)
+        .child(div().text_sm().font_semibold().child(clip.name.clone()))
+        .child(
+            div()
+                .text_xs()
+                .text_color(cx.theme().muted_foreground)
+                .child(match cursor {
+                    Some(frame) => format!("Cursor {:.3} s", frame as f64 / sample_rate),
+                    None => "Click the spectrogram to place the cursor".to_string(),
+                })
+        )
+        .child(nudge("clip-editor-back-10", "-10ms", -10 * ms, cx))
+        .child(nudge("clip-editor-back-1", "-1ms", -ms, cx))
+        .child(nudge("clip-editor-fwd-1", "+1ms", ms, cx))
+        .child(nudge("clip-editor-fwd-10", "+10ms", 10 * ms, cx))
+        .child(div().flex_1())
+        .children(ClipCursorEdit::ALL.iter().enumerate().map(|(i, &edit)| {
+            Button::new(("clip-editor-edit", i))
+                .label(edit.label())
+                .compact()
+                .small()
+                .ghost()
+                .disabled(cursor.is_none())
+                .on_click(cx.listener(move |this, _, _window, cx| {
+                    if let Some(track_id) = this.state.apply_clip_cursor_edit(edit) {
+                        this.sync_tracks_to_audio_service(vec![track_id], cx);
+                        cx.notify();
+                    }
+                }))
+        }))
+        .child(
+            Button::new("clip-editor-clear-loop")
+                .label("Clear Loop")
+                .compact()
+                .small()
+                .ghost()
+                .disabled(clip.loop_points.is_none())
+                .on_click(cx.listener(|this, _, _window, cx| {
+                    if let Some(track_id) = this.state.clear_edited_clip_loop() {
+                        this.sync_tracks_to_audio_service(vec![track_id], cx);
+                    }
+                    cx.notify();
+                }))
+        )
+        .child(
+            Button::new("clip-editor-audition")
+                .label("Audition Loop")
+                .compact()
+                .small()
+                .ghost()
+                .disabled(!can_audition)
+                .on_click(cx.listener(|this, _, _window, cx| {
+                    this.audition_clip_loop(cx);
+                }))
+        )
+        .child(
+            Button::new("clip-editor-zoom-out")
+                .label("-")
+                .compact()
+                .small()
+                .ghost()
+                .tooltip("Zoom Out")
+                .on_click(cx.listener(|this, _, _window, cx| {
+                    this.state.clip_editor_zoom = (this.state.clip_editor_zoom * 2.0).min(1.0);
+                    cx.notify();
+                }))
+        )
+        .child(
+            Button::new("clip-editor-zoom-in")
+                .label("+")
+                .compact()
+                .small()
+                .ghost()
+                .tooltip("Zoom In")
+                .on_click(cx.listener(|this, _, _window, cx| {
+                    this.state.clip_editor_zoom = (this.state.clip_editor_zoom / 2.0).max(MIN_ZOOM);
+                    cx.notify();
+                }))
+        );
+
+    // One clickable column of cells per grid column, high frequencies on top
+    let grid = spectrogram.grid(view_start, view_end, GRID_COLUMNS, GRID_ROWS);
+    let columns = (0..GRID_COLUMNS).map(|column| {
+        let frame = view_start + (span * (column as f64 + 0.5) / GRID_COLUMNS as f64) as SampleTime;
+        v_flex()
+            .id(("clip-editor-column", column))
+            .flex_1()
+            .h_full()
+            .children(grid.iter().map(|row| {
+                let [r, g, b] = heat_color(row[column], spectrogram.min_db);
+                div().flex_1().w_full().bg(Hsla::from(Rgba { r, g, b, a: 1.0 }))
+            }))
+            .on_mouse_down(MouseButton::Left, cx.listener(move |this, _: &MouseDownEvent, _window, cx| {
+                this.state.clip_editor_cursor = Some(frame);
+                cx.notify();
+            }))
+            .on_mouse_move(cx.listener(move |this, _: &MouseMoveEvent, _window, cx| {
+                if this.state.clip_editor_drag.is_some() && this.state.clip_editor_cursor != Some(frame) {
+                    this.state.clip_editor_cursor = Some(frame);
+                    cx.notify();
+                }
+            }))
+            .on_mouse_up(MouseButton::Left, cx.listener(move |this, _: &MouseUpEvent, _window, cx| {
+                if let Some(edit) = this.state.clip_editor_drag.take() {
+                    this.state.clip_editor_cursor = Some(frame);
+                    if let Some(track_id) = this.state.apply_clip_cursor_edit(edit) {
+                        this.sync_tracks_to_audio_service(vec![track_id], cx);
+                    }
+                    cx.notify();
+                }
+            }))
+    });
+
+    let shade = |from: f32, to: f32, color: Hsla| {
+        div()
+            .absolute()
+            .top_0()
+            .bottom_0()
+            .left(relative(from))
+            .w(relative((to - from).max(0.0)))
+            .bg(color)
+    };
+
+    // Loop markers with a handle wide enough to grab
+    let marker = |id: &'static str, edit: ClipCursorEdit, frame: SampleTime, cx: &mut Context<DawPanel>| {
+        div()
+            .id(id)
+            .absolute()
+            .top_0()
+            .bottom_0()
+            .left(relative(fraction(frame)))
+            .ml(px(-3.0))
+            .w(px(7.0))
+            .cursor_ew_resize()
+            .child(div().mx_auto().w(px(1.0)).h_full().bg(cx.theme().green))
+            .on_mouse_down(MouseButton::Left, cx.listener(move |this, _: &MouseDownEvent, _window, cx| {
+                cx.stop_propagation();
+                this.state.clip_editor_drag = Some(edit);
+                this.state.clip_editor_cursor = Some(frame);
+                cx.notify();
+            }))
+    };
+    let visible = |frame: SampleTime| (view_start..=view_end).contains(&frame);
+
+    let spectrum = div()
+        .relative()
+        .flex_1()
+        .w_full()
+        .child(h_flex().size_full().children(columns))
+        // Audio outside the clip is dimmed, fades are tinted
+        .child(shade(0.0, fraction(clip_start), gpui::black().opacity(0.6)))
+        .child(shade(fraction(clip_end), 1.0, gpui::black().opacity(0.6)))
+        .child(shade(fraction(clip_start), fraction(fade_in_end), cx.theme().accent.opacity(0.25)))
+        .child(shade(fraction(fade_out_start), fraction(clip_end), cx.theme().accent.opacity(0.25)))
+        .when_some(loop_points, |d, (start, end)| {
+            d.child(shade(fraction(start), fraction(end), cx.theme().green.opacity(0.15)))
+                .when(visible(start), |d| d.child(marker("clip-editor-loop-start", ClipCursorEdit::LoopStart, start, cx)))
+                .when(visible(end), |d| d.child(marker("clip-editor-loop-end", ClipCursorEdit::LoopEnd, end, cx)))
+        })
+        .when_some(cursor.filter(|&frame| visible(frame)), |d, frame| {
+            d.child(
+                div()
+                    .absolute()
+                    .top_0()
+                    .bottom_0()
+                    .left(relative(fraction(frame)))
+                    .w(px(1.0))
+                    .bg(cx.theme().foreground)
+            )
+        });
+
+    let axis = h_flex()
+        .w_full()
+        .px_3()
+        .py_1()
+        .justify_between()
+        .text_xs()
+        .text_color(cx.theme().muted_foreground)
+        .child(format!("{:.3} s", view_start as f64 / sample_rate))
+        .child(format!("{:.0} Hz – {:.0} Hz", spectrogram.min_freq, spectrogram.max_freq))
+        .child(format!("{:.3} s", view_end as f64 / sample_rate));
+
+    v_flex()
+        .size_full()
+        .bg(cx.theme().background)
+        .child(header)
+        .when_some(state.clip_editor_error.clone(), |d, error| {
+            d.child(
+                div()
+                    .px_3()
+                    .py_1()
+                    .text_xs()
+                    .text_color(cx.theme().danger)
+                    .child(error)
+            )
+        })
+        .child(spectrum)
+        .child(axis)
+        .into_any_element()
+}
+
+fn render_message(message: &str, cx: &mut Context<DawPanel>) -> impl IntoElement {
     v_flex()
         .size_full()
         .items_center()
@@ -16,6 +283,6 @@ pub fn render_clip_editor(state: &DawUiState, cx: &mut Context<DawPanel>) -> imp
             div()
                 .text_sm()
                 .text_color(cx.theme().muted_foreground)
-                .child("Clip Editor View")
+                .child(message.to_string())
         )
 }
@@ -0,0 +1,96 @@
+/// Clip Export Dialog
+/// Progress for a batch export of selected clips or marker regions, with a
+/// cancel button while it runs and the result once it stops
+
+use super::state::*;
+use super::panel::DawPanel;
+use gpui::*;
+use ui::{
+    button::*, h_flex, v_flex, Sizable, StyledExt, ActiveTheme,
+};
+use std::sync::atomic::Ordering;
+
+pub fn render_clip_export_dialog(state: &DawUiState, cx: &mut Context<DawPanel>) -> impl IntoElement {
+    let Some(ref export) = state.clip_export else {
+        return div().into_any_element();
+    };
+    let fraction = if export.total == 0 { 1.0 } else { export.done as f32 / export.total as f32 };
+    let cancelling = export.summary.is_none() && export.cancel.load(Ordering::Relaxed);
+    let status = match export.summary {
+        Some(ref summary) => summary.clone(),
+        None if cancelling => format!("Cancelling after {} {} of {}…", export.item, (export.done + 1).min(export.total), export.total),
+        None => format!("Exporting {} {} of {}…", export.item, (export.done + 1).min(export.total), export.total),
+    };
+
+    let button = match export.summary {
+        Some(_) => Button::new("clip-export-close")
+            .label("Close")
+            .small()
+            .primary()
+            .on_click(cx.listener(|this, _, _window, cx| {
+                this.state.clip_export = None;
+                cx.notify();
+            })),
+        None => Button::new("clip-export-cancel")
+            .label(if cancelling { "Cancelling…" } else { "Cancel" })
+            .small()
+            .ghost()
+            .on_click(cx.listener(|this, _, _window, cx| {
+                if let Some(ref export) = this.state.clip_export {
+                    export.cancel.store(true, Ordering::Relaxed);
+                }
+                cx.notify();
+            })),
+    };
+
+    // Backdrop swallows clicks so the project can't change mid-export
+    div()
+        .id("clip-export-backdrop")
+        .absolute()
+        .inset_0()
+        .flex()
+        .items_center()
+        .justify_center()
+        .bg(gpui::black().opacity(0.4))
+        .on_mouse_down(MouseButton::Left, |_, _, cx| cx.stop_propagation())
+        .child(
+            v_flex()
+                .w(px(360.0))
+                .p_4()
+                .gap_3()
+                .bg(cx.theme().background)
+                .rounded_lg()
+                .border_1()
+                .border_color(cx.theme().border)
+                .shadow_lg()
+                .child(
+                    div()
+                        .text_sm()
+                        .font_semibold()
+                        .text_color(cx.theme().foreground)
+                        .child(export.title)
+                )
+                .child(
+                    div()
+                        .w_full()
+                        .h(px(6.0))
+                        .rounded_sm()
+                        .bg(cx.theme().muted)
+                        .child(
+                            div()
+                                .h_full()
+                                .w(relative(fraction))
+                                .rounded_sm()
+                                .bg(cx.theme().primary)
+                        )
+                )
+                .child(
+                    div()
+                        .text_xs()
+                        .text_color(cx.theme().muted_foreground)
+                        .child(status)
+                )
+                .child(h_flex().w_full().justify_end().child(button))
+        )
+        .into_any_element()
+}
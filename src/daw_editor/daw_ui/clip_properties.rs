@@ -0,0 +1,394 @@
+/// Clip Properties Dialog
+/// Modal for typing exact clip start, length, source offset and loop points,
+/// choosing how the clip follows tempo changes, and detecting a loop's tempo
+/// from its length
+
+use super::state::*;
+use super::panel::DawPanel;
+use super::super::daw_engine::audio_types::TempoMode;
+use gpui::*;
+use gpui::prelude::FluentBuilder;
+use ui::{
+    button::*, h_flex, v_flex, Sizable, StyledExt, ActiveTheme, Disableable,
+};
+
+/// Most bars a loop can be set to span for tempo detection
+const MAX_LOOP_BARS: u32 = 64;
+
+pub fn render_clip_properties_dialog(state: &mut DawUiState, cx: &mut Context<DawPanel>) -> impl IntoElement {
+    let transport = state.project.as_ref().map(|p| p.transport.clone()).unwrap_or_default();
+    let Some(dialog) = state.clip_properties.as_ref() else {
+        return div().into_any_element();
+    };
+
+    let show_bars_beats = dialog.show_bars_beats;
+    let loop_enabled = dialog.draft.loop_points.is_some();
+    let source_info = match dialog.source_len {
+        Some(len) => format!("Source: {} samples", len),
+        None => "Source not loaded, buffer length unchecked".to_string(),
+    };
+    let error = dialog.error.clone();
+    let can_apply = dialog.validate().is_ok();
+
+    // Clips without their own choice show the default they'd get
+    let tempo_mode = dialog.draft.effective_tempo_mode();
+    let tempo_buttons: Vec<AnyElement> = TempoMode::ALL
+        .iter()
+        .map(|mode| {
+            let mode = *mode;
+            Button::new(ElementId::Name(format!("clip-tempo-mode-{}", mode.label()).into()))
+                .label(mode.label())
+                .compact()
+                .small()
+                .when(tempo_mode == mode, |b| b.primary())
+                .when(tempo_mode != mode, |b| b.ghost())
+                .tooltip(match mode {
+                    TempoMode::Fixed => "Ignore tempo changes",
+                    TempoMode::Repitch => "Follow tempo by changing speed and pitch",
+                    TempoMode::Stretch => "Follow tempo and keep the pitch",
+                })
+                .on_click(cx.listener(move |this, _, _window, cx| {
+                    if let Some(ref mut dialog) = this.state.clip_properties {
+                        dialog.draft.tempo_mode = Some(mode);
+                    }
+                    cx.notify();
+                }))
+                .into_any_element()
+        })
+        .collect();
+
+    // Tempo the loop was recorded at, from its length and bar count
+    let loop_bars = dialog.detected_bars(&transport);
+    let loop_tempo = dialog.detected_tempo(&transport);
+    let project_tempo = transport.tempo;
+    let loop_tempo_label = match (loop_bars, loop_tempo) {
+        (Some(bars), Some(tempo)) => format!("{} bar{} = {:.1} BPM", bars, if bars == 1 { "" } else { "s" }, tempo),
+        _ => "No tempo detected".to_string(),
+    };
+
+    let rows: Vec<AnyElement> = ClipProperty::ALL
+        .iter()
+        .map(|property| render_property_row(dialog, *property, &transport, cx).into_any_element())
+        .collect();
+
+    // Backdrop swallows clicks so the timeline underneath isn't edited
+    div()
+        .id("clip-properties-backdrop")
+        .absolute()
+        .inset_0()
+        .flex()
+        .items_center()
+        .justify_center()
+        .bg(gpui::black().opacity(0.4))
+        .on_mouse_down(MouseButton::Left, |_, _, cx| cx.stop_propagation())
+        .child(
+            v_flex()
+                .w(px(360.0))
+                .p_4()
+                .gap_3()
+                .bg(cx.theme().background)
+                .rounded_lg()
+                .border_1()
+                .border_color(cx.theme().border)
+                .shadow_lg()
+                // Title and unit toggle
+                .child(
+                    h_flex()
+                        .w_full()
+                        .items_center()
+                        .justify_between()
+                        .child(
+                            v_flex()
+                                .child(
+                                    div()
+                                        .text_sm()
+                                        .font_semibold()
+                                        .text_color(cx.theme().foreground)
+                                        .child(format!("Clip Properties: {}", dialog.draft.name))
+                                )
+                                .child(
+                                    div()
+                                        .text_xs()
+                                        .text_color(cx.theme().muted_foreground)
+                                        .child(source_info)
+                                )
+                        )
+                        .child(
+                            Button::new("clip-properties-units")
+                                .label(if show_bars_beats { "Bars:Beats" } else { "Samples" })
+                                .compact()
+                                .small()
+                                .ghost()
+                                .tooltip("Toggle between samples and bars:beats:ticks")
+                                .on_click(cx.listener(|this, _, _window, cx| {
+                                    if let Some(ref mut dialog) = this.state.clip_properties {
+                                        dialog.cancel_edit();
+                                        dialog.show_bars_beats = !dialog.show_bars_beats;
+                                    }
+                                    cx.notify();
+                                }))
+                        )
+                )
+                .child(v_flex().w_full().gap_1().children(rows))
+                .child(
+                    Button::new("clip-properties-loop")
+                        .label(if loop_enabled { "Remove Loop" } else { "Add Loop" })
+                        .compact()
+                        .small()
+                        .ghost()
+                        .on_click(cx.listener(move |this, _, _window, cx| {
+                            if let Some(ref mut dialog) = this.state.clip_properties {
+                                dialog.cancel_edit();
+                                dialog.set_loop_enabled(!loop_enabled);
+                            }
+                            cx.notify();
+                        }))
+                )
+                .child(
+                    h_flex()
+                        .w_full()
+                        .gap_1()
+                        .items_center()
+                        .child(
+                            div()
+                                .w(px(90.0))
+                                .text_xs()
+                                .text_color(cx.theme().muted_foreground)
+                                .child("On tempo change")
+                        )
+                        .children(tempo_buttons)
+                )
+                .child(
+                    h_flex()
+                        .w_full()
+                        .gap_1()
+                        .items_center()
+                        .child(
+                            div()
+                                .w(px(90.0))
+                                .text_xs()
+                                .text_color(cx.theme().muted_foreground)
+                                .child("Loop tempo")
+                        )
+                        .child(
+                            Button::new("clip-loop-bars-down")
+                                .label("−")
+                                .ghost()
+                                .compact()
+                                .small()
+                                .disabled(!matches!(loop_bars, Some(bars) if bars > 1))
+                                .tooltip("Fewer bars")
+                                .on_click(cx.listener(move |this, _, _window, cx| {
+                                    if let Some(ref mut dialog) = this.state.clip_properties {
+                                        dialog.loop_bars = loop_bars.map(|bars| bars.saturating_sub(1).max(1));
+                                    }
+                                    cx.notify();
+                                }))
+                        )
+                        .child(
+                            div()
+                                .flex_1()
+                                .text_xs()
+                                .text_center()
+                                .text_color(cx.theme().foreground)
+                                .child(loop_tempo_label)
+                        )
+                        .child(
+                            Button::new("clip-loop-bars-up")
+                                .label("+")
+                                .ghost()
+                                .compact()
+                                .small()
+                                .tooltip("More bars")
+                                .on_click(cx.listener(move |this, _, _window, cx| {
+                                    if let Some(ref mut dialog) = this.state.clip_properties {
+                                        dialog.loop_bars = Some(loop_bars.map_or(1, |bars| (bars + 1).min(MAX_LOOP_BARS)));
+                                    }
+                                    cx.notify();
+                                }))
+                        )
+                )
+                .child(
+                    h_flex()
+                        .w_full()
+                        .gap_1()
+                        .justify_end()
+                        .child(
+                            Button::new("clip-loop-guess")
+                                .label("Guess")
+                                .ghost()
+                                .compact()
+                                .small()
+                                .tooltip("Guess the bar count from common loop lengths")
+                                .on_click(cx.listener(|this, _, _window, cx| {
+                                    if let Some(ref mut dialog) = this.state.clip_properties {
+                                        dialog.loop_bars = None;
+                                    }
+                                    cx.notify();
+                                }))
+                        )
+                        .child(
+                            Button::new("clip-loop-set-tempo")
+                                .label("Set Project Tempo")
+                                .ghost()
+                                .compact()
+                                .small()
+                                .disabled(loop_tempo.is_none())
+                                .tooltip("Change the project tempo to the loop's and play it unstretched")
+                                .on_click(cx.listener(|this, _, _window, cx| {
+                                    this.set_tempo_from_clip(cx);
+                                }))
+                        )
+                        .child(
+                            Button::new("clip-loop-fit")
+                                .label("Fit to Project")
+                                .ghost()
+                                .compact()
+                                .small()
+                                .disabled(loop_tempo.is_none())
+                                .tooltip(format!("Stretch the clip to {:.1} BPM", project_tempo))
+                                .on_click(cx.listener(move |this, _, _window, cx| {
+                                    if let (Some(ref mut dialog), Some(tempo)) = (&mut this.state.clip_properties, loop_tempo) {
+                                        dialog.cancel_edit();
+                                        dialog.draft.fit_to_tempo(tempo, project_tempo);
+                                    }
+                                    cx.notify();
+                                }))
+                        )
+                )
+                .when_some(error, |this, error| {
+                    this.child(
+                        div()
+                            .text_xs()
+                            .text_color(cx.theme().danger)
+                            .child(error)
+                    )
+                })
+                // Actions
+                .child(
+                    h_flex()
+                        .w_full()
+                        .gap_2()
+                        .justify_end()
+                        .child(
+                            Button::new("clip-properties-cancel")
+                                .label("Cancel")
+                                .small()
+                                .ghost()
+                                .on_click(cx.listener(|this, _, _window, cx| {
+                                    this.state.clip_properties = None;
+                                    cx.notify();
+                                }))
+                        )
+                        .child(
+                            Button::new("clip-properties-apply")
+                                .label("Apply")
+                                .small()
+                                .primary()
+                                .disabled(!can_apply)
+                                .on_click(cx.listener(|this, _, _window, cx| {
+                                    this.apply_clip_properties(cx);
+                                }))
+                        )
+                )
+        )
+        .into_any_element()
+}
+
+fn render_property_row(
+    dialog: &ClipPropertiesDialog,
+    property: ClipProperty,
+    transport: &super::super::daw_engine::audio_types::Transport,
+    cx: &mut Context<DawPanel>,
+) -> impl IntoElement {
+    let is_editing = dialog.editing == Some(property);
+    let text = if is_editing {
+        format!("{}|", dialog.input)
+    } else {
+        dialog.format_value(property, transport)
+    };
+    let is_loop_field = matches!(property, ClipProperty::LoopStart | ClipProperty::LoopEnd);
+    let is_disabled = is_loop_field && dialog.draft.loop_points.is_none();
+
+    h_flex()
+        .w_full()
+        .gap_2()
+        .items_center()
+        .child(
+            div()
+                .w(px(110.0))
+                .text_xs()
+                .text_color(cx.theme().muted_foreground)
+                .child(property.label())
+        )
+        .child(
+            div()
+                .id(ElementId::Name(format!("clip-property-{:?}", property).into()))
+                .flex_1()
+                .h(px(26.0))
+                .px_2()
+                .flex()
+                .items_center()
+                .rounded_md()
+                .border_1()
+                .border_color(if is_editing {
+                    cx.theme().accent
+                } else {
+                    cx.theme().border
+                })
+                .bg(if is_editing {
+                    cx.theme().accent.opacity(0.1)
+                } else {
+                    cx.theme().muted.opacity(0.2)
+                })
+                .text_sm()
+                .font_family("monospace")
+                .text_color(if is_disabled {
+                    cx.theme().muted_foreground
+                } else {
+                    cx.theme().foreground
+                })
+                .when(!is_disabled, |d| {
+                    d.cursor_text().on_mouse_down(MouseButton::Left, cx.listener(move |this, _event: &MouseDownEvent, _window, cx| {
+                        let transport = this.state.project.as_ref().map(|p| p.transport.clone()).unwrap_or_default();
+                        if let Some(ref mut dialog) = this.state.clip_properties {
+                            // Clicking another field commits the one being typed in
+                            if dialog.commit_edit(&transport) {
+                                dialog.begin_edit(property, &transport);
+                            }
+                        }
+                        cx.notify();
+                    }))
+                })
+                .child(text)
+        )
+}
+
+/// Route a key press to the field being edited. Returns true if the dialog
+/// consumed the key.
+pub fn handle_dialog_key(state: &mut DawUiState, keystroke: &Keystroke) -> bool {
+    let transport = state.project.as_ref().map(|p| p.transport.clone()).unwrap_or_default();
+    let Some(ref mut dialog) = state.clip_properties else {
+        return false;
+    };
+
+    match keystroke.key.as_str() {
+        "escape" if dialog.editing.is_some() => dialog.cancel_edit(),
+        "escape" => state.clip_properties = None,
+        "enter" => {
+            dialog.commit_edit(&transport);
+        }
+        "tab" => dialog.edit_next(&transport),
+        "backspace" => {
+            dialog.input.pop();
+        }
+        _ if dialog.editing.is_some() => {
+            let typed = keystroke.key_char.as_deref().unwrap_or(keystroke.key.as_str());
+            if typed.len() == 1 && typed.chars().all(|c| c.is_ascii_digit() || matches!(c, ':' | '.' | '-')) {
+                dialog.input.push_str(typed);
+            }
+        }
+        _ => {}
+    }
+    true
+}
@@ -117,8 +117,114 @@ fn render_track_inspector(state: &DawUiState, cx: &mut Context<DawPanel>) -> imp
     }
 }
 
-fn render_clip_inspector(_state: &DawUiState, cx: &mut Context<DawPanel>) -> impl IntoElement {
-    render_empty_inspector("No Clip Selected", cx)
+fn render_clip_inspector(state: &DawUiState, cx: &mut Context<DawPanel>) -> impl IntoElement {
+    let selected = state.selected_clip_refs().into_iter().next()
+        .and_then(|(track_id, clip_id)| state.get_clip(track_id, clip_id).map(|clip| (track_id, clip)));
+    let Some((track_id, clip)) = selected else {
+        return render_empty_inspector("No Clip Selected", cx).into_any_element();
+    };
+
+    let clip_id = clip.id;
+    let track_locked = state.get_track(track_id).is_some_and(|t| t.locked);
+    let renaming = state.clip_rename.as_ref().filter(|r| r.clip == clip_id);
+    let name = match renaming {
+        Some(rename) => format!("{}|", rename.text),
+        None => clip.display_name(),
+    };
+
+    v_flex()
+        .w_full()
+        .p_4()
+        .gap_2()
+        .child(
+            div()
+                .text_sm()
+                .font_semibold()
+                .child("Clip Properties")
+        )
+        .child(
+            h_flex()
+                .gap_2()
+                .child(
+                    // Type straight into the name once renaming
+                    div()
+                        .id("clip-inspector-name")
+                        .flex_1()
+                        .min_w_0()
+                        .px_2()
+                        .py_1()
+                        .rounded_sm()
+                        .border_1()
+                        .border_color(if renaming.is_some() { cx.theme().accent } else { cx.theme().border })
+                        .overflow_hidden()
+                        .whitespace_nowrap()
+                        .text_ellipsis()
+                        .text_xs()
+                        .child(name)
+                        .on_mouse_down(MouseButton::Left, cx.listener(move |this, _event: &MouseDownEvent, _window, cx| {
+                            cx.stop_propagation();
+                            this.state.begin_clip_rename(track_id, clip_id);
+                            cx.notify();
+                        }))
+                )
+                .child(
+                    Button::new("clip-inspector-rename")
+                        .label(if renaming.is_some() { "Done" } else { "Rename" })
+                        .compact()
+                        .small()
+                        .ghost()
+                        .on_click(cx.listener(move |this, _, _window, cx| {
+                            if this.state.clip_rename.is_some() {
+                                this.state.commit_clip_rename();
+                            } else {
+                                this.state.begin_clip_rename(track_id, clip_id);
+                            }
+                            cx.notify();
+                        }))
+                )
+        )
+        .child(
+            div()
+                .text_xs()
+                .text_color(cx.theme().muted_foreground)
+                .overflow_hidden()
+                .whitespace_nowrap()
+                .text_ellipsis()
+                .child(format!("Source: {}", clip.asset_path.display()))
+        )
+        .children(state.asset_health(&clip.asset_path).into_iter().map(|line| {
+            div()
+                .text_xs()
+                .text_color(cx.theme().danger)
+                .child(format!("⚠️ {}", line))
+        }))
+        .child(
+            h_flex()
+                .gap_2()
+                .items_center()
+                .child(
+                    Button::new("clip-inspector-lock")
+                        .label("Lock")
+                        .compact()
+                        .small()
+                        .when(clip.locked, |b| b.primary())
+                        .when(!clip.locked, |b| b.ghost())
+                        .disabled(track_locked)
+                        .on_click(cx.listener(move |this, _, _window, cx| {
+                            this.state.toggle_selected_clips_lock();
+                            cx.notify();
+                        }))
+                )
+                .when(track_locked, |d| {
+                    d.child(
+                        div()
+                            .text_xs()
+                            .text_color(cx.theme().muted_foreground)
+                            .child("Locked with its track")
+                    )
+                })
+        )
+        .into_any_element()
 }
 
 fn render_automation_inspector(_state: &DawUiState, cx: &mut Context<DawPanel>) -> impl IntoElement {
@@ -22,22 +22,13 @@ pub fn render_add_channel_button(cx: &mut Context<DawPanel>) -> impl IntoElement
         .on_mouse_down(MouseButton::Left, cx.listener(|panel, _event: &MouseDownEvent, _window, cx| {
             // Add a new track with sync to audio service
             if let Some(ref mut project) = panel.state.project {
-                let new_track_id = uuid::Uuid::new_v4();
-                let new_track = Track {
-                    id: new_track_id,
-                    name: format!("Track {}", project.tracks.len() + 1),
-                    track_type: super::super::super::audio_types::TrackType::Audio,
-                    volume: 1.0,
-                    pan: 0.0,
-                    muted: false,
-                    solo: false,
-                    record_armed: false,
-                    clips: Vec::new(),
-                    sends: Vec::new(),
-                    automation: Vec::new(),
-                    color: [0.5, 0.5, 0.8],
-                };
+                let mut new_track = Track::new(
+                    format!("Track {}", project.tracks.len() + 1),
+                    super::super::super::daw_engine::audio_types::TrackType::Audio,
+                );
+                new_track.color = [0.5, 0.5, 0.8];
                 project.tracks.push(new_track.clone());
+                panel.state.dirty.mark_dirty();
 
                 // Sync to audio service
                 if let Some(ref service) = panel.state.audio_service {
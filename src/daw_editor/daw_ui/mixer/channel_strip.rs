@@ -5,7 +5,13 @@ use ui::{
     h_virtual_list, scroll::{Scrollbar, ScrollbarAxis},
 };
 use super::super::DawPanel;
-use super::{Track, DawUiState};
+use super::{Track, DawUiState, TrackId};
+use crate::daw_editor::daw_engine::audio_types::TrackType;
+use crate::daw_editor::daw_engine::quick_filters::{format_filter_frequency, MAX_FILTER_HZ, MIN_FILTER_HZ};
+
+/// Corners the quick filter buttons step through, starting fully open
+const HIGH_PASS_STEPS: [f32; 6] = [MIN_FILTER_HZ, 40.0, 80.0, 120.0, 200.0, 400.0];
+const LOW_PASS_STEPS: [f32; 6] = [MAX_FILTER_HZ, 16_000.0, 12_000.0, 8_000.0, 5_000.0, 3_000.0];
 
 pub fn render_channel_strip(
     track: &Track,
@@ -16,13 +22,17 @@ pub fn render_channel_strip(
     let is_selected = state.selection.selected_track_ids.contains(&track.id);
     let is_muted = track.muted || state.is_track_effectively_muted(track.id);
     let track_id = track.id;
+    let narrow = state.mixer_narrow;
+    // A VCA carries no audio, so it gets none of the controls on the signal path
+    let is_vca = track.track_type == TrackType::Vca;
 
-    // Beautiful color per track with golden ratio
-    let track_hue = (idx as f32 * 137.5) % 360.0;
-    let track_color = hsla(track_hue / 360.0, 0.7, 0.5, 1.0);
+    let track_color = state.track_color_at(idx);
+    let surround_layout = state.project.as_ref()
+        .map(|p| p.channel_layout)
+        .filter(|layout| layout.is_surround());
 
     v_flex()
-        .w(px(90.0))
+        .w(px(state.mixer_strip_width()))
         .h_full()
         .gap_1()
         .p_2()
@@ -50,7 +60,9 @@ pub fn render_channel_strip(
                 .shadow_lg()
         })
         .on_mouse_down(MouseButton::Left, cx.listener(move |panel, _event: &MouseDownEvent, _window, cx| {
-            panel.state.select_track(track_id, false);
+            panel.select_track(track_id, false, cx);
+            // Already in view, no need to scroll to it
+            panel.state.mixer_reveal = None;
             cx.notify();
         }))
         // Track color indicator at top with gradient
@@ -104,6 +116,7 @@ pub fn render_channel_strip(
                             if let Some(ref mut project) = panel.state.project {
                                 if let Some(track) = project.tracks.iter_mut().find(|t| t.id == track_id) {
                                     track.muted = !track.muted;
+                                    panel.state.dirty.mark_dirty();
 
                                     // Sync to audio service
                                     if let Some(ref service) = panel.state.audio_service {
@@ -132,6 +145,7 @@ pub fn render_channel_strip(
                             if let Some(ref mut project) = panel.state.project {
                                 if let Some(track) = project.tracks.iter_mut().find(|t| t.id == track_id) {
                                     track.solo = !track.solo;
+                                    panel.state.dirty.mark_dirty();
 
                                     // Sync to audio service
                                     if let Some(ref service) = panel.state.audio_service {
@@ -146,7 +160,7 @@ pub fn render_channel_strip(
                             cx.notify();
                         }))
                 )
-                .child(
+                .when(!is_vca, |d| d.child(
                     // Record Arm button
                     Button::new(ElementId::Name(format!("record-arm-{}", track_id).into()))
                         .icon(Icon::new(IconName::Circle).size_3())
@@ -160,22 +174,61 @@ pub fn render_channel_strip(
                             if let Some(ref mut project) = panel.state.project {
                                 if let Some(track) = project.tracks.iter_mut().find(|t| t.id == track_id) {
                                     track.record_armed = !track.record_armed;
+                                    panel.state.dirty.mark_dirty();
                                 }
                             }
                             cx.notify();
                         }))
-                )
+                ))
         )
-        // Output routing dropdown
-        .child(super::output_routing::render_output_routing(track, track_id, cx))
+        // Buses and VCAs can spill the tracks that feed them
+        .when(track.track_type.is_bus() || is_vca, |d| d.child(render_spill_button(track_id, is_vca, state, cx)))
+        .when(!is_vca && !state.vca_track_ids().is_empty(), |d| d.child(render_vca_button(track, track_id, state, cx)))
+        // Narrow strips leave out everything but pan, meter and fader
+        .when(!narrow && !is_vca, |d| {
+            d
+                // Polarity invert per channel
+                .child(
+                    h_flex()
+                        .w_full()
+                        .gap_0p5()
+                        .child(render_phase_button(track, track_id, false, cx))
+                        .child(render_phase_button(track, track_id, true, cx))
+                )
+                // Quick high-pass and low-pass filters
+                .child(
+                    h_flex()
+                        .w_full()
+                        .gap_0p5()
+                        .child(render_quick_filter_button(track, track_id, true, cx))
+                        .child(render_quick_filter_button(track, track_id, false, cx))
+                )
+                // Solo safe and solo isolate
+                .child(
+                    h_flex()
+                        .w_full()
+                        .gap_0p5()
+                        .child(render_solo_flag_button(track, track_id, false, cx))
+                        .child(render_solo_flag_button(track, track_id, true, cx))
+                )
+                // Output routing dropdown
+                .child(super::output_routing::render_output_routing(track, track_id, cx))
+        })
         // Pan control with visual feedback
-        .child(super::pan_control::render_pan_control(track, track_id, cx))
-        // Insert slots (3 effect slots)
-        .child(super::insert_slots::render_insert_slots(track, cx))
-        // Send levels (A and B with pre/post toggle)
-        .child(super::send_controls::render_send_controls(track, track_id, cx))
+        .when(!is_vca, |d| d.child(super::pan_control::render_pan_control(track, track_id, cx)))
+        .when(!narrow && !is_vca, |d| {
+            d
+                // Surround position, for projects with a surround master
+                .when_some(surround_layout, |d, layout| {
+                    d.child(super::surround_panner::render_surround_panner(track, track_id, layout, cx))
+                })
+                // Insert slots (3 effect slots)
+                .child(super::insert_slots::render_insert_slots(track, cx))
+                // Send levels (A and B with pre/post toggle)
+                .child(super::send_controls::render_send_controls(track, track_id, state, cx))
+        })
         // Peak meter LEDs with smooth animation
-        .child(super::peak_meters::render_peak_meters(track, state, cx))
+        .when(!is_vca, |d| d.child(super::peak_meters::render_peak_meters(track, state, cx)))
         // Vertical output fader slider
         .child(super::fader_slider::render_fader_slider(track, track_id, cx))
         // Volume readout with dB display
@@ -193,6 +246,161 @@ pub fn render_channel_strip(
                 } else {
                     cx.theme().foreground
                 })
-                .child(format!("{:+.1} dB", track.volume_db()))
+                .child(super::fader_slider::render_fader_readout(track_id, track.volume, state, cx))
         )
-}
\ No newline at end of file
+}
+
+/// Shows only this bus and the tracks sending to it, or this VCA and the
+/// tracks on it, or every channel again
+fn render_spill_button(track_id: TrackId, is_vca: bool, state: &DawUiState, cx: &mut Context<DawPanel>) -> impl IntoElement {
+    let spilled = state.mixer_spill == Some(track_id);
+    let feeders = state.bus_feeders(track_id).len();
+
+    Button::new(ElementId::Name(format!("spill-{}", track_id).into()))
+        .label(if spilled { "Collapse".to_string() } else { format!("Spill {}", feeders) })
+        .compact()
+        .small()
+        .w_full()
+        .when(spilled, |b| b.primary())
+        .when(!spilled, |b| b.ghost())
+        .disabled(feeders == 0 && !spilled)
+        .tooltip(if is_vca { "Show only the tracks on this VCA" } else { "Show only the tracks sending to this bus" })
+        .on_click(cx.listener(move |panel, _, _window, cx| {
+            panel.state.toggle_mixer_spill(track_id);
+            cx.notify();
+        }))
+}
+
+/// The VCA riding this track. Click for the next VCA, the selected tracks
+/// moving together when this is one of them.
+fn render_vca_button(track: &Track, track_id: TrackId, state: &DawUiState, cx: &mut Context<DawPanel>) -> impl IntoElement {
+    let vca_name = track.vca
+        .and_then(|id| state.get_track(id))
+        .map(|vca| vca.name.clone());
+
+    Button::new(ElementId::Name(format!("vca-{}", track_id).into()))
+        .label(vca_name.clone().unwrap_or_else(|| "No VCA".to_string()))
+        .compact()
+        .small()
+        .w_full()
+        .when(vca_name.is_some(), |b| b.primary())
+        .when(vca_name.is_none(), |b| b.ghost())
+        .tooltip("VCA group: its fader, mute and automation ride this track. Click for the next one")
+        .on_click(cx.listener(move |panel, _, _window, cx| {
+            panel.cycle_vca(track_id, cx);
+        }))
+}
+
+fn render_phase_button(track: &Track, track_id: TrackId, is_right: bool, cx: &mut Context<DawPanel>) -> impl IntoElement {
+    let active = if is_right { track.phase_invert.right } else { track.phase_invert.left };
+    let side = if is_right { "R" } else { "L" };
+
+    Button::new(ElementId::Name(format!("phase-{}-{}", side, track_id).into()))
+        .label(format!("Ø{}", side))
+        .compact()
+        .small()
+        .flex_1()
+        .when(active, |b| b.primary())
+        .when(!active, |b| b.ghost())
+        .tooltip(format!("Invert Polarity ({})", if is_right { "Right" } else { "Left" }))
+        .on_click(cx.listener(move |panel, _, _window, cx| {
+            if let Some(track) = panel.state.get_track_mut(track_id) {
+                if is_right {
+                    track.phase_invert.right = !track.phase_invert.right;
+                } else {
+                    track.phase_invert.left = !track.phase_invert.left;
+                }
+            }
+            panel.sync_tracks_to_audio_service(vec![track_id], cx);
+            cx.notify();
+        }))
+}
+
+fn render_quick_filter_button(track: &Track, track_id: TrackId, high_pass: bool, cx: &mut Context<DawPanel>) -> impl IntoElement {
+    let filters = track.quick_filters;
+    let (active, frequency, name, steps) = if high_pass {
+        (filters.high_pass_active(), filters.high_pass_hz, "HP", HIGH_PASS_STEPS)
+    } else {
+        (filters.low_pass_active(), filters.low_pass_hz, "LP", LOW_PASS_STEPS)
+    };
+    let tooltip = format!(
+        "{}: {}. Click for the next corner, right-click to open it fully",
+        if high_pass { "High-Pass" } else { "Low-Pass" },
+        if active { format_filter_frequency(frequency) } else { "Off".to_string() },
+    );
+
+    let set_frequency = move |panel: &mut DawPanel, frequency: f32, cx: &mut Context<DawPanel>| {
+        if let Some(track) = panel.state.get_track_mut(track_id) {
+            if high_pass {
+                track.quick_filters.high_pass_hz = frequency;
+            } else {
+                track.quick_filters.low_pass_hz = frequency;
+            }
+        }
+        panel.sync_tracks_to_audio_service(vec![track_id], cx);
+        cx.notify();
+    };
+
+    div()
+        .flex_1()
+        .on_mouse_down(MouseButton::Right, cx.listener(move |panel, _event: &MouseDownEvent, _window, cx| {
+            set_frequency(panel, steps[0], cx);
+        }))
+        .child(
+            Button::new(ElementId::Name(format!("quick-filter-{}-{}", name, track_id).into()))
+                .label(if active { compact_frequency(frequency) } else { name.to_string() })
+                .compact()
+                .small()
+                .w_full()
+                .when(active, |b| b.primary())
+                .when(!active, |b| b.ghost())
+                .tooltip(tooltip)
+                .on_click(cx.listener(move |panel, _, _window, cx| {
+                    // Step to the first corner past the current one, wrapping
+                    // back to open, so automated or typed-in values still step
+                    let next = steps
+                        .iter()
+                        .copied()
+                        .find(|&step| if high_pass { step > frequency } else { step < frequency })
+                        .unwrap_or(steps[0]);
+                    set_frequency(panel, next, cx);
+                }))
+        )
+}
+
+/// "80" or "12k", short enough for a half-width button
+fn compact_frequency(hz: f32) -> String {
+    if hz >= 1000.0 {
+        format!("{}k", (hz / 100.0).round() / 10.0)
+    } else {
+        format!("{:.0}", hz)
+    }
+}
+
+fn render_solo_flag_button(track: &Track, track_id: TrackId, isolate: bool, cx: &mut Context<DawPanel>) -> impl IntoElement {
+    let (active, label, tooltip) = if isolate {
+        (track.solo_isolate, "ISO", "Solo Isolate: always audible, and its solo doesn't silence others")
+    } else {
+        (track.solo_safe, "SAFE", "Solo Safe: never silenced when other tracks are soloed")
+    };
+
+    Button::new(ElementId::Name(format!("solo-{}-{}", label, track_id).into()))
+        .label(label)
+        .compact()
+        .small()
+        .flex_1()
+        .when(active, |b| b.primary())
+        .when(!active, |b| b.ghost())
+        .tooltip(tooltip)
+        .on_click(cx.listener(move |panel, _, _window, cx| {
+            if let Some(track) = panel.state.get_track_mut(track_id) {
+                if isolate {
+                    track.solo_isolate = !track.solo_isolate;
+                } else {
+                    track.solo_safe = !track.solo_safe;
+                }
+            }
+            panel.sync_tracks_to_audio_service(vec![track_id], cx);
+            cx.notify();
+        }))
+}
@@ -5,7 +5,8 @@ use ui::{
     h_virtual_list, scroll::{Scrollbar, ScrollbarAxis},
 };
 use super::super::DawPanel;
-use super::{Track, DawUiState, TrackId, DragState};
+use super::{Track, DawUiState, TrackId, DragState, FaderEntry};
+use crate::daw_editor::daw_engine::audio_types::fader_position;
 
 pub fn render_fader_slider(
     track: &Track,
@@ -13,7 +14,7 @@ pub fn render_fader_slider(
     cx: &mut Context<DawPanel>,
 ) -> impl IntoElement {
     let volume = track.volume;
-    let volume_percent = ((volume / 1.5) * 100.0).clamp(0.0, 100.0);
+    let volume_percent = fader_position(volume) * 100.0;
 
     v_flex()
         .w_full()
@@ -86,6 +87,15 @@ pub fn render_fader_slider(
                                     style.shadow_xl()
                                 })
                                 .on_mouse_down(MouseButton::Left, cx.listener(move |panel, event: &MouseDownEvent, _window, cx| {
+                                    cx.stop_propagation();
+                                    // Double-click returns to unity gain
+                                    if event.click_count >= 2 {
+                                        panel.state.drag_state = DragState::None;
+                                        panel.set_fader_volume(track_id, 1.0, cx);
+                                        panel.state.record_vca_fader(track_id, volume);
+                                        cx.notify();
+                                        return;
+                                    }
                                     panel.state.drag_state = DragState::DraggingFader {
                                         track_id,
                                         start_mouse_y: event.position.y.as_f32(),
@@ -96,4 +106,34 @@ pub fn render_fader_slider(
                         )
                 )
         )
-}
\ No newline at end of file
+}
+
+/// Fader level in dB. Double-click to type an exact value; while typing the
+/// entry is shown with a caret and Enter applies it.
+pub fn render_fader_readout(
+    track_id: TrackId,
+    volume: f32,
+    state: &DawUiState,
+    cx: &mut Context<DawPanel>,
+) -> impl IntoElement {
+    let entry = state.fader_entry.as_ref().filter(|e| e.track_id == track_id);
+    let label = match entry {
+        Some(entry) => format!("{}|", entry.text),
+        None if volume <= 0.0 => "-inf dB".to_string(),
+        None => format!("{:+.1} dB", 20.0 * volume.log10()),
+    };
+
+    div()
+        .id(ElementId::Name(format!("fader-readout-{}", track_id).into()))
+        .px_1()
+        .rounded_sm()
+        .cursor_text()
+        .when(entry.is_some(), |d| d.bg(cx.theme().secondary).border_1().border_color(cx.theme().accent))
+        .on_mouse_down(MouseButton::Left, cx.listener(move |panel, event: &MouseDownEvent, _window, cx| {
+            if event.click_count >= 2 {
+                panel.state.fader_entry = Some(FaderEntry { track_id, text: String::new() });
+                cx.notify();
+            }
+        }))
+        .child(label)
+}
@@ -6,6 +6,7 @@ use ui::{
 };
 use super::super::DawPanel;
 use super::{Track, DawUiState, TrackId, DragState};
+use crate::daw_editor::daw_engine::surround::ChannelLayout;
 
 pub fn render_master_channel(state: &DawUiState, cx: &mut Context<DawPanel>) -> impl IntoElement {
     let master_volume = state.project.as_ref()
@@ -48,8 +49,9 @@ pub fn render_master_channel(state: &DawUiState, cx: &mut Context<DawPanel>) ->
                         .child("MASTER")
                 )
         )
-        // Spacer for insert slots
-        .child(div().h(px(44.0)))
+        .child(render_channel_layout(state, cx))
+        // Brickwall limiter in the insert area
+        .child(render_master_limiter(state, cx))
         // Master peak meters
         .child(super::master_meters::render_master_meters(state, cx))
         // Master fader
@@ -69,6 +71,129 @@ pub fn render_master_channel(state: &DawUiState, cx: &mut Context<DawPanel>) ->
                 } else {
                     cx.theme().accent
                 })
-                .child(format!("{:+.1} dB", volume_db))
+                .child(super::fader_slider::render_fader_readout(uuid::Uuid::nil(), master_volume, state, cx))
         )
-}
\ No newline at end of file
+}
+
+/// Speaker layout of the master, click to step through them
+fn render_channel_layout(state: &DawUiState, cx: &mut Context<DawPanel>) -> impl IntoElement {
+    let layout = state.project.as_ref()
+        .map(|p| p.channel_layout)
+        .unwrap_or_default();
+    let next = ChannelLayout::ALL[(ChannelLayout::ALL.iter().position(|&l| l == layout).unwrap_or(0) + 1) % ChannelLayout::ALL.len()];
+
+    h_flex()
+        .w_full()
+        .justify_center()
+        .child(
+            Button::new("master-channel-layout")
+                .label(layout.label())
+                .compact()
+                .small()
+                .when(layout.is_surround(), |b| b.primary())
+                .when(!layout.is_surround(), |b| b.ghost())
+                .tooltip("Master speaker layout. Surround layouts pan every track into a bed for multichannel export and devices with enough outputs")
+                .on_click(cx.listener(move |panel, _, _window, cx| {
+                    tracing::error!("🔊 Master layout set to {}", next.label());
+                    panel.set_channel_layout(next, cx);
+                }))
+        )
+}
+
+fn render_master_limiter(state: &DawUiState, cx: &mut Context<DawPanel>) -> impl IntoElement {
+    let settings = state.project.as_ref()
+        .map(|p| p.master_limiter)
+        .unwrap_or_default();
+    let reduction = if settings.enabled { state.master_limiter_reduction } else { 0.0 };
+
+    v_flex()
+        .w_full()
+        .h(px(44.0))
+        .gap_0p5()
+        .child(
+            h_flex()
+                .w_full()
+                .gap_1()
+                .items_center()
+                .child(
+                    Button::new("master-limiter-toggle")
+                        .label("LIM")
+                        .compact()
+                        .small()
+                        .when(settings.enabled, |b| b.primary())
+                        .when(!settings.enabled, |b| b.ghost())
+                        .tooltip("True-peak limiter on the master output")
+                        .on_click(cx.listener(|panel, _, _window, cx| {
+                            if let Some(ref mut project) = panel.state.project {
+                                project.master_limiter.enabled = !project.master_limiter.enabled;
+                                tracing::error!("🧱 Master limiter {}", if project.master_limiter.enabled { "enabled" } else { "disabled" });
+                            }
+                            panel.sync_master_limiter(cx);
+                            cx.notify();
+                        }))
+                )
+                .child(
+                    Button::new("master-limiter-gain-match")
+                        .label("GM")
+                        .compact()
+                        .small()
+                        .when(settings.gain_match, |b| b.primary())
+                        .when(!settings.gain_match, |b| b.ghost())
+                        .tooltip("Gain match: make up the level the limiter takes away, for A/B comparison. Also applies to exports")
+                        .on_click(cx.listener(|panel, _, _window, cx| {
+                            if let Some(ref mut project) = panel.state.project {
+                                project.master_limiter.gain_match = !project.master_limiter.gain_match;
+                                tracing::error!("🧱 Limiter gain match {}", if project.master_limiter.gain_match { "enabled" } else { "disabled" });
+                            }
+                            panel.sync_master_limiter(cx);
+                            cx.notify();
+                        }))
+                )
+                // Gain reduction readout, with the gain match compensation
+                .child(
+                    div()
+                        .flex_1()
+                        .text_xs()
+                        .font_medium()
+                        .text_color(if reduction > 0.1 {
+                            hsla(0.08, 0.9, 0.55, 1.0)
+                        } else {
+                            cx.theme().muted_foreground
+                        })
+                        .child(if settings.enabled && settings.gain_match {
+                            format!("{:.1}/{:+.1}", reduction, state.master_gain_match_db)
+                        } else {
+                            format!("GR {:.1}", reduction)
+                        })
+                )
+        )
+        // Ceiling, drag horizontally to adjust
+        .child(
+            div()
+                .id("master-limiter-ceiling")
+                .w_full()
+                .h(px(18.0))
+                .flex()
+                .items_center()
+                .justify_center()
+                .rounded_sm()
+                .border_1()
+                .border_color(cx.theme().border.opacity(0.5))
+                .bg(cx.theme().secondary.opacity(0.3))
+                .cursor_ew_resize()
+                .text_xs()
+                .text_color(if settings.enabled {
+                    cx.theme().foreground
+                } else {
+                    cx.theme().muted_foreground
+                })
+                .on_mouse_down(MouseButton::Left, cx.listener(move |panel, event: &MouseDownEvent, _window, cx| {
+                    panel.state.drag_state = DragState::DraggingLimiterCeiling {
+                        start_mouse_x: event.position.x.as_f32(),
+                        start_ceiling: settings.ceiling_db,
+                    };
+                    cx.notify();
+                }))
+                .child(format!("{:.1} dBTP", settings.ceiling_db))
+        )
+}
@@ -6,9 +6,10 @@ use ui::{
 };
 use super::super::DawPanel;
 use super::{Track, DawUiState, TrackId, DragState};
+use crate::daw_editor::daw_engine::audio_types::fader_position;
 
 pub fn render_master_fader(master_volume: f32, cx: &mut Context<DawPanel>) -> impl IntoElement {
-    let volume_percent = ((master_volume / 1.5) * 100.0).clamp(0.0, 100.0);
+    let volume_percent = fader_position(master_volume) * 100.0;
 
     v_flex()
         .w_full()
@@ -77,6 +78,14 @@ pub fn render_master_fader(master_volume: f32, cx: &mut Context<DawPanel>) -> im
                                     style.shadow_2xl()
                                 })
                                 .on_mouse_down(MouseButton::Left, cx.listener(move |panel, event: &MouseDownEvent, _window, cx| {
+                                    cx.stop_propagation();
+                                    // Double-click returns to unity gain
+                                    if event.click_count >= 2 {
+                                        panel.state.drag_state = DragState::None;
+                                        panel.set_fader_volume(uuid::Uuid::nil(), 1.0, cx);
+                                        cx.notify();
+                                        return;
+                                    }
                                     panel.state.drag_state = DragState::DraggingFader {
                                         track_id: uuid::Uuid::nil(),
                                         start_mouse_y: event.position.y.as_f32(),
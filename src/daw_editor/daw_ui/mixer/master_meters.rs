@@ -8,7 +8,7 @@ use super::super::DawPanel;
 use super::{Track, DawUiState, TrackId, DragState};
 
 pub fn render_master_meters(state: &DawUiState, cx: &mut Context<DawPanel>) -> impl IntoElement {
-    let (left_peak, right_peak) = (state.master_meter.peak_left, state.master_meter.peak_right);
+    let (left_peak, right_peak) = (state.master_meter.level_left, state.master_meter.level_right);
 
     h_flex()
         .w_full()
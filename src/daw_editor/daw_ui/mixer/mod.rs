@@ -10,7 +10,7 @@ use ui::{
     button::*, h_flex, v_flex, Icon, IconName, Sizable, StyledExt, ActiveTheme, PixelsExt,
     h_virtual_list, scroll::{Scrollbar, ScrollbarAxis},
 };
-use crate::daw_editor::audio_types::{Track, TrackId};
+use crate::daw_editor::daw_engine::audio_types::{Track, TrackId};
 use std::rc::Rc;
 
 pub mod add_channel_button;
@@ -24,86 +24,180 @@ pub mod pan_control;
 pub mod peak_meters;
 pub mod send_controls;
 pub mod send_row;
+pub mod surround_panner;
 pub mod master_meters;
 pub mod master_fader;
 
 pub const CHANNEL_STRIP_WIDTH: f32 = 90.0;
+pub const NARROW_CHANNEL_STRIP_WIDTH: f32 = 64.0;
 pub const MIXER_PADDING: f32 = 8.0;
+const MIXER_TOOLBAR_HEIGHT: f32 = 28.0;
 
 pub fn render_mixer(state: &mut DawUiState, cx: &mut Context<DawPanel>) -> impl IntoElement {
-    let num_tracks = state.project.as_ref()
-        .map(|p| p.tracks.len())
-        .unwrap_or(0);
+    let track_order = state.mixer_track_order();
+    let strip_width = state.mixer_strip_width();
 
-    // Prepare item sizes for horizontal virtualization
-    let channel_sizes: Rc<Vec<Size<Pixels>>> = {
-        // num_tracks + add button + master = total items
-        let total_items = num_tracks + 2;
-        Rc::new(
-            (0..total_items).map(|_| Size {
-                width: px(CHANNEL_STRIP_WIDTH),
+    // Follow the selection made in the timeline or track list
+    if let Some(track_id) = state.mixer_reveal.take() {
+        let position = state.project.as_ref()
+            .and_then(|p| track_order.iter().position(|&idx| p.tracks[idx].id == track_id));
+        if let Some(position) = position {
+            state.mixer_scroll_handle.scroll_to_item(position, ScrollStrategy::Top);
+        }
+    }
+
+    // Prepare item sizes for horizontal virtualization: the strips, then the add button
+    let channel_sizes: Rc<Vec<Size<Pixels>>> = Rc::new(
+        track_order.iter().map(|_| strip_width)
+            .chain(std::iter::once(CHANNEL_STRIP_WIDTH))
+            .map(|width| Size {
+                width: px(width),
                 height: px(400.0), // Fixed mixer height to match panel
-            }).collect()
-        )
-    };
+            })
+            .collect()
+    );
 
     let panel_entity = cx.entity().clone();
 
-    div()
+    v_flex()
         .w_full()
         .h_full()
-        .relative()
         .overflow_hidden()
         .bg(cx.theme().background)
+        .child(render_mixer_toolbar(state, cx))
         .child(
-            h_virtual_list(
-                panel_entity.clone(),
-                "mixer-channels",
-                channel_sizes,
-                move |panel, visible_range, _, cx| {
-                    let num_tracks = panel.state.project.as_ref()
-                        .map(|p| p.tracks.len())
-                        .unwrap_or(0);
+            h_flex()
+                .w_full()
+                .flex_1()
+                .min_h_0()
+                .bg(cx.theme().muted.opacity(0.15))
+                .child(
+                    div()
+                        .flex_1()
+                        .min_w_0()
+                        .h_full()
+                        .relative()
+                        .child(
+                            h_virtual_list(
+                                panel_entity.clone(),
+                                "mixer-channels",
+                                channel_sizes,
+                                move |panel, visible_range, _, cx| {
+                                    let track_order = panel.state.mixer_track_order();
+                                    let num_tracks = track_order.len();
 
-                    visible_range.filter_map(|idx| {
-                        if idx < num_tracks {
-                            // Render track channel
-                            if let Some(ref project) = panel.state.project {
-                                if idx < project.tracks.len() {
-                                    let track = &project.tracks[idx];
-                                    return Some(channel_strip::render_channel_strip(track, idx, &panel.state, cx).into_any_element());
-                                }
-                            }
-                            None
-                        } else if idx == num_tracks {
-                            // Render add channel button
-                            Some(add_channel_button::render_add_channel_button(cx).into_any_element())
-                        } else if idx == num_tracks + 1 {
-                            // Render master channel
-                            Some(master_channel::render_master_channel(&panel.state, cx).into_any_element())
-                        } else {
-                            None
-                        }
-                    }).collect::<Vec<_>>()
-                },
-            )
-            .track_scroll(&state.mixer_scroll_handle)
-            .px(px(MIXER_PADDING))
-            .py_2()
-            .bg(cx.theme().muted.opacity(0.15))
-            .gap_2()
+                                    visible_range.filter_map(|idx| {
+                                        if idx < num_tracks {
+                                            // Render track channel
+                                            if let Some(ref project) = panel.state.project {
+                                                let track_idx = track_order[idx];
+                                                if track_idx < project.tracks.len() {
+                                                    let track = &project.tracks[track_idx];
+                                                    return Some(channel_strip::render_channel_strip(track, track_idx, &panel.state, cx).into_any_element());
+                                                }
+                                            }
+                                            None
+                                        } else if idx == num_tracks {
+                                            // Render add channel button
+                                            Some(add_channel_button::render_add_channel_button(cx).into_any_element())
+                                        } else {
+                                            None
+                                        }
+                                    }).collect::<Vec<_>>()
+                                },
+                            )
+                            .track_scroll(&state.mixer_scroll_handle)
+                            .px(px(MIXER_PADDING))
+                            .py_2()
+                            .gap_2()
+                        )
+                        .child(
+                            // Scrollbar overlay
+                            div()
+                                .absolute()
+                                .inset_0()
+                                .child(
+                                    Scrollbar::both(
+                                        &state.mixer_scroll_state,
+                                        &state.mixer_scroll_handle,
+                                    )
+                                    .axis(ScrollbarAxis::Horizontal)
+                                )
+                        )
+                )
+                // The master stays pinned at the right while the strips scroll
+                .child(
+                    div()
+                        .h_full()
+                        .flex_shrink_0()
+                        .py_2()
+                        .px(px(MIXER_PADDING))
+                        .border_l_1()
+                        .border_color(cx.theme().border)
+                        .child(master_channel::render_master_channel(state, cx))
+                )
         )
+}
+
+/// Strip width toggle and, while a bus is spilled, the way back to every channel
+fn render_mixer_toolbar(state: &DawUiState, cx: &mut Context<DawPanel>) -> impl IntoElement {
+    let narrow = state.mixer_narrow;
+    let spilled = state.mixer_spill
+        .and_then(|bus| state.project.as_ref()?.tracks.iter().find(|t| t.id == bus))
+        .map(|bus| (bus.id, bus.name.clone()));
+
+    h_flex()
+        .w_full()
+        .h(px(MIXER_TOOLBAR_HEIGHT))
+        .flex_shrink_0()
+        .px_2()
+        .gap_2()
+        .items_center()
+        .border_b_1()
+        .border_color(cx.theme().border)
         .child(
-            // Scrollbar overlay
             div()
-                .absolute()
-                .inset_0()
-                .child(
-                    Scrollbar::both(
-                        &state.mixer_scroll_state,
-                        &state.mixer_scroll_handle,
-                    )
-                    .axis(ScrollbarAxis::Horizontal)
-                )
+                .text_xs()
+                .font_semibold()
+                .text_color(cx.theme().muted_foreground)
+                .child("MIXER")
+        )
+        .when_some(spilled, |el, (bus, name)| {
+            el.child(
+                Button::new("mixer-collapse-spill")
+                    .label(format!("{} ✕", name))
+                    .compact()
+                    .small()
+                    .primary()
+                    .tooltip("Showing only the tracks feeding this bus. Click to show every channel")
+                    .on_click(cx.listener(move |panel, _, _window, cx| {
+                        panel.state.toggle_mixer_spill(bus);
+                        cx.notify();
+                    }))
+            )
+        })
+        .child(div().flex_1())
+        .child(
+            Button::new("mixer-add-vca")
+                .label("+ VCA")
+                .compact()
+                .small()
+                .ghost()
+                .tooltip("Add a VCA fader riding the selected tracks")
+                .on_click(cx.listener(|panel, _, _window, cx| {
+                    panel.add_vca(cx);
+                }))
+        )
+        .child(
+            Button::new("mixer-strip-width")
+                .label(if narrow { "Narrow" } else { "Wide" })
+                .compact()
+                .small()
+                .ghost()
+                .tooltip("Switch between narrow and wide channel strips")
+                .on_click(cx.listener(|panel, _, _window, cx| {
+                    panel.state.mixer_narrow = !panel.state.mixer_narrow;
+                    cx.notify();
+                }))
         )
-}
\ No newline at end of file
+}
@@ -49,6 +49,14 @@ pub fn render_pan_control(
                 .cursor_ew_resize()
                 .hover(|style| style.bg(cx.theme().secondary.opacity(0.45)))
                 .on_mouse_down(MouseButton::Left, cx.listener(move |panel, event: &MouseDownEvent, _window, cx| {
+                    // Double-click centers the pan
+                    if event.click_count >= 2 {
+                        panel.state.drag_state = DragState::None;
+                        panel.reset_pan(track_id, cx);
+                        cx.notify();
+                        return;
+                    }
+
                     // Calculate initial slider value (0.0 to 1.0)
                     let slider_value = (pan + 1.0) / 2.0;
 
@@ -8,20 +8,79 @@ use super::super::DawPanel;
 use super::{Track, DawUiState, TrackId, DragState};
 
 pub fn render_peak_meters(track: &Track, state: &DawUiState, cx: &mut Context<DawPanel>) -> impl IntoElement {
-    // Get actual meter data from audio service
-    let (left_peak, right_peak) = if let Some(meter) = state.track_meters.get(&track.id) {
-        (meter.peak_left, meter.peak_right)
+    // Armed and monitoring tracks show what arrives at their input, so the
+    // level can be set before recording, playing or not
+    let input = state.input_meters.get(&track.id).copied();
+    let meter = input.map(|i| i.meter).or_else(|| state.track_meters.get(&track.id).copied());
+    let (left_peak, right_peak, correlation) = if let Some(meter) = meter {
+        (meter.level_left, meter.level_right, meter.correlation)
     } else {
-        (0.0, 0.0)
+        (0.0, 0.0, 0.0)
     };
 
-    h_flex()
+    v_flex()
         .w_full()
-        .h(px(48.0))
-        .gap_1()
-        .p_0p5()
-        .bg(cx.theme().secondary.opacity(0.2))
+        .gap_0p5()
+        .when_some(input, |d, input| d.child(render_input_clip_light(track.id, input.clipped, cx)))
+        .child(
+            h_flex()
+                .w_full()
+                .h(px(44.0))
+                .gap_1()
+                .p_0p5()
+                .bg(cx.theme().secondary.opacity(0.2))
+                .rounded_sm()
+                .child(super::meter_bar::render_meter_bar(left_peak, cx))
+                .child(super::meter_bar::render_meter_bar(right_peak, cx))
+        )
+        .child(render_correlation_meter(correlation, cx))
+}
+
+/// "IN" over the meter while it reads the input, red once the input has
+/// clipped until clicked
+fn render_input_clip_light(track_id: TrackId, clipped: bool, cx: &mut Context<DawPanel>) -> impl IntoElement {
+    div()
+        .id(ElementId::Name(format!("input-clip-{}", track_id).into()))
+        .w_full()
+        .h(px(12.0))
+        .flex()
+        .items_center()
+        .justify_center()
+        .rounded_sm()
+        .text_xs()
+        .when(clipped, |d| d.bg(hsla(0.0, 0.95, 0.5, 1.0)).text_color(cx.theme().background))
+        .when(!clipped, |d| d.bg(cx.theme().secondary.opacity(0.3)).text_color(cx.theme().muted_foreground))
+        .child(if clipped { "CLIP" } else { "IN" })
+        .on_mouse_down(MouseButton::Left, cx.listener(move |panel, _event: &MouseDownEvent, _window, cx| {
+            cx.stop_propagation();
+            panel.clear_input_clip(track_id, cx);
+        }))
+}
+
+/// Horizontal phase correlation bar: centre is 0, right is +1 (mono), left is -1
+fn render_correlation_meter(correlation: f32, cx: &mut Context<DawPanel>) -> impl IntoElement {
+    let correlation = correlation.clamp(-1.0, 1.0);
+    let width = correlation.abs() * 50.0;
+    let color = if correlation < 0.0 {
+        hsla(0.0, 0.85, 0.55, 1.0)
+    } else {
+        hsla(0.33, 0.7, 0.45, 1.0)
+    };
+
+    div()
+        .w_full()
+        .h(px(4.0))
+        .relative()
         .rounded_sm()
-        .child(super::meter_bar::render_meter_bar(left_peak, cx))
-        .child(super::meter_bar::render_meter_bar(right_peak, cx))
+        .bg(cx.theme().secondary.opacity(0.3))
+        .child(
+            div()
+                .absolute()
+                .top_0()
+                .bottom_0()
+                .when(correlation >= 0.0, |d| d.left(relative(0.5)))
+                .when(correlation < 0.0, |d| d.left(relative(0.5 - width / 100.0)))
+                .w(relative(width / 100.0))
+                .bg(color)
+        )
 }
\ No newline at end of file
@@ -10,13 +10,19 @@ use super::{Track, DawUiState, TrackId, DragState};
 pub fn render_send_controls(
     track: &Track,
     track_id: TrackId,
+    state: &DawUiState,
     cx: &mut Context<DawPanel>,
 ) -> impl IntoElement {
     // Get send values from track if available
-    let send_a_amount = track.sends.get(0).map(|s| s.amount).unwrap_or(0.0);
-    let send_a_pre = track.sends.get(0).map(|s| s.pre_fader).unwrap_or(false);
-    let send_b_amount = track.sends.get(1).map(|s| s.amount).unwrap_or(0.0);
-    let send_b_pre = track.sends.get(1).map(|s| s.pre_fader).unwrap_or(false);
+    let send_a = track.sends.first().cloned().unwrap_or_default();
+    let send_b = track.sends.get(1).cloned().unwrap_or_default();
+
+    let target_name = |send_idx: usize| -> Option<String> {
+        let target = track.sends.get(send_idx)?.target_track?;
+        state.get_track(target).map(|t| t.name.clone())
+    };
+    let send_a_target = target_name(0);
+    let send_b_target = target_name(1);
 
     v_flex()
         .w_full()
@@ -32,7 +38,7 @@ pub fn render_send_controls(
             v_flex()
                 .w_full()
                 .gap_1()
-                .child(super::send_row::render_send_row("A", send_a_amount, send_a_pre, track_id, 0, cx))
-                .child(super::send_row::render_send_row("B", send_b_amount, send_b_pre, track_id, 1, cx))
+                .child(super::send_row::render_send_row("A", &send_a, send_a_target, track_id, 0, cx))
+                .child(super::send_row::render_send_row("B", &send_b, send_b_target, track_id, 1, cx))
         )
 }
\ No newline at end of file
@@ -1,18 +1,72 @@
 use gpui::*;
 use gpui::prelude::FluentBuilder;
 use ui::{
-    button::*, h_flex, Sizable, StyledExt, ActiveTheme, PixelsExt};
+    button::*, h_flex, v_flex, Sizable, StyledExt, ActiveTheme, PixelsExt};
 use super::super::DawPanel;
+use crate::daw_editor::daw_engine::audio_types::Send;
 use super::{TrackId, DragState};
 
 pub fn render_send_row(
     label: &'static str,
-    value: f32,
-    is_pre_fader: bool,
+    send: &Send,
+    target_name: Option<String>,
     track_id: TrackId,
     send_idx: usize,
     cx: &mut Context<DawPanel>,
 ) -> impl IntoElement {
+    let has_target = target_name.is_some();
+
+    v_flex()
+        .w_full()
+        .gap_0p5()
+        // Send destination: click cycles through returns, right-click creates a new one
+        .child(
+            div()
+                .id(ElementId::Name(format!("send-{}-{}-target-wrap", track_id, send_idx).into()))
+                .w_full()
+                .on_mouse_down(MouseButton::Right, cx.listener(move |panel, _event: &MouseDownEvent, _window, cx| {
+                    panel.cycle_send_target(track_id, send_idx, true, cx);
+                }))
+                .child(
+                    Button::new(ElementId::Name(format!("send-{}-{}-target", track_id, send_idx).into()))
+                        .label(target_name.unwrap_or_else(|| "—".to_string()))
+                        .compact()
+                        .small()
+                        .w_full()
+                        .when(has_target, |b| b.primary())
+                        .when(!has_target, |b| b.ghost())
+                        .tooltip(format!("Send {}: Destination (right-click for new return)", label))
+                        .on_click(cx.listener(move |panel, _, _window, cx| {
+                            panel.cycle_send_target(track_id, send_idx, false, cx);
+                        }))
+                )
+        )
+        .child(render_send_controls_row(label, send, track_id, send_idx, cx))
+}
+
+/// Change one send of a track, creating the slot if the track has fewer,
+/// and pass it on to the audio engine
+fn edit_send(panel: &mut DawPanel, track_id: TrackId, send_idx: usize, edit: impl FnOnce(&mut Send), cx: &mut Context<DawPanel>) {
+    if let Some(track) = panel.state.get_track_mut(track_id) {
+        while track.sends.len() <= send_idx {
+            track.sends.push(Send::default());
+        }
+        edit(&mut track.sends[send_idx]);
+        panel.state.dirty.mark_dirty();
+        panel.sync_tracks_to_audio_service(vec![track_id], cx);
+    }
+    cx.notify();
+}
+
+fn render_send_controls_row(
+    label: &'static str,
+    send: &Send,
+    track_id: TrackId,
+    send_idx: usize,
+    cx: &mut Context<DawPanel>,
+) -> impl IntoElement {
+    let (value, is_pre_fader, follows_mute) = (send.amount, send.pre_fader, send.follow_mute);
+
     h_flex()
         .w_full()
         .gap_1()
@@ -28,25 +82,28 @@ pub fn render_send_row(
                 .tooltip(format!("Send {}: Pre/Post Fader", label))
                 .flex_shrink_0()
                 .on_click(cx.listener(move |panel, _, _window, cx| {
-                    // Toggle pre/post fader
-                    if let Some(ref mut project) = panel.state.project {
-                        if let Some(track) = project.tracks.iter_mut().find(|t| t.id == track_id) {
-                            // Ensure send exists
-                            while track.sends.len() <= send_idx {
-                                track.sends.push(super::super::super::audio_types::Send {
-                                    target_track: None,
-                                    amount: 0.0,
-                                    pre_fader: false,
-                                    enabled: false,
-                                });
-                            }
-                            if let Some(send) = track.sends.get_mut(send_idx) {
-                                send.pre_fader = !send.pre_fader;
-                                tracing::error!("🎚️ Send {} set to {}", label, if send.pre_fader { "PRE" } else { "POST" });
-                            }
-                        }
-                    }
-                    cx.notify();
+                    edit_send(panel, track_id, send_idx, |send| {
+                        send.pre_fader = !send.pre_fader;
+                        tracing::error!("🎚️ Send {} set to {}", label, if send.pre_fader { "PRE" } else { "POST" });
+                    }, cx);
+                }))
+        )
+        // Whether the send goes quiet with the track's mute
+        .child(
+            Button::new(ElementId::Name(format!("send-{}-{}-mute", track_id, send_idx).into()))
+                .label(if follows_mute { "FOL" } else { "IGN" })
+                .compact()
+                .small()
+                .when(!follows_mute, |b| b.primary())
+                .when(follows_mute, |b| b.ghost())
+                .tooltip(if follows_mute {
+                    format!("Send {}: Follows Track Mute", label)
+                } else {
+                    format!("Send {}: Ignores Track Mute", label)
+                })
+                .flex_shrink_0()
+                .on_click(cx.listener(move |panel, _, _window, cx| {
+                    edit_send(panel, track_id, send_idx, |send| send.follow_mute = !send.follow_mute, cx);
                 }))
         )
         // Send level control with dragging
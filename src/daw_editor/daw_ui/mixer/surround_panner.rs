@@ -0,0 +1,117 @@
+use gpui::*;
+use gpui::prelude::FluentBuilder;
+use ui::{
+    button::*, h_flex, v_flex, Sizable, StyledExt, ActiveTheme,
+};
+use super::super::DawPanel;
+use super::{Track, TrackId};
+use crate::daw_editor::daw_engine::surround::{ChannelLayout, SurroundPan};
+
+/// Cells per side of the panner pad; clicking a cell moves the track there
+const PAD_CELLS: usize = 9;
+const PAD_SIZE: f32 = 72.0;
+
+/// Surround field seen from above, front at the top. Shows the layout's
+/// speakers and the track's position, with an LFE send for layouts that
+/// have one.
+pub fn render_surround_panner(
+    track: &Track,
+    track_id: TrackId,
+    layout: ChannelLayout,
+    cx: &mut Context<DawPanel>,
+) -> impl IntoElement {
+    let pan = track.surround_pan;
+    // Pad fractions from the left and top edges
+    let to_pad = |x: f32, y: f32| ((x + 1.0) / 2.0, (1.0 - y) / 2.0);
+    let (pan_left, pan_top) = to_pad(pan.x, pan.y);
+    let has_lfe = layout.speakers().iter().any(|s| s.azimuth.is_none());
+
+    let step = 2.0 / (PAD_CELLS - 1) as f32;
+    let mut cells = Vec::with_capacity(PAD_CELLS);
+    for row in 0..PAD_CELLS {
+        let mut line = h_flex().flex_1().w_full();
+        for column in 0..PAD_CELLS {
+            let (x, y) = (-1.0 + column as f32 * step, 1.0 - row as f32 * step);
+            line = line.child(
+                div()
+                    .id(ElementId::Name(format!("surround-pan-{}-{}-{}", track_id, row, column).into()))
+                    .flex_1()
+                    .h_full()
+                    .cursor_pointer()
+                    .on_mouse_down(MouseButton::Left, cx.listener(move |panel, _: &MouseDownEvent, _window, cx| {
+                        cx.stop_propagation();
+                        panel.set_track_surround_pan(track_id, SurroundPan { x, y, ..pan }, cx);
+                    }))
+            );
+        }
+        cells.push(line);
+    }
+
+    let speakers = layout.speakers().iter().filter_map(|speaker| {
+        let azimuth = speaker.azimuth?.to_radians();
+        // Speakers sit on the circle inscribed in the pad
+        let (left, top) = to_pad(azimuth.sin(), azimuth.cos());
+        Some(
+            div()
+                .absolute()
+                .left(relative(left))
+                .top(relative(top))
+                .ml(px(-3.0))
+                .mt(px(-3.0))
+                .size(px(6.0))
+                .rounded_sm()
+                .bg(cx.theme().muted_foreground.opacity(0.6))
+        )
+    });
+
+    v_flex()
+        .w_full()
+        .gap_0p5()
+        .items_center()
+        .child(
+            div()
+                .text_xs()
+                .font_semibold()
+                .text_color(cx.theme().muted_foreground)
+                .child(format!("SURROUND {}", layout.label()))
+        )
+        .child(
+            div()
+                .relative()
+                .size(px(PAD_SIZE))
+                .rounded_sm()
+                .border_1()
+                .border_color(cx.theme().border.opacity(0.6))
+                .bg(cx.theme().secondary.opacity(0.3))
+                .child(v_flex().size_full().children(cells))
+                .children(speakers)
+                .child(
+                    // Track position
+                    div()
+                        .absolute()
+                        .left(relative(pan_left))
+                        .top(relative(pan_top))
+                        .ml(px(-5.0))
+                        .mt(px(-5.0))
+                        .size(px(10.0))
+                        .rounded_full()
+                        .bg(cx.theme().accent)
+                        .border_1()
+                        .border_color(cx.theme().foreground.opacity(0.4))
+                )
+        )
+        .when(has_lfe, |d| {
+            d.child(
+                Button::new(ElementId::Name(format!("surround-lfe-{}", track_id).into()))
+                    .label(format!("LFE {:.0}%", pan.lfe * 100.0))
+                    .compact()
+                    .small()
+                    .ghost()
+                    .tooltip("Send to the LFE channel, click to step through 0, 50 and 100%")
+                    .on_click(cx.listener(move |panel, _, _window, cx| {
+                        let lfe = if pan.lfe < 0.25 { 0.5 } else if pan.lfe < 0.75 { 1.0 } else { 0.0 };
+                        panel.set_track_surround_pan(track_id, SurroundPan { lfe, ..pan }, cx);
+                    }))
+            )
+        })
+}
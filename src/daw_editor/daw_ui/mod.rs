@@ -9,5 +9,11 @@ pub mod transport;
 pub mod browser;
 pub mod toolbar;
 pub mod track_header;
+pub mod clip_properties;
+pub mod sample_rate_dialog;
+pub mod reference_image_dialog;
+pub mod clip_export_dialog;
+pub mod render_queue_dialog;
+pub mod clip_editor;
 
 pub use panel::DawPanel;
@@ -2,14 +2,16 @@
 /// Top-level container that assembles all UI components
 
 use super::state::*;
-use super::super::{audio_service::AudioService, audio_types::*};
+use super::super::daw_engine::{audio_service::AudioService, audio_types::*, clipboard::ClipboardPayload, effects::{Effect, EffectClipboard, EffectId, EffectKind}, preferences::{EditPreferences, FadeEnds}, loop_crossfade::parse_loop_crossfade, loop_import::{build_loop_set, loop_files}, project_report::{format_bytes, ProjectReport}, profiling::ProcessTiming, project::{parse_tempo, parse_time_signature, AUDIO_FOLDER}, render::{bounce_track, conform_asset, export_reference_image, render_generator, export_clips, split_asset_channels, export_marker_regions, flatten_project, flattened_project_path, marker_regions, reference_match_gain, region_cues, ClipExportOptions, ClipExportReport, FlattenOptions, RenderOptions}, render_queue::{queue_summary, queued_render_path, JobState, RenderJob, RenderQueue, RenderRange}, spectrogram::{Spectrogram, SpectrogramSettings}, surround::{ChannelLayout, SurroundPan}, timing_sidecar::render_with_timing, transients::clip_onset, transport_chase::ChaseEvent, midi_input::{MidiInputs, MidiMessage}};
 use gpui::*;
 use gpui::prelude::FluentBuilder;
 use ui::{v_flex, h_flex, StyledExt, ActiveTheme, PixelsExt, Sizable, Selectable, button::{Button, ButtonVariants}};
+use std::collections::HashSet;
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::Duration;
-use crate::daw_editor::audio_types::SAMPLE_RATE;
+use crate::daw_editor::daw_engine::audio_types::SAMPLE_RATE;
 use futures::channel::mpsc;
 use futures::{SinkExt, StreamExt};
 
@@ -36,6 +38,7 @@ impl DawPanel {
 
                 // Sync loaded project to audio service
                 self.sync_project_to_audio_service(cx);
+                self.load_waveforms(cx);
 
                 cx.notify();
             }
@@ -50,6 +53,10 @@ impl DawPanel {
         if let (Some(ref project), Some(ref service)) = (&self.state.project, &self.state.audio_service) {
             let service = service.clone();
             let project = project.clone();
+            let monitor_gain = self.state.preferences.monitor_gain();
+            let smoothing = self.state.preferences.smoothing_samples(project.sample_rate);
+            let meter_ballistics = self.state.preferences.meter_ballistics;
+            let scan_clipping = self.state.preferences.scan_clipping;
 
             cx.spawn(async move |_this, _cx| {
                 tracing::error!("🔄 Syncing project to audio service...");
@@ -59,8 +66,24 @@ impl DawPanel {
                 let loop_enabled = project.transport.loop_enabled;
                 let loop_start = project.transport.loop_start;
                 let loop_end = project.transport.loop_end;
+                let loop_crossfade_ms = project.transport.loop_crossfade_ms;
+                let playback_end = project.transport.playback_end;
+                let project_length = project.transport.project_length;
                 let metronome_enabled = project.transport.metronome_enabled;
                 let master_volume = project.master_track.volume;
+                let master_limiter = project.master_limiter;
+                let channel_layout = project.channel_layout;
+
+                // Buffers follow the project rate and the clipping scan preference
+                for (path, e) in service.set_scan_clipping(scan_clipping).await {
+                    tracing::error!("❌ Failed to reload {:?}: {}", path, e);
+                }
+                for (path, e) in service.set_asset_sample_rate(project.sample_rate).await {
+                    tracing::error!("❌ Failed to resample {:?}: {}", path, e);
+                }
+                if let Err(e) = service.set_sample_rate(project.sample_rate).await {
+                    tracing::error!("❌ Failed to set the device sample rate: {}", e);
+                }
 
                 // Set tempo
                 if let Err(e) = service.set_tempo(tempo).await {
@@ -75,6 +98,12 @@ impl DawPanel {
                 ).await {
                     tracing::error!("❌ Failed to set loop: {}", e);
                 }
+                if let Err(e) = service.set_loop_crossfade(loop_crossfade_ms).await {
+                    tracing::error!("❌ Failed to set loop crossfade: {}", e);
+                }
+                if let Err(e) = service.set_playback_end(playback_end, project_length).await {
+                    tracing::error!("❌ Failed to set playback end: {}", e);
+                }
 
                 // Set metronome
                 if let Err(e) = service.set_metronome(metronome_enabled).await {
@@ -105,6 +134,14 @@ impl DawPanel {
                 if let Err(e) = service.set_master_volume(master_volume).await {
                     tracing::error!("❌ Failed to set master volume: {}", e);
                 }
+                service.set_master_limiter(master_limiter).await;
+                service.set_channel_layout(channel_layout).await;
+                service.set_listen_reference(false).await;
+                if let Err(e) = service.set_monitor_gain(monitor_gain).await {
+                    tracing::error!("❌ Failed to set monitor level: {}", e);
+                }
+                service.set_smoothing_samples(smoothing).await;
+                service.set_meter_ballistics(meter_ballistics).await;
 
                 tracing::error!("✅ Project sync complete");
             }).detach();
@@ -131,12 +168,145 @@ impl DawPanel {
 
         // Sync existing project to audio service if one exists
         self.sync_project_to_audio_service(cx);
+        self.load_waveforms(cx);
 
         // Start periodic playhead sync
         self.start_playhead_sync(cx);
 
         // Start periodic meter sync
         self.start_meter_sync(cx);
+
+        self.start_midi_input(cx);
+    }
+
+    /// Fetch waveform peaks for every clip asset that doesn't have them yet.
+    /// The disk cache is tried first; misses load the audio, build the peaks
+    /// off the UI thread and write them back to the cache.
+    pub fn load_waveforms(&mut self, cx: &mut Context<Self>) {
+        let Some(ref project) = self.state.project else {
+            return;
+        };
+        let paths: HashSet<PathBuf> = project
+            .tracks
+            .iter()
+            .flat_map(|t| t.clips.iter().map(|c| c.asset_path.clone()))
+            .filter(|p| !self.state.waveforms.contains_key(p) && !self.state.waveforms_pending.contains(p))
+            .collect();
+
+        for path in paths {
+            self.state.waveforms_pending.insert(path.clone());
+            let cache = self.state.peak_cache.clone();
+            let service = self.state.audio_service.clone();
+
+            cx.spawn(async move |this, cx| {
+                let cached = {
+                    let (cache, path) = (cache.clone(), path.clone());
+                    cx.background_executor().spawn(async move { cache.load(&path) }).await
+                };
+                let peaks = match (cached, service) {
+                    (Some(peaks), _) => Some(peaks),
+                    (None, Some(service)) => match service.load_asset(path.clone()).await {
+                        Ok(asset) => {
+                            let path = path.clone();
+                            Some(cx.background_executor().spawn(async move { cache.load_or_build(&path, &asset) }).await)
+                        }
+                        Err(e) => {
+                            tracing::error!("❌ Failed to load {:?} for its waveform: {}", path, e);
+                            None
+                        }
+                    },
+                    (None, None) => None,
+                };
+
+                cx.update(|cx| {
+                    this.update(cx, |this, cx| {
+                        this.state.waveforms_pending.remove(&path);
+                        if let Some(peaks) = peaks {
+                            this.state.waveforms.insert(path, Arc::new(peaks));
+                            cx.notify();
+                        }
+                    }).ok();
+                }).ok();
+            }).detach();
+        }
+    }
+
+    /// Build the spectrogram of a source file for the clip editor in the
+    /// background. The whole file is analysed so trims can also be extended.
+    pub fn load_spectrogram(&mut self, path: PathBuf, cx: &mut Context<Self>) {
+        if self.state.spectrograms.contains_key(&path) || self.state.spectrograms_pending.contains(&path) {
+            return;
+        }
+        let Some(service) = self.state.audio_service.clone() else {
+            return;
+        };
+        self.state.spectrograms_pending.insert(path.clone());
+
+        cx.spawn(async move |this, cx| {
+            let spectrogram = match service.load_asset(path.clone()).await {
+                Ok(asset) => Some(cx.background_executor().spawn(async move {
+                    let frames = asset.asset_ref.duration_samples as SampleTime;
+                    Spectrogram::from_asset(&asset, 0, frames, &SpectrogramSettings::default())
+                }).await),
+                Err(e) => {
+                    tracing::error!("❌ Failed to load {:?} for its spectrogram: {}", path, e);
+                    None
+                }
+            };
+
+            cx.update(|cx| {
+                this.update(cx, |this, cx| {
+                    this.state.spectrograms_pending.remove(&path);
+                    if let Some(spectrogram) = spectrogram {
+                        this.state.spectrograms.insert(path, Arc::new(spectrogram));
+                        cx.notify();
+                    }
+                }).ok();
+            }).ok();
+        }).detach();
+    }
+
+    /// Move the playhead to `beats` and the engine with it
+    pub fn seek_to_beat(&mut self, beats: f64, cx: &mut Context<Self>) {
+        self.state.set_playhead(beats);
+        let Some(position) = self.state.project.as_ref().map(|p| p.transport.beats_to_samples(beats.max(0.0))) else {
+            cx.notify();
+            return;
+        };
+        if let Some(service) = self.state.audio_service.clone() {
+            cx.spawn(async move |_this, _cx| {
+                let _ = service.seek(position).await;
+            }).detach();
+        }
+        cx.notify();
+    }
+
+    /// Play the edited clip from the start of its loop, which then repeats
+    /// until the clip ends
+    pub fn audition_clip_loop(&mut self, cx: &mut Context<Self>) {
+        let Some((track_id, clip)) = self.state.edited_clip() else {
+            return;
+        };
+        let Some((loop_start, _)) = clip.loop_timeline_range() else {
+            return;
+        };
+        let offset = self.state.project.as_ref()
+            .and_then(|p| p.get_track(track_id))
+            .map_or(0, |t| t.track_offset_samples);
+        let position = (loop_start as i64 + offset).max(0) as SampleTime;
+        let Some(beats) = self.state.project.as_ref().map(|p| p.transport.samples_to_beats(position)) else {
+            return;
+        };
+        self.state.set_playhead(beats);
+        self.state.is_playing = true;
+
+        if let Some(service) = self.state.audio_service.clone() {
+            cx.spawn(async move |_this, _cx| {
+                let _ = service.seek(position).await;
+                let _ = service.play().await;
+            }).detach();
+        }
+        cx.notify();
     }
 
     /// Start a periodic task to sync playhead position from audio service
@@ -176,11 +346,17 @@ impl DawPanel {
                     cx.update(|cx| {
                         this.update(cx, |this, cx| {
                             let tempo = this.state.get_tempo();
-                            let seconds = position as f64 / SAMPLE_RATE as f64;
+                            let seconds = position as f64 / this.state.sample_rate() as f64;
                             let beats = (seconds * tempo as f64) / 60.0;
 
                             this.state.selection.playhead_position = beats;
                             this.state.is_playing = is_playing;
+                            this.state.follow_playhead();
+                            this.advance_automation_recording(position, is_playing, cx);
+                            let chase_events = this.state.transport_chase.poll_now();
+                            if !chase_events.is_empty() {
+                                this.apply_chase_events(chase_events, cx);
+                            }
                             cx.notify();
                         }).ok();
                     }).ok();
@@ -191,6 +367,28 @@ impl DawPanel {
         }
     }
 
+    /// Open the MIDI inputs and hand each message to the UI as it arrives
+    fn start_midi_input(&mut self, cx: &mut Context<Self>) {
+        let Some(ref service) = self.state.audio_service else {
+            return;
+        };
+        let monitor = Arc::new(service.get_position_monitor());
+        let (inputs, rx) = MidiInputs::open(move || monitor.get_position());
+        tracing::error!("🎹 MIDI inputs: {:?}", inputs.port_names());
+        self.state.midi_inputs = Some(inputs);
+
+        cx.spawn(async move |this, cx| {
+            while let Ok(message) = rx.recv_async().await {
+                let open = cx.update(|cx| {
+                    this.update(cx, |this, cx| this.handle_midi_message(message, cx)).is_ok()
+                }).unwrap_or(false);
+                if !open {
+                    break;
+                }
+            }
+        }).detach();
+    }
+
     /// Start a periodic task to sync meter data from audio service
     /// Updates visual meters at 30 FPS for smooth visualization
     fn start_meter_sync(&self, cx: &mut Context<Self>) {
@@ -204,6 +402,8 @@ impl DawPanel {
 
                     // Get meter data from audio service
                     let master_meter = service.get_master_meter().await;
+                    let limiter_reduction = service.get_master_limiter_reduction().await;
+                    let gain_match_db = service.get_master_gain_match_db().await;
 
                     // Get all track IDs first
                     let track_ids: Vec<TrackId> = cx.update(|cx| {
@@ -215,17 +415,31 @@ impl DawPanel {
 
                     // Get meter data for all tracks
                     let mut track_meters = std::collections::HashMap::new();
+                    let mut input_meters = std::collections::HashMap::new();
                     for track_id in track_ids {
                         if let Some(meter) = service.get_track_meter(track_id).await {
                             track_meters.insert(track_id, meter);
                         }
+                        if let Some(meter) = service.get_input_meter(track_id).await {
+                            input_meters.insert(track_id, meter);
+                        }
                     }
+                    let profiling = cx.update(|cx| {
+                        this.upgrade().is_some_and(|entity| entity.read(cx).state.dsp_profiling)
+                    }).unwrap_or(false);
+                    let dsp_profile = if profiling { Some(service.get_dsp_profile().await) } else { None };
 
                     // Update UI state
                     cx.update(|cx| {
                         this.update(cx, |this, cx| {
                             this.state.master_meter = master_meter;
+                            this.state.master_limiter_reduction = limiter_reduction;
+                            this.state.master_gain_match_db = gain_match_db;
                             this.state.track_meters = track_meters;
+                            this.state.input_meters = input_meters;
+                            if let Some(profile) = dsp_profile {
+                                this.state.dsp_profile = profile;
+                            }
                             cx.notify();
                         }).ok();
                     }).ok();
@@ -236,460 +450,3273 @@ impl DawPanel {
         }
     }
 
-    /// Convert window-relative coordinates to timeline element coordinates
-    /// Following GPUI best practices from gpui-mouse-position.md
-    pub fn window_to_timeline_pos(window_pos: Point<Pixels>, panel: &Self) -> Point<Pixels> {
-        if let Some(bounds) = &panel.timeline_element_bounds {
-            Point::new(
-                window_pos.x - bounds.origin.x,
-                window_pos.y - bounds.origin.y,
-            )
-        } else {
-            // Before first frame, bounds aren't captured yet
-            window_pos
-        }
-    }
-}
+    /// Write the project on the save thread. Returns false if there is no
+    /// project or path to save to. The result is reported through
+    /// `state.save_error` and the dirty flag once the write finishes.
+    pub fn save_in_background(&mut self, cx: &mut Context<Self>) -> bool {
+        let Some(receiver) = self.state.begin_background_save() else {
+            return false;
+        };
+        self.state.save_view_state();
+        self.state.save_drum_rack();
+        cx.notify();
+
+        cx.spawn(async move |this, cx| {
+            let result = receiver
+                .await
+                .unwrap_or_else(|_| Err("Save thread stopped before finishing".to_string()));
+            if result.is_ok() {
+                tracing::error!("💾 Project saved");
+            }
+            cx.update(|cx| {
+                this.update(cx, |this, cx| {
+                    this.state.finish_background_save(result);
+                    cx.notify();
+                }).ok();
+            }).ok();
+        }).detach();
 
-impl Focusable for DawPanel {
-    fn focus_handle(&self, _: &App) -> FocusHandle {
-        self.focus_handle.clone()
+        true
     }
-}
 
-impl Render for DawPanel {
-    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
-        v_flex()
-            .key_context("DawPanel")
-            .track_focus(&self.focus_handle)
-            .size_full()
-            .bg(cx.theme().background)
-            .overflow_hidden()
-            // Handle mouse move for dragging with proper coordinates
-            .on_mouse_move(cx.listener(|this, event: &MouseMoveEvent, _window, cx| {
-                // Update drag visual feedback position and values
-                match &this.state.drag_state.clone() {
-                    DragState::DraggingFile { .. } => {
-                        // Just trigger re-render for visual feedback
-                        cx.notify();
-                    }
-                    DragState::DraggingClip { .. } => {
-                        // Trigger re-render for clip drag visual feedback
-                        cx.notify();
-                    }
-                    DragState::DraggingFader { track_id, start_mouse_y, start_volume } => {
-                        // Update fader position based on mouse drag
-                        let current_y = event.position.y.as_f32();
-                        let delta_y = *start_mouse_y - current_y; // Inverted: up = increase
-                        let delta_volume = delta_y / 100.0; // Sensitivity factor
-                        let new_volume = (*start_volume + delta_volume).clamp(0.0, 1.5);
-
-                        if let Some(ref mut project) = this.state.project {
-                            // Handle master fader (nil UUID)
-                            if track_id.is_nil() {
-                                project.master_track.volume = new_volume;
-
-                                // Sync to audio service in real-time
-                                if let Some(ref service) = this.state.audio_service {
-                                    let service = service.clone();
-                                    cx.spawn(async move |_this, _cx| {
-                                        let _ = service.set_master_volume(new_volume).await;
-                                    }).detach();
-                                }
-                            } else if let Some(track) = project.tracks.iter_mut().find(|t| t.id == *track_id) {
-                                track.volume = new_volume;
-
-                                // Sync to audio service in real-time
-                                if let Some(ref service) = this.state.audio_service {
-                                    let service = service.clone();
-                                    let track_id_val = *track_id;
-                                    cx.spawn(async move |_this, _cx| {
-                                        let _ = service.set_track_volume(track_id_val, new_volume).await;
-                                    }).detach();
-                                }
-                            }
-                        }
-                        cx.notify();
-                    }
-                    DragState::DraggingPan { track_id, start_mouse_x, start_pan } => {
-                        // Update pan position based on mouse drag
-                        let current_x = event.position.x.as_f32();
-                        let delta_x = current_x - *start_mouse_x;
-                        let delta_pan = delta_x / 50.0; // Sensitivity factor
-                        let new_pan = (*start_pan + delta_pan).clamp(-1.0, 1.0);
-
-                        if let Some(ref mut project) = this.state.project {
-                            if let Some(track) = project.tracks.iter_mut().find(|t| t.id == *track_id) {
-                                track.pan = new_pan;
+    /// Push the current project state of the given tracks to the audio service.
+    /// Tracks that no longer exist in the project are removed from the engine.
+    pub fn sync_tracks_to_audio_service(&self, track_ids: Vec<TrackId>, cx: &mut Context<Self>) {
+        self.state.dirty.mark_dirty();
+        if let (Some(ref project), Some(ref service)) = (&self.state.project, &self.state.audio_service) {
+            let service = service.clone();
+            let changes: Vec<(TrackId, Option<Track>)> = track_ids
+                .into_iter()
+                .map(|id| (id, project.get_track(id).cloned()))
+                .collect();
 
-                                // Sync to audio service in real-time
-                                if let Some(ref service) = this.state.audio_service {
-                                    let service = service.clone();
-                                    let track_id_val = *track_id;
-                                    cx.spawn(async move |_this, _cx| {
-                                        let _ = service.set_track_pan(track_id_val, new_pan).await;
-                                    }).detach();
-                                }
-                            }
+            cx.spawn(async move |_this, _cx| {
+                for (track_id, track) in changes {
+                    match track {
+                        Some(track) => {
+                            service.add_track(track).await;
                         }
-                        cx.notify();
+                        None => service.remove_track(track_id).await,
                     }
-                    DragState::DraggingTrackHeaderVolume { track_id, start_mouse_x, start_value } => {
-                        // Update track header volume slider (horizontal)
-                        let current_x = event.position.x;
-                        let delta_px = current_x - *start_mouse_x;
-                        let delta_value = delta_px / px(200.0); // Sensitivity factor (200 pixels = full range)
-                        let new_value = (*start_value + delta_value).clamp(0.0, 1.0);
+                }
+            }).detach();
+        }
+    }
 
-                        // Convert slider value (0..1) to dB then to linear
-                        let db = (new_value * 72.0) - 60.0; // Map 0..1 to -60..+12 dB
-                        let linear = 10f32.powf(db / 20.0);
+    /// Route a send to the next existing return track, or to a newly created
+    /// return when `create_new` is set or no returns exist yet.
+    pub fn cycle_send_target(&mut self, track_id: TrackId, send_idx: usize, create_new: bool, cx: &mut Context<Self>) {
+        let returns: Vec<TrackId> = self.state.return_track_ids()
+            .into_iter()
+            .filter(|id| *id != track_id)
+            .collect();
+        let current = self.state.get_track(track_id)
+            .and_then(|t| t.sends.get(send_idx))
+            .and_then(|s| s.target_track);
+
+        let mut changed = vec![track_id];
+        let target = if create_new || returns.is_empty() {
+            let Some(return_id) = self.state.add_return_track() else {
+                return;
+            };
+            changed.push(return_id);
+            Some(return_id)
+        } else {
+            match current.and_then(|id| returns.iter().position(|r| *r == id)) {
+                None => Some(returns[0]),
+                Some(pos) => returns.get(pos + 1).copied(),
+            }
+        };
 
-                        if let Some(ref mut project) = this.state.project {
-                            if let Some(track) = project.tracks.iter_mut().find(|t| t.id == *track_id) {
-                                track.volume = linear.clamp(0.0, 2.0);
+        self.state.set_send_target(track_id, send_idx, target);
+        tracing::error!("🎚️ Send {} of track {} routed to {:?}", send_idx, track_id, target);
+        self.sync_tracks_to_audio_service(changed, cx);
+        cx.notify();
+    }
 
-                                // Sync to audio service in real-time
-                                if let Some(ref service) = this.state.audio_service {
-                                    let service = service.clone();
-                                    let track_id_val = *track_id;
-                                    let volume = track.volume;
-                                    cx.spawn(async move |_this, _cx| {
-                                        let _ = service.set_track_volume(track_id_val, volume).await;
-                                    }).detach();
-                                }
-                            }
-                        }
-                        cx.notify();
-                    }
-                    DragState::DraggingTrackHeaderPan { track_id, start_mouse_x, start_value } => {
-                        // Update track header pan slider (horizontal)
-                        let current_x = event.position.x;
-                        let delta_px = current_x - *start_mouse_x;
-                        let delta_value = delta_px / px(100.0); // Sensitivity factor (100 pixels = full range)
-                        let new_value = (*start_value + delta_value).clamp(0.0, 1.0);
+    /// Add a VCA fader and put the selected tracks on it
+    pub fn add_vca(&mut self, cx: &mut Context<Self>) {
+        let Some(vca_id) = self.state.add_vca_track() else {
+            return;
+        };
+        let selected: Vec<TrackId> = self.state.selection.selected_track_ids.iter().copied().collect();
+        self.state.assign_vca(&selected, Some(vca_id));
+        tracing::error!("🎚️ Added VCA {} riding {} tracks", vca_id, self.state.bus_feeders(vca_id).len());
+        let mut changed = selected;
+        changed.push(vca_id);
+        self.sync_tracks_to_audio_service(changed, cx);
+        cx.notify();
+    }
 
-                        // Convert slider value (0..1) to pan (-1..1)
-                        let pan = (new_value * 2.0 - 1.0) as f32;
+    /// Move a track to the next VCA, or off the last one. With the track
+    /// selected, every selected track moves with it.
+    pub fn cycle_vca(&mut self, track_id: TrackId, cx: &mut Context<Self>) {
+        let vcas = self.state.vca_track_ids();
+        let current = self.state.get_track(track_id).and_then(|t| t.vca);
+        let target = match current.and_then(|id| vcas.iter().position(|v| *v == id)) {
+            None => vcas.first().copied(),
+            Some(pos) => vcas.get(pos + 1).copied(),
+        };
+        let tracks: Vec<TrackId> = if self.state.selection.selected_track_ids.contains(&track_id) {
+            self.state.selection.selected_track_ids.iter().copied().collect()
+        } else {
+            vec![track_id]
+        };
 
-                        if let Some(ref mut project) = this.state.project {
-                            if let Some(track) = project.tracks.iter_mut().find(|t| t.id == *track_id) {
-                                track.pan = pan.clamp(-1.0, 1.0);
+        self.state.assign_vca(&tracks, target);
+        tracing::error!("🎚️ {} tracks assigned to VCA {:?}", tracks.len(), target);
+        self.sync_tracks_to_audio_service(tracks, cx);
+        cx.notify();
+    }
 
-                                // Sync to audio service in real-time
-                                if let Some(ref service) = this.state.audio_service {
-                                    let service = service.clone();
-                                    let track_id_val = *track_id;
-                                    cx.spawn(async move |_this, _cx| {
-                                        let _ = service.set_track_pan(track_id_val, pan).await;
-                                    }).detach();
-                                }
-                            }
-                        }
-                        cx.notify();
+    /// Pick an audio file, copy it into the project and put it on a new
+    /// reference track, then match its level to the mix
+    pub fn import_reference_track(&mut self, cx: &mut Context<Self>) {
+        let Some(service) = self.state.audio_service.clone() else {
+            return;
+        };
+        cx.spawn(async move |this, cx| {
+            let Some(file) = rfd::AsyncFileDialog::new()
+                .add_filter("Audio Files", &["wav", "mp3", "ogg", "flac", "aiff"])
+                .set_title("Import Reference Track")
+                .pick_file()
+                .await
+            else {
+                return;
+            };
+            let imported = cx.update(|cx| {
+                this.update(cx, |this, _cx| this.state.import_audio_file(file.path().to_path_buf())).ok()
+            }).ok().flatten();
+            let path = match imported {
+                Some(Ok(path)) => path,
+                Some(Err(e)) => {
+                    tracing::error!("❌ Failed to import reference {:?}: {}", file.path(), e);
+                    return;
+                }
+                None => return,
+            };
+            let asset = match service.load_asset(path.clone()).await {
+                Ok(asset) => asset,
+                Err(e) => {
+                    tracing::error!("❌ Failed to load reference {:?}: {}", path, e);
+                    return;
+                }
+            };
+
+            cx.update(|cx| {
+                this.update(cx, |this, cx| {
+                    let duration = asset.asset_ref.duration_samples as SampleTime;
+                    if let Some(track_id) = this.state.add_reference_track(path, duration) {
+                        this.sync_tracks_to_audio_service(vec![track_id], cx);
+                        this.load_waveforms(cx);
+                        this.match_reference_loudness(track_id, cx);
                     }
-                    DragState::DraggingSend { track_id, send_idx, start_mouse_x, start_amount } => {
-                        // Update send level (horizontal drag)
-                        let current_x = event.position.x.as_f32();
-                        let delta_x = current_x - *start_mouse_x;
-                        let delta_amount = delta_x / 100.0; // Sensitivity: 100 pixels = full range (0.0 to 1.0)
-                        let new_amount = (*start_amount + delta_amount).clamp(0.0, 1.0);
+                    cx.notify();
+                }).ok();
+            }).ok();
+        }).detach();
+    }
 
-                        if let Some(ref mut project) = this.state.project {
-                            if let Some(track) = project.tracks.iter_mut().find(|t| t.id == *track_id) {
-                                // Ensure send exists
-                                while track.sends.len() <= *send_idx {
-                                    track.sends.push(super::super::audio_types::Send {
-                                        target_track: None,
-                                        amount: 0.0,
-                                        pre_fader: false,
-                                        enabled: false,
-                                    });
-                                }
-                                if let Some(send) = track.sends.get_mut(*send_idx) {
-                                    send.amount = new_amount;
-                                }
+    /// Pick an impulse response, copy it into the project and load it into
+    /// a convolution reverb
+    pub fn load_impulse_response(&mut self, track_id: TrackId, effect_id: EffectId, cx: &mut Context<Self>) {
+        cx.spawn(async move |this, cx| {
+            let Some(file) = rfd::AsyncFileDialog::new()
+                .add_filter("Audio Files", &["wav", "aiff", "flac"])
+                .set_title("Load Impulse Response")
+                .pick_file()
+                .await
+            else {
+                return;
+            };
+            cx.update(|cx| {
+                this.update(cx, |this, cx| {
+                    match this.state.import_audio_file(file.path().to_path_buf()) {
+                        Ok(path) => {
+                            tracing::error!("🏛️ Using impulse response {:?}", path);
+                            if this.state.set_effect_impulse_response(track_id, effect_id, Some(path)) {
+                                this.sync_tracks_to_audio_service(vec![track_id], cx);
                             }
                         }
-                        cx.notify();
+                        Err(e) => tracing::error!("❌ Failed to import impulse response {:?}: {}", file.path(), e),
                     }
-                    _ => {}
+                    cx.notify();
+                }).ok();
+            }).ok();
+        }).detach();
+    }
+
+    /// Pick an interleaved file and import each of its channels into the
+    /// project as a mono file on a track of its own
+    pub fn import_split_channels(&mut self, cx: &mut Context<Self>) {
+        let Some(service) = self.state.audio_service.clone() else {
+            return;
+        };
+        let Some(folder) = self.state.project_dir.as_ref().map(|dir| dir.join(AUDIO_FOLDER)) else {
+            tracing::error!("❌ Save the project before importing split channels");
+            return;
+        };
+        cx.spawn(async move |this, cx| {
+            let Some(file) = rfd::AsyncFileDialog::new()
+                .add_filter("Audio Files", &["wav", "mp3", "ogg", "flac", "aiff"])
+                .set_title("Import Channels as Mono Tracks")
+                .pick_file()
+                .await
+            else {
+                return;
+            };
+            let source = file.path().to_path_buf();
+            let asset = match service.load_asset(source.clone()).await {
+                Ok(asset) => asset,
+                Err(e) => {
+                    tracing::error!("❌ Failed to load {:?}: {}", source, e);
+                    return;
                 }
-            }))
-            // Handle mouse up to clear drag state
-            .on_mouse_up(gpui::MouseButton::Left, cx.listener(|this, _event: &MouseUpEvent, _window, cx| {
-                // Sync changes to audio service when drag completes
-                match &this.state.drag_state {
-                    DragState::DraggingFader { .. } => {
-                        // Volume already synced in real-time during drag
-                    }
-                    DragState::DraggingPan { .. } => {
-                        // Pan already synced in real-time during drag
+            };
+            let duration = asset.asset_ref.duration_samples as SampleTime;
+            let split = cx.background_executor().spawn(async move {
+                std::fs::create_dir_all(&folder)
+                    .map_err(anyhow::Error::from)
+                    .and_then(|_| split_asset_channels(&asset, &folder))
+            }).await;
+            let split = match split {
+                Ok(split) => split,
+                Err(e) => {
+                    tracing::error!("❌ Failed to split {:?}: {:#}", source, e);
+                    return;
+                }
+            };
+            let mut loaded = Vec::new();
+            for (_, path) in &split {
+                match service.load_asset(path.clone()).await {
+                    Ok(asset) => loaded.push((path.clone(), asset)),
+                    Err(e) => {
+                        tracing::error!("❌ Failed to load split channel {:?}: {}", path, e);
+                        return;
                     }
-                    DragState::DraggingTrackHeaderVolume { .. } => {
-                        // Volume already synced in real-time during drag
+                }
+            }
+
+            cx.update(|cx| {
+                this.update(cx, |this, cx| {
+                    this.state.loaded_assets.extend(loaded);
+                    let added = this.state.add_split_channel_tracks(split, duration);
+                    if let Some(dir) = this.state.project_dir.clone() {
+                        this.state.scan_audio_files(&dir);
                     }
-                    DragState::DraggingTrackHeaderPan { .. } => {
-                        // Pan already synced in real-time during drag
+                    this.sync_tracks_to_audio_service(added, cx);
+                    this.load_waveforms(cx);
+                    cx.notify();
+                }).ok();
+            }).ok();
+        }).detach();
+    }
+
+    /// Pick a folder of loops, copy each file into the project and lay them
+    /// out as a loop set on new tracks, stretched to the project tempo
+    pub fn import_loop_folder(&mut self, cx: &mut Context<Self>) {
+        let Some(service) = self.state.audio_service.clone() else {
+            return;
+        };
+        cx.spawn(async move |this, cx| {
+            let Some(folder) = rfd::AsyncFileDialog::new()
+                .set_title("Import Loop Folder")
+                .pick_folder()
+                .await
+            else {
+                return;
+            };
+            let files = match loop_files(folder.path()) {
+                Ok(files) if !files.is_empty() => files,
+                Ok(_) => {
+                    tracing::error!("⚠️ No audio files in {:?}", folder.path());
+                    return;
+                }
+                Err(e) => {
+                    tracing::error!("❌ Failed to read loop folder {:?}: {}", folder.path(), e);
+                    return;
+                }
+            };
+
+            let mut loaded = Vec::new();
+            for file in files {
+                let imported = cx.update(|cx| {
+                    this.update(cx, |this, _cx| this.state.import_audio_file(file.clone())).ok()
+                }).ok().flatten();
+                let path = match imported {
+                    Some(Ok(path)) => path,
+                    Some(Err(e)) => {
+                        tracing::error!("❌ Failed to import loop {:?}: {}", file, e);
+                        continue;
                     }
-                    DragState::DraggingSend { track_id, send_idx, .. } => {
-                        let track_id_val = *track_id;
-                        let send_idx_val = *send_idx;
-                        if let Some(ref service) = this.state.audio_service {
-                            let service = service.clone();
-                            let send_amount = this.state.project.as_ref()
-                                .and_then(|p| p.tracks.iter().find(|t| t.id == track_id_val))
-                                .and_then(|t| t.sends.get(send_idx_val))
-                                .map(|s| s.amount)
-                                .unwrap_or(0.0);
-
-                            // Future: Sync send levels to audio service
-                            tracing::error!("🎚️ Send {} level set to: {:.0}%", send_idx_val, send_amount * 100.0);
-                            // let _ = service.set_send_level(track_id_val, send_idx_val, send_amount).await;
+                    None => return,
+                };
+                match service.load_asset(path.clone()).await {
+                    Ok(asset) => loaded.push((path, asset.asset_ref.duration_samples as SampleTime)),
+                    Err(e) => tracing::error!("❌ Failed to load loop {:?}: {}", path, e),
+                }
+            }
+
+            cx.update(|cx| {
+                this.update(cx, |this, cx| {
+                    let Some(transport) = this.state.project.as_ref().map(|p| p.transport.clone()) else {
+                        return;
+                    };
+                    let layers = build_loop_set(&transport, &loaded);
+                    for layer in &layers {
+                        if let Some(ref reason) = layer.unwarped {
+                            tracing::error!("⚠️ '{}' imported unwarped: {}", layer.name, reason);
                         }
                     }
-                    DragState::DraggingFile { .. } => {
-                        // File drop is handled by timeline drop zones
-                        // Don't clear it here
+                    let added = this.state.add_loop_set(layers);
+                    tracing::info!("🔁 Imported {} loops at {:.1} BPM", added.len(), transport.tempo);
+                    this.sync_tracks_to_audio_service(added, cx);
+                    this.load_waveforms(cx);
+                    cx.notify();
+                }).ok();
+            }).ok();
+        }).detach();
+    }
+
+    /// Line up the first onsets of the selected clips with the reference
+    /// clip's: the focused clip if it's selected, otherwise the topmost
+    pub fn align_selected_transients(&mut self, cx: &mut Context<Self>) {
+        let refs = self.state.selected_clip_refs();
+        let (Some(project), Some(service)) = (&self.state.project, self.state.audio_service.clone()) else {
+            return;
+        };
+        if refs.len() < 2 {
+            return;
+        }
+        let reference = match self.state.keyboard_focus {
+            Some(KeyboardFocus::Clip(_, clip_id)) if refs.iter().any(|(_, id)| *id == clip_id) => clip_id,
+            _ => refs[0].1,
+        };
+        let clips: Vec<AudioClip> = project
+            .tracks
+            .iter()
+            .flat_map(|track| track.clips.iter())
+            .filter(|clip| refs.iter().any(|(_, id)| *id == clip.id))
+            .cloned()
+            .collect();
+
+        cx.spawn(async move |this, cx| {
+            let mut onsets = Vec::new();
+            for clip in clips {
+                let asset = match service.load_asset(clip.asset_path.clone()).await {
+                    Ok(asset) => asset,
+                    Err(e) => {
+                        tracing::error!("❌ Failed to load {:?} to find its transient: {}", clip.asset_path, e);
+                        continue;
                     }
-                    _ => {}
+                };
+                let name = clip.name.clone();
+                let found = cx
+                    .background_executor()
+                    .spawn(async move { clip_onset(&clip, &asset).map(|onset| (clip.id, onset)) })
+                    .await;
+                match found {
+                    Some(onset) => onsets.push(onset),
+                    None => tracing::error!("⚠️ No transient found in '{}', leaving it in place", name),
                 }
+            }
 
-                // Clear drag state when mouse is released (except for DraggingFile which is handled by drop zones)
-                if !matches!(this.state.drag_state, DragState::None | DragState::DraggingFile { .. }) {
-                    this.state.drag_state = DragState::None;
+            cx.update(|cx| {
+                this.update(cx, |this, cx| {
+                    if !onsets.iter().any(|(id, _)| *id == reference) {
+                        tracing::error!("⚠️ The reference clip has no transient to align to");
+                        return;
+                    }
+                    let changed = this.state.align_clip_onsets(reference, &onsets);
+                    this.sync_tracks_to_audio_service(changed, cx);
                     cx.notify();
-                }
+                }).ok();
+            }).ok();
+        }).detach();
+    }
+
+    /// Measure the mix and a reference track in the background and set the
+    /// reference's fader so both play at the same integrated loudness
+    pub fn match_reference_loudness(&mut self, track_id: TrackId, cx: &mut Context<Self>) {
+        let Some(project) = self.state.project.clone() else {
+            return;
+        };
+        if !self.state.reference_matching.insert(track_id) {
+            return;
+        }
+        let base_dir = self.state.project_dir.clone().unwrap_or_else(|| PathBuf::from("."));
+        cx.notify();
+
+        cx.spawn(async move |this, cx| {
+            let gain = cx.background_executor().spawn(async move {
+                reference_match_gain(&project, &base_dir, track_id)
+            }).await;
+
+            cx.update(|cx| {
+                this.update(cx, |this, cx| {
+                    this.state.reference_matching.remove(&track_id);
+                    match gain {
+                        Ok(gain) => {
+                            tracing::info!("🎚️ Reference {} matched to the mix at {:+.1} dB", track_id, 20.0 * gain.log10());
+                            this.set_fader_volume(track_id, gain, cx);
+                        }
+                        Err(e) => tracing::error!("❌ Failed to match reference loudness: {:#}", e),
+                    }
+                    cx.notify();
+                }).ok();
+            }).ok();
+        }).detach();
+    }
+
+    /// Rebuild the project inspector's statistics in the background, since
+    /// it reads every referenced file's size and walks the project folder
+    pub fn refresh_project_report(&mut self, cx: &mut Context<Self>) {
+        let Some(project) = self.state.project.clone() else {
+            return;
+        };
+        if self.state.building_report {
+            return;
+        }
+        self.state.building_report = true;
+        let base_dir = self.state.project_dir.clone().unwrap_or_else(|| PathBuf::from("."));
+        let project_path = self.state.project_path.clone();
+        cx.notify();
+
+        cx.spawn(async move |this, cx| {
+            let report = cx.background_executor().spawn(async move {
+                ProjectReport::build(&project, &base_dir)
+            }).await;
+
+            cx.update(|cx| {
+                this.update(cx, |this, cx| {
+                    this.state.building_report = false;
+                    // A report for a project that has since been closed is dropped
+                    if this.state.project_path == project_path {
+                        this.state.project_report = Some(report);
+                    }
+                    cx.notify();
+                }).ok();
+            }).ok();
+        }).detach();
+    }
+
+    /// Save the last project report, as JSON when the chosen file ends in
+    /// `.json` and as text otherwise
+    /// Start or stop timing every track and effect in the audio graph
+    pub fn toggle_dsp_profiling(&mut self, cx: &mut Context<Self>) {
+        let enabled = !self.state.dsp_profiling;
+        self.state.dsp_profiling = enabled;
+        self.state.dsp_profile = Default::default();
+        if let Some(ref service) = self.state.audio_service {
+            let service = service.clone();
+            cx.spawn(async move |_this, _cx| {
+                service.set_dsp_profiling(enabled).await;
+            }).detach();
+        }
+        tracing::error!("⏱️ DSP profiling {}", if enabled { "on" } else { "off" });
+        cx.notify();
+    }
+
+    pub fn export_project_report(&mut self, cx: &mut Context<Self>) {
+        let Some(report) = self.state.project_report.clone() else {
+            return;
+        };
+        let directory = self.state.project_dir.clone().unwrap_or_else(|| PathBuf::from("."));
+        cx.spawn(async move |_this, _cx| {
+            let Some(file) = rfd::AsyncFileDialog::new()
+                .add_filter("Text Report", &["txt"])
+                .add_filter("JSON Report", &["json"])
+                .set_directory(&directory)
+                .set_file_name(format!("{} Report.txt", report.name))
+                .set_title("Export Project Report")
+                .save_file()
+                .await
+            else {
+                return;
+            };
+            match report.write(file.path()) {
+                Ok(()) => tracing::info!("📋 Project report written to {:?}", file.path()),
+                Err(e) => tracing::error!("❌ Failed to export project report: {:#}", e),
+            }
+        }).detach();
+    }
+
+    /// Switch between hearing the mix and the reference tracks
+    pub fn toggle_reference_listening(&mut self, cx: &mut Context<Self>) {
+        if self.state.has_reference_tracks() {
+            self.set_reference_listening(!self.state.listening_reference, cx);
+        }
+    }
+
+    fn set_reference_listening(&mut self, listen: bool, cx: &mut Context<Self>) {
+        self.state.listening_reference = listen;
+        if let Some(ref service) = self.state.audio_service {
+            let service = service.clone();
+            cx.spawn(async move |_this, _cx| {
+                service.set_listen_reference(listen).await;
+            }).detach();
+        }
+        cx.notify();
+    }
+
+    /// Push the project's master limiter settings to the engine
+    pub fn sync_master_limiter(&self, cx: &mut Context<Self>) {
+        self.state.dirty.mark_dirty();
+        if let (Some(ref project), Some(ref service)) = (&self.state.project, &self.state.audio_service) {
+            let service = service.clone();
+            let settings = project.master_limiter;
+            cx.spawn(async move |_this, _cx| {
+                service.set_master_limiter(settings).await;
+            }).detach();
+        }
+    }
+
+    /// Change the master's speaker layout. Tracks keep their surround pan,
+    /// so switching back and forth loses nothing.
+    pub fn set_channel_layout(&mut self, layout: ChannelLayout, cx: &mut Context<Self>) {
+        let Some(project) = self.state.project.as_mut() else {
+            return;
+        };
+        if project.channel_layout == layout {
+            return;
+        }
+        project.channel_layout = layout;
+        self.state.dirty.mark_dirty();
+        if let Some(service) = self.state.audio_service.clone() {
+            cx.spawn(async move |_this, _cx| {
+                service.set_channel_layout(layout).await;
+            }).detach();
+        }
+        cx.notify();
+    }
+
+    /// Place a track in the surround field and push it to the engine
+    pub fn set_track_surround_pan(&mut self, track_id: TrackId, pan: SurroundPan, cx: &mut Context<Self>) {
+        let Some(track) = self.state.project.as_mut().and_then(|p| p.get_track_mut(track_id)) else {
+            return;
+        };
+        track.surround_pan = pan.clamped();
+        self.state.dirty.mark_dirty();
+        self.sync_tracks_to_audio_service(vec![track_id], cx);
+        cx.notify();
+    }
+
+    /// Start recording a take on every armed track, rolling from where
+    /// capture begins
+    pub fn start_recording(&mut self, cx: &mut Context<Self>) {
+        let targets = self.state.take_targets();
+        if targets.is_empty() {
+            tracing::error!("⚠️ Arm a track in a saved project to record");
+            return;
+        }
+        let rolling = self.state.is_playing;
+        let Some(take) = self.state.begin_take() else {
+            return;
+        };
+        self.state.is_playing = true;
+        if let Some(service) = self.state.audio_service.clone() {
+            cx.spawn(async move |_this, _cx| {
+                if !rolling {
+                    let _ = service.seek(take.captured_from).await;
+                }
+                if let Err(e) = service.start_take(targets).await {
+                    tracing::error!("❌ Failed to start recording: {}", e);
+                }
+                if !rolling {
+                    let _ = service.play().await;
+                }
+            }).detach();
+        }
+        cx.notify();
+    }
+
+    /// Stop the take and put it on the tracks it was recorded on
+    pub fn stop_recording(&mut self, cx: &mut Context<Self>) {
+        let Some(service) = self.state.audio_service.clone() else {
+            self.state.cancel_take();
+            return;
+        };
+        cx.spawn(async move |this, cx| {
+            let take = match service.finish_take().await {
+                Some(Ok(take)) => Some(take),
+                Some(Err(e)) => {
+                    tracing::error!("❌ Failed to finish the take: {}", e);
+                    None
+                }
+                None => None,
+            };
+            let mut assets = Vec::new();
+            for (_, path) in take.iter().flat_map(|take| &take.files) {
+                match service.load_asset(path.clone()).await {
+                    Ok(asset) => assets.push((path.clone(), asset)),
+                    Err(e) => tracing::error!("❌ Failed to load take {:?}: {}", path, e),
+                }
+            }
+            cx.update(|cx| {
+                this.update(cx, |this, cx| {
+                    this.state.loaded_assets.extend(assets);
+                    let placed = match take.and_then(|take| Some((take.captured_from?, take.end()?, take.files))) {
+                        Some((from, end, files)) => this.state.finish_take(files, from, end),
+                        None => {
+                            this.state.cancel_take();
+                            Vec::new()
+                        }
+                    };
+                    if !placed.is_empty() {
+                        this.sync_tracks_to_audio_service(placed, cx);
+                        this.load_waveforms(cx);
+                    }
+                    cx.notify();
+                }).ok();
+            }).ok();
+        }).detach();
+    }
+
+    /// Take a control change message from a hardware controller, stamped
+    /// with the engine's playhead at `time`, and push whatever it moved to
+    /// the engine
+    pub fn handle_control_change(&mut self, channel: u8, controller: u8, value: u8, time: SampleTime, cx: &mut Context<Self>) {
+        if let Some(track_id) = self.state.control_change(channel, controller, value, time) {
+            self.sync_tracks_to_audio_service(vec![track_id], cx);
+        }
+        cx.notify();
+    }
+
+    /// Route a message from the MIDI inputs
+    fn handle_midi_message(&mut self, message: MidiMessage, cx: &mut Context<Self>) {
+        if message.is_sync() {
+            self.handle_sync_message(&message, cx);
+        } else if let Some((channel, controller, value)) = message.control_change() {
+            self.handle_control_change(channel, controller, value, message.position, cx);
+        }
+    }
+
+    /// Take a MIDI Time Code or clock message from the sync input and make
+    /// the transport follow the master it came from
+    pub fn handle_sync_message(&mut self, message: &MidiMessage, cx: &mut Context<Self>) {
+        if !self.state.transport_chase.is_chasing() {
+            return;
+        }
+        let Some(transport) = self.state.project.as_ref().map(|p| p.transport.clone()) else {
+            return;
+        };
+        let events = self.state.transport_chase.receive_at(&message.bytes, message.arrived, message.position, &transport);
+        self.apply_chase_events(events, cx);
+    }
+
+    /// Move, start, stop or retime the transport as the master did
+    fn apply_chase_events(&mut self, events: Vec<ChaseEvent>, cx: &mut Context<Self>) {
+        for event in events {
+            match event {
+                ChaseEvent::Locate(position) => {
+                    let Some(beats) = self.state.project.as_ref().map(|p| p.transport.samples_to_beats(position)) else {
+                        continue;
+                    };
+                    self.seek_to_beat(beats, cx);
+                }
+                ChaseEvent::Play | ChaseEvent::Stop => {
+                    let playing = event == ChaseEvent::Play;
+                    self.state.is_playing = playing;
+                    if let Some(service) = self.state.audio_service.clone() {
+                        cx.spawn(async move |_this, _cx| {
+                            let _ = if playing { service.play().await } else { service.pause().await };
+                        }).detach();
+                    }
+                }
+                ChaseEvent::Tempo(tempo) => {
+                    let changed = self.state.chase_tempo(tempo);
+                    self.sync_tracks_to_audio_service(changed, cx);
+                    super::transport::sync_tempo(&self.state, cx);
+                    super::transport::sync_loop(&self.state, cx);
+                }
+            }
+        }
+        cx.notify();
+    }
+
+    /// Keep controller passes writing as the playhead moves, ending them
+    /// when the transport stops
+    fn advance_automation_recording(&mut self, position: SampleTime, is_playing: bool, cx: &mut Context<Self>) {
+        let changed = self.state.advance_automation_passes(position, !is_playing);
+        if !changed.is_empty() {
+            self.sync_tracks_to_audio_service(changed, cx);
+        }
+    }
+
+    /// Apply the clip properties dialog and push the edited clip to the engine
+    pub fn apply_clip_properties(&mut self, cx: &mut Context<Self>) {
+        let clip_id = self.state.clip_properties.as_ref().map(|d| d.clip_id);
+        match self.state.apply_clip_properties() {
+            Ok(track_id) => {
+                if let Some(clip_id) = clip_id {
+                    self.sync_clip_to_audio_service(track_id, clip_id, cx);
+                }
+            }
+            Err(e) => {
+                tracing::error!("❌ Invalid clip properties: {}", e);
+                if let Some(ref mut dialog) = self.state.clip_properties {
+                    dialog.error = Some(e.to_string());
+                }
+            }
+        }
+        cx.notify();
+    }
+
+    /// Set the project tempo from the loop in the clip properties dialog
+    pub fn set_tempo_from_clip(&mut self, cx: &mut Context<Self>) {
+        match self.state.set_tempo_from_clip() {
+            Ok(changed) => {
+                self.sync_tracks_to_audio_service(changed, cx);
+                super::transport::sync_tempo(&self.state, cx);
+                super::transport::sync_loop(&self.state, cx);
+            }
+            Err(e) => {
+                tracing::error!("⚠️ {}", e);
+                if let Some(ref mut dialog) = self.state.clip_properties {
+                    dialog.error = Some(e.to_string());
+                }
+            }
+        }
+        cx.notify();
+    }
+
+    /// Apply the sample rate dialog, then resample loaded buffers and push the
+    /// rescaled project to the engine
+    pub fn apply_sample_rate_change(&mut self, cx: &mut Context<Self>) {
+        let (rate, mode) = match self.state.apply_sample_rate_change() {
+            Ok(change) => change,
+            Err(e) => {
+                tracing::error!("❌ Failed to change sample rate: {}", e);
+                self.state.sample_rate_dialog = None;
+                cx.notify();
+                return;
+            }
+        };
+        tracing::error!("🎛️ Project sample rate changed to {} Hz ({:?})", rate, mode);
+
+        // Resample before the rescaled clips reach the engine, then refresh the
+        // UI's copies of loaded assets
+        if let Some(ref service) = self.state.audio_service {
+            let service = service.clone();
+            let paths: Vec<PathBuf> = self.state.loaded_assets.keys().cloned().collect();
+            cx.spawn(async move |this, cx| {
+                for (path, e) in service.set_asset_sample_rate(rate).await {
+                    tracing::error!("❌ Failed to resample {:?}: {}", path, e);
+                }
+                let mut refreshed = Vec::new();
+                for path in paths {
+                    if let Ok(asset) = service.load_asset(path.clone()).await {
+                        refreshed.push((path, asset));
+                    }
+                }
+                cx.update(|cx| {
+                    this.update(cx, |this, cx| {
+                        this.state.loaded_assets.extend(refreshed);
+                        this.sync_project_to_audio_service(cx);
+                        cx.notify();
+                    }).ok();
+                }).ok();
+            }).detach();
+        }
+        cx.notify();
+    }
+
+    /// Turn the clipping scan of loaded files on or off, then reload loaded
+    /// buffers so their repairs and reports follow it
+    pub fn set_scan_clipping(&mut self, scan_clipping: bool, cx: &mut Context<Self>) {
+        self.state.set_preferences(EditPreferences { scan_clipping, ..self.state.preferences });
+        if let Some(ref service) = self.state.audio_service {
+            let service = service.clone();
+            let paths: Vec<PathBuf> = self.state.loaded_assets.keys().cloned().collect();
+            cx.spawn(async move |this, cx| {
+                for (path, e) in service.set_scan_clipping(scan_clipping).await {
+                    tracing::error!("❌ Failed to reload {:?}: {}", path, e);
+                }
+                let mut refreshed = Vec::new();
+                for path in paths {
+                    if let Ok(asset) = service.load_asset(path.clone()).await {
+                        refreshed.push((path, asset));
+                    }
+                }
+                cx.update(|cx| {
+                    this.update(cx, |this, cx| {
+                        this.state.loaded_assets.extend(refreshed);
+                        this.sync_project_to_audio_service(cx);
+                        cx.notify();
+                    }).ok();
+                }).ok();
+            }).detach();
+        }
+        cx.notify();
+    }
+
+    /// Turn arm-follows-selection on or off. Turning it on arms the current
+    /// selection straight away.
+    pub fn set_arm_follows_selection(&mut self, arm_follows_selection: bool, cx: &mut Context<Self>) {
+        self.state.set_preferences(EditPreferences { arm_follows_selection, ..self.state.preferences });
+        self.arm_selected_tracks(cx);
+        cx.notify();
+    }
+
+    /// Select a track, moving the record arm to it when arming follows the
+    /// selection
+    pub fn select_track(&mut self, track_id: TrackId, multi: bool, cx: &mut Context<Self>) {
+        self.state.select_track(track_id, multi);
+        self.arm_selected_tracks(cx);
+    }
+
+    /// Arm the selected tracks if arming follows the selection, and send the
+    /// arm and monitor changes to the engine
+    fn arm_selected_tracks(&mut self, cx: &mut Context<Self>) {
+        let changed = self.state.arm_selected_tracks();
+        if !changed.is_empty() {
+            tracing::error!("⏺️ Record arm moved to the selection, {} tracks changed", changed.len());
+            self.sync_tracks_to_audio_service(changed, cx);
+        }
+    }
+
+    /// Replace a clip in the audio engine with its current project state
+    pub fn sync_clip_to_audio_service(&self, track_id: TrackId, clip_id: ClipId, cx: &mut Context<Self>) {
+        self.state.dirty.mark_dirty();
+        if let (Some(clip), Some(ref service)) = (self.state.get_clip(track_id, clip_id), &self.state.audio_service) {
+            let service = service.clone();
+            let clip = clip.clone();
+            cx.spawn(async move |_this, _cx| {
+                if let Err(e) = service.remove_clip_from_track(track_id, clip.id).await {
+                    tracing::error!("❌ Failed to remove old clip from audio service: {}", e);
+                }
+                if let Err(e) = service.add_clip_to_track(track_id, clip).await {
+                    tracing::error!("❌ Failed to add updated clip to audio service: {}", e);
+                }
+            }).detach();
+        }
+    }
+
+    /// Delete tracks (undoable) and sync the engine
+    pub fn delete_tracks(&mut self, track_ids: &[TrackId], cx: &mut Context<Self>) {
+        let changed = self.state.delete_tracks(track_ids);
+        if !changed.is_empty() {
+            self.sync_tracks_to_audio_service(changed, cx);
+            // Without a reference left there'd be nothing to hear
+            if self.state.listening_reference && !self.state.has_reference_tracks() {
+                self.set_reference_listening(false, cx);
+            }
+            cx.notify();
+        }
+    }
+
+    pub fn undo(&mut self, cx: &mut Context<Self>) {
+        if let Some(changed) = self.state.undo() {
+            self.sync_tracks_to_audio_service(changed, cx);
+            super::transport::sync_tempo(&self.state, cx);
+            super::transport::sync_loop(&self.state, cx);
+            cx.notify();
+        }
+    }
+
+    pub fn redo(&mut self, cx: &mut Context<Self>) {
+        if let Some(changed) = self.state.redo() {
+            self.sync_tracks_to_audio_service(changed, cx);
+            super::transport::sync_tempo(&self.state, cx);
+            super::transport::sync_loop(&self.state, cx);
+            cx.notify();
+        }
+    }
+
+    /// Insert silence at the playhead across all tracks
+    pub fn insert_silence(&mut self, cx: &mut Context<Self>) {
+        match self.state.insert_silence_at_playhead() {
+            Ok(changed) => {
+                self.sync_tracks_to_audio_service(changed, cx);
+                super::transport::sync_loop(&self.state, cx);
+            }
+            Err(e) => tracing::error!("⚠️ {}", e),
+        }
+        cx.notify();
+    }
+
+    pub fn duplicate_to_fill(&mut self, cx: &mut Context<Self>) {
+        let changed = self.state.duplicate_selected_to_fill();
+        self.sync_tracks_to_audio_service(changed, cx);
+        cx.notify();
+    }
+
+    /// Change the project tempo, moving clips that follow tempo
+    pub fn change_tempo(&mut self, tempo: f32, cx: &mut Context<Self>) {
+        match self.state.change_tempo(tempo) {
+            Ok(changed) => {
+                self.sync_tracks_to_audio_service(changed, cx);
+                super::transport::sync_tempo(&self.state, cx);
+                super::transport::sync_loop(&self.state, cx);
+            }
+            Err(e) => tracing::error!("⚠️ {}", e),
+        }
+        cx.notify();
+    }
+
+    /// Take a track out of processing, or put it back. Unlike mute, an
+    /// inactive track isn't processed or metered and feeds no sends.
+    pub fn toggle_track_active(&mut self, track_id: TrackId, cx: &mut Context<Self>) {
+        let Some(track) = self.state.get_track_mut(track_id) else {
+            return;
+        };
+        track.active = !track.active;
+        let active = track.active;
+        self.state.dirty.mark_dirty();
+
+        if let Some(ref service) = self.state.audio_service {
+            let service = service.clone();
+            cx.spawn(async move |_this, _cx| {
+                if let Err(e) = service.set_track_active(track_id, active).await {
+                    tracing::error!("❌ Failed to set track active: {}", e);
+                }
+            }).detach();
+        }
+        cx.notify();
+    }
+
+    /// Register a tap tempo tap and follow the tapped tempo
+    pub fn tap_tempo(&mut self, cx: &mut Context<Self>) {
+        let changed = self.state.tap_tempo();
+        self.sync_tracks_to_audio_service(changed, cx);
+        super::transport::sync_tempo(&self.state, cx);
+        super::transport::sync_loop(&self.state, cx);
+        cx.notify();
+    }
+
+    /// Trim the project to the end of its last clip
+    pub fn trim_project_end(&mut self, cx: &mut Context<Self>) {
+        match self.state.trim_project_end() {
+            Ok(changed) => {
+                self.sync_tracks_to_audio_service(changed, cx);
+                super::transport::sync_loop(&self.state, cx);
+            }
+            Err(e) => tracing::error!("⚠️ {}", e),
+        }
+        cx.notify();
+    }
+
+    /// Copy selected clips to the OS clipboard as JSON
+    pub fn copy_selected_clips(&mut self, cx: &mut Context<Self>) {
+        let Some(payload) = self.state.copy_selected_clips() else {
+            return;
+        };
+
+        match payload.to_json() {
+            Ok(json) => cx.write_to_clipboard(ClipboardItem::new_string(json)),
+            Err(e) => tracing::error!("❌ Failed to copy clips: {}", e),
+        }
+    }
+
+    /// Copy one effect of a track, or its whole chain, to the OS clipboard
+    pub fn copy_effects(&mut self, track_id: TrackId, only: Option<EffectId>, cx: &mut Context<Self>) {
+        let Some(clipboard) = self.state.copy_effects(track_id, only) else {
+            return;
+        };
+        match clipboard.to_json() {
+            Ok(json) => cx.write_to_clipboard(ClipboardItem::new_string(json)),
+            Err(e) => tracing::error!("❌ Failed to copy effects: {}", e),
+        }
+    }
+
+    /// Paste effects from the OS clipboard onto a track, or only their
+    /// parameters onto the effect `onto`
+    pub fn paste_effects(&mut self, track_id: TrackId, onto: Option<EffectId>, cx: &mut Context<Self>) {
+        let text = cx.read_from_clipboard().and_then(|item| item.text()).unwrap_or_default();
+        match EffectClipboard::from_json(&text) {
+            Ok(clipboard) => {
+                if self.state.paste_effects(track_id, &clipboard, onto) {
+                    self.sync_tracks_to_audio_service(vec![track_id], cx);
+                }
+            }
+            Err(e) => self.state.effect_error = Some(e.to_string()),
+        }
+        cx.notify();
+    }
+
+    /// Paste clips from the OS clipboard, asking the user to locate any
+    /// audio files that can't be found
+    pub fn paste_clips(&mut self, cx: &mut Context<Self>) {
+        let payload = cx
+            .read_from_clipboard()
+            .and_then(|item| item.text())
+            .and_then(|text| ClipboardPayload::from_json(&text).ok())
+            .or_else(|| self.state.clip_clipboard.clone());
+
+        let Some(mut payload) = payload.filter(|p| !p.is_empty()) else {
+            return;
+        };
+
+        let search_dirs: Vec<PathBuf> = self.state.project_dir
+            .iter()
+            .map(|dir| dir.join(AUDIO_FOLDER))
+            .collect();
+        let missing = payload.resolve_asset_paths(&search_dirs);
+
+        if missing.is_empty() {
+            self.insert_pasted_clips(payload, cx);
+            return;
+        }
+
+        // Relink missing files before pasting
+        cx.spawn(async move |this, cx| {
+            for path in missing {
+                let name = path.file_name()
+                    .map(|n| n.to_string_lossy().into_owned())
+                    .unwrap_or_else(|| path.display().to_string());
+
+                let picked = rfd::AsyncFileDialog::new()
+                    .add_filter("Audio Files", &["wav", "mp3", "ogg", "flac", "aiff"])
+                    .set_title(format!("Locate missing file: {}", name))
+                    .pick_file()
+                    .await;
+
+                match picked {
+                    Some(file) => payload.relink(&path, file.path().to_path_buf()),
+                    None => tracing::error!("❌ Pasting clip with missing file: {}", path.display()),
+                }
+            }
+
+            let _ = cx.update(|cx| {
+                let _ = this.update(cx, |this, cx| {
+                    this.insert_pasted_clips(payload, cx);
+                });
+            });
+        }).detach();
+    }
+
+    fn insert_pasted_clips(&mut self, payload: ClipboardPayload, cx: &mut Context<Self>) {
+        let pasted = self.state.paste_clips(payload);
+
+        if let Some(ref service) = self.state.audio_service {
+            let service = service.clone();
+            cx.spawn(async move |_this, _cx| {
+                for (track_id, clip) in pasted {
+                    if let Err(e) = service.add_clip_to_track(track_id, clip).await {
+                        tracing::error!("❌ Failed to add pasted clip: {}", e);
+                    }
+                }
+            }).detach();
+        }
+
+        cx.notify();
+    }
+
+    /// Ask for a folder and export every selected clip to its own file in the
+    /// background, reporting progress to the clip export dialog
+    pub fn export_selected_clips(&mut self, cx: &mut Context<Self>) {
+        let clips = self.state.selected_clip_refs();
+        if clips.is_empty() {
+            return;
+        }
+        let Some(project) = self.state.project.clone() else {
+            return;
+        };
+        let base_dir = self.state.project_dir.clone().unwrap_or_else(|| PathBuf::from("."));
+        let options = ClipExportOptions {
+            include_track: self.state.clip_export_include_track,
+            ..ClipExportOptions::default()
+        };
+
+        let total = clips.len();
+        self.run_export_batch("Export Clips", "clip", total, Vec::new(), cx, move |folder, progress| {
+            export_clips(&project, &base_dir, &clips, &folder, &options, progress)
+        });
+    }
+
+    /// Ask for a folder and render the audio from each marker to the next
+    /// into a file named after the marker. With `only` set, just that
+    /// marker's region is exported.
+    pub fn export_marker_regions(&mut self, only: Option<MarkerId>, cx: &mut Context<Self>) {
+        let Some(project) = self.state.project.clone() else {
+            return;
+        };
+        let (mut regions, warnings) = marker_regions(&project);
+        if let Some(marker) = only {
+            regions.retain(|r| r.marker == marker);
+        }
+        for warning in &warnings {
+            tracing::error!("⚠️ {}", warning);
+        }
+        if regions.is_empty() {
+            return;
+        }
+        let base_dir = self.state.project_dir.clone().unwrap_or_else(|| PathBuf::from("."));
+
+        let total = regions.len();
+        self.run_export_batch("Export Cues", "cue", total, warnings, cx, move |folder, progress| {
+            export_marker_regions(&project, &base_dir, &regions, &folder, &RenderOptions::default(), progress)
+        });
+    }
+
+    /// Ask for a folder and render each arrangement region into a file
+    /// named after it. With `only` set, just that region is exported.
+    pub fn export_region_cues(&mut self, only: Option<RegionId>, cx: &mut Context<Self>) {
+        let Some(project) = self.state.project.clone() else {
+            return;
+        };
+        let mut regions = region_cues(&project);
+        if let Some(region) = only {
+            regions.retain(|r| r.marker == region);
+        }
+        if regions.is_empty() {
+            return;
+        }
+        let base_dir = self.state.project_dir.clone().unwrap_or_else(|| PathBuf::from("."));
+
+        let total = regions.len();
+        self.run_export_batch("Export Region Cues", "cue", total, Vec::new(), cx, move |folder, progress| {
+            export_marker_regions(&project, &base_dir, &regions, &folder, &RenderOptions::default(), progress)
+        });
+    }
+
+    /// Ask for a folder and flatten the project into it: every audio track
+    /// rendered to one file with its effects and automation baked in, and a
+    /// new project playing them
+    pub fn flatten_project(&mut self, cx: &mut Context<Self>) {
+        let Some(project) = self.state.project.clone() else {
+            return;
+        };
+        let base_dir = self.state.project_dir.clone().unwrap_or_else(|| PathBuf::from("."));
+        // Two seconds past the last clip for reverb and delay tails
+        let options = FlattenOptions {
+            tail_samples: (2.0 * project.sample_rate) as SampleTime,
+            ..FlattenOptions::default()
+        };
+
+        let total = project.tracks.iter().filter(|t| t.track_type == TrackType::Audio && t.active && !t.clips.is_empty()).count();
+        self.run_export_batch("Flatten Project", "track", total, Vec::new(), cx, move |folder, progress| {
+            let output = flattened_project_path(&folder, &project.name);
+            flatten_project(&project, &base_dir, &output, &options, progress)
+        });
+    }
+
+    /// Ask where to save and render the master mix to a WAV, with a JSON
+    /// sidecar beside it giving marker, loop, tempo and region times for a
+    /// game to schedule against
+    pub fn export_mix_with_timing(&mut self, cx: &mut Context<Self>) {
+        let Some(project) = self.state.project.clone() else {
+            return;
+        };
+        let base_dir = self.state.project_dir.clone().unwrap_or_else(|| PathBuf::from("."));
+        cx.spawn(async move |_this, cx| {
+            let Some(file) = rfd::AsyncFileDialog::new()
+                .add_filter("WAV Audio", &["wav"])
+                .set_directory(&base_dir)
+                .set_file_name(format!("{}.wav", project.name))
+                .set_title("Export Mix with Timing Data")
+                .save_file()
+                .await
+            else {
+                return;
+            };
+            let output = file.path().with_extension("wav");
+            let result = cx.background_executor().spawn(async move {
+                render_with_timing(&project, &base_dir, &output, &RenderOptions::default())
+            }).await;
+            match result {
+                Ok((summary, sidecar)) => tracing::info!(
+                    "🎮 Exported {} frames to {:?} with timing data in {:?}",
+                    summary.frames,
+                    summary.output_path,
+                    sidecar
+                ),
+                Err(e) => tracing::error!("❌ Failed to export mix with timing data: {:#}", e),
+            }
+        }).detach();
+    }
+
+    /// Pick where to save the picture the reference image dialog describes,
+    /// then draw it on a background thread
+    pub fn export_reference_image(&mut self, cx: &mut Context<Self>) {
+        let (Some(dialog), Some(project)) = (self.state.reference_image_dialog.take(), self.state.project.clone()) else {
+            return;
+        };
+        let (start, end) = self.state.reference_image_range();
+        let name = match dialog.source {
+            Some(track_id) => self.state.get_track(track_id).map_or_else(|| project.name.clone(), |t| t.name.clone()),
+            None => project.name.clone(),
+        };
+        let base_dir = self.state.project_dir.clone().unwrap_or_else(|| PathBuf::from("."));
+        cx.notify();
+        cx.spawn(async move |_this, cx| {
+            let Some(file) = rfd::AsyncFileDialog::new()
+                .add_filter("PNG Image", &["png"])
+                .set_directory(&base_dir)
+                .set_file_name(format!("{}.png", name))
+                .set_title("Export Reference Image")
+                .save_file()
+                .await
+            else {
+                return;
+            };
+            let output = file.path().with_extension("png");
+            let result = cx.background_executor().spawn(async move {
+                export_reference_image(&project, &base_dir, dialog.source, start, end, &dialog.settings, &output)
+            }).await;
+            if let Err(e) = result {
+                tracing::error!("❌ Failed to export reference image of '{}': {:#}", name, e);
+            }
+        }).detach();
+    }
+
+    /// Pick a folder, then run `export` on a background thread with the
+    /// clip export dialog showing its progress and result. `warnings` found
+    /// before starting are added to the result.
+    fn run_export_batch<F>(
+        &mut self,
+        title: &'static str,
+        item: &'static str,
+        total: usize,
+        warnings: Vec<String>,
+        cx: &mut Context<Self>,
+        export: F,
+    ) where
+        F: FnOnce(PathBuf, Box<dyn FnMut(usize, usize) -> bool + Send>) -> anyhow::Result<ClipExportReport> + Send + 'static,
+    {
+        if self.state.clip_export.as_ref().is_some_and(|e| e.summary.is_none()) {
+            return;
+        }
+
+        cx.spawn(async move |this, cx| {
+            let Some(folder) = rfd::AsyncFileDialog::new()
+                .set_title(format!("{} To Folder", title))
+                .pick_folder()
+                .await
+            else {
+                return;
+            };
+            let folder = folder.path().to_path_buf();
+
+            let cancel = Arc::new(AtomicBool::new(false));
+            let progress = ClipExportProgress { title, item, done: 0, total, cancel: cancel.clone(), summary: None };
+            cx.update(|cx| {
+                this.update(cx, |this, cx| {
+                    this.state.clip_export = Some(progress);
+                    cx.notify();
+                }).ok();
+            }).ok();
+
+            let (tx, mut rx) = mpsc::unbounded();
+            let batch = cx.background_executor().spawn(async move {
+                export(folder, Box::new(move |done, _| {
+                    let _ = tx.unbounded_send(done);
+                    !cancel.load(Ordering::Relaxed)
+                }))
+            });
+
+            while let Some(done) = rx.next().await {
+                cx.update(|cx| {
+                    this.update(cx, |this, cx| {
+                        if let Some(ref mut export) = this.state.clip_export {
+                            export.done = done;
+                        }
+                        cx.notify();
+                    }).ok();
+                }).ok();
+            }
+
+            let summary = match batch.await {
+                Ok(report) => {
+                    for (name, e) in &report.failed {
+                        tracing::error!("❌ Failed to export {} '{}': {}", item, name, e);
+                    }
+                    let mut summary = format!("Exported {} of {} {}s", report.exported.len(), total, item);
+                    if !report.failed.is_empty() {
+                        summary.push_str(&format!(", {} failed", report.failed.len()));
+                    }
+                    if report.cancelled {
+                        summary.push_str(" (cancelled)");
+                    }
+                    for warning in &warnings {
+                        summary.push_str(&format!("\n{}", warning));
+                    }
+                    summary
+                }
+                Err(e) => {
+                    tracing::error!("❌ {} failed: {:#}", title, e);
+                    format!("Export failed: {}", e)
+                }
+            };
+            cx.update(|cx| {
+                this.update(cx, |this, cx| {
+                    if let Some(ref mut export) = this.state.clip_export {
+                        export.summary = Some(summary);
+                    }
+                    cx.notify();
+                }).ok();
+            }).ok();
+        }).detach();
+    }
+
+    /// Queue a render of the open project as last saved, to one file or one
+    /// file per marker region, in a Renders folder beside it
+    pub fn queue_current_project(&mut self, regions: bool, cx: &mut Context<Self>) {
+        let Some(project_path) = self.state.project_path.clone() else {
+            return;
+        };
+        let job = RenderJob::new(&project_path, PathBuf::new());
+        let job = if regions {
+            RenderJob {
+                name: format!("{} regions", job.name),
+                output: queued_render_path(&project_path, &format!("{} Regions", job.name)),
+                range: RenderRange::AllMarkers,
+                ..job
+            }
+        } else {
+            RenderJob { output: queued_render_path(&project_path, &format!("{}.wav", job.name)), ..job }
+        };
+        if self.state.dirty.is_dirty() {
+            tracing::error!("⚠️ '{}' is queued as last saved, unsaved edits won't be rendered", job.name);
+        }
+        self.state.render_queue.add_job(job);
+        cx.notify();
+    }
+
+    /// Pick project files and queue a whole-project render of each
+    pub fn queue_projects(&mut self, cx: &mut Context<Self>) {
+        cx.spawn(async move |this, cx| {
+            let Some(files) = rfd::AsyncFileDialog::new()
+                .add_filter("DAW Projects", &["pdaw"])
+                .set_title("Queue Projects to Render")
+                .pick_files()
+                .await
+            else {
+                return;
+            };
+            cx.update(|cx| {
+                this.update(cx, |this, cx| {
+                    for file in files {
+                        let project = file.path().to_path_buf();
+                        let job = RenderJob::new(&project, PathBuf::new());
+                        let output = queued_render_path(&project, &format!("{}.wav", job.name));
+                        this.state.render_queue.add_job(RenderJob { output, ..job });
+                    }
+                    cx.notify();
+                }).ok();
+            }).ok();
+        }).detach();
+    }
+
+    /// Replace the queued jobs with a saved queue file
+    pub fn open_render_queue_file(&mut self, cx: &mut Context<Self>) {
+        cx.spawn(async move |this, cx| {
+            let Some(file) = rfd::AsyncFileDialog::new()
+                .add_filter("Render Queue", &["json"])
+                .set_title("Open Render Queue")
+                .pick_file()
+                .await
+            else {
+                return;
+            };
+            let queue = match RenderQueue::load(file.path()) {
+                Ok(queue) => queue,
+                Err(e) => {
+                    tracing::error!("❌ Failed to open render queue: {:#}", e);
+                    return;
+                }
+            };
+            cx.update(|cx| {
+                this.update(cx, |this, cx| {
+                    if !this.state.render_queue.running {
+                        this.state.render_queue.states = vec![JobState::Queued; queue.jobs.len()];
+                        this.state.render_queue.queue = queue;
+                    }
+                    cx.notify();
+                }).ok();
+            }).ok();
+        }).detach();
+    }
+
+    /// Save the queued jobs for a pipeline to run later
+    pub fn save_render_queue_file(&mut self, cx: &mut Context<Self>) {
+        let queue = self.state.render_queue.queue.clone();
+        cx.spawn(async move |_this, _cx| {
+            let Some(file) = rfd::AsyncFileDialog::new()
+                .add_filter("Render Queue", &["json"])
+                .set_file_name("Render Queue.json")
+                .set_title("Save Render Queue")
+                .save_file()
+                .await
+            else {
+                return;
+            };
+            match queue.save(file.path()) {
+                Ok(()) => tracing::info!("🎬 Render queue saved to {:?}", file.path()),
+                Err(e) => tracing::error!("❌ Failed to save render queue: {:#}", e),
+            }
+        }).detach();
+    }
+
+    /// Run the queued jobs one after another on a background thread. The
+    /// stop button lets the running job finish and skips the rest.
+    pub fn run_render_queue(&mut self, cx: &mut Context<Self>) {
+        let dialog = &mut self.state.render_queue;
+        if dialog.running || dialog.queue.jobs.is_empty() {
+            return;
+        }
+        dialog.running = true;
+        dialog.cancel = Arc::new(AtomicBool::new(false));
+        dialog.states = vec![JobState::Queued; dialog.queue.jobs.len()];
+        let queue = dialog.queue.clone();
+        let cancel = dialog.cancel.clone();
+        cx.notify();
+
+        cx.spawn(async move |this, cx| {
+            let (tx, mut rx) = mpsc::unbounded();
+            let jobs = queue.jobs.clone();
+            let run = cx.background_executor().spawn(async move {
+                queue.run(move |index, state| {
+                    let _ = tx.unbounded_send((index, state.clone()));
+                    !cancel.load(Ordering::Relaxed)
+                })
+            });
+
+            while let Some((index, state)) = rx.next().await {
+                cx.update(|cx| {
+                    this.update(cx, |this, cx| {
+                        if let Some(slot) = this.state.render_queue.states.get_mut(index) {
+                            *slot = state;
+                        }
+                        cx.notify();
+                    }).ok();
+                }).ok();
+            }
+
+            let states = run.await;
+            tracing::info!("🎬 Render queue finished\n{}", queue_summary(&jobs, &states));
+            cx.update(|cx| {
+                this.update(cx, |this, cx| {
+                    this.state.render_queue.states = states;
+                    this.state.render_queue.running = false;
+                    cx.notify();
+                }).ok();
+            }).ok();
+        }).detach();
+    }
+
+    /// Set a track's fader, or the master's for the nil ID, and send it to the engine
+    pub fn set_fader_volume(&mut self, track_id: TrackId, volume: f32, cx: &mut Context<Self>) {
+        let Some(ref mut project) = self.state.project else {
+            return;
+        };
+        if track_id.is_nil() {
+            project.master_track.volume = volume;
+        } else if let Some(track) = project.tracks.iter_mut().find(|t| t.id == track_id) {
+            track.volume = volume;
+        } else {
+            return;
+        }
+        self.state.dirty.mark_dirty();
+
+        if let Some(ref service) = self.state.audio_service {
+            let service = service.clone();
+            cx.spawn(async move |_this, _cx| {
+                if track_id.is_nil() {
+                    let _ = service.set_master_volume(volume).await;
+                } else {
+                    let _ = service.set_track_volume(track_id, volume).await;
+                }
+            }).detach();
+        }
+    }
+
+    /// Center a track's pan and send it to the engine
+    pub fn reset_pan(&mut self, track_id: TrackId, cx: &mut Context<Self>) {
+        let Some(track) = self.state.get_track_mut(track_id) else {
+            return;
+        };
+        track.pan = 0.0;
+        self.state.dirty.mark_dirty();
+
+        if let Some(ref service) = self.state.audio_service {
+            let service = service.clone();
+            cx.spawn(async move |_this, _cx| {
+                let _ = service.set_track_pan(track_id, 0.0).await;
+            }).detach();
+        }
+    }
+
+    /// Apply the dB value typed into a fader readout
+    fn apply_fader_entry(&mut self, cx: &mut Context<Self>) {
+        let Some(entry) = self.state.fader_entry.take() else {
+            return;
+        };
+        match parse_db(&entry.text) {
+            Some(db) => {
+                let volume = if db <= -100.0 { 0.0 } else { 10.0_f32.powf(db / 20.0) };
+                let before = self.state.get_track(entry.track_id).map(|t| t.volume);
+                self.set_fader_volume(entry.track_id, volume, cx);
+                if let Some(before) = before {
+                    self.state.record_vca_fader(entry.track_id, before);
+                }
+            }
+            None => tracing::error!("⚠️ Ignoring fader value '{}', expected a level in dB", entry.text),
+        }
+        cx.notify();
+    }
+
+    /// Apply the tempo or time signature typed into the transport. An entry
+    /// that doesn't parse is kept open with the reason, so it can be fixed.
+    fn apply_transport_entry(&mut self, cx: &mut Context<Self>) {
+        let Some(entry) = self.state.transport_entry.take() else {
+            return;
+        };
+        let applied = match entry.field {
+            TransportField::Tempo => parse_tempo(&entry.text).map(|tempo| self.change_tempo(tempo, cx)),
+            TransportField::TimeSignature => parse_time_signature(&entry.text)
+                .and_then(|(numerator, denominator)| self.state.change_time_signature(numerator, denominator))
+                .map(|()| super::transport::sync_tempo(&self.state, cx)),
+            TransportField::LoopCrossfade => {
+                let transport = self.state.project.as_ref().map(|p| p.transport.clone()).unwrap_or_default();
+                parse_loop_crossfade(&entry.text, &transport)
+                    .and_then(|ms| self.state.set_loop_crossfade(ms))
+                    .map(|()| super::transport::sync_loop(&self.state, cx))
+            }
+        };
+        if let Err(e) = applied {
+            self.state.transport_entry = Some(TransportEntry { error: Some(e.to_string()), ..entry });
+        }
+        cx.notify();
+    }
+
+    /// Write a copy of a resampled or mono asset in the project's format into
+    /// the project's audio folder and switch every clip using it to the copy
+    pub fn convert_asset_format(&mut self, path: PathBuf, cx: &mut Context<Self>) {
+        let (Some(asset), Some(service)) = (self.state.loaded_assets.get(&path).cloned(), self.state.audio_service.clone()) else {
+            return;
+        };
+        let folder = self.state.project_dir
+            .as_ref()
+            .map(|dir| dir.join(AUDIO_FOLDER))
+            .or_else(|| path.parent().map(|p| p.to_path_buf()))
+            .unwrap_or_else(|| PathBuf::from("."));
+
+        cx.spawn(async move |this, cx| {
+            let converted = cx.background_executor().spawn(async move {
+                std::fs::create_dir_all(&folder)
+                    .map_err(anyhow::Error::from)
+                    .and_then(|_| conform_asset(&asset, &folder))
+            }).await;
+            let converted = match converted {
+                Ok(converted) => converted,
+                Err(e) => {
+                    tracing::error!("❌ Failed to convert {:?}: {:#}", path, e);
+                    return;
+                }
+            };
+            let loaded = match service.load_asset(converted.clone()).await {
+                Ok(loaded) => loaded,
+                Err(e) => {
+                    tracing::error!("❌ Failed to load converted file {:?}: {}", converted, e);
+                    return;
+                }
+            };
+
+            cx.update(|cx| {
+                this.update(cx, |this, cx| {
+                    this.state.loaded_assets.insert(converted.clone(), loaded);
+                    let changed = this.state.relink_asset(&path, converted);
+                    if let Some(dir) = this.state.project_dir.clone() {
+                        this.state.scan_audio_files(&dir);
+                    }
+                    this.sync_tracks_to_audio_service(changed, cx);
+                    this.load_waveforms(cx);
+                    cx.notify();
+                }).ok();
+            }).ok();
+        }).detach();
+    }
+
+    /// Synthesize the generator chosen in the insert menu into the project's
+    /// audio folder and put it on the selected track at the playhead
+    pub fn insert_generated_clip(&mut self, cx: &mut Context<Self>) {
+        let (Some(project), Some(service)) = (self.state.project.as_ref(), self.state.audio_service.clone()) else {
+            return;
+        };
+        let settings = self.state.generator;
+        let sample_rate = project.sample_rate;
+        let folder = self.state.project_dir
+            .as_ref()
+            .map(|dir| dir.join(AUDIO_FOLDER))
+            .unwrap_or_else(|| std::env::temp_dir().join("pulsar_daw_generated"));
+        self.state.generator_menu_open = false;
+
+        cx.spawn(async move |this, cx| {
+            let rendered = cx.background_executor().spawn(async move {
+                render_generator(&settings, sample_rate, &folder)
+            }).await;
+            let path = match rendered {
+                Ok(path) => path,
+                Err(e) => {
+                    tracing::error!("❌ Failed to generate {}: {:#}", settings.generator.name(), e);
+                    return;
+                }
+            };
+            let loaded = match service.load_asset(path.clone()).await {
+                Ok(loaded) => loaded,
+                Err(e) => {
+                    tracing::error!("❌ Failed to load generated file {:?}: {}", path, e);
+                    return;
+                }
+            };
+
+            cx.update(|cx| {
+                this.update(cx, |this, cx| {
+                    let duration = loaded.asset_ref.duration_samples as SampleTime;
+                    this.state.loaded_assets.insert(path.clone(), loaded);
+                    match this.state.place_generated_clip(path, settings.generator.name(), duration) {
+                        Some(track_id) => this.sync_tracks_to_audio_service(vec![track_id], cx),
+                        None => tracing::error!("⚠️ No track to put the generated clip on"),
+                    }
+                    if let Some(dir) = this.state.project_dir.clone() {
+                        this.state.scan_audio_files(&dir);
+                    }
+                    this.load_waveforms(cx);
+                    cx.notify();
+                }).ok();
+            }).ok();
+        }).detach();
+        cx.notify();
+    }
+
+    /// Turn off a track's input clip light
+    pub fn clear_input_clip(&mut self, track_id: TrackId, cx: &mut Context<Self>) {
+        if let Some(meter) = self.state.input_meters.get_mut(&track_id) {
+            meter.clipped = false;
+        }
+        if let Some(service) = self.state.audio_service.clone() {
+            cx.spawn(async move |_this, _cx| {
+                service.clear_input_clip(Some(track_id)).await;
+            }).detach();
+        }
+        cx.notify();
+    }
+
+    /// Print the selected audio track, effects and all, to a new track below
+    /// it in the project's audio folder, and mute the original
+    pub fn bounce_selected_track(&mut self, cx: &mut Context<Self>) {
+        let (Some(project), Some(service)) = (self.state.project.clone(), self.state.audio_service.clone()) else {
+            return;
+        };
+        let Some(source) = project
+            .tracks
+            .iter()
+            .find(|t| self.state.selection.selected_track_ids.contains(&t.id) && t.track_type == TrackType::Audio)
+            .map(|t| t.id)
+        else {
+            tracing::error!("⚠️ Select an audio track to bounce");
+            return;
+        };
+        let base_dir = self.state.project_dir.clone().unwrap_or_else(|| PathBuf::from("."));
+        let folder = self.state.project_dir
+            .as_ref()
+            .map(|dir| dir.join(AUDIO_FOLDER))
+            .unwrap_or_else(|| std::env::temp_dir().join("pulsar_daw_bounced"));
+        // Two seconds past the last clip for reverb and delay tails
+        let tail = (2.0 * project.sample_rate) as SampleTime;
+
+        cx.spawn(async move |this, cx| {
+            let rendered = cx.background_executor().spawn(async move {
+                bounce_track(&project, &base_dir, source, &folder, tail)
+            }).await;
+            let bounced = match rendered {
+                Ok(bounced) => bounced,
+                Err(e) => {
+                    tracing::error!("❌ Failed to bounce track: {:#}", e);
+                    return;
+                }
+            };
+            let loaded = match service.load_asset(bounced.path.clone()).await {
+                Ok(loaded) => loaded,
+                Err(e) => {
+                    tracing::error!("❌ Failed to load bounced file {:?}: {}", bounced.path, e);
+                    return;
+                }
+            };
+
+            cx.update(|cx| {
+                this.update(cx, |this, cx| {
+                    this.state.loaded_assets.insert(bounced.path.clone(), loaded);
+                    match this.state.add_bounced_track(source, bounced) {
+                        Some(track_ids) => this.sync_tracks_to_audio_service(track_ids, cx),
+                        None => tracing::error!("⚠️ The bounced track is gone, the print was kept on disk"),
+                    }
+                    if let Some(dir) = this.state.project_dir.clone() {
+                        this.state.scan_audio_files(&dir);
+                    }
+                    this.load_waveforms(cx);
+                    cx.notify();
+                }).ok();
+            }).ok();
+        }).detach();
+    }
+
+    fn handle_key_down(&mut self, event: &KeyDownEvent, cx: &mut Context<Self>) {
+        let keystroke = &event.keystroke;
+        let secondary = keystroke.modifiers.secondary();
+
+        if self.state.reference_image_dialog.is_some() {
+            match keystroke.key.as_str() {
+                "enter" => self.export_reference_image(cx),
+                "escape" => {
+                    self.state.reference_image_dialog = None;
+                    cx.notify();
+                }
+                _ => {}
+            }
+            return;
+        }
+
+        // The sample rate dialog only answers Enter and Escape
+        if self.state.sample_rate_dialog.is_some() {
+            match keystroke.key.as_str() {
+                "enter" => self.apply_sample_rate_change(cx),
+                "escape" => {
+                    self.state.sample_rate_dialog = None;
+                    cx.notify();
+                }
+                _ => {}
+            }
+            return;
+        }
+
+        // Typing a fader level: digits and signs edit, Enter applies, Escape cancels
+        if let Some(ref mut entry) = self.state.fader_entry {
+            match keystroke.key.as_str() {
+                "enter" => self.apply_fader_entry(cx),
+                "escape" => {
+                    self.state.fader_entry = None;
+                    cx.notify();
+                }
+                "backspace" => {
+                    entry.text.pop();
+                    cx.notify();
+                }
+                _ => {
+                    let typed = keystroke.key_char.as_deref().unwrap_or(keystroke.key.as_str());
+                    if typed.chars().all(|c| c.is_ascii_digit() || "+-.infdbINFDB".contains(c)) && entry.text.len() < 8 {
+                        entry.text.push_str(typed);
+                        cx.notify();
+                    }
+                }
+            }
+            return;
+        }
+
+        // Typing a tempo, time signature or loop crossfade: Enter applies,
+        // Escape cancels
+        if let Some(ref mut entry) = self.state.transport_entry {
+            match keystroke.key.as_str() {
+                "enter" => self.apply_transport_entry(cx),
+                "escape" => self.state.transport_entry = None,
+                "backspace" => {
+                    entry.text.pop();
+                    entry.error = None;
+                }
+                _ => {
+                    let typed = keystroke.key_char.as_deref().unwrap_or(keystroke.key.as_str());
+                    let allowed = match entry.field {
+                        TransportField::Tempo | TransportField::LoopCrossfade => ".",
+                        TransportField::TimeSignature => "/",
+                    };
+                    if typed.chars().all(|c| c.is_ascii_digit() || allowed.contains(c)) && entry.text.len() < 6 {
+                        entry.text.push_str(typed);
+                        entry.error = None;
+                    }
+                }
+            }
+            cx.notify();
+            return;
+        }
+
+        // Typing a marker name: Enter applies, Escape cancels
+        if let Some(ref mut rename) = self.state.marker_rename {
+            match keystroke.key.as_str() {
+                "enter" => {
+                    self.state.commit_marker_rename();
+                }
+                "escape" => self.state.marker_rename = None,
+                "backspace" => {
+                    rename.text.pop();
+                }
+                _ => {
+                    let typed = keystroke.key_char.as_deref().unwrap_or(keystroke.key.as_str());
+                    if !secondary && typed.chars().count() == 1 && !typed.chars().any(char::is_control) && rename.text.len() < 64 {
+                        rename.text.push_str(typed);
+                    }
+                }
+            }
+            cx.notify();
+            return;
+        }
+
+        // Typing a clip name: Enter applies, Escape cancels
+        if let Some(ref mut rename) = self.state.clip_rename {
+            match keystroke.key.as_str() {
+                "enter" => {
+                    self.state.commit_clip_rename();
+                }
+                "escape" => self.state.clip_rename = None,
+                "backspace" => {
+                    rename.text.pop();
+                }
+                _ => {
+                    let typed = keystroke.key_char.as_deref().unwrap_or(keystroke.key.as_str());
+                    if !secondary && typed.chars().count() == 1 && !typed.chars().any(char::is_control) && rename.text.len() < 64 {
+                        rename.text.push_str(typed);
+                    }
+                }
+            }
+            cx.notify();
+            return;
+        }
+
+        // Typing a region name: Enter applies, Escape cancels
+        if let Some(ref mut rename) = self.state.region_rename {
+            match keystroke.key.as_str() {
+                "enter" => {
+                    self.state.commit_region_rename();
+                }
+                "escape" => self.state.region_rename = None,
+                "backspace" => {
+                    rename.text.pop();
+                }
+                _ => {
+                    let typed = keystroke.key_char.as_deref().unwrap_or(keystroke.key.as_str());
+                    if !secondary && typed.chars().count() == 1 && !typed.chars().any(char::is_control) && rename.text.len() < 64 {
+                        rename.text.push_str(typed);
+                    }
+                }
+            }
+            cx.notify();
+            return;
+        }
+
+        // Escape cancels a running clip export, or dismisses a finished one
+        if let Some(ref export) = self.state.clip_export {
+            if keystroke.key == "escape" {
+                if export.summary.is_some() {
+                    self.state.clip_export = None;
+                } else {
+                    export.cancel.store(true, Ordering::Relaxed);
+                }
+                cx.notify();
+            }
+            return;
+        }
+
+        // The clip properties dialog is modal and takes all typing while open
+        if self.state.clip_properties.is_some() {
+            if keystroke.key == "enter" && self.state.clip_properties.as_ref().is_some_and(|d| d.editing.is_none()) {
+                self.apply_clip_properties(cx);
+            } else if super::clip_properties::handle_dialog_key(&mut self.state, keystroke) {
+                cx.notify();
+            }
+            return;
+        }
+
+        match keystroke.key.as_str() {
+            "z" if secondary && keystroke.modifiers.shift => self.redo(cx),
+            "z" if secondary => self.undo(cx),
+            "y" if secondary => self.redo(cx),
+            "i" if secondary && keystroke.modifiers.shift => self.insert_silence(cx),
+            "d" if secondary && keystroke.modifiers.shift => self.duplicate_to_fill(cx),
+            "b" if secondary && keystroke.modifiers.shift => self.bounce_selected_track(cx),
+            "c" if secondary => self.copy_selected_clips(cx),
+            "v" if secondary => self.paste_clips(cx),
+            "delete" | "backspace" => {
+                if !self.state.selection.selected_clip_ids.is_empty() {
+                    let changed = self.state.delete_selected_clips();
+                    self.sync_tracks_to_audio_service(changed, cx);
+                    cx.notify();
+                } else {
+                    let selected: Vec<TrackId> = self.state.selection.selected_track_ids.iter().copied().collect();
+                    self.delete_tracks(&selected, cx);
+                }
+            }
+            "escape" if self.state.snap_menu_open || self.state.fade_menu_open || self.state.color_menu_open => {
+                self.state.snap_menu_open = false;
+                self.state.fade_menu_open = false;
+                self.state.color_menu_open = false;
+                cx.notify();
+            }
+            "t" if !secondary => self.tap_tempo(cx),
+            "f" if !secondary => {
+                let follow_playhead = !self.state.preferences.follow_playhead;
+                self.state.set_preferences(EditPreferences { follow_playhead, ..self.state.preferences });
+                cx.notify();
+            }
+            "r" if !secondary => self.toggle_reference_listening(cx),
+            "m" if keystroke.modifiers.shift && !secondary => {
+                self.state.add_marker_at_playhead();
+                cx.notify();
+            }
+            "l" if !secondary => {
+                if self.state.loop_selection() {
+                    super::transport::sync_loop(&self.state, cx);
+                    cx.notify();
+                }
+            }
+            "m" if !secondary && !self.state.selection.selected_clip_ids.is_empty() => {
+                let changed = self.state.toggle_selected_clips_mute();
+                self.sync_tracks_to_audio_service(changed, cx);
+                cx.notify();
+            }
+            "left" | "right" if !self.state.selection.selected_clip_ids.is_empty() => {
+                let step = self.state.snap_value.to_beats();
+                let delta = if keystroke.key == "left" { -step } else { step };
+                let changed = self.state.nudge_selected_clips(delta);
+                self.sync_tracks_to_audio_service(changed, cx);
+                cx.notify();
+            }
+            // Keyboard navigation; arrows nudge instead while clips are selected
+            "tab" => {
+                self.state.focus_next(keystroke.modifiers.shift);
+                cx.notify();
+            }
+            "up" | "down" => {
+                self.state.focus_adjacent_track(if keystroke.key == "up" { -1 } else { 1 });
+                cx.notify();
+            }
+            "left" | "right" => {
+                self.state.focus_adjacent_clip(if keystroke.key == "left" { -1 } else { 1 });
+                cx.notify();
+            }
+            "enter" | "space" if !secondary => {
+                if self.state.select_focused(keystroke.modifiers.shift) {
+                    if let Some(KeyboardFocus::Clip(track_id, clip_id)) = self.state.keyboard_focus {
+                        self.state.open_clip_properties(track_id, clip_id);
+                    }
+                }
+                self.arm_selected_tracks(cx);
+                cx.notify();
+            }
+            "escape" => {
+                self.state.selection.selected_clip_ids.clear();
+                self.state.selection.selected_track_ids.clear();
+                self.state.selected_marker = None;
+                self.state.selected_region = None;
+                cx.notify();
+            }
+            _ => {}
+        }
+    }
+
+    /// Convert window-relative coordinates to timeline element coordinates
+    /// Following GPUI best practices from gpui-mouse-position.md
+    pub fn window_to_timeline_pos(window_pos: Point<Pixels>, panel: &Self) -> Point<Pixels> {
+        if let Some(bounds) = &panel.timeline_element_bounds {
+            Point::new(
+                window_pos.x - bounds.origin.x,
+                window_pos.y - bounds.origin.y,
+            )
+        } else {
+            // Before first frame, bounds aren't captured yet
+            window_pos
+        }
+    }
+}
+
+impl Focusable for DawPanel {
+    fn focus_handle(&self, _: &App) -> FocusHandle {
+        self.focus_handle.clone()
+    }
+}
+
+impl Render for DawPanel {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        v_flex()
+            .key_context("DawPanel")
+            .track_focus(&self.focus_handle)
+            .size_full()
+            .relative()
+            .bg(cx.theme().background)
+            .overflow_hidden()
+            .on_key_down(cx.listener(|this, event: &KeyDownEvent, _window, cx| {
+                this.handle_key_down(event, cx);
+            }))
+            // Handle mouse move for dragging with proper coordinates
+            .on_mouse_move(cx.listener(|this, event: &MouseMoveEvent, _window, cx| {
+                // Update drag visual feedback position and values
+                match &this.state.drag_state.clone() {
+                    DragState::DraggingFile { .. } => {
+                        // Just trigger re-render for visual feedback
+                        cx.notify();
+                    }
+                    DragState::DraggingClip { .. } => {
+                        // Trigger re-render for clip drag visual feedback
+                        this.state.drag_pointer = event.position;
+                        cx.notify();
+                    }
+                    DragState::DraggingFadeLength { clip_id, track_id, is_fade_in, start_mouse_x, start_length } => {
+                        // Fade-in handles grow to the right, fade-out handles to the left
+                        let delta_px = event.position.x.as_f32() - *start_mouse_x;
+                        let delta_px = if *is_fade_in { delta_px } else { -delta_px };
+                        let delta_samples = (delta_px as f64 * this.state.samples_per_pixel()) as i64;
+
+                        if let Some(clip) = this.state.get_clip_mut(*track_id, *clip_id) {
+                            let other_fade = if *is_fade_in { clip.fade_out } else { clip.fade_in };
+                            let max_length = clip.duration.saturating_sub(other_fade) as i64;
+                            let new_length = (*start_length as i64 + delta_samples).clamp(0, max_length) as SampleTime;
+
+                            if *is_fade_in {
+                                clip.fade_in = new_length;
+                            } else {
+                                clip.fade_out = new_length;
+                            }
+                        }
+                        cx.notify();
+                    }
+                    DragState::DraggingFadeCurve { clip_id, track_id, is_fade_in, start_mouse_y, start_curve } => {
+                        // Dragging up raises the middle of the fade (towards logarithmic)
+                        let delta_y = *start_mouse_y - event.position.y.as_f32();
+                        let new_curve = (*start_curve - delta_y / 100.0).clamp(-1.0, 1.0);
+
+                        if let Some(clip) = this.state.get_clip_mut(*track_id, *clip_id) {
+                            if *is_fade_in {
+                                clip.fade_in_curve = new_curve;
+                            } else {
+                                clip.fade_out_curve = new_curve;
+                            }
+                        }
+                        cx.notify();
+                    }
+                    DragState::DraggingFader { track_id, start_mouse_y, start_volume } => {
+                        // Drag along the fader law so travel near unity is finer
+                        let current_y = event.position.y.as_f32();
+                        let delta_y = *start_mouse_y - current_y; // Inverted: up = increase
+                        let position = fader_position(*start_volume) + delta_y / 150.0; // 150 pixels = full travel
+                        this.set_fader_volume(*track_id, fader_volume(position), cx);
+                        cx.notify();
+                    }
+                    DragState::DraggingPan { track_id, start_mouse_x, start_pan } => {
+                        // Update pan position based on mouse drag
+                        let current_x = event.position.x.as_f32();
+                        let delta_x = current_x - *start_mouse_x;
+                        let delta_pan = delta_x / 50.0; // Sensitivity factor
+                        let new_pan = (*start_pan + delta_pan).clamp(-1.0, 1.0);
+
+                        if let Some(ref mut project) = this.state.project {
+                            if let Some(track) = project.tracks.iter_mut().find(|t| t.id == *track_id) {
+                                track.pan = new_pan;
+
+                                // Sync to audio service in real-time
+                                if let Some(ref service) = this.state.audio_service {
+                                    let service = service.clone();
+                                    let track_id_val = *track_id;
+                                    cx.spawn(async move |_this, _cx| {
+                                        let _ = service.set_track_pan(track_id_val, new_pan).await;
+                                    }).detach();
+                                }
+                            }
+                        }
+                        cx.notify();
+                    }
+                    DragState::DraggingTrackHeaderVolume { track_id, start_mouse_x, start_value, .. } => {
+                        // Update track header volume slider (horizontal)
+                        let current_x = event.position.x;
+                        let delta_px = current_x - *start_mouse_x;
+                        let delta_value = delta_px / px(200.0); // Sensitivity factor (200 pixels = full range)
+                        let new_value = (*start_value + delta_value).clamp(0.0, 1.0);
+
+                        // Convert slider value (0..1) to dB then to linear
+                        let db = (new_value * 72.0) - 60.0; // Map 0..1 to -60..+12 dB
+                        let linear = 10f32.powf(db / 20.0);
+
+                        if let Some(ref mut project) = this.state.project {
+                            if let Some(track) = project.tracks.iter_mut().find(|t| t.id == *track_id) {
+                                track.volume = linear.clamp(0.0, 2.0);
+
+                                // Sync to audio service in real-time
+                                if let Some(ref service) = this.state.audio_service {
+                                    let service = service.clone();
+                                    let track_id_val = *track_id;
+                                    let volume = track.volume;
+                                    cx.spawn(async move |_this, _cx| {
+                                        let _ = service.set_track_volume(track_id_val, volume).await;
+                                    }).detach();
+                                }
+                            }
+                        }
+                        cx.notify();
+                    }
+                    DragState::DraggingTrackHeaderPan { track_id, start_mouse_x, start_value } => {
+                        // Update track header pan slider (horizontal)
+                        let current_x = event.position.x;
+                        let delta_px = current_x - *start_mouse_x;
+                        let delta_value = delta_px / px(100.0); // Sensitivity factor (100 pixels = full range)
+                        let new_value = (*start_value + delta_value).clamp(0.0, 1.0);
+
+                        // Convert slider value (0..1) to pan (-1..1)
+                        let pan = (new_value * 2.0 - 1.0) as f32;
+
+                        if let Some(ref mut project) = this.state.project {
+                            if let Some(track) = project.tracks.iter_mut().find(|t| t.id == *track_id) {
+                                track.pan = pan.clamp(-1.0, 1.0);
+
+                                // Sync to audio service in real-time
+                                if let Some(ref service) = this.state.audio_service {
+                                    let service = service.clone();
+                                    let track_id_val = *track_id;
+                                    cx.spawn(async move |_this, _cx| {
+                                        let _ = service.set_track_pan(track_id_val, pan).await;
+                                    }).detach();
+                                }
+                            }
+                        }
+                        cx.notify();
+                    }
+                    DragState::DraggingSend { track_id, send_idx, start_mouse_x, start_amount } => {
+                        // Update send level (horizontal drag)
+                        let current_x = event.position.x.as_f32();
+                        let delta_x = current_x - *start_mouse_x;
+                        let delta_amount = delta_x / 100.0; // Sensitivity: 100 pixels = full range (0.0 to 1.0)
+                        let new_amount = (*start_amount + delta_amount).clamp(0.0, 1.0);
+
+                        if let Some(ref mut project) = this.state.project {
+                            if let Some(track) = project.tracks.iter_mut().find(|t| t.id == *track_id) {
+                                // Ensure send exists
+                                while track.sends.len() <= *send_idx {
+                                    track.sends.push(super::super::daw_engine::audio_types::Send {
+                                        target_track: None,
+                                        amount: 0.0,
+                                        pre_fader: false,
+                                        enabled: false,
+                                        follow_mute: true,
+                                    });
+                                }
+                                if let Some(send) = track.sends.get_mut(*send_idx) {
+                                    send.amount = new_amount;
+                                }
+                            }
+                        }
+                        cx.notify();
+                    }
+                    DragState::DraggingAutomationPoint { track_id, param_type, point_index, start_mouse_x, start_mouse_y, start_beat, start_value } => {
+                        // Sideways moves in time, up and down along the lane's value scale.
+                        // Shift skips the value snap for fine adjustments.
+                        let height = *this.state.track_heights.get(track_id).unwrap_or(&this.state.viewport.track_height);
+                        let beat = *start_beat + this.state.pixels_to_beats(event.position.x.as_f32() - *start_mouse_x);
+                        let delta_y = *start_mouse_y - event.position.y.as_f32();
+                        let value = param_type.lane_value(param_type.lane_position(*start_value) + delta_y / height);
+
+                        if let Some(index) = this.state.move_automation_point(*track_id, *param_type, *point_index, beat, value, event.modifiers.shift) {
+                            if let DragState::DraggingAutomationPoint { point_index, .. } = &mut this.state.drag_state {
+                                *point_index = index;
+                            }
+                        }
+                        cx.notify();
+                    }
+                    DragState::DraggingAutomationCurve { .. } => {
+                        this.state.drag_automation_curve(event.position.y.as_f32());
+                        cx.notify();
+                    }
+                    DragState::ResizingMixer { start_mouse_y, start_height } => {
+                        // Dragging the top edge up makes the mixer taller
+                        let delta_y = *start_mouse_y - event.position.y.as_f32();
+                        this.state.mixer_height = (*start_height + delta_y).clamp(MIN_MIXER_HEIGHT, MAX_MIXER_HEIGHT);
+                        cx.notify();
+                    }
+                    DragState::DraggingLimiterCeiling { start_mouse_x, start_ceiling } => {
+                        // 20 pixels per dB, ceiling limited to -12..0 dBFS
+                        let delta_db = (event.position.x.as_f32() - *start_mouse_x) / 20.0;
+                        let new_ceiling = ((*start_ceiling + delta_db) * 10.0).round() / 10.0;
+
+                        if let Some(ref mut project) = this.state.project {
+                            project.master_limiter.ceiling_db = new_ceiling.clamp(-12.0, 0.0);
+                        }
+                        this.sync_master_limiter(cx);
+                        cx.notify();
+                    }
+                    DragState::DraggingRegion { .. } => {
+                        this.state.drag_region(event.position.x.as_f32());
+                        cx.notify();
+                    }
+                    DragState::DraggingClipEdge { .. } => {
+                        this.state.drag_clip_trim(event.position.x.as_f32());
+                        this.state.drag_pointer = event.position;
+                        cx.notify();
+                    }
+                    _ => {}
+                }
+            }))
+            // Handle mouse up to clear drag state
+            .on_mouse_up(gpui::MouseButton::Left, cx.listener(|this, _event: &MouseUpEvent, _window, cx| {
+                // Sync changes to audio service when drag completes
+                match &this.state.drag_state {
+                    DragState::DraggingFadeLength { clip_id, track_id, .. }
+                    | DragState::DraggingFadeCurve { clip_id, track_id, .. } => {
+                        this.sync_clip_to_audio_service(*track_id, *clip_id, cx);
+                    }
+                    DragState::DraggingFader { track_id, start_volume, .. } => {
+                        // Volume already synced in real-time during drag
+                        let (track_id, start_volume) = (*track_id, *start_volume);
+                        this.state.record_vca_fader(track_id, start_volume);
+                    }
+                    DragState::DraggingPan { .. } => {
+                        // Pan already synced in real-time during drag
+                    }
+                    DragState::DraggingTrackHeaderVolume { track_id, start_volume, .. } => {
+                        // Volume already synced in real-time during drag
+                        let (track_id, start_volume) = (*track_id, *start_volume);
+                        this.state.record_vca_fader(track_id, start_volume);
+                    }
+                    DragState::DraggingTrackHeaderPan { .. } => {
+                        // Pan already synced in real-time during drag
+                    }
+                    DragState::DraggingSend { track_id, send_idx, .. } => {
+                        let track_id_val = *track_id;
+                        let send_idx_val = *send_idx;
+                        let send_amount = this.state.get_track(track_id_val)
+                            .and_then(|t| t.sends.get(send_idx_val))
+                            .map(|s| s.amount)
+                            .unwrap_or(0.0);
+
+                        tracing::error!("🎚️ Send {} level set to: {:.0}%", send_idx_val, send_amount * 100.0);
+                        this.sync_tracks_to_audio_service(vec![track_id_val], cx);
+                    }
+                    DragState::DraggingAutomationPoint { track_id, param_type, point_index, start_beat, start_value, .. } => {
+                        let (track_id, param_type, point_index) = (*track_id, *param_type, *point_index);
+                        let (start_beat, start_value) = (*start_beat, *start_value);
+                        let moved = this.state.get_track(track_id)
+                            .and_then(|track| track.get_automation_lane(param_type))
+                            .and_then(|lane| lane.points.get(point_index))
+                            .zip(this.state.project.as_ref())
+                            .is_some_and(|(point, project)| {
+                                point.value != start_value || project.transport.samples_to_beats(point.time) != start_beat
+                            });
+                        this.state.finish_automation_drag(moved);
+                        if moved {
+                            this.sync_tracks_to_audio_service(vec![track_id], cx);
+                        }
+                    }
+                    DragState::DraggingAutomationCurve { .. } => {
+                        if let Some(track_id) = this.state.finish_automation_curve_drag() {
+                            this.sync_tracks_to_audio_service(vec![track_id], cx);
+                        }
+                    }
+                    DragState::DraggingRegion { .. } => {
+                        this.state.finish_region_drag();
+                    }
+                    DragState::DraggingClipEdge { .. } => {
+                        if let Some(track_id) = this.state.finish_clip_trim() {
+                            this.sync_tracks_to_audio_service(vec![track_id], cx);
+                        }
+                    }
+                    DragState::DraggingFile { .. } => {
+                        // File drop is handled by timeline drop zones
+                        // Don't clear it here
+                    }
+                    _ => {}
+                }
+
+                // Any drag that edits the project leaves it with unsaved changes
+                let edited = match &this.state.drag_state {
+                    DragState::None
+                    | DragState::DraggingFile { .. }
+                    | DragState::Marquee { .. }
+                    | DragState::ResizingTrack { .. }
+                    | DragState::ResizingMixer { .. }
+                    | DragState::DraggingRegion { .. } => false,
+                    DragState::DraggingClip { clip_id, track_id, start_beat, .. } => {
+                        let (tempo, rate) = (this.state.get_tempo(), this.state.sample_rate());
+                        this.state.get_clip(*track_id, *clip_id)
+                            .map(|c| c.start_beat(tempo, rate) != *start_beat)
+                            .unwrap_or(true)
+                    }
+                    _ => true,
+                };
+                if edited {
+                    this.state.dirty.mark_dirty();
+                }
+
+                // Clear drag state when mouse is released (except for DraggingFile which is handled by drop zones)
+                if !matches!(this.state.drag_state, DragState::None | DragState::DraggingFile { .. }) {
+                    this.state.drag_state = DragState::None;
+                    this.state.magnetic_snap_line = None;
+                    cx.notify();
+                }
             }))
             .child(self.render_content(cx))
             // Render drag cursor overlay
             .child(self.render_drag_cursor(cx))
+            // Modal clip properties dialog
+            .when(self.state.clip_properties.is_some(), |this| {
+                this.child(super::clip_properties::render_clip_properties_dialog(&mut self.state, cx))
+            })
+            .when(self.state.sample_rate_dialog.is_some(), |this| {
+                this.child(super::sample_rate_dialog::render_sample_rate_dialog(&self.state, cx))
+            })
+            .when(self.state.reference_image_dialog.is_some(), |this| {
+                this.child(super::reference_image_dialog::render_reference_image_dialog(&self.state, cx))
+            })
+            .when(self.state.render_queue_open, |this| {
+                this.child(super::render_queue_dialog::render_render_queue_dialog(&self.state, cx))
+            })
+            .when(self.state.clip_export.is_some(), |this| {
+                this.child(super::clip_export_dialog::render_clip_export_dialog(&self.state, cx))
+            })
+    }
+}
+
+impl DawPanel {
+    fn render_content(&mut self, cx: &mut Context<Self>) -> impl IntoElement {
+        v_flex()
+            .size_full()
+            .gap_0()
+            // Toolbar
+            .child(self.render_toolbar(cx))
+            // Transport controls
+            .child(self.render_transport(cx))
+            // Main content area
+            .child(
+                h_flex()
+                    .flex_1()
+                    .overflow_hidden()
+                    .gap_0()
+                    // Left sidebar (browser)
+                    .when(self.state.show_browser, |this| {
+                        this.child(self.render_browser(cx))
+                    })
+                    // Center content (timeline/mixer)
+                    .child(self.render_main_area(cx))
+                    // Right sidebar (inspector)
+                    .when(self.state.show_inspector, |this| {
+                        this.child(self.render_inspector(cx))
+                    })
+            )
+    }
+
+    // Toolbar implementation
+    pub fn render_toolbar(&mut self, cx: &mut Context<Self>) -> impl IntoElement {
+        super::toolbar::render_toolbar(&mut self.state, cx)
+    }
+
+    pub fn render_transport(&mut self, cx: &mut Context<Self>) -> impl IntoElement {
+        super::transport::render_transport(&mut self.state, cx)
+    }
+
+    fn render_main_area(&mut self, cx: &mut Context<Self>) -> impl IntoElement {
+        v_flex()
+            .flex_1()
+            .h_full()
+            .min_w_0()  // Allow shrinking below content width
+            .overflow_hidden()  // Prevent content overflow
+            .bg(cx.theme().background)
+            .gap_0()
+            // Main content area (timeline/editor) - takes up most of the space
+            .child(
+                div()
+                    .flex_1()
+                    .w_full()
+                    .min_w_0()  // Allow shrinking
+                    .overflow_hidden()
+                    .child(match self.state.view_mode {
+                        ViewMode::Arrange => self.render_timeline(cx).into_any_element(),
+                        ViewMode::Mix => self.render_mixer(cx).into_any_element(),
+                        ViewMode::Edit => self.render_clip_editor(cx).into_any_element(),
+                    })
+            )
+            // Mixer panel at the bottom, resized from its top edge. The mix
+            // view already shows it full size.
+            .when(self.state.show_mixer && self.state.view_mode != ViewMode::Mix, |this| {
+                let height = self.state.mixer_height;
+                this.child(
+                    v_flex()
+                        .w_full()
+                        .h(px(height)).flex_shrink_0()
+                        .border_t_1()
+                        .border_color(cx.theme().border)
+                        .child(
+                            div()
+                                .id("mixer-resize-handle")
+                                .w_full()
+                                .h(px(4.0))
+                                .flex_shrink_0()
+                                .cursor_ns_resize()
+                                .hover(|style| style.bg(cx.theme().accent.opacity(0.4)))
+                                .on_mouse_down(MouseButton::Left, cx.listener(move |this, event: &MouseDownEvent, _window, cx| {
+                                    this.state.drag_state = DragState::ResizingMixer {
+                                        start_mouse_y: event.position.y.as_f32(),
+                                        start_height: height,
+                                    };
+                                    cx.stop_propagation();
+                                    cx.notify();
+                                }))
+                        )
+                        .child(div().w_full().flex_1().min_h_0().child(self.render_mixer(cx)))
+                )
+            })
+    }
+
+    fn render_inspector(&mut self, cx: &mut Context<Self>) -> impl IntoElement {
+        use ui::{button::*, Sizable};
+
+        let selected_track_id = self.state.selection.selected_track_ids.iter().next().copied();
+
+        v_flex()
+            .w(px(300.0))
+            .h_full()
+            .bg(cx.theme().background)
+            .border_l_1()
+            .border_color(cx.theme().border)
+            // Tab bar
+            .child(
+                h_flex()
+                    .w_full()
+                    .h(px(44.0))
+                    .px_2()
+                    .gap_1()
+                    .items_center()
+                    .bg(cx.theme().muted.opacity(0.2))
+                    .border_b_1()
+                    .border_color(cx.theme().border)
+                    .child(self.render_inspector_tab_button("Track", InspectorTab::Track, cx))
+                    .child(self.render_inspector_tab_button("Clip", InspectorTab::Clip, cx))
+                    .child(self.render_inspector_tab_button("Auto", InspectorTab::Automation, cx))
+                    .child(self.render_inspector_tab_button("FX", InspectorTab::Effects, cx))
+                    .child(self.render_inspector_tab_button("Info", InspectorTab::Project, cx))
+            )
+            // Content area
+            .child(
+                div()
+                    .flex_1()
+                    .w_full()
+                    .p_4()
+                    .child(match self.state.inspector_tab {
+                        InspectorTab::Track => self.render_track_inspector(selected_track_id, cx).into_any_element(),
+                        InspectorTab::Clip => self.render_clip_inspector(cx).into_any_element(),
+                        InspectorTab::Automation => self.render_automation_inspector(selected_track_id, cx).into_any_element(),
+                        InspectorTab::Effects => self.render_effects_inspector(selected_track_id, cx).into_any_element(),
+                        InspectorTab::Project => self.render_project_inspector(cx).into_any_element(),
+                    })
+            )
+    }
+
+    fn render_track_inspector(&mut self, track_id: Option<TrackId>, cx: &mut Context<Self>) -> impl IntoElement {
+        if let Some(track) = track_id.and_then(|id| self.state.get_track(id)) {
+            v_flex()
+                .w_full()
+                .gap_3()
+                .child(
+                    v_flex()
+                        .w_full()
+                        .gap_1()
+                        .child(
+                            div()
+                                .text_xs()
+                                .font_semibold()
+                                .text_color(cx.theme().muted_foreground)
+                                .child("TRACK NAME")
+                        )
+                        .child(
+                            div()
+                                .text_sm()
+                                .font_medium()
+                                .text_color(cx.theme().foreground)
+                                .child(track.name.clone())
+                        )
+                )
+                .child(
+                    div()
+                        .w_full()
+                        .h(px(1.0))
+                        .bg(cx.theme().border)
+                )
+                .child(
+                    v_flex()
+                        .w_full()
+                        .gap_3()
+                        .child(
+                            v_flex()
+                                .w_full()
+                                .gap_1()
+                                .child(
+                                    div()
+                                        .text_xs()
+                                        .text_color(cx.theme().muted_foreground)
+                                        .child("Type")
+                                )
+                                .child(
+                                    div()
+                                        .text_sm()
+                                        .text_color(cx.theme().foreground)
+                                        .child(format!("{:?}", track.track_type))
+                                )
+                        )
+                        .child(
+                            v_flex()
+                                .w_full()
+                                .gap_1()
+                                .child(
+                                    div()
+                                        .text_xs()
+                                        .text_color(cx.theme().muted_foreground)
+                                        .child("Volume")
+                                )
+                                .child(
+                                    div()
+                                        .text_sm()
+                                        .font_family("monospace")
+                                        .text_color(cx.theme().foreground)
+                                        .child(format!("{:+.1} dB", track.volume_db()))
+                                )
+                        )
+                        .child(
+                            v_flex()
+                                .w_full()
+                                .gap_1()
+                                .child(
+                                    div()
+                                        .text_xs()
+                                        .text_color(cx.theme().muted_foreground)
+                                        .child("Pan")
+                                )
+                                .child(
+                                    div()
+                                        .text_sm()
+                                        .font_family("monospace")
+                                        .text_color(cx.theme().foreground)
+                                        .child(format!("{:.0}%", track.pan * 100.0))
+                                )
+                        )
+                        .child(
+                            v_flex()
+                                .w_full()
+                                .gap_1()
+                                .child(
+                                    div()
+                                        .text_xs()
+                                        .text_color(cx.theme().muted_foreground)
+                                        .child("Clips")
+                                )
+                                .child(
+                                    div()
+                                        .text_sm()
+                                        .text_color(cx.theme().foreground)
+                                        .child(format!("{} clips", track.clips.len()))
+                                )
+                        )
+                        .child(self.render_track_offset(track.id, cx))
+                )
+                .into_any_element()
+        } else {
+            self.render_empty_inspector_message("Track Inspector", "Select a track to view and edit its properties", cx)
+                .into_any_element()
+        }
     }
-}
 
-impl DawPanel {
-    fn render_content(&mut self, cx: &mut Context<Self>) -> impl IntoElement {
+    /// Mute automation steps for the selected track, added at the playhead
+    fn render_automation_inspector(&mut self, track_id: Option<TrackId>, cx: &mut Context<Self>) -> impl IntoElement {
+        use ui::{button::*, Sizable};
+
+        let Some(track) = track_id.and_then(|id| self.state.get_track(id)) else {
+            return self.render_empty_inspector_message("Automation", "Draw automation curves on the timeline to control parameters over time", cx)
+                .into_any_element();
+        };
+        let track_id = track.id;
+        let lane = track.get_automation_lane(AutomationParameter::Mute);
+        let steps = lane.map_or(0, |lane| lane.points.len());
+        let enabled = !lane.is_some_and(|lane| !lane.enabled);
+        let volume_points = track.get_automation_lane(AutomationParameter::Volume).map_or(0, |lane| lane.points.len());
+        let volume_shown = self.state.volume_lane_tracks.contains(&track_id);
+        let value_snap = self.state.automation_value_snap;
+        let time_snap = self.state.automation_time_snap;
+        let mode = track.automation_mode;
+        let learning = self.state.control_learn;
+        let mappings: Vec<_> = self.state.project.as_ref()
+            .map(|p| p.control_mappings.iter().copied().filter(|m| m.track_id == track_id).collect())
+            .unwrap_or_default();
+        let learnable: Vec<(AutomationParameter, String)> = [
+            (AutomationParameter::Volume, "Volume".to_string()),
+            (AutomationParameter::Pan, "Pan".to_string()),
+            (AutomationParameter::HighPass, "High-Pass".to_string()),
+            (AutomationParameter::LowPass, "Low-Pass".to_string()),
+        ]
+        .into_iter()
+        .chain(track.effects.iter().enumerate().map(|(index, effect)| {
+            (AutomationParameter::EffectMix(index), format!("{}. {} Mix", index + 1, effect.label()))
+        }))
+        .collect();
+
+        let edit_button = |id: &'static str, label: &'static str, tooltip: &'static str, edit: fn(&mut DawUiState, TrackId) -> bool| {
+            Button::new(id)
+                .label(label)
+                .ghost()
+                .small()
+                .tooltip(tooltip)
+                .on_click(cx.listener(move |this, _, _window, cx| {
+                    if edit(&mut this.state, track_id) {
+                        this.sync_tracks_to_audio_service(vec![track_id], cx);
+                    }
+                    cx.notify();
+                }))
+        };
+
         v_flex()
-            .size_full()
-            .gap_0()
-            // Toolbar
-            .child(self.render_toolbar(cx))
-            // Transport controls
-            .child(self.render_transport(cx))
-            // Main content area
+            .w_full()
+            .gap_2()
+            .child(
+                div()
+                    .text_xs()
+                    .font_semibold()
+                    .text_color(cx.theme().muted_foreground)
+                    .child("MUTE LANE")
+            )
+            .child(
+                div()
+                    .text_xs()
+                    .text_color(cx.theme().muted_foreground)
+                    .child(match steps {
+                        0 => "No steps, the track plays throughout".to_string(),
+                        1 => "1 step".to_string(),
+                        n => format!("{} steps", n),
+                    })
+            )
             .child(
                 h_flex()
-                    .flex_1()
-                    .overflow_hidden()
-                    .gap_0()
-                    // Left sidebar (browser)
-                    .when(self.state.show_browser, |this| {
-                        this.child(self.render_browser(cx))
+                    .gap_1()
+                    .flex_wrap()
+                    .child(edit_button("mute-step-on", "Mute Here", "Mute the track from the playhead on", |state, id| state.set_mute_step_at_playhead(id, true)))
+                    .child(edit_button("mute-step-off", "Unmute Here", "Unmute the track from the playhead on", |state, id| state.set_mute_step_at_playhead(id, false)))
+            )
+            .child(
+                h_flex()
+                    .gap_1()
+                    .child(edit_button("mute-lane-toggle", if enabled { "Bypass" } else { "Enable" }, "Turn the mute lane on or off, keeping its steps", DawUiState::toggle_mute_automation))
+                    .child(edit_button("mute-lane-clear", "Clear", "Remove every mute step", DawUiState::clear_mute_automation))
+            )
+            .child(
+                div()
+                    .mt_2()
+                    .text_xs()
+                    .font_semibold()
+                    .text_color(cx.theme().muted_foreground)
+                    .child("VOLUME LANE")
+            )
+            .child(
+                div()
+                    .text_xs()
+                    .text_color(cx.theme().muted_foreground)
+                    .child(match volume_points {
+                        0 => "No points, the fader sets the level".to_string(),
+                        1 => "1 point".to_string(),
+                        n => format!("{} points. Drag to move, right-click to delete", n),
                     })
-                    // Center content (timeline/mixer)
-                    .child(self.render_main_area(cx))
-                    // Right sidebar (inspector)
-                    .when(self.state.show_inspector, |this| {
-                        this.child(self.render_inspector(cx))
+            )
+            .child(
+                h_flex()
+                    .gap_1()
+                    .flex_wrap()
+                    .child(edit_button("volume-point-add", "Add Point Here", "Add a volume point at the playhead", DawUiState::add_volume_point_at_playhead))
+                    .child(
+                        Button::new("volume-lane-show")
+                            .label(if volume_shown { "Hide" } else { "Show" })
+                            .ghost()
+                            .small()
+                            .tooltip("Draw the volume points over the track's clips")
+                            .on_click(cx.listener(move |this, _, _window, cx| {
+                                if !this.state.volume_lane_tracks.remove(&track_id) {
+                                    this.state.volume_lane_tracks.insert(track_id);
+                                }
+                                cx.notify();
+                            }))
+                    )
+                    .child(edit_button("volume-lane-clear", "Clear", "Remove every volume point", DawUiState::clear_volume_automation))
+            )
+            .child(
+                h_flex()
+                    .gap_1()
+                    .child(
+                        Button::new("automation-value-snap")
+                            .label("Snap Values")
+                            .ghost()
+                            .small()
+                            .selected(value_snap)
+                            .tooltip("Snap dragged points to whole dB and 5% pan steps. Hold Shift for fine control.")
+                            .on_click(cx.listener(|this, _, _window, cx| {
+                                this.state.automation_value_snap = !this.state.automation_value_snap;
+                                cx.notify();
+                            }))
+                    )
+                    .child(
+                        Button::new("automation-time-snap")
+                            .label("Snap to Grid")
+                            .ghost()
+                            .small()
+                            .selected(time_snap)
+                            .tooltip("Snap dragged points to the grid division")
+                            .on_click(cx.listener(|this, _, _window, cx| {
+                                this.state.automation_time_snap = !this.state.automation_time_snap;
+                                cx.notify();
+                            }))
+                    )
+            )
+            .child(
+                h_flex()
+                    .mt_2()
+                    .justify_between()
+                    .child(
+                        div()
+                            .text_xs()
+                            .font_semibold()
+                            .text_color(cx.theme().muted_foreground)
+                            .child("CONTROL SURFACE")
+                    )
+                    .child(
+                        Button::new("automation-mode")
+                            .label(mode.label())
+                            .ghost()
+                            .small()
+                            .selected(mode.writes())
+                            .tooltip("Read plays the automation back. Touch writes while a mapped control moves, Latch until the transport stops.")
+                            .on_click(cx.listener(move |this, _, _window, cx| {
+                                this.state.cycle_automation_mode(track_id);
+                                cx.notify();
+                            }))
+                    )
+            )
+            .children(learnable.into_iter().map(|(parameter, name)| {
+                let mapping = mappings.iter().find(|m| m.parameter == parameter);
+                let waiting = learning == Some((track_id, parameter));
+                h_flex()
+                    .gap_1()
+                    .child(
+                        div()
+                            .flex_1()
+                            .text_xs()
+                            .text_color(cx.theme().muted_foreground)
+                            .child(match mapping {
+                                _ if waiting => format!("{}: move a control", name),
+                                Some(m) => format!("{}: CC {} ch {}", name, m.controller, m.channel + 1),
+                                None => format!("{}: not mapped", name),
+                            })
+                    )
+                    .child(
+                        Button::new(ElementId::Name(format!("control-learn-{}", name).into()))
+                            .label(if waiting { "Cancel" } else { "Learn" })
+                            .ghost()
+                            .small()
+                            .tooltip("Map the next hardware control that moves to this parameter")
+                            .on_click(cx.listener(move |this, _, _window, cx| {
+                                this.state.control_learn = if waiting { None } else { Some((track_id, parameter)) };
+                                cx.notify();
+                            }))
+                    )
+                    .when(mapping.is_some(), |row| {
+                        row.child(
+                            Button::new(ElementId::Name(format!("control-forget-{}", name).into()))
+                                .label("Forget")
+                                .ghost()
+                                .small()
+                                .on_click(cx.listener(move |this, _, _window, cx| {
+                                    this.state.forget_control_mapping(track_id, parameter);
+                                    cx.notify();
+                                }))
+                        )
                     })
+            }))
+            .into_any_element()
+    }
+
+    /// Manual track offset with step buttons in ms or samples
+    fn render_track_offset(&self, track_id: TrackId, cx: &mut Context<Self>) -> impl IntoElement {
+        use ui::{button::*, Sizable};
+
+        let value = self.state.get_track(track_id)
+            .map(|track| self.state.track_offset_display(track))
+            .unwrap_or_default();
+
+        let step_button = |id: &'static str, label: String, steps: Option<i64>| {
+            Button::new(id)
+                .label(label)
+                .ghost()
+                .compact()
+                .small()
+                .on_click(cx.listener(move |this, _, _window, cx| {
+                    if this.state.nudge_track_offset(track_id, steps) {
+                        this.sync_tracks_to_audio_service(vec![track_id], cx);
+                    }
+                    cx.notify();
+                }))
+        };
+
+        v_flex()
+            .w_full()
+            .gap_1()
+            .child(
+                div()
+                    .text_xs()
+                    .text_color(cx.theme().muted_foreground)
+                    .child(if self.state.track_offset_in_ms { "Offset (1 ms steps)" } else { "Offset (1 sample steps)" })
+            )
+            .child(
+                h_flex()
+                    .gap_1()
+                    .items_center()
+                    .child(step_button("track-offset-minus-10", "-10".to_string(), Some(-10)))
+                    .child(step_button("track-offset-minus-1", "-1".to_string(), Some(-1)))
+                    .child(
+                        Button::new("track-offset-unit")
+                            .label(value)
+                            .ghost()
+                            .compact()
+                            .small()
+                            .tooltip("Positive plays the track later. Click to switch between ms and samples")
+                            .on_click(cx.listener(|this, _, _window, cx| {
+                                this.state.track_offset_in_ms = !this.state.track_offset_in_ms;
+                                cx.notify();
+                            }))
+                    )
+                    .child(step_button("track-offset-plus-1", "+1".to_string(), Some(1)))
+                    .child(step_button("track-offset-plus-10", "+10".to_string(), Some(10)))
+                    .child(step_button("track-offset-reset", "Reset".to_string(), None))
+            )
+    }
+
+    /// Insert effect chain of the selected track
+    fn render_effects_inspector(&mut self, track_id: Option<TrackId>, cx: &mut Context<Self>) -> impl IntoElement {
+        use ui::{button::*, Sizable};
+
+        let Some(track) = track_id.and_then(|id| self.state.get_track(id)) else {
+            return self.render_empty_inspector_message("Effects", "Add audio effects and processors to track inserts", cx)
+                .into_any_element();
+        };
+        let track_id = track.id;
+
+        v_flex()
+            .w_full()
+            .gap_2()
+            .child(
+                div()
+                    .text_xs()
+                    .font_semibold()
+                    .text_color(cx.theme().muted_foreground)
+                    .child("INSERTS")
+            )
+            .child(
+                h_flex()
+                    .gap_1()
+                    .flex_wrap()
+                    .children(EffectKind::all().into_iter().enumerate().map(|(i, kind)| {
+                        Button::new(("fx-add", i))
+                            .label(format!("+ {}", kind.label()))
+                            .small()
+                            .on_click(cx.listener(move |this, _, _window, cx| {
+                                if this.state.add_effect(track_id, kind) {
+                                    this.sync_tracks_to_audio_service(vec![track_id], cx);
+                                }
+                                cx.notify();
+                            }))
+                    }))
+                    .child(
+                        Button::new("fx-copy-chain")
+                            .label("Copy Chain")
+                            .ghost()
+                            .small()
+                            .disabled(track.effects.is_empty())
+                            .on_click(cx.listener(move |this, _, _window, cx| {
+                                this.copy_effects(track_id, None, cx);
+                            }))
+                    )
+                    .child(
+                        Button::new("fx-paste")
+                            .label("Paste")
+                            .ghost()
+                            .small()
+                            .tooltip("Add the copied effects to the end of the chain")
+                            .on_click(cx.listener(move |this, _, _window, cx| {
+                                this.paste_effects(track_id, None, cx);
+                            }))
+                    )
+            )
+            .when_some(self.state.effect_error.clone(), |d, error| {
+                d.child(div().text_xs().text_color(cx.theme().danger).child(error))
+            })
+            .when(track.effects.is_empty(), |d| {
+                d.child(
+                    div()
+                        .text_xs()
+                        .text_color(cx.theme().muted_foreground)
+                        .child("No effects, the track plays dry")
+                )
+            })
+            .children(track.effects.iter().enumerate().map(|(index, effect)| {
+                Self::render_effect_slot(track_id, index, effect, cx)
+            }))
+            .into_any_element()
+    }
+
+    /// One insert effect with its bypass, copy/paste, mix and parameter controls.
+    /// Plugin effects say they pass audio through, as plugins can't be loaded
+    /// yet. Right-clicking the slot copies the effect.
+    fn render_effect_slot(track_id: TrackId, index: usize, effect: &Effect, cx: &mut Context<Self>) -> impl IntoElement {
+        use ui::{button::*, Sizable};
+
+        let effect_id = effect.id;
+        let kind = effect.kind;
+        let nudge = move |param: usize, steps: f32, cx: &mut Context<Self>| {
+            cx.listener(move |this: &mut Self, _: &ClickEvent, _window, cx| {
+                if this.state.nudge_effect_param(track_id, effect_id, param, steps) {
+                    this.sync_tracks_to_audio_service(vec![track_id], cx);
+                }
+                cx.notify();
+            })
+        };
+        let mix_nudge = move |steps: f32, cx: &mut Context<Self>| {
+            cx.listener(move |this: &mut Self, _: &ClickEvent, _window, cx| {
+                if this.state.nudge_effect_mix(track_id, effect_id, steps) {
+                    this.sync_tracks_to_audio_service(vec![track_id], cx);
+                }
+                cx.notify();
+            })
+        };
+
+        v_flex()
+            .w_full()
+            .p_2()
+            .gap_1()
+            .rounded_md()
+            .border_1()
+            .border_color(cx.theme().border)
+            .bg(cx.theme().muted.opacity(0.2))
+            .when(!effect.enabled, |d| d.opacity(0.5))
+            .on_mouse_down(MouseButton::Right, cx.listener(move |this, _: &MouseDownEvent, _window, cx| {
+                this.copy_effects(track_id, Some(effect_id), cx);
+            }))
+            .child(
+                h_flex()
+                    .gap_1()
+                    .items_center()
+                    .child(div().flex_1().text_sm().font_medium().child(format!("{}. {}", index + 1, effect.label())))
+                    .child(
+                        Button::new(("fx-bypass", index))
+                            .label(if effect.enabled { "On" } else { "Off" })
+                            .small()
+                            .when(effect.enabled, |b| b.ghost())
+                            .when(!effect.enabled, |b| b.warning())
+                            .tooltip("Bypass Effect")
+                            .on_click(cx.listener(move |this, _, _window, cx| {
+                                if this.state.toggle_effect_bypass(track_id, effect_id) {
+                                    this.sync_tracks_to_audio_service(vec![track_id], cx);
+                                }
+                                cx.notify();
+                            }))
+                    )
+                    .child(
+                        Button::new(("fx-gain-match", index))
+                            .label("Match")
+                            .small()
+                            .when(effect.gain_match, |b| b.primary())
+                            .when(!effect.gain_match, |b| b.ghost())
+                            .tooltip("Gain match: bring the processed signal back to the level going in, for A/B comparison. Also applies to exports")
+                            .on_click(cx.listener(move |this, _, _window, cx| {
+                                if this.state.toggle_effect_gain_match(track_id, effect_id) {
+                                    this.sync_tracks_to_audio_service(vec![track_id], cx);
+                                }
+                                cx.notify();
+                            }))
+                    )
+                    .child(
+                        Button::new(("fx-paste-params", index))
+                            .label("Paste Params")
+                            .ghost()
+                            .small()
+                            .tooltip(format!("Paste the parameters of a copied {}", effect.label()))
+                            .on_click(cx.listener(move |this, _, _window, cx| {
+                                this.paste_effects(track_id, Some(effect_id), cx);
+                            }))
+                    )
+                    .child(
+                        Button::new(("fx-remove", index))
+                            .icon(ui::Icon::new(ui::IconName::Close))
+                            .ghost()
+                            .small()
+                            .tooltip("Remove Effect")
+                            .on_click(cx.listener(move |this, _, _window, cx| {
+                                if this.state.remove_effect(track_id, effect_id) {
+                                    this.sync_tracks_to_audio_service(vec![track_id], cx);
+                                }
+                                cx.notify();
+                            }))
+                    )
             )
+            .child(
+                h_flex()
+                    .gap_1()
+                    .items_center()
+                    .child(div().w(px(72.0)).text_xs().text_color(cx.theme().muted_foreground).child("Mix"))
+                    .child(div().flex_1().text_xs().font_family("monospace").child(format!("{:.0}% wet", effect.mix * 100.0)))
+                    .child(Button::new(("fx-mix-down", index)).label("-").ghost().small().tooltip("Blend in More of the Dry Signal").on_click(mix_nudge(-1.0, cx)))
+                    .child(Button::new(("fx-mix-up", index)).label("+").ghost().small().on_click(mix_nudge(1.0, cx)))
+            )
+            .children(kind.params().iter().enumerate().map(|(param, info)| {
+                let value = kind.param(param).unwrap_or_default();
+                let id = index * 16 + param;
+                h_flex()
+                    .gap_1()
+                    .items_center()
+                    .child(div().w(px(72.0)).text_xs().text_color(cx.theme().muted_foreground).child(info.name))
+                    .child(div().flex_1().text_xs().font_family("monospace").child(format!("{:.1} {}", value, info.unit)))
+                    .child(Button::new(("fx-param-down", id)).label("-").ghost().small().on_click(nudge(param, -1.0, cx)))
+                    .child(Button::new(("fx-param-up", id)).label("+").ghost().small().on_click(nudge(param, 1.0, cx)))
+            }))
+            .when(matches!(kind, EffectKind::ConvolutionReverb { .. }), |d| {
+                let response = effect.impulse_response.as_ref().and_then(|path| path.file_name()).map(|name| name.to_string_lossy().to_string());
+                d.child(
+                    h_flex()
+                        .gap_1()
+                        .items_center()
+                        .child(div().w(px(72.0)).text_xs().text_color(cx.theme().muted_foreground).child("Response"))
+                        .child(
+                            div()
+                                .flex_1()
+                                .text_xs()
+                                .text_color(if response.is_some() { cx.theme().foreground } else { cx.theme().yellow })
+                                .child(response.clone().unwrap_or_else(|| "None, passing audio through".to_string()))
+                        )
+                        .child(
+                            Button::new(("fx-impulse-load", index))
+                                .label("Load…")
+                                .ghost()
+                                .small()
+                                .tooltip("Load a WAV Impulse Response, Resampled to the Project Rate")
+                                .on_click(cx.listener(move |this, _, _window, cx| {
+                                    this.load_impulse_response(track_id, effect_id, cx);
+                                }))
+                        )
+                        .child(
+                            Button::new(("fx-impulse-clear", index))
+                                .icon(ui::Icon::new(ui::IconName::Close))
+                                .ghost()
+                                .small()
+                                .disabled(response.is_none())
+                                .tooltip("Clear Impulse Response")
+                                .on_click(cx.listener(move |this, _, _window, cx| {
+                                    if this.state.set_effect_impulse_response(track_id, effect_id, None) {
+                                        this.sync_tracks_to_audio_service(vec![track_id], cx);
+                                    }
+                                    cx.notify();
+                                }))
+                        )
+                )
+            })
+            .when(effect.plugin.is_some(), |d| {
+                d.child(
+                    div()
+                        .text_xs()
+                        .text_color(cx.theme().yellow)
+                        .child("⚠️ Plugins can't be loaded yet, passing audio through. The plugin's settings are kept.")
+                )
+            })
+            .when_some(effect.unavailable.as_ref().map(|u| u.reason.clone()), |d, reason| {
+                d.child(
+                    div()
+                        .text_xs()
+                        .text_color(cx.theme().yellow)
+                        .child(format!("⚠️ {}. Passing audio through; its settings are kept.", reason))
+                )
+            })
     }
 
-    // Toolbar implementation
-    pub fn render_toolbar(&mut self, cx: &mut Context<Self>) -> impl IntoElement {
-        super::toolbar::render_toolbar(&mut self.state, cx)
-    }
+    fn render_clip_inspector(&mut self, cx: &mut Context<Self>) -> impl IntoElement {
+        use ui::{button::*, Sizable};
 
-    pub fn render_transport(&mut self, cx: &mut Context<Self>) -> impl IntoElement {
-        super::transport::render_transport(&mut self.state, cx)
-    }
+        let selected = self.state.selection.selected_clip_ids.iter().next().copied().and_then(|clip_id| {
+            self.state.project.as_ref()?.tracks.iter().find_map(|track| {
+                track.clips.iter().find(|c| c.id == clip_id).map(|clip| (track.id, clip.clone()))
+            })
+        });
+
+        let Some((track_id, clip)) = selected else {
+            return self.render_empty_inspector_message("Clip Inspector", "Select a clip to view and edit its properties", cx).into_any_element();
+        };
+        let clip_id = clip.id;
+        let source = self.state.loaded_assets.get(&clip.asset_path).map(|asset| asset.asset_ref.source);
+        let mismatches = self.state.asset_format_mismatches(&clip.asset_path);
+        let health = self.state.asset_health(&clip.asset_path);
+        let project_rate = self.state.project.as_ref().map(|p| p.sample_rate).unwrap_or(SAMPLE_RATE);
+        let asset_path = clip.asset_path.clone();
 
-    fn render_main_area(&mut self, cx: &mut Context<Self>) -> impl IntoElement {
         v_flex()
-            .flex_1()
-            .h_full()
-            .min_w_0()  // Allow shrinking below content width
-            .overflow_hidden()  // Prevent content overflow
-            .bg(cx.theme().background)
-            .gap_0()
-            // Main content area (timeline/editor) - takes up most of the space
+            .w_full()
+            .gap_2()
             .child(
                 div()
-                    .flex_1()
-                    .w_full()
-                    .min_w_0()  // Allow shrinking
-                    .overflow_hidden()
-                    .child(match self.state.view_mode {
-                        ViewMode::Arrange => self.render_timeline(cx).into_any_element(),
-                        ViewMode::Mix => div().child("Full Mix View").into_any_element(),
-                        ViewMode::Edit => self.render_clip_editor(cx).into_any_element(),
+                    .text_sm()
+                    .font_medium()
+                    .text_color(cx.theme().foreground)
+                    .child(clip.name.clone())
+            )
+            .child(
+                div()
+                    .text_xs()
+                    .text_color(cx.theme().muted_foreground)
+                    .child(format!("Start: {} · Length: {} samples", clip.start_time, clip.duration))
+            )
+            .child(
+                div()
+                    .text_xs()
+                    .text_color(cx.theme().muted_foreground)
+                    .child(match source.filter(|s| s.sample_rate > 0) {
+                        Some(source) => format!("Source: {}", source.describe()),
+                        None => "Source: not loaded".to_string(),
                     })
             )
-            // Mixer panel at the bottom - fixed height
-            .when(self.state.show_mixer, |this| {
-                this.child(
-                    div()
-                        .w_full()
-                        .h(px(420.0)).flex_shrink_0()
-                        .border_t_1()
-                        .border_color(cx.theme().border)
-                        .child(self.render_mixer(cx))
+            .children(mismatches.iter().map(|mismatch| {
+                div()
+                    .text_xs()
+                    .text_color(cx.theme().yellow)
+                    .child(format!("⚠️ {}", mismatch.description(project_rate)))
+            }))
+            .children(health.into_iter().map(|line| {
+                div()
+                    .text_xs()
+                    .text_color(cx.theme().danger)
+                    .child(format!("⚠️ {}", line))
+            }))
+            .child(
+                div()
+                    .text_xs()
+                    .text_color(cx.theme().muted_foreground)
+                    .child("Channels")
+            )
+            .child(
+                h_flex()
+                    .gap_1()
+                    .flex_wrap()
+                    .children(ChannelMode::ALL.into_iter().map(|mode| {
+                        Button::new(ElementId::Name(format!("inspector-clip-channels-{:?}", mode).into()))
+                            .label(mode.label())
+                            .ghost()
+                            .compact()
+                            .small()
+                            .when(clip.channel_mode == mode, |btn| btn.selected(true))
+                            .on_click(cx.listener(move |this, _, _window, cx| {
+                                let changed = this.state.set_selected_clips_channel_mode(mode);
+                                this.sync_tracks_to_audio_service(changed, cx);
+                                cx.notify();
+                            }))
+                    }))
+            )
+            .child(self.render_fade_preset_dropdown(track_id, &clip, cx))
+            .when(!mismatches.is_empty(), |d| {
+                d.child(
+                    Button::new("inspector-clip-convert")
+                        .label("Convert to Project Format")
+                        .small()
+                        .tooltip("Write a stereo copy at the project rate and use it for every clip of this file")
+                        .on_click(cx.listener(move |this, _, _window, cx| {
+                            this.convert_asset_format(asset_path.clone(), cx);
+                        }))
                 )
             })
+            .child(
+                Button::new("inspector-clip-properties")
+                    .label("Properties…")
+                    .small()
+                    .on_click(cx.listener(move |this, _, _window, cx| {
+                        this.state.open_clip_properties(track_id, clip_id);
+                        cx.notify();
+                    }))
+            )
+            .into_any_element()
     }
 
-    fn render_inspector(&mut self, cx: &mut Context<Self>) -> impl IntoElement {
+    /// Fade preset dropdown of the clip inspector. A preset chosen from it is
+    /// given to every selected clip, to the fade-in, fade-out or both.
+    fn render_fade_preset_dropdown(&self, track_id: TrackId, clip: &AudioClip, cx: &mut Context<Self>) -> impl IntoElement {
         use ui::{button::*, Sizable};
 
-        let selected_track_id = self.state.selection.selected_track_ids.iter().next().copied();
+        let clip_id = clip.id;
+        let presets = self.state.fade_presets.all();
+        let preset_name = |shape: FadeShape, curve: f32| {
+            presets.iter().find(|p| p.matches(shape, curve)).map_or("Custom".to_string(), |p| p.name.clone())
+        };
+        let (fade_in_name, fade_out_name) = (
+            preset_name(clip.fade_in_shape, clip.fade_in_curve),
+            preset_name(clip.fade_out_shape, clip.fade_out_curve),
+        );
+        let summary = if fade_in_name == fade_out_name {
+            fade_in_name
+        } else {
+            format!("{} / {}", fade_in_name, fade_out_name)
+        };
+        let selected_count = self.state.selection.selected_clip_ids.len();
+        let open = self.state.fade_preset_menu_open;
 
         v_flex()
-            .w(px(300.0))
-            .h_full()
-            .bg(cx.theme().background)
-            .border_l_1()
-            .border_color(cx.theme().border)
-            // Tab bar
-            .child(
-                h_flex()
-                    .w_full()
-                    .h(px(44.0))
-                    .px_2()
-                    .gap_1()
-                    .items_center()
-                    .bg(cx.theme().muted.opacity(0.2))
-                    .border_b_1()
-                    .border_color(cx.theme().border)
-                    .child(self.render_inspector_tab_button("Track", InspectorTab::Track, cx))
-                    .child(self.render_inspector_tab_button("Clip", InspectorTab::Clip, cx))
-                    .child(self.render_inspector_tab_button("Auto", InspectorTab::Automation, cx))
-                    .child(self.render_inspector_tab_button("FX", InspectorTab::Effects, cx))
-            )
-            // Content area
+            .gap_1()
             .child(
                 div()
-                    .flex_1()
-                    .w_full()
-                    .p_4()
-                    .child(match self.state.inspector_tab {
-                        InspectorTab::Track => self.render_track_inspector(selected_track_id, cx).into_any_element(),
-                        InspectorTab::Clip => self.render_empty_inspector_message("Clip Inspector", "Select a clip to view and edit its properties", cx).into_any_element(),
-                        InspectorTab::Automation => self.render_empty_inspector_message("Automation", "Draw automation curves on the timeline to control parameters over time", cx).into_any_element(),
-                        InspectorTab::Effects => self.render_empty_inspector_message("Effects", "Add audio effects and processors to track inserts", cx).into_any_element(),
+                    .text_xs()
+                    .text_color(cx.theme().muted_foreground)
+                    .child(if selected_count > 1 {
+                        format!("Fade Curves · {} clips", selected_count)
+                    } else {
+                        "Fade Curves".to_string()
                     })
             )
-    }
-
-    fn render_track_inspector(&mut self, track_id: Option<TrackId>, cx: &mut Context<Self>) -> impl IntoElement {
-        if let Some(track) = track_id.and_then(|id| self.state.get_track(id)) {
-            v_flex()
-                .w_full()
-                .gap_3()
-                .child(
-                    v_flex()
-                        .w_full()
-                        .gap_1()
-                        .child(
-                            div()
-                                .text_xs()
-                                .font_semibold()
-                                .text_color(cx.theme().muted_foreground)
-                                .child("TRACK NAME")
-                        )
-                        .child(
-                            div()
-                                .text_sm()
-                                .font_medium()
-                                .text_color(cx.theme().foreground)
-                                .child(track.name.clone())
-                        )
-                )
-                .child(
-                    div()
-                        .w_full()
-                        .h(px(1.0))
-                        .bg(cx.theme().border)
-                )
-                .child(
+            .child(
+                Button::new("inspector-fade-preset")
+                    .label(format!("{} ▾", summary))
+                    .ghost()
+                    .small()
+                    .tooltip("Fade-in / fade-out preset")
+                    .on_click(cx.listener(|this, _, _window, cx| {
+                        this.state.fade_preset_menu_open = !this.state.fade_preset_menu_open;
+                        cx.notify();
+                    }))
+            )
+            .when(open, |d| {
+                d.child(
                     v_flex()
-                        .w_full()
-                        .gap_3()
-                        .child(
-                            v_flex()
-                                .w_full()
-                                .gap_1()
-                                .child(
-                                    div()
-                                        .text_xs()
-                                        .text_color(cx.theme().muted_foreground)
-                                        .child("Type")
-                                )
-                                .child(
-                                    div()
-                                        .text_sm()
-                                        .text_color(cx.theme().foreground)
-                                        .child(format!("{:?}", track.track_type))
-                                )
-                        )
-                        .child(
-                            v_flex()
-                                .w_full()
-                                .gap_1()
-                                .child(
-                                    div()
-                                        .text_xs()
-                                        .text_color(cx.theme().muted_foreground)
-                                        .child("Volume")
-                                )
-                                .child(
-                                    div()
-                                        .text_sm()
-                                        .font_family("monospace")
-                                        .text_color(cx.theme().foreground)
-                                        .child(format!("{:+.1} dB", track.volume_db()))
-                                )
-                        )
-                        .child(
-                            v_flex()
-                                .w_full()
+                        .p_1()
+                        .gap_0p5()
+                        .rounded_md()
+                        .border_1()
+                        .border_color(cx.theme().border)
+                        .bg(cx.theme().background)
+                        .children(presets.iter().enumerate().map(|(index, preset)| {
+                            h_flex()
                                 .gap_1()
                                 .child(
                                     div()
+                                        .flex_1()
                                         .text_xs()
-                                        .text_color(cx.theme().muted_foreground)
-                                        .child("Pan")
-                                )
-                                .child(
-                                    div()
-                                        .text_sm()
-                                        .font_family("monospace")
                                         .text_color(cx.theme().foreground)
-                                        .child(format!("{:.0}%", track.pan * 100.0))
+                                        .child(preset.name.clone())
                                 )
-                        )
+                                .children([(FadeEnds::In, "In"), (FadeEnds::Out, "Out"), (FadeEnds::Both, "Both")].into_iter().map(|(ends, label)| {
+                                    let preset = preset.clone();
+                                    Button::new(ElementId::Name(format!("fade-preset-{}-{:?}", index, ends).into()))
+                                        .label(label)
+                                        .ghost()
+                                        .compact()
+                                        .small()
+                                        .on_click(cx.listener(move |this, _, _window, cx| {
+                                            let changed = this.state.apply_fade_preset_to_selected(&preset, ends);
+                                            this.sync_tracks_to_audio_service(changed, cx);
+                                            cx.notify();
+                                        }))
+                                }))
+                        }))
                         .child(
-                            v_flex()
-                                .w_full()
-                                .gap_1()
-                                .child(
-                                    div()
-                                        .text_xs()
-                                        .text_color(cx.theme().muted_foreground)
-                                        .child("Clips")
-                                )
-                                .child(
-                                    div()
-                                        .text_sm()
-                                        .text_color(cx.theme().foreground)
-                                        .child(format!("{} clips", track.clips.len()))
-                                )
+                            Button::new("fade-preset-save")
+                                .label("Save Fade-In as Preset")
+                                .ghost()
+                                .small()
+                                .tooltip("Keep this clip's fade-in curve in your presets")
+                                .on_click(cx.listener(move |this, _, _window, cx| {
+                                    if let Some(name) = this.state.save_fade_preset(track_id, clip_id) {
+                                        tracing::error!("💾 Saved fade preset {}", name);
+                                    }
+                                    cx.notify();
+                                }))
                         )
                 )
-                .into_any_element()
-        } else {
-            self.render_empty_inspector_message("Track Inspector", "Select a track to view and edit its properties", cx)
-                .into_any_element()
-        }
+            })
     }
 
     fn render_inspector_tab_button(&self, label: &str, tab: InspectorTab, cx: &mut Context<Self>) -> impl IntoElement {
@@ -704,10 +3731,161 @@ impl DawPanel {
             .when(is_active, |btn| btn.selected(true))
             .on_click(cx.listener(move |this, _, _, cx| {
                 this.state.inspector_tab = tab;
+                if tab == InspectorTab::Project {
+                    this.refresh_project_report(cx);
+                }
                 cx.notify();
             }))
     }
 
+    fn render_project_inspector(&mut self, cx: &mut Context<Self>) -> impl IntoElement {
+        use ui::{button::*, Sizable};
+
+        let Some(report) = self.state.project_report.clone() else {
+            let message = if self.state.building_report { "Reading the project's files…" } else { "Open a project to see its statistics" };
+            return self.render_empty_inspector_message("Project", message, cx).into_any_element();
+        };
+        let heading = |text: &'static str| {
+            div()
+                .pt_1()
+                .text_xs()
+                .font_semibold()
+                .text_color(cx.theme().muted_foreground)
+                .child(text)
+        };
+        let line = |text: String| div().text_xs().text_color(cx.theme().muted_foreground).child(text);
+        let minutes = (report.duration_seconds / 60.0).floor();
+
+        v_flex()
+            .w_full()
+            .gap_1()
+            .child(
+                h_flex()
+                    .w_full()
+                    .gap_1()
+                    .child(
+                        div()
+                            .flex_1()
+                            .text_sm()
+                            .font_medium()
+                            .text_color(cx.theme().foreground)
+                            .child(report.name.clone())
+                    )
+                    .child(
+                        Button::new("project-report-refresh")
+                            .label(if self.state.building_report { "Reading…" } else { "Refresh" })
+                            .ghost()
+                            .compact()
+                            .small()
+                            .disabled(self.state.building_report)
+                            .on_click(cx.listener(|this, _, _window, cx| {
+                                this.refresh_project_report(cx);
+                            }))
+                    )
+                    .child(
+                        Button::new("project-report-export")
+                            .label("Export")
+                            .ghost()
+                            .compact()
+                            .small()
+                            .tooltip("Save this report as text or JSON")
+                            .on_click(cx.listener(|this, _, _window, cx| {
+                                this.export_project_report(cx);
+                            }))
+                    )
+            )
+            .child(heading("STATISTICS"))
+            .child(line(format!("{} tracks ({} buses)", report.tracks, report.buses)))
+            .child(line(format!("{} clips over {}:{:04.1}", report.clips, minutes, report.duration_seconds - minutes * 60.0)))
+            .child(line(format!("{} automation points, {} markers", report.automation_points, report.markers)))
+            .child(
+                h_flex()
+                    .w_full()
+                    .pt_1()
+                    .child(div().flex_1().child(heading("DSP LOAD")))
+                    .child(
+                        Button::new("dsp-profiling")
+                            .label(if self.state.dsp_profiling { "Stop" } else { "Profile" })
+                            .compact()
+                            .small()
+                            .when(self.state.dsp_profiling, |b| b.primary())
+                            .when(!self.state.dsp_profiling, |b| b.ghost())
+                            .tooltip("Time every track and effect per buffer while playing")
+                            .on_click(cx.listener(|this, _, _window, cx| {
+                                this.toggle_dsp_profiling(cx);
+                            }))
+                    )
+            )
+            .when(!self.state.dsp_profiling, |d| d.child(line("Off".to_string())))
+            .when(self.state.dsp_profiling, |d| d.children(self.dsp_load_lines(cx)))
+            .child(heading("FILES"))
+            .child(line(format!("{} referenced, {} on disk", report.files.len(), format_bytes(report.total_file_bytes))))
+            .children(report.files.iter().map(|file| {
+                let size = file.size_bytes.map_or_else(|| "missing".to_string(), format_bytes);
+                line(format!("{} · used {}× · {}", file.path.display(), file.clips, size))
+            }))
+            .child(heading("UNUSED AUDIO FILES"))
+            .when(report.unused_files.is_empty(), |d| d.child(line("None".to_string())))
+            .children(report.unused_files.iter().map(|path| {
+                let shown = self.state.project_dir.as_deref().and_then(|dir| path.strip_prefix(dir).ok()).unwrap_or(path);
+                line(shown.display().to_string())
+            }))
+            .child(heading("WARNINGS"))
+            .when(report.warnings.is_empty(), |d| d.child(line("None".to_string())))
+            .children(report.warnings.iter().map(|warning| {
+                div().text_xs().text_color(cx.theme().yellow).child(format!("⚠️ {}", warning))
+            }))
+            .into_any_element()
+    }
+
+    /// Average and peak time of each track and its effects per buffer, the
+    /// costliest first, against the time a buffer lasts
+    fn dsp_load_lines(&self, cx: &Context<Self>) -> Vec<AnyElement> {
+        let Some(project) = self.state.project.as_ref() else {
+            return Vec::new();
+        };
+        let profile = &self.state.dsp_profile;
+        let budget = profile.budget_us(project.sample_rate);
+        let percent = |us: f32| us / budget.max(1.0) * 100.0;
+        let color = cx.theme().muted_foreground;
+        let line = move |text: String, indent: bool| {
+            div().text_xs().text_color(color).when(indent, |d| d.pl_3()).child(text).into_any_element()
+        };
+
+        let mut tracks: Vec<(&Track, ProcessTiming)> = project
+            .tracks
+            .iter()
+            .filter_map(|track| Some((track, profile.tracks.get(&track.id).copied()?)))
+            .collect();
+        if tracks.is_empty() {
+            return vec![line("Play the project to measure it".to_string(), false)];
+        }
+        tracks.sort_by(|a, b| b.1.average_us.total_cmp(&a.1.average_us));
+
+        let total: f32 = tracks.iter().map(|(_, timing)| timing.average_us).sum();
+        let mut lines = vec![line(format!("{:.0} µs of {:.0} µs per buffer ({:.1}%)", total, budget, percent(total)), false)];
+        for (track, timing) in tracks {
+            lines.push(line(
+                format!("{} · {:.0} µs, peak {:.0} µs ({:.1}%)", track.name, timing.average_us, timing.peak_us, percent(timing.average_us)),
+                false,
+            ));
+            if !track.track_type.is_bus() {
+                let playback = profile.playback_us(track.id, track.effects.iter().map(|e| e.id));
+                lines.push(line(format!("Playback and voices · {:.0} µs", playback), true));
+            }
+            for effect in &track.effects {
+                let Some(timing) = profile.effects.get(&effect.id) else {
+                    continue;
+                };
+                lines.push(line(
+                    format!("{} · {:.0} µs, peak {:.0} µs", effect.label(), timing.average_us, timing.peak_us),
+                    true,
+                ));
+            }
+        }
+        lines
+    }
+
     fn render_empty_inspector_message(&self, title: &str, description: &str, cx: &mut Context<Self>) -> impl IntoElement {
         let title = title.to_string();
         let description = description.to_string();
@@ -749,9 +3927,11 @@ impl DawPanel {
     }
 
     fn render_clip_editor(&mut self, cx: &mut Context<Self>) -> impl IntoElement {
-        div()
-            .size_full()
-            .child("Clip Editor Placeholder")
+        if let Some((_, clip)) = self.state.edited_clip() {
+            let path = clip.asset_path.clone();
+            self.load_spectrogram(path, cx);
+        }
+        super::clip_editor::render_clip_editor(&self.state, cx)
     }
 
     fn render_drag_cursor(&mut self, cx: &mut Context<Self>) -> impl IntoElement {
@@ -788,6 +3968,49 @@ impl DawPanel {
                     )
                     .into_any_element()
             }
+            DragState::DraggingClip { .. } | DragState::DraggingClipEdge { .. } => {
+                let Some(readout) = self.state.clip_drag_readout() else {
+                    return div().into_any_element();
+                };
+                let row = |label: &'static str, value: String| {
+                    h_flex()
+                        .gap_2()
+                        .justify_between()
+                        .child(div().text_color(cx.theme().muted_foreground).child(label))
+                        .child(div().font_medium().child(value))
+                };
+                // Offset down and right so the readout never sits under the pointer
+                let position = self.state.drag_pointer + point(px(16.0), px(20.0));
+                deferred(
+                    anchored().position(position).child(
+                        v_flex()
+                            .min_w(px(150.0))
+                            .px_2()
+                            .py_1()
+                            .gap_0p5()
+                            .text_xs()
+                            .bg(cx.theme().background)
+                            .text_color(cx.theme().foreground)
+                            .rounded_md()
+                            .border_1()
+                            .border_color(cx.theme().border)
+                            .shadow_lg()
+                            .child(row("Start", readout.start))
+                            .child(row("Length", readout.length))
+                            .child(row("End", readout.end))
+                            .child(row("Moved", readout.delta))
+                            .child(
+                                div()
+                                    .pt_0p5()
+                                    .border_t_1()
+                                    .border_color(cx.theme().border)
+                                    .text_color(cx.theme().muted_foreground)
+                                    .child(readout.snap)
+                            )
+                    )
+                )
+                .into_any_element()
+            }
             _ => div().into_any_element(),
         }
     }
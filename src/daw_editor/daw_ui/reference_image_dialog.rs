@@ -0,0 +1,172 @@
+/// Reference Image Dialog
+/// Modal for exporting a PNG of a track's or the master's waveform and
+/// spectrogram over the loop or the whole mix, for delivery notes and QA
+
+use super::state::*;
+use super::panel::DawPanel;
+use super::super::daw_engine::reference_image::{ImageView, IMAGE_SIZES};
+use super::super::daw_engine::spectrogram::FrequencyScale;
+use gpui::*;
+use gpui::prelude::FluentBuilder;
+use ui::{
+    button::*, h_flex, v_flex, Sizable, StyledExt, ActiveTheme, Disableable,
+};
+
+/// Spectrogram floors and ceilings offered, in dB
+const FLOOR_DB: [f32; 4] = [-60.0, -72.0, -96.0, -120.0];
+const CEILING_DB: [f32; 4] = [0.0, -6.0, -12.0, -24.0];
+
+pub fn render_reference_image_dialog(state: &DawUiState, cx: &mut Context<DawPanel>) -> impl IntoElement {
+    let Some(dialog) = state.reference_image_dialog else {
+        return div().into_any_element();
+    };
+    let settings = dialog.settings;
+    let source_name = dialog.source
+        .and_then(|id| state.get_track(id))
+        .map_or_else(|| "Master".to_string(), |t| t.name.clone());
+    let (start, end) = state.reference_image_range();
+    let sample_rate = state.project.as_ref().map_or(1.0, |p| p.sample_rate.max(1.0));
+    let looping = state.project.as_ref().is_some_and(|p| p.transport.loop_enabled && p.transport.loop_end > p.transport.loop_start);
+    let range = format!(
+        "{} from {:.2} s to {:.2} s",
+        if looping { "Loop" } else { "Whole mix" },
+        start as f32 / sample_rate,
+        end as f32 / sample_rate
+    );
+    let problem = match settings.check() {
+        Err(e) => Some(e.to_string()),
+        Ok(()) if end <= start => Some("Nothing to draw: the project has no clips".to_string()),
+        Ok(()) => None,
+    };
+    let spectrogram = settings.view != ImageView::Waveform;
+
+    // Every option is a row of buttons, the chosen one highlighted
+    let update = |change: fn(&mut ReferenceImageDialog, usize), index: usize| {
+        move |this: &mut DawPanel, _: &ClickEvent, _: &mut Window, cx: &mut Context<DawPanel>| {
+            if let Some(ref mut dialog) = this.state.reference_image_dialog {
+                change(dialog, index);
+            }
+            cx.notify();
+        }
+    };
+    let option_row = |id: &str, labels: Vec<(String, bool)>, enabled: bool, change: fn(&mut ReferenceImageDialog, usize), cx: &mut Context<DawPanel>| {
+        h_flex()
+            .w_full()
+            .flex_wrap()
+            .gap_1()
+            .children(labels.into_iter().enumerate().map(|(index, (label, chosen))| {
+                Button::new(ElementId::Name(format!("reference-image-{}-{}", id, index).into()))
+                    .label(label)
+                    .compact()
+                    .small()
+                    .when(chosen, |b| b.primary())
+                    .when(!chosen, |b| b.ghost())
+                    .disabled(!enabled)
+                    .on_click(cx.listener(update(change, index)))
+            }))
+    };
+
+    let sizes = IMAGE_SIZES
+        .iter()
+        .map(|&(width, height)| (format!("{}×{}", width, height), (settings.width, settings.height) == (width, height)))
+        .collect();
+    let views = ImageView::ALL.iter().map(|view| (view.label().to_string(), settings.view == *view)).collect();
+    let scales = [FrequencyScale::Log, FrequencyScale::Linear]
+        .iter()
+        .map(|scale| (scale.label().to_string(), settings.scale == *scale))
+        .collect();
+    let floors = FLOOR_DB.iter().map(|db| (format!("{} dB", db), settings.min_db == *db)).collect();
+    let ceilings = CEILING_DB.iter().map(|db| (format!("{} dB", db), settings.max_db == *db)).collect();
+
+    let label = |text: &'static str, cx: &mut Context<DawPanel>| {
+        div().text_xs().text_color(cx.theme().muted_foreground).child(text)
+    };
+
+    // Backdrop swallows clicks so the timeline underneath isn't edited
+    div()
+        .id("reference-image-backdrop")
+        .absolute()
+        .inset_0()
+        .flex()
+        .items_center()
+        .justify_center()
+        .bg(gpui::black().opacity(0.4))
+        .on_mouse_down(MouseButton::Left, |_, _, cx| cx.stop_propagation())
+        .child(
+            v_flex()
+                .w(px(420.0))
+                .p_4()
+                .gap_2()
+                .bg(cx.theme().background)
+                .rounded_lg()
+                .border_1()
+                .border_color(cx.theme().border)
+                .shadow_lg()
+                .child(
+                    v_flex()
+                        .child(
+                            div()
+                                .text_sm()
+                                .font_semibold()
+                                .text_color(cx.theme().foreground)
+                                .child(format!("Reference Image: {}", source_name))
+                        )
+                        .child(
+                            div()
+                                .text_xs()
+                                .text_color(cx.theme().muted_foreground)
+                                .child(range)
+                        )
+                )
+                .child(label("Size", cx))
+                .child(option_row("size", sizes, true, |dialog, index| {
+                    (dialog.settings.width, dialog.settings.height) = IMAGE_SIZES[index];
+                }, cx))
+                .child(label("Show", cx))
+                .child(option_row("view", views, true, |dialog, index| {
+                    dialog.settings.view = ImageView::ALL[index];
+                }, cx))
+                .child(label("Frequency axis", cx))
+                .child(option_row("scale", scales, spectrogram, |dialog, index| {
+                    dialog.settings.scale = [FrequencyScale::Log, FrequencyScale::Linear][index];
+                }, cx))
+                .child(label("Spectrogram floor", cx))
+                .child(option_row("floor", floors, spectrogram, |dialog, index| {
+                    dialog.settings.min_db = FLOOR_DB[index];
+                }, cx))
+                .child(label("Spectrogram ceiling", cx))
+                .child(option_row("ceiling", ceilings, spectrogram, |dialog, index| {
+                    dialog.settings.max_db = CEILING_DB[index];
+                }, cx))
+                .when_some(problem.clone(), |this, problem| {
+                    this.child(div().text_xs().text_color(cx.theme().danger).child(problem))
+                })
+                .child(
+                    h_flex()
+                        .w_full()
+                        .gap_2()
+                        .justify_end()
+                        .child(
+                            Button::new("reference-image-cancel")
+                                .label("Cancel")
+                                .small()
+                                .ghost()
+                                .on_click(cx.listener(|this, _, _window, cx| {
+                                    this.state.reference_image_dialog = None;
+                                    cx.notify();
+                                }))
+                        )
+                        .child(
+                            Button::new("reference-image-export")
+                                .label("Export PNG…")
+                                .small()
+                                .primary()
+                                .disabled(problem.is_some())
+                                .on_click(cx.listener(|this, _, _window, cx| {
+                                    this.export_reference_image(cx);
+                                }))
+                        )
+                )
+        )
+        .into_any_element()
+}
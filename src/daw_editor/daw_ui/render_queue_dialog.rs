@@ -0,0 +1,240 @@
+/// Render Queue Dialog
+/// Lists queued project and region renders with each job's state, and runs
+/// them unattended with a stop button that skips whatever hasn't started
+
+use super::state::*;
+use super::panel::DawPanel;
+use super::super::daw_engine::render_queue::JobState;
+use gpui::*;
+use gpui::prelude::FluentBuilder;
+use ui::{
+    button::*, h_flex, v_flex, Sizable, StyledExt, ActiveTheme, Disableable,
+};
+use std::sync::atomic::Ordering;
+
+pub fn render_render_queue_dialog(state: &DawUiState, cx: &mut Context<DawPanel>) -> impl IntoElement {
+    let dialog = &state.render_queue;
+    let total = dialog.queue.jobs.len();
+    let finished = dialog.finished();
+    let fraction = if total == 0 { 0.0 } else { finished as f32 / total as f32 };
+    let stopping = dialog.running && dialog.cancel.load(Ordering::Relaxed);
+    let failed = dialog.states.iter().filter(|s| matches!(s, JobState::Failed(_))).count();
+    let status = if total == 0 {
+        "No jobs queued. Add the open project or pick projects to render.".to_string()
+    } else if stopping {
+        "Stopping after the current job…".to_string()
+    } else if dialog.running {
+        format!("Rendering job {} of {}…", (finished + 1).min(total), total)
+    } else if finished > 0 {
+        format!("{} of {} jobs done, {} failed", finished - failed, total, failed)
+    } else {
+        format!("{} jobs queued", total)
+    };
+
+    let rows = dialog.queue.jobs.iter().enumerate().map(|(index, job)| {
+        let job_state = dialog.states.get(index).cloned().unwrap_or_default();
+        let state_color = match job_state {
+            JobState::Failed(_) => cx.theme().danger,
+            JobState::Done(_) => cx.theme().foreground,
+            _ => cx.theme().muted_foreground,
+        };
+        let detail = match job_state {
+            JobState::Failed(ref error) => Some(error.clone()),
+            JobState::Done(ref files) if files.len() > 1 => Some(format!("{} files written", files.len())),
+            _ => None,
+        };
+
+        h_flex()
+            .w_full()
+            .gap_2()
+            .items_start()
+            .child(
+                v_flex()
+                    .flex_1()
+                    .min_w_0()
+                    .gap_0p5()
+                    .child(div().text_xs().font_semibold().text_color(cx.theme().foreground).child(job.name.clone()))
+                    .child(
+                        div()
+                            .text_xs()
+                            .text_color(cx.theme().muted_foreground)
+                            .child(format!("{} → {}", job.range.label(), job.output.display()))
+                    )
+                    .when_some(detail, |d, detail| {
+                        d.child(div().text_xs().text_color(state_color).child(detail))
+                    })
+            )
+            .child(div().text_xs().text_color(state_color).child(job_state.label()))
+            .when(!dialog.running, |row| {
+                row.child(
+                    Button::new(("render-queue-remove", index))
+                        .label("✕")
+                        .ghost()
+                        .compact()
+                        .small()
+                        .tooltip("Remove from Queue")
+                        .on_click(cx.listener(move |this, _, _window, cx| {
+                            this.state.render_queue.remove_job(index);
+                            cx.notify();
+                        }))
+                )
+            })
+    });
+
+    let running = dialog.running;
+    let no_project = state.project_path.is_none();
+
+    // Backdrop swallows clicks so the queue isn't edited under a run
+    div()
+        .id("render-queue-backdrop")
+        .absolute()
+        .inset_0()
+        .flex()
+        .items_center()
+        .justify_center()
+        .bg(gpui::black().opacity(0.4))
+        .on_mouse_down(MouseButton::Left, |_, _, cx| cx.stop_propagation())
+        .child(
+            v_flex()
+                .w(px(520.0))
+                .p_4()
+                .gap_3()
+                .bg(cx.theme().background)
+                .rounded_lg()
+                .border_1()
+                .border_color(cx.theme().border)
+                .shadow_lg()
+                .child(
+                    div()
+                        .text_sm()
+                        .font_semibold()
+                        .text_color(cx.theme().foreground)
+                        .child("Render Queue")
+                )
+                .child(
+                    v_flex()
+                        .id("render-queue-jobs")
+                        .w_full()
+                        .max_h(px(280.0))
+                        .overflow_y_scroll()
+                        .gap_2()
+                        .children(rows)
+                )
+                .child(
+                    div()
+                        .w_full()
+                        .h(px(6.0))
+                        .rounded_sm()
+                        .bg(cx.theme().muted)
+                        .child(
+                            div()
+                                .h_full()
+                                .w(relative(fraction))
+                                .rounded_sm()
+                                .bg(cx.theme().primary)
+                        )
+                )
+                .child(
+                    div()
+                        .text_xs()
+                        .text_color(cx.theme().muted_foreground)
+                        .child(status)
+                )
+                .child(
+                    h_flex()
+                        .w_full()
+                        .gap_1()
+                        .flex_wrap()
+                        .child(
+                            Button::new("render-queue-add-project")
+                                .label("Add This Project")
+                                .ghost()
+                                .small()
+                                .disabled(running || no_project)
+                                .tooltip("Queue the Open Project as Last Saved")
+                                .on_click(cx.listener(|this, _, _window, cx| {
+                                    this.queue_current_project(false, cx);
+                                }))
+                        )
+                        .child(
+                            Button::new("render-queue-add-regions")
+                                .label("Add Marker Regions")
+                                .ghost()
+                                .small()
+                                .disabled(running || no_project)
+                                .tooltip("Queue One File per Marker Region of the Open Project")
+                                .on_click(cx.listener(|this, _, _window, cx| {
+                                    this.queue_current_project(true, cx);
+                                }))
+                        )
+                        .child(
+                            Button::new("render-queue-add-files")
+                                .label("Add Projects…")
+                                .ghost()
+                                .small()
+                                .disabled(running)
+                                .on_click(cx.listener(|this, _, _window, cx| {
+                                    this.queue_projects(cx);
+                                }))
+                        )
+                        .child(
+                            Button::new("render-queue-open")
+                                .label("Open…")
+                                .ghost()
+                                .small()
+                                .disabled(running)
+                                .tooltip("Load a Saved Queue File")
+                                .on_click(cx.listener(|this, _, _window, cx| {
+                                    this.open_render_queue_file(cx);
+                                }))
+                        )
+                        .child(
+                            Button::new("render-queue-save")
+                                .label("Save…")
+                                .ghost()
+                                .small()
+                                .disabled(total == 0)
+                                .tooltip("Save the Queue as JSON for Pipelines")
+                                .on_click(cx.listener(|this, _, _window, cx| {
+                                    this.save_render_queue_file(cx);
+                                }))
+                        )
+                )
+                .child(
+                    h_flex()
+                        .w_full()
+                        .gap_1()
+                        .justify_end()
+                        .child(
+                            Button::new("render-queue-close")
+                                .label("Close")
+                                .ghost()
+                                .small()
+                                .tooltip("Hide the Queue; a Run Keeps Going")
+                                .on_click(cx.listener(|this, _, _window, cx| {
+                                    this.state.render_queue_open = false;
+                                    cx.notify();
+                                }))
+                        )
+                        .child(if running {
+                            Button::new("render-queue-stop")
+                                .label(if stopping { "Stopping…" } else { "Stop" })
+                                .small()
+                                .ghost()
+                                .on_click(cx.listener(|this, _, _window, cx| {
+                                    this.state.render_queue.cancel.store(true, Ordering::Relaxed);
+                                    cx.notify();
+                                }))
+                        } else {
+                            Button::new("render-queue-run")
+                                .label("Run")
+                                .small()
+                                .primary()
+                                .disabled(total == 0)
+                                .on_click(cx.listener(|this, _, _window, cx| {
+                                    this.run_render_queue(cx);
+                                }))
+                        })
+                )
+        )
+}
@@ -0,0 +1,135 @@
+/// Project Sample Rate Dialog
+/// Modal for changing the project rate, choosing whether positions keep their
+/// musical timing or their sample values
+
+use super::state::*;
+use super::panel::DawPanel;
+use super::super::daw_engine::audio_types::SampleRateChange;
+use gpui::*;
+use gpui::prelude::FluentBuilder;
+use ui::{
+    button::*, h_flex, v_flex, Sizable, StyledExt, ActiveTheme, Disableable,
+};
+
+pub fn render_sample_rate_dialog(state: &DawUiState, cx: &mut Context<DawPanel>) -> impl IntoElement {
+    let Some(dialog) = state.sample_rate_dialog else {
+        return div().into_any_element();
+    };
+    let current_rate = state.project.as_ref().map(|p| p.sample_rate).unwrap_or_default();
+    let unchanged = dialog.rate == current_rate;
+
+    let rate_buttons: Vec<AnyElement> = PROJECT_SAMPLE_RATES
+        .iter()
+        .map(|rate| {
+            let rate = *rate;
+            Button::new(ElementId::Name(format!("sample-rate-{}", rate as u32).into()))
+                .label(format!("{:.1} kHz", rate / 1000.0))
+                .compact()
+                .small()
+                .when(dialog.rate == rate, |b| b.primary())
+                .when(dialog.rate != rate, |b| b.ghost())
+                .on_click(cx.listener(move |this, _, _window, cx| {
+                    if let Some(ref mut dialog) = this.state.sample_rate_dialog {
+                        dialog.rate = rate;
+                    }
+                    cx.notify();
+                }))
+                .into_any_element()
+        })
+        .collect();
+
+    let mode_buttons: Vec<AnyElement> = [
+        (SampleRateChange::KeepMusicalTiming, "Keep musical timing", "Clips, fades and automation stay on the same beats"),
+        (SampleRateChange::KeepSamplePositions, "Keep sample positions", "Sample values are kept, so material moves in time"),
+    ]
+    .into_iter()
+    .map(|(mode, label, tooltip)| {
+        Button::new(ElementId::Name(format!("sample-rate-mode-{:?}", mode).into()))
+            .label(label)
+            .small()
+            .when(dialog.mode == mode, |b| b.primary())
+            .when(dialog.mode != mode, |b| b.ghost())
+            .tooltip(tooltip)
+            .on_click(cx.listener(move |this, _, _window, cx| {
+                if let Some(ref mut dialog) = this.state.sample_rate_dialog {
+                    dialog.mode = mode;
+                }
+                cx.notify();
+            }))
+            .into_any_element()
+    })
+    .collect();
+
+    // Backdrop swallows clicks so the timeline underneath isn't edited
+    div()
+        .id("sample-rate-backdrop")
+        .absolute()
+        .inset_0()
+        .flex()
+        .items_center()
+        .justify_center()
+        .bg(gpui::black().opacity(0.4))
+        .on_mouse_down(MouseButton::Left, |_, _, cx| cx.stop_propagation())
+        .child(
+            v_flex()
+                .w(px(400.0))
+                .p_4()
+                .gap_3()
+                .bg(cx.theme().background)
+                .rounded_lg()
+                .border_1()
+                .border_color(cx.theme().border)
+                .shadow_lg()
+                .child(
+                    v_flex()
+                        .child(
+                            div()
+                                .text_sm()
+                                .font_semibold()
+                                .text_color(cx.theme().foreground)
+                                .child("Project Sample Rate")
+                        )
+                        .child(
+                            div()
+                                .text_xs()
+                                .text_color(cx.theme().muted_foreground)
+                                .child(format!("Current: {} Hz", current_rate as u32))
+                        )
+                )
+                .child(h_flex().w_full().flex_wrap().gap_1().children(rate_buttons))
+                .child(v_flex().w_full().gap_1().children(mode_buttons))
+                .child(
+                    div()
+                        .text_xs()
+                        .text_color(cx.theme().danger)
+                        .child("This changes the whole project: every clip, fade and automation point, and all loaded audio is resampled. It can't be undone.")
+                )
+                .child(
+                    h_flex()
+                        .w_full()
+                        .gap_2()
+                        .justify_end()
+                        .child(
+                            Button::new("sample-rate-cancel")
+                                .label("Cancel")
+                                .small()
+                                .ghost()
+                                .on_click(cx.listener(|this, _, _window, cx| {
+                                    this.state.sample_rate_dialog = None;
+                                    cx.notify();
+                                }))
+                        )
+                        .child(
+                            Button::new("sample-rate-apply")
+                                .label("Change Rate")
+                                .small()
+                                .primary()
+                                .disabled(unchanged)
+                                .on_click(cx.listener(|this, _, _window, cx| {
+                                    this.apply_sample_rate_change(cx);
+                                }))
+                        )
+                )
+        )
+        .into_any_element()
+}
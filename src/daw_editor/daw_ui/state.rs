@@ -1,14 +1,26 @@
 /// DAW Panel State Management
 /// Central state for all DAW UI components
 
-use super::super::{audio_service::AudioService, audio_types::*, project::DawProject};
+use super::super::daw_engine::{audio_service::AudioService, audio_types::*, clipboard::ClipboardPayload, history::{ArrangementSnapshot, EditAction, EditHistory}, peak_cache::{PeakCache, WaveformPeaks}, preferences::{self, EditPreferences, FadeEnds, FadePreset, FadePresets, Fades}, project::{DawProject, TrackSnapshot, AUDIO_FOLDER, MAX_TEMPO, MIN_TEMPO}, save_queue::{DirtyTracker, SaveQueue, SaveResult}, view_state::ViewState, drum_rack::{DrumPad, DrumRack}, tap_tempo::TapTempo, transport_chase::{SyncSource, TransportChase}, midi_input::MidiInputs, effects::{Effect, EffectClipboard, EffectId, EffectKind}, spectrogram::Spectrogram, automation_recording::{self, AutomationRecorder, ControlMapping}, project_report::ProjectReport, profiling::DspProfile, render_queue::{JobState, RenderJob, RenderQueue}, loop_import::LoopLayer, loop_crossfade, record_quantize, generators::GeneratorSettings, fill, render::BouncedTrack, metering::InputMeter, reference_image::ReferenceImageSettings, take_recorder::{take_path, TakeTarget}};
 use gpui::*;
 use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
 
-use crate::daw_editor::audio_types::{SAMPLE_RATE, AudioClip, AudioAssetData};
-use ui::{VirtualListScrollHandle, scroll::ScrollbarState};
+use crate::daw_editor::daw_engine::audio_types::{SAMPLE_RATE, AudioClip, AudioAssetData};
+use ui::{PixelsExt, VirtualListScrollHandle, scroll::ScrollbarState};
+
+/// Minimum interactive width of a clip in pixels, regardless of zoom
+pub const MIN_CLIP_HIT_WIDTH: f32 = 4.0;
+
+/// How close a click has to land to a snap point to jump to it, in pixels
+pub const SNAP_TOLERANCE_PIXELS: f32 = 8.0;
+
+/// Height range of the mixer docked under the timeline, in pixels
+pub const DEFAULT_MIXER_HEIGHT: f32 = 420.0;
+pub const MIN_MIXER_HEIGHT: f32 = 220.0;
+pub const MAX_MIXER_HEIGHT: f32 = 900.0;
 
 /// Main view modes
 #[derive(Clone, Copy, PartialEq, Debug)]
@@ -35,6 +47,22 @@ pub enum InspectorTab {
     Clip,
     Automation,
     Effects,
+    Project,
+}
+
+impl InspectorTab {
+    pub const ALL: [InspectorTab; 5] = [InspectorTab::Track, InspectorTab::Clip, InspectorTab::Automation, InspectorTab::Effects, InspectorTab::Project];
+
+    /// Name stored in saved layouts
+    pub fn name(self) -> &'static str {
+        match self {
+            InspectorTab::Track => "Track",
+            InspectorTab::Clip => "Clip",
+            InspectorTab::Automation => "Automation",
+            InspectorTab::Effects => "Effects",
+            InspectorTab::Project => "Project",
+        }
+    }
 }
 
 /// Tool selection for timeline editing
@@ -63,9 +91,37 @@ pub enum SnapValue {
     Eighth,
     Sixteenth,
     ThirtySecond,
+    QuarterTriplet,
+    EighthTriplet,
+    SixteenthTriplet,
+    HalfDotted,
+    QuarterDotted,
+    EighthDotted,
 }
 
 impl SnapValue {
+    /// Snap values grouped by feel, in the order the snap menu lists them
+    pub const GROUPS: [(&'static str, &'static [SnapValue]); 3] = [
+        ("Straight", &[
+            SnapValue::Bar,
+            SnapValue::Half,
+            SnapValue::Quarter,
+            SnapValue::Eighth,
+            SnapValue::Sixteenth,
+            SnapValue::ThirtySecond,
+        ]),
+        ("Triplet", &[
+            SnapValue::QuarterTriplet,
+            SnapValue::EighthTriplet,
+            SnapValue::SixteenthTriplet,
+        ]),
+        ("Dotted", &[
+            SnapValue::HalfDotted,
+            SnapValue::QuarterDotted,
+            SnapValue::EighthDotted,
+        ]),
+    ];
+
     pub fn to_beats(&self) -> f64 {
         match self {
             SnapValue::Bar => 4.0,
@@ -74,6 +130,22 @@ impl SnapValue {
             SnapValue::Eighth => 0.5,
             SnapValue::Sixteenth => 0.25,
             SnapValue::ThirtySecond => 0.125,
+            // Three triplet notes fill the space of two straight ones
+            SnapValue::QuarterTriplet => 2.0 / 3.0,
+            SnapValue::EighthTriplet => 1.0 / 3.0,
+            SnapValue::SixteenthTriplet => 1.0 / 6.0,
+            // A dot adds half the note's length
+            SnapValue::HalfDotted => 3.0,
+            SnapValue::QuarterDotted => 1.5,
+            SnapValue::EighthDotted => 0.75,
+        }
+    }
+
+    pub fn feel(&self) -> GridFeel {
+        match self {
+            SnapValue::QuarterTriplet | SnapValue::EighthTriplet | SnapValue::SixteenthTriplet => GridFeel::Triplet,
+            SnapValue::HalfDotted | SnapValue::QuarterDotted | SnapValue::EighthDotted => GridFeel::Dotted,
+            _ => GridFeel::Straight,
         }
     }
 
@@ -85,10 +157,53 @@ impl SnapValue {
             SnapValue::Eighth => "1/8",
             SnapValue::Sixteenth => "1/16",
             SnapValue::ThirtySecond => "1/32",
+            SnapValue::QuarterTriplet => "1/4T",
+            SnapValue::EighthTriplet => "1/8T",
+            SnapValue::SixteenthTriplet => "1/16T",
+            SnapValue::HalfDotted => "1/2.",
+            SnapValue::QuarterDotted => "1/4.",
+            SnapValue::EighthDotted => "1/8.",
+        }
+    }
+}
+
+/// How times are shown in the ruler and transport
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimeFormat {
+    BarsBeats,
+    Timecode,
+}
+
+impl TimeFormat {
+    pub fn next(&self) -> Self {
+        match self {
+            TimeFormat::BarsBeats => TimeFormat::Timecode,
+            TimeFormat::Timecode => TimeFormat::BarsBeats,
         }
     }
 }
 
+/// Where the clip being moved or trimmed sits now, in the active time
+/// format, for the readout shown while dragging
+#[derive(Clone, Debug, PartialEq)]
+pub struct ClipDragReadout {
+    pub start: String,
+    pub length: String,
+    pub end: String,
+    /// How far the dragged edge, or the whole clip, is from where it started
+    pub delta: String,
+    pub snap: String,
+}
+
+/// Whole bars, whole beats past them and hundredths of a beat in `samples`
+fn bars_beats(transport: &Transport, samples: SampleTime) -> (u64, u64, u64) {
+    let beats = transport.samples_to_beats(samples);
+    let beats_per_bar = transport.time_signature_numerator.max(1) as f64;
+    let bars = (beats / beats_per_bar).floor();
+    let beat = (beats - bars * beats_per_bar).floor();
+    (bars as u64, beat as u64, (beats.fract() * 100.0).floor() as u64)
+}
+
 /// Audio file metadata
 #[derive(Clone, Debug)]
 pub struct AudioFile {
@@ -134,6 +249,410 @@ pub struct SelectionState {
     pub loop_end: Option<f64>,
 }
 
+/// How a marquee combines with the existing selection
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum MarqueeMode {
+    Replace,
+    /// Shift: add to the selection
+    Add,
+    /// Ctrl: toggle items under the marquee
+    Toggle,
+}
+
+impl MarqueeMode {
+    /// The mode the held modifiers ask for
+    pub fn from_modifiers(modifiers: &Modifiers) -> Self {
+        if modifiers.shift {
+            MarqueeMode::Add
+        } else if modifiers.secondary() {
+            MarqueeMode::Toggle
+        } else {
+            MarqueeMode::Replace
+        }
+    }
+}
+
+/// What a marquee selects
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum MarqueeTarget {
+    Clips,
+    /// Breakpoints of one automation lane, on every track the marquee spans
+    AutomationPoints(AutomationParameter),
+}
+
+/// A fader level being typed into its readout
+#[derive(Clone, PartialEq, Debug)]
+pub struct FaderEntry {
+    /// Track whose fader is edited, nil for the master
+    pub track_id: TrackId,
+    pub text: String,
+}
+
+/// Transport setting typed into its display
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TransportField {
+    Tempo,
+    TimeSignature,
+    LoopCrossfade,
+}
+
+/// A tempo, time signature or loop crossfade being typed. An entry that doesn't parse
+/// stays open with the reason shown beside it.
+#[derive(Clone, PartialEq, Debug)]
+pub struct TransportEntry {
+    pub field: TransportField,
+    pub text: String,
+    pub error: Option<String>,
+}
+
+/// A take being recorded: where record was pressed, and where capture
+/// begins, a bar earlier with a count-in
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct TakeStart {
+    pub position: SampleTime,
+    pub captured_from: SampleTime,
+}
+
+/// A marker name being typed into its label on the ruler
+#[derive(Clone, PartialEq, Debug)]
+pub struct MarkerRename {
+    pub marker: MarkerId,
+    pub text: String,
+}
+
+/// A region name being typed into its label in the region lane
+#[derive(Clone, PartialEq, Debug)]
+pub struct RegionRename {
+    pub region: RegionId,
+    pub text: String,
+}
+
+/// A clip name being typed into its title on the timeline or in the inspector
+#[derive(Clone, PartialEq, Debug)]
+pub struct ClipRename {
+    pub track: TrackId,
+    pub clip: ClipId,
+    pub text: String,
+}
+
+/// Part of a region being dragged in the region lane
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RegionEdge {
+    /// The whole region, keeping its length
+    Body,
+    Start,
+    End,
+}
+
+/// Track or clip the keyboard works on, drawn with a focus ring
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum KeyboardFocus {
+    Track(TrackId),
+    Clip(TrackId, ClipId),
+}
+
+impl KeyboardFocus {
+    pub fn track_id(&self) -> TrackId {
+        match self {
+            KeyboardFocus::Track(track_id) | KeyboardFocus::Clip(track_id, _) => *track_id,
+        }
+    }
+}
+
+/// Numeric fields in the clip properties dialog
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ClipProperty {
+    Start,
+    Length,
+    Offset,
+    LoopStart,
+    LoopEnd,
+}
+
+impl ClipProperty {
+    pub const ALL: [ClipProperty; 5] = [
+        ClipProperty::Start,
+        ClipProperty::Length,
+        ClipProperty::Offset,
+        ClipProperty::LoopStart,
+        ClipProperty::LoopEnd,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ClipProperty::Start => "Start",
+            ClipProperty::Length => "Length",
+            ClipProperty::Offset => "Source Offset",
+            ClipProperty::LoopStart => "Loop Start",
+            ClipProperty::LoopEnd => "Loop End",
+        }
+    }
+
+    /// Timeline positions show as 1-based bars:beats, everything else as a length
+    fn is_position(&self) -> bool {
+        matches!(self, ClipProperty::Start)
+    }
+
+    fn next(&self) -> Self {
+        let idx = Self::ALL.iter().position(|p| p == self).unwrap_or(0);
+        Self::ALL[(idx + 1) % Self::ALL.len()]
+    }
+}
+
+/// Sample rates offered when changing the project rate
+pub const PROJECT_SAMPLE_RATES: [f32; 6] = [44_100.0, 48_000.0, 88_200.0, 96_000.0, 176_400.0, 192_000.0];
+
+/// Modal for changing the project sample rate
+#[derive(Clone, Copy, Debug)]
+pub struct SampleRateDialog {
+    pub rate: f32,
+    pub mode: SampleRateChange,
+}
+
+/// Modal for exporting a waveform and spectrogram picture
+#[derive(Clone, Copy, Debug)]
+pub struct ReferenceImageDialog {
+    /// Track to draw, or the master with `None`
+    pub source: Option<TrackId>,
+    pub settings: ReferenceImageSettings,
+}
+
+/// A batch export of clips or marker regions, shown in a progress dialog
+/// until dismissed
+#[derive(Clone, Debug)]
+pub struct ClipExportProgress {
+    /// Dialog title, such as "Export Clips"
+    pub title: &'static str,
+    /// What is being exported, singular, for the status line
+    pub item: &'static str,
+    pub done: usize,
+    pub total: usize,
+    /// Set by the cancel button, checked between clips
+    pub cancel: Arc<AtomicBool>,
+    /// Result line once the batch has stopped
+    pub summary: Option<String>,
+}
+
+/// Render jobs queued in the render queue dialog, with how each one went
+/// in the current or last run
+#[derive(Clone, Debug, Default)]
+pub struct RenderQueueDialog {
+    pub queue: RenderQueue,
+    /// One per job, reset to Queued when jobs are added or removed
+    pub states: Vec<JobState>,
+    pub running: bool,
+    /// Set by the stop button, checked between jobs
+    pub cancel: Arc<AtomicBool>,
+}
+
+impl RenderQueueDialog {
+    pub fn add_job(&mut self, job: RenderJob) {
+        self.queue.jobs.push(job);
+        self.states = vec![JobState::Queued; self.queue.jobs.len()];
+    }
+
+    pub fn remove_job(&mut self, index: usize) {
+        if index < self.queue.jobs.len() {
+            self.queue.jobs.remove(index);
+            self.states = vec![JobState::Queued; self.queue.jobs.len()];
+        }
+    }
+
+    /// Jobs that have finished in this run, for the progress bar
+    pub fn finished(&self) -> usize {
+        self.states.iter().filter(|s| s.is_finished()).count()
+    }
+}
+
+/// Clip change made at the clip editor's cursor
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ClipCursorEdit {
+    TrimStart,
+    TrimEnd,
+    FadeIn,
+    FadeOut,
+    LoopStart,
+    LoopEnd,
+}
+
+impl ClipCursorEdit {
+    pub const ALL: [ClipCursorEdit; 6] = [
+        ClipCursorEdit::TrimStart,
+        ClipCursorEdit::TrimEnd,
+        ClipCursorEdit::FadeIn,
+        ClipCursorEdit::FadeOut,
+        ClipCursorEdit::LoopStart,
+        ClipCursorEdit::LoopEnd,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ClipCursorEdit::TrimStart => "Start Here",
+            ClipCursorEdit::TrimEnd => "End Here",
+            ClipCursorEdit::FadeIn => "Fade In to Here",
+            ClipCursorEdit::FadeOut => "Fade Out from Here",
+            ClipCursorEdit::LoopStart => "Loop from Here",
+            ClipCursorEdit::LoopEnd => "Loop to Here",
+        }
+    }
+}
+
+/// Modal for typing exact clip positions. Edits go to a draft copy of the
+/// clip and are only written back to the project when applied.
+#[derive(Clone, Debug)]
+pub struct ClipPropertiesDialog {
+    pub track_id: TrackId,
+    pub clip_id: ClipId,
+    pub original: AudioClip,
+    pub draft: AudioClip,
+    /// Length of the clip's audio in frames, if the asset is loaded
+    pub source_len: Option<SampleTime>,
+    pub show_bars_beats: bool,
+    pub editing: Option<ClipProperty>,
+    pub input: String,
+    pub error: Option<String>,
+    /// Bars the clip's loop spans for tempo detection, None to guess
+    pub loop_bars: Option<u32>,
+}
+
+impl ClipPropertiesDialog {
+    pub fn new(track_id: TrackId, clip: AudioClip, source_len: Option<SampleTime>) -> Self {
+        Self {
+            track_id,
+            clip_id: clip.id,
+            original: clip.clone(),
+            draft: clip,
+            source_len,
+            show_bars_beats: false,
+            editing: None,
+            input: String::new(),
+            error: None,
+            loop_bars: None,
+        }
+    }
+
+    /// Bars the loop is taken to span: the chosen count, else the best guess
+    pub fn detected_bars(&self, transport: &Transport) -> Option<u32> {
+        self.loop_bars.or_else(|| transport.guess_loop_bars(self.draft.loop_length()).first().map(|(bars, _)| *bars))
+    }
+
+    /// Tempo the loop was recorded at, from its length and bar count
+    pub fn detected_tempo(&self, transport: &Transport) -> Option<f32> {
+        transport.tempo_for_length(self.draft.loop_length(), self.detected_bars(transport)?)
+    }
+
+    pub fn value(&self, property: ClipProperty) -> Option<SampleTime> {
+        match property {
+            ClipProperty::Start => Some(self.draft.start_time),
+            ClipProperty::Length => Some(self.draft.duration),
+            ClipProperty::Offset => Some(self.draft.offset),
+            ClipProperty::LoopStart => self.draft.loop_points.map(|l| l.start),
+            ClipProperty::LoopEnd => self.draft.loop_points.map(|l| l.end),
+        }
+    }
+
+    fn set_value(&mut self, property: ClipProperty, value: SampleTime) {
+        match property {
+            ClipProperty::Start => self.draft.start_time = value,
+            ClipProperty::Length => self.draft.duration = value,
+            // Only the source window moves; the clip stays put on the timeline
+            ClipProperty::Offset => self.draft.offset = value,
+            ClipProperty::LoopStart => {
+                let end = self.draft.loop_points.map(|l| l.end).unwrap_or(value);
+                self.draft.loop_points = Some(ClipLoop { start: value, end });
+            }
+            ClipProperty::LoopEnd => {
+                let start = self.draft.loop_points.map(|l| l.start).unwrap_or(0);
+                self.draft.loop_points = Some(ClipLoop { start, end: value });
+            }
+        }
+    }
+
+    /// Display text for a field in the current unit
+    pub fn format_value(&self, property: ClipProperty, transport: &Transport) -> String {
+        match self.value(property) {
+            Some(value) if self.show_bars_beats => transport.format_bars_beats(value, property.is_position()),
+            Some(value) => value.to_string(),
+            None => "—".to_string(),
+        }
+    }
+
+    pub fn begin_edit(&mut self, property: ClipProperty, transport: &Transport) {
+        self.input = match self.value(property) {
+            Some(_) => self.format_value(property, transport),
+            None => String::new(),
+        };
+        self.editing = Some(property);
+        self.error = None;
+    }
+
+    pub fn cancel_edit(&mut self) {
+        self.editing = None;
+        self.input.clear();
+    }
+
+    /// Parse the input into the field being edited. On failure the field
+    /// stays in edit mode and `error` explains why.
+    pub fn commit_edit(&mut self, transport: &Transport) -> bool {
+        let Some(property) = self.editing else {
+            return true;
+        };
+
+        let text = self.input.trim();
+        let parsed = if self.show_bars_beats {
+            transport
+                .parse_bars_beats(text, property.is_position())
+                .ok_or_else(|| "Enter a value as bars:beats:ticks".to_string())
+        } else {
+            match text.parse::<i64>() {
+                Ok(v) if v < 0 => Err(format!("{} can't be negative", property.label())),
+                Ok(v) => Ok(v as SampleTime),
+                Err(_) => Err("Enter a whole number of samples".to_string()),
+            }
+        };
+
+        match parsed {
+            Ok(value) => {
+                self.set_value(property, value);
+                self.editing = None;
+                self.input.clear();
+                self.error = self.validate().err().map(|e| e.to_string());
+                true
+            }
+            Err(message) => {
+                self.error = Some(message);
+                false
+            }
+        }
+    }
+
+    /// Commit the current field and start editing the next one
+    pub fn edit_next(&mut self, transport: &Transport) {
+        if let Some(property) = self.editing {
+            if self.commit_edit(transport) {
+                self.begin_edit(property.next(), transport);
+            }
+        }
+    }
+
+    /// Enable or clear the clip loop. New loops cover the visible region.
+    pub fn set_loop_enabled(&mut self, enabled: bool) {
+        self.draft.loop_points = enabled.then(|| ClipLoop {
+            start: self.draft.offset,
+            end: self.draft.offset + self.draft.source_duration(),
+        });
+        self.error = self.validate().err().map(|e| e.to_string());
+    }
+
+    pub fn validate(&self) -> anyhow::Result<()> {
+        match self.source_len {
+            Some(source_len) => self.draft.validate_source_range(source_len),
+            None if self.draft.duration == 0 => Err(anyhow::anyhow!("Clip length must be greater than zero")),
+            None => Ok(()),
+        }
+    }
+}
+
 /// Drag and drop state
 #[derive(Clone, Debug)]
 pub enum DragState {
@@ -143,16 +662,63 @@ pub enum DragState {
         track_id: TrackId,
         start_beat: f64,
         mouse_offset: (f32, f32),
+        /// Where each movable selected clip started, the dragged one
+        /// included, so the group moves from there and the move is undone
+        /// exactly even for clips held at the timeline start
+        origins: Vec<(TrackId, ClipId, SampleTime)>,
     },
+    /// Trimming a clip from one edge. The clip as it was is kept so the
+    /// edge follows the pointer from where it started and the trim can be
+    /// undone in one step.
     DraggingClipEdge {
         clip_id: uuid::Uuid,
         track_id: TrackId,
         is_start: bool,
+        start_mouse_x: f32,
+        before: Box<AudioClip>,
+    },
+    Marquee {
+        target: MarqueeTarget,
+        mode: MarqueeMode,
+        start_track: TrackId,
+        start_x: f32,
+        current_track: TrackId,
+        current_x: f32,
+        base_clips: HashSet<ClipId>,
+        base_points: Vec<(TrackId, AutomationParameter, usize)>,
+    },
+    DraggingFadeLength {
+        clip_id: uuid::Uuid,
+        track_id: TrackId,
+        is_fade_in: bool,
+        start_mouse_x: f32,
+        start_length: SampleTime,
+    },
+    DraggingFadeCurve {
+        clip_id: uuid::Uuid,
+        track_id: TrackId,
+        is_fade_in: bool,
+        start_mouse_y: f32,
+        start_curve: f32,
     },
     DraggingAutomationPoint {
         track_id: TrackId,
         param_type: AutomationParameter,
         point_index: usize,
+        start_mouse_x: f32,
+        start_mouse_y: f32,
+        start_beat: f64,
+        start_value: f32,
+    },
+    /// Bending the automation segment that starts at `point_index`
+    DraggingAutomationCurve {
+        track_id: TrackId,
+        param_type: AutomationParameter,
+        point_index: usize,
+        start_mouse_y: f32,
+        start_tension: f32,
+        /// Whether the segment ends higher in the lane than it starts
+        rising: bool,
     },
     DraggingFile {
         file_path: PathBuf,
@@ -176,6 +742,7 @@ pub enum DragState {
         track_id: TrackId,
         start_mouse_x: Pixels,
         start_value: f32,
+        start_volume: f32,
     },
     DraggingTrackHeaderPan {
         track_id: TrackId,
@@ -188,6 +755,21 @@ pub enum DragState {
         start_mouse_x: f32,
         start_amount: f32,
     },
+    DraggingLimiterCeiling {
+        start_mouse_x: f32,
+        start_ceiling: f32,
+    },
+    DraggingRegion {
+        region_id: RegionId,
+        edge: RegionEdge,
+        start_mouse_x: f32,
+        /// Region start and end in beats when the drag began
+        start_beats: (f64, f64),
+    },
+    ResizingMixer {
+        start_mouse_y: f32,
+        start_height: f32,
+    },
 }
 
 impl Default for DragState {
@@ -206,6 +788,11 @@ pub struct DawUiState {
 
     // Audio asset cache for getting real durations
     pub loaded_assets: std::collections::HashMap<PathBuf, Arc<AudioAssetData>>,
+    /// Waveform peaks by asset path, filled from the disk cache in the background
+    pub waveforms: HashMap<PathBuf, Arc<WaveformPeaks>>,
+    /// Assets whose peaks are being loaded or generated
+    pub waveforms_pending: HashSet<PathBuf>,
+    pub peak_cache: PeakCache,
 
     // View state
     pub view_mode: ViewMode,
@@ -221,16 +808,24 @@ pub struct DawUiState {
     pub is_looping: bool,
     pub metronome_enabled: bool,
     pub count_in_enabled: bool,
+    /// Snap recorded takes to the grid when recording stops
+    pub record_quantize: bool,
+    /// The take being recorded, if one is
+    pub recording_take: Option<TakeStart>,
     
     // Edit state
     pub current_tool: EditTool,
     pub snap_mode: SnapMode,
     pub snap_value: SnapValue,
+    pub time_format: TimeFormat,
     
     // Viewport and selection
     pub viewport: ViewportState,
     pub selection: SelectionState,
     pub drag_state: DragState,
+    /// Last pointer position in the window during a clip move or trim, for
+    /// the readout that follows it
+    pub drag_pointer: Point<Pixels>,
     
     // Asset library
     pub audio_files: Vec<AudioFile>,
@@ -247,10 +842,24 @@ pub struct DawUiState {
     pub mixer_width: f32,
     pub mixer_scroll_handle: VirtualListScrollHandle,
     pub mixer_scroll_state: ScrollbarState,
+    /// Height of the mixer docked under the timeline
+    pub mixer_height: f32,
+    /// Narrow strips keep the name, buttons, pan, meter and fader only
+    pub mixer_narrow: bool,
+    /// Bus whose feeding tracks are spilled: only they and the bus are shown
+    pub mixer_spill: Option<TrackId>,
+    /// Track the mixer should scroll to, set when a track is selected elsewhere
+    pub mixer_reveal: Option<TrackId>,
 
     // Metering data
     pub track_meters: std::collections::HashMap<TrackId, MeterData>,
+    /// Input levels of armed and monitoring tracks, polled even while stopped
+    pub input_meters: std::collections::HashMap<TrackId, InputMeter>,
     pub master_meter: MeterData,
+    /// Master limiter gain reduction in dB, polled with the meters
+    pub master_limiter_reduction: f32,
+    /// Loudness compensation the master limiter's gain match applies, in dB
+    pub master_gain_match_db: f32,
 
     // Virtual list scroll handles for performance
     pub timeline_scroll_handle: VirtualListScrollHandle,  // For horizontal scrolling
@@ -258,26 +867,161 @@ pub struct DawUiState {
     pub timeline_vertical_scroll_handle: UniformListScrollHandle,  // For vertical scrolling of rows
     pub timeline_scroll_axis_lock: Option<Axis>,  // Lock scrolling to one axis at a time
     pub timeline_scroll_lock_timeout: Option<std::time::Instant>,  // Release lock after inactivity
+    /// Width of the visible timeline in pixels, measured as it's drawn
+    pub timeline_width: f32,
+    /// Where following the playhead last left the view, to tell when it's
+    /// been scrolled by hand since
+    pub follow_scroll: Option<f32>,
+    /// Following is paused until then after a manual scroll
+    pub follow_paused_until: Option<std::time::Instant>,
     
     // Undo/Redo
+    pub history: EditHistory,
     pub can_undo: bool,
     pub can_redo: bool,
+
+    // Saving
+    pub save_queue: SaveQueue,
+    pub dirty: Arc<DirtyTracker>,
+    /// Saves requested but not yet finished, drives the "saving…" indicator
+    pub pending_saves: usize,
+    pub save_error: Option<String>,
     
+    // Last copied clips, used when the OS clipboard holds something else
+    pub clip_clipboard: Option<ClipboardPayload>,
+
     // UI state
     pub context_menu_position: Option<Point<Pixels>>,
     pub show_track_color_picker: Option<TrackId>,
     pub renaming_track: Option<TrackId>,
     pub rename_buffer: String,
+    /// Last clip click (track, x position, index into stacked clips) for cycling selection
+    pub clip_click_cycle: Option<(TrackId, f32, usize)>,
+    /// Keyboard focus, moved with Tab and the arrow keys or by clicking
+    pub keyboard_focus: Option<KeyboardFocus>,
+    pub clip_properties: Option<ClipPropertiesDialog>,
+    pub sample_rate_dialog: Option<SampleRateDialog>,
+    pub reference_image_dialog: Option<ReferenceImageDialog>,
+    pub fader_entry: Option<FaderEntry>,
+    pub transport_entry: Option<TransportEntry>,
+    /// Whether the snap value menu is open
+    pub snap_menu_open: bool,
+    /// Whether seeking snaps the playhead to clip edges, markers and the
+    /// loop points as well as the grid
+    pub snap_playhead_to_events: bool,
+    /// Whether dragged clips snap their edges to the edges of clips on other
+    /// tracks, independently of the grid
+    pub magnetic_snap: bool,
+    /// Edge a dragged clip is locked to by magnetic snapping, in beats, drawn
+    /// as a line across the tracks
+    pub magnetic_snap_line: Option<f64>,
+    /// Length of silence the insert command adds, in bars
+    pub insert_silence_bars: u32,
+    /// Bars from the start of the selection duplicate to fill repeats it
+    /// for, or None to fill to the loop end
+    pub fill_bars: Option<u32>,
+    /// Fill by looping a lone clip's audio rather than adding copies
+    pub fill_with_clip_loop: bool,
+    /// Fade and crossfade defaults, stored per user rather than in the project
+    pub preferences: EditPreferences,
+    pub preferences_path: PathBuf,
+    /// Whether the fade preferences menu is open
+    pub fade_menu_open: bool,
+    /// Fade presets the user saved, on top of the built-in ones
+    pub fade_presets: FadePresets,
+    pub fade_presets_path: PathBuf,
+    /// Whether the clip inspector's fade preset dropdown is open
+    pub fade_preset_menu_open: bool,
+    /// Whether the track color and contrast menu is open
+    pub color_menu_open: bool,
+    /// Whether the generator insert menu is open
+    pub generator_menu_open: bool,
+    /// Generator the insert menu will make next
+    pub generator: GeneratorSettings,
+    /// Whether exported clips go through their track's volume, pan and automation
+    pub clip_export_include_track: bool,
+    pub clip_export: Option<ClipExportProgress>,
+    /// Jobs in the render queue dialog, kept while it's closed
+    pub render_queue: RenderQueueDialog,
+    pub render_queue_open: bool,
+    /// Whether the inspector shows and steps track offsets in milliseconds
+    pub track_offset_in_ms: bool,
+    /// Tracks whose volume automation is drawn over their clips for editing
+    pub volume_lane_tracks: HashSet<TrackId>,
+    /// Whether edited automation points snap to round values such as whole dB
+    pub automation_value_snap: bool,
+    /// Whether edited automation points snap to the grid
+    pub automation_time_snap: bool,
+    /// Arrangement before the automation point being dragged, for its undo step
+    pub automation_drag_before: Option<ArrangementSnapshot>,
+    /// Automation being written from mapped controllers
+    pub automation_recorder: AutomationRecorder,
+    /// Arrangement before the controller passes in progress, for their undo step
+    pub automation_pass_before: Option<ArrangementSnapshot>,
+    /// Track parameter the next controller to move gets mapped to
+    pub control_learn: Option<(TrackId, AutomationParameter)>,
+    /// Whether the browser and inspector docks of the workspace are open,
+    /// mirrored here so they're saved with the rest of the layout
+    pub left_dock_open: bool,
+    pub right_dock_open: bool,
+    /// Set when the layout was reset or restored and the workspace docks
+    /// still need to follow `left_dock_open` and `right_dock_open`
+    pub dock_layout_changed: bool,
+    /// Pads of the project's drum rack, saved beside the project
+    pub drum_rack: DrumRack,
+    pub selected_pad: usize,
+    pub tap_tempo: TapTempo,
+    /// Follows an external MIDI Time Code or clock master, when one is set
+    pub transport_chase: TransportChase,
+    /// Open MIDI input ports, `None` until the engine starts
+    pub midi_inputs: Option<MidiInputs>,
+    pub selected_marker: Option<MarkerId>,
+    pub marker_rename: Option<MarkerRename>,
+    pub selected_region: Option<RegionId>,
+    pub region_rename: Option<RegionRename>,
+    pub clip_rename: Option<ClipRename>,
+    /// Arrangement before the region drag in progress, for its undo step
+    pub region_drag_before: Option<ArrangementSnapshot>,
+    /// Why the last effect paste was refused, shown in the FX inspector
+    pub effect_error: Option<String>,
+    /// Spectrograms of clip source files, built on demand for the clip editor
+    pub spectrograms: HashMap<PathBuf, Arc<Spectrogram>>,
+    pub spectrograms_pending: HashSet<PathBuf>,
+    /// Clip editor cursor, as a frame of the edited clip's source file
+    pub clip_editor_cursor: Option<SampleTime>,
+    /// Fraction of the source file the clip editor shows, 1.0 for all of it
+    pub clip_editor_zoom: f64,
+    /// Loop marker being dragged in the clip editor; the cursor follows the
+    /// mouse and the edit is applied on release
+    pub clip_editor_drag: Option<ClipCursorEdit>,
+    /// Why the last clip editor change was refused
+    pub clip_editor_error: Option<String>,
+    /// Whether the reference tracks are playing instead of the mix
+    pub listening_reference: bool,
+    /// Reference tracks whose loudness is being matched to the mix
+    pub reference_matching: HashSet<TrackId>,
+    /// Last statistics built for the project inspector
+    pub project_report: Option<ProjectReport>,
+    /// Whether a project report is being built
+    pub building_report: bool,
+    /// Whether the audio graph is timing every track and effect
+    pub dsp_profiling: bool,
+    /// Last timings read from the audio graph while profiling
+    pub dsp_profile: DspProfile,
 }
 
 impl DawUiState {
     pub fn new() -> Self {
+        let preferences = EditPreferences::load(&EditPreferences::default_path());
         Self {
             project: None,
             project_path: None,
             project_dir: None,
             audio_service: None,
             loaded_assets: std::collections::HashMap::new(),
+            waveforms: HashMap::new(),
+            waveforms_pending: HashSet::new(),
+            peak_cache: PeakCache::default_location(),
 
             view_mode: ViewMode::Arrange, // Start in arrange view with timeline
             browser_tab: BrowserTab::Files,
@@ -291,14 +1035,18 @@ impl DawUiState {
             is_looping: false,
             metronome_enabled: false,
             count_in_enabled: false,
+            record_quantize: false,
+            recording_take: None,
             
             current_tool: EditTool::Select,
             snap_mode: SnapMode::Grid,
             snap_value: SnapValue::Quarter,
+            time_format: TimeFormat::BarsBeats,
             
             viewport: ViewportState::default(),
             selection: SelectionState::default(),
             drag_state: DragState::None,
+            drag_pointer: Point::default(),
             
             audio_files: Vec::new(),
             filtered_files: Vec::new(),
@@ -312,23 +1060,102 @@ impl DawUiState {
             mixer_width: 80.0,
             mixer_scroll_handle: VirtualListScrollHandle::new(),
             mixer_scroll_state: ScrollbarState::default(),
+            mixer_height: DEFAULT_MIXER_HEIGHT,
+            mixer_narrow: false,
+            mixer_spill: None,
+            mixer_reveal: None,
 
             track_meters: std::collections::HashMap::new(),
+            input_meters: std::collections::HashMap::new(),
             master_meter: MeterData::default(),
+            master_limiter_reduction: 0.0,
+            master_gain_match_db: 0.0,
 
             timeline_scroll_handle: VirtualListScrollHandle::new(),
             timeline_scroll_state: ScrollbarState::default(),
             timeline_vertical_scroll_handle: UniformListScrollHandle::new(),
             timeline_scroll_axis_lock: None,
             timeline_scroll_lock_timeout: None,
+            timeline_width: 0.0,
+            follow_scroll: None,
+            follow_paused_until: None,
             
+            history: EditHistory::new(),
             can_undo: false,
             can_redo: false,
+            save_queue: SaveQueue::new(),
+            dirty: Arc::new(DirtyTracker::default()),
+            pending_saves: 0,
+            save_error: None,
             
+            clip_clipboard: None,
+
             context_menu_position: None,
             show_track_color_picker: None,
             renaming_track: None,
             rename_buffer: String::new(),
+            clip_click_cycle: None,
+            keyboard_focus: None,
+            clip_properties: None,
+            sample_rate_dialog: None,
+            reference_image_dialog: None,
+            fader_entry: None,
+            transport_entry: None,
+            snap_menu_open: false,
+            snap_playhead_to_events: true,
+            magnetic_snap: false,
+            magnetic_snap_line: None,
+            insert_silence_bars: 1,
+            fill_bars: None,
+            fill_with_clip_loop: false,
+            transport_chase: TransportChase::for_preferences(&preferences),
+            midi_inputs: None,
+            preferences,
+            preferences_path: EditPreferences::default_path(),
+            fade_menu_open: false,
+            fade_presets: FadePresets::load(&FadePresets::default_path()),
+            fade_presets_path: FadePresets::default_path(),
+            fade_preset_menu_open: false,
+            color_menu_open: false,
+            generator_menu_open: false,
+            generator: GeneratorSettings::default(),
+            clip_export_include_track: false,
+            clip_export: None,
+            render_queue: RenderQueueDialog::default(),
+            render_queue_open: false,
+            track_offset_in_ms: true,
+            volume_lane_tracks: HashSet::new(),
+            automation_value_snap: false,
+            automation_time_snap: true,
+            automation_drag_before: None,
+            automation_recorder: AutomationRecorder::default(),
+            automation_pass_before: None,
+            control_learn: None,
+            left_dock_open: true,
+            right_dock_open: true,
+            dock_layout_changed: false,
+            drum_rack: DrumRack::default(),
+            selected_pad: 0,
+            tap_tempo: TapTempo::new(),
+            selected_marker: None,
+            marker_rename: None,
+            selected_region: None,
+            region_rename: None,
+            clip_rename: None,
+            region_drag_before: None,
+            effect_error: None,
+            spectrograms: HashMap::new(),
+            spectrograms_pending: HashSet::new(),
+            clip_editor_cursor: None,
+            clip_editor_zoom: 1.0,
+            clip_editor_drag: None,
+            clip_editor_error: None,
+            listening_reference: false,
+            reference_matching: HashSet::new(),
+            project_report: None,
+            building_report: false,
+            dsp_profiling: false,
+            dsp_profile: DspProfile::default(),
         }
     }
 
@@ -342,36 +1169,205 @@ impl DawUiState {
         }
         
         self.project_path = Some(path);
-        self.project = Some(project);
         self.reset_selection();
+        // The loop region lives in the transport, in samples
+        let transport = &project.transport;
+        self.is_looping = transport.loop_enabled;
+        if transport.loop_enabled {
+            self.selection.loop_start = Some(transport.samples_to_beats(transport.loop_start));
+            self.selection.loop_end = Some(transport.samples_to_beats(transport.loop_end));
+        }
+        self.project = Some(project);
+        self.clear_history();
+        self.dirty.mark_clean();
+        self.save_error = None;
+        if let Some(view) = self.project_path.as_deref().and_then(ViewState::load_for_project) {
+            self.apply_view_state(view);
+        }
+        self.load_drum_rack();
+        self.report_unavailable_effects();
+        self.selected_marker = None;
+        self.marker_rename = None;
+        self.selected_region = None;
+        self.region_rename = None;
+        self.clip_rename = None;
+        self.effect_error = None;
+        self.clip_editor_cursor = None;
+        self.clip_editor_drag = None;
+        self.clip_editor_error = None;
+        self.automation_recorder = AutomationRecorder::default();
+        self.automation_pass_before = None;
+        self.control_learn = None;
+        self.listening_reference = false;
+        self.reference_matching.clear();
+        self.project_report = None;
         
         Ok(())
     }
 
+    /// Panel visibility, zoom, scroll, track heights and selection as they are now
+    pub fn capture_view_state(&self) -> ViewState {
+        let rows = self.project.as_ref().map_or(0, |p| p.tracks.len());
+        ViewState {
+            left_dock_open: self.left_dock_open,
+            right_dock_open: self.right_dock_open,
+            show_browser: self.show_browser,
+            show_inspector: self.show_inspector,
+            show_mixer: self.show_mixer,
+            mixer_width: self.mixer_width,
+            mixer_height: self.mixer_height,
+            mixer_narrow: self.mixer_narrow,
+            inspector_tab: self.inspector_tab.name().to_string(),
+            zoom: self.viewport.zoom,
+            scroll_x: -self.timeline_scroll_handle.offset().x.as_f32(),
+            top_track: self.timeline_vertical_scroll_handle.logical_scroll_top_index().min(rows.saturating_sub(1)),
+            track_height: self.viewport.track_height,
+            track_heights: self.track_heights.clone(),
+            selected_tracks: self.selection.selected_track_ids.iter().copied().collect(),
+            selected_clips: self.selection.selected_clip_ids.iter().copied().collect(),
+            playhead: self.selection.playhead_position,
+        }
+    }
+
+    /// Restore a saved layout, ignoring tracks and clips the project doesn't have
+    pub fn apply_view_state(&mut self, mut view: ViewState) {
+        let (tracks, clips): (Vec<TrackId>, Vec<ClipId>) = self.project.as_ref().map_or_else(Default::default, |p| {
+            (p.tracks.iter().map(|t| t.id).collect(), p.tracks.iter().flat_map(|t| &t.clips).map(|c| c.id).collect())
+        });
+        view.retain_existing(&tracks, &clips);
+
+        self.left_dock_open = view.left_dock_open;
+        self.right_dock_open = view.right_dock_open;
+        self.dock_layout_changed = true;
+        self.show_browser = view.show_browser;
+        self.show_inspector = view.show_inspector;
+        self.show_mixer = view.show_mixer;
+        self.mixer_width = view.mixer_width;
+        self.mixer_height = view.mixer_height.clamp(MIN_MIXER_HEIGHT, MAX_MIXER_HEIGHT);
+        self.mixer_narrow = view.mixer_narrow;
+        self.inspector_tab = InspectorTab::ALL.into_iter()
+            .find(|tab| tab.name() == view.inspector_tab)
+            .unwrap_or(InspectorTab::Track);
+        // Layout files can be edited by hand, keep the zoom usable
+        self.viewport.zoom = view.zoom.clamp(10.0, 500.0);
+        self.viewport.track_height = view.track_height.clamp(super::timeline::MIN_TRACK_HEIGHT, super::timeline::MAX_TRACK_HEIGHT);
+        self.track_heights = view.track_heights;
+        self.timeline_scroll_handle.set_offset(point(px(-view.scroll_x.max(0.0)), px(0.0)));
+        self.timeline_vertical_scroll_handle.scroll_to_item(view.top_track, ScrollStrategy::Top);
+        self.selection.selected_track_ids = view.selected_tracks.into_iter().collect();
+        self.selection.selected_clip_ids = view.selected_clips.into_iter().collect();
+        self.selection.playhead_position = view.playhead.max(0.0);
+    }
+
+    /// Put panels, zoom and track heights back to the defaults, keeping the selection
+    pub fn reset_layout(&mut self) {
+        let selection = self.selection.clone();
+        self.apply_view_state(ViewState::default());
+        self.selection = selection;
+        tracing::error!("🪟 Layout reset");
+    }
+
+    /// Write the layout sidecar next to the project, if it has a path
+    pub fn save_view_state(&self) {
+        let Some(ref path) = self.project_path else {
+            return;
+        };
+        if let Err(e) = self.capture_view_state().save_for_project(path) {
+            tracing::error!("❌ Failed to save layout: {}", e);
+        }
+    }
+
     /// Save current project
     pub fn save_project(&self) -> anyhow::Result<()> {
         if let (Some(ref project), Some(ref path)) = (&self.project, &self.project_path) {
             project.save(path.clone())?;
+            self.dirty.mark_clean();
+            self.save_view_state();
+            self.save_drum_rack();
         }
         Ok(())
     }
 
+    /// Read the project's drum rack, or start an empty one
+    fn load_drum_rack(&mut self) {
+        let path = self.project_path.as_deref().map(DrumRack::sidecar_path);
+        self.drum_rack = match path {
+            Some(path) if path.exists() => DrumRack::load(&path).unwrap_or_else(|e| {
+                tracing::error!("❌ Failed to load drum rack: {}", e);
+                DrumRack::default()
+            }),
+            _ => DrumRack::default(),
+        };
+        self.selected_pad = 0;
+    }
+
+    /// Write the drum rack next to the project, if it has a path
+    pub fn save_drum_rack(&self) {
+        let Some(ref path) = self.project_path else {
+            return;
+        };
+        if let Err(e) = self.drum_rack.save(&DrumRack::sidecar_path(path)) {
+            tracing::error!("❌ Failed to save drum rack: {}", e);
+        }
+    }
+
+    /// Change a pad and save the rack
+    pub fn edit_pad(&mut self, index: usize, edit: impl FnOnce(&mut DrumPad)) {
+        if let Some(pad) = self.drum_rack.pads.get_mut(index) {
+            edit(pad);
+            self.save_drum_rack();
+        }
+    }
+
+    /// Queue a snapshot of the project for writing on the save thread.
+    /// Returns None if there is nothing to save.
+    pub fn begin_background_save(&mut self) -> Option<futures::channel::oneshot::Receiver<SaveResult>> {
+        let (project, path) = (self.project.as_ref()?, self.project_path.as_ref()?);
+        let revision = self.dirty.revision();
+        let receiver = self.save_queue.request(path.clone(), project.clone(), revision);
+        self.pending_saves += 1;
+        Some(receiver)
+    }
+
+    /// Record the outcome of a background save. The project only becomes
+    /// clean if the written snapshot includes the latest edit.
+    pub fn finish_background_save(&mut self, result: SaveResult) {
+        self.pending_saves = self.pending_saves.saturating_sub(1);
+        match result {
+            Ok(revision) => {
+                self.dirty.mark_saved(revision);
+                self.save_error = None;
+            }
+            Err(e) => {
+                tracing::error!("❌ Save failed: {}", e);
+                self.save_error = Some(e);
+            }
+        }
+    }
+
+    pub fn is_saving(&self) -> bool {
+        self.pending_saves > 0
+    }
+
     /// Create new empty project
     pub fn new_project(&mut self, name: String, project_dir: PathBuf) {
         self.project = Some(DawProject::new(name));
         self.project_dir = Some(project_dir.clone());
         
         // Create project directory structure
-        let audio_dir = project_dir.join("audio");
+        let audio_dir = project_dir.join(AUDIO_FOLDER);
         let _ = std::fs::create_dir_all(&audio_dir);
         
         self.scan_audio_files(&project_dir);
         self.reset_selection();
+        self.clear_history();
+        self.dirty.mark_clean();
+        self.project_report = None;
     }
 
     /// Scan audio files in project directory
     pub fn scan_audio_files(&mut self, project_dir: &std::path::Path) {
-        let audio_dir = project_dir.join("audio");
+        let audio_dir = project_dir.join(AUDIO_FOLDER);
         if !audio_dir.exists() {
             let _ = std::fs::create_dir_all(&audio_dir);
         }
@@ -441,6 +1437,43 @@ impl DawUiState {
         }
         self.selection.selected_track_ids.insert(track_id);
         self.selection.selected_clip_ids.clear();
+        self.keyboard_focus = Some(KeyboardFocus::Track(track_id));
+        self.mixer_reveal = Some(track_id);
+    }
+
+    /// With arm following the selection, arm the selected audio tracks and
+    /// disarm the rest. Armed tracks with monitoring off get Auto, so the
+    /// input is heard straight away while stopped. A selection without audio
+    /// tracks leaves arming alone, as does selecting while a take records,
+    /// which would start or cut short the armed tracks' takes. Inactive
+    /// tracks can't record and are skipped. Returns the tracks that changed.
+    pub fn arm_selected_tracks(&mut self) -> Vec<TrackId> {
+        let Some(ref mut project) = self.project else {
+            return Vec::new();
+        };
+        let selected = &self.selection.selected_track_ids;
+        let recordable = |t: &Track| t.track_type == TrackType::Audio && t.active;
+        if !self.preferences.arm_follows_selection
+            || self.is_recording
+            || !project.tracks.iter().any(|t| recordable(t) && selected.contains(&t.id))
+        {
+            return Vec::new();
+        }
+
+        let mut changed = Vec::new();
+        for track in project.tracks.iter_mut().filter(|t| recordable(t)) {
+            let armed = selected.contains(&track.id);
+            let monitor = if armed && track.monitor == MonitorMode::Off { MonitorMode::Auto } else { track.monitor };
+            if track.record_armed != armed || track.monitor != monitor {
+                track.record_armed = armed;
+                track.monitor = monitor;
+                changed.push(track.id);
+            }
+        }
+        if !changed.is_empty() {
+            self.dirty.mark_dirty();
+        }
+        changed
     }
 
     /// Select clip
@@ -451,96 +1484,3007 @@ impl DawUiState {
         self.selection.selected_clip_ids.insert(clip_id);
     }
 
-    /// Snap beat position to grid
-    pub fn snap_beat(&self, beat: f64) -> f64 {
-        match self.snap_mode {
-            SnapMode::Off => beat,
-            SnapMode::Grid => {
-                let snap_beats = self.snap_value.to_beats();
-                (beat / snap_beats).round() * snap_beats
-            }
-            SnapMode::Events => beat, // Would snap to nearby clip edges
+    /// Everything Tab moves through: each track followed by its clips in time order
+    fn focus_order(&self) -> Vec<KeyboardFocus> {
+        let Some(ref project) = self.project else {
+            return Vec::new();
+        };
+        let mut order = Vec::new();
+        for track in &project.tracks {
+            order.push(KeyboardFocus::Track(track.id));
+            let mut clips: Vec<&AudioClip> = track.clips.iter().collect();
+            clips.sort_by_key(|c| c.start_time);
+            order.extend(clips.into_iter().map(|c| KeyboardFocus::Clip(track.id, c.id)));
         }
+        order
     }
 
-    /// Convert beats to pixels
-    pub fn beats_to_pixels(&self, beats: f64) -> f32 {
-        (beats * self.viewport.zoom) as f32
+    /// Tab / Shift+Tab: focus the next or previous track or clip, wrapping
+    /// around. Focus on something that was deleted starts over from the top.
+    pub fn focus_next(&mut self, backwards: bool) {
+        let order = self.focus_order();
+        if order.is_empty() {
+            self.keyboard_focus = None;
+            return;
+        }
+        let current = self.keyboard_focus.and_then(|focus| order.iter().position(|f| *f == focus));
+        let index = match (current, backwards) {
+            (Some(i), false) => (i + 1) % order.len(),
+            (Some(i), true) => (i + order.len() - 1) % order.len(),
+            (None, false) => 0,
+            (None, true) => order.len() - 1,
+        };
+        self.keyboard_focus = Some(order[index]);
     }
 
-    /// Convert pixels to beats
-    pub fn pixels_to_beats(&self, pixels: f32) -> f64 {
-        pixels as f64 / self.viewport.zoom
-    }
-    
-    /// Get current tempo from project
-    pub fn get_tempo(&self) -> f32 {
-        self.project.as_ref().map(|p| p.transport.tempo).unwrap_or(120.0)
-    }
+    /// Up / Down: focus the track above or below. A focused clip moves to the
+    /// clip on that track closest in time, or to the track if it has none.
+    pub fn focus_adjacent_track(&mut self, delta: i32) {
+        let Some(ref project) = self.project else {
+            return;
+        };
+        let Some(focus) = self.keyboard_focus else {
+            self.focus_next(delta < 0);
+            return;
+        };
+        let Some(index) = project.tracks.iter().position(|t| t.id == focus.track_id()) else {
+            self.keyboard_focus = None;
+            return;
+        };
+        let target = (index as i64 + delta as i64).clamp(0, project.tracks.len() as i64 - 1) as usize;
+        let track = &project.tracks[target];
 
-    /// Get track by ID
-    pub fn get_track(&self, track_id: TrackId) -> Option<&Track> {
-        self.project.as_ref()?.tracks.iter().find(|t| t.id == track_id)
+        self.keyboard_focus = Some(match focus {
+            KeyboardFocus::Clip(from_track, clip_id) => {
+                let time = self.get_clip(from_track, clip_id).map(|c| c.start_time).unwrap_or(0);
+                track
+                    .clips
+                    .iter()
+                    .min_by_key(|c| c.start_time.abs_diff(time))
+                    .map(|c| KeyboardFocus::Clip(track.id, c.id))
+                    .unwrap_or(KeyboardFocus::Track(track.id))
+            }
+            KeyboardFocus::Track(_) => KeyboardFocus::Track(track.id),
+        });
     }
 
-    /// Get track mutably
-    pub fn get_track_mut(&mut self, track_id: TrackId) -> Option<&mut Track> {
-        self.project.as_mut()?.tracks.iter_mut().find(|t| t.id == track_id)
-    }
+    /// Left / Right: step through the focused track's clips in time order.
+    /// Left from the first clip goes back to the track itself.
+    pub fn focus_adjacent_clip(&mut self, delta: i32) {
+        let Some(focus) = self.keyboard_focus else {
+            return;
+        };
+        let Some(track) = self.get_track(focus.track_id()) else {
+            self.keyboard_focus = None;
+            return;
+        };
+        let mut clips: Vec<&AudioClip> = track.clips.iter().collect();
+        clips.sort_by_key(|c| c.start_time);
+        if clips.is_empty() {
+            return;
+        }
 
-    /// Add new audio track
-    pub fn add_audio_track(&mut self, name: String) -> TrackId {
-        if let Some(ref mut project) = self.project {
-            let track = Track::new(name, TrackType::Audio);
-            let track_id = track.id;
-            project.tracks.push(track);
-            track_id
+        // The track sits before its first clip
+        let position = match focus {
+            KeyboardFocus::Track(_) => -1,
+            KeyboardFocus::Clip(_, clip_id) => clips.iter().position(|c| c.id == clip_id).map_or(-1, |i| i as i64),
+        };
+        let target = (position + delta as i64).clamp(-1, clips.len() as i64 - 1);
+        self.keyboard_focus = Some(if target < 0 {
+            KeyboardFocus::Track(track.id)
         } else {
-            uuid::Uuid::new_v4()
-        }
+            KeyboardFocus::Clip(track.id, clips[target as usize].id)
+        });
     }
 
-    /// Delete track
-    pub fn delete_track(&mut self, track_id: TrackId) {
-        if let Some(ref mut project) = self.project {
-            project.tracks.retain(|t| t.id != track_id);
-            self.selection.selected_track_ids.remove(&track_id);
-        }
+    /// Enter / Space: select the focused track or clip, adding to the
+    /// selection with `multi`. Returns true if it was a clip that was already
+    /// the only selection, which opens its properties.
+    pub fn select_focused(&mut self, multi: bool) -> bool {
+        match self.keyboard_focus {
+            Some(KeyboardFocus::Track(track_id)) => {
+                self.select_track(track_id, multi);
+                false
+            }
+            Some(KeyboardFocus::Clip(track_id, clip_id)) => {
+                if self.get_clip(track_id, clip_id).is_none() {
+                    self.keyboard_focus = None;
+                    return false;
+                }
+                let selected = &self.selection.selected_clip_ids;
+                let only_selected = selected.len() == 1 && selected.contains(&clip_id);
+                if multi && selected.contains(&clip_id) {
+                    self.selection.selected_clip_ids.remove(&clip_id);
+                } else {
+                    self.select_clip(clip_id, multi);
+                }
+                !multi && only_selected
+            }
+            None => false,
+        }
+    }
+
+    /// Clips on a track whose hit region contains `x` (timeline pixels),
+    /// topmost first. Hit regions are at least `MIN_CLIP_HIT_WIDTH` wide.
+    pub fn clips_at(&self, track_id: TrackId, x: f32) -> Vec<ClipId> {
+        let Some(track) = self.get_track(track_id) else {
+            return Vec::new();
+        };
+        let (tempo, rate) = (self.get_tempo(), self.sample_rate());
+
+        // Later clips are drawn on top
+        track.clips
+            .iter()
+            .rev()
+            .filter(|clip| {
+                let start = self.beats_to_pixels(clip.start_beat(tempo, rate));
+                let width = self.beats_to_pixels(clip.duration_beats(tempo, rate));
+                let hit_width = width.max(MIN_CLIP_HIT_WIDTH);
+                let hit_start = start - (hit_width - width) / 2.0;
+                x >= hit_start && x <= hit_start + hit_width
+            })
+            .map(|clip| clip.id)
+            .collect()
+    }
+
+    /// Select the topmost clip at `x`. Clicking again at the same spot cycles
+    /// through clips stacked underneath.
+    pub fn select_clip_at(&mut self, track_id: TrackId, x: f32, multi: bool) -> Option<ClipId> {
+        let candidates = self.clips_at(track_id, x);
+        if candidates.is_empty() {
+            self.clip_click_cycle = None;
+            return None;
+        }
+
+        let index = match self.clip_click_cycle {
+            Some((last_track, last_x, last_index))
+                if last_track == track_id && (last_x - x).abs() <= 2.0 =>
+            {
+                (last_index + 1) % candidates.len()
+            }
+            _ => 0,
+        };
+
+        self.clip_click_cycle = Some((track_id, x, index));
+        let clip_id = candidates[index];
+        self.keyboard_focus = Some(KeyboardFocus::Clip(track_id, clip_id));
+        // Keep an existing multi-selection intact so it can be dragged as a group
+        if multi || !self.selection.selected_clip_ids.contains(&clip_id) {
+            self.select_clip(clip_id, multi);
+        }
+        Some(clip_id)
+    }
+
+    /// Start a marquee at `x` (timeline pixels) on a track, selecting clips
+    /// or the breakpoints of one automation lane
+    pub fn begin_marquee(&mut self, target: MarqueeTarget, track_id: TrackId, x: f32, mode: MarqueeMode) {
+        self.drag_state = DragState::Marquee {
+            target,
+            mode,
+            start_track: track_id,
+            start_x: x,
+            current_track: track_id,
+            current_x: x,
+            base_clips: self.selection.selected_clip_ids.clone(),
+            base_points: self.selection.selected_automation_points.clone(),
+        };
+        self.update_marquee(track_id, x);
+    }
+
+    /// Extend the marquee to `x` on `track_id` and update the selection live
+    pub fn update_marquee(&mut self, track_id: TrackId, x: f32) {
+        let DragState::Marquee { current_track, current_x, .. } = &mut self.drag_state else {
+            return;
+        };
+        *current_track = track_id;
+        *current_x = x;
+
+        let Some((tracks, x_range)) = self.marquee_bounds() else {
+            return;
+        };
+        let start_beat = self.pixels_to_beats(x_range.0);
+        let end_beat = self.pixels_to_beats(x_range.1);
+
+        let (Some(project), DragState::Marquee { target, mode, base_clips, base_points, .. }) = (&self.project, &self.drag_state) else {
+            return;
+        };
+        let start_time = project.transport.beats_to_samples(start_beat.max(0.0));
+        let end_time = project.transport.beats_to_samples(end_beat.max(0.0));
+
+        match *target {
+            MarqueeTarget::Clips => {
+                let hits: HashSet<ClipId> = project.tracks[tracks]
+                    .iter()
+                    .flat_map(|t| t.clips.iter())
+                    .filter(|c| c.start_time <= end_time && c.end_time() >= start_time)
+                    .map(|c| c.id)
+                    .collect();
+
+                self.selection.selected_clip_ids = match mode {
+                    MarqueeMode::Replace => hits,
+                    MarqueeMode::Add => base_clips.union(&hits).copied().collect(),
+                    MarqueeMode::Toggle => base_clips.symmetric_difference(&hits).copied().collect(),
+                };
+            }
+            MarqueeTarget::AutomationPoints(parameter) => {
+                let hits: Vec<(TrackId, AutomationParameter, usize)> = project.tracks[tracks]
+                    .iter()
+                    .filter_map(|t| t.get_automation_lane(parameter).map(|lane| (t.id, lane)))
+                    .flat_map(|(track_id, lane)| {
+                        lane.points
+                            .iter()
+                            .enumerate()
+                            .filter(|(_, p)| p.time >= start_time && p.time <= end_time)
+                            .map(move |(index, _)| (track_id, parameter, index))
+                    })
+                    .collect();
+
+                let (kept, new): (Vec<_>, Vec<_>) = match mode {
+                    MarqueeMode::Replace => (Vec::new(), hits),
+                    MarqueeMode::Add => (base_points.clone(), hits.into_iter().filter(|h| !base_points.contains(h)).collect()),
+                    MarqueeMode::Toggle => (
+                        base_points.iter().filter(|p| !hits.contains(p)).copied().collect(),
+                        hits.into_iter().filter(|h| !base_points.contains(h)).collect(),
+                    ),
+                };
+                self.selection.selected_automation_points = kept.into_iter().chain(new).collect();
+            }
+        }
+    }
+
+    /// Current marquee as (track index range, pixel range)
+    fn marquee_bounds(&self) -> Option<(std::ops::Range<usize>, (f32, f32))> {
+        let DragState::Marquee { start_track, start_x, current_track, current_x, .. } = &self.drag_state else {
+            return None;
+        };
+        let (first, last) = self.marquee_track_span(*start_track, *current_track)?;
+        Some((first..last + 1, (start_x.min(*current_x), start_x.max(*current_x))))
+    }
+
+    fn marquee_track_span(&self, a: TrackId, b: TrackId) -> Option<(usize, usize)> {
+        let tracks = &self.project.as_ref()?.tracks;
+        let a = tracks.iter().position(|t| t.id == a)?;
+        let b = tracks.iter().position(|t| t.id == b)?;
+        Some((a.min(b), a.max(b)))
+    }
+
+    /// Horizontal pixel span of the marquee on a track, if the track is inside it
+    pub fn marquee_span_for_track(&self, track_id: TrackId) -> Option<(f32, f32)> {
+        let DragState::Marquee { start_track, start_x, current_track, current_x, .. } = &self.drag_state else {
+            return None;
+        };
+        let (first, last) = self.marquee_track_span(*start_track, *current_track)?;
+        let index = self.project.as_ref()?.tracks.iter().position(|t| t.id == track_id)?;
+        (first..=last)
+            .contains(&index)
+            .then(|| (start_x.min(*current_x), start_x.max(*current_x)))
+    }
+
+    /// Remove all selected clips as one undoable edit. Returns the affected tracks.
+    pub fn delete_selected_clips(&mut self) -> Vec<TrackId> {
+        let Some(ref mut project) = self.project else {
+            return Vec::new();
+        };
+
+        let mut removed = Vec::new();
+        for track in &mut project.tracks {
+            let mut index = 0;
+            while index < track.clips.len() {
+                let clip = &track.clips[index];
+                if self.selection.selected_clip_ids.contains(&clip.id) && !track.clip_locked(clip) {
+                    removed.push((track.id, index, track.clips.remove(index)));
+                } else {
+                    index += 1;
+                }
+            }
+        }
+
+        if removed.is_empty() {
+            return Vec::new();
+        }
+
+        let mut touched: Vec<TrackId> = removed.iter().map(|(track_id, _, _)| *track_id).collect();
+        touched.dedup();
+        // Locked clips stay, and stay selected
+        for (_, _, clip) in &removed {
+            self.selection.selected_clip_ids.remove(&clip.id);
+        }
+        self.record_edit(EditAction::RemoveClips { clips: removed });
+        touched
+    }
+
+    /// Move all selected clips by `delta_beats` as one undoable edit.
+    /// Returns the affected tracks.
+    pub fn nudge_selected_clips(&mut self, delta_beats: f64) -> Vec<TrackId> {
+        let Some(ref mut project) = self.project else {
+            return Vec::new();
+        };
+        let delta = project.transport.beats_to_samples(delta_beats.abs()) as i64 * delta_beats.signum() as i64;
+
+        let mut moves = Vec::new();
+        for track in &mut project.tracks {
+            let track_locked = track.locked;
+            for clip in &mut track.clips {
+                if self.selection.selected_clip_ids.contains(&clip.id) && !(track_locked || clip.locked) {
+                    let old_start = clip.start_time;
+                    clip.start_time = (old_start as i64 + delta).max(0) as SampleTime;
+                    if clip.start_time != old_start {
+                        moves.push((track.id, clip.id, old_start, clip.start_time));
+                    }
+                }
+            }
+        }
+
+        if moves.is_empty() {
+            return Vec::new();
+        }
+
+        if let Some(touched) = self.resolve_moved_overlaps(&moves) {
+            return touched;
+        }
+        let fades = self.crossfade_moved_clips(&moves);
+        let mut touched: Vec<TrackId> = moves.iter().map(|(track_id, ..)| *track_id).collect();
+        touched.dedup();
+        self.record_edit(EditAction::MoveClips { moves, fades });
+        touched
+    }
+
+    /// Move clips so their onsets land on the reference clip's, as one
+    /// undoable edit. `onsets` holds each clip's first onset on the timeline,
+    /// the reference's included. Returns the affected tracks.
+    pub fn align_clip_onsets(&mut self, reference: ClipId, onsets: &[(ClipId, SampleTime)]) -> Vec<TrackId> {
+        let Some(target) = onsets.iter().find(|(id, _)| *id == reference).map(|(_, onset)| *onset) else {
+            return Vec::new();
+        };
+        let Some(ref mut project) = self.project else {
+            return Vec::new();
+        };
+
+        let mut moves = Vec::new();
+        for track in &mut project.tracks {
+            let track_locked = track.locked;
+            for clip in &mut track.clips {
+                let Some((_, onset)) = onsets.iter().find(|(id, _)| *id == clip.id && *id != reference) else {
+                    continue;
+                };
+                if track_locked || clip.locked {
+                    continue;
+                }
+                let old_start = clip.start_time;
+                clip.start_time = (old_start as i64 + target as i64 - *onset as i64).max(0) as SampleTime;
+                if clip.start_time != old_start {
+                    moves.push((track.id, clip.id, old_start, clip.start_time));
+                }
+            }
+        }
+
+        if moves.is_empty() {
+            return Vec::new();
+        }
+
+        if let Some(touched) = self.resolve_moved_overlaps(&moves) {
+            return touched;
+        }
+        let fades = self.crossfade_moved_clips(&moves);
+        let mut touched: Vec<TrackId> = moves.iter().map(|(track_id, ..)| *track_id).collect();
+        touched.dedup();
+        self.record_edit(EditAction::MoveClips { moves, fades });
+        touched
+    }
+
+    /// Cut holes under clips moved on Replace tracks, and bring clips moved
+    /// on Comp tracks to the top. Those change more than a move can undo, so
+    /// the whole edit is recorded as an arrangement. Returns the tracks
+    /// touched, or None if no moved clip is on such a track.
+    fn resolve_moved_overlaps(&mut self, moves: &[(TrackId, ClipId, SampleTime, SampleTime)]) -> Option<Vec<TrackId>> {
+        let prefs = self.preferences;
+        let project = self.project.as_mut()?;
+        let sample_rate = project.sample_rate;
+        let reshaped = moves.iter().any(|(track_id, ..)| {
+            project.get_track(*track_id).is_some_and(|t| t.overlap_mode != OverlapMode::Layer)
+        });
+        if !reshaped {
+            return None;
+        }
+
+        // The clips have already moved, so put them back to see how it was
+        let set_starts = |project: &mut DawProject, old: bool| {
+            for (track_id, clip_id, old_start, new_start) in moves {
+                if let Some(clip) = project.get_track_mut(*track_id).and_then(|t| t.clips.iter_mut().find(|c| c.id == *clip_id)) {
+                    clip.start_time = if old { *old_start } else { *new_start };
+                }
+            }
+        };
+        set_starts(project, true);
+        let before = ArrangementSnapshot::capture(project);
+        set_starts(project, false);
+
+        let mut touched: Vec<TrackId> = moves.iter().map(|(track_id, ..)| *track_id).collect();
+        touched.dedup();
+        for track_id in &touched {
+            let Some(track) = project.get_track_mut(*track_id) else {
+                continue;
+            };
+            let moved: Vec<ClipId> = moves.iter().filter(|(t, ..)| t == track_id).map(|(_, clip_id, ..)| *clip_id).collect();
+            match track.overlap_mode {
+                OverlapMode::Layer => {}
+                OverlapMode::Replace => {
+                    preferences::replace_overlaps(&mut track.clips, &moved, Self::replace_overlap(&prefs, sample_rate));
+                }
+                OverlapMode::Comp => {
+                    // Stable, so the moved clips keep their order among themselves
+                    track.clips.sort_by_key(|c| moved.contains(&c.id));
+                }
+            }
+        }
+        self.crossfade_moved_clips(moves);
+
+        let after = ArrangementSnapshot::capture(self.project.as_ref()?);
+        self.record_edit(EditAction::Arrangement { label: "Move Clips", before, after });
+        Some(touched)
+    }
+
+    /// Crossfade the overlaps created by moving clips, if auto-crossfade is on.
+    /// Returns the fade changes for the move's undo entry.
+    fn crossfade_moved_clips(
+        &mut self,
+        moves: &[(TrackId, ClipId, SampleTime, SampleTime)],
+    ) -> Vec<(TrackId, ClipId, Fades, Fades)> {
+        let prefs = self.preferences;
+        let Some(ref mut project) = self.project else {
+            return Vec::new();
+        };
+        if !prefs.auto_crossfade {
+            return Vec::new();
+        }
+        let max_length = prefs.crossfade_samples(project.sample_rate);
+
+        let mut fades = Vec::new();
+        for track in &mut project.tracks {
+            let moved: Vec<ClipId> = moves
+                .iter()
+                .filter(|(track_id, ..)| *track_id == track.id)
+                .map(|(_, clip_id, ..)| *clip_id)
+                .collect();
+            if moved.is_empty() {
+                continue;
+            }
+            fades.extend(
+                preferences::crossfade_overlaps(&mut track.clips, &moved, max_length, prefs.crossfade_shape)
+                    .into_iter()
+                    .map(|(clip_id, before, after)| (track.id, clip_id, before, after)),
+            );
+        }
+        fades
+    }
+
+    /// Toggle mute on the selected clips. If any of them is audible they are all
+    /// muted, otherwise they are all unmuted. Returns the affected tracks.
+    pub fn toggle_selected_clips_mute(&mut self) -> Vec<TrackId> {
+        let Some(ref mut project) = self.project else {
+            return Vec::new();
+        };
+        let selected = &self.selection.selected_clip_ids;
+        let mute = project
+            .tracks
+            .iter()
+            .flat_map(|t| t.clips.iter())
+            .any(|c| selected.contains(&c.id) && !c.muted);
+
+        let mut changes = Vec::new();
+        for track in &mut project.tracks {
+            for clip in &mut track.clips {
+                if selected.contains(&clip.id) && clip.muted != mute {
+                    clip.muted = mute;
+                    changes.push((track.id, clip.id, mute));
+                }
+            }
+        }
+
+        if changes.is_empty() {
+            return Vec::new();
+        }
+
+        let mut touched: Vec<TrackId> = changes.iter().map(|(track_id, ..)| *track_id).collect();
+        touched.dedup();
+        self.record_edit(EditAction::MuteClips { changes });
+        touched
+    }
+
+    /// Start typing a new name into a clip, beginning from the one shown
+    pub fn begin_clip_rename(&mut self, track_id: TrackId, clip_id: ClipId) {
+        if let Some(clip) = self.get_clip(track_id, clip_id) {
+            let text = clip.display_name();
+            self.clip_rename = Some(ClipRename { track: track_id, clip: clip_id, text });
+        }
+    }
+
+    /// Apply the clip name being typed. Blank names are ignored.
+    pub fn commit_clip_rename(&mut self) -> bool {
+        let Some(rename) = self.clip_rename.take() else {
+            return false;
+        };
+        let name = rename.text.trim().to_string();
+        let Some(clip) = self
+            .project
+            .as_mut()
+            .and_then(|p| p.get_track_mut(rename.track))
+            .and_then(|t| t.clips.iter_mut().find(|c| c.id == rename.clip))
+        else {
+            return false;
+        };
+        if name.is_empty() || clip.name == name {
+            return false;
+        }
+        let before = std::mem::replace(&mut clip.name, name.clone());
+        self.record_edit(EditAction::RenameClip { track_id: rename.track, clip_id: rename.clip, before, after: name });
+        true
+    }
+
+    /// Set how the selected clips' channels are played. Returns the affected tracks.
+    pub fn set_selected_clips_channel_mode(&mut self, mode: ChannelMode) -> Vec<TrackId> {
+        let Some(ref mut project) = self.project else {
+            return Vec::new();
+        };
+        let selected = &self.selection.selected_clip_ids;
+
+        let mut changes = Vec::new();
+        for track in &mut project.tracks {
+            for clip in &mut track.clips {
+                if selected.contains(&clip.id) && clip.channel_mode != mode {
+                    let before = clip.clone();
+                    clip.channel_mode = mode;
+                    changes.push((track.id, before, clip.clone()));
+                }
+            }
+        }
+
+        if changes.is_empty() {
+            return Vec::new();
+        }
+
+        let mut touched: Vec<TrackId> = changes.iter().map(|(track_id, ..)| *track_id).collect();
+        touched.dedup();
+        self.record_edit(EditAction::ModifyClips { changes });
+        touched
+    }
+
+    /// Give the `ends` fades of every selected clip the curve of `preset`,
+    /// keeping their lengths. Returns the affected tracks.
+    pub fn apply_fade_preset_to_selected(&mut self, preset: &FadePreset, ends: FadeEnds) -> Vec<TrackId> {
+        let Some(ref mut project) = self.project else {
+            return Vec::new();
+        };
+        let selected = &self.selection.selected_clip_ids;
+        let curves = |clip: &AudioClip| (clip.fade_in_shape, clip.fade_in_curve, clip.fade_out_shape, clip.fade_out_curve);
+
+        let mut changes = Vec::new();
+        for track in &mut project.tracks {
+            let track_locked = track.locked;
+            for clip in &mut track.clips {
+                if selected.contains(&clip.id) && !(track_locked || clip.locked) {
+                    let before = clip.clone();
+                    preset.apply(clip, ends);
+                    if curves(&before) != curves(clip) {
+                        changes.push((track.id, before, clip.clone()));
+                    }
+                }
+            }
+        }
+
+        if changes.is_empty() {
+            return Vec::new();
+        }
+
+        let mut touched: Vec<TrackId> = changes.iter().map(|(track_id, ..)| *track_id).collect();
+        touched.dedup();
+        self.record_edit(EditAction::ModifyClips { changes });
+        touched
+    }
+
+    /// Save the fade-in curve of a clip as a user preset, so it can be given
+    /// to other clips. Returns the preset's name.
+    pub fn save_fade_preset(&mut self, track_id: TrackId, clip_id: ClipId) -> Option<String> {
+        let clip = self.get_clip(track_id, clip_id)?;
+        let (shape, curve) = (clip.fade_in_shape, clip.fade_in_curve);
+        let all = self.fade_presets.all();
+        if let Some(existing) = all.iter().find(|p| p.matches(shape, curve)) {
+            return Some(existing.name.clone());
+        }
+
+        let name = (1..).map(|n| format!("Custom {}", n)).find(|name| all.iter().all(|p| &p.name != name))?;
+        self.fade_presets.add(FadePreset::new(name.clone(), shape, curve));
+        if let Err(e) = self.fade_presets.save(&self.fade_presets_path) {
+            tracing::error!("❌ Failed to save fade presets: {}", e);
+        }
+        Some(name)
+    }
+
+    /// Start dragging a clip, with the rest of the selection moving along
+    pub fn begin_clip_drag(&mut self, track_id: TrackId, clip_id: ClipId, start_beat: f64, mouse_offset: (f32, f32)) {
+        let Some(ref project) = self.project else {
+            return;
+        };
+        let origins = project.tracks
+            .iter()
+            .flat_map(|t| t.clips.iter().filter(|c| !t.clip_locked(c)).map(move |c| (t.id, c)))
+            .filter(|(_, c)| c.id == clip_id || self.selection.selected_clip_ids.contains(&c.id))
+            .map(|(track_id, c)| (track_id, c.id, c.start_time))
+            .collect();
+        self.drag_state = DragState::DraggingClip { clip_id, track_id, start_beat, mouse_offset, origins };
+    }
+
+    /// Shift every selected clip except `except` to `delta` samples from
+    /// where it started the drag (used while dragging a clip that belongs to
+    /// a multi-selection)
+    pub fn shift_selected_clips(&mut self, except: ClipId, delta: i64) {
+        let DragState::DraggingClip { ref origins, .. } = self.drag_state else {
+            return;
+        };
+        let Some(ref mut project) = self.project else {
+            return;
+        };
+        for (track_id, clip_id, origin) in origins {
+            if *clip_id == except {
+                continue;
+            }
+            if let Some(clip) = project.get_track_mut(*track_id).and_then(|t| t.clips.iter_mut().find(|c| c.id == *clip_id)) {
+                clip.start_time = (*origin as i64 + delta).max(0) as SampleTime;
+            }
+        }
+    }
+
+    /// Move the dragged clip to `beat` and the rest of the selection with it
+    pub fn drag_clip_to(&mut self, track_id: TrackId, clip_id: ClipId, beat: f64) {
+        let (tempo, rate) = (self.get_tempo(), self.sample_rate());
+        let DragState::DraggingClip { ref origins, .. } = self.drag_state else {
+            return;
+        };
+        let Some(origin) = origins.iter().find(|(_, id, _)| *id == clip_id).map(|(.., start)| *start) else {
+            return;
+        };
+        let Some(clip) = self.get_clip_mut(track_id, clip_id) else {
+            return;
+        };
+        clip.set_start_beat(beat, tempo, rate);
+        let delta = clip.start_time as i64 - origin as i64;
+        self.shift_selected_clips(clip_id, delta);
+    }
+
+    /// Record the clip drag in progress for undo, from where each clip
+    /// started it. Returns the affected tracks.
+    pub fn record_selection_move(&mut self) -> Vec<TrackId> {
+        let DragState::DraggingClip { ref origins, .. } = self.drag_state else {
+            return Vec::new();
+        };
+        let Some(ref project) = self.project else {
+            return Vec::new();
+        };
+
+        let moves: Vec<_> = origins
+            .iter()
+            .filter_map(|(track_id, clip_id, origin)| {
+                let clip = project.get_track(*track_id)?.clips.iter().find(|c| c.id == *clip_id)?;
+                (clip.start_time != *origin).then_some((*track_id, *clip_id, *origin, clip.start_time))
+            })
+            .collect();
+
+        if let Some(touched) = self.resolve_moved_overlaps(&moves) {
+            return touched;
+        }
+        let fades = self.crossfade_moved_clips(&moves);
+        let mut touched: Vec<TrackId> = moves.iter().map(|(track_id, ..)| *track_id).collect();
+        touched.extend(fades.iter().map(|(track_id, ..)| *track_id));
+        touched.dedup();
+        if !moves.is_empty() {
+            self.record_edit(EditAction::MoveClips { moves, fades });
+        }
+        touched
+    }
+
+    /// Snap beat position to grid
+    pub fn snap_beat(&self, beat: f64) -> f64 {
+        match self.snap_mode {
+            SnapMode::Off => beat,
+            SnapMode::Grid => {
+                let transport = self.project.as_ref().map(|p| p.transport.clone()).unwrap_or_default();
+                transport.snap_to_division(beat, self.snap_value.to_beats(), self.snap_value.feel())
+            }
+            SnapMode::Events => self.nearest_snap_point(beat, &self.event_snap_points()).unwrap_or(beat),
+        }
+    }
+
+    /// Grid recorded takes are quantized to, in beats: the snap grid, or
+    /// bars while snapping to anything but the grid. None with record
+    /// quantize off.
+    pub fn record_quantize_division(&self) -> Option<BeatTime> {
+        let project = self.project.as_ref().filter(|_| self.record_quantize)?;
+        Some(match self.snap_mode {
+            SnapMode::Grid if self.snap_value != SnapValue::Bar => self.snap_value.to_beats(),
+            _ => project.transport.time_signature_numerator.max(1) as f64,
+        })
+    }
+
+    /// Start a take at the playhead. Returns where capture begins, which the
+    /// transport has to start from, or None without a project. The count-in
+    /// only applies when starting from stopped.
+    pub fn begin_take(&mut self) -> Option<TakeStart> {
+        let transport = &self.project.as_ref()?.transport;
+        let position = transport.beats_to_samples(self.selection.playhead_position);
+        let count_in = self.count_in_enabled && !self.is_playing;
+        let take = TakeStart { position, captured_from: record_quantize::capture_start(transport, position, count_in) };
+        self.recording_take = Some(take);
+        self.is_recording = true;
+        Some(take)
+    }
+
+    /// Where each armed track's take goes, from its record input
+    pub fn take_targets(&self) -> Vec<TakeTarget> {
+        let (Some(project), Some(dir)) = (self.project.as_ref(), self.project_dir.as_ref()) else {
+            return Vec::new();
+        };
+        let now = chrono::Local::now();
+        project
+            .tracks
+            .iter()
+            .filter(|t| t.active && t.record_armed && t.track_type == TrackType::Audio)
+            .map(|t| TakeTarget { track_id: t.id, input: t.record_input, path: take_path(dir, &t.name, now) })
+            .collect()
+    }
+
+    /// End the take begun by [`Self::begin_take`], putting each track's
+    /// file on it, quantized with record quantize on, as one undoable edit.
+    /// `captured_from` and `end` are the timeline positions the audio really
+    /// covers. Returns the tracks that got a clip.
+    pub fn finish_take(&mut self, files: Vec<(TrackId, PathBuf)>, captured_from: SampleTime, end: SampleTime) -> Vec<TrackId> {
+        self.is_recording = false;
+        let Some(take) = self.recording_take.take() else {
+            return Vec::new();
+        };
+        let division = self.record_quantize_division();
+        let Some(project) = self.project.as_ref() else {
+            return Vec::new();
+        };
+        let transport = project.transport.clone();
+        let before = ArrangementSnapshot::capture(project);
+        let mut placed = Vec::new();
+        for (track_id, path) in files {
+            let clip = record_quantize::finished_take_clip(&transport, path, take.position, captured_from, end, division);
+            if self.place_new_clip(track_id, clip).is_some() {
+                placed.push(track_id);
+            }
+        }
+        if let (false, Some(project)) = (placed.is_empty(), self.project.as_ref()) {
+            let after = ArrangementSnapshot::capture(project);
+            self.record_edit(EditAction::Arrangement { label: "Record Take", before, after });
+        }
+        placed
+    }
+
+    /// Drop the take begun by [`Self::begin_take`] without placing anything
+    pub fn cancel_take(&mut self) {
+        self.is_recording = false;
+        self.recording_take = None;
+    }
+
+    /// Clip starts and ends, markers and the loop points, in beats
+    pub fn event_snap_points(&self) -> Vec<f64> {
+        let Some(ref project) = self.project else {
+            return Vec::new();
+        };
+        let transport = &project.transport;
+        let mut points: Vec<f64> = project.tracks
+            .iter()
+            .flat_map(|t| &t.clips)
+            .flat_map(|c| [c.start_time, c.end_time()])
+            .chain(project.markers.iter().map(|m| m.position))
+            .map(|time| transport.samples_to_beats(time))
+            .collect();
+        points.extend(self.selection.loop_start);
+        points.extend(self.selection.loop_end);
+        points
+    }
+
+    /// Everything a position near `beat` can snap to: the nearest grid line
+    /// while snapping is on, and with `events` the clip edges, markers and
+    /// loop points
+    pub fn snap_candidates(&self, beat: f64, events: bool) -> Vec<f64> {
+        let mut candidates = if events { self.event_snap_points() } else { Vec::new() };
+        if self.snap_mode != SnapMode::Off {
+            candidates.push(self.snap_beat(beat));
+        }
+        candidates
+    }
+
+    /// The candidate closest to `beat` within [`SNAP_TOLERANCE_PIXELS`] at
+    /// the current zoom
+    pub fn nearest_snap_point(&self, beat: f64, candidates: &[f64]) -> Option<f64> {
+        let tolerance = self.pixels_to_beats(SNAP_TOLERANCE_PIXELS);
+        candidates
+            .iter()
+            .copied()
+            .filter(|candidate| (candidate - beat).abs() <= tolerance)
+            .min_by(|a, b| (a - beat).abs().total_cmp(&(b - beat).abs()))
+    }
+
+    /// Where clip `clip_id` on `track_id` starts when dragged to `beat`: on
+    /// the grid as usual, unless magnetic snapping pulls its start or end
+    /// onto the edge of a clip on another track within
+    /// [`SNAP_TOLERANCE_PIXELS`]. Edges are matched where they're heard, so
+    /// each track's offset is taken into account. Clips moving with the
+    /// selection don't attract. Returns the start and the edge it locked to,
+    /// if any, on the dragged clip's track.
+    pub fn snap_clip_drag(&self, track_id: TrackId, clip_id: ClipId, beat: f64) -> (f64, Option<f64>) {
+        let grid = (self.snap_beat(beat).max(0.0), None);
+        let (Some(project), Some(clip)) = (self.project.as_ref().filter(|_| self.magnetic_snap), self.get_clip(track_id, clip_id)) else {
+            return grid;
+        };
+        let (tempo, rate) = (project.transport.tempo, project.transport.sample_rate);
+        let length = clip.duration_beats(tempo, rate);
+        let tolerance = self.pixels_to_beats(SNAP_TOLERANCE_PIXELS);
+        let offset = |track: &Track| track.track_offset_samples as f64 / rate as f64 * tempo as f64 / 60.0;
+        let own_offset = project.get_track(track_id).map_or(0.0, offset);
+
+        project.tracks
+            .iter()
+            .filter(|t| t.id != track_id)
+            .flat_map(|t| t.clips.iter().map(move |c| (offset(t) - own_offset, c)))
+            .filter(|(_, c)| !self.selection.selected_clip_ids.contains(&c.id))
+            .flat_map(|(shift, c)| {
+                let start = c.start_beat(tempo, rate) + shift;
+                let end = start + c.duration_beats(tempo, rate);
+                // Either edge of the dragged clip onto either edge of this one
+                [(start, start), (end, end), (start - length, start), (end - length, end)]
+            })
+            .filter(|(start, _)| *start >= 0.0 && (start - beat).abs() <= tolerance)
+            .min_by(|a, b| (a.0 - beat).abs().total_cmp(&(b.0 - beat).abs()))
+            .map_or(grid, |(start, edge)| (start, Some(edge)))
+    }
+
+    /// Where a click at `beat` puts the playhead. `bypass`, from holding
+    /// Alt, places it exactly where clicked.
+    pub fn snap_playhead(&self, beat: f64, bypass: bool) -> f64 {
+        if bypass {
+            return beat.max(0.0);
+        }
+        let candidates = self.snap_candidates(beat, self.snap_playhead_to_events);
+        self.nearest_snap_point(beat, &candidates).unwrap_or(beat).max(0.0)
+    }
+
+    /// Convert beats to pixels
+    pub fn beats_to_pixels(&self, beats: f64) -> f32 {
+        (beats * self.viewport.zoom) as f32
+    }
+
+    /// Grid lines between two x positions on the timeline
+    pub fn grid_lines_in(&self, start_x: f32, end_x: f32) -> Vec<GridLine> {
+        let transport = self.project.as_ref().map(|p| p.transport.clone()).unwrap_or_default();
+        transport.grid_lines(
+            self.pixels_to_beats(start_x),
+            self.pixels_to_beats(end_x),
+            self.viewport.zoom,
+            self.snap_value.feel(),
+        )
+    }
+
+    /// Ruler label for a grid line in the active time format
+    pub fn grid_label(&self, line: &GridLine) -> String {
+        match self.time_format {
+            TimeFormat::BarsBeats if line.level == GridLevel::Bar => line.bar.to_string(),
+            TimeFormat::BarsBeats => format!("{}.{}", line.bar, line.beat_in_bar),
+            TimeFormat::Timecode => {
+                let transport = self.project.as_ref().map(|p| p.transport.clone()).unwrap_or_default();
+                transport.format_timecode(transport.beats_to_samples(line.beat))
+            }
+        }
+    }
+
+    /// A timeline position in the active time format: `bar.beat.hundredths`
+    /// like the transport, or `m:ss.mmm`
+    pub fn format_time(&self, samples: SampleTime) -> String {
+        let transport = self.project.as_ref().map(|p| p.transport.clone()).unwrap_or_default();
+        match self.time_format {
+            TimeFormat::BarsBeats => {
+                let (bars, beats, hundredths) = bars_beats(&transport, samples);
+                format!("{}.{}.{:02}", bars + 1, beats + 1, hundredths)
+            }
+            TimeFormat::Timecode => transport.format_timecode(samples),
+        }
+    }
+
+    /// A length in the active time format, counted from zero bars and beats
+    pub fn format_length(&self, samples: SampleTime) -> String {
+        let transport = self.project.as_ref().map(|p| p.transport.clone()).unwrap_or_default();
+        match self.time_format {
+            TimeFormat::BarsBeats => {
+                let (bars, beats, hundredths) = bars_beats(&transport, samples);
+                format!("{}.{}.{:02}", bars, beats, hundredths)
+            }
+            TimeFormat::Timecode => transport.format_timecode(samples),
+        }
+    }
+
+    /// Readout of the clip move or trim in progress, or None when no clip
+    /// is being dragged
+    pub fn clip_drag_readout(&self) -> Option<ClipDragReadout> {
+        let transport = &self.project.as_ref()?.transport;
+        let (clip, edge, delta, edge_name) = match &self.drag_state {
+            DragState::DraggingClip { clip_id, track_id, start_beat, .. } => {
+                let clip = self.get_clip(*track_id, *clip_id)?;
+                let delta = clip.start_time as i64 - transport.beats_to_samples(*start_beat) as i64;
+                (clip, clip.start_time, delta, "")
+            }
+            DragState::DraggingClipEdge { clip_id, track_id, is_start, before, .. } => {
+                let clip = self.get_clip(*track_id, *clip_id)?;
+                let (now, was) = if *is_start {
+                    (clip.start_time, before.start_time)
+                } else {
+                    (clip.end_time(), before.end_time())
+                };
+                (clip, now, now as i64 - was as i64, if *is_start { "Start " } else { "End " })
+            }
+            _ => return None,
+        };
+
+        let sign = if delta < 0 { "-" } else { "+" };
+        let edge_beat = transport.samples_to_beats(edge);
+        let snap = match self.snap_mode {
+            SnapMode::Off => "Snap off".to_string(),
+            SnapMode::Grid => format!("Grid {} at {}", self.snap_value.label(), self.format_time(edge)),
+            SnapMode::Events => {
+                // The dragged edge is itself an event, so another one has to
+                // sit on it as well
+                let events = self.event_snap_points().iter().filter(|&&beat| (beat - edge_beat).abs() < 1e-6).count();
+                if events >= 2 {
+                    format!("Event at {}", self.format_time(edge))
+                } else {
+                    "No event in reach".to_string()
+                }
+            }
+        };
+        // A magnetic snap wins over the grid, so it's what the readout names
+        let magnet = self.magnetic_snap_line.filter(|_| matches!(self.drag_state, DragState::DraggingClip { .. }));
+        let snap = magnet.map_or(snap, |line| format!("Clip edge at {}", self.format_time(transport.beats_to_samples(line))));
+
+        Some(ClipDragReadout {
+            start: self.format_time(clip.start_time),
+            length: self.format_length(clip.duration),
+            end: self.format_time(clip.end_time()),
+            delta: format!("{}{}{}", edge_name, sign, self.format_length(delta.unsigned_abs())),
+            snap,
+        })
+    }
+
+    /// Convert pixels to beats
+    pub fn pixels_to_beats(&self, pixels: f32) -> f64 {
+        pixels as f64 / self.viewport.zoom
+    }
+    
+    /// Get current tempo from project
+    pub fn get_tempo(&self) -> f32 {
+        self.project.as_ref().map(|p| p.transport.tempo).unwrap_or(120.0)
+    }
+
+    /// Rate the project's sample positions count in
+    pub fn sample_rate(&self) -> f32 {
+        self.project.as_ref().map_or(SAMPLE_RATE, |p| p.transport.sample_rate)
+    }
+
+    /// Get track by ID
+    pub fn get_track(&self, track_id: TrackId) -> Option<&Track> {
+        self.project.as_ref()?.tracks.iter().find(|t| t.id == track_id)
+    }
+
+    /// Get track mutably
+    pub fn get_track_mut(&mut self, track_id: TrackId) -> Option<&mut Track> {
+        self.project.as_mut()?.tracks.iter_mut().find(|t| t.id == track_id)
+    }
+
+    /// Get clip by track and clip ID
+    pub fn get_clip(&self, track_id: TrackId, clip_id: ClipId) -> Option<&AudioClip> {
+        self.get_track(track_id)?.clips.iter().find(|c| c.id == clip_id)
+    }
+
+    /// Get clip mutably
+    pub fn get_clip_mut(&mut self, track_id: TrackId, clip_id: ClipId) -> Option<&mut AudioClip> {
+        self.get_track_mut(track_id)?.clips.iter_mut().find(|c| c.id == clip_id)
+    }
+
+    /// Whether a clip is locked, by itself or with its track
+    pub fn is_clip_locked(&self, track_id: TrackId, clip_id: ClipId) -> bool {
+        self.get_track(track_id)
+            .and_then(|t| t.clips.iter().find(|c| c.id == clip_id).map(|c| t.clip_locked(c)))
+            .unwrap_or(false)
+    }
+
+    /// Lock the selected clips, or unlock them if they're all locked
+    pub fn toggle_selected_clips_lock(&mut self) -> bool {
+        let Some(ref mut project) = self.project else {
+            return false;
+        };
+        let selected = &self.selection.selected_clip_ids;
+        let mut clips: Vec<&mut AudioClip> =
+            project.tracks.iter_mut().flat_map(|t| t.clips.iter_mut()).filter(|c| selected.contains(&c.id)).collect();
+        if clips.is_empty() {
+            return false;
+        }
+        let lock = !clips.iter().all(|c| c.locked);
+        for clip in &mut clips {
+            clip.locked = lock;
+        }
+        self.dirty.mark_dirty();
+        true
+    }
+
+    pub fn toggle_track_lock(&mut self, track_id: TrackId) {
+        if let Some(track) = self.get_track_mut(track_id) {
+            track.locked = !track.locked;
+            self.dirty.mark_dirty();
+        }
+    }
+
+    /// Lock every track, or unlock every track and clip
+    pub fn set_all_locked(&mut self, locked: bool) {
+        if self.project.as_mut().is_some_and(|p| p.set_all_locked(locked)) {
+            self.dirty.mark_dirty();
+        }
+    }
+
+    /// Whether every track is locked, for the toolbar's Lock All
+    pub fn all_tracks_locked(&self) -> bool {
+        self.project.as_ref().is_some_and(|p| !p.tracks.is_empty() && p.tracks.iter().all(|t| t.locked))
+    }
+
+    /// Number of timeline samples covered by one pixel at the current zoom
+    pub fn samples_per_pixel(&self) -> f64 {
+        (60.0 * self.sample_rate() as f64) / (self.get_tempo() as f64 * self.viewport.zoom)
+    }
+
+    /// Keep the playhead in view while playing, by page or continuously as
+    /// set in the preferences. Scrolling by hand pauses this for a few
+    /// seconds, or until playback stops and starts again.
+    pub fn follow_playhead(&mut self) {
+        if !self.preferences.follow_playhead || !self.is_playing {
+            self.follow_scroll = None;
+            self.follow_paused_until = None;
+            return;
+        }
+        let now = std::time::Instant::now();
+        let scroll = -self.timeline_scroll_handle.offset().x.as_f32();
+        if self.follow_scroll.is_some_and(|last| (scroll - last).abs() > 0.5) {
+            self.follow_paused_until = Some(now + std::time::Duration::from_secs_f32(preferences::FOLLOW_RESUME_SECONDS));
+        }
+        self.follow_scroll = Some(scroll);
+        if self.follow_paused_until.is_some_and(|until| now < until) {
+            return;
+        }
+        self.follow_paused_until = None;
+
+        let playhead = self.beats_to_pixels(self.selection.playhead_position);
+        if let Some(target) = self.preferences.follow_mode.scroll_for(playhead, scroll, self.timeline_width) {
+            self.timeline_scroll_handle.set_offset(point(px(-target), px(0.0)));
+            self.follow_scroll = Some(target);
+        }
+    }
+
+    /// Add new audio track
+    pub fn add_audio_track(&mut self, name: String) -> TrackId {
+        if let Some(ref mut project) = self.project {
+            let track = Track::new(name, TrackType::Audio);
+            let track_id = track.id;
+            project.tracks.push(track);
+            track_id
+        } else {
+            uuid::Uuid::new_v4()
+        }
+    }
+
+    /// Add a new return track, placed after the existing tracks
+    pub fn add_return_track(&mut self) -> Option<TrackId> {
+        let project = self.project.as_mut()?;
+        let count = project.tracks.iter().filter(|t| t.track_type == TrackType::Return).count();
+        let mut track = Track::new(format!("Return {}", (b'A' + (count % 26) as u8) as char), TrackType::Return);
+        track.color = [0.4, 0.7, 0.8];
+        let track_id = track.id;
+        project.tracks.push(track);
+        Some(track_id)
+    }
+
+    /// Add a new VCA fader, placed after the existing tracks, as one
+    /// undoable edit
+    pub fn add_vca_track(&mut self) -> Option<TrackId> {
+        let project = self.project.as_mut()?;
+        let count = project.tracks.iter().filter(|t| t.track_type == TrackType::Vca).count();
+        let mut track = Track::new(format!("VCA {}", count + 1), TrackType::Vca);
+        track.color = [0.9, 0.6, 0.3];
+        let track_id = track.id;
+        let index = project.tracks.len();
+        project.tracks.push(track.clone());
+        self.record_edit(EditAction::AddTracks {
+            label: "Add VCA",
+            snapshots: vec![TrackSnapshot { track, index, incoming_sends: Vec::new() }],
+        });
+        Some(track_id)
+    }
+
+    pub fn vca_track_ids(&self) -> Vec<TrackId> {
+        self.project.as_ref().map_or_else(Vec::new, |p| {
+            p.tracks.iter().filter(|t| t.track_type == TrackType::Vca).map(|t| t.id).collect()
+        })
+    }
+
+    /// Assign tracks to a VCA, or take them off theirs with `None`, as one
+    /// undoable edit. VCAs can't be assigned to one another.
+    pub fn assign_vca(&mut self, track_ids: &[TrackId], vca: Option<TrackId>) {
+        let Some(project) = self.project.as_mut() else {
+            return;
+        };
+        let changes: Vec<_> = project
+            .tracks
+            .iter_mut()
+            .filter(|t| track_ids.contains(&t.id) && t.track_type != TrackType::Vca && t.vca != vca)
+            .map(|t| {
+                let before = std::mem::replace(&mut t.vca, vca);
+                (t.id, before, vca)
+            })
+            .collect();
+        if !changes.is_empty() {
+            self.record_edit(EditAction::AssignVca { changes });
+        }
+    }
+
+    /// Record a finished move of a VCA fader that started at `before`, so
+    /// riding the group can be undone. Other faders aren't in the history.
+    pub fn record_vca_fader(&mut self, track_id: TrackId, before: f32) {
+        let Some(track) = self.get_track(track_id).filter(|t| t.track_type == TrackType::Vca) else {
+            return;
+        };
+        let after = track.volume;
+        if after != before {
+            self.record_edit(EditAction::VcaFader { track_id, before, after });
+        }
+    }
+
+    /// Add a reference track playing `path` from the start of the timeline.
+    /// The clip gets no fades so the reference is heard as it was mastered.
+    pub fn add_reference_track(&mut self, path: PathBuf, duration: SampleTime) -> Option<TrackId> {
+        let project = self.project.as_mut()?;
+        let name = path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+        let mut track = Track::new(format!("Reference: {}", name), TrackType::Reference);
+        track.color = [0.8, 0.7, 0.3];
+        track.clips.push(AudioClip::new(path, 0, duration));
+        let track_id = track.id;
+        project.tracks.push(track);
+        self.dirty.mark_dirty();
+        Some(track_id)
+    }
+
+    /// Put each channel split from an interleaved file on a new mono track
+    /// named after it, in channel order, as one undoable edit. Returns the
+    /// new tracks' IDs.
+    pub fn add_split_channel_tracks(&mut self, channels: Vec<(String, PathBuf)>, duration: SampleTime) -> Vec<TrackId> {
+        let Some(project) = self.project.as_mut() else {
+            return Vec::new();
+        };
+        let mut snapshots = Vec::new();
+        for (name, path) in channels {
+            let mut track = Track::new(name.clone(), TrackType::Audio);
+            let mut clip = AudioClip::new(path, 0, duration);
+            clip.name = name;
+            track.clips.push(clip);
+            snapshots.push(TrackSnapshot { track: track.clone(), index: project.tracks.len(), incoming_sends: Vec::new() });
+            project.tracks.push(track);
+        }
+        let added: Vec<TrackId> = snapshots.iter().map(|s| s.track.id).collect();
+        if !snapshots.is_empty() {
+            self.record_edit(EditAction::AddTracks { label: "Split Channels", snapshots });
+        }
+        added
+    }
+
+    /// Put each layer of a loop set on a new track named after its file.
+    /// Returns the new tracks' IDs.
+    pub fn add_loop_set(&mut self, layers: Vec<LoopLayer>) -> Vec<TrackId> {
+        let Some(project) = self.project.as_mut() else {
+            return Vec::new();
+        };
+        let mut added = Vec::new();
+        for layer in layers {
+            let mut track = Track::new(layer.name, TrackType::Audio);
+            track.clips.push(layer.clip);
+            added.push(track.id);
+            project.tracks.push(track);
+        }
+        if !added.is_empty() {
+            self.dirty.mark_dirty();
+        }
+        added
+    }
+
+    /// Put a printed track on a new track just below its source and mute the
+    /// source, as one undoable edit. Returns the source and the new track.
+    pub fn add_bounced_track(&mut self, source: TrackId, bounced: BouncedTrack) -> Option<Vec<TrackId>> {
+        let project = self.project.as_mut()?;
+        let index = project.tracks.iter().position(|t| t.id == source)?;
+        let original = &project.tracks[index];
+        let name = format!("{} (Bounced)", original.name);
+        let mut track = Track::new(name.clone(), TrackType::Audio);
+        track.color = original.color;
+        // Keep feeding the same buses and outputs, now from the print
+        track.output = original.output;
+        track.sends = original.sends.clone();
+        let mut clip = AudioClip::new(bounced.path, bounced.start, bounced.frames);
+        clip.name = name;
+        clip.gain = bounced.gain;
+        track.clips.push(clip);
+        let track_id = track.id;
+        let source_muted = original.muted;
+
+        project.tracks.insert(index + 1, track.clone());
+        project.tracks[index].muted = true;
+        self.record_edit(EditAction::BounceTrack {
+            source,
+            source_muted,
+            bounced: TrackSnapshot { track, index: index + 1, incoming_sends: Vec::new() },
+        });
+        self.select_track(track_id, false);
+        Some(vec![source, track_id])
+    }
+
+    pub fn has_reference_tracks(&self) -> bool {
+        self.project.as_ref().is_some_and(|p| p.tracks.iter().any(|t| t.track_type == TrackType::Reference))
+    }
+
+    /// IDs of all return tracks in project order
+    pub fn return_track_ids(&self) -> Vec<TrackId> {
+        self.project
+            .as_ref()
+            .map(|p| {
+                p.tracks
+                    .iter()
+                    .filter(|t| t.track_type == TrackType::Return)
+                    .map(|t| t.id)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Track indices in mixer order: returns sit at the right, after all other channels,
+    /// followed by the VCAs.
+    /// While a bus is spilled only the tracks feeding it are listed, followed by the bus.
+    pub fn mixer_track_order(&self) -> Vec<usize> {
+        let Some(ref project) = self.project else {
+            return Vec::new();
+        };
+
+        if let Some(bus) = self.mixer_spill {
+            if let Some(bus_idx) = project.tracks.iter().position(|t| t.id == bus) {
+                let feeders = self.bus_feeders(bus);
+                return (0..project.tracks.len())
+                    .filter(|&idx| feeders.contains(&project.tracks[idx].id))
+                    .chain(std::iter::once(bus_idx))
+                    .collect();
+            }
+        }
+
+        let (returns, others): (Vec<usize>, Vec<usize>) = (0..project.tracks.len())
+            .partition(|&idx| project.tracks[idx].track_type == TrackType::Return);
+        let (vcas, others): (Vec<usize>, Vec<usize>) = others
+            .into_iter()
+            .partition(|&idx| project.tracks[idx].track_type == TrackType::Vca);
+        others.into_iter().chain(returns).chain(vcas).collect()
+    }
+
+    /// Tracks with an enabled send into `bus`, or for a VCA the tracks
+    /// assigned to it
+    pub fn bus_feeders(&self, bus: TrackId) -> Vec<TrackId> {
+        self.project.as_ref().map_or_else(Vec::new, |p| {
+            let is_vca = p.get_track(bus).is_some_and(|t| t.track_type == TrackType::Vca);
+            p.tracks
+                .iter()
+                .filter(|t| t.id != bus)
+                .filter(|t| if is_vca {
+                    t.vca == Some(bus)
+                } else {
+                    t.sends.iter().any(|s| s.enabled && s.target_track == Some(bus))
+                })
+                .map(|t| t.id)
+                .collect()
+        })
+    }
+
+    /// Spill a bus in the mixer, or collapse it back if it's already spilled
+    pub fn toggle_mixer_spill(&mut self, bus: TrackId) {
+        self.mixer_spill = (self.mixer_spill != Some(bus)).then_some(bus);
+        self.mixer_scroll_handle.set_offset(point(px(0.0), px(0.0)));
+    }
+
+    /// Width of one channel strip in the current strip mode
+    pub fn mixer_strip_width(&self) -> f32 {
+        if self.mixer_narrow {
+            super::mixer::NARROW_CHANNEL_STRIP_WIDTH
+        } else {
+            super::mixer::CHANNEL_STRIP_WIDTH
+        }
+    }
+
+    /// Point a send at a target track, creating the send slot if needed.
+    /// Clearing the target disables the send.
+    pub fn set_send_target(&mut self, track_id: TrackId, send_idx: usize, target: Option<TrackId>) {
+        if let Some(track) = self.get_track_mut(track_id) {
+            while track.sends.len() <= send_idx {
+                track.sends.push(Send::default());
+            }
+            let send = &mut track.sends[send_idx];
+            send.target_track = target;
+            send.enabled = target.is_some();
+        }
+    }
+
+    /// Delete track, recording a snapshot so it can be restored with undo.
+    /// Returns the IDs of every track whose state changed.
+    pub fn delete_track(&mut self, track_id: TrackId) -> Vec<TrackId> {
+        self.delete_tracks(&[track_id])
+    }
+
+    /// Delete several tracks as a single undoable edit.
+    /// Returns the IDs of every track whose state changed.
+    pub fn delete_tracks(&mut self, track_ids: &[TrackId]) -> Vec<TrackId> {
+        let Some(ref mut project) = self.project else {
+            return Vec::new();
+        };
+
+        let snapshots: Vec<_> = track_ids
+            .iter()
+            .filter(|id| !project.get_track(**id).is_some_and(|t| t.locked))
+            .filter_map(|id| project.delete_track(*id))
+            .collect();
+        if snapshots.is_empty() {
+            return Vec::new();
+        }
+
+        let mut changed = Vec::new();
+        for snapshot in &snapshots {
+            let track_id = snapshot.track.id;
+            for clip in &snapshot.track.clips {
+                self.selection.selected_clip_ids.remove(&clip.id);
+            }
+            self.selection.selected_track_ids.remove(&track_id);
+            self.solo_tracks.remove(&track_id);
+            changed.push(track_id);
+            changed.extend(snapshot.incoming_sends.iter().map(|s| s.source_track));
+        }
+
+        self.record_edit(EditAction::DeleteTracks { snapshots });
+        changed
+    }
+
+    /// Push an action onto the undo stack
+    pub fn record_edit(&mut self, action: EditAction) {
+        self.dirty.mark_dirty();
+        self.history.push(action);
+        self.update_history_flags();
+    }
+
+    /// Undo the last edit. Returns the IDs of tracks whose state changed.
+    pub fn undo(&mut self) -> Option<Vec<TrackId>> {
+        let project = self.project.as_mut()?;
+        let touched = self.history.undo(project);
+        if touched.is_some() {
+            self.dirty.mark_dirty();
+            self.refresh_loop_selection();
+        }
+        self.update_history_flags();
+        touched
+    }
+
+    /// Redo the last undone edit. Returns the IDs of tracks whose state changed.
+    pub fn redo(&mut self) -> Option<Vec<TrackId>> {
+        let project = self.project.as_mut()?;
+        let touched = self.history.redo(project);
+        if touched.is_some() {
+            self.dirty.mark_dirty();
+            self.refresh_loop_selection();
+        }
+        self.update_history_flags();
+        touched
+    }
+
+    fn clear_history(&mut self) {
+        self.history.clear();
+        self.update_history_flags();
+    }
+
+    /// Open the properties dialog for a clip
+    pub fn open_clip_properties(&mut self, track_id: TrackId, clip_id: ClipId) {
+        if let Some(clip) = self.get_clip(track_id, clip_id).cloned() {
+            let source_len = self.loaded_assets
+                .get(&clip.asset_path)
+                .map(|asset| asset.asset_ref.duration_samples as SampleTime);
+            self.clip_properties = Some(ClipPropertiesDialog::new(track_id, clip, source_len));
+        }
+    }
+
+    /// Write the dialog's draft back to the project as an undoable edit and
+    /// close the dialog. Returns the track that owns the edited clip.
+    pub fn apply_clip_properties(&mut self) -> anyhow::Result<TrackId> {
+        let transport = self.project.as_ref().map(|p| p.transport.clone()).unwrap_or_default();
+        let dialog = self.clip_properties
+            .as_mut()
+            .ok_or_else(|| anyhow::anyhow!("No clip properties dialog open"))?;
+
+        if !dialog.commit_edit(&transport) {
+            return Err(anyhow::anyhow!(dialog.error.clone().unwrap_or_default()));
+        }
+        dialog.validate()?;
+
+        let (track_id, before, after) = (dialog.track_id, dialog.original.clone(), dialog.draft.clone());
+        if self.is_clip_locked(track_id, after.id) {
+            return Err(anyhow::anyhow!("The clip is locked; unlock it to edit it"));
+        }
+        let clip = self.get_clip_mut(track_id, after.id)
+            .ok_or_else(|| anyhow::anyhow!("Clip no longer exists"))?;
+        *clip = after.clone();
+
+        self.record_edit(EditAction::ModifyClips { changes: vec![(track_id, before, after)] });
+        self.clip_properties = None;
+        Ok(track_id)
+    }
+
+    /// Set the project tempo to the tempo detected for the clip in the
+    /// properties dialog, playing the clip at its recorded speed. The
+    /// dialog's other edits are kept and the whole change is one undo step.
+    /// Returns the IDs of tracks whose clips changed.
+    pub fn set_tempo_from_clip(&mut self) -> anyhow::Result<Vec<TrackId>> {
+        let project = self.project.as_mut().ok_or_else(|| anyhow::anyhow!("No project loaded"))?;
+        let dialog = self.clip_properties
+            .as_mut()
+            .ok_or_else(|| anyhow::anyhow!("No clip properties dialog open"))?;
+        if !dialog.commit_edit(&project.transport) {
+            return Err(anyhow::anyhow!(dialog.error.clone().unwrap_or_default()));
+        }
+        dialog.validate()?;
+        let tempo = dialog.detected_tempo(&project.transport)
+            .ok_or_else(|| anyhow::anyhow!("Couldn't detect a tempo for this clip"))?;
+        let tempo = ((tempo * 10.0).round() / 10.0).clamp(MIN_TEMPO, MAX_TEMPO);
+        let (track_id, draft) = (dialog.track_id, dialog.draft.clone());
+
+        let before = ArrangementSnapshot::capture(project);
+        let clip = project.get_track_mut(track_id)
+            .and_then(|t| t.clips.iter_mut().find(|c| c.id == draft.id))
+            .ok_or_else(|| anyhow::anyhow!("Clip no longer exists"))?;
+        *clip = draft;
+        let mut touched = project.change_tempo(tempo)?;
+        if let Some(clip) = project.get_track_mut(track_id).and_then(|t| t.clips.iter_mut().find(|c| c.id == dialog.clip_id)) {
+            clip.fit_to_tempo(tempo, tempo);
+        }
+        touched.push(track_id);
+        let after = ArrangementSnapshot::capture(project);
+
+        self.clip_properties = None;
+        self.refresh_loop_selection();
+        self.record_edit(EditAction::Arrangement { label: "Set Tempo from Clip", before, after });
+        tracing::error!("🎼 Project tempo set to {:.1} BPM from clip", tempo);
+        Ok(touched)
+    }
+
+    pub fn open_sample_rate_dialog(&mut self) {
+        if let Some(ref project) = self.project {
+            self.sample_rate_dialog = Some(SampleRateDialog {
+                rate: project.sample_rate,
+                mode: SampleRateChange::KeepMusicalTiming,
+            });
+        }
+    }
+
+    /// Open the reference image dialog on the selected audio track, or on the
+    /// master when none is selected
+    pub fn open_reference_image_dialog(&mut self) {
+        let Some(ref project) = self.project else {
+            return;
+        };
+        let source = project
+            .tracks
+            .iter()
+            .find(|t| {
+                self.selection.selected_track_ids.contains(&t.id)
+                    && matches!(t.track_type, TrackType::Audio | TrackType::Reference)
+            })
+            .map(|t| t.id);
+        self.reference_image_dialog = Some(ReferenceImageDialog { source, settings: ReferenceImageSettings::default() });
+    }
+
+    /// Frames a reference image covers: the loop while looping, otherwise
+    /// the whole mix
+    pub fn reference_image_range(&self) -> (SampleTime, SampleTime) {
+        let Some(ref project) = self.project else {
+            return (0, 0);
+        };
+        let transport = &project.transport;
+        if transport.loop_enabled && transport.loop_end > transport.loop_start {
+            (transport.loop_start, transport.loop_end)
+        } else {
+            (0, project.mix_duration())
+        }
+    }
+
+    /// Apply the sample rate dialog to the whole project. The change can't be
+    /// undone, so history is cleared. Returns the new rate.
+    pub fn apply_sample_rate_change(&mut self) -> anyhow::Result<(f32, SampleRateChange)> {
+        let dialog = self.sample_rate_dialog
+            .ok_or_else(|| anyhow::anyhow!("No sample rate dialog open"))?;
+        let project = self.project
+            .as_mut()
+            .ok_or_else(|| anyhow::anyhow!("No project loaded"))?;
+
+        project.change_sample_rate(dialog.rate, dialog.mode)?;
+        self.sample_rate_dialog = None;
+        self.clear_history();
+        self.dirty.mark_dirty();
+        Ok((dialog.rate, dialog.mode))
+    }
+
+    fn update_history_flags(&mut self) {
+        self.can_undo = self.history.can_undo();
+        self.can_redo = self.history.can_redo();
+    }
+
+    /// Add clip to track
+    pub fn add_clip(&mut self, track_id: TrackId, start_beat: f64, file_path: PathBuf) -> Option<uuid::Uuid> {
+        // Get tempo first before borrowing track mutably
+        let (tempo, sample_rate) = (self.get_tempo(), self.sample_rate());
+        let samples_per_beat = (sample_rate * 60.0) / tempo;
+        let start_samples = (start_beat * samples_per_beat as f64) as u64;
+        let duration_samples = (4.0 * samples_per_beat) as u64; // Default 4 beats
+        
+        let prefs = self.preferences;
+        if let Some(track) = self.get_track_mut(track_id) {
+            let mut clip = AudioClip::new(file_path, start_samples, duration_samples);
+            prefs.apply_default_fades(&mut clip, sample_rate);
+            let clip_id = clip.id;
+            track.clips.push(clip);
+            Some(clip_id)
+        } else {
+            None
+        }
+    }
+
+    /// Put a newly created clip on a track, giving it the default fades and
+    /// crossfading any overlap when auto-crossfade is on. Returns whether
+    /// neighbouring clips changed too, or None if the track doesn't exist or
+    /// is locked.
+    pub fn place_new_clip(&mut self, track_id: TrackId, mut clip: AudioClip) -> Option<bool> {
+        let prefs = self.preferences;
+        let project = self.project.as_mut()?;
+        let sample_rate = project.sample_rate;
+        let track = project.get_track_mut(track_id).filter(|t| !t.locked)?;
+
+        prefs.apply_default_fades(&mut clip, sample_rate);
+        let clip_id = clip.id;
+        // Last is on top, which is what a comped track plays
+        track.clips.push(clip);
+
+        let replaced = track.overlap_mode == OverlapMode::Replace
+            && preferences::replace_overlaps(&mut track.clips, &[clip_id], Self::replace_overlap(&prefs, sample_rate));
+        let neighbours_changed = replaced
+            || prefs.auto_crossfade
+                && preferences::crossfade_overlaps(&mut track.clips, &[clip_id], prefs.crossfade_samples(sample_rate), prefs.crossfade_shape)
+                    .iter()
+                    .any(|(id, ..)| *id != clip_id);
+        self.dirty.mark_dirty();
+        Some(neighbours_changed)
+    }
+
+    /// How far what's left of a clip under a replacing one reaches into it,
+    /// to crossfade across the cut
+    fn replace_overlap(prefs: &EditPreferences, sample_rate: f32) -> SampleTime {
+        if prefs.auto_crossfade { prefs.crossfade_samples(sample_rate) } else { 0 }
+    }
+
+    /// Put a generated file on the first selected track, or the first audio
+    /// track, at the playhead. Returns the track it went on.
+    pub fn place_generated_clip(&mut self, path: PathBuf, name: String, duration: SampleTime) -> Option<TrackId> {
+        let project = self.project.as_ref()?;
+        let track_id = project
+            .tracks
+            .iter()
+            .find(|t| self.selection.selected_track_ids.contains(&t.id))
+            .or_else(|| project.tracks.iter().find(|t| t.track_type == TrackType::Audio))?
+            .id;
+        let start = project.transport.beats_to_samples(self.selection.playhead_position);
+
+        let mut clip = AudioClip::new(path, start, duration);
+        clip.name = name;
+        self.place_clip_as_edit(track_id, clip)?;
+        Some(track_id)
+    }
+
+    /// [`Self::place_new_clip`] as one undoable edit. Returns whether
+    /// neighbouring clips changed too.
+    pub fn place_clip_as_edit(&mut self, track_id: TrackId, clip: AudioClip) -> Option<bool> {
+        let before = ArrangementSnapshot::capture(self.project.as_ref()?);
+        let clip_id = clip.id;
+        let neighbours_changed = self.place_new_clip(track_id, clip)?;
+        // Clips underneath cut or crossfaded have to come back on undo too
+        if neighbours_changed {
+            let after = ArrangementSnapshot::capture(self.project.as_ref()?);
+            self.record_edit(EditAction::Arrangement { label: "Add Clip", before, after });
+        } else if let Some(placed) = self.get_clip(track_id, clip_id).cloned() {
+            self.record_edit(EditAction::AddClips { clips: vec![(track_id, placed)] });
+        }
+        Some(neighbours_changed)
+    }
+
+    /// The selected clips as (track, clip), in track order then by start time
+    pub fn selected_clip_refs(&self) -> Vec<(TrackId, ClipId)> {
+        let Some(ref project) = self.project else {
+            return Vec::new();
+        };
+        project
+            .tracks
+            .iter()
+            .flat_map(|track| {
+                let mut clips: Vec<&AudioClip> = track
+                    .clips
+                    .iter()
+                    .filter(|c| self.selection.selected_clip_ids.contains(&c.id))
+                    .collect();
+                clips.sort_by_key(|c| c.start_time);
+                clips.into_iter().map(move |c| (track.id, c.id))
+            })
+            .collect()
+    }
+
+    /// Copy the selected clips into a clipboard payload
+    pub fn copy_selected_clips(&mut self) -> Option<ClipboardPayload> {
+        let project = self.project.as_ref()?;
+        let clips: Vec<(usize, AudioClip)> = project
+            .tracks
+            .iter()
+            .enumerate()
+            .flat_map(|(idx, track)| {
+                track
+                    .clips
+                    .iter()
+                    .filter(|c| self.selection.selected_clip_ids.contains(&c.id))
+                    .map(move |c| (idx, c.clone()))
+            })
+            .collect();
+
+        if clips.is_empty() {
+            return None;
+        }
+
+        let payload = ClipboardPayload::from_clips(clips, project.sample_rate);
+        self.clip_clipboard = Some(payload.clone());
+        Some(payload)
+    }
+
+    /// Paste clips at the playhead, starting on the first selected track.
+    /// Clips copied at another sample rate are rescaled to this project's.
+    /// Returns the pasted clips so they can be sent to the audio engine.
+    pub fn paste_clips(&mut self, mut payload: ClipboardPayload) -> Vec<(TrackId, AudioClip)> {
+        let playhead = self.selection.playhead_position;
+        let Some(ref mut project) = self.project else {
+            return Vec::new();
+        };
+        payload.conform_to_rate(project.sample_rate);
+
+        let base_track = project
+            .tracks
+            .iter()
+            .position(|t| self.selection.selected_track_ids.contains(&t.id))
+            .unwrap_or(0);
+        let paste_start = project.transport.beats_to_samples(playhead);
+
+        let mut pasted = Vec::new();
+        for entry in payload.clips {
+            let Some(track) = project.tracks.get_mut(base_track + entry.track_offset) else {
+                continue;
+            };
+
+            let mut clip = entry.clip;
+            clip.id = uuid::Uuid::new_v4();
+            clip.start_time += paste_start;
+            track.clips.push(clip.clone());
+            pasted.push((track.id, clip));
+        }
+
+        if !pasted.is_empty() {
+            self.selection.selected_clip_ids = pasted.iter().map(|(_, c)| c.id).collect();
+            self.record_edit(EditAction::AddClips { clips: pasted.clone() });
+        }
+        pasted
+    }
+
+    /// Import audio file to project
+    pub fn import_audio_file(&mut self, source_path: PathBuf) -> anyhow::Result<PathBuf> {
+        // Clone project_dir to avoid borrow issue
+        let proj_dir = self.project_dir.clone().ok_or_else(|| anyhow::anyhow!("No project directory"))?;
+        
+        let audio_dir = proj_dir.join(AUDIO_FOLDER);
+        std::fs::create_dir_all(&audio_dir)?;
+        
+        if let Some(filename) = source_path.file_name() {
+            let dest = audio_dir.join(filename);
+            std::fs::copy(&source_path, &dest)?;
+            self.scan_audio_files(&proj_dir);
+            Ok(dest)
+        } else {
+            Err(anyhow::anyhow!("Invalid file name"))
+        }
+    }
+
+    /// Set the loop region in beats, widened outward to the snap grid, and
+    /// enable looping. Returns false for an empty range.
+    pub fn set_loop_region(&mut self, start_beat: f64, end_beat: f64) -> bool {
+        let (mut start, mut end) = (start_beat.max(0.0), end_beat);
+        if self.snap_mode == SnapMode::Grid {
+            let step = self.snap_value.to_beats();
+            start = (start / step).floor() * step;
+            end = (end / step).ceil() * step;
+        }
+        if end <= start {
+            return false;
+        }
+
+        self.selection.loop_start = Some(start);
+        self.selection.loop_end = Some(end);
+        self.set_looping(true);
+        true
+    }
+
+    /// Loop the span covered by the selected clips
+    pub fn loop_selection(&mut self) -> bool {
+        let Some(ref project) = self.project else {
+            return false;
+        };
+        let (tempo, rate) = (project.transport.tempo, project.transport.sample_rate);
+        let span = project
+            .tracks
+            .iter()
+            .flat_map(|t| t.clips.iter())
+            .filter(|c| self.selection.selected_clip_ids.contains(&c.id))
+            .map(|c| (c.start_beat(tempo, rate), c.start_beat(tempo, rate) + c.duration_beats(tempo, rate)))
+            .reduce(|(start, end), (s, e)| (start.min(s), end.max(e)));
+
+        match span {
+            Some((start, end)) => self.set_loop_region(start, end),
+            None => false,
+        }
+    }
+
+    /// Loop from the start of the project to the end of its last clip
+    pub fn loop_project(&mut self) -> bool {
+        let Some(ref project) = self.project else {
+            return false;
+        };
+        let length = project.transport.project_length.unwrap_or_else(|| project.duration());
+        let end = project.transport.samples_to_beats(length);
+        self.set_loop_region(0.0, end)
+    }
+
+    /// Choose what playback does when it reaches the project end
+    pub fn set_playback_end(&mut self, mode: PlaybackEnd) {
+        if let Some(ref mut project) = self.project {
+            project.transport.playback_end = mode;
+            self.dirty.mark_dirty();
+        }
+    }
+
+    /// Put the project length marker at the playhead, or remove it with
+    /// None so the project ends with its last clip
+    pub fn set_project_length_at_playhead(&mut self, at_playhead: bool) {
+        let playhead = self.selection.playhead_position;
+        if let Some(ref mut project) = self.project {
+            project.transport.project_length = at_playhead
+                .then(|| project.transport.beats_to_samples(playhead))
+                .filter(|&length| length > 0);
+            self.dirty.mark_dirty();
+        }
+    }
+
+    /// Remove the loop region and stop looping
+    pub fn clear_loop(&mut self) {
+        self.selection.loop_start = None;
+        self.selection.loop_end = None;
+        self.set_looping(false);
+    }
+
+    /// Turn looping on or off, writing the loop region to the project transport
+    pub fn set_looping(&mut self, enabled: bool) {
+        self.is_looping = enabled;
+        let start = self.selection.loop_start.unwrap_or(0.0);
+        let end = self.selection.loop_end.unwrap_or(16.0);
+        if let Some(ref mut project) = self.project {
+            project.transport.loop_enabled = enabled;
+            project.transport.loop_start = project.transport.beats_to_samples(start);
+            project.transport.loop_end = project.transport.beats_to_samples(end);
+            self.dirty.mark_dirty();
+        }
+    }
+
+    /// Set how long the loop wrap crossfades, which has to be shorter than
+    /// the loop
+    pub fn set_loop_crossfade(&mut self, ms: f32) -> anyhow::Result<()> {
+        let Some(ref mut project) = self.project else {
+            return Ok(());
+        };
+        loop_crossfade::check_loop_crossfade(ms, &project.transport)?;
+        project.transport.loop_crossfade_ms = ms;
+        self.dirty.mark_dirty();
+        Ok(())
+    }
+
+    /// Insert `insert_silence_bars` bars of silence at the playhead, rippling
+    /// every track. Refused while a locked clip or track is in the way.
+    /// Returns the IDs of tracks whose state changed.
+    pub fn insert_silence_at_playhead(&mut self) -> anyhow::Result<Vec<TrackId>> {
+        let playhead = self.selection.playhead_position;
+        let bars = self.insert_silence_bars as f64;
+        let Some(ref mut project) = self.project else {
+            return Ok(Vec::new());
+        };
+        let transport = &project.transport;
+        let at = transport.beats_to_samples(playhead);
+        let length = transport.beats_to_samples(bars * transport.time_signature_numerator.max(1) as f64);
+
+        let cut_fade = self.preferences.fade_samples(project.sample_rate);
+
+        let before = ArrangementSnapshot::capture(project);
+        let touched = project.insert_silence(at, length, cut_fade)?;
+        let after = ArrangementSnapshot::capture(project);
+        self.refresh_loop_selection();
+        self.record_edit(EditAction::Arrangement { label: "Insert Silence", before, after });
+        Ok(touched)
+    }
+
+    /// Repeat the selected clips back to back, each copy snapped to the grid
+    /// after the one before, up to the loop end or `fill_bars` bars from the
+    /// selection start. With `fill_with_clip_loop` a lone selected clip loops
+    /// its audio there instead. Overlaps at the seams are handled as for a
+    /// move. Returns the IDs of tracks whose state changed.
+    pub fn duplicate_selected_to_fill(&mut self) -> Vec<TrackId> {
+        let prefs = self.preferences;
+        let Some(project) = self.project.as_ref() else {
+            return Vec::new();
+        };
+        let selection: Vec<(TrackId, AudioClip)> = project
+            .tracks
+            .iter()
+            .flat_map(|t| t.clips.iter().filter(|c| self.selection.selected_clip_ids.contains(&c.id)).map(move |c| (t.id, c.clone())))
+            .collect();
+        let (Some(start), Some(end)) = (
+            selection.iter().map(|(_, c)| c.start_time).min(),
+            selection.iter().map(|(_, c)| c.end_time()).max(),
+        ) else {
+            return Vec::new();
+        };
+
+        let transport = &project.transport;
+        // The tempo counts quarter notes, so a bar of 6/8 is three of them
+        let bar = transport.time_signature_numerator.max(1) as f64 * 4.0 / transport.time_signature_denominator.max(1) as f64;
+        let target = match self.fill_bars {
+            Some(bars) => start + transport.beats_to_samples(bars as f64 * bar),
+            None if self.selection.loop_end.is_some() => transport.loop_end,
+            None => {
+                tracing::error!("⚠️ Set a loop to fill to, or pick a length in bars");
+                return Vec::new();
+            }
+        };
+        if target <= end {
+            tracing::error!("⚠️ The selection already reaches past where the fill would end");
+            return Vec::new();
+        }
+
+        let placed: Vec<(TrackId, AudioClip)> = if self.fill_with_clip_loop && selection.len() == 1 {
+            let (track_id, mut clip) = selection[0].clone();
+            if self.is_clip_locked(track_id, clip.id) {
+                tracing::error!("⚠️ '{}' is locked, so it can't be looped to fill", clip.display_name());
+                return Vec::new();
+            }
+            fill::loop_clip_to(&mut clip, target);
+            vec![(track_id, clip)]
+        } else {
+            let snap = |time: SampleTime| transport.beats_to_samples(self.snap_beat(transport.samples_to_beats(time)));
+            let group: Vec<AudioClip> = selection.iter().map(|(_, c)| c.clone()).collect();
+            fill::fill_copies(&group, target, snap)
+                .into_iter()
+                .map(|(index, copy)| (selection[index].0, copy))
+                .collect()
+        };
+        if placed.is_empty() {
+            return Vec::new();
+        }
+
+        let Some(ref mut project) = self.project else {
+            return Vec::new();
+        };
+        let sample_rate = project.sample_rate;
+        let before = ArrangementSnapshot::capture(project);
+        let mut touched: Vec<TrackId> = placed.iter().map(|(track_id, _)| *track_id).collect();
+        touched.sort();
+        touched.dedup();
+        for track_id in &touched {
+            let Some(track) = project.get_track_mut(*track_id) else {
+                continue;
+            };
+            let mut ids = Vec::new();
+            for (_, clip) in placed.iter().filter(|(t, _)| t == track_id) {
+                ids.push(clip.id);
+                match track.clips.iter_mut().find(|c| c.id == clip.id) {
+                    Some(looped) => *looped = clip.clone(),
+                    None => track.clips.push(clip.clone()),
+                }
+            }
+            if track.overlap_mode == OverlapMode::Replace {
+                preferences::replace_overlaps(&mut track.clips, &ids, Self::replace_overlap(&prefs, sample_rate));
+            }
+            if prefs.auto_crossfade {
+                preferences::crossfade_overlaps(&mut track.clips, &ids, prefs.crossfade_samples(sample_rate), prefs.crossfade_shape);
+            }
+        }
+        let after = ArrangementSnapshot::capture(project);
+        self.record_edit(EditAction::Arrangement { label: "Duplicate to Fill", before, after });
+        touched
+    }
+
+    /// Step the duplicate to fill length through the loop end and 2 to 16 bars
+    pub fn cycle_fill_length(&mut self) {
+        self.fill_bars = match self.fill_bars {
+            None => Some(2),
+            Some(2) => Some(4),
+            Some(4) => Some(8),
+            Some(8) => Some(16),
+            _ => None,
+        };
+    }
+
+    /// Trim automation and the loop and punch ranges to the last clip end.
+    /// Refused while that would cut a locked track's automation. Returns the
+    /// IDs of tracks whose state changed.
+    pub fn trim_project_end(&mut self) -> anyhow::Result<Vec<TrackId>> {
+        let Some(ref mut project) = self.project else {
+            return Ok(Vec::new());
+        };
+        let before = ArrangementSnapshot::capture(project);
+        let touched = project.trim_to_content()?;
+        let after = ArrangementSnapshot::capture(project);
+        self.refresh_loop_selection();
+        self.record_edit(EditAction::Arrangement { label: "Trim Project End", before, after });
+        Ok(touched)
+    }
+
+    /// Change the project tempo as one undoable edit. Returns the IDs of
+    /// tracks whose clips or automation moved.
+    pub fn change_tempo(&mut self, tempo: f32) -> anyhow::Result<Vec<TrackId>> {
+        let Some(ref mut project) = self.project else {
+            return Ok(Vec::new());
+        };
+        let before = ArrangementSnapshot::capture(project);
+        let touched = project.change_tempo(tempo)?;
+        let after = ArrangementSnapshot::capture(project);
+        self.refresh_loop_selection();
+        self.record_edit(EditAction::Arrangement { label: "Change Tempo", before, after });
+        Ok(touched)
+    }
+
+    /// Change the time signature as one undoable edit. Nothing moves, the
+    /// grid just counts bars differently.
+    pub fn change_time_signature(&mut self, numerator: u32, denominator: u32) -> anyhow::Result<()> {
+        let Some(ref mut project) = self.project else {
+            return Ok(());
+        };
+        let transport = &project.transport;
+        if (transport.time_signature_numerator, transport.time_signature_denominator) == (numerator, denominator) {
+            return Ok(());
+        }
+        let before = ArrangementSnapshot::capture(project);
+        project.change_time_signature(numerator, denominator)?;
+        let after = ArrangementSnapshot::capture(project);
+        self.record_edit(EditAction::Arrangement { label: "Change Time Signature", before, after });
+        Ok(())
+    }
+
+    /// Tap along to set the tempo. From the second tap of a run the project
+    /// follows the tapped tempo, and the whole run undoes as one step.
+    /// Returns the IDs of tracks whose clips moved.
+    pub fn tap_tempo(&mut self) -> Vec<TrackId> {
+        let Some(tempo) = self.tap_tempo.tap_now() else {
+            return Vec::new();
+        };
+        let Some(ref mut project) = self.project else {
+            return Vec::new();
+        };
+        let tempo = ((tempo * 10.0).round() / 10.0).clamp(MIN_TEMPO, MAX_TEMPO);
+        let before = ArrangementSnapshot::capture(project);
+        let touched = match project.change_tempo(tempo) {
+            Ok(touched) => touched,
+            Err(e) => {
+                tracing::error!("⚠️ {}", e);
+                return Vec::new();
+            }
+        };
+        let after = ArrangementSnapshot::capture(project);
+        self.refresh_loop_selection();
+
+        let continues_run = self.tap_tempo.tap_count() > 2;
+        if continues_run && self.history.amend_last("Tap Tempo", after.clone()) {
+            self.dirty.mark_dirty();
+            self.update_history_flags();
+        } else {
+            self.record_edit(EditAction::Arrangement { label: "Tap Tempo", before, after });
+        }
+        tracing::error!("🥁 Tapped tempo {:.1} BPM", tempo);
+        touched
+    }
+
+    /// Follow the tempo of a MIDI clock master. Changes in a row undo as
+    /// one step. Returns the IDs of tracks whose clips moved.
+    pub fn chase_tempo(&mut self, tempo: f32) -> Vec<TrackId> {
+        let Some(ref mut project) = self.project else {
+            return Vec::new();
+        };
+        let before = ArrangementSnapshot::capture(project);
+        let touched = match project.change_tempo(tempo.clamp(MIN_TEMPO, MAX_TEMPO)) {
+            Ok(touched) => touched,
+            Err(e) => {
+                tracing::error!("⚠️ {}", e);
+                return Vec::new();
+            }
+        };
+        let after = ArrangementSnapshot::capture(project);
+        self.refresh_loop_selection();
+
+        if self.history.amend_last("Chase Tempo", after.clone()) {
+            self.dirty.mark_dirty();
+            self.update_history_flags();
+        } else {
+            self.record_edit(EditAction::Arrangement { label: "Chase Tempo", before, after });
+        }
+        touched
+    }
+
+    /// Choose the master the transport chases and save it to the user
+    /// preferences
+    pub fn set_sync_source(&mut self, sync_source: SyncSource) {
+        self.set_preferences(EditPreferences { sync_source, ..self.preferences });
+        self.transport_chase.set_source(sync_source);
+        tracing::error!("🔗 Transport sync: {}", sync_source.label());
     }
 
-    /// Add clip to track
-    pub fn add_clip(&mut self, track_id: TrackId, start_beat: f64, file_path: PathBuf) -> Option<uuid::Uuid> {
-        // Get tempo first before borrowing track mutably
-        let tempo = self.get_tempo();
-        let samples_per_beat = (SAMPLE_RATE * 60.0) / tempo;
-        let start_samples = (start_beat * samples_per_beat as f64) as u64;
-        let duration_samples = (4.0 * samples_per_beat) as u64; // Default 4 beats
-        
-        if let Some(track) = self.get_track_mut(track_id) {
-            let clip = AudioClip::new(file_path, start_samples, duration_samples);
-            let clip_id = clip.id;
-            track.clips.push(clip);
-            Some(clip_id)
+    /// Whether the transport is left to a master. Without a MIDI input open
+    /// there is nothing to follow, so it stays under local control.
+    pub fn follows_sync_master(&self) -> bool {
+        self.transport_chase.is_chasing() && self.midi_inputs.as_ref().is_some_and(|inputs| !inputs.is_empty())
+    }
+
+    /// Change the project's markers as one undoable edit. `edit` returns
+    /// whether it changed anything.
+    fn edit_markers(&mut self, label: &'static str, edit: impl FnOnce(&mut Vec<Marker>) -> bool) -> bool {
+        let Some(ref mut project) = self.project else {
+            return false;
+        };
+        let before = ArrangementSnapshot::capture(project);
+        if !edit(&mut project.markers) {
+            return false;
+        }
+        let after = ArrangementSnapshot::capture(project);
+        self.record_edit(EditAction::Arrangement { label, before, after });
+        true
+    }
+
+    /// Drop a numbered marker at the playhead and select it
+    pub fn add_marker_at_playhead(&mut self) -> Option<MarkerId> {
+        let project = self.project.as_ref()?;
+        let position = project.transport.beats_to_samples(self.selection.playhead_position);
+        let marker = Marker::new(format!("Marker {}", project.markers.len() + 1), position);
+        let id = marker.id;
+        self.edit_markers("Add Marker", |markers| {
+            markers.push(marker);
+            true
+        });
+        self.selected_marker = Some(id);
+        Some(id)
+    }
+
+    pub fn remove_marker(&mut self, id: MarkerId) -> bool {
+        if self.selected_marker == Some(id) {
+            self.selected_marker = None;
+        }
+        self.edit_markers("Delete Marker", |markers| {
+            let count = markers.len();
+            markers.retain(|m| m.id != id);
+            markers.len() != count
+        })
+    }
+
+    /// Apply the marker name being typed. Blank names are ignored.
+    pub fn commit_marker_rename(&mut self) -> bool {
+        let Some(rename) = self.marker_rename.take() else {
+            return false;
+        };
+        let name = rename.text.trim().to_string();
+        if name.is_empty() {
+            return false;
+        }
+        self.edit_markers("Rename Marker", |markers| match markers.iter_mut().find(|m| m.id == rename.marker) {
+            Some(marker) if marker.name != name => {
+                marker.name = name;
+                true
+            }
+            _ => false,
+        })
+    }
+
+    /// Change the project's regions as one undoable edit. `edit` returns
+    /// whether it changed anything.
+    fn edit_regions(&mut self, label: &'static str, edit: impl FnOnce(&mut Vec<Region>) -> bool) -> bool {
+        let Some(ref mut project) = self.project else {
+            return false;
+        };
+        let before = ArrangementSnapshot::capture(project);
+        if !edit(&mut project.regions) {
+            return false;
+        }
+        let after = ArrangementSnapshot::capture(project);
+        self.record_edit(EditAction::Arrangement { label, before, after });
+        true
+    }
+
+    /// Add a numbered region over the loop range, or the bar under the
+    /// playhead without one, and select it
+    pub fn add_region(&mut self) -> Option<RegionId> {
+        let project = self.project.as_ref()?;
+        let transport = &project.transport;
+        let (start, end) = match (self.selection.loop_start, self.selection.loop_end) {
+            (Some(start), Some(end)) if end > start => (start, end),
+            _ => {
+                let bar = transport.time_signature_numerator.max(1) as f64;
+                let start = (self.selection.playhead_position / bar).floor() * bar;
+                (start, start + bar)
+            }
+        };
+        let region = Region::new(
+            format!("Region {}", project.regions.len() + 1),
+            transport.beats_to_samples(start),
+            transport.beats_to_samples(end),
+            project.regions.len(),
+        );
+        let id = region.id;
+        self.edit_regions("Add Region", |regions| {
+            regions.push(region);
+            true
+        });
+        self.selected_region = Some(id);
+        Some(id)
+    }
+
+    pub fn remove_region(&mut self, id: RegionId) -> bool {
+        if self.selected_region == Some(id) {
+            self.selected_region = None;
+        }
+        self.edit_regions("Delete Region", |regions| {
+            let count = regions.len();
+            regions.retain(|r| r.id != id);
+            regions.len() != count
+        })
+    }
+
+    /// Start typing a new name for a region
+    pub fn begin_region_rename(&mut self, id: RegionId) {
+        let text = self.project.as_ref()
+            .and_then(|p| p.regions.iter().find(|r| r.id == id))
+            .map(|r| r.name.clone());
+        self.region_rename = text.map(|text| RegionRename { region: id, text });
+    }
+
+    /// Apply the region name being typed. Blank names are ignored.
+    pub fn commit_region_rename(&mut self) -> bool {
+        let Some(rename) = self.region_rename.take() else {
+            return false;
+        };
+        let name = rename.text.trim().to_string();
+        if name.is_empty() {
+            return false;
+        }
+        self.edit_regions("Rename Region", |regions| match regions.iter_mut().find(|r| r.id == rename.region) {
+            Some(region) if region.name != name => {
+                region.name = name;
+                true
+            }
+            _ => false,
+        })
+    }
+
+    /// Draw a region in the next palette color
+    pub fn cycle_region_color(&mut self, id: RegionId) -> bool {
+        self.edit_regions("Region Color", |regions| match regions.iter_mut().find(|r| r.id == id) {
+            Some(region) => {
+                region.color += 1;
+                true
+            }
+            None => false,
+        })
+    }
+
+    /// Select a region and loop over it
+    pub fn loop_region(&mut self, id: RegionId) -> bool {
+        let Some(project) = self.project.as_ref() else {
+            return false;
+        };
+        let Some(region) = project.regions.iter().find(|r| r.id == id) else {
+            return false;
+        };
+        let transport = &project.transport;
+        let (start, end) = (transport.samples_to_beats(region.start), transport.samples_to_beats(region.end));
+        self.selected_region = Some(id);
+        self.selection.loop_start = Some(start);
+        self.selection.loop_end = Some(end);
+        self.set_looping(true);
+        true
+    }
+
+    /// Start dragging a region or one of its edges, keeping the arrangement
+    /// for undo
+    pub fn begin_region_drag(&mut self, id: RegionId, edge: RegionEdge, mouse_x: f32) {
+        let Some(project) = self.project.as_ref() else {
+            return;
+        };
+        let Some(region) = project.regions.iter().find(|r| r.id == id) else {
+            return;
+        };
+        let transport = &project.transport;
+        self.selected_region = Some(id);
+        self.drag_state = DragState::DraggingRegion {
+            region_id: id,
+            edge,
+            start_mouse_x: mouse_x,
+            start_beats: (transport.samples_to_beats(region.start), transport.samples_to_beats(region.end)),
+        };
+        self.region_drag_before = Some(ArrangementSnapshot::capture(project));
+    }
+
+    /// Follow a region drag to `mouse_x`, snapping the dragged edge (the
+    /// start when moving the whole region) to the grid. Edges can't cross.
+    pub fn drag_region(&mut self, mouse_x: f32) {
+        let DragState::DraggingRegion { region_id, edge, start_mouse_x, start_beats: (start, end) } = self.drag_state else {
+            return;
+        };
+        let delta = self.pixels_to_beats(mouse_x - start_mouse_x);
+        let from = if edge == RegionEdge::End { end } else { start };
+        let beat = self.snap_beat(from + delta).max(0.0);
+        let Some(project) = self.project.as_mut() else {
+            return;
+        };
+        let position = project.transport.beats_to_samples(beat);
+        let Some(region) = project.regions.iter_mut().find(|r| r.id == region_id) else {
+            return;
+        };
+        match edge {
+            RegionEdge::Body => region.move_to(position),
+            RegionEdge::Start => region.start = position.min(region.end - 1),
+            RegionEdge::End => region.end = position.max(region.start + 1),
+        }
+    }
+
+    /// Record the region drag that just finished as one undo step, unless
+    /// the region ended up where it started
+    pub fn finish_region_drag(&mut self) {
+        let DragState::DraggingRegion { region_id, start_beats, .. } = self.drag_state else {
+            return;
+        };
+        let (Some(before), Some(project)) = (self.region_drag_before.take(), self.project.as_ref()) else {
+            return;
+        };
+        let transport = &project.transport;
+        let moved = project.regions.iter().find(|r| r.id == region_id).is_some_and(|region| {
+            (transport.samples_to_beats(region.start), transport.samples_to_beats(region.end)) != start_beats
+        });
+        if moved {
+            let after = ArrangementSnapshot::capture(project);
+            self.record_edit(EditAction::Arrangement { label: "Move Region", before, after });
+        }
+    }
+
+    /// Drop a marker at the start of each region that doesn't have one,
+    /// named after the region, as one undo step
+    pub fn regions_to_markers(&mut self) -> usize {
+        let Some(project) = self.project.as_ref() else {
+            return 0;
+        };
+        let mut regions: Vec<&Region> = project.regions.iter().collect();
+        regions.sort_by_key(|r| r.start);
+        let added: Vec<Marker> = regions
+            .into_iter()
+            .filter(|region| !project.markers.iter().any(|m| m.position == region.start))
+            .map(|region| Marker::new(region.name.clone(), region.start))
+            .collect();
+        let count = added.len();
+        self.edit_markers("Regions to Markers", |markers| {
+            markers.extend(added);
+            count > 0
+        });
+        count
+    }
+
+    /// Change a track's effect chain as one undoable edit. `edit` returns
+    /// whether it changed anything.
+    fn edit_effects(
+        &mut self,
+        track_id: TrackId,
+        label: &'static str,
+        edit: impl FnOnce(&mut Vec<Effect>) -> anyhow::Result<bool>,
+    ) -> anyhow::Result<bool> {
+        let Some(track) = self.get_track_mut(track_id) else {
+            return Ok(false);
+        };
+        let before = track.effects.clone();
+        if !edit(&mut track.effects)? {
+            return Ok(false);
+        }
+        let after = track.effects.clone();
+        self.record_edit(EditAction::EditEffects { label, track_id, before, after });
+        Ok(true)
+    }
+
+    pub fn add_effect(&mut self, track_id: TrackId, kind: EffectKind) -> bool {
+        self.edit_effects(track_id, "Add Effect", |effects| {
+            effects.push(Effect::new(kind));
+            Ok(true)
+        })
+        .unwrap_or(false)
+    }
+
+    /// Log each effect that loaded as a pass-through placeholder, either
+    /// because this build doesn't know it or it names a plugin, which can't
+    /// be loaded yet
+    fn report_unavailable_effects(&self) {
+        let Some(project) = &self.project else { return };
+        for track in project.tracks.iter().chain(std::iter::once(&project.master_track)) {
+            for effect in &track.effects {
+                if let Some(unavailable) = &effect.unavailable {
+                    tracing::error!("⚠️ '{}' on '{}': {}, passing audio through", effect.label(), track.name, unavailable.reason);
+                } else if effect.plugin.is_some() {
+                    tracing::error!("⚠️ Plugin '{}' on '{}' can't be loaded, passing audio through", effect.label(), track.name);
+                }
+            }
+        }
+    }
+
+    pub fn remove_effect(&mut self, track_id: TrackId, effect_id: EffectId) -> bool {
+        self.edit_effects(track_id, "Remove Effect", |effects| {
+            let count = effects.len();
+            effects.retain(|e| e.id != effect_id);
+            Ok(effects.len() != count)
+        })
+        .unwrap_or(false)
+    }
+
+    pub fn toggle_effect_bypass(&mut self, track_id: TrackId, effect_id: EffectId) -> bool {
+        self.edit_effects(track_id, "Bypass Effect", |effects| {
+            let Some(effect) = effects.iter_mut().find(|e| e.id == effect_id) else {
+                return Ok(false);
+            };
+            effect.enabled = !effect.enabled;
+            Ok(true)
+        })
+        .unwrap_or(false)
+    }
+
+    /// Turn an effect's gain match on or off
+    pub fn toggle_effect_gain_match(&mut self, track_id: TrackId, effect_id: EffectId) -> bool {
+        self.edit_effects(track_id, "Gain Match Effect", |effects| {
+            let Some(effect) = effects.iter_mut().find(|e| e.id == effect_id) else {
+                return Ok(false);
+            };
+            effect.gain_match = !effect.gain_match;
+            Ok(true)
+        })
+        .unwrap_or(false)
+    }
+
+    /// Move an effect parameter by `steps` of its nudge amount
+    pub fn nudge_effect_param(&mut self, track_id: TrackId, effect_id: EffectId, param: usize, steps: f32) -> bool {
+        self.edit_effects(track_id, "Adjust Effect", |effects| {
+            let Some(effect) = effects.iter_mut().find(|e| e.id == effect_id) else {
+                return Ok(false);
+            };
+            let (Some(info), Some(value)) = (effect.kind.params().get(param), effect.kind.param(param)) else {
+                return Ok(false);
+            };
+            effect.kind.set_param(param, value + info.step * steps);
+            Ok(effect.kind.param(param) != Some(value))
+        })
+        .unwrap_or(false)
+    }
+
+    /// Move an effect's wet/dry mix by `steps` of 10%
+    pub fn nudge_effect_mix(&mut self, track_id: TrackId, effect_id: EffectId, steps: f32) -> bool {
+        self.edit_effects(track_id, "Adjust Effect Mix", |effects| {
+            let Some(effect) = effects.iter_mut().find(|e| e.id == effect_id) else {
+                return Ok(false);
+            };
+            let mix = ((effect.mix * 10.0).round() + steps).clamp(0.0, 10.0) / 10.0;
+            let changed = mix != effect.mix;
+            effect.mix = mix;
+            Ok(changed)
+        })
+        .unwrap_or(false)
+    }
+
+    /// Point a convolution reverb at an impulse response file, or at none
+    pub fn set_effect_impulse_response(&mut self, track_id: TrackId, effect_id: EffectId, path: Option<PathBuf>) -> bool {
+        self.edit_effects(track_id, "Set Impulse Response", |effects| {
+            let Some(effect) = effects.iter_mut().find(|e| e.id == effect_id) else {
+                return Ok(false);
+            };
+            let changed = effect.impulse_response != path;
+            effect.impulse_response = path;
+            Ok(changed)
+        })
+        .unwrap_or(false)
+    }
+
+    /// One effect of a track, or its whole chain, ready for the clipboard
+    pub fn copy_effects(&self, track_id: TrackId, only: Option<EffectId>) -> Option<EffectClipboard> {
+        let track = self.get_track(track_id)?;
+        let effects: Vec<Effect> = track
+            .effects
+            .iter()
+            .filter(|e| only.is_none() || only == Some(e.id))
+            .cloned()
+            .collect();
+        (!effects.is_empty()).then(|| EffectClipboard::from_effects(effects))
+    }
+
+    /// Paste copied effects onto a track. With `onto` set, only the
+    /// parameters of a single copied effect are written into that existing
+    /// effect, which must be the same kind. Otherwise the copies are added to
+    /// the end of the chain. Refusals are kept in `effect_error`.
+    pub fn paste_effects(&mut self, track_id: TrackId, clipboard: &EffectClipboard, onto: Option<EffectId>) -> bool {
+        let result = match onto {
+            Some(target_id) => self.edit_effects(track_id, "Paste Effect Parameters", |effects| {
+                let [source] = clipboard.effects.as_slice() else {
+                    anyhow::bail!("Copy a single effect to paste its parameters");
+                };
+                let Some(target) = effects.iter_mut().find(|e| e.id == target_id) else {
+                    return Ok(false);
+                };
+                target.paste_parameters(source)?;
+                Ok(true)
+            }),
+            None => self.edit_effects(track_id, "Paste Effects", |effects| {
+                effects.extend(clipboard.instantiate());
+                Ok(!clipboard.effects.is_empty())
+            }),
+        };
+        match result {
+            Ok(changed) => {
+                self.effect_error = None;
+                changed
+            }
+            Err(e) => {
+                tracing::error!("⚠️ Effect paste refused: {}", e);
+                self.effect_error = Some(e.to_string());
+                false
+            }
+        }
+    }
+
+    /// Clip shown in the clip editor: the first selected clip
+    pub fn edited_clip(&self) -> Option<(TrackId, &AudioClip)> {
+        let (track_id, clip_id) = *self.selected_clip_refs().first()?;
+        Some((track_id, self.get_clip(track_id, clip_id)?))
+    }
+
+    /// Source frames the clip editor shows at its zoom, centred on the
+    /// cursor, or on the clip when there's no cursor
+    pub fn clip_editor_view(&self, clip: &AudioClip, source_len: SampleTime) -> (SampleTime, SampleTime) {
+        let source_len = source_len.max(1);
+        let span = ((source_len as f64 * self.clip_editor_zoom.clamp(0.0, 1.0)) as SampleTime).clamp(1, source_len);
+        let centre = self.clip_editor_cursor.unwrap_or(clip.offset + clip.source_duration() / 2);
+        let start = centre.saturating_sub(span / 2).min(source_len - span);
+        (start, start + span)
+    }
+
+    /// Trim or fade the edited clip at the clip editor's cursor, as an
+    /// undoable edit. Returns the clip's track.
+    pub fn apply_clip_cursor_edit(&mut self, edit: ClipCursorEdit) -> Option<TrackId> {
+        let cursor = self.clip_editor_cursor?;
+        let (track_id, clip) = self.edited_clip().map(|(track_id, clip)| (track_id, clip.clone()))?;
+        let source_len = self.spectrograms.get(&clip.asset_path).map(|s| s.start + s.frames)?;
+        let source_end = clip.offset + clip.source_duration();
+        // Where the cursor falls in the clip, from its start on the timeline
+        let at = clip.source_to_timeline(cursor.saturating_sub(clip.offset)).min(clip.duration);
+
+        let loop_points = clip.loop_points.unwrap_or(ClipLoop { start: clip.offset, end: source_end.min(source_len) });
+        let after = match edit {
+            ClipCursorEdit::TrimStart => clip.with_source_range(cursor, source_end)?,
+            ClipCursorEdit::TrimEnd => clip.with_source_range(clip.offset, cursor.min(source_len))?,
+            ClipCursorEdit::FadeIn => AudioClip { fade_in: at.min(clip.duration.saturating_sub(clip.fade_out)), ..clip.clone() },
+            ClipCursorEdit::FadeOut => AudioClip { fade_out: (clip.duration - at).min(clip.duration.saturating_sub(clip.fade_in)), ..clip.clone() },
+            ClipCursorEdit::LoopStart => AudioClip { loop_points: Some(ClipLoop { start: cursor, ..loop_points }), ..clip.clone() },
+            ClipCursorEdit::LoopEnd => AudioClip { loop_points: Some(ClipLoop { end: cursor, ..loop_points }), ..clip.clone() },
+        };
+        if let Err(e) = after.validate_source_range(source_len) {
+            self.clip_editor_error = Some(e.to_string());
+            return None;
+        }
+        self.clip_editor_error = None;
+        self.commit_clip_editor_change(track_id, clip, after)
+    }
+
+    /// Remove the edited clip's loop. Returns the track to sync.
+    pub fn clear_edited_clip_loop(&mut self) -> Option<TrackId> {
+        let (track_id, clip) = self.edited_clip().map(|(track_id, clip)| (track_id, clip.clone()))?;
+        self.clip_editor_error = None;
+        let after = AudioClip { loop_points: None, ..clip.clone() };
+        self.commit_clip_editor_change(track_id, clip, after)
+    }
+
+    fn commit_clip_editor_change(&mut self, track_id: TrackId, clip: AudioClip, after: AudioClip) -> Option<TrackId> {
+        let unchanged = (after.start_time, after.duration, after.offset, after.fade_in, after.fade_out, after.loop_points)
+            == (clip.start_time, clip.duration, clip.offset, clip.fade_in, clip.fade_out, clip.loop_points);
+        if unchanged {
+            return None;
+        }
+        if self.is_clip_locked(track_id, clip.id) {
+            self.clip_editor_error = Some("The clip is locked; unlock it to edit it".to_string());
+            return None;
+        }
+        *self.get_clip_mut(track_id, clip.id)? = after.clone();
+        self.record_edit(EditAction::ModifyClips { changes: vec![(track_id, clip, after)] });
+        Some(track_id)
+    }
+
+    /// Start trimming a clip from its start or end edge
+    pub fn begin_clip_trim(&mut self, track_id: TrackId, clip_id: ClipId, is_start: bool, mouse_x: f32) {
+        if self.is_clip_locked(track_id, clip_id) {
+            return;
+        }
+        let Some(clip) = self.get_clip(track_id, clip_id).cloned() else {
+            return;
+        };
+        self.drag_state = DragState::DraggingClipEdge {
+            clip_id,
+            track_id,
+            is_start,
+            start_mouse_x: mouse_x,
+            before: Box::new(clip),
+        };
+    }
+
+    /// Follow a trim to `mouse_x`, snapping the dragged edge to the grid.
+    /// The edge stops at the ends of the clip's audio file.
+    pub fn drag_clip_trim(&mut self, mouse_x: f32) {
+        let DragState::DraggingClipEdge { clip_id, track_id, is_start, start_mouse_x, ref before } = self.drag_state else {
+            return;
+        };
+        let Some(project) = self.project.as_ref() else {
+            return;
+        };
+        let transport = &project.transport;
+        let edge = if is_start { before.start_time } else { before.end_time() };
+        let beat = self.snap_beat(transport.samples_to_beats(edge) + self.pixels_to_beats(mouse_x - start_mouse_x)).max(0.0);
+        let time = transport.beats_to_samples(beat);
+        // Until the file is loaded only what the clip already plays is known
+        let source_len = self.loaded_assets
+            .get(&before.asset_path)
+            .map(|asset| asset.asset_ref.duration_samples as SampleTime)
+            .unwrap_or(before.offset + before.source_duration());
+
+        if let Some(after) = before.with_edge_at(is_start, time, source_len) {
+            if let Some(clip) = self.get_clip_mut(track_id, clip_id) {
+                *clip = after;
+            }
+        }
+    }
+
+    /// Record the trim that just finished as one undo step. Returns the
+    /// track to sync, or None if the clip ended up as it was.
+    pub fn finish_clip_trim(&mut self) -> Option<TrackId> {
+        let DragState::DraggingClipEdge { clip_id, track_id, ref before, .. } = self.drag_state else {
+            return None;
+        };
+        let before = (**before).clone();
+        let after = self.get_clip(track_id, clip_id)?.clone();
+        if (after.start_time, after.duration, after.offset) == (before.start_time, before.duration, before.offset) {
+            return None;
+        }
+        self.record_edit(EditAction::ModifyClips { changes: vec![(track_id, before, after)] });
+        Some(track_id)
+    }
+
+    /// Base color of a track from the chosen palette, by its position in the project
+    pub fn track_color(&self, track_id: TrackId) -> Hsla {
+        let index = self.project.as_ref()
+            .and_then(|p| p.tracks.iter().position(|t| t.id == track_id))
+            .unwrap_or(0);
+        self.track_color_at(index)
+    }
+
+    pub fn track_color_at(&self, index: usize) -> Hsla {
+        let [r, g, b] = self.preferences.track_palette.color(index);
+        Hsla::from(Rgba { r, g, b, a: 1.0 })
+    }
+
+    /// Replace the edit preferences and write them to the user config.
+    /// Existing clips keep their fades.
+    pub fn set_preferences(&mut self, preferences: EditPreferences) {
+        self.preferences = preferences;
+        if let Err(e) = preferences.save(&self.preferences_path) {
+            tracing::error!("❌ Failed to save preferences: {}", e);
+        }
+    }
+
+    /// Step the insert silence length through 1, 2, 4 and 8 bars
+    pub fn cycle_insert_silence_bars(&mut self) {
+        self.insert_silence_bars = match self.insert_silence_bars {
+            1 => 2,
+            2 => 4,
+            4 => 8,
+            _ => 1,
+        };
+    }
+
+    /// Add a mute automation step at the playhead, muting or unmuting the
+    /// track from there on. Returns whether the lane changed.
+    pub fn set_mute_step_at_playhead(&mut self, track_id: TrackId, muted: bool) -> bool {
+        let playhead = self.selection.playhead_position;
+        self.edit_mute_lane(track_id, "Mute Automation", |lane, transport| {
+            lane.add_point(AutomationPoint {
+                id: uuid::Uuid::new_v4(),
+                time: transport.beats_to_samples(playhead),
+                value: if muted { 1.0 } else { 0.0 },
+                curve_type: CurveType::Hold,
+                tension: 0.0,
+            });
+        })
+    }
+
+    /// Remove every step from a track's mute automation
+    pub fn clear_mute_automation(&mut self, track_id: TrackId) -> bool {
+        self.edit_mute_lane(track_id, "Clear Mute Automation", |lane, _| lane.points.clear())
+    }
+
+    /// Turn a track's mute automation on or off without losing its steps
+    pub fn toggle_mute_automation(&mut self, track_id: TrackId) -> bool {
+        self.edit_mute_lane(track_id, "Toggle Mute Automation", |lane, _| lane.enabled = !lane.enabled)
+    }
+
+    fn edit_mute_lane(
+        &mut self,
+        track_id: TrackId,
+        label: &'static str,
+        edit: impl FnOnce(&mut AutomationLane, &Transport),
+    ) -> bool {
+        self.edit_automation_lane(track_id, AutomationParameter::Mute, label, edit)
+    }
+
+    fn edit_automation_lane(
+        &mut self,
+        track_id: TrackId,
+        parameter: AutomationParameter,
+        label: &'static str,
+        edit: impl FnOnce(&mut AutomationLane, &Transport),
+    ) -> bool {
+        let Some(ref mut project) = self.project else {
+            return false;
+        };
+        let before = ArrangementSnapshot::capture(project);
+        let transport = project.transport.clone();
+        let Some(track) = project.tracks.iter_mut().find(|t| t.id == track_id) else {
+            return false;
+        };
+        edit(track.get_automation_lane_mut(parameter), &transport);
+        let after = ArrangementSnapshot::capture(project);
+        self.record_edit(EditAction::Arrangement { label, before, after });
+        true
+    }
+
+    /// Beat and value an automation point edited to `beat` and `value` ends
+    /// up at. Time snaps to the grid and value to round steps when each is
+    /// turned on; `fine` skips the value snap while a modifier is held.
+    pub fn snap_automation_point(&self, parameter: AutomationParameter, beat: f64, value: f32, fine: bool) -> (f64, f32) {
+        let beat = if self.automation_time_snap {
+            let transport = self.project.as_ref().map(|p| p.transport.clone()).unwrap_or_default();
+            transport.snap_to_division(beat, self.snap_value.to_beats(), self.snap_value.feel())
         } else {
-            None
+            beat
+        };
+        let value = if self.automation_value_snap && !fine { parameter.snap_value(value) } else { value };
+        (beat.max(0.0), value)
+    }
+
+    /// Add a volume automation point at the playhead holding the curve's
+    /// current value, or unity on an empty lane
+    pub fn add_volume_point_at_playhead(&mut self, track_id: TrackId) -> bool {
+        let parameter = AutomationParameter::Volume;
+        let Some(track) = self.get_track(track_id) else {
+            return false;
+        };
+        let playhead = self.selection.playhead_position;
+        let time = self.project.as_ref().map_or(0, |p| p.transport.beats_to_samples(playhead));
+        let value = track.get_automation_lane(parameter).and_then(|lane| lane.curve_value_at(time)).unwrap_or(1.0);
+        let (beat, value) = self.snap_automation_point(parameter, playhead, value, false);
+        self.volume_lane_tracks.insert(track_id);
+        self.edit_automation_lane(track_id, parameter, "Add Automation Point", |lane, transport| {
+            lane.add_point(AutomationPoint {
+                id: uuid::Uuid::new_v4(),
+                time: transport.beats_to_samples(beat),
+                value,
+                curve_type: CurveType::Linear,
+                tension: 0.0,
+            });
+        })
+    }
+
+    /// Remove every point from a track's volume automation
+    pub fn clear_volume_automation(&mut self, track_id: TrackId) -> bool {
+        self.edit_automation_lane(track_id, AutomationParameter::Volume, "Clear Volume Automation", |lane, _| lane.points.clear())
+    }
+
+    /// Move the point at `index` of a lane to `beat` and `value`, snapped as
+    /// [`Self::snap_automation_point`] does. Points keep their time order,
+    /// so the point's new index is returned.
+    pub fn move_automation_point(
+        &mut self,
+        track_id: TrackId,
+        parameter: AutomationParameter,
+        index: usize,
+        beat: f64,
+        value: f32,
+        fine: bool,
+    ) -> Option<usize> {
+        let (beat, value) = self.snap_automation_point(parameter, beat, value, fine);
+        let project = self.project.as_mut()?;
+        let time = project.transport.beats_to_samples(beat);
+        let lane = project.tracks.iter_mut().find(|t| t.id == track_id)?.get_automation_lane_mut(parameter);
+        let mut point = (index < lane.points.len()).then(|| lane.points.remove(index))?;
+        point.time = time;
+        point.value = value;
+        // A point dropped onto another one replaces it, as when adding
+        let id = point.id;
+        lane.add_point(point);
+        lane.points.iter().position(|p| p.id == id)
+    }
+
+    /// Remove the point at `index` from a track's automation lane
+    pub fn remove_automation_point(&mut self, track_id: TrackId, parameter: AutomationParameter, index: usize) -> bool {
+        let exists = self.get_track(track_id)
+            .and_then(|track| track.get_automation_lane(parameter))
+            .is_some_and(|lane| index < lane.points.len());
+        exists && self.edit_automation_lane(track_id, parameter, "Delete Automation Point", |lane, _| {
+            lane.points.remove(index);
+        })
+    }
+
+    /// Start dragging the point at `index`, keeping the arrangement for undo
+    pub fn begin_automation_drag(&mut self, track_id: TrackId, parameter: AutomationParameter, index: usize, mouse: (f32, f32)) {
+        let Some(project) = self.project.as_ref() else {
+            return;
+        };
+        let Some(point) = project.tracks.iter()
+            .find(|t| t.id == track_id)
+            .and_then(|track| track.get_automation_lane(parameter))
+            .and_then(|lane| lane.points.get(index))
+        else {
+            return;
+        };
+        self.drag_state = DragState::DraggingAutomationPoint {
+            track_id,
+            param_type: parameter,
+            point_index: index,
+            start_mouse_x: mouse.0,
+            start_mouse_y: mouse.1,
+            start_beat: project.transport.samples_to_beats(point.time),
+            start_value: point.value,
+        };
+        self.automation_drag_before = Some(ArrangementSnapshot::capture(project));
+    }
+
+    /// Record the automation point drag that just finished as one undo
+    /// step, unless the point ended up where it started
+    pub fn finish_automation_drag(&mut self, moved: bool) {
+        let (Some(before), Some(project)) = (self.automation_drag_before.take(), self.project.as_ref()) else {
+            return;
+        };
+        if !moved {
+            return;
         }
+        let after = ArrangementSnapshot::capture(project);
+        self.record_edit(EditAction::Arrangement { label: "Move Automation Point", before, after });
     }
 
-    /// Import audio file to project
-    pub fn import_audio_file(&mut self, source_path: PathBuf) -> anyhow::Result<PathBuf> {
-        // Clone project_dir to avoid borrow issue
-        let proj_dir = self.project_dir.clone().ok_or_else(|| anyhow::anyhow!("No project directory"))?;
-        
-        let audio_dir = proj_dir.join("audio");
-        std::fs::create_dir_all(&audio_dir)?;
-        
-        if let Some(filename) = source_path.file_name() {
-            let dest = audio_dir.join(filename);
-            std::fs::copy(&source_path, &dest)?;
-            self.scan_audio_files(&proj_dir);
-            Ok(dest)
+    /// Start bending the segment after the point at `index`, keeping the
+    /// arrangement for undo. The last point has no segment to bend.
+    pub fn begin_automation_curve_drag(&mut self, track_id: TrackId, parameter: AutomationParameter, index: usize, mouse_y: f32) {
+        let Some(project) = self.project.as_ref() else {
+            return;
+        };
+        let Some(lane) = project.tracks.iter().find(|t| t.id == track_id).and_then(|track| track.get_automation_lane(parameter)) else {
+            return;
+        };
+        let (Some(start), Some(end)) = (lane.points.get(index), lane.points.get(index + 1)) else {
+            return;
+        };
+        self.drag_state = DragState::DraggingAutomationCurve {
+            track_id,
+            param_type: parameter,
+            point_index: index,
+            start_mouse_y: mouse_y,
+            start_tension: start.tension,
+            rising: parameter.lane_position(end.value) >= parameter.lane_position(start.value),
+        };
+        self.automation_drag_before = Some(ArrangementSnapshot::capture(project));
+    }
+
+    /// Bend the dragged segment as the mouse moves to `mouse_y`. Dragging up
+    /// raises the middle of the segment whichever way it runs.
+    pub fn drag_automation_curve(&mut self, mouse_y: f32) {
+        let DragState::DraggingAutomationCurve { track_id, param_type, point_index, start_mouse_y, start_tension, rising } = self.drag_state else {
+            return;
+        };
+        let delta = (start_mouse_y - mouse_y) / 100.0;
+        let tension = if rising { start_tension - delta } else { start_tension + delta };
+        if let Some(point) = self.project.as_mut()
+            .and_then(|project| project.tracks.iter_mut().find(|t| t.id == track_id))
+            .and_then(|track| track.get_automation_lane_mut(param_type).points.get_mut(point_index))
+        {
+            point.tension = tension.clamp(-1.0, 1.0);
+        }
+    }
+
+    /// Record the bend that just finished as one undo step. Returns the
+    /// track whose curve changed.
+    pub fn finish_automation_curve_drag(&mut self) -> Option<TrackId> {
+        let DragState::DraggingAutomationCurve { track_id, param_type, point_index, start_tension, .. } = self.drag_state else {
+            return None;
+        };
+        let tension = self.get_track(track_id)
+            .and_then(|track| track.get_automation_lane(param_type))
+            .and_then(|lane| lane.points.get(point_index))
+            .map(|point| point.tension);
+        let before = self.automation_drag_before.take()?;
+        if tension.is_none_or(|t| t == start_tension) {
+            return None;
+        }
+        let after = ArrangementSnapshot::capture(self.project.as_ref()?);
+        self.record_edit(EditAction::Arrangement { label: "Bend Automation Curve", before, after });
+        Some(track_id)
+    }
+
+    /// Give the segment after the point at `index` the next shape in
+    /// [`CurveType::ALL`]. Stepped lanes keep their steps.
+    pub fn cycle_automation_shape(&mut self, track_id: TrackId, parameter: AutomationParameter, index: usize) -> bool {
+        let exists = self.get_track(track_id)
+            .and_then(|track| track.get_automation_lane(parameter))
+            .is_some_and(|lane| index + 1 < lane.points.len());
+        !parameter.is_stepped() && exists && self.edit_automation_lane(track_id, parameter, "Change Automation Shape", |lane, _| {
+            let point = &mut lane.points[index];
+            point.curve_type = point.curve_type.next();
+        })
+    }
+
+    /// Take the bend out of the segment after the point at `index`
+    pub fn straighten_automation_curve(&mut self, track_id: TrackId, parameter: AutomationParameter, index: usize) -> bool {
+        let bent = self.get_track(track_id)
+            .and_then(|track| track.get_automation_lane(parameter))
+            .and_then(|lane| lane.points.get(index))
+            .is_some_and(|point| point.tension != 0.0);
+        bent && self.edit_automation_lane(track_id, parameter, "Straighten Automation Curve", |lane, _| {
+            lane.points[index].tension = 0.0;
+        })
+    }
+
+    /// Take a control change from a hardware controller at `time` on the
+    /// timeline. While learning, the controller is mapped to the parameter
+    /// waiting for it. A mapped controller writes automation on tracks in
+    /// Touch or Latch while the transport rolls and moves the parameter
+    /// itself otherwise. Returns the track that changed.
+    pub fn control_change(&mut self, channel: u8, controller: u8, value: u8, time: SampleTime) -> Option<TrackId> {
+        let project = self.project.as_mut()?;
+        if let Some((track_id, parameter)) = self.control_learn.take() {
+            automation_recording::learn_mapping(&mut project.control_mappings, ControlMapping { channel, controller, track_id, parameter });
+            self.dirty.mark_dirty();
+            return None;
+        }
+        let mapping = *project.control_mappings.iter().find(|m| m.channel == channel && m.controller == controller)?;
+        let position = automation_recording::controller_position(value);
+        let writes = self.is_playing && project.tracks.iter().any(|t| t.id == mapping.track_id && t.automation_mode.writes());
+        if writes && self.automation_pass_before.is_none() {
+            self.automation_pass_before = Some(ArrangementSnapshot::capture(project));
+        }
+        let sample_rate = project.sample_rate;
+        let track = project.tracks.iter_mut().find(|t| t.id == mapping.track_id)?;
+        let changed = if writes {
+            self.automation_recorder.control_change(track, mapping.parameter, time, position, sample_rate)
         } else {
-            Err(anyhow::anyhow!("Invalid file name"))
+            automation_recording::apply_control(track, mapping.parameter, mapping.parameter.lane_value(position))
+        };
+        if changed && !writes {
+            self.dirty.mark_dirty();
+        }
+        changed.then_some(mapping.track_id)
+    }
+
+    /// Move the controller passes on to `time` as the playhead advances,
+    /// or end them all once the transport has `stopped`. The passes become
+    /// one undo step when the last one ends. Returns the tracks whose
+    /// automation changed.
+    pub fn advance_automation_passes(&mut self, time: SampleTime, stopped: bool) -> Vec<TrackId> {
+        if !self.automation_recorder.is_recording() {
+            return Vec::new();
+        }
+        let Some(project) = self.project.as_mut() else {
+            return Vec::new();
+        };
+        let changed = if stopped {
+            self.automation_recorder.finish(&mut project.tracks, time)
+        } else {
+            self.automation_recorder.advance(&mut project.tracks, time)
+        };
+        if !self.automation_recorder.is_recording() {
+            if let Some(before) = self.automation_pass_before.take() {
+                let after = ArrangementSnapshot::capture(project);
+                self.record_edit(EditAction::Arrangement { label: "Automation Pass", before, after });
+            }
+        }
+        changed
+    }
+
+    /// Step a track through Read, Touch and Latch
+    pub fn cycle_automation_mode(&mut self, track_id: TrackId) {
+        if let Some(track) = self.project.as_mut().and_then(|p| p.tracks.iter_mut().find(|t| t.id == track_id)) {
+            track.automation_mode = track.automation_mode.next();
+            self.dirty.mark_dirty();
+        }
+    }
+
+    /// Forget the controller mapped to a track parameter
+    pub fn forget_control_mapping(&mut self, track_id: TrackId, parameter: AutomationParameter) {
+        if let Some(project) = self.project.as_mut() {
+            project.control_mappings.retain(|m| !(m.track_id == track_id && m.parameter == parameter));
+            self.dirty.mark_dirty();
+        }
+    }
+
+    /// Track offset in the inspector's unit, milliseconds or samples
+    pub fn track_offset_display(&self, track: &Track) -> String {
+        if self.track_offset_in_ms {
+            let rate = self.project.as_ref().map(|p| p.sample_rate).unwrap_or(SAMPLE_RATE);
+            format!("{:+.2} ms", track.track_offset_samples as f64 * 1000.0 / rate as f64)
+        } else {
+            format!("{:+} smp", track.track_offset_samples)
+        }
+    }
+
+    /// Shift a track's clips by `steps` of the inspector's unit, or back to
+    /// zero when `steps` is None. Returns whether the offset changed.
+    pub fn nudge_track_offset(&mut self, track_id: TrackId, steps: Option<i64>) -> bool {
+        let rate = self.project.as_ref().map(|p| p.sample_rate).unwrap_or(SAMPLE_RATE);
+        let step = if self.track_offset_in_ms { (rate as f64 / 1000.0).round() as i64 } else { 1 };
+        let Some(track) = self.get_track_mut(track_id) else {
+            return false;
+        };
+        let offset = steps.map(|n| track.track_offset_samples + n * step).unwrap_or(0);
+        let changed = offset != track.track_offset_samples;
+        track.track_offset_samples = offset;
+        changed
+    }
+
+    /// Mirror the transport loop into the beat-based loop selection, after
+    /// an edit moved it
+    fn refresh_loop_selection(&mut self) {
+        let Some(ref project) = self.project else {
+            return;
+        };
+        let transport = &project.transport;
+        self.is_looping = transport.loop_enabled;
+        if self.selection.loop_start.is_some() || transport.loop_enabled {
+            self.selection.loop_start = Some(transport.samples_to_beats(transport.loop_start));
+            self.selection.loop_end = Some(transport.samples_to_beats(transport.loop_end));
         }
     }
 
@@ -558,17 +4502,23 @@ impl DawUiState {
         }
     }
 
-    /// Get effective mute state (considering solo)
+    /// Get effective mute state (considering solo, solo safe and solo isolate)
     pub fn is_track_effectively_muted(&self, track_id: TrackId) -> bool {
-        if let Some(track) = self.get_track(track_id) {
-            if self.solo_tracks.is_empty() {
-                track.muted
-            } else {
-                !self.solo_tracks.contains(&track_id)
-            }
-        } else {
-            false
-        }
+        let Some(project) = self.project.as_ref() else {
+            return false;
+        };
+        let Some(track) = project.tracks.iter().find(|t| t.id == track_id) else {
+            return false;
+        };
+
+        let soloed = |t: &Track| t.active && (t.solo || self.solo_tracks.contains(&t.id));
+        let any_solo = project.tracks.iter().any(|t| soloed(t) && !t.solo_isolate);
+        let vca = track.vca.and_then(|id| project.tracks.iter().find(|t| t.id == id && t.active && t.track_type == TrackType::Vca));
+        let soloed_by_vca = vca.is_some_and(|v| v.solo);
+        !track.active
+            || track.muted
+            || vca.is_some_and(|v| v.muted)
+            || (!soloed(track) && !soloed_by_vca && track.solo_muted(any_solo))
     }
 
     /// Load audio asset and cache it for duration info
@@ -592,4 +4542,333 @@ impl DawUiState {
     pub fn get_audio_duration_samples(&self, path: &PathBuf) -> Option<u64> {
         self.loaded_assets.get(path).map(|asset| asset.asset_ref.duration_samples as u64)
     }
+
+    /// How a loaded asset's source file differs from the project format.
+    /// Empty until the asset has been loaded.
+    pub fn asset_format_mismatches(&self, path: &PathBuf) -> Vec<FormatMismatch> {
+        let rate = self.project.as_ref().map(|p| p.sample_rate).unwrap_or(SAMPLE_RATE);
+        self.loaded_assets
+            .get(path)
+            .map(|asset| asset.asset_ref.source.mismatches(rate))
+            .unwrap_or_default()
+    }
+
+    /// What was repaired in a loaded asset's samples, one line per problem.
+    /// Empty for a clean file or one that hasn't loaded.
+    pub fn asset_health(&self, path: &PathBuf) -> Vec<String> {
+        self.loaded_assets
+            .get(path)
+            .map(|asset| asset.asset_ref.health.descriptions(asset.asset_ref.sample_rate))
+            .unwrap_or_default()
+    }
+
+    /// Point every clip using `from` at `to` as one undoable edit. Returns the
+    /// tracks that changed.
+    pub fn relink_asset(&mut self, from: &PathBuf, to: PathBuf) -> Vec<TrackId> {
+        let Some(ref mut project) = self.project else {
+            return Vec::new();
+        };
+        let before = ArrangementSnapshot::capture(project);
+        let mut changed = Vec::new();
+        for track in &mut project.tracks {
+            for clip in track.clips.iter_mut().filter(|c| &c.asset_path == from) {
+                clip.asset_path = to.clone();
+                if !changed.contains(&track.id) {
+                    changed.push(track.id);
+                }
+            }
+        }
+        if !changed.is_empty() {
+            let after = ArrangementSnapshot::capture(project);
+            self.record_edit(EditAction::Arrangement { label: "Convert Audio Format", before, after });
+        }
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::daw_editor::daw_engine::test_support::temp_dir;
+
+    /// State with one audio track holding a locked clip and an unlocked one
+    /// a bar later, both selected
+    fn state_with_locked_clip() -> (DawUiState, TrackId, ClipId, ClipId) {
+        let mut state = DawUiState::new();
+        state.preferences = EditPreferences::default();
+        let mut project = DawProject::new("Locks");
+        let mut track = Track::new("Audio", TrackType::Audio);
+        let locked = AudioClip { locked: true, ..AudioClip::new("locked.wav".into(), 0, 48_000) };
+        let free = AudioClip::new("free.wav".into(), 96_000, 48_000);
+        let (track_id, locked_id, free_id) = (track.id, locked.id, free.id);
+        track.clips = vec![locked, free];
+        project.tracks.push(track);
+        state.project = Some(project);
+        state.selection.selected_clip_ids = [locked_id, free_id].into_iter().collect();
+        (state, track_id, locked_id, free_id)
+    }
+
+    fn span(state: &DawUiState, track_id: TrackId, clip_id: ClipId) -> Option<(SampleTime, SampleTime, SampleTime, SampleTime)> {
+        state.get_clip(track_id, clip_id).map(|c| (c.start_time, c.duration, c.offset, c.fade_out))
+    }
+
+    #[test]
+    fn test_move_and_delete_leave_locked_clips_alone() {
+        let (mut state, track_id, locked, free) = state_with_locked_clip();
+        assert_eq!(state.nudge_selected_clips(1.0), vec![track_id]);
+        assert_eq!(span(&state, track_id, locked), Some((0, 48_000, 0, 0)));
+        assert_eq!(state.get_clip(track_id, free).map(|c| c.start_time), Some(120_000));
+
+        state.begin_clip_drag(track_id, free, 5.0, (0.0, 0.0));
+        state.shift_selected_clips(free, 5_000);
+        assert_eq!(state.get_clip(track_id, locked).map(|c| c.start_time), Some(0));
+        state.drag_state = DragState::None;
+
+        assert_eq!(state.delete_selected_clips(), vec![track_id]);
+        assert!(state.get_clip(track_id, free).is_none());
+        assert_eq!(span(&state, track_id, locked), Some((0, 48_000, 0, 0)));
+        assert!(state.selection.selected_clip_ids.contains(&locked));
+    }
+
+    #[test]
+    fn test_group_drag_moves_from_where_clips_started() {
+        let mut state = DawUiState::new();
+        state.preferences = EditPreferences::default();
+        let mut project = DawProject::new("Drag");
+        let mut track = Track::new("Audio", TrackType::Audio);
+        let early = AudioClip::new("early.wav".into(), 30_000, 10_000);
+        let dragged = AudioClip::new("dragged.wav".into(), 96_000, 10_000);
+        let (track_id, early_id, dragged_id) = (track.id, early.id, dragged.id);
+        track.clips = vec![early, dragged];
+        project.tracks.push(track);
+        state.project = Some(project);
+        state.selection.selected_clip_ids = [early_id, dragged_id].into_iter().collect();
+        let start = |state: &DawUiState, clip_id| state.get_clip(track_id, clip_id).map(|c| c.start_time);
+
+        // Dragged far enough left that the early clip stops at zero, then
+        // partway back, where the early clip follows from where it began
+        state.begin_clip_drag(track_id, dragged_id, 4.0, (0.0, 0.0));
+        state.drag_clip_to(track_id, dragged_id, 2.0);
+        assert_eq!((start(&state, early_id), start(&state, dragged_id)), (Some(0), Some(48_000)));
+        state.drag_clip_to(track_id, dragged_id, 3.5);
+        assert_eq!((start(&state, early_id), start(&state, dragged_id)), (Some(18_000), Some(84_000)));
+
+        // Undo puts back where the drag began
+        assert_eq!(state.record_selection_move(), vec![track_id]);
+        state.drag_state = DragState::None;
+        state.undo();
+        assert_eq!((start(&state, early_id), start(&state, dragged_id)), (Some(30_000), Some(96_000)));
+    }
+
+    #[test]
+    fn test_magnetic_snap_pairs_either_edge_where_tracks_are_heard() {
+        let mut state = DawUiState::new();
+        state.magnetic_snap = true;
+        state.viewport.zoom = 100.0;
+        let mut project = DawProject::new("Magnet");
+        // A beat long clip dragged past a two beat one on a track heard half
+        // a beat late, so its edges sound at 4.5 and 6.5
+        let mut dragged = Track::new("Dragged", TrackType::Audio);
+        let clip = AudioClip::new("dragged.wav".into(), 0, 24_000);
+        let mut other = Track::new("Other", TrackType::Audio);
+        other.track_offset_samples = 12_000;
+        other.clips.push(AudioClip::new("other.wav".into(), 96_000, 48_000));
+        let (track_id, clip_id) = (dragged.id, clip.id);
+        dragged.clips.push(clip);
+        project.tracks = vec![dragged, other];
+        state.project = Some(project);
+
+        assert_eq!(state.snap_clip_drag(track_id, clip_id, 4.52), (4.5, Some(4.5)));
+        assert_eq!(state.snap_clip_drag(track_id, clip_id, 6.47), (6.5, Some(6.5)));
+        assert_eq!(state.snap_clip_drag(track_id, clip_id, 3.53), (3.5, Some(4.5)));
+        assert_eq!(state.snap_clip_drag(track_id, clip_id, 5.46), (5.5, Some(6.5)));
+        assert_eq!(state.snap_clip_drag(track_id, clip_id, 5.0).1, None);
+
+        // The dragged track's own offset counts too
+        state.project.as_mut().unwrap().tracks[0].track_offset_samples = 12_000;
+        assert_eq!(state.snap_clip_drag(track_id, clip_id, 3.98), (4.0, Some(4.0)));
+    }
+
+    #[test]
+    fn test_fill_bars_follow_the_beat_value() {
+        let mut state = DawUiState::new();
+        state.preferences = EditPreferences::default();
+        let mut project = DawProject::new("Fill");
+        project.transport.time_signature_numerator = 6;
+        project.transport.time_signature_denominator = 8;
+        let mut track = Track::new("Audio", TrackType::Audio);
+        let clip = AudioClip::new("loop.wav".into(), 0, 24_000);
+        let (track_id, clip_id) = (track.id, clip.id);
+        track.clips.push(clip);
+        project.tracks.push(track);
+        state.project = Some(project);
+        state.selection.selected_clip_ids = [clip_id].into_iter().collect();
+
+        // A bar of 6/8 is three quarter notes, so two bars run six
+        state.fill_bars = Some(2);
+        state.fill_with_clip_loop = true;
+        assert_eq!(state.duplicate_selected_to_fill(), vec![track_id]);
+        assert_eq!(state.get_clip(track_id, clip_id).map(|c| c.end_time()), Some(144_000));
+    }
+
+    #[test]
+    fn test_arm_follows_selection_skips_inactive_tracks_and_takes() {
+        let mut state = DawUiState::new();
+        state.preferences = EditPreferences { arm_follows_selection: true, ..EditPreferences::default() };
+        let mut project = DawProject::new("Arm");
+        let vocal = Track::new("Vocal", TrackType::Audio);
+        let guitar = Track::new("Guitar", TrackType::Audio);
+        let inactive = Track { active: false, ..Track::new("Spare", TrackType::Audio) };
+        let (vocal_id, guitar_id, inactive_id) = (vocal.id, guitar.id, inactive.id);
+        project.tracks = vec![vocal, guitar, inactive];
+        state.project = Some(project);
+        let armed = |state: &DawUiState, id| state.get_track(id).map(|t| t.record_armed);
+
+        state.selection.selected_track_ids = [vocal_id, inactive_id].into_iter().collect();
+        assert_eq!(state.arm_selected_tracks(), vec![vocal_id]);
+        assert_eq!((armed(&state, vocal_id), armed(&state, inactive_id)), (Some(true), Some(false)));
+
+        // Selecting elsewhere mid-take leaves the armed tracks recording
+        state.is_recording = true;
+        state.selection.selected_track_ids = [guitar_id].into_iter().collect();
+        assert!(state.arm_selected_tracks().is_empty());
+        assert_eq!((armed(&state, vocal_id), armed(&state, guitar_id)), (Some(true), Some(false)));
+    }
+
+    #[test]
+    fn test_failed_save_keeps_project_dirty() {
+        let mut state = DawUiState::new();
+        state.project = Some(DawProject::new("Test"));
+        let dir = temp_dir();
+        state.project_path = Some(dir.join("missing").join("song.pdaw"));
+        state.dirty.mark_dirty();
+
+        let rx = state.begin_background_save().unwrap();
+        assert!(state.is_saving());
+        state.finish_background_save(futures::executor::block_on(rx).unwrap());
+        assert!(!state.is_saving());
+        assert!(state.save_error.is_some());
+        assert!(state.dirty.is_dirty());
+
+        // A later save that gets through cleans it
+        state.finish_background_save(Ok(state.dirty.revision()));
+        assert!(!state.dirty.is_dirty());
+        assert!(state.save_error.is_none());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_trim_leaves_locked_clips_alone() {
+        let (mut state, track_id, locked, _) = state_with_locked_clip();
+        state.begin_clip_trim(track_id, locked, false, 200.0);
+        state.drag_clip_trim(20.0);
+        assert_eq!(span(&state, track_id, locked), Some((0, 48_000, 0, 0)));
+        assert_eq!(state.finish_clip_trim(), None);
+    }
+
+    #[test]
+    fn test_overlap_resolution_leaves_locked_clips_alone() {
+        let (mut state, track_id, locked, _) = state_with_locked_clip();
+        state.preferences.auto_crossfade = true;
+        state.get_track_mut(track_id).unwrap().overlap_mode = OverlapMode::Replace;
+
+        // A clip dropped over the locked one cuts nothing out of it and
+        // doesn't fade it
+        let dropped = AudioClip::new("dropped.wav".into(), 24_000, 48_000);
+        let dropped_id = dropped.id;
+        assert!(state.place_new_clip(track_id, dropped).is_some());
+        assert_eq!(span(&state, track_id, locked), Some((0, 48_000, 0, 0)));
+        assert!(state.get_clip(track_id, dropped_id).is_some());
+
+        // Nothing is placed on a locked track
+        state.get_track_mut(track_id).unwrap().locked = true;
+        assert_eq!(state.place_new_clip(track_id, AudioClip::new("late.wav".into(), 0, 1_000)), None);
+    }
+
+    #[test]
+    fn test_insert_silence_and_trim_leave_locked_clips_alone() {
+        let (mut state, track_id, locked, free) = state_with_locked_clip();
+
+        // Silence inside the locked clip would split it
+        state.selection.playhead_position = 1.0;
+        let error = state.insert_silence_at_playhead().unwrap_err();
+        assert_eq!(error.to_string(), "The clip is locked; unlock it to edit it");
+        assert_eq!(span(&state, track_id, locked), Some((0, 48_000, 0, 0)));
+        assert_eq!(state.get_clip(track_id, free).map(|c| c.start_time), Some(96_000));
+
+        // Past it only the free clip moves, by a bar
+        state.selection.playhead_position = 3.0;
+        assert_eq!(state.insert_silence_at_playhead().unwrap(), vec![track_id]);
+        assert_eq!(span(&state, track_id, locked), Some((0, 48_000, 0, 0)));
+        assert_eq!(state.get_clip(track_id, free).map(|c| c.start_time), Some(192_000));
+
+        // On a locked track every clip is locked, and its automation too
+        let track = state.get_track_mut(track_id).unwrap();
+        track.locked = true;
+        track.get_automation_lane_mut(AutomationParameter::Volume).add_point(AutomationPoint {
+            id: uuid::Uuid::new_v4(),
+            time: 480_000,
+            value: 0.5,
+            curve_type: CurveType::Linear,
+            tension: 0.0,
+        });
+        assert!(state.insert_silence_at_playhead().is_err());
+        assert_eq!(state.get_clip(track_id, free).map(|c| c.start_time), Some(192_000));
+        assert_eq!(state.trim_project_end().unwrap_err().to_string(), "The track is locked; unlock it to edit it");
+        let lane = state.get_track(track_id).unwrap().get_automation_lane(AutomationParameter::Volume).unwrap();
+        assert_eq!(lane.points.last().map(|p| p.time), Some(480_000));
+    }
+
+    #[test]
+    fn test_marquee_selects_automation_points() {
+        let mut state = DawUiState::new();
+        let mut project = DawProject::new("Marquee");
+        let mut ids = Vec::new();
+        for name in ["Vocal", "Bass"] {
+            let mut track = Track::new(name, TrackType::Audio);
+            track.clips.push(AudioClip::new("take.wav".into(), 0, 96_000));
+            let lane = track.get_automation_lane_mut(AutomationParameter::Volume);
+            for beat in 0..4u64 {
+                lane.add_point(AutomationPoint {
+                    id: uuid::Uuid::new_v4(),
+                    time: beat * 24_000,
+                    value: 0.5,
+                    curve_type: CurveType::Linear,
+                    tension: 0.0,
+                });
+            }
+            ids.push(track.id);
+            project.tracks.push(track);
+        }
+        state.project = Some(project);
+        let (vocal, bass) = (ids[0], ids[1]);
+        let x = |beats: f64| state.beats_to_pixels(beats);
+        let (half, two_and_half, three_and_half) = (x(0.5), x(2.5), x(3.5));
+        let volume = |track_id: TrackId, index: usize| (track_id, AutomationParameter::Volume, index);
+        let target = MarqueeTarget::AutomationPoints(AutomationParameter::Volume);
+
+        // The points between the edges, on every track spanned, and no clips
+        state.begin_marquee(target, vocal, half, MarqueeMode::Replace);
+        state.update_marquee(bass, two_and_half);
+        state.drag_state = DragState::None;
+        assert_eq!(state.selection.selected_automation_points, vec![volume(vocal, 1), volume(vocal, 2), volume(bass, 1), volume(bass, 2)]);
+        assert!(state.selection.selected_clip_ids.is_empty());
+
+        // Shift adds, keeping what was selected
+        state.begin_marquee(target, vocal, two_and_half, MarqueeMode::Add);
+        state.update_marquee(vocal, three_and_half);
+        state.drag_state = DragState::None;
+        assert_eq!(state.selection.selected_automation_points.len(), 5);
+        assert!(state.selection.selected_automation_points.contains(&volume(vocal, 3)));
+
+        // Ctrl toggles the points under it
+        state.begin_marquee(target, bass, half, MarqueeMode::Toggle);
+        state.update_marquee(bass, three_and_half);
+        state.drag_state = DragState::None;
+        assert_eq!(state.selection.selected_automation_points, vec![volume(vocal, 1), volume(vocal, 2), volume(vocal, 3), volume(bass, 3)]);
+
+        // A marquee on mute breakpoints finds none where the lane doesn't exist
+        state.begin_marquee(MarqueeTarget::AutomationPoints(AutomationParameter::Mute), vocal, half, MarqueeMode::Replace);
+        assert!(state.selection.selected_automation_points.is_empty());
+    }
 }
@@ -1,4 +1,5 @@
 use super::*;
+use crate::daw_editor::daw_engine::audio_types::{ChannelMode, FormatMismatch};
 pub use gpui::*;
 pub use gpui::prelude::FluentBuilder;
 use ui::{
@@ -6,96 +7,417 @@ use ui::{
     scroll::{Scrollbar, ScrollbarAxis}, PixelsExt, h_virtual_list};
 
 pub fn render_clip(
-    clip: &crate::daw_editor::audio_types::AudioClip,
+    clip: &crate::daw_editor::daw_engine::audio_types::AudioClip,
     track_id: uuid::Uuid,
     state: &DawUiState,
     cx: &mut Context<DawPanel>,
 ) -> impl IntoElement {
-    let tempo = state.get_tempo();
-    let x = state.beats_to_pixels(clip.start_beat(tempo));
-    let width = state.beats_to_pixels(clip.duration_beats(tempo));
+    let (tempo, rate) = (state.get_tempo(), state.sample_rate());
+    let x = state.beats_to_pixels(clip.start_beat(tempo, rate));
+    let width = state.beats_to_pixels(clip.duration_beats(tempo, rate));
     let is_selected = state.selection.selected_clip_ids.contains(&clip.id);
+    let is_focused = state.keyboard_focus == Some(KeyboardFocus::Clip(track_id, clip.id));
     let clip_id = clip.id;
+    let locked = state.is_clip_locked(track_id, clip_id);
 
-    let file_name = std::path::Path::new(&clip.asset_path)
-        .file_stem()
-        .map(|s| s.to_string_lossy().into_owned())
-        .unwrap_or_else(|| "Clip".to_string());
+    let name = clip.display_name();
+    let title = match state.clip_rename {
+        Some(ref rename) if rename.clip == clip_id => format!("{}|", rename.text),
+        _ if clip.muted => format!("{} (muted)", name),
+        _ => name.clone(),
+    };
 
-    // Get track color for clip coloring
-    let track_idx = state.project.as_ref()
-        .and_then(|p| p.tracks.iter().position(|t| t.id == track_id))
-        .unwrap_or(0);
-
-    // Generate consistent color per track
-    let track_hue = (track_idx as f32 * 137.5) % 360.0; // Golden angle
-    let clip_color = hsla(track_hue / 360.0, 0.5, 0.45, 1.0);
-    let clip_border_color = hsla(track_hue / 360.0, 0.7, 0.35, 1.0);
+    // Clips take their track's palette color
+    let base = state.track_color(track_id);
+    let high_contrast = state.preferences.high_contrast;
+    let waveform_style = state.preferences.waveform;
+    // Muted clips are drawn washed out so they read as inactive
+    let (clip_color, clip_border_color) = if clip.muted {
+        (hsla(base.h, 0.1, 0.35, 0.5), hsla(base.h, 0.15, 0.3, 0.6))
+    } else {
+        (base, hsla(base.h, (base.s + 0.2).min(1.0), (base.l - 0.1).max(0.0), 1.0))
+    };
+    // High contrast selection uses the foreground color, which always stands
+    // out from the background, and a brighter fill
+    let selection_color = if high_contrast { cx.theme().foreground } else { cx.theme().accent };
+    let selected_fill = if high_contrast && is_selected {
+        hsla(clip_color.h, clip_color.s, (clip_color.l + 0.15).min(0.9), clip_color.a)
+    } else {
+        clip_color
+    };
+    let focus_color = if high_contrast { cx.theme().accent } else { cx.theme().foreground };
 
     let track_height = *state.track_heights.get(&track_id)
         .unwrap_or(&state.viewport.track_height);
 
+    // Fade regions in pixels, proportional to the clip width
+    let samples_to_px = |samples: u64| if clip.duration > 0 {
+        width * samples as f32 / clip.duration as f32
+    } else {
+        0.0
+    };
+    let fade_in_px = samples_to_px(clip.fade_in);
+
+    // The visible part of the source as fractions of its length; clip
+    // positions are at the project rate, peaks at the rate they were loaded
+    let project_rate = state.project.as_ref().map(|p| p.sample_rate as f64).unwrap_or(48000.0);
+    let waveform = state.waveforms.get(&clip.asset_path).map(|peaks| {
+        let length = peaks.duration_secs().max(f64::EPSILON);
+        let start = clip.offset as f64 / project_rate / length;
+        let end = (clip.offset + clip.source_duration()) as f64 / project_rate / length;
+        (peaks.clone(), start, end)
+    });
+    let fade_out_px = samples_to_px(clip.fade_out);
+    let mismatches = state.asset_format_mismatches(&clip.asset_path);
+    // NaN or clipped samples found as the file loaded
+    let health_badge = state.loaded_assets
+        .get(&clip.asset_path)
+        .map(|asset| asset.asset_ref.health.badge())
+        .filter(|badge| !badge.is_empty());
+    // Peaks merge all channels, so only a mono file played as stereo is drawn
+    // as two lanes; clips folded to mono get a label instead
+    let source_channels = state.loaded_assets.get(&clip.asset_path).map(|a| a.asset_ref.channels);
+    let stereo_lanes = clip.channel_mode == ChannelMode::Stereo && source_channels == Some(1);
+    let channel_label = match clip.channel_mode {
+        ChannelMode::AsRecorded => None,
+        ChannelMode::Stereo => Some("ST"),
+        ChannelMode::MonoSum => Some("L+R"),
+        ChannelMode::MonoLeft => Some("L"),
+        ChannelMode::MonoRight => Some("R"),
+    };
+
+    // Short clips keep a minimum hit region so they stay clickable at any zoom
+    let is_thin = width < MIN_CLIP_HIT_WIDTH;
+    let hit_width = width.max(MIN_CLIP_HIT_WIDTH);
+    let hit_x = x - (hit_width - width) / 2.0;
+
     div()
         .id(ElementId::Name(format!("clip-{}", clip_id).into()))
         .absolute()
-        .left(px(x))
+        .left(px(hit_x))
         .top(px(4.0))
-        .w(px(width))
+        .w(px(hit_width))
         .h(px(track_height - 8.0))
         .rounded_sm()
         .overflow_hidden()
         .cursor_pointer()
-        .when(is_selected, |d| {
-            d.border_2().border_color(cx.theme().accent).shadow_lg()
+        .when(!is_thin, |d| {
+            d.when(is_selected, |d| {
+                d.border_2().border_color(selection_color).shadow_lg()
+            })
+            .when(!is_selected, |d| {
+                d.border_1().border_color(clip_border_color)
+            })
+            .bg(selected_fill)
+            .hover(|d| d.bg(selected_fill.opacity(0.9)))
+            // Focus ring for keyboard navigation, drawn over the selection border
+            .when(is_focused, |d| d.border_2().border_color(focus_color))
         })
-        .when(!is_selected, |d| {
-            d.border_1().border_color(clip_border_color)
+        .when(is_thin && is_focused, |d| d.border_1().border_color(focus_color))
+        .when(is_thin, |d| {
+            d.child(
+                div()
+                    .absolute()
+                    .top_0()
+                    .left(px((hit_width - width) / 2.0))
+                    .w(px(width.max(1.0)))
+                    .h_full()
+                    .bg(if is_selected { selection_color } else { clip_color })
+            )
         })
-        .bg(clip_color)
-        .hover(|d| d.bg(clip_color.opacity(0.9)))
-        .on_click(cx.listener(move |this, _event: &ClickEvent, _window, cx| {
-            this.state.select_clip(clip_id, false);
-            cx.notify();
-        }))
-        // Make clips draggable with mouse down
-        .on_mouse_down(gpui::MouseButton::Left, cx.listener({
-            let start_beat = clip.start_beat(tempo);
-            move |this, event: &MouseDownEvent, _window, cx| {
-                // Use proper coordinate conversion: window → element
-                let element_pos = DawPanel::window_to_timeline_pos(event.position, this);
-                let mouse_x = element_pos.x.as_f32();
-                let clip_x = x;
-
-                this.state.drag_state = DragState::DraggingClip {
-                    clip_id,
-                    track_id,
-                    start_beat,
-                    mouse_offset: (mouse_x - clip_x, 0.0),
-                };
-                // Also select the clip
-                this.state.select_clip(clip_id, false);
+        // Select the topmost clip under the pointer (or cycle through stacked
+        // clips on repeated clicks) and start dragging it
+        .on_mouse_down(gpui::MouseButton::Left, cx.listener(move |this, event: &MouseDownEvent, _window, cx| {
+            // Don't start a marquee on the track underneath
+            cx.stop_propagation();
+
+            // Use proper coordinate conversion: window → element
+            let element_pos = DawPanel::window_to_timeline_pos(event.position, this);
+            let mouse_x = element_pos.x.as_f32();
+            let multi = event.modifiers.shift;
+
+            // Double-click opens the properties of the clip picked by the first click
+            if event.click_count >= 2 {
+                let target = this.state
+                    .clips_at(track_id, mouse_x)
+                    .into_iter()
+                    .find(|id| this.state.selection.selected_clip_ids.contains(id))
+                    .unwrap_or(clip_id);
+                this.state.drag_state = DragState::None;
+                this.state.open_clip_properties(track_id, target);
+                cx.notify();
+                return;
+            }
+
+            let selected = this.state
+                .select_clip_at(track_id, mouse_x, multi)
+                .unwrap_or(clip_id);
+            // Locked clips can be selected, e.g. to copy them, but not dragged
+            if this.state.is_clip_locked(track_id, selected) {
                 cx.notify();
+                return;
             }
+
+            let (tempo, rate) = (this.state.get_tempo(), this.state.sample_rate());
+            if let Some(start_beat) = this.state.get_clip(track_id, selected).map(|c| c.start_beat(tempo, rate)) {
+                let clip_x = this.state.beats_to_pixels(start_beat);
+                this.state.begin_clip_drag(track_id, selected, start_beat, (mouse_x - clip_x, 0.0));
+                this.state.drag_pointer = event.position;
+            }
+            cx.notify();
+        }))
+        .when(!is_thin, |d| {
+            d.child(
+                v_flex()
+                    .size_full()
+                    .px_2()
+                    .py_1()
+                    .gap_1()
+                    .child(
+                        h_flex()
+                            .gap_1()
+                            .min_w_0()
+                            .child(
+                                // Long names are cut short, with the full name on hover
+                                div()
+                                    .id(ElementId::Name(format!("clip-title-{}", clip_id).into()))
+                                    .min_w_0()
+                                    .overflow_hidden()
+                                    .whitespace_nowrap()
+                                    .text_ellipsis()
+                                    .text_xs()
+                                    .font_semibold()
+                                    .text_color(cx.theme().background) // Contrast with clip color
+                                    .when(clip.muted, |d| d.italic())
+                                    .child(title)
+                                    .tooltip(move |window, cx| ui::tooltip::Tooltip::new(name.clone()).build(window, cx))
+                                    // Double-clicking the name renames the clip; the
+                                    // rest of the clip opens its properties
+                                    .on_mouse_down(gpui::MouseButton::Left, cx.listener(move |this, event: &MouseDownEvent, _window, cx| {
+                                        if event.click_count >= 2 {
+                                            cx.stop_propagation();
+                                            this.state.drag_state = DragState::None;
+                                            this.state.begin_clip_rename(track_id, clip_id);
+                                            cx.notify();
+                                        }
+                                    }))
+                            )
+                            .when(locked, |d| {
+                                d.child(
+                                    div()
+                                        .text_xs()
+                                        .font_semibold()
+                                        .text_color(cx.theme().background.opacity(0.8))
+                                        .child("LOCK")
+                                )
+                            })
+                            .when_some(channel_label, |d, label| {
+                                d.child(
+                                    div()
+                                        .text_xs()
+                                        .text_color(cx.theme().background.opacity(0.8))
+                                        .child(label)
+                                )
+                            })
+                            .when(!mismatches.is_empty(), |d| {
+                                d.child(render_format_badge(&mismatches, clip.asset_path.clone(), cx))
+                            })
+                            .when_some(health_badge, |d, badge| {
+                                d.child(
+                                    div()
+                                        .px_1()
+                                        .rounded_sm()
+                                        .text_xs()
+                                        .bg(cx.theme().danger.opacity(0.85))
+                                        .text_color(cx.theme().background)
+                                        .child(badge)
+                                )
+                            })
+                    )
+                    .child(
+                        div()
+                            .flex_1()
+                            .relative()
+                            // Waveform with track-colored tint, or a placeholder while peaks load
+                            .child(match waveform {
+                                Some((peaks, start, end)) if stereo_lanes => v_flex()
+                                    .size_full()
+                                    .child(div().flex_1().relative().child(super::waveform::render_waveform(&peaks, start, end, width, clip_color, waveform_style)))
+                                    .child(div().flex_1().relative().child(super::waveform::render_waveform(&peaks, start, end, width, clip_color, waveform_style)))
+                                    .into_any_element(),
+                                Some((peaks, start, end)) => {
+                                    super::waveform::render_waveform(&peaks, start, end, width, clip_color, waveform_style).into_any_element()
+                                }
+                                None => super::waveform::render_waveform_placeholder(clip_color, cx).into_any_element(),
+                            })
+                    )
+            )
+            // Locked clips show their fades but have no handles to edit them
+            .when(!locked, |d| {
+                d.child(render_trim_handle(clip_id, track_id, true, cx))
+                    .child(render_trim_handle(clip_id, track_id, false, cx))
+            })
+            .child(render_fade_overlay(clip, track_id, true, fade_in_px, locked, cx))
+            .child(render_fade_overlay(clip, track_id, false, fade_out_px, locked, cx))
+        })
+}
+
+/// Width of the strip at each end of a clip that trims it
+const TRIM_HANDLE_WIDTH: f32 = 5.0;
+
+/// Strip along a clip's start or end edge. Dragging it trims the clip
+/// without touching the file, so dragging back out reveals the audio again.
+fn render_trim_handle(
+    clip_id: uuid::Uuid,
+    track_id: uuid::Uuid,
+    is_start: bool,
+    cx: &mut Context<DawPanel>,
+) -> impl IntoElement {
+    let side = if is_start { "start" } else { "end" };
+
+    div()
+        .id(ElementId::Name(format!("clip-{}-trim-{}", clip_id, side).into()))
+        .absolute()
+        .top_0()
+        .h_full()
+        .w(px(TRIM_HANDLE_WIDTH))
+        .when(is_start, |d| d.left_0())
+        .when(!is_start, |d| d.right_0())
+        .cursor_col_resize()
+        .hover(|d| d.bg(cx.theme().foreground.opacity(0.25)))
+        .on_mouse_down(gpui::MouseButton::Left, cx.listener(move |this, event: &MouseDownEvent, _window, cx| {
+            cx.stop_propagation();
+            this.state.begin_clip_trim(track_id, clip_id, is_start, event.position.x.as_f32());
+            this.state.drag_pointer = event.position;
+            cx.notify();
+        }))
+}
+
+/// Badge for a clip whose file was resampled on load or is mono, e.g.
+/// "44.1k MONO". Clicking it converts the file to the project format.
+fn render_format_badge(
+    mismatches: &[FormatMismatch],
+    asset_path: std::path::PathBuf,
+    cx: &mut Context<DawPanel>,
+) -> impl IntoElement {
+    let label = mismatches.iter().map(|m| m.badge()).collect::<Vec<_>>().join(" ");
+
+    div()
+        .id(ElementId::Name(format!("clip-format-{}", asset_path.display()).into()))
+        .px_1()
+        .rounded_sm()
+        .text_xs()
+        .bg(cx.theme().yellow.opacity(0.85))
+        .text_color(cx.theme().background)
+        .cursor_pointer()
+        .child(label)
+        .on_mouse_down(gpui::MouseButton::Left, cx.listener(move |this, _event: &MouseDownEvent, _window, cx| {
+            // Don't select or drag the clip
+            cx.stop_propagation();
+            this.convert_asset_format(asset_path.clone(), cx);
         }))
-        .child(
-            v_flex()
-                .size_full()
-                .px_2()
-                .py_1()
-                .gap_1()
-                .child(
-                    div()
-                        .text_xs()
-                        .font_semibold()
-                        .text_color(cx.theme().background) // Contrast with clip color
-                        .child(file_name)
-                )
-                .child(
-                    div()
-                        .flex_1()
-                        .relative()
-                        // Placeholder waveform with track-colored tint
-                        .child(super::waveform::render_waveform_placeholder(clip_color, cx))
-                )
-        )
+}
+
+/// Number of columns used to draw a fade curve, per pixel of fade width
+const FADE_COLUMN_WIDTH: f32 = 2.0;
+
+/// Draw a fade region with its curve, a length handle and a curvature handle.
+/// The curve is sampled with the same `FadeShape::gain` the mixer uses.
+fn render_fade_overlay(
+    clip: &crate::daw_editor::daw_engine::audio_types::AudioClip,
+    track_id: uuid::Uuid,
+    is_fade_in: bool,
+    fade_px: f32,
+    locked: bool,
+    cx: &mut Context<DawPanel>,
+) -> impl IntoElement {
+    let clip_id = clip.id;
+    let (length, curve, shape) = if is_fade_in {
+        (clip.fade_in, clip.fade_in_curve, clip.fade_in_shape)
+    } else {
+        (clip.fade_out, clip.fade_out_curve, clip.fade_out_shape)
+    };
+    let side = if is_fade_in { "in" } else { "out" };
+
+    // Gain at a position across the region, left to right
+    let gain_at = move |x: f32| {
+        let t = if is_fade_in { x } else { 1.0 - x };
+        shape.gain(t, curve)
+    };
+
+    let columns = (fade_px / FADE_COLUMN_WIDTH).ceil().clamp(1.0, 256.0) as usize;
+    let curve_shade = h_flex()
+        .size_full()
+        .items_start()
+        .children((0..columns).map(|i| {
+            let x = (i as f32 + 0.5) / columns as f32;
+            div()
+                .flex_1()
+                .h(relative(1.0 - gain_at(x)))
+                .bg(gpui::black().opacity(0.35))
+        }));
+
+    let region = div()
+        .absolute()
+        .top_0()
+        .h_full()
+        .w(px(fade_px))
+        .when(is_fade_in, |d| d.left_0())
+        .when(!is_fade_in, |d| d.right_0())
+        .when(length > 0, |d| d.child(curve_shade));
+
+    // Length handle sits at the inner top corner of the fade
+    let length_handle = div()
+        .id(ElementId::Name(format!("clip-{}-fade-{}-length", clip_id, side).into()))
+        .absolute()
+        .top_0()
+        .w(px(8.0))
+        .h(px(8.0))
+        .when(is_fade_in, |d| d.right(px(-4.0)))
+        .when(!is_fade_in, |d| d.left(px(-4.0)))
+        .bg(cx.theme().foreground.opacity(0.8))
+        .rounded_sm()
+        .cursor_ew_resize()
+        .on_mouse_down(gpui::MouseButton::Left, cx.listener(move |this, event: &MouseDownEvent, _window, cx| {
+            cx.stop_propagation();
+            this.state.drag_state = DragState::DraggingFadeLength {
+                clip_id,
+                track_id,
+                is_fade_in,
+                start_mouse_x: event.position.x.as_f32(),
+                start_length: length,
+            };
+            cx.notify();
+        }));
+
+    // Curvature handle sits on the curve at the middle of the fade
+    let curve_handle = div()
+        .id(ElementId::Name(format!("clip-{}-fade-{}-curve", clip_id, side).into()))
+        .absolute()
+        .left(relative(0.5))
+        .top(relative(1.0 - gain_at(0.5)))
+        .ml(px(-4.0))
+        .mt(px(-4.0))
+        .w(px(8.0))
+        .h(px(8.0))
+        .rounded_full()
+        .border_1()
+        .border_color(cx.theme().foreground)
+        .bg(cx.theme().background.opacity(0.8))
+        .cursor_ns_resize()
+        .on_mouse_down(gpui::MouseButton::Left, cx.listener(move |this, event: &MouseDownEvent, _window, cx| {
+            cx.stop_propagation();
+            this.state.drag_state = DragState::DraggingFadeCurve {
+                clip_id,
+                track_id,
+                is_fade_in,
+                start_mouse_y: event.position.y.as_f32(),
+                start_curve: curve,
+            };
+            cx.notify();
+        }));
+
+    region
+        .when(!locked, |d| d.child(length_handle))
+        .when(!locked && length > 0, |d| d.child(curve_handle))
 }
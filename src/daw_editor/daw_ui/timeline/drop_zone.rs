@@ -4,6 +4,7 @@ pub use gpui::prelude::FluentBuilder;
 use ui::{
     h_flex, v_flex, Icon, IconName, StyledExt, ActiveTheme,
     scroll::{Scrollbar, ScrollbarAxis}, PixelsExt, h_virtual_list};
+use crate::daw_editor::daw_engine::audio_types::AutomationParameter;
 
 pub fn render_drop_zone(
     track_id: uuid::Uuid,
@@ -15,9 +16,38 @@ pub fn render_drop_zone(
         DragState::DraggingFile { .. }
     );
 
+    let marquee_span = state.marquee_span_for_track(track_id);
+
     div()
         .absolute()
         .inset_0()
+        // Marquee band for this track
+        .when_some(marquee_span, |d, (x0, x1)| {
+            d.child(
+                div()
+                    .absolute()
+                    .top_0()
+                    .left(px(x0))
+                    .w(px((x1 - x0).max(1.0)))
+                    .h_full()
+                    .bg(cx.theme().accent.opacity(0.15))
+                    .border_1()
+                    .border_color(cx.theme().accent.opacity(0.6))
+            )
+        })
+        // Start a marquee selection on empty timeline space. While the volume
+        // lane is shown the track's empty space is the lane, so the marquee
+        // picks its breakpoints instead of clips.
+        .on_mouse_down(gpui::MouseButton::Left, cx.listener(move |this, event: &MouseDownEvent, _window, cx| {
+            let element_pos = DawPanel::window_to_timeline_pos(event.position, this);
+            let target = if this.state.volume_lane_tracks.contains(&track_id) {
+                MarqueeTarget::AutomationPoints(AutomationParameter::Volume)
+            } else {
+                MarqueeTarget::Clips
+            };
+            this.state.begin_marquee(target, track_id, element_pos.x.as_f32(), MarqueeMode::from_modifiers(&event.modifiers));
+            cx.notify();
+        }))
         // Make sure we capture pointer events when dragging
         .when(is_drag_target, |d| {
             d.border_2()
@@ -35,17 +65,10 @@ pub fn render_drop_zone(
 
                 // Calculate beat position from mouse X
                 let beat = this.state.pixels_to_beats(mouse_x);
-                let tempo = this.state.get_tempo();
-                let snap_mode = this.state.snap_mode;
-                let snap_value = this.state.snap_value;
+                let (tempo, sample_rate) = (this.state.get_tempo(), this.state.sample_rate());
 
                 // Apply snap if enabled
-                let snapped_beat = if snap_mode == SnapMode::Grid {
-                    let snap_beats = snap_value.to_beats();
-                    (beat / snap_beats).round() * snap_beats
-                } else {
-                    beat
-                };
+                let snapped_beat = this.state.snap_beat(beat);
 
                 let file_path_clone = file_path.clone();
                 let file_name_clone = file_name.clone();
@@ -95,38 +118,28 @@ pub fn render_drop_zone(
                                         this.state.loaded_assets.insert(file_path_clone.clone(), asset);
 
                                         // Create new clip with real duration
-                                        if let Some(project) = &mut this.state.project {
-                                            if let Some(track) = project.tracks.iter_mut().find(|t| t.id == track_id_val) {
-                                                // Convert beats to samples for start time
-                                                let start_time = ((snapped_beat_val * 60.0 * SAMPLE_RATE as f64) / tempo_val as f64) as u64;
-
-                                                let clip = crate::daw_editor::audio_types::AudioClip::new(
-                                                    file_path_clone.clone(),
-                                                    start_time,
-                                                    duration_samples,
-                                                );
-
-                                                let clip_clone = clip.clone();
-                                                track.clips.push(clip);
-
-                                                let duration_beats = (duration_samples as f64 * tempo_val as f64) / (60.0 * SAMPLE_RATE as f64);
-                                                tracing::error!("📎 Created clip '{}' at beat {} on track '{}' (duration: {:.2} beats, {} samples)",
-                                                    file_name_clone, snapped_beat_val, track.name, duration_beats, duration_samples);
-
-                                                // IMPORTANT: Add clip to audio service's graph too!
-                                                if let Some(ref service) = this.state.audio_service {
-                                                    let service = service.clone();
-                                                    let track_id = track_id_val;
-                                                    cx.spawn(async move |_this, _cx| {
-                                                        if let Err(e) = service.add_clip_to_track(track_id, clip_clone).await {
-                                                            tracing::error!("❌ Failed to add clip to audio service: {}", e);
-                                                        } else {
-                                                            tracing::error!("✅ Added clip to audio service graph");
-                                                        }
-                                                    }).detach();
-                                                }
+                                        // Convert beats to samples for start time
+                                        let start_time = ((snapped_beat_val * 60.0 * sample_rate as f64) / tempo_val as f64) as u64;
+                                        let mut clip = crate::daw_editor::daw_engine::audio_types::AudioClip::new(
+                                            file_path_clone.clone(),
+                                            start_time,
+                                            duration_samples,
+                                        );
+                                        let clip_id = clip.id;
+                                        if let Some(asset) = this.state.loaded_assets.get(&file_path_clone) {
+                                            if this.state.preferences.trim_imported_silence(&mut clip, asset) {
+                                                tracing::info!("✂️ Trimmed '{}' to the sound, {} of {} samples",
+                                                    file_name_clone, clip.duration, duration_samples);
                                             }
                                         }
+
+                                        if let Some(neighbours_changed) = this.state.place_clip_as_edit(track_id_val, clip) {
+                                            let duration_beats = (duration_samples as f64 * tempo_val as f64) / (60.0 * sample_rate as f64);
+                                            tracing::error!("📎 Created clip '{}' at beat {} (duration: {:.2} beats, {} samples)",
+                                                file_name_clone, snapped_beat_val, duration_beats, duration_samples);
+                                            add_placed_clip_to_service(this, track_id_val, clip_id, neighbours_changed, cx);
+                                        }
+                                        this.load_waveforms(cx);
                                         cx.notify();
                                     }).ok();
                                 }).ok();
@@ -137,34 +150,20 @@ pub fn render_drop_zone(
                                 // Fallback: create clip with default duration
                                 cx.update(|cx| {
                                     this.update(cx, |this, cx| {
-                                        if let Some(project) = &mut this.state.project {
-                                            if let Some(track) = project.tracks.iter_mut().find(|t| t.id == track_id_val) {
-                                                let start_time = ((snapped_beat_val * 60.0 * SAMPLE_RATE as f64) / tempo_val as f64) as u64;
-                                                let duration = ((10.0 * 60.0 * SAMPLE_RATE as f64) / tempo_val as f64) as u64; // Fallback: 10 beats
+                                        let start_time = ((snapped_beat_val * 60.0 * sample_rate as f64) / tempo_val as f64) as u64;
+                                        let duration = ((10.0 * 60.0 * sample_rate as f64) / tempo_val as f64) as u64; // Fallback: 10 beats
 
-                                                let clip = crate::daw_editor::audio_types::AudioClip::new(
-                                                    file_path_clone.clone(),
-                                                    start_time,
-                                                    duration,
-                                                );
-                                                let clip_clone = clip.clone();
-                                                track.clips.push(clip);
-                                                tracing::error!("📎 Created clip '{}' at beat {} with fallback duration (failed to load audio)",
-                                                    file_name_clone, snapped_beat_val);
-
-                                                // Add to audio service even if load failed (will try again at playback)
-                                                if let Some(ref service) = this.state.audio_service {
-                                                    let service = service.clone();
-                                                    let track_id = track_id_val;
-                                                    cx.spawn(async move |_this, _cx| {
-                                                        if let Err(e) = service.add_clip_to_track(track_id, clip_clone).await {
-                                                            tracing::error!("❌ Failed to add clip to audio service: {}", e);
-                                                        } else {
-                                                            tracing::error!("✅ Added clip to audio service graph (fallback)");
-                                                        }
-                                                    }).detach();
-                                                }
-                                            }
+                                        let clip = crate::daw_editor::daw_engine::audio_types::AudioClip::new(
+                                            file_path_clone.clone(),
+                                            start_time,
+                                            duration,
+                                        );
+                                        let clip_id = clip.id;
+                                        // Added to the audio service even if load failed (will try again at playback)
+                                        if let Some(neighbours_changed) = this.state.place_clip_as_edit(track_id_val, clip) {
+                                            tracing::error!("📎 Created clip '{}' at beat {} with fallback duration (failed to load audio)",
+                                                file_name_clone, snapped_beat_val);
+                                            add_placed_clip_to_service(this, track_id_val, clip_id, neighbours_changed, cx);
                                         }
                                         cx.notify();
                                     }).ok();
@@ -180,7 +179,14 @@ pub fn render_drop_zone(
         }))
         // Handle mouse move for clip dragging
         .on_mouse_move(cx.listener(move |this, event: &MouseMoveEvent, _window, cx| {
-            if let DragState::DraggingClip { clip_id, track_id: drag_track_id, start_beat, mouse_offset } = &this.state.drag_state.clone() {
+            if matches!(this.state.drag_state, DragState::Marquee { .. }) {
+                let element_pos = DawPanel::window_to_timeline_pos(event.position, this);
+                this.state.update_marquee(track_id, element_pos.x.as_f32());
+                cx.notify();
+                return;
+            }
+
+            if let DragState::DraggingClip { clip_id, track_id: drag_track_id, mouse_offset, .. } = &this.state.drag_state.clone() {
                 // Only update if dragging on THIS track
                 if drag_track_id == &track_id {
                     // Convert window position to element-local position
@@ -189,88 +195,70 @@ pub fn render_drop_zone(
 
                     // Calculate new beat position
                     let new_beat = this.state.pixels_to_beats(mouse_x);
-                    let snap_mode = this.state.snap_mode;
-                    let snap_value = this.state.snap_value;
-                    let tempo = this.state.get_tempo();
 
-                    // Apply snap if enabled
-                    let snapped_beat = if snap_mode == SnapMode::Grid {
-                        let snap_beats = snap_value.to_beats();
-                        (new_beat / snap_beats).round() * snap_beats
-                    } else {
-                        new_beat
-                    }.max(0.0);
+                    // Snap to the grid, or magnetically to clips on other tracks
+                    let (snapped_beat, magnet) = this.state.snap_clip_drag(track_id, *clip_id, new_beat);
+                    this.state.magnetic_snap_line = magnet;
 
-                    // Update clip position
-                    if let Some(project) = &mut this.state.project {
-                        if let Some(track) = project.tracks.iter_mut().find(|t| t.id == track_id) {
-                            if let Some(clip) = track.clips.iter_mut().find(|c| c.id == *clip_id) {
-                                clip.set_start_beat(snapped_beat, tempo);
-                            }
-                        }
-                    }
+                    // Update clip position, moving the rest of the selection with it
+                    this.state.drag_clip_to(track_id, *clip_id, snapped_beat);
                     cx.notify();
                 }
             }
         }))
         // Handle mouse up for clip drop
         .on_mouse_up(gpui::MouseButton::Left, cx.listener(move |this, event: &MouseUpEvent, _window, cx| {
-            if let DragState::DraggingClip { clip_id, track_id: drag_track_id, start_beat, mouse_offset } = &this.state.drag_state.clone() {
+            if let DragState::DraggingClip { clip_id, track_id: drag_track_id, mouse_offset, .. } = &this.state.drag_state.clone() {
                 // Convert window position to element-local position
                 let element_pos = DawPanel::window_to_timeline_pos(event.position, this);
                 let mouse_x = element_pos.x.as_f32() - mouse_offset.0;
 
                 // Calculate final beat position
                 let new_beat = this.state.pixels_to_beats(mouse_x);
-                let snap_mode = this.state.snap_mode;
-                let snap_value = this.state.snap_value;
-                let tempo = this.state.get_tempo();
 
-                // Apply snap if enabled
-                let snapped_beat = if snap_mode == SnapMode::Grid {
-                    let snap_beats = snap_value.to_beats();
-                    (new_beat / snap_beats).round() * snap_beats
-                } else {
-                    new_beat
-                }.max(0.0);
+                // Snap to the grid, or magnetically to clips on other tracks
+                let (snapped_beat, _) = this.state.snap_clip_drag(*drag_track_id, *clip_id, new_beat);
+                this.state.magnetic_snap_line = None;
 
                 tracing::error!("📍 Dropped clip at beat {} (snapped from {})",
                     snapped_beat, new_beat);
 
-                // Finalize clip position
-                let updated_clip = if let Some(project) = &mut this.state.project {
-                    if let Some(track) = project.tracks.iter_mut().find(|t| t.id == track_id) {
-                        if let Some(clip) = track.clips.iter_mut().find(|c| c.id == *clip_id) {
-                            clip.set_start_beat(snapped_beat, tempo);
-                            tracing::error!("✅ Final clip position: beat {}",
-                                snapped_beat);
-                            Some(clip.clone())
-                        } else { None }
-                    } else { None }
-                } else { None };
+                // Finalize clip position, with the rest of the selection
+                this.state.drag_clip_to(*drag_track_id, *clip_id, snapped_beat);
+                tracing::error!("✅ Final clip position: beat {}", snapped_beat);
 
-                // Sync updated clip to audio service
-                if let Some(clip) = updated_clip {
-                    if let Some(ref service) = this.state.audio_service {
-                        let service = service.clone();
-                        let track_id_copy = track_id;
-                        cx.spawn(async move |_this, _cx| {
-                            // Remove old clip and add updated one
-                            if let Err(e) = service.remove_clip_from_track(track_id_copy, clip.id).await {
-                                tracing::error!("❌ Failed to remove old clip from audio service: {}", e);
-                            }
-                            if let Err(e) = service.add_clip_to_track(track_id_copy, clip).await {
-                                tracing::error!("❌ Failed to add updated clip to audio service: {}", e);
-                            } else {
-                                tracing::error!("✅ Updated clip position in audio service");
-                            }
-                        }).detach();
-                    }
-                }
+                // Record the whole group move for undo. Whole tracks are synced
+                // since auto-crossfading may have changed neighbouring clips too.
+                let touched = this.state.record_selection_move();
+                this.sync_tracks_to_audio_service(touched, cx);
 
                 // Clear drag state
                 this.state.drag_state = DragState::None;
                 cx.notify();
             }
         }))
-}
\ No newline at end of file
+}
+/// Send a clip placed by `place_clip_as_edit` to the audio graph. When the drop
+/// crossfaded neighbouring clips the whole track is resynced instead.
+fn add_placed_clip_to_service(
+    this: &mut DawPanel,
+    track_id: TrackId,
+    clip_id: uuid::Uuid,
+    neighbours_changed: bool,
+    cx: &mut Context<DawPanel>,
+) {
+    if neighbours_changed {
+        this.sync_tracks_to_audio_service(vec![track_id], cx);
+        return;
+    }
+    let (Some(clip), Some(service)) = (this.state.get_clip(track_id, clip_id).cloned(), this.state.audio_service.clone()) else {
+        return;
+    };
+    cx.spawn(async move |_this, _cx| {
+        if let Err(e) = service.add_clip_to_track(track_id, clip).await {
+            tracing::error!("❌ Failed to add clip to audio service: {}", e);
+        } else {
+            tracing::error!("✅ Added clip to audio service graph");
+        }
+    }).detach();
+}
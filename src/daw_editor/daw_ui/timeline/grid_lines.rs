@@ -7,15 +7,23 @@ use ui::{
 
 /// Render grid lines
 pub fn render_grid_lines(state: &DawUiState, cx: &mut Context<DawPanel>) -> impl IntoElement {
-    let _zoom = state.viewport.zoom;
-    let num_beats = 500; // Total beats to show
+    let end_x = state.beats_to_pixels(500.0); // Total beats to show
+    let lines = state.grid_lines_in(0.0, end_x);
+    let high_contrast = state.preferences.high_contrast;
 
     div()
         .absolute()
         .inset_0()
-        
-        .children((0..num_beats).step_by(4).map(|beat| {
-            let x = state.beats_to_pixels(beat as f64);
+        .children(lines.into_iter().map(|line| {
+            let x = state.beats_to_pixels(line.beat);
+            let color = match (line.level, high_contrast) {
+                (GridLevel::Bar, false) => cx.theme().border.opacity(0.5),
+                (GridLevel::Beat, false) => cx.theme().border.opacity(0.2),
+                (GridLevel::Subdivision, false) => cx.theme().border.opacity(0.1),
+                (GridLevel::Bar, true) => cx.theme().foreground.opacity(0.6),
+                (GridLevel::Beat, true) => cx.theme().foreground.opacity(0.3),
+                (GridLevel::Subdivision, true) => cx.theme().foreground.opacity(0.15),
+            };
 
             div()
                 .absolute()
@@ -23,6 +31,6 @@ pub fn render_grid_lines(state: &DawUiState, cx: &mut Context<DawPanel>) -> impl
                 .top_0()
                 .bottom_0()
                 .w_px()
-                .bg(cx.theme().border.opacity(0.3))
+                .bg(color)
         }))
-}
\ No newline at end of file
+}
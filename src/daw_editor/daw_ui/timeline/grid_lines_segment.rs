@@ -12,27 +12,29 @@ pub fn render_grid_lines_segment(
     segment_width: f32,
     cx: &mut Context<DawPanel>,
 ) -> impl IntoElement {
-    let beat_width = state.beats_to_pixels(1.0);
-    let start_beat = (start_x / beat_width).floor() as i32;
-    let end_beat = ((start_x + segment_width) / beat_width).ceil() as i32;
-    
+    let lines = state.grid_lines_in(start_x, start_x + segment_width);
+    let high_contrast = state.preferences.high_contrast;
+
     div()
         .absolute()
         .inset_0()
-        .children((start_beat..=end_beat).map(|beat| {
-            let x = state.beats_to_pixels(beat as f64) - start_x;
-            let is_bar = beat % 4 == 0;
-            
+        .children(lines.into_iter().map(|line| {
+            let x = state.beats_to_pixels(line.beat) - start_x;
+            let color = match (line.level, high_contrast) {
+                (GridLevel::Bar, false) => cx.theme().border,
+                (GridLevel::Beat, false) => cx.theme().border.opacity(0.3),
+                (GridLevel::Subdivision, false) => cx.theme().border.opacity(0.12),
+                (GridLevel::Bar, true) => cx.theme().foreground.opacity(0.6),
+                (GridLevel::Beat, true) => cx.theme().foreground.opacity(0.3),
+                (GridLevel::Subdivision, true) => cx.theme().foreground.opacity(0.15),
+            };
+
             div()
                 .absolute()
                 .left(px(x))
                 .top_0()
                 .bottom_0()
                 .w_px()
-                .bg(if is_bar {
-                    cx.theme().border
-                } else {
-                    cx.theme().border.opacity(0.3)
-                })
+                .bg(color)
         }))
-}
\ No newline at end of file
+}
@@ -7,19 +7,22 @@ use ui::{
     scroll::{Scrollbar, ScrollbarAxis}, PixelsExt, h_virtual_list};
 pub use std::rc::Rc;
 pub use std::ops::Range;
-pub use crate::daw_editor::audio_types::SAMPLE_RATE;
+pub use crate::daw_editor::daw_engine::audio_types::{GridLevel, SAMPLE_RATE};
 
 pub mod clip;
 pub mod drop_zone;
 pub mod grid_lines;
 pub mod grid_lines_segment;
+pub mod mute_lane;
 pub mod playhead;
+pub mod region_lane;
 pub mod ruler_segment;
 pub mod ruler;
 pub mod track_content;
 pub mod track_content_segment;
 pub mod track_row;
 pub mod virtual_track_area;
+pub mod volume_lane;
 pub mod waveform;
 
 pub const TIMELINE_HEADER_HEIGHT: f32 = 40.0;
@@ -53,6 +56,8 @@ pub fn render_timeline(state: &mut DawUiState, cx: &mut Context<DawPanel>) -> im
     v_flex()
         .size_full()
         .bg(cx.theme().background)
+        // Arrangement regions above the ruler
+        .child(region_lane::render_region_lane(state, cx))
         // Ruler/timeline header
         .child(ruler::render_ruler(state, cx))
         // Scrollable track area with virtualization
@@ -0,0 +1,72 @@
+use super::*;
+use crate::daw_editor::daw_engine::audio_types::{AutomationParameter, SampleTime, Track};
+use ui::{ActiveTheme, PixelsExt};
+
+/// Height of the strip along the bottom of a track row showing mute automation
+const MUTE_STRIP_HEIGHT: f32 = 4.0;
+
+/// Muted stretches of a track's mute lane as (start, end) in samples, the
+/// last one running to `timeline_end`
+fn muted_ranges(track: &Track, timeline_end: SampleTime) -> Vec<(SampleTime, SampleTime)> {
+    let Some(lane) = track.get_automation_lane(AutomationParameter::Mute) else {
+        return Vec::new();
+    };
+    lane.points
+        .iter()
+        .enumerate()
+        .filter(|(_, point)| point.value >= 0.5)
+        .map(|(i, point)| {
+            let end = lane.points.get(i + 1).map_or(timeline_end, |next| next.time);
+            (point.time, end)
+        })
+        .filter(|(start, end)| end > start)
+        .collect()
+}
+
+/// Bars along the bottom of the track where mute automation silences it,
+/// dimmed while the lane is bypassed. Dragging along the strip marquees the
+/// lane's breakpoints.
+pub fn render_mute_lane(track: &Track, state: &DawUiState, cx: &mut Context<DawPanel>) -> impl IntoElement {
+    let Some(ref project) = state.project else {
+        return div().into_any_element();
+    };
+    let Some(lane) = track.get_automation_lane(AutomationParameter::Mute).filter(|lane| !lane.points.is_empty()) else {
+        return div().into_any_element();
+    };
+    let transport = &project.transport;
+    let color = cx.theme().danger.opacity(if lane.enabled { 0.8 } else { 0.3 });
+    let timeline_end = transport.beats_to_samples(500.0);
+    let track_id = track.id;
+
+    div()
+        .absolute()
+        .inset_0()
+        .child(
+            div()
+                .id(ElementId::Name(format!("mute-lane-{}", track_id).into()))
+                .absolute()
+                .bottom_0()
+                .left_0()
+                .right_0()
+                .h(px(MUTE_STRIP_HEIGHT))
+                .on_mouse_down(gpui::MouseButton::Left, cx.listener(move |this, event: &MouseDownEvent, _window, cx| {
+                    cx.stop_propagation();
+                    let x = DawPanel::window_to_timeline_pos(event.position, this).x.as_f32();
+                    let target = MarqueeTarget::AutomationPoints(AutomationParameter::Mute);
+                    this.state.begin_marquee(target, track_id, x, MarqueeMode::from_modifiers(&event.modifiers));
+                    cx.notify();
+                }))
+        )
+        .children(muted_ranges(track, timeline_end).into_iter().map(|(start, end)| {
+            let left = state.beats_to_pixels(transport.samples_to_beats(start));
+            let right = state.beats_to_pixels(transport.samples_to_beats(end));
+            div()
+                .absolute()
+                .bottom_0()
+                .left(px(left))
+                .w(px((right - left).max(1.0)))
+                .h(px(MUTE_STRIP_HEIGHT))
+                .bg(color)
+        }))
+        .into_any_element()
+}
@@ -7,14 +7,20 @@ use ui::{
 
 pub fn render_playhead(state: &DawUiState, cx: &mut Context<DawPanel>) -> impl IntoElement {
     let x = state.beats_to_pixels(state.selection.playhead_position);
+    // High contrast draws the playhead in the foreground color, a little wider
+    let (color, icon_color, width) = if state.preferences.high_contrast {
+        (cx.theme().foreground, cx.theme().background, 3.0)
+    } else {
+        (cx.theme().accent, cx.theme().accent_foreground, 2.0)
+    };
 
     div()
         .absolute()
         .left(px(x))
         .top_0()
         .bottom_0()
-        .w(px(2.0))
-        .bg(cx.theme().accent)
+        .w(px(width))
+        .bg(color)
         
         .child(
             div()
@@ -23,11 +29,11 @@ pub fn render_playhead(state: &DawUiState, cx: &mut Context<DawPanel>) -> impl I
                 .left(px(-6.0))
                 .w(px(14.0))
                 .h(px(14.0))
-                .bg(cx.theme().accent)
+                .bg(color)
                 .child(
                     Icon::new(IconName::Play)
                         .size_3()
-                        .text_color(cx.theme().accent_foreground)
+                        .text_color(icon_color)
                 )
         )
 }
\ No newline at end of file
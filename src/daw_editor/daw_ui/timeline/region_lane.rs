@@ -0,0 +1,232 @@
+use super::*;
+pub use gpui::*;
+pub use gpui::prelude::FluentBuilder;
+use ui::{
+    button::*, h_flex, Icon, IconName, Sizable, StyledExt, ActiveTheme, PixelsExt, h_virtual_list};
+
+pub const REGION_LANE_HEIGHT: f32 = 20.0;
+
+/// Width of the grab strip at each end of a region
+const REGION_EDGE_WIDTH: f32 = 4.0;
+
+/// Lane above the ruler holding the arrangement regions, with buttons to
+/// add, rename, recolor, export and delete them
+pub fn render_region_lane(state: &DawUiState, cx: &mut Context<DawPanel>) -> impl IntoElement {
+    let horizontal_scroll_handle = state.timeline_scroll_handle.clone();
+    let view = cx.entity().clone();
+    let selected = state.selected_region;
+    let has_regions = state.project.as_ref().is_some_and(|p| !p.regions.is_empty());
+
+    h_flex()
+        .w_full()
+        .h(px(REGION_LANE_HEIGHT))
+        .bg(cx.theme().muted)
+        .border_b_1()
+        .border_color(cx.theme().border)
+        .child(
+            h_flex()
+                .w(px(TRACK_HEADER_WIDTH))
+                .h_full()
+                .px_1()
+                .gap_0p5()
+                .border_r_1()
+                .border_color(cx.theme().border)
+                .child(
+                    div()
+                        .flex_1()
+                        .text_xs()
+                        .font_semibold()
+                        .text_color(cx.theme().muted_foreground)
+                        .child("REGIONS")
+                )
+                .child(
+                    Button::new("region-add")
+                        .icon(Icon::new(IconName::Plus))
+                        .ghost()
+                        .small()
+                        .disabled(state.project.is_none())
+                        .tooltip("Add a Region over the Loop, or the Bar at the Playhead")
+                        .on_click(cx.listener(|this, _, _window, cx| {
+                            this.state.add_region();
+                            cx.notify();
+                        }))
+                )
+                .when_some(selected, |d, region_id| {
+                    d.child(
+                        Button::new("region-rename")
+                            .icon(Icon::new(IconName::EditPencil))
+                            .ghost()
+                            .small()
+                            .tooltip("Rename Region")
+                            .on_click(cx.listener(move |this, _, _window, cx| {
+                                this.state.begin_region_rename(region_id);
+                                cx.notify();
+                            }))
+                    )
+                    .child(
+                        Button::new("region-color")
+                            .icon(Icon::new(IconName::Circle))
+                            .ghost()
+                            .small()
+                            .tooltip("Next Region Color")
+                            .on_click(cx.listener(move |this, _, _window, cx| {
+                                this.state.cycle_region_color(region_id);
+                                cx.notify();
+                            }))
+                    )
+                    .child(
+                        Button::new("region-delete")
+                            .icon(Icon::new(IconName::Trash))
+                            .ghost()
+                            .small()
+                            .tooltip("Delete Region")
+                            .on_click(cx.listener(move |this, _, _window, cx| {
+                                this.state.remove_region(region_id);
+                                cx.notify();
+                            }))
+                    )
+                })
+                .child(
+                    Button::new("region-markers")
+                        .label("M")
+                        .ghost()
+                        .small()
+                        .disabled(!has_regions)
+                        .tooltip("Add a Marker at the Start of Each Region")
+                        .on_click(cx.listener(|this, _, _window, cx| {
+                            let added = this.state.regions_to_markers();
+                            tracing::error!("📍 Added {} markers from regions", added);
+                            cx.notify();
+                        }))
+                )
+                .child(
+                    Button::new("region-export")
+                        .icon(Icon::new(IconName::Download))
+                        .ghost()
+                        .small()
+                        .disabled(!has_regions)
+                        .tooltip("Export the Audio of Each Region as a Cue; only the selected region's when one is selected")
+                        .on_click(cx.listener(|this, _, _window, cx| {
+                            let only = this.state.selected_region;
+                            this.export_region_cues(only, cx);
+                        }))
+                )
+        )
+        .child(
+            div()
+                .flex_1()
+                .h_full()
+                .relative()
+                .overflow_hidden()
+                .child(
+                    h_virtual_list(
+                        view,
+                        "regions",
+                        {
+                            let total_width = state.beats_to_pixels(500.0);
+                            let segment_width = 100.0;
+                            let num_segments = (total_width / segment_width).ceil() as usize;
+                            Rc::new(
+                                (0..num_segments).map(|_| Size {
+                                    width: px(segment_width),
+                                    height: px(REGION_LANE_HEIGHT),
+                                }).collect()
+                            )
+                        },
+                        move |panel, visible_range, _window, cx| {
+                            let segment_width = 100.0;
+                            visible_range.into_iter().map(|segment_idx| {
+                                let start_x = segment_idx as f32 * segment_width;
+                                render_region_segment(&panel.state, start_x, segment_width, cx)
+                            }).collect()
+                        },
+                    )
+                    .track_scroll(&horizontal_scroll_handle)
+                )
+        )
+}
+
+/// The parts of regions that fall in one segment of the lane. The body drags
+/// the region and double-click loops it; the strips at the ends resize it.
+fn render_region_segment(state: &DawUiState, start_x: f32, segment_width: f32, cx: &mut Context<DawPanel>) -> impl IntoElement {
+    let Some(project) = state.project.as_ref() else {
+        return div().w(px(segment_width)).h_full();
+    };
+    let transport = &project.transport;
+
+    div()
+        .w(px(segment_width))
+        .h_full()
+        .relative()
+        .children(project.regions.iter().filter_map(|region| {
+            let left = state.beats_to_pixels(transport.samples_to_beats(region.start)) - start_x;
+            let right = state.beats_to_pixels(transport.samples_to_beats(region.end)) - start_x;
+            let (visible_left, visible_right) = (left.max(0.0), right.min(segment_width));
+            if visible_right <= visible_left {
+                return None;
+            }
+
+            let region_id = region.id;
+            let is_selected = state.selected_region == Some(region_id);
+            let color = state.track_color_at(region.color);
+            let starts_here = left >= 0.0;
+            let label = match state.region_rename {
+                Some(ref rename) if rename.region == region_id => format!("{}|", rename.text),
+                _ => region.name.clone(),
+            };
+            let edge = |id: &'static str, edge: RegionEdge, at: f32, cx: &mut Context<DawPanel>| {
+                div()
+                    .id(ElementId::Name(format!("{}-{}", id, region_id).into()))
+                    .absolute()
+                    .top_0()
+                    .left(px(at))
+                    .w(px(REGION_EDGE_WIDTH))
+                    .h_full()
+                    .cursor_col_resize()
+                    .on_mouse_down(MouseButton::Left, cx.listener(move |this, event: &MouseDownEvent, _window, cx| {
+                        cx.stop_propagation();
+                        this.state.begin_region_drag(region_id, edge, event.position.x.as_f32());
+                        cx.notify();
+                    }))
+            };
+
+            Some(
+                div()
+                    .id(ElementId::Name(format!("region-{}-{}", region_id, start_x as i64).into()))
+                    .absolute()
+                    .top(px(2.0))
+                    .bottom(px(2.0))
+                    .left(px(visible_left))
+                    .w(px(visible_right - visible_left))
+                    .bg(color.opacity(if is_selected { 0.6 } else { 0.35 }))
+                    .when(is_selected, |d| d.border_t_1().border_b_1().border_color(cx.theme().accent))
+                    .when(starts_here, |d| d.border_l_2().border_color(color))
+                    .cursor_grab()
+                    .overflow_hidden()
+                    .on_mouse_down(MouseButton::Left, cx.listener(move |this, event: &MouseDownEvent, _window, cx| {
+                        cx.stop_propagation();
+                        if event.click_count >= 2 {
+                            this.state.loop_region(region_id);
+                            super::super::transport::sync_loop(&this.state, cx);
+                        } else {
+                            this.state.begin_region_drag(region_id, RegionEdge::Body, event.position.x.as_f32());
+                        }
+                        cx.notify();
+                    }))
+                    .when(starts_here, |d| {
+                        d.child(
+                            div()
+                                .pl_1()
+                                .text_xs()
+                                .whitespace_nowrap()
+                                .text_color(cx.theme().foreground)
+                                .child(label)
+                        )
+                        .child(edge("region-start", RegionEdge::Start, 0.0, cx))
+                    })
+                    .when(right <= segment_width, |d| {
+                        d.child(edge("region-end", RegionEdge::End, visible_right - visible_left - REGION_EDGE_WIDTH, cx))
+                    })
+            )
+        }))
+}
@@ -42,6 +42,18 @@ pub fn render_ruler(state: &DawUiState, cx: &mut Context<DawPanel>) -> impl Into
                 .h_full()
                 .relative()
                 .overflow_hidden()
+                // Measure the visible width, for following the playhead
+                .child({
+                    let view = view.clone();
+                    canvas(
+                        move |bounds, _window, cx| {
+                            view.update(cx, |panel, _cx| panel.state.timeline_width = bounds.size.width.as_f32());
+                        },
+                        |_, _, _, _| {},
+                    )
+                    .absolute()
+                    .size_full()
+                })
                 .child(
                     h_virtual_list(
                         view,
@@ -13,52 +13,169 @@ pub fn render_ruler_segment(
     total_width: f32,
     cx: &mut Context<DawPanel>,
 ) -> impl IntoElement {
+    let lines = state.grid_lines_in(start_x, start_x + segment_width);
+
+    // Part of the loop brace that falls in this segment, dimmed while looping is off
+    let loop_brace = match (state.selection.loop_start, state.selection.loop_end) {
+        (Some(start), Some(end)) => {
+            let left = (state.beats_to_pixels(start) - start_x).max(0.0);
+            let right = (state.beats_to_pixels(end) - start_x).min(segment_width);
+            (right > left).then_some((left, right - left))
+        }
+        _ => None,
+    };
+    // Project length marker, if it falls in this segment
+    let project_end = state.project.as_ref()
+        .and_then(|p| p.transport.project_length.map(|length| p.transport.samples_to_beats(length)))
+        .map(|beat| state.beats_to_pixels(beat) - start_x)
+        .filter(|x| *x >= 0.0 && *x < segment_width);
+    let brace_color = if state.is_looping {
+        cx.theme().accent
+    } else {
+        cx.theme().muted_foreground.opacity(0.5)
+    };
+
     div()
         .w(px(segment_width))
         .h_full()
         .relative()
         .bg(cx.theme().muted)
-        // Render beat markings that intersect with this segment
-        .child(
-            div()
-                .absolute()
-                .left(px(-start_x))  // Offset to account for segment position
-                .top_0()
-                .w(px(total_width))  // Full timeline width
-                .h_full()
-                .children((0..=125).filter_map(|bar| {  // 500 beats / 4 = 125 bars
-                    let beat = (bar * 4) as f64;
-                    let x = state.beats_to_pixels(beat);
-                    
-                    // Only render if bar marking intersects with this segment
-                    if x >= start_x && x < start_x + segment_width {
-                        Some(div()
+        // Clicking the ruler moves the playhead, snapped unless Alt is held
+        .on_mouse_down(gpui::MouseButton::Left, cx.listener(|this, event: &MouseDownEvent, _window, cx| {
+            let x = DawPanel::window_to_timeline_pos(event.position, this).x.as_f32();
+            let beat = this.state.snap_playhead(this.state.pixels_to_beats(x), event.modifiers.alt);
+            this.seek_to_beat(beat, cx);
+        }))
+        .when_some(loop_brace, |d, (left, width)| {
+            d.child(
+                div()
+                    .absolute()
+                    .top_0()
+                    .left(px(left))
+                    .w(px(width))
+                    .h(px(4.0))
+                    .bg(brace_color)
+            )
+        })
+        // Right-click removes the marker, ending the project with its last clip
+        .when_some(project_end, |d, x| {
+            d.child(
+                div()
+                    .id("project-end-marker")
+                    .absolute()
+                    .top_0()
+                    .left(px(x))
+                    .h_full()
+                    .pl_1()
+                    .border_l_2()
+                    .border_color(cx.theme().danger)
+                    .text_xs()
+                    .text_color(cx.theme().danger)
+                    .child("End")
+                    .on_mouse_down(gpui::MouseButton::Right, cx.listener(|this, _event: &MouseDownEvent, _window, cx| {
+                        cx.stop_propagation();
+                        this.state.set_project_length_at_playhead(false);
+                        super::super::transport::sync_playback_end(&this.state, cx);
+                        cx.notify();
+                    }))
+            )
+        })
+        // Grid ticks and labels that fall in this segment; bars run the full
+        // ruler height, beats and subdivisions are shorter ticks
+        .children(lines.into_iter().filter_map(|line| {
+            let x = state.beats_to_pixels(line.beat) - start_x;
+            if x < 0.0 || x >= segment_width {
+                return None;
+            }
+
+            let (tick_height, tick_color) = match line.level {
+                GridLevel::Bar => (relative(1.0), cx.theme().border),
+                GridLevel::Beat => (relative(0.4), cx.theme().border.opacity(0.7)),
+                GridLevel::Subdivision => (relative(0.2), cx.theme().border.opacity(0.4)),
+            };
+            let label = line.labeled.then(|| state.grid_label(&line));
+            let is_bar = line.level == GridLevel::Bar;
+
+            Some(
+                div()
+                    .absolute()
+                    .left(px(x))
+                    .top_0()
+                    .h_full()
+                    .child(
+                        div()
                             .absolute()
-                            .left(px(x))
-                            .h_full()
-                            .child(
-                                v_flex()
-                                    .h_full()
-                                    .child(
-                                        div()
-                                            .px_2()
-                                            .text_xs()
-                                            .font_family("monospace")
-                                            .text_color(cx.theme().foreground)
-                                            .child(format!("{}", bar + 1))
-                                    )
-                                    .child(
-                                        div()
-                                            .w_px()
-                                            .flex_1()
-                                            .bg(cx.theme().border)
-                                    )
-                            ))
-                    } else {
-                        None
-                    }
-                }))
-        )
+                            .bottom_0()
+                            .w_px()
+                            .h(tick_height)
+                            .bg(tick_color)
+                    )
+                    .when_some(label, |d, label| {
+                        d.child(
+                            div()
+                                .absolute()
+                                .top_0()
+                                .left(px(3.0))
+                                .text_xs()
+                                .font_family("monospace")
+                                .when(is_bar, |d| d.text_color(cx.theme().foreground))
+                                .when(!is_bar, |d| d.text_color(cx.theme().muted_foreground))
+                                .child(label)
+                        )
+                    })
+            )
+        }))
+        // Markers whose flag starts in this segment. Click selects, double-click
+        // renames, right-click deletes.
+        .children(state.project.iter().flat_map(|p| p.markers.iter().map(move |m| (m, p.transport.samples_to_beats(m.position)))).filter_map(|(marker, beat)| {
+            let x = state.beats_to_pixels(beat) - start_x;
+            if x < 0.0 || x >= segment_width {
+                return None;
+            }
+            let marker_id = marker.id;
+            let is_selected = state.selected_marker == Some(marker_id);
+            let color = if is_selected { cx.theme().accent } else { cx.theme().yellow };
+            let label = match state.marker_rename {
+                Some(ref rename) if rename.marker == marker_id => format!("{}|", rename.text),
+                _ => marker.name.clone(),
+            };
+
+            Some(
+                div()
+                    .id(ElementId::Name(format!("marker-{}", marker_id).into()))
+                    .absolute()
+                    .left(px(x))
+                    .bottom_0()
+                    .h(px(14.0))
+                    .pl_1()
+                    .pr_1()
+                    .border_l_2()
+                    .border_color(color)
+                    .bg(color.opacity(if is_selected { 0.35 } else { 0.2 }))
+                    .cursor_pointer()
+                    .text_xs()
+                    .whitespace_nowrap()
+                    .text_color(cx.theme().foreground)
+                    .child(label)
+                    .on_mouse_down(gpui::MouseButton::Left, cx.listener(move |this, event: &MouseDownEvent, _window, cx| {
+                        cx.stop_propagation();
+                        this.state.selected_marker = Some(marker_id);
+                        if event.click_count >= 2 {
+                            let text = this.state.project.as_ref()
+                                .and_then(|p| p.markers.iter().find(|m| m.id == marker_id))
+                                .map(|m| m.name.clone())
+                                .unwrap_or_default();
+                            this.state.marker_rename = Some(MarkerRename { marker: marker_id, text });
+                        }
+                        cx.notify();
+                    }))
+                    .on_mouse_down(gpui::MouseButton::Right, cx.listener(move |this, _event: &MouseDownEvent, _window, cx| {
+                        cx.stop_propagation();
+                        this.state.remove_marker(marker_id);
+                        cx.notify();
+                    }))
+            )
+        }))
         // Playhead (full width, offset to account for segment position)
         .child(
             div()
@@ -7,7 +7,7 @@ use ui::{
 
 /// Render track timeline content (clips, automation, etc.)
 pub fn render_track_content(
-    track: &crate::daw_editor::audio_types::Track,
+    track: &crate::daw_editor::daw_engine::audio_types::Track,
     state: &DawUiState,
     total_width: f32,
     cx: &mut Context<DawPanel>,
@@ -7,7 +7,7 @@ use ui::{
 
 /// Render a segment of the track content for horizontal virtual scrolling
 pub fn render_track_content_segment(
-    track: &crate::daw_editor::audio_types::Track,
+    track: &crate::daw_editor::daw_engine::audio_types::Track,
     state: &DawUiState,
     start_x: f32,
     segment_width: f32,
@@ -15,6 +15,10 @@ pub fn render_track_content_segment(
     cx: &mut Context<DawPanel>,
 ) -> impl IntoElement {
     let track_id = track.id;
+    // Edge a dragged clip is locked to, drawn on every track it passes
+    let magnet_x = state.magnetic_snap_line
+        .map(|beat| state.beats_to_pixels(beat) - start_x)
+        .filter(|x| *x >= 0.0 && *x < segment_width);
 
     div()
         .w(px(segment_width))
@@ -43,9 +47,9 @@ pub fn render_track_content_segment(
                 .w(px(state.beats_to_pixels(500.0)))  // Full timeline width
                 .h_full()
                 .children(track.clips.iter().filter_map(|clip| {
-                    let tempo = state.get_tempo();
-                    let clip_start = state.beats_to_pixels(clip.start_beat(tempo));
-                    let clip_end = clip_start + state.beats_to_pixels(clip.duration_beats(tempo));
+                    let (tempo, rate) = (state.get_tempo(), state.sample_rate());
+                    let clip_start = state.beats_to_pixels(clip.start_beat(tempo, rate));
+                    let clip_end = clip_start + state.beats_to_pixels(clip.duration_beats(tempo, rate));
                     
                     // Only render if clip intersects with this segment
                     if clip_end >= start_x && clip_start < start_x + segment_width {
@@ -55,4 +59,26 @@ pub fn render_track_content_segment(
                     }
                 }))
         )
+        // Mute and volume automation, above the clips so they stay visible
+        .child(
+            div()
+                .absolute()
+                .left(px(-start_x))
+                .top_0()
+                .w(px(state.beats_to_pixels(500.0)))
+                .h_full()
+                .child(super::mute_lane::render_mute_lane(track, state, cx))
+                .child(super::volume_lane::render_volume_lane(track, state, start_x, segment_width, cx))
+        )
+        .when_some(magnet_x, |d, x| {
+            d.child(
+                div()
+                    .absolute()
+                    .left(px(x))
+                    .top_0()
+                    .bottom_0()
+                    .w(px(2.0))
+                    .bg(cx.theme().yellow)
+            )
+        })
 }
\ No newline at end of file
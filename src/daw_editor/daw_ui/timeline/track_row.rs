@@ -7,7 +7,7 @@ use ui::{
 
 /// Render a single track row - fixed header on left, scrollable content on right (Table pattern)
 pub fn render_track_row(
-    track: &crate::daw_editor::audio_types::Track,
+    track: &crate::daw_editor::daw_engine::audio_types::Track,
     state: &DawUiState,
     total_width: f32,
     cx: &mut Context<DawPanel>,
@@ -50,6 +50,8 @@ pub fn render_track_row(
                 .h_full()
                 .overflow_hidden()
                 .relative()
+                // Inactive tracks keep their clips but show them greyed out
+                .when(!track.active, |d| d.opacity(0.4))
                 .child(
                     h_virtual_list(
                         view.clone(),
@@ -0,0 +1,203 @@
+use super::*;
+use crate::daw_editor::daw_engine::audio_types::{AutomationParameter, SampleTime, Track};
+use ui::{ActiveTheme, PixelsExt};
+
+/// Size of the handle drawn at each automation point
+const POINT_SIZE: f32 = 8.0;
+
+/// Size of the curve handle in the middle of each segment
+const CURVE_HANDLE_SIZE: f32 = 6.0;
+
+/// Pixels between the dots tracing a segment
+const TRACE_SPACING: f32 = 4.0;
+
+/// Volume automation points drawn over the track's clips, at the height the
+/// fader law puts their value, with the curve between them traced in dots.
+/// Drag a point to move it, right-click to delete it. The handle in the
+/// middle of a segment bends it when dragged up or down, changes its shape
+/// on a double-click and straightens it on a right-click.
+pub fn render_volume_lane(
+    track: &Track,
+    state: &DawUiState,
+    start_x: f32,
+    segment_width: f32,
+    cx: &mut Context<DawPanel>,
+) -> impl IntoElement {
+    let parameter = AutomationParameter::Volume;
+    let (Some(project), Some(lane)) = (state.project.as_ref(), track.get_automation_lane(parameter)) else {
+        return div().into_any_element();
+    };
+    if !state.volume_lane_tracks.contains(&track.id) {
+        return div().into_any_element();
+    }
+    let track_id = track.id;
+    let height = *state.track_heights.get(&track_id).unwrap_or(&state.viewport.track_height);
+    let dragged = match state.drag_state {
+        DragState::DraggingAutomationPoint { track_id: id, param_type, point_index, .. } if id == track_id && param_type == parameter => {
+            Some(point_index)
+        }
+        _ => None,
+    };
+    let bent = match state.drag_state {
+        DragState::DraggingAutomationCurve { track_id: id, param_type, point_index, .. } if id == track_id && param_type == parameter => {
+            Some(point_index)
+        }
+        _ => None,
+    };
+    let color = if lane.enabled { cx.theme().yellow } else { cx.theme().muted_foreground };
+    let visible = (start_x - POINT_SIZE)..(start_x + segment_width + POINT_SIZE);
+    let transport = &project.transport;
+    let x_at = |time: SampleTime| state.beats_to_pixels(transport.samples_to_beats(time));
+    let y_at = |time: SampleTime| (1.0 - parameter.lane_position(lane.curve_value_at(time).unwrap_or(0.0))) * height;
+
+    // Dots along each segment on screen, sampled from the curve the mixer plays
+    let mut trace = Vec::new();
+    for pair in lane.points.windows(2) {
+        let (start, end) = (pair[0].time, pair[1].time);
+        let (x1, x2) = (x_at(start), x_at(end));
+        if x2 < visible.start || x1 > visible.end {
+            continue;
+        }
+        let steps = ((x2 - x1) / TRACE_SPACING).clamp(0.0, 256.0) as usize;
+        for step in 1..steps {
+            let time = start + ((end - start) as f64 * step as f64 / steps as f64) as SampleTime;
+            let x = x_at(time);
+            if visible.contains(&x) {
+                trace.push(
+                    div()
+                        .absolute()
+                        .left(px(x - 1.0))
+                        .top(px(y_at(time) - 1.0))
+                        .w(px(2.0))
+                        .h(px(2.0))
+                        .rounded_full()
+                        .bg(color.opacity(0.6))
+                );
+            }
+        }
+    }
+
+    // A bend handle in the middle of each segment wide enough to grab
+    let curve_handles = lane.points.windows(2).enumerate().filter_map(|(index, pair)| {
+        let (x1, x2) = (x_at(pair[0].time), x_at(pair[1].time));
+        let middle = pair[0].time + (pair[1].time - pair[0].time) / 2;
+        let x = x_at(middle);
+        if x2 - x1 < POINT_SIZE * 3.0 || !visible.contains(&x) {
+            return None;
+        }
+        let (curve_type, tension) = (pair[0].curve_type, pair[0].tension);
+        let is_bent = bent == Some(index);
+
+        Some(
+            div()
+                .id(ElementId::Name(format!("volume-curve-{}-{}", track_id, pair[0].id).into()))
+                .absolute()
+                .left(px(x - CURVE_HANDLE_SIZE / 2.0))
+                .top(px(y_at(middle) - CURVE_HANDLE_SIZE / 2.0))
+                .w(px(CURVE_HANDLE_SIZE))
+                .h(px(CURVE_HANDLE_SIZE))
+                .rounded_sm()
+                .border_1()
+                .border_color(color)
+                .bg(if is_bent { color } else { cx.theme().background.opacity(0.8) })
+                .cursor_ns_resize()
+                .on_mouse_down(gpui::MouseButton::Left, cx.listener(move |this, event: &MouseDownEvent, _window, cx| {
+                    cx.stop_propagation();
+                    if event.click_count >= 2 {
+                        if this.state.cycle_automation_shape(track_id, parameter, index) {
+                            this.state.dirty.mark_dirty();
+                            this.sync_tracks_to_audio_service(vec![track_id], cx);
+                        }
+                    } else {
+                        this.state.begin_automation_curve_drag(track_id, parameter, index, event.position.y.as_f32());
+                    }
+                    cx.notify();
+                }))
+                .on_mouse_down(gpui::MouseButton::Right, cx.listener(move |this, _event: &MouseDownEvent, _window, cx| {
+                    cx.stop_propagation();
+                    if this.state.straighten_automation_curve(track_id, parameter, index) {
+                        this.state.dirty.mark_dirty();
+                        this.sync_tracks_to_audio_service(vec![track_id], cx);
+                    }
+                    cx.notify();
+                }))
+                // Shape and bend beside the handle while it's dragged
+                .when(is_bent, |el| {
+                    el.child(
+                        div()
+                            .absolute()
+                            .left(px(CURVE_HANDLE_SIZE + 2.0))
+                            .top(px(-POINT_SIZE))
+                            .px_1()
+                            .rounded_sm()
+                            .bg(cx.theme().background.opacity(0.9))
+                            .text_xs()
+                            .text_color(cx.theme().foreground)
+                            .whitespace_nowrap()
+                            .child(format!("{} {:+.2}", curve_type.label(), tension))
+                    )
+                })
+        )
+    }).collect::<Vec<_>>();
+
+    div()
+        .absolute()
+        .inset_0()
+        .children(trace)
+        .children(curve_handles)
+        .children(lane.points.iter().enumerate().filter_map(|(index, point)| {
+            let x = x_at(point.time);
+            if !visible.contains(&x) {
+                return None;
+            }
+            let y = (1.0 - parameter.lane_position(point.value)) * height;
+            let is_dragged = dragged == Some(index);
+            let is_selected = state.selection.selected_automation_points.contains(&(track_id, parameter, index));
+
+            Some(
+                div()
+                    .id(ElementId::Name(format!("volume-point-{}-{}", track_id, point.id).into()))
+                    .absolute()
+                    .left(px(x - POINT_SIZE / 2.0))
+                    .top(px(y - POINT_SIZE / 2.0))
+                    .w(px(POINT_SIZE))
+                    .h(px(POINT_SIZE))
+                    .rounded_full()
+                    .border_1()
+                    .border_color(if is_selected { cx.theme().accent } else { cx.theme().foreground })
+                    .bg(if is_dragged || is_selected { cx.theme().foreground } else { color })
+                    .cursor_grab()
+                    .on_mouse_down(gpui::MouseButton::Left, cx.listener(move |this, event: &MouseDownEvent, _window, cx| {
+                        cx.stop_propagation();
+                        let mouse = (event.position.x.as_f32(), event.position.y.as_f32());
+                        this.state.begin_automation_drag(track_id, parameter, index, mouse);
+                        cx.notify();
+                    }))
+                    .on_mouse_down(gpui::MouseButton::Right, cx.listener(move |this, _event: &MouseDownEvent, _window, cx| {
+                        cx.stop_propagation();
+                        if this.state.remove_automation_point(track_id, parameter, index) {
+                            this.state.dirty.mark_dirty();
+                            this.sync_tracks_to_audio_service(vec![track_id], cx);
+                        }
+                        cx.notify();
+                    }))
+                    // Value readout beside the point while it's dragged
+                    .when(is_dragged, |el| {
+                        el.child(
+                            div()
+                                .absolute()
+                                .left(px(POINT_SIZE + 2.0))
+                                .top(px(-POINT_SIZE))
+                                .px_1()
+                                .rounded_sm()
+                                .bg(cx.theme().background.opacity(0.9))
+                                .text_xs()
+                                .text_color(cx.theme().foreground)
+                                .whitespace_nowrap()
+                                .child(parameter.format_value(point.value))
+                        )
+                    })
+            )
+        }))
+        .into_any_element()
+}
@@ -1,4 +1,5 @@
 use super::*;
+use crate::daw_editor::daw_engine::{peak_cache::WaveformPeaks, preferences::WaveformStyle};
 pub use gpui::*;
 pub use gpui::prelude::FluentBuilder;
 use ui::{
@@ -25,4 +26,54 @@ pub fn render_waveform_placeholder(tint_color: Hsla, cx: &mut Context<DawPanel>)
                 .size_4()
                 .text_color(darkened_color)
         )
-}
\ No newline at end of file
+}
+
+/// Width in pixels of one waveform column
+const WAVEFORM_COLUMN_WIDTH: f32 = 2.0;
+
+/// Upper bound on columns per clip so very wide clips stay cheap to draw
+const MAX_WAVEFORM_COLUMNS: usize = 1024;
+
+/// Draw the envelope of `start..end` (fractions of the source file) across
+/// `width` pixels in the given style, centred vertically in the parent or
+/// standing on its bottom edge when rectified
+pub fn render_waveform(
+    peaks: &WaveformPeaks,
+    start: f64,
+    end: f64,
+    width: f32,
+    tint_color: Hsla,
+    style: WaveformStyle,
+) -> impl IntoElement {
+    let color = hsla(tint_color.h, tint_color.s, (tint_color.l * 0.6).max(0.0), tint_color.a);
+    let rms_color = hsla(tint_color.h, tint_color.s, (tint_color.l * 0.85).min(1.0), tint_color.a);
+    let columns = ((width / WAVEFORM_COLUMN_WIDTH).ceil() as usize).clamp(1, MAX_WAVEFORM_COLUMNS);
+    let column_width = width / columns as f32;
+
+    // Top and bottom of a span as fractions of the height
+    let span = move |low: f32, high: f32| -> (f32, f32) {
+        if style.rectified {
+            (1.0 - style.scale(high.abs().max(low.abs())), 1.0)
+        } else {
+            ((1.0 - style.scale(high)) * 0.5, (1.0 - style.scale(low)) * 0.5)
+        }
+    };
+    let bar = move |i: usize, (top, bottom): (f32, f32), color: Hsla| {
+        div()
+            .absolute()
+            .left(px(i as f32 * column_width))
+            .w(px(column_width.max(1.0)))
+            .top(relative(top))
+            .h(relative((bottom - top).max(0.01)))
+            .bg(color)
+    };
+
+    div()
+        .size_full()
+        .relative()
+        .children(peaks.column_bins(start, end, columns).into_iter().enumerate().flat_map(|(i, [min, max, rms])| {
+            let peak = bar(i, span(min, max), color);
+            let rms = style.rms_overlay.then(|| bar(i, span(-rms, rms), rms_color));
+            std::iter::once(peak).chain(rms)
+        }))
+}
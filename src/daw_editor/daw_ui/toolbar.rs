@@ -3,10 +3,11 @@
 
 use super::state::*;
 use super::panel::DawPanel;
+use super::super::daw_engine::{audio_types::{FadeShape, TrackType, SAMPLE_RATE}, generators::Generator, metering::MeterBallistics, preferences::{EditPreferences, FollowMode, TrackPalette, WaveformStyle}};
 use gpui::*;
 use gpui::prelude::FluentBuilder;
 use ui::{
-    button::*, h_flex, Icon, IconName, Sizable, StyledExt, ActiveTheme, Disableable, divider::Divider,
+    button::*, h_flex, v_flex, Icon, IconName, Sizable, Selectable, StyledExt, ActiveTheme, Disableable, divider::Divider,
 };
 
 pub fn render_toolbar(state: &mut DawUiState, cx: &mut Context<DawPanel>) -> impl IntoElement {
@@ -25,6 +26,9 @@ pub fn render_toolbar(state: &mut DawUiState, cx: &mut Context<DawPanel>) -> imp
         // Edit tools
         .child(render_tools_section(state, cx))
         .child(Divider::vertical().h(px(24.0)).bg(cx.theme().border))
+        // Timeline-wide edits
+        .child(render_arrange_section(state, cx))
+        .child(Divider::vertical().h(px(24.0)).bg(cx.theme().border))
         // View options
         .child(render_view_section(state, cx))
         .child(Divider::vertical().h(px(24.0)).bg(cx.theme().border))
@@ -37,6 +41,8 @@ pub fn render_toolbar(state: &mut DawUiState, cx: &mut Context<DawPanel>) -> imp
 }
 
 fn render_file_section(state: &mut DawUiState, cx: &mut Context<DawPanel>) -> impl IntoElement {
+    let has_markers = state.project.as_ref().is_some_and(|p| !p.markers.is_empty());
+
     h_flex()
         .gap_1()
         .items_center()
@@ -59,12 +65,9 @@ fn render_file_section(state: &mut DawUiState, cx: &mut Context<DawPanel>) -> im
                 .disabled(state.project.is_none())
                 .tooltip("Save Project")
                 .on_click(cx.listener(|this, _, _window, cx| {
-                    if let Err(e) = this.state.save_project() {
-                        tracing::error!("❌ Save failed: {}", e);
-                    } else {
-                        tracing::error!("✅ Project saved");
+                    if !this.save_in_background(cx) {
+                        tracing::error!("❌ Save failed: no project path set");
                     }
-                    cx.notify();
                 }))
         )
         .child(
@@ -75,6 +78,153 @@ fn render_file_section(state: &mut DawUiState, cx: &mut Context<DawPanel>) -> im
                 .disabled(state.project.is_none())
                 .tooltip("Export Audio")
         )
+        .child(
+            Button::new("toolbar-export-clips")
+                .label("Export Clips")
+                .ghost()
+                .small()
+                .disabled(state.selection.selected_clip_ids.is_empty())
+                .tooltip("Export Each Selected Clip to Its Own File")
+                .on_click(cx.listener(|this, _, _window, cx| {
+                    this.export_selected_clips(cx);
+                }))
+        )
+        .child(
+            Button::new("toolbar-export-clips-mode")
+                .label(if state.clip_export_include_track { "With Track" } else { "Dry" })
+                .ghost()
+                .compact()
+                .small()
+                .tooltip("Dry exports only the clip's fades, gain and pitch; With Track adds its track's volume, pan and automation")
+                .on_click(cx.listener(|this, _, _window, cx| {
+                    this.state.clip_export_include_track = !this.state.clip_export_include_track;
+                    cx.notify();
+                }))
+        )
+        .child(
+            Button::new("toolbar-align-transients")
+                .label("Align")
+                .ghost()
+                .small()
+                .disabled(state.selection.selected_clip_ids.len() < 2)
+                .tooltip("Align the Selected Clips' First Transients to the Focused or Topmost Clip")
+                .on_click(cx.listener(|this, _, _window, cx| {
+                    this.align_selected_transients(cx);
+                }))
+        )
+        .child(
+            Button::new("toolbar-import-reference")
+                .label("Reference")
+                .ghost()
+                .small()
+                .disabled(state.project.is_none())
+                .tooltip("Import a Reference Track for A/B Comparison, Matched to the Mix's Loudness")
+                .on_click(cx.listener(|this, _, _window, cx| {
+                    this.import_reference_track(cx);
+                }))
+        )
+        .child(
+            Button::new("toolbar-import-loops")
+                .label("Loops")
+                .ghost()
+                .small()
+                .disabled(state.project.is_none())
+                .tooltip("Import a Folder of Loops, One Track Each, Stretched to the Project Tempo from Bar One")
+                .on_click(cx.listener(|this, _, _window, cx| {
+                    this.import_loop_folder(cx);
+                }))
+        )
+        .child(
+            Button::new("toolbar-import-split")
+                .label("Split")
+                .ghost()
+                .small()
+                .disabled(state.project.is_none())
+                .tooltip("Import an Interleaved File as One Mono Track per Channel, for Dual-Mono Stems")
+                .on_click(cx.listener(|this, _, _window, cx| {
+                    this.import_split_channels(cx);
+                }))
+        )
+        .child(
+            Button::new("toolbar-add-marker")
+                .label("Marker")
+                .ghost()
+                .small()
+                .disabled(state.project.is_none())
+                .tooltip("Add a Marker at the Playhead (Shift+M); double-click to rename, right-click to delete")
+                .on_click(cx.listener(|this, _, _window, cx| {
+                    this.state.add_marker_at_playhead();
+                    cx.notify();
+                }))
+        )
+        .child(
+            Button::new("toolbar-export-cues")
+                .label(if state.selected_marker.is_some() { "Export Cue" } else { "Export Cues" })
+                .ghost()
+                .small()
+                .disabled(!has_markers)
+                .tooltip("Export the audio from each marker to the next, named after the marker; only the selected marker's when one is selected")
+                .on_click(cx.listener(|this, _, _window, cx| {
+                    let only = this.state.selected_marker;
+                    this.export_marker_regions(only, cx);
+                }))
+        )
+        .child(
+            Button::new("toolbar-export-mix")
+                .label("Export Mix")
+                .ghost()
+                .small()
+                .disabled(state.project.is_none())
+                .tooltip("Render the Mix to WAV with a JSON Sidecar of Marker, Loop, Tempo and Region Times for Game Code")
+                .on_click(cx.listener(|this, _, _window, cx| {
+                    this.export_mix_with_timing(cx);
+                }))
+        )
+        .child(
+            Button::new("toolbar-flatten")
+                .label("Flatten")
+                .ghost()
+                .small()
+                .disabled(state.project.is_none())
+                .tooltip("Save a Flattened Copy: Each Track Rendered to One File with Its Effects and Automation Baked In")
+                .on_click(cx.listener(|this, _, _window, cx| {
+                    this.flatten_project(cx);
+                }))
+        )
+        .child(
+            Button::new("toolbar-render-queue")
+                .label("Queue")
+                .ghost()
+                .small()
+                .selected(state.render_queue_open || state.render_queue.running)
+                .tooltip("Render Queue: Render Several Projects or Regions Unattended")
+                .on_click(cx.listener(|this, _, _window, cx| {
+                    this.state.render_queue_open = !this.state.render_queue_open;
+                    cx.notify();
+                }))
+        )
+        .child(
+            Button::new("toolbar-undo")
+                .label("Undo")
+                .ghost()
+                .small()
+                .disabled(!state.can_undo)
+                .tooltip("Undo (Ctrl+Z)")
+                .on_click(cx.listener(|this, _, _window, cx| {
+                    this.undo(cx);
+                }))
+        )
+        .child(
+            Button::new("toolbar-redo")
+                .label("Redo")
+                .ghost()
+                .small()
+                .disabled(!state.can_redo)
+                .tooltip("Redo (Ctrl+Shift+Z)")
+                .on_click(cx.listener(|this, _, _window, cx| {
+                    this.redo(cx);
+                }))
+        )
 }
 
 fn render_tools_section(state: &mut DawUiState, cx: &mut Context<DawPanel>) -> impl IntoElement {
@@ -132,6 +282,8 @@ fn render_tools_section(state: &mut DawUiState, cx: &mut Context<DawPanel>) -> i
 }
 
 fn render_view_section(state: &mut DawUiState, cx: &mut Context<DawPanel>) -> impl IntoElement {
+    let (follow, follow_mode) = (state.preferences.follow_playhead, state.preferences.follow_mode);
+
     h_flex()
         .gap_1()
         .items_center()
@@ -168,6 +320,732 @@ fn render_view_section(state: &mut DawUiState, cx: &mut Context<DawPanel>) -> im
                     cx.notify();
                 }))
         )
+        .child(
+            Button::new("follow-playhead")
+                .label("Follow")
+                .when(follow, |b| b.primary())
+                .when(!follow, |b| b.ghost())
+                .small()
+                .tooltip("Keep the playhead in view while playing (F). Scrolling by hand pauses it for a few seconds")
+                .on_click(cx.listener(move |this, _, _window, cx| {
+                    this.state.set_preferences(EditPreferences { follow_playhead: !follow, ..this.state.preferences });
+                    cx.notify();
+                }))
+        )
+        .child(
+            Button::new("follow-mode")
+                .label(follow_mode.label())
+                .ghost()
+                .compact()
+                .small()
+                .disabled(!follow)
+                .tooltip("Page turns the view at the right edge, Scroll keeps the playhead centred")
+                .on_click(cx.listener(move |this, _, _window, cx| {
+                    let follow_mode = match follow_mode {
+                        FollowMode::Page => FollowMode::Continuous,
+                        FollowMode::Continuous => FollowMode::Page,
+                    };
+                    this.state.set_preferences(EditPreferences { follow_mode, ..this.state.preferences });
+                    cx.notify();
+                }))
+        )
+        .child(
+            Button::new("view-reset-layout")
+                .label("Reset Layout")
+                .ghost()
+                .small()
+                .tooltip("Reopen every panel and restore the default zoom and track heights")
+                .on_click(cx.listener(|this, _, _window, cx| {
+                    this.state.reset_layout();
+                    cx.notify();
+                }))
+        )
+}
+
+fn render_arrange_section(state: &mut DawUiState, cx: &mut Context<DawPanel>) -> impl IntoElement {
+    let bars = state.insert_silence_bars;
+    let no_project = state.project.is_none();
+    let no_clips = state.selection.selected_clip_ids.is_empty();
+    let (fill_bars, fill_loops) = (state.fill_bars, state.fill_with_clip_loop);
+    let all_locked = state.all_tracks_locked();
+    let no_audio_track = !state.project.as_ref().is_some_and(|p| {
+        p.tracks.iter().any(|t| t.track_type == TrackType::Audio && state.selection.selected_track_ids.contains(&t.id))
+    });
+
+    h_flex()
+        .gap_1()
+        .items_center()
+        .child(
+            Button::new("insert-silence")
+                .label("Insert")
+                .ghost()
+                .small()
+                .disabled(no_project)
+                .tooltip("Insert Silence at Playhead (Ctrl+Shift+I)")
+                .on_click(cx.listener(|this, _, _window, cx| {
+                    this.insert_silence(cx);
+                }))
+        )
+        .child(
+            Button::new("insert-silence-length")
+                .label(if bars == 1 { "1 bar".to_string() } else { format!("{} bars", bars) })
+                .ghost()
+                .compact()
+                .small()
+                .tooltip("Length of Inserted Silence")
+                .on_click(cx.listener(|this, _, _window, cx| {
+                    this.state.cycle_insert_silence_bars();
+                    cx.notify();
+                }))
+        )
+        .child(
+            Button::new("duplicate-to-fill")
+                .label("Fill")
+                .ghost()
+                .small()
+                .disabled(no_clips)
+                .tooltip("Duplicate Selected Clips to Fill (Ctrl+Shift+D)")
+                .on_click(cx.listener(|this, _, _window, cx| {
+                    this.duplicate_to_fill(cx);
+                }))
+        )
+        .child(
+            Button::new("duplicate-to-fill-length")
+                .label(match fill_bars {
+                    Some(bars) => format!("{} bars", bars),
+                    None => "To loop".to_string(),
+                })
+                .ghost()
+                .compact()
+                .small()
+                .tooltip("Fill to the Loop End or for a Number of Bars")
+                .on_click(cx.listener(|this, _, _window, cx| {
+                    this.state.cycle_fill_length();
+                    cx.notify();
+                }))
+        )
+        .child(
+            Button::new("duplicate-to-fill-mode")
+                .label(if fill_loops { "Loop" } else { "Copies" })
+                .compact()
+                .small()
+                .when(fill_loops, |b| b.primary())
+                .when(!fill_loops, |b| b.ghost())
+                .tooltip("Fill a single clip by looping its audio instead of adding copies")
+                .on_click(cx.listener(move |this, _, _window, cx| {
+                    this.state.fill_with_clip_loop = !fill_loops;
+                    cx.notify();
+                }))
+        )
+        .child(
+            Button::new("bounce-track")
+                .label("Bounce")
+                .ghost()
+                .small()
+                .disabled(no_audio_track)
+                .tooltip("Print the Selected Track with its Effects to a New Track (Ctrl+Shift+B)")
+                .on_click(cx.listener(|this, _, _window, cx| {
+                    this.bounce_selected_track(cx);
+                }))
+        )
+        .child(
+            Button::new("reference-image")
+                .label("Image")
+                .ghost()
+                .small()
+                .disabled(no_project)
+                .tooltip("Export a PNG of the Selected Track's or the Master's Waveform and Spectrogram")
+                .on_click(cx.listener(|this, _, _window, cx| {
+                    this.state.open_reference_image_dialog();
+                    cx.notify();
+                }))
+        )
+        .child(
+            Button::new("lock-all")
+                .label(if all_locked { "Unlock All" } else { "Lock All" })
+                .compact()
+                .small()
+                .when(all_locked, |b| b.primary())
+                .when(!all_locked, |b| b.ghost())
+                .disabled(no_project)
+                .tooltip(if all_locked {
+                    "Unlock Every Track and Clip"
+                } else {
+                    "Lock Every Track against Moves, Trims and Deletes"
+                })
+                .on_click(cx.listener(move |this, _, _window, cx| {
+                    this.state.set_all_locked(!all_locked);
+                    cx.notify();
+                }))
+        )
+        .child(
+            div()
+                .relative()
+                .child(
+                    Button::new("generator-menu")
+                        .label("Generate")
+                        .ghost()
+                        .small()
+                        .disabled(no_project)
+                        .tooltip("Insert Silence, a Test Tone, Noise or a Sweep")
+                        .on_click(cx.listener(|this, _, _window, cx| {
+                            this.state.generator_menu_open = !this.state.generator_menu_open;
+                            cx.notify();
+                        }))
+                )
+                .when(state.generator_menu_open, |d| d.child(render_generator_menu(state, cx)))
+        )
+        .child(
+            Button::new("trim-project-end")
+                .label("Trim End")
+                .ghost()
+                .small()
+                .disabled(no_project)
+                .tooltip("Trim Project to Last Clip")
+                .on_click(cx.listener(|this, _, _window, cx| {
+                    this.trim_project_end(cx);
+                }))
+        )
+        .child(
+            div()
+                .relative()
+                .child(
+                    Button::new("fade-preferences")
+                        .label("Fades")
+                        .ghost()
+                        .small()
+                        .tooltip("Default Fades, Crossfades and Import Trimming")
+                        .on_click(cx.listener(|this, _, _window, cx| {
+                            this.state.fade_menu_open = !this.state.fade_menu_open;
+                            cx.notify();
+                        }))
+                )
+                .when(state.fade_menu_open, |d| d.child(render_fade_menu(state, cx)))
+        )
+        .child(
+            div()
+                .relative()
+                .child(
+                    Button::new("color-preferences")
+                        .label("Colors")
+                        .ghost()
+                        .small()
+                        .tooltip("Track Colors, Contrast, Waveforms and Meters")
+                        .on_click(cx.listener(|this, _, _window, cx| {
+                            this.state.color_menu_open = !this.state.color_menu_open;
+                            cx.notify();
+                        }))
+                )
+                .when(state.color_menu_open, |d| d.child(render_color_menu(state, cx)))
+        )
+}
+
+/// Choices offered for the default fade length, in milliseconds
+const FADE_LENGTHS_MS: [f32; 5] = [0.0, 5.0, 10.0, 20.0, 50.0];
+
+/// Choices offered for the crossfade width, in milliseconds
+const CROSSFADE_LENGTHS_MS: [f32; 5] = [5.0, 10.0, 25.0, 50.0, 100.0];
+
+/// Choices offered for the mute, solo, fader and bypass ramp, in milliseconds
+const SMOOTHING_TIMES_MS: [f32; 5] = [0.0, 2.0, 5.0, 10.0, 20.0];
+
+/// Choices offered for the level below which imports count as silent, in dBFS
+const IMPORT_SILENCE_DB: [f32; 4] = [-72.0, -60.0, -48.0, -36.0];
+
+/// Choices offered for the margin kept around trimmed imports, in milliseconds
+const IMPORT_MARGINS_MS: [f32; 5] = [0.0, 2.0, 5.0, 10.0, 20.0];
+
+/// Fade preference picker. Changes apply to clips created or moved from
+/// now on and are saved to the user preferences right away.
+fn render_fade_menu(state: &DawUiState, cx: &mut Context<DawPanel>) -> impl IntoElement {
+    let prefs = state.preferences;
+    let length_label = |ms: f32| if ms == 0.0 { "Off".to_string() } else { format!("{} ms", ms) };
+
+    let fade_buttons: Vec<AnyElement> = FADE_LENGTHS_MS
+        .iter()
+        .map(|ms| {
+            let ms = *ms;
+            Button::new(ElementId::Name(format!("default-fade-{}", ms).into()))
+                .label(length_label(ms))
+                .compact()
+                .small()
+                .when(prefs.default_fade_ms == ms, |b| b.primary())
+                .when(prefs.default_fade_ms != ms, |b| b.ghost())
+                .on_click(cx.listener(move |this, _, _window, cx| {
+                    this.state.set_preferences(EditPreferences { default_fade_ms: ms, ..this.state.preferences });
+                    cx.notify();
+                }))
+                .into_any_element()
+        })
+        .collect();
+
+    let crossfade_buttons: Vec<AnyElement> = CROSSFADE_LENGTHS_MS
+        .iter()
+        .map(|ms| {
+            let ms = *ms;
+            Button::new(ElementId::Name(format!("crossfade-width-{}", ms).into()))
+                .label(length_label(ms))
+                .compact()
+                .small()
+                .disabled(!prefs.auto_crossfade)
+                .when(prefs.crossfade_ms == ms, |b| b.primary())
+                .when(prefs.crossfade_ms != ms, |b| b.ghost())
+                .on_click(cx.listener(move |this, _, _window, cx| {
+                    this.state.set_preferences(EditPreferences { crossfade_ms: ms, ..this.state.preferences });
+                    cx.notify();
+                }))
+                .into_any_element()
+        })
+        .collect();
+
+    let crossfade_shape_buttons: Vec<AnyElement> = FadeShape::ALL
+        .into_iter()
+        .map(|shape| {
+            Button::new(ElementId::Name(format!("crossfade-shape-{:?}", shape).into()))
+                .label(shape.label())
+                .compact()
+                .small()
+                .disabled(!prefs.auto_crossfade)
+                .when(prefs.crossfade_shape == shape, |b| b.primary())
+                .when(prefs.crossfade_shape != shape, |b| b.ghost())
+                .on_click(cx.listener(move |this, _, _window, cx| {
+                    this.state.set_preferences(EditPreferences { crossfade_shape: shape, ..this.state.preferences });
+                    cx.notify();
+                }))
+                .into_any_element()
+        })
+        .collect();
+
+    let smoothing_buttons: Vec<AnyElement> = SMOOTHING_TIMES_MS
+        .iter()
+        .map(|ms| {
+            let ms = *ms;
+            Button::new(ElementId::Name(format!("smoothing-time-{}", ms).into()))
+                .label(length_label(ms))
+                .compact()
+                .small()
+                .when(prefs.smoothing_ms == ms, |b| b.primary())
+                .when(prefs.smoothing_ms != ms, |b| b.ghost())
+                .on_click(cx.listener(move |this, _, _window, cx| {
+                    set_smoothing_time(&mut this.state, ms, cx);
+                    cx.notify();
+                }))
+                .into_any_element()
+        })
+        .collect();
+
+    let silence_buttons: Vec<AnyElement> = IMPORT_SILENCE_DB
+        .iter()
+        .map(|db| {
+            let db = *db;
+            Button::new(ElementId::Name(format!("import-silence-{}", db).into()))
+                .label(format!("{} dB", db))
+                .compact()
+                .small()
+                .disabled(!prefs.trim_import_silence)
+                .when(prefs.import_silence_db == db, |b| b.primary())
+                .when(prefs.import_silence_db != db, |b| b.ghost())
+                .on_click(cx.listener(move |this, _, _window, cx| {
+                    this.state.set_preferences(EditPreferences { import_silence_db: db, ..this.state.preferences });
+                    cx.notify();
+                }))
+                .into_any_element()
+        })
+        .collect();
+
+    let margin_buttons: Vec<AnyElement> = IMPORT_MARGINS_MS
+        .iter()
+        .map(|ms| {
+            let ms = *ms;
+            Button::new(ElementId::Name(format!("import-margin-{}", ms).into()))
+                .label(length_label(ms))
+                .compact()
+                .small()
+                .disabled(!prefs.trim_import_silence)
+                .when(prefs.import_margin_ms == ms, |b| b.primary())
+                .when(prefs.import_margin_ms != ms, |b| b.ghost())
+                .on_click(cx.listener(move |this, _, _window, cx| {
+                    this.state.set_preferences(EditPreferences { import_margin_ms: ms, ..this.state.preferences });
+                    cx.notify();
+                }))
+                .into_any_element()
+        })
+        .collect();
+
+    let heading_color = cx.theme().muted_foreground;
+    let heading = move |text: &'static str| {
+        div()
+            .text_xs()
+            .font_semibold()
+            .text_color(heading_color)
+            .child(text)
+    };
+
+    deferred(
+        anchored().child(
+            v_flex()
+                .mt(px(28.0))
+                .p_2()
+                .gap_2()
+                .bg(cx.theme().background)
+                .rounded_md()
+                .border_1()
+                .border_color(cx.theme().border)
+                .shadow_lg()
+                .child(heading("Fade on new and split clips"))
+                .child(h_flex().gap_1().children(fade_buttons))
+                .child(
+                    Button::new("auto-crossfade")
+                        .label("Auto-crossfade overlaps")
+                        .small()
+                        .when(prefs.auto_crossfade, |b| b.primary())
+                        .when(!prefs.auto_crossfade, |b| b.ghost())
+                        .on_click(cx.listener(move |this, _, _window, cx| {
+                            let auto_crossfade = !this.state.preferences.auto_crossfade;
+                            this.state.set_preferences(EditPreferences { auto_crossfade, ..this.state.preferences });
+                            cx.notify();
+                        }))
+                )
+                .child(heading("Longest crossfade"))
+                .child(h_flex().gap_1().children(crossfade_buttons))
+                .child(heading("Crossfade curve"))
+                .child(h_flex().gap_1().children(crossfade_shape_buttons))
+                .child(heading("Mute, solo, fader and bypass smoothing"))
+                .child(h_flex().gap_1().children(smoothing_buttons))
+                .child(
+                    Button::new("trim-import-silence")
+                        .label("Trim silence from imports")
+                        .small()
+                        .tooltip("Start and end files dropped onto tracks at their first and last sound; the files are untouched")
+                        .when(prefs.trim_import_silence, |b| b.primary())
+                        .when(!prefs.trim_import_silence, |b| b.ghost())
+                        .on_click(cx.listener(move |this, _, _window, cx| {
+                            let trim_import_silence = !this.state.preferences.trim_import_silence;
+                            this.state.set_preferences(EditPreferences { trim_import_silence, ..this.state.preferences });
+                            cx.notify();
+                        }))
+                )
+                .child(heading("Silent below"))
+                .child(h_flex().gap_1().children(silence_buttons))
+                .child(heading("Margin kept before the first sound"))
+                .child(h_flex().gap_1().children(margin_buttons))
+                .child(
+                    Button::new("scan-clipping")
+                        .label("Scan files for clipping")
+                        .small()
+                        .tooltip("Find runs of clipped samples as files load and rebuild the short ones; loading takes longer")
+                        .when(prefs.scan_clipping, |b| b.primary())
+                        .when(!prefs.scan_clipping, |b| b.ghost())
+                        .on_click(cx.listener(move |this, _, _window, cx| {
+                            let scan_clipping = !this.state.preferences.scan_clipping;
+                            this.set_scan_clipping(scan_clipping, cx);
+                        }))
+                )
+        )
+    )
+}
+
+/// Choices offered for the test tone frequency, in Hz
+const GENERATOR_FREQUENCIES: [f32; 4] = [100.0, 440.0, 1000.0, 10000.0];
+
+/// Choices offered for the generated clip length, in seconds
+const GENERATOR_SECONDS: [f32; 4] = [1.0, 5.0, 10.0, 30.0];
+
+/// Choices offered for the generator level, in dBFS
+const GENERATOR_LEVELS_DB: [f32; 5] = [-24.0, -18.0, -12.0, -6.0, 0.0];
+
+/// Picker for the generated clip. It's written to the project's audio
+/// folder and placed at the playhead when inserted.
+fn render_generator_menu(state: &DawUiState, cx: &mut Context<DawPanel>) -> impl IntoElement {
+    let settings = state.generator;
+    let frequency = match settings.generator {
+        Generator::Sine { frequency } => frequency,
+        _ => 1000.0,
+    };
+    let kinds = [
+        ("Silence", Generator::Silence),
+        ("Sine", Generator::Sine { frequency }),
+        ("White", Generator::WhiteNoise),
+        ("Pink", Generator::PinkNoise),
+        ("Sweep", Generator::Sweep { from: 20.0, to: 20000.0 }),
+    ];
+    let same_kind = |a: Generator, b: Generator| std::mem::discriminant(&a) == std::mem::discriminant(&b);
+
+    let kind_buttons: Vec<AnyElement> = kinds
+        .into_iter()
+        .map(|(label, generator)| {
+            let selected = same_kind(settings.generator, generator);
+            Button::new(ElementId::Name(format!("generator-kind-{}", label).into()))
+                .label(label)
+                .compact()
+                .small()
+                .when(selected, |b| b.primary())
+                .when(!selected, |b| b.ghost())
+                .on_click(cx.listener(move |this, _, _window, cx| {
+                    this.state.generator.generator = generator;
+                    cx.notify();
+                }))
+                .into_any_element()
+        })
+        .collect();
+
+    let is_sine = matches!(settings.generator, Generator::Sine { .. });
+    let frequency_buttons: Vec<AnyElement> = GENERATOR_FREQUENCIES
+        .iter()
+        .map(|hz| {
+            let hz = *hz;
+            let selected = is_sine && frequency == hz;
+            Button::new(ElementId::Name(format!("generator-frequency-{}", hz).into()))
+                .label(if hz >= 1000.0 { format!("{} kHz", hz / 1000.0) } else { format!("{} Hz", hz) })
+                .compact()
+                .small()
+                .disabled(!is_sine)
+                .when(selected, |b| b.primary())
+                .when(!selected, |b| b.ghost())
+                .on_click(cx.listener(move |this, _, _window, cx| {
+                    this.state.generator.generator = Generator::Sine { frequency: hz };
+                    cx.notify();
+                }))
+                .into_any_element()
+        })
+        .collect();
+
+    let length_buttons: Vec<AnyElement> = GENERATOR_SECONDS
+        .iter()
+        .map(|seconds| {
+            let seconds = *seconds;
+            Button::new(ElementId::Name(format!("generator-length-{}", seconds).into()))
+                .label(format!("{} s", seconds))
+                .compact()
+                .small()
+                .when(settings.seconds == seconds, |b| b.primary())
+                .when(settings.seconds != seconds, |b| b.ghost())
+                .on_click(cx.listener(move |this, _, _window, cx| {
+                    this.state.generator.seconds = seconds;
+                    cx.notify();
+                }))
+                .into_any_element()
+        })
+        .collect();
+
+    let has_level = settings.generator.has_level();
+    let level_buttons: Vec<AnyElement> = GENERATOR_LEVELS_DB
+        .iter()
+        .map(|db| {
+            let db = *db;
+            Button::new(ElementId::Name(format!("generator-level-{}", db).into()))
+                .label(format!("{} dBFS", db))
+                .compact()
+                .small()
+                .disabled(!has_level)
+                .when(settings.level_db == db, |b| b.primary())
+                .when(settings.level_db != db, |b| b.ghost())
+                .on_click(cx.listener(move |this, _, _window, cx| {
+                    this.state.generator.level_db = db;
+                    cx.notify();
+                }))
+                .into_any_element()
+        })
+        .collect();
+
+    let heading_color = cx.theme().muted_foreground;
+    let heading = move |text: &'static str| {
+        div()
+            .text_xs()
+            .font_semibold()
+            .text_color(heading_color)
+            .child(text)
+    };
+
+    deferred(
+        anchored().child(
+            v_flex()
+                .mt(px(28.0))
+                .p_2()
+                .gap_2()
+                .bg(cx.theme().background)
+                .rounded_md()
+                .border_1()
+                .border_color(cx.theme().border)
+                .shadow_lg()
+                .child(heading("Generator"))
+                .child(h_flex().gap_1().children(kind_buttons))
+                .child(heading("Sine frequency"))
+                .child(h_flex().gap_1().children(frequency_buttons))
+                .child(heading("Length"))
+                .child(h_flex().gap_1().children(length_buttons))
+                .child(heading("Peak level"))
+                .child(h_flex().gap_1().children(level_buttons))
+                .child(
+                    Button::new("generator-insert")
+                        .label(format!("Insert {} at Playhead", settings.generator.name()))
+                        .primary()
+                        .small()
+                        .on_click(cx.listener(|this, _, _window, cx| {
+                            this.insert_generated_clip(cx);
+                        }))
+                )
+        )
+    )
+}
+
+/// Store the smoothing time in the user preferences and send it to the
+/// audio thread
+fn set_smoothing_time(state: &mut DawUiState, smoothing_ms: f32, cx: &mut Context<DawPanel>) {
+    state.set_preferences(EditPreferences { smoothing_ms, ..state.preferences });
+
+    if let Some(ref service) = state.audio_service {
+        let service = service.clone();
+        let sample_rate = state.project.as_ref().map_or(SAMPLE_RATE, |p| p.sample_rate);
+        let samples = state.preferences.smoothing_samples(sample_rate);
+
+        cx.spawn(async move |_this, _cx| {
+            service.set_smoothing_samples(samples).await;
+        }).detach();
+    }
+}
+
+/// Store the meter ballistics in the user preferences and send them to the
+/// audio thread, which works the readings out for every meter
+fn set_meter_ballistics(state: &mut DawUiState, meter_ballistics: MeterBallistics, cx: &mut Context<DawPanel>) {
+    state.set_preferences(EditPreferences { meter_ballistics, ..state.preferences });
+
+    if let Some(ref service) = state.audio_service {
+        let service = service.clone();
+        cx.spawn(async move |_this, _cx| {
+            service.set_meter_ballistics(meter_ballistics).await;
+        }).detach();
+    }
+}
+
+/// Track palette, high contrast, waveform and meter picker, saved to the
+/// user preferences
+fn render_color_menu(state: &DawUiState, cx: &mut Context<DawPanel>) -> impl IntoElement {
+    let prefs = state.preferences;
+
+    let palette_buttons: Vec<AnyElement> = TrackPalette::ALL
+        .into_iter()
+        .map(|palette| {
+            // A strip of the palette's first colors next to its name
+            let swatches: Vec<AnyElement> = (0..6)
+                .map(|index| {
+                    let [r, g, b] = palette.color(index);
+                    div().w(px(8.0)).h(px(12.0)).rounded_sm().bg(Rgba { r, g, b, a: 1.0 }).into_any_element()
+                })
+                .collect();
+            h_flex()
+                .gap_2()
+                .items_center()
+                .child(
+                    Button::new(ElementId::Name(format!("track-palette-{:?}", palette).into()))
+                        .label(palette.label())
+                        .compact()
+                        .small()
+                        .when(prefs.track_palette == palette, |b| b.primary())
+                        .when(prefs.track_palette != palette, |b| b.ghost())
+                        .on_click(cx.listener(move |this, _, _window, cx| {
+                            this.state.set_preferences(EditPreferences { track_palette: palette, ..this.state.preferences });
+                            cx.notify();
+                        }))
+                )
+                .child(h_flex().gap_px().children(swatches))
+                .into_any_element()
+        })
+        .collect();
+
+    let meter_buttons: Vec<AnyElement> = MeterBallistics::ALL
+        .into_iter()
+        .map(|ballistics| {
+            Button::new(ElementId::Name(format!("meter-ballistics-{:?}", ballistics).into()))
+                .label(ballistics.label())
+                .compact()
+                .small()
+                .when(prefs.meter_ballistics == ballistics, |b| b.primary())
+                .when(prefs.meter_ballistics != ballistics, |b| b.ghost())
+                .on_click(cx.listener(move |this, _, _window, cx| {
+                    set_meter_ballistics(&mut this.state, ballistics, cx);
+                    cx.notify();
+                }))
+                .into_any_element()
+        })
+        .collect();
+
+    // Each option toggles on its own; together they apply to every clip
+    let waveform_options: [(&'static str, &'static str, fn(&mut WaveformStyle) -> &mut bool); 3] = [
+        ("waveform-rectified", "Rectified", |style| &mut style.rectified),
+        ("waveform-rms", "RMS overlay", |style| &mut style.rms_overlay),
+        ("waveform-log", "Log amplitude scale", |style| &mut style.log_scale),
+    ];
+    let waveform_buttons: Vec<AnyElement> = waveform_options
+        .into_iter()
+        .map(|(id, label, option)| {
+            let mut style = prefs.waveform;
+            let enabled = *option(&mut style);
+            Button::new(id)
+                .label(label)
+                .small()
+                .when(enabled, |b| b.primary())
+                .when(!enabled, |b| b.ghost())
+                .on_click(cx.listener(move |this, _, _window, cx| {
+                    let mut waveform = this.state.preferences.waveform;
+                    let flag = option(&mut waveform);
+                    *flag = !*flag;
+                    this.state.set_preferences(EditPreferences { waveform, ..this.state.preferences });
+                    cx.notify();
+                }))
+                .into_any_element()
+        })
+        .collect();
+
+    deferred(
+        anchored().child(
+            v_flex()
+                .mt(px(28.0))
+                .p_2()
+                .gap_2()
+                .bg(cx.theme().background)
+                .rounded_md()
+                .border_1()
+                .border_color(cx.theme().border)
+                .shadow_lg()
+                .child(
+                    div()
+                        .text_xs()
+                        .font_semibold()
+                        .text_color(cx.theme().muted_foreground)
+                        .child("Track colors")
+                )
+                .children(palette_buttons)
+                .child(
+                    Button::new("high-contrast")
+                        .label("High contrast selection and grid")
+                        .small()
+                        .when(prefs.high_contrast, |b| b.primary())
+                        .when(!prefs.high_contrast, |b| b.ghost())
+                        .on_click(cx.listener(move |this, _, _window, cx| {
+                            let high_contrast = !this.state.preferences.high_contrast;
+                            this.state.set_preferences(EditPreferences { high_contrast, ..this.state.preferences });
+                            cx.notify();
+                        }))
+                )
+                .child(
+                    div()
+                        .text_xs()
+                        .font_semibold()
+                        .text_color(cx.theme().muted_foreground)
+                        .child("Waveforms")
+                )
+                .children(waveform_buttons)
+                .child(
+                    div()
+                        .text_xs()
+                        .font_semibold()
+                        .text_color(cx.theme().muted_foreground)
+                        .child("Meters")
+                )
+                .child(h_flex().gap_1().children(meter_buttons))
+        )
+    )
 }
 
 fn render_snap_section(state: &mut DawUiState, cx: &mut Context<DawPanel>) -> impl IntoElement {
@@ -190,24 +1068,102 @@ fn render_snap_section(state: &mut DawUiState, cx: &mut Context<DawPanel>) -> im
                 }))
         )
         .child(
-            Button::new("snap-value")
-                .label(state.snap_value.label())
-                .ghost()
+            Button::new("snap-magnetic")
+                .label("Magnet")
+                .compact()
                 .small()
-                .tooltip("Snap Value")
+                .when(state.magnetic_snap, |b| b.primary())
+                .when(!state.magnetic_snap, |b| b.ghost())
+                .tooltip("Magnetic Snap: dragged clips snap their edges to clip edges on other tracks, with or without the grid")
                 .on_click(cx.listener(|this, _, _window, cx| {
-                    // Cycle through snap values
-                    this.state.snap_value = match this.state.snap_value {
-                        SnapValue::Bar => SnapValue::Half,
-                        SnapValue::Half => SnapValue::Quarter,
-                        SnapValue::Quarter => SnapValue::Eighth,
-                        SnapValue::Eighth => SnapValue::Sixteenth,
-                        SnapValue::Sixteenth => SnapValue::ThirtySecond,
-                        SnapValue::ThirtySecond => SnapValue::Bar,
-                    };
+                    this.state.magnetic_snap = !this.state.magnetic_snap;
                     cx.notify();
                 }))
         )
+        .child(
+            div()
+                .relative()
+                .child(
+                    Button::new("snap-value")
+                        .label(state.snap_value.label())
+                        .ghost()
+                        .small()
+                        .tooltip("Snap Value")
+                        .on_click(cx.listener(|this, _, _window, cx| {
+                            this.state.snap_menu_open = !this.state.snap_menu_open;
+                            cx.notify();
+                        }))
+                )
+                .when(state.snap_menu_open, |d| d.child(render_snap_menu(state, cx)))
+        )
+}
+
+/// Snap value picker with one column per feel
+fn render_snap_menu(state: &DawUiState, cx: &mut Context<DawPanel>) -> impl IntoElement {
+    let columns: Vec<AnyElement> = SnapValue::GROUPS
+        .iter()
+        .map(|(group, values)| {
+            let buttons: Vec<AnyElement> = values
+                .iter()
+                .map(|value| {
+                    let value = *value;
+                    Button::new(ElementId::Name(format!("snap-value-{}", value.label()).into()))
+                        .label(value.label())
+                        .compact()
+                        .small()
+                        .when(state.snap_value == value, |b| b.primary())
+                        .when(state.snap_value != value, |b| b.ghost())
+                        .on_click(cx.listener(move |this, _, _window, cx| {
+                            this.state.snap_value = value;
+                            this.state.snap_menu_open = false;
+                            cx.notify();
+                        }))
+                        .into_any_element()
+                })
+                .collect();
+
+            v_flex()
+                .gap_1()
+                .child(
+                    div()
+                        .text_xs()
+                        .font_semibold()
+                        .text_color(cx.theme().muted_foreground)
+                        .child(*group)
+                )
+                .children(buttons)
+                .into_any_element()
+        })
+        .collect();
+
+    let events = state.snap_playhead_to_events;
+    deferred(
+        anchored().child(
+            v_flex()
+                .mt(px(28.0))
+                .p_2()
+                .gap_2()
+                .bg(cx.theme().background)
+                .rounded_md()
+                .border_1()
+                .border_color(cx.theme().border)
+                .shadow_lg()
+                .child(h_flex().gap_3().items_start().children(columns))
+                .child(
+                    Button::new("snap-playhead-events")
+                        .label("Playhead Snaps to Clips, Markers and Loop")
+                        .compact()
+                        .small()
+                        .when(events, |b| b.primary())
+                        .when(!events, |b| b.ghost())
+                        .tooltip("Clicking the ruler snaps to nearby clip edges, markers and loop points as well as the grid. Hold Alt to place it freely.")
+                        .on_click(cx.listener(move |this, _, _window, cx| {
+                            this.state.snap_playhead_to_events = !events;
+                            cx.notify();
+                        }))
+                )
+        )
+    )
 }
 
 fn render_project_info(state: &mut DawUiState, cx: &mut Context<DawPanel>) -> impl IntoElement {
@@ -218,6 +1174,19 @@ fn render_project_info(state: &mut DawUiState, cx: &mut Context<DawPanel>) -> im
     let track_count = state.project.as_ref()
         .map(|p| p.tracks.len())
         .unwrap_or(0);
+
+    let sample_rate = state.project.as_ref().map(|p| p.sample_rate);
+
+    // A failed save stays visible until the next successful one
+    let (save_status, save_color) = if state.is_saving() {
+        (Some("Saving…".to_string()), cx.theme().muted_foreground)
+    } else if let Some(ref error) = state.save_error {
+        (Some(format!("Save failed: {}", error)), cx.theme().danger)
+    } else if state.project.is_some() && state.dirty.is_dirty() {
+        (Some("● Unsaved".to_string()), cx.theme().muted_foreground)
+    } else {
+        (None, cx.theme().muted_foreground)
+    };
     
     h_flex()
         .gap_3()
@@ -235,6 +1204,29 @@ fn render_project_info(state: &mut DawUiState, cx: &mut Context<DawPanel>) -> im
                 .text_color(cx.theme().muted_foreground)
                 .child(format!("{} tracks", track_count))
         )
+        .when_some(sample_rate, |this, rate| {
+            this.child(
+                Button::new("toolbar-sample-rate")
+                    .label(format!("{:.1} kHz", rate / 1000.0))
+                    .ghost()
+                    .small()
+                    .tooltip("Change Project Sample Rate")
+                    .on_click(cx.listener(|this, _, _window, cx| {
+                        this.state.open_sample_rate_dialog();
+                        cx.notify();
+                    }))
+            )
+        })
+        .when_some(save_status, |this, status| {
+            this.child(
+                div()
+                    .max_w(px(240.0))
+                    .overflow_hidden()
+                    .text_xs()
+                    .text_color(save_color)
+                    .child(status)
+            )
+        })
 }
 
 // Event handlers
@@ -3,6 +3,7 @@
 
 use super::state::*;
 use super::panel::DawPanel;
+use crate::daw_editor::daw_engine::audio_types::{MonitorMode, OutputTarget, OverlapMode, TrackType};
 use gpui::*;
 use gpui::prelude::FluentBuilder;
 use ui::{
@@ -10,7 +11,7 @@ use ui::{
 };
 
 pub fn render_track_header(
-    track: &crate::daw_editor::audio_types::Track,
+    track: &crate::daw_editor::daw_engine::audio_types::Track,
     state: &DawUiState,
     cx: &mut Context<DawPanel>,
 ) -> impl IntoElement {
@@ -18,14 +19,37 @@ pub fn render_track_header(
         .unwrap_or(&state.viewport.track_height);
     
     let is_selected = state.selection.selected_track_ids.contains(&track.id);
+    let is_focused = state.keyboard_focus == Some(KeyboardFocus::Track(track.id));
     let is_muted = state.is_track_effectively_muted(track.id);
     let is_soloed = state.solo_tracks.contains(&track.id);
     let track_id = track.id;
+    let is_active = track.active;
+    let is_reference = track.track_type == TrackType::Reference;
+    let is_armed = track.record_armed;
+    let monitor = track.monitor;
+    let overlap_mode = track.overlap_mode;
+    let output = track.output;
+    let output_channels = state.audio_service.as_ref().map(|s| s.channels()).unwrap_or(2);
+    // Routed to outputs the current device doesn't have, so it plays on Main
+    let output_unavailable = matches!(output, OutputTarget::Pair(pair) if pair as u32 * 2 + 2 > output_channels as u32);
+    let record_input = track.record_input;
+    let input_channels = state.audio_service.as_ref().map_or(0, |s| s.input_channels());
+    let input_device = state.audio_service.as_ref().and_then(|s| s.input_device_name());
+    let input_unavailable = input_device.is_some() && !record_input.resolve(input_channels).fits(input_channels);
+    let input_tooltip = match (&input_device, record_input.resolve(input_channels)) {
+        (None, _) => "No input device".to_string(),
+        (Some(name), input) if !input.fits(input_channels) => {
+            format!("{} isn't available on {} ({} inputs)", record_input.label(), name, input_channels)
+        }
+        (Some(name), input) if input != record_input => format!("{} ({} inputs), recording mono", name, input_channels),
+        (Some(name), _) => format!("Record input from {} ({} inputs)", name, input_channels),
+    };
     
     // Convert linear volume (0.0-2.0) to dB slider value (-60 to +12 dB)
     let current_db = track.volume_db();
     let slider_value = ((current_db + 60.0) / 72.0).clamp(0.0, 1.0); // Map -60..+12 to 0..1
     let pan_slider_value = ((track.pan + 1.0) / 2.0).clamp(0.0, 1.0); // Map -1..1 to 0..1
+    let volume = track.volume;
     
     v_flex()
         .w_full()
@@ -34,12 +58,17 @@ pub fn render_track_header(
         .py_2()
         .gap_2()
         .bg(if is_selected {
-            cx.theme().accent.opacity(0.1)
+            cx.theme().accent.opacity(if state.preferences.high_contrast { 0.3 } else { 0.1 })
         } else {
             cx.theme().muted.opacity(0.05)
         })
         .border_b_1()
         .border_color(cx.theme().border)
+        // Focus ring for keyboard navigation
+        .when(is_focused, |d| {
+            let ring = if state.preferences.high_contrast { cx.theme().accent } else { cx.theme().foreground.opacity(0.8) };
+            d.border_2().border_color(ring)
+        })
         // Track name and color with controls
         .child(
             h_flex()
@@ -52,7 +81,7 @@ pub fn render_track_header(
                         .w(px(4.0))
                         .h(px(32.0))
                         .rounded_sm()
-                        .bg(rgb(0x3b82f6)) // Track color
+                        .bg(state.track_color(track_id))
                 )
                 // Name and button group
                 .child(
@@ -64,13 +93,29 @@ pub fn render_track_header(
                             div()
                                 .text_sm()
                                 .font_semibold()
-                                .text_color(cx.theme().foreground)
+                                .text_color(if is_active { cx.theme().foreground } else { cx.theme().muted_foreground })
                                 .child(track.name.clone())
                         )
                         // Control buttons
                         .child(
                             h_flex()
                                 .gap_1()
+                                .child(
+                                    Button::new(ElementId::Name(format!("track-{}-active", track_id).into()))
+                                        .label(if is_active { "On" } else { "Off" })
+                                        .compact()
+                                        .small()
+                                        .when(is_active, |b| b.ghost())
+                                        .when(!is_active, |b| b.warning())
+                                        .tooltip(if is_active {
+                                            "Deactivate track (stops all processing)"
+                                        } else {
+                                            "Activate track"
+                                        })
+                                        .on_click(cx.listener(move |this, _, _window, cx| {
+                                            this.toggle_track_active(track_id, cx);
+                                        }))
+                                )
                                 .child(
                                     Button::new(ElementId::Name(format!("track-{}-mute", track_id).into()))
                                         .label("M")
@@ -81,6 +126,7 @@ pub fn render_track_header(
                                             if let Some(t) = this.state.get_track_mut(track_id) {
                                                 t.muted = !t.muted;
                                                 let new_muted_val = t.muted;
+                                                this.state.dirty.mark_dirty();
 
                                                 // Sync to audio service
                                                 if let Some(ref service) = this.state.audio_service {
@@ -94,7 +140,7 @@ pub fn render_track_header(
                                             }
                                         }))
                                 )
-                                .child(
+                                .when(!is_reference, |row| row.child(
                                     Button::new(ElementId::Name(format!("track-{}-solo", track_id).into()))
                                         .label("S")
                                         .compact()
@@ -102,6 +148,7 @@ pub fn render_track_header(
                                         .when(is_soloed, |b| b.primary())
                                         .on_click(cx.listener(move |this, _, _window, cx| {
                                             this.state.toggle_solo(track_id);
+                                            this.state.dirty.mark_dirty();
                                             let is_solo_val = this.state.solo_tracks.contains(&track_id);
 
                                             // Sync to audio service
@@ -114,13 +161,171 @@ pub fn render_track_header(
 
                                             cx.notify();
                                         }))
+                                ))
+                                // References are heard instead of the mix rather than soloed in it
+                                .when(is_reference, |row| row
+                                    .child(
+                                        Button::new(ElementId::Name(format!("track-{}-ab", track_id).into()))
+                                            .label(if state.listening_reference { "Ref" } else { "Mix" })
+                                            .compact()
+                                            .small()
+                                            .when(state.listening_reference, |b| b.primary())
+                                            .tooltip("Switch between the mix and the reference (R)")
+                                            .on_click(cx.listener(|this, _, _window, cx| {
+                                                this.toggle_reference_listening(cx);
+                                            }))
+                                    )
+                                    .child(
+                                        Button::new(ElementId::Name(format!("track-{}-match", track_id).into()))
+                                            .label(if state.reference_matching.contains(&track_id) { "Matching…" } else { "Match" })
+                                            .compact()
+                                            .small()
+                                            .ghost()
+                                            .disabled(state.reference_matching.contains(&track_id))
+                                            .tooltip("Set the reference's level to the loudness of the mix")
+                                            .on_click(cx.listener(move |this, _, _window, cx| {
+                                                this.match_reference_loudness(track_id, cx);
+                                            }))
+                                    )
                                 )
                                 .child(
                                     Button::new(ElementId::Name(format!("track-{}-record", track_id).into()))
                                         .icon(Icon::new(IconName::Circle))
                                         .compact()
                                         .small()
+                                        .when(is_armed, |b| b.danger())
+                                        .when(!is_armed, |b| b.ghost())
+                                        .tooltip("Record Arm")
+                                        .on_click(cx.listener(move |this, _, _window, cx| {
+                                            if let Some(t) = this.state.get_track_mut(track_id) {
+                                                t.record_armed = !t.record_armed;
+                                                let armed = t.record_armed;
+                                                this.state.dirty.mark_dirty();
+
+                                                if let Some(ref service) = this.state.audio_service {
+                                                    let service = service.clone();
+                                                    cx.spawn(async move |_this, _cx| {
+                                                        let _ = service.set_track_record_arm(track_id, armed).await;
+                                                    }).detach();
+                                                }
+
+                                                cx.notify();
+                                            }
+                                        }))
+                                )
+                                .child(
+                                    Button::new(ElementId::Name(format!("track-{}-monitor", track_id).into()))
+                                        .label(monitor.label())
+                                        .compact()
+                                        .small()
+                                        .when(monitor != MonitorMode::Off, |b| b.primary())
+                                        .when(monitor == MonitorMode::Off, |b| b.ghost())
+                                        .tooltip("Input Monitoring (Off / In / Auto)")
+                                        .on_click(cx.listener(move |this, _, _window, cx| {
+                                            if let Some(t) = this.state.get_track_mut(track_id) {
+                                                t.monitor = t.monitor.next();
+                                                let mode = t.monitor;
+                                                this.state.dirty.mark_dirty();
+
+                                                if let Some(ref service) = this.state.audio_service {
+                                                    let service = service.clone();
+                                                    cx.spawn(async move |_this, _cx| {
+                                                        let _ = service.set_track_monitor(track_id, mode).await;
+                                                    }).detach();
+                                                }
+
+                                                cx.notify();
+                                            }
+                                        }))
+                                )
+                                .child(
+                                    Button::new(ElementId::Name(format!("track-{}-overlap", track_id).into()))
+                                        .label(overlap_mode.label())
+                                        .compact()
+                                        .small()
+                                        .when(overlap_mode != OverlapMode::Layer, |b| b.primary())
+                                        .when(overlap_mode == OverlapMode::Layer, |b| b.ghost())
+                                        .tooltip("Overlapping Clips (Layer / Replace / Comp)")
+                                        .on_click(cx.listener(move |this, _, _window, cx| {
+                                            if let Some(t) = this.state.get_track_mut(track_id) {
+                                                t.overlap_mode = t.overlap_mode.next();
+                                                this.state.dirty.mark_dirty();
+                                                this.sync_tracks_to_audio_service(vec![track_id], cx);
+                                                cx.notify();
+                                            }
+                                        }))
+                                )
+                                .child(
+                                    Button::new(ElementId::Name(format!("track-{}-input", track_id).into()))
+                                        .label(record_input.label())
+                                        .compact()
+                                        .small()
+                                        .when(input_unavailable, |b| b.danger())
+                                        .when(!input_unavailable, |b| b.ghost())
+                                        .tooltip(input_tooltip)
+                                        .on_click(cx.listener(move |this, _, _window, cx| {
+                                            let channels = this.state.audio_service.as_ref().map_or(2, |s| s.input_channels().max(1));
+                                            if let Some(t) = this.state.get_track_mut(track_id) {
+                                                t.record_input = t.record_input.next(channels);
+                                                let input = t.record_input;
+                                                this.state.dirty.mark_dirty();
+
+                                                if let Some(ref service) = this.state.audio_service {
+                                                    let service = service.clone();
+                                                    cx.spawn(async move |_this, _cx| {
+                                                        service.set_track_record_input(track_id, input).await;
+                                                    }).detach();
+                                                }
+
+                                                cx.notify();
+                                            }
+                                        }))
+                                )
+                                .child(
+                                    Button::new(ElementId::Name(format!("track-{}-output", track_id).into()))
+                                        .label(output.label())
+                                        .compact()
+                                        .small()
+                                        .when(output_unavailable, |b| b.danger())
+                                        .when(!output_unavailable, |b| b.ghost())
+                                        .tooltip(if output_unavailable {
+                                            "Output not available on this device, playing on Main"
+                                        } else {
+                                            "Output (Main / hardware pairs)"
+                                        })
+                                        .on_click(cx.listener(move |this, _, _window, cx| {
+                                            let channels = this.state.audio_service.as_ref().map(|s| s.channels()).unwrap_or(2);
+                                            if let Some(t) = this.state.get_track_mut(track_id) {
+                                                t.output = t.output.next(channels);
+                                                this.sync_tracks_to_audio_service(vec![track_id], cx);
+                                                cx.notify();
+                                            }
+                                        }))
+                                )
+                                .child(
+                                    Button::new(ElementId::Name(format!("track-{}-lock", track_id).into()))
+                                        .label("Lock")
+                                        .compact()
+                                        .small()
+                                        .when(track.locked, |b| b.primary())
+                                        .when(!track.locked, |b| b.ghost())
+                                        .tooltip("Lock the track's clips against moves, trims and deletes")
+                                        .on_click(cx.listener(move |this, _, _window, cx| {
+                                            this.state.toggle_track_lock(track_id);
+                                            cx.notify();
+                                        }))
+                                )
+                                .child(
+                                    Button::new(ElementId::Name(format!("track-{}-delete", track_id).into()))
+                                        .icon(Icon::new(IconName::Trash))
+                                        .compact()
+                                        .small()
                                         .ghost()
+                                        .disabled(track.locked)
+                                        .tooltip(if track.locked { "Unlock to Delete Track" } else { "Delete Track" })
+                                        .on_click(cx.listener(move |this, _, _window, cx| {
+                                            this.delete_tracks(&[track_id], cx);
+                                        }))
                                 )
                         )
                 )
@@ -197,6 +402,7 @@ pub fn render_track_header(
                                                 track_id,
                                                 start_mouse_x: event.position.x,
                                                 start_value: slider_value as f32,
+                                                start_volume: volume,
                                             };
                                             cx.notify();
                                         }))
@@ -3,12 +3,12 @@
 
 use super::state::*;
 use super::panel::DawPanel;
+use super::super::daw_engine::{audio_types::PlaybackEnd, preferences::{EditPreferences, MAX_MONITOR_DB, MIN_MONITOR_DB}, transport_chase::SyncSource};
 use gpui::*;
 use gpui::prelude::FluentBuilder;
 use ui::{
-    button::*, h_flex, Icon, IconName, Sizable, StyledExt, ActiveTheme, divider::Divider,
+    button::*, h_flex, Icon, IconName, Sizable, StyledExt, ActiveTheme, Disableable, divider::Divider,
 };
-use crate::daw_editor::audio_types::SAMPLE_RATE;
 
 pub fn render_transport(state: &mut DawUiState, cx: &mut Context<DawPanel>) -> impl IntoElement {
     h_flex()
@@ -32,8 +32,14 @@ pub fn render_transport(state: &mut DawUiState, cx: &mut Context<DawPanel>) -> i
         // Loop section
         .child(render_loop_section(state, cx))
         .child(Divider::vertical().h(px(36.0)).bg(cx.theme().border))
+        // What happens at the project end
+        .child(render_end_section(state, cx))
+        .child(Divider::vertical().h(px(36.0)).bg(cx.theme().border))
         // Metronome and count-in
         .child(render_metronome_section(state, cx))
+        .child(Divider::vertical().h(px(36.0)).bg(cx.theme().border))
+        // Speaker level, outside the mix
+        .child(render_monitor_section(state, cx))
 }
 
 fn render_transport_buttons(state: &mut DawUiState, cx: &mut Context<DawPanel>) -> impl IntoElement {
@@ -60,12 +66,20 @@ fn render_transport_buttons(state: &mut DawUiState, cx: &mut Context<DawPanel>)
                 .small()
                 .tooltip("Stop")
                 .on_click(cx.listener(|this, _, window, cx| {
+                    if this.state.is_recording {
+                        this.stop_recording(cx);
+                    }
                     handle_stop(&mut this.state, window, cx);
                 }))
         )
-        // Play/Pause
+        // Play/Pause, left to the master while chasing
         .child({
-            let tooltip_text = if state.is_playing { "Pause" } else { "Play" };
+            let chasing = state.follows_sync_master();
+            let tooltip_text = match (chasing, state.is_playing) {
+                (true, _) => "Following the Sync Master",
+                (false, true) => "Pause",
+                (false, false) => "Play",
+            };
             Button::new("transport-play")
                 .icon(Icon::new(if state.is_playing {
                     IconName::Pause
@@ -74,6 +88,7 @@ fn render_transport_buttons(state: &mut DawUiState, cx: &mut Context<DawPanel>)
                 }))
                 .primary()
                 .small()
+                .disabled(chasing)
                 .tooltip(tooltip_text)
                 .on_click(cx.listener(|this, _, window, cx| {
                     handle_play_pause(&mut this.state, window, cx);
@@ -89,10 +104,33 @@ fn render_transport_buttons(state: &mut DawUiState, cx: &mut Context<DawPanel>)
                 .small()
                 .tooltip("Record")
                 .on_click(cx.listener(|this, _, _window, cx| {
-                    this.state.is_recording = !this.state.is_recording;
-                    cx.notify();
+                    if this.state.is_recording {
+                        this.stop_recording(cx);
+                    } else {
+                        this.start_recording(cx);
+                    }
                 }))
         )
+        // Sync source
+        .child({
+            let source = state.transport_chase.source();
+            let tooltip_text = if source != SyncSource::Internal && !state.follows_sync_master() {
+                "Sync Source: no MIDI input is open, so the transport runs on its own clock"
+            } else {
+                "Sync Source: Internal, or Chase MIDI Time Code or MIDI Clock"
+            };
+            Button::new("transport-sync")
+                .label(source.label())
+                .compact()
+                .small()
+                .when(source != SyncSource::Internal, |b| b.primary())
+                .when(source == SyncSource::Internal, |b| b.ghost())
+                .tooltip(tooltip_text)
+                .on_click(cx.listener(move |this, _, _window, cx| {
+                    this.state.set_sync_source(source.next());
+                    cx.notify();
+                }))
+        })
 }
 
 fn render_position_display(state: &mut DawUiState, cx: &mut Context<DawPanel>) -> impl IntoElement {
@@ -108,44 +146,51 @@ fn render_position_display(state: &mut DawUiState, cx: &mut Context<DawPanel>) -
     let millis = ((seconds % 1.0) * 1000.0).floor() as u32;
 
     // Convert to bars:beats format
-    let bars = (position / 4.0).floor() as u32 + 1;
-    let beats = (position % 4.0).floor() as u32 + 1;
+    let beats_per_bar = state.project.as_ref()
+        .map(|p| p.transport.time_signature_numerator.max(1))
+        .unwrap_or(4) as f64;
+    let bars = (position / beats_per_bar).floor() as u32 + 1;
+    let beats = (position % beats_per_bar).floor() as u32 + 1;
     let subdivisions = ((position % 1.0) * 100.0).floor() as u32;
 
+    let timecode = format!("{:02}:{:02}.{:03}", minutes, secs, millis);
+    let bars_beats = format!("{:03}.{}.{:02}", bars, beats, subdivisions);
+    let active = state.time_format;
+
+    // Clicking a display makes it the time format used by the ruler
     h_flex()
         .gap_2()
         .items_center()
+        .child(render_time_display("transport-timecode", timecode, active == TimeFormat::Timecode, TimeFormat::Timecode, cx))
+        .child(render_time_display("transport-bars-beats", bars_beats, active == TimeFormat::BarsBeats, TimeFormat::BarsBeats, cx))
+}
+
+fn render_time_display(
+    id: &'static str,
+    text: String,
+    is_active: bool,
+    format: TimeFormat,
+    cx: &mut Context<DawPanel>,
+) -> impl IntoElement {
+    div()
+        .id(id)
+        .px_3()
+        .py_2()
+        .rounded_md()
+        .bg(cx.theme().background)
+        .border_1()
+        .border_color(if is_active { cx.theme().accent } else { cx.theme().border })
+        .cursor_pointer()
+        .on_click(cx.listener(move |this, _, _window, cx| {
+            this.state.time_format = format;
+            cx.notify();
+        }))
         .child(
             div()
-                .px_3()
-                .py_2()
-                .rounded_md()
-                .bg(cx.theme().background)
-                .border_1()
-                .border_color(cx.theme().border)
-                .child(
-                    div()
-                        .text_sm()
-                        .font_family("monospace")
-                        .text_color(cx.theme().foreground)
-                        .child(format!("{:02}:{:02}.{:03}", minutes, secs, millis))
-                )
-        )
-        .child(
-            div()
-                .px_3()
-                .py_2()
-                .rounded_md()
-                .bg(cx.theme().background)
-                .border_1()
-                .border_color(cx.theme().border)
-                .child(
-                    div()
-                        .text_sm()
-                        .font_family("monospace")
-                        .text_color(cx.theme().foreground)
-                        .child(format!("{:03}.{}.{:02}", bars, beats, subdivisions))
-                )
+                .text_sm()
+                .font_family("monospace")
+                .text_color(cx.theme().foreground)
+                .child(text)
         )
 }
 
@@ -153,6 +198,7 @@ fn render_tempo_section(state: &mut DawUiState, cx: &mut Context<DawPanel>) -> i
     let tempo = state.project.as_ref()
         .map(|p| p.transport.tempo)
         .unwrap_or(120.0);
+    let has_project = state.project.is_some();
 
     let time_sig_num = state.project.as_ref()
         .map(|p| p.transport.time_signature_numerator)
@@ -162,6 +208,12 @@ fn render_tempo_section(state: &mut DawUiState, cx: &mut Context<DawPanel>) -> i
         .map(|p| p.transport.time_signature_denominator)
         .unwrap_or(4);
 
+    let entry = state.transport_entry.clone();
+    let typing = |field| entry.as_ref().filter(|e| e.field == field).map(|e| format!("{}|", e.text));
+    let tempo_label = typing(TransportField::Tempo).unwrap_or_else(|| format!("{:.1}", tempo));
+    let time_sig_label = typing(TransportField::TimeSignature).unwrap_or_else(|| format!("{}/{}", time_sig_num, time_sig_denom));
+    let error = entry.as_ref().filter(|e| e.field != TransportField::LoopCrossfade).and_then(|e| e.error.clone());
+
     h_flex()
         .gap_2()
         .items_center()
@@ -181,11 +233,8 @@ fn render_tempo_section(state: &mut DawUiState, cx: &mut Context<DawPanel>) -> i
                         .items_center()
                         .child(Icon::new(IconName::Timer).size_4().text_color(cx.theme().muted_foreground))
                         .child(
-                            div()
-                                .text_sm()
+                            render_entry_value("transport-tempo-value", tempo_label, TransportField::Tempo, &entry, has_project, cx)
                                 .font_semibold()
-                                .text_color(cx.theme().foreground)
-                                .child(format!("{:.1}", tempo))
                         )
                         .child(
                             div()
@@ -193,6 +242,42 @@ fn render_tempo_section(state: &mut DawUiState, cx: &mut Context<DawPanel>) -> i
                                 .text_color(cx.theme().muted_foreground)
                                 .child("BPM")
                         )
+                        .child(
+                            Button::new("tempo-down")
+                                .label("−")
+                                .ghost()
+                                .compact()
+                                .small()
+                                .disabled(!has_project)
+                                .tooltip("Slower by 1 BPM")
+                                .on_click(cx.listener(move |this, _, _window, cx| {
+                                    this.change_tempo(tempo - 1.0, cx);
+                                }))
+                        )
+                        .child(
+                            Button::new("tempo-up")
+                                .label("+")
+                                .ghost()
+                                .compact()
+                                .small()
+                                .disabled(!has_project)
+                                .tooltip("Faster by 1 BPM")
+                                .on_click(cx.listener(move |this, _, _window, cx| {
+                                    this.change_tempo(tempo + 1.0, cx);
+                                }))
+                        )
+                        .child(
+                            Button::new("tempo-tap")
+                                .label("Tap")
+                                .ghost()
+                                .compact()
+                                .small()
+                                .disabled(!has_project)
+                                .tooltip("Tap along to set the tempo (T)")
+                                .on_click(cx.listener(|this, _, _window, cx| {
+                                    this.tap_tempo(cx);
+                                }))
+                        )
                 )
         )
         .child(
@@ -211,17 +296,63 @@ fn render_tempo_section(state: &mut DawUiState, cx: &mut Context<DawPanel>) -> i
                         .items_center()
                         .child(Icon::new(IconName::Heart).size_4().text_color(cx.theme().muted_foreground))
                         .child(
-                            div()
-                                .text_sm()
+                            render_entry_value("transport-time-signature-value", time_sig_label, TransportField::TimeSignature, &entry, has_project, cx)
                                 .font_family("monospace")
-                                .text_color(cx.theme().foreground)
-                                .child(format!("{}/{}", time_sig_num, time_sig_denom))
                         )
                 )
         )
+        .when_some(error, |d, error| {
+            d.child(
+                div()
+                    .text_xs()
+                    .text_color(cx.theme().danger)
+                    .child(error)
+            )
+        })
+}
+
+/// A transport value that can be clicked to type a new one. While typing
+/// it shows the entry with a caret; Enter applies it and Escape cancels.
+fn render_entry_value(
+    id: &'static str,
+    label: String,
+    field: TransportField,
+    entry: &Option<TransportEntry>,
+    enabled: bool,
+    cx: &mut Context<DawPanel>,
+) -> Stateful<Div> {
+    let editing = entry.as_ref().is_some_and(|e| e.field == field);
+    let invalid = editing && entry.as_ref().is_some_and(|e| e.error.is_some());
+
+    div()
+        .id(id)
+        .px_1()
+        .rounded_sm()
+        .text_sm()
+        .text_color(cx.theme().foreground)
+        .cursor_text()
+        .when(editing, |d| {
+            d.bg(cx.theme().secondary)
+                .border_1()
+                .border_color(if invalid { cx.theme().danger } else { cx.theme().accent })
+        })
+        .when(enabled, |d| {
+            d.on_mouse_down(MouseButton::Left, cx.listener(move |this, _event: &MouseDownEvent, _window, cx| {
+                cx.stop_propagation();
+                this.state.transport_entry = Some(TransportEntry { field, text: String::new(), error: None });
+                cx.notify();
+            }))
+        })
+        .child(label)
 }
 
 fn render_loop_section(state: &mut DawUiState, cx: &mut Context<DawPanel>) -> impl IntoElement {
+    let entry = state.transport_entry.clone().filter(|e| e.field == TransportField::LoopCrossfade);
+    let crossfade_ms = state.project.as_ref().map_or(0.0, |p| p.transport.loop_crossfade_ms);
+    let crossfade_label = entry.as_ref().map_or_else(|| format!("{}", crossfade_ms), |e| format!("{}|", e.text));
+    let error = entry.as_ref().and_then(|e| e.error.clone());
+    let has_project = state.project.is_some();
+
     h_flex()
         .gap_1()
         .items_center()
@@ -237,6 +368,49 @@ fn render_loop_section(state: &mut DawUiState, cx: &mut Context<DawPanel>) -> im
                     handle_loop_toggle(&mut this.state, window, cx);
                 }))
         )
+        .child(
+            Button::new("transport-loop-selection")
+                .label("Sel")
+                .compact()
+                .ghost()
+                .small()
+                .disabled(state.selection.selected_clip_ids.is_empty())
+                .tooltip("Loop Selection (L)")
+                .on_click(cx.listener(|this, _, _window, cx| {
+                    if this.state.loop_selection() {
+                        sync_loop(&this.state, cx);
+                    }
+                    cx.notify();
+                }))
+        )
+        .child(
+            Button::new("transport-loop-project")
+                .label("All")
+                .compact()
+                .ghost()
+                .small()
+                .tooltip("Loop to Project Length")
+                .on_click(cx.listener(|this, _, _window, cx| {
+                    if this.state.loop_project() {
+                        sync_loop(&this.state, cx);
+                    }
+                    cx.notify();
+                }))
+        )
+        .child(
+            Button::new("transport-loop-clear")
+                .icon(Icon::new(IconName::Close))
+                .compact()
+                .ghost()
+                .small()
+                .disabled(state.selection.loop_start.is_none())
+                .tooltip("Clear Loop")
+                .on_click(cx.listener(|this, _, _window, cx| {
+                    this.state.clear_loop();
+                    sync_loop(&this.state, cx);
+                    cx.notify();
+                }))
+        )
         .when(state.is_looping, |flex| {
             let loop_start = state.selection.loop_start.unwrap_or(0.0);
             let loop_end = state.selection.loop_end.unwrap_or(16.0);
@@ -256,6 +430,90 @@ fn render_loop_section(state: &mut DawUiState, cx: &mut Context<DawPanel>) -> im
                             .child(format!("{:.1} - {:.1}", loop_start, loop_end))
                     )
             )
+            // Crossfade where the loop wraps, click to type a length
+            .child(
+                h_flex()
+                    .gap_1()
+                    .items_center()
+                    .child(div().text_xs().text_color(cx.theme().muted_foreground).child("XF"))
+                    .child(render_entry_value(
+                        "transport-loop-crossfade-value",
+                        crossfade_label,
+                        TransportField::LoopCrossfade,
+                        &entry,
+                        has_project,
+                        cx,
+                    ))
+                    .child(div().text_xs().text_color(cx.theme().muted_foreground).child("ms"))
+            )
+            .when_some(error, |d, error| {
+                d.child(
+                    div()
+                        .text_xs()
+                        .text_color(cx.theme().danger)
+                        .child(error)
+                )
+            })
+        })
+}
+
+fn render_end_section(state: &mut DawUiState, cx: &mut Context<DawPanel>) -> impl IntoElement {
+    let transport = state.project.as_ref().map(|p| &p.transport);
+    let mode = transport.map_or(PlaybackEnd::default(), |t| t.playback_end);
+    let has_marker = transport.is_some_and(|t| t.project_length.is_some());
+    let has_project = transport.is_some();
+
+    h_flex()
+        .gap_1()
+        .items_center()
+        .child(
+            Button::new("transport-playback-end")
+                .label(mode.label())
+                .compact()
+                .small()
+                .when(mode != PlaybackEnd::Continue, |b| b.primary())
+                .when(mode == PlaybackEnd::Continue, |b| b.ghost())
+                .disabled(!has_project)
+                .tooltip(match mode {
+                    PlaybackEnd::StopAtProjectEnd => "Playback Stops at the Project End Marker, or the Last Clip without One",
+                    PlaybackEnd::StopAtLastEvent => "Playback Stops after the Last Clip or Automation Point",
+                    PlaybackEnd::Continue => "Playback Carries On past the Project End",
+                })
+                .on_click(cx.listener(move |this, _, _window, cx| {
+                    this.state.set_playback_end(mode.next());
+                    sync_playback_end(&this.state, cx);
+                    cx.notify();
+                }))
+        )
+        .child(
+            Button::new("transport-project-end")
+                .label("End")
+                .compact()
+                .small()
+                .when(has_marker, |b| b.primary())
+                .when(!has_marker, |b| b.ghost())
+                .disabled(!has_project)
+                .tooltip("Set the Project End Marker at the Playhead")
+                .on_click(cx.listener(|this, _, _window, cx| {
+                    this.state.set_project_length_at_playhead(true);
+                    sync_playback_end(&this.state, cx);
+                    cx.notify();
+                }))
+        )
+        .when(has_marker, |flex| {
+            flex.child(
+                Button::new("transport-project-end-clear")
+                    .icon(Icon::new(IconName::Close))
+                    .compact()
+                    .ghost()
+                    .small()
+                    .tooltip("Remove the Project End Marker, Ending with the Last Clip")
+                    .on_click(cx.listener(|this, _, _window, cx| {
+                        this.state.set_project_length_at_playhead(false);
+                        sync_playback_end(&this.state, cx);
+                        cx.notify();
+                    }))
+            )
         })
 }
 
@@ -286,6 +544,73 @@ fn render_metronome_section(state: &mut DawUiState, cx: &mut Context<DawPanel>)
                     cx.notify();
                 }))
         )
+        .child(
+            Button::new("transport-record-quantize")
+                .label("Q")
+                .compact()
+                .ghost()
+                .small()
+                .when(state.record_quantize, |b| b.primary())
+                .tooltip("Record Quantize: snap takes to the grid")
+                .on_click(cx.listener(|this, _, _window, cx| {
+                    this.state.record_quantize = !this.state.record_quantize;
+                    cx.notify();
+                }))
+        )
+        .child({
+            let follows = state.preferences.arm_follows_selection;
+            Button::new("transport-arm-follows-selection")
+                .label("Arm Sel")
+                .compact()
+                .ghost()
+                .small()
+                .when(follows, |b| b.primary())
+                .tooltip("Record Arm Follows Selection: selecting an audio track arms it, with input monitoring, and disarms the others")
+                .on_click(cx.listener(move |this, _, _window, cx| {
+                    this.set_arm_follows_selection(!follows, cx);
+                }))
+        })
+}
+
+fn render_monitor_section(state: &mut DawUiState, cx: &mut Context<DawPanel>) -> impl IntoElement {
+    let level = state.preferences.monitor_level_db;
+    let step = move |id: &'static str, label: &'static str, db: f32, cx: &mut Context<DawPanel>| {
+        Button::new(id)
+            .label(label)
+            .compact()
+            .ghost()
+            .small()
+            .on_click(cx.listener(move |this, _, _window, cx| {
+                let level = this.state.preferences.monitor_level_db + db;
+                set_monitor_level(&mut this.state, level, cx);
+                cx.notify();
+            }))
+    };
+
+    h_flex()
+        .gap_1()
+        .items_center()
+        .child(
+            div()
+                .text_xs()
+                .text_color(cx.theme().muted_foreground)
+                .child("MON")
+        )
+        .child(step("transport-monitor-down", "-", -1.0, cx))
+        .child(
+            Button::new("transport-monitor-level")
+                .label(format!("{:+.0} dB", level))
+                .compact()
+                .ghost()
+                .small()
+                .when(level != 0.0, |b| b.warning())
+                .tooltip("Monitor level, not part of the mix or exports. Click to reset.")
+                .on_click(cx.listener(|this, _, _window, cx| {
+                    set_monitor_level(&mut this.state, 0.0, cx);
+                    cx.notify();
+                }))
+        )
+        .child(step("transport-monitor-up", "+", 1.0, cx))
 }
 
 // Event handlers
@@ -325,28 +650,84 @@ fn handle_stop(state: &mut DawUiState, window: &mut Window, cx: &mut Context<Daw
 }
 
 fn handle_loop_toggle(state: &mut DawUiState, window: &mut Window, cx: &mut Context<DawPanel>) {
-    state.is_looping = !state.is_looping;
+    let enabled = !state.is_looping;
+    state.set_looping(enabled);
+    sync_loop(state, cx);
+    cx.notify();
+}
+
+/// Send the project's loop region to the audio thread, which picks it up
+/// at the next wrap
+pub fn sync_loop(state: &DawUiState, cx: &mut Context<DawPanel>) {
+    let (Some(ref project), Some(ref service)) = (&state.project, &state.audio_service) else {
+        return;
+    };
+    let service = service.clone();
+    let transport = &project.transport;
+    let (enabled, start, end) = (transport.loop_enabled, transport.loop_start, transport.loop_end);
+    let crossfade_ms = transport.loop_crossfade_ms;
+
+    cx.spawn(async move |_this, _cx| {
+        if let Err(e) = service.set_loop(enabled, start, end).await {
+            tracing::error!("❌ Failed to set loop: {}", e);
+        }
+        if let Err(e) = service.set_loop_crossfade(crossfade_ms).await {
+            tracing::error!("❌ Failed to set loop crossfade: {}", e);
+        }
+    }).detach();
+}
 
-    // Get loop points in samples
-    let tempo = state.get_tempo();
-    let loop_start_beats = state.selection.loop_start.unwrap_or(0.0);
-    let loop_end_beats = state.selection.loop_end.unwrap_or(16.0);
+/// Send the project's playback end mode and length marker to the audio thread
+pub fn sync_playback_end(state: &DawUiState, cx: &mut Context<DawPanel>) {
+    let (Some(ref project), Some(ref service)) = (&state.project, &state.audio_service) else {
+        return;
+    };
+    let service = service.clone();
+    let (mode, project_length) = (project.transport.playback_end, project.transport.project_length);
+
+    cx.spawn(async move |_this, _cx| {
+        if let Err(e) = service.set_playback_end(mode, project_length).await {
+            tracing::error!("❌ Failed to set playback end: {}", e);
+        }
+    }).detach();
+}
 
-    // Convert beats to samples
-    let samples_per_beat = (SAMPLE_RATE * 60.0) / tempo;
-    let loop_start_samples = (loop_start_beats * samples_per_beat as f64) as u64;
-    let loop_end_samples = (loop_end_beats * samples_per_beat as f64) as u64;
+/// Store the monitor level in the user preferences and send it to the
+/// audio thread
+fn set_monitor_level(state: &mut DawUiState, db: f32, cx: &mut Context<DawPanel>) {
+    let monitor_level_db = db.clamp(MIN_MONITOR_DB, MAX_MONITOR_DB);
+    state.set_preferences(EditPreferences { monitor_level_db, ..state.preferences });
 
     if let Some(ref service) = state.audio_service {
         let service = service.clone();
-        let enabled = state.is_looping;
+        let gain = state.preferences.monitor_gain();
 
         cx.spawn(async move |_this, _cx| {
-            let _ = service.set_loop(enabled, loop_start_samples, loop_end_samples).await;
+            if let Err(e) = service.set_monitor_gain(gain).await {
+                tracing::error!("❌ Failed to set monitor level: {}", e);
+            }
         }).detach();
     }
+}
 
-    cx.notify();
+/// Push the project tempo and time signature to the audio service
+pub fn sync_tempo(state: &DawUiState, cx: &mut Context<DawPanel>) {
+    let (Some(ref project), Some(ref service)) = (&state.project, &state.audio_service) else {
+        return;
+    };
+    let service = service.clone();
+    let transport = &project.transport;
+    let tempo = transport.tempo;
+    let (numerator, denominator) = (transport.time_signature_numerator, transport.time_signature_denominator);
+
+    cx.spawn(async move |_this, _cx| {
+        if let Err(e) = service.set_tempo(tempo).await {
+            tracing::error!("❌ Failed to set tempo: {}", e);
+        }
+        if let Err(e) = service.set_time_signature(numerator, denominator).await {
+            tracing::error!("❌ Failed to set time signature: {}", e);
+        }
+    }).detach();
 }
 
 fn handle_metronome_toggle(state: &mut DawUiState, window: &mut Window, cx: &mut Context<DawPanel>) {
@@ -4,19 +4,21 @@
 /// with real-time multi-track mixing, sample-accurate automation, GPU-accelerated DSP,
 /// and a complete GPUI-based user interface.
 
-mod asset_manager;
-mod audio_graph;
-mod audio_service;
-mod audio_types;
-mod ecs_integration;
-mod gpu_dsp;
-mod project;
-mod real_time_audio;
+pub mod daw_engine;
 mod daw_ui;
 mod workspace_panels;
 
-pub use audio_service::AudioService;
+pub use daw_engine::audio_service::AudioService;
 pub use daw_ui::DawPanel;
+pub use daw_engine::save_queue::DirtyTracker;
+pub use daw_engine::loudness::{LoudnessReport, LoudnessTarget};
+pub use daw_engine::render::{
+    conform_asset, export_clips, export_marker_regions, marker_regions, region_cues, render_project, ClipExportOptions, ClipExportReport,
+    MarkerRegion, OverReport, RenderBitDepth, RenderOptions, RenderSummary, SilenceTrim, TrimReport,
+};
+pub use daw_engine::render_queue::{queue_summary, JobState, RenderJob, RenderQueue, RenderRange};
+pub use daw_engine::true_peak::OverCount;
+pub use daw_engine::timing_sidecar::{render_with_timing, TimingSidecar, TIMING_SIDECAR_VERSION};
 pub use workspace_panels::*;
 
 use gpui::*;
@@ -67,6 +69,11 @@ impl DawEditorPanel {
         // Initialize workspace layout
         panel.setup_workspace(window, cx);
 
+        // Follow layout resets and restores made through the DAW panel
+        cx.observe_in(&daw_panel, window, |this, _panel, window, cx| {
+            this.apply_dock_layout(window, cx);
+        }).detach();
+
         // Initialize audio service
         panel.initialize_audio_service(window, cx);
 
@@ -228,6 +235,7 @@ impl DawEditorPanel {
                 self.daw_panel.update(cx, |panel, _cx| {
                     let _ = panel.state.load_project(path);
                 });
+                self.apply_dock_layout(window, cx);
 
                 self.sync_project_to_audio_service(cx);
                 cx.notify();
@@ -242,13 +250,54 @@ impl DawEditorPanel {
         }
     }
 
+    /// Open or close the workspace docks to match the DAW panel's layout
+    /// after it was restored or reset
+    fn apply_dock_layout(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let wanted = self.daw_panel.update(cx, |panel, _cx| {
+            std::mem::take(&mut panel.state.dock_layout_changed)
+                .then_some((panel.state.left_dock_open, panel.state.right_dock_open))
+        });
+        let Some((left_open, right_open)) = wanted else {
+            return;
+        };
+        let dock_area = self.workspace.read(cx).dock_area().clone();
+        dock_area.update(cx, |dock_area, cx| {
+            for (placement, open) in [(DockPlacement::Left, left_open), (DockPlacement::Right, right_open)] {
+                if dock_area.is_dock_open(placement, cx) != open {
+                    dock_area.toggle_dock(placement, window, cx);
+                }
+            }
+        });
+    }
+
+    /// Record which workspace docks are open so the layout saved with the
+    /// project includes them
+    fn capture_dock_layout(&self, cx: &mut Context<Self>) {
+        let dock_area = self.workspace.read(cx).dock_area().clone();
+        let (left_open, right_open) = {
+            let dock_area = dock_area.read(cx);
+            (dock_area.is_dock_open(DockPlacement::Left, cx), dock_area.is_dock_open(DockPlacement::Right, cx))
+        };
+        self.daw_panel.update(cx, |panel, _cx| {
+            panel.state.left_dock_open = left_open;
+            panel.state.right_dock_open = right_open;
+        });
+    }
+
+    /// Save the edited project on the background save thread
     pub fn save_project(&self, cx: &mut Context<Self>) -> anyhow::Result<()> {
-        if let Some(path) = &self.project_path {
-            self.state.read().save_project()?;
+        self.capture_dock_layout(cx);
+        if self.project_path.is_some() && !self.daw_panel.update(cx, |panel, cx| panel.save_in_background(cx)) {
+            anyhow::bail!("No project loaded to save");
         }
         Ok(())
     }
 
+    /// Dirty flag of the edited project, readable without UI access
+    pub fn dirty_tracker(&self, cx: &App) -> Arc<DirtyTracker> {
+        self.daw_panel.read(cx).state.dirty.clone()
+    }
+
     pub fn new_project(&mut self, name: String, window: &mut Window, cx: &mut Context<Self>) {
         if let Some(ref project_dir) = self.state.read().project_dir {
             let project_dir = project_dir.clone();
@@ -288,6 +337,7 @@ impl DawEditorPanel {
                     let loop_enabled = project.transport.loop_enabled;
                     let loop_start = project.transport.loop_start;
                     let loop_end = project.transport.loop_end;
+                    let loop_crossfade_ms = project.transport.loop_crossfade_ms;
                     let metronome_enabled = project.transport.metronome_enabled;
                     let master_volume = project.master_track.volume;
 
@@ -298,6 +348,9 @@ impl DawEditorPanel {
                     if let Err(e) = service.set_loop(loop_enabled, loop_start, loop_end).await {
                         tracing::error!("❌ Failed to set loop: {}", e);
                     }
+                    if let Err(e) = service.set_loop_crossfade(loop_crossfade_ms).await {
+                        tracing::error!("❌ Failed to set loop crossfade: {}", e);
+                    }
 
                     if let Err(e) = service.set_metronome(metronome_enabled).await {
                         tracing::error!("❌ Failed to set metronome: {}", e);
@@ -461,18 +514,18 @@ impl Render for DawEditorPanel {
 // Plugin-related methods (called by DawEditorWrapper)
 impl DawEditorPanel {
     pub fn plugin_save(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> Result<(), plugin_editor_api::PluginError> {
+        // The write happens on the save thread; failures are shown in the
+        // toolbar and keep the project dirty
         if let Some(ref project_path) = self.project_path {
-            match self.state.read().save_project() {
-                Ok(_) => {
-                    cx.notify();
-                    Ok(())
-                }
-                Err(e) => {
-                    Err(plugin_editor_api::PluginError::FileSaveError {
-                        path: project_path.clone(),
-                        message: e.to_string(),
-                    })
-                }
+            self.capture_dock_layout(cx);
+            if self.daw_panel.update(cx, |panel, cx| panel.save_in_background(cx)) {
+                cx.notify();
+                Ok(())
+            } else {
+                Err(plugin_editor_api::PluginError::FileSaveError {
+                    path: project_path.clone(),
+                    message: "No project loaded to save".into(),
+                })
             }
         } else {
             Err(plugin_editor_api::PluginError::Other {
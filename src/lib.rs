@@ -28,6 +28,11 @@ mod daw_editor;
 // Re-export main types
 pub use daw_editor::DawEditorPanel;
 pub use daw_editor::AudioService;
+pub use daw_editor::{
+    conform_asset, export_clips, export_marker_regions, marker_regions, render_project, ClipExportOptions, ClipExportReport,
+    LoudnessReport, LoudnessTarget, MarkerRegion, RenderBitDepth, RenderOptions, RenderSummary, SilenceTrim, TrimReport,
+};
+pub use daw_editor::{queue_summary, JobState, RenderJob, RenderQueue, RenderRange};
 
 /// Storage for editor instances owned by the plugin
 struct EditorStorage {
@@ -125,9 +130,11 @@ impl EditorPlugin for DawEditorPlugin {
         if editor_id.as_str() == "daw-editor" {
             let panel = cx.new(|cx| DawEditorPanel::new_with_project(file_path.clone(), window, cx));
             let panel_arc: Arc<dyn ui::dock::PanelView> = Arc::new(panel.clone());
+            let dirty = panel.read(cx).dirty_tracker(cx);
             let wrapper = Box::new(DawEditorWrapper {
                 panel: panel.into(),
                 file_path: file_path.clone(),
+                dirty,
             });
 
             let id = {
@@ -165,6 +172,8 @@ impl EditorPlugin for DawEditorPlugin {
 pub struct DawEditorWrapper {
     panel: Entity<DawEditorPanel>,
     file_path: std::path::PathBuf,
+    /// Shared with the editor so the host can poll it without UI access
+    dirty: Arc<daw_editor::DirtyTracker>,
 }
 
 impl plugin_editor_api::EditorInstance for DawEditorWrapper {
@@ -185,7 +194,7 @@ impl plugin_editor_api::EditorInstance for DawEditorWrapper {
     }
 
     fn is_dirty(&self) -> bool {
-        false
+        self.dirty.is_dirty()
     }
 
     fn as_any(&self) -> &dyn std::any::Any {